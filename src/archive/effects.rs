@@ -0,0 +1,45 @@
+//! ArchiveEffectHandler - upload cached objects for /external/archive/upload/**
+
+use async_trait::async_trait;
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::{s3_error, ArchiveConfig};
+use crate::core::paths::archive as paths;
+use crate::mind::EffectHandler;
+
+/// Needs only the bucket credentials: what it uploads is already encrypted
+pub struct ArchiveEffectHandler {
+    config: ArchiveConfig,
+    bucket: Arc<s3::Bucket>,
+}
+
+impl ArchiveEffectHandler {
+    pub fn new(config: &ArchiveConfig) -> NineSResult<Self> {
+        Ok(Self { config: config.clone(), bucket: Arc::from(config.bucket()?) })
+    }
+}
+
+#[async_trait]
+impl EffectHandler for ArchiveEffectHandler {
+    fn watches(&self) -> &str { paths::EXTERNAL_UPLOAD }
+
+    /// Uploads of different objects overlap; the same object's go in order
+    fn resource(&self, scroll: &Scroll) -> String {
+        scroll.data["object"].as_str().unwrap_or_default().to_string()
+    }
+
+    async fn execute(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let object = scroll.data["object"].as_str().ok_or_else(|| anyhow::anyhow!("upload without object"))?.to_string();
+        let cached = self.config.cached(&object);
+        let blob = tokio::fs::read(&cached).await.map_err(|e| anyhow::anyhow!("{}: {}", cached.display(), e))?;
+        let (bucket, name, bytes) = (self.bucket.clone(), object.clone(), blob.len());
+        let response = tokio::task::spawn_blocking(move || bucket.put_object(&name, &blob)).await?
+            .map_err(|e| anyhow::anyhow!("{}", s3_error(&object, e)))?;
+        if response.status_code() >= 300 {
+            anyhow::bail!("archive {}: HTTP {}", object, response.status_code());
+        }
+        Ok(json!({"path": scroll.data["path"], "object": object, "bytes": bytes}))
+    }
+}
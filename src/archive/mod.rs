@@ -0,0 +1,137 @@
+//! Archive - Cold storage in an S3-compatible bucket (`s3` feature)
+//!
+//! `NodeConfig::with_archive` mounts a `RemoteObjectNamespace` at `/archive`
+//! once the node has a seed. Each scroll written there becomes one object,
+//! `{prefix}{path}`, encrypted on this machine (ChaCha20-Poly1305 under the
+//! seed's `archive` protocol key, object name as AAD) so the bucket only ever
+//! holds ciphertext. Object names, sizes and timing are visible to it.
+//!
+//! A write reads the bucket's current object first, so its version follows
+//! whatever another node last uploaded (a write fails while the bucket is
+//! unreachable). It lands in the local cache and returns; the upload is queued at
+//! `/external/archive/upload/{id}` `{path, object}` for `ArchiveEffectHandler`,
+//! so a node without services registered keeps them cached until it has.
+//! Reads come from the cache, else the bucket (then cached). `list` is the
+//! bucket's listing plus uploads still queued.
+//!
+//! ```ignore
+//! let archive = ArchiveConfig::new("https://s3.eu-central-1.amazonaws.com", "my-bucket", key_id, secret)
+//!     .with_region("eu-central-1");
+//! let node = Node::from_config(NodeConfig::new("app").with_archive(archive.clone()))?;
+//! node.start_services(ServicesConfig::new().with_handler(Box::new(ArchiveEffectHandler::new(&archive)?)), &shutdown)?;
+//! node.put("/archive/history/2024", json!({...}))?;
+//! ```
+
+mod effects;
+mod namespace;
+
+pub use effects::ArchiveEffectHandler;
+pub use namespace::RemoteObjectNamespace;
+
+use nine_s_core::prelude::*;
+use nine_s_store::crypto::{decrypt_with_aad, encrypt_with_aad, DerivedKey};
+use std::path::PathBuf;
+
+/// Bucket and local cache for `/archive`
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    /// `https://s3.amazonaws.com`, `http://localhost:9000` (MinIO), ...
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Prepended to every object name (default `beenode`)
+    pub prefix: String,
+    /// `endpoint/bucket/key` addressing instead of `bucket.endpoint/key` (MinIO)
+    pub path_style: bool,
+    /// Encrypted copies of written and fetched objects
+    pub cache_dir: PathBuf,
+}
+
+impl ArchiveConfig {
+    pub fn new(endpoint: impl Into<String>, bucket: impl Into<String>, access_key: impl Into<String>, secret_key: impl Into<String>) -> Self {
+        let bucket = bucket.into();
+        let cache_dir = dirs::cache_dir().unwrap_or_else(std::env::temp_dir).join("beenode-archive").join(&bucket);
+        Self {
+            endpoint: endpoint.into(),
+            region: "us-east-1".into(),
+            bucket,
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            prefix: "beenode".into(),
+            path_style: false,
+            cache_dir,
+        }
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>) -> Self { self.region = region.into(); self }
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self { self.prefix = prefix.into(); self }
+    pub fn path_style(mut self) -> Self { self.path_style = true; self }
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.cache_dir = dir.into(); self }
+
+    /// Object name for a path under `/archive`
+    pub fn object(&self, path: &str) -> String {
+        format!("{}{}", self.prefix.trim_end_matches('/'), path)
+    }
+
+    /// Path under `/archive` for an object name of ours
+    fn path_of(&self, object: &str) -> Option<String> {
+        object.strip_prefix(self.prefix.trim_end_matches('/')).filter(|p| p.starts_with('/')).map(str::to_string)
+    }
+
+    /// Cache file for an object
+    fn cached(&self, object: &str) -> PathBuf {
+        self.cache_dir.join(blake3::hash(object.as_bytes()).to_hex().as_str())
+    }
+
+    fn bucket(&self) -> NineSResult<Box<s3::Bucket>> {
+        let region = s3::Region::Custom { region: self.region.clone(), endpoint: self.endpoint.trim_end_matches('/').to_string() };
+        let creds = s3::creds::Credentials::new(Some(&self.access_key), Some(&self.secret_key), None, None, None)
+            .map_err(|e| NineSError::Other(format!("archive credentials: {}", e)))?;
+        let bucket = s3::Bucket::new(&self.bucket, region, creds).map_err(|e| s3_error(&self.bucket, e))?;
+        Ok(if self.path_style { bucket.with_path_style() } else { bucket })
+    }
+}
+
+/// Encryption key for archived objects, from the 64-byte master seed
+pub fn derive_key(seed: &[u8; 64]) -> NineSResult<DerivedKey> {
+    let protocol_seed = crate::identity::derive::derive_seed(seed, "archive", 0)?;
+    Ok(DerivedKey(blake3::derive_key("beenode archive v1", &protocol_seed)))
+}
+
+fn seal(key: &DerivedKey, object: &str, plaintext: &[u8]) -> NineSResult<Vec<u8>> {
+    let (nonce, ciphertext) = encrypt_with_aad(key, plaintext, object.as_bytes())?;
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+fn open(key: &DerivedKey, object: &str, blob: &[u8]) -> NineSResult<Vec<u8>> {
+    if blob.len() < 12 { return Err(NineSError::Other(format!("archive {}: truncated", object))); }
+    let nonce: [u8; 12] = blob[..12].try_into().expect("12 bytes");
+    decrypt_with_aad(key, &nonce, &blob[12..], object.as_bytes())
+}
+
+fn s3_error(what: &str, e: s3::error::S3Error) -> NineSError {
+    NineSError::Other(format!("archive {}: {}", what, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_objects_and_encryption() {
+        let config = ArchiveConfig::new("http://localhost:9000", "bucket", "key", "secret").with_prefix("node-a/");
+        assert_eq!(config.object("/history/2024"), "node-a/history/2024");
+        assert_eq!(config.path_of("node-a/history/2024").as_deref(), Some("/history/2024"));
+        assert_eq!(config.path_of("node-ab/x"), None);
+
+        let key = derive_key(&[7u8; 64]).unwrap();
+        let blob = seal(&key, "node-a/x", b"{}").unwrap();
+        assert_eq!(open(&key, "node-a/x", &blob).unwrap(), b"{}");
+        assert!(open(&key, "node-a/y", &blob).is_err());
+        assert!(open(&derive_key(&[8u8; 64]).unwrap(), "node-a/x", &blob).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! RemoteObjectNamespace - `/archive` over a bucket and a local cache
+
+use super::{open, s3_error, seal, ArchiveConfig};
+use crate::core::paths::{archive as paths, mind};
+use nine_s_core::prelude::*;
+use nine_s_store::crypto::DerivedKey;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+pub struct RemoteObjectNamespace {
+    config: ArchiveConfig,
+    bucket: Box<s3::Bucket>,
+    key: DerivedKey,
+    store: Arc<Store>,
+}
+
+impl RemoteObjectNamespace {
+    pub fn new(config: ArchiveConfig, key: DerivedKey, store: Arc<Store>) -> NineSResult<Self> {
+        std::fs::create_dir_all(&config.cache_dir).map_err(|e| NineSError::Other(format!("{}: {}", config.cache_dir.display(), e)))?;
+        Ok(Self { bucket: config.bucket()?, config, key, store })
+    }
+
+    fn decode(&self, object: &str, blob: &[u8]) -> NineSResult<Scroll> {
+        serde_json::from_slice(&open(&self.key, object, blob)?).map_err(|e| NineSError::Other(format!("archive {}: {}", object, e)))
+    }
+
+    /// The cached copy, else the bucket's (cached on the way)
+    fn fetch(&self, object: &str) -> NineSResult<Option<Vec<u8>>> {
+        let cached = self.config.cached(object);
+        if let Ok(blob) = std::fs::read(&cached) { return Ok(Some(blob)); }
+        let Some(blob) = self.remote(object)? else { return Ok(None) };
+        if let Err(e) = std::fs::write(&cached, &blob) { tracing::warn!("archive cache {}: {}", cached.display(), e); }
+        Ok(Some(blob))
+    }
+
+    /// The bucket's copy, bypassing the cache
+    fn remote(&self, object: &str) -> NineSResult<Option<Vec<u8>>> {
+        match self.bucket.get_object(object) {
+            Ok(r) if r.status_code() == 404 => Ok(None),
+            Ok(r) if r.status_code() >= 300 => Err(NineSError::Other(format!("archive {}: HTTP {}", object, r.status_code()))),
+            Ok(r) => Ok(Some(r.bytes().to_vec())),
+            Err(s3::error::S3Error::HttpFailWithBody(404, _)) => Ok(None),
+            Err(e) => Err(s3_error(object, e)),
+        }
+    }
+
+    /// Version of `blob`, 0 if there is none or it can't be read
+    fn version(&self, object: &str, blob: Option<Vec<u8>>) -> u64 {
+        blob.and_then(|b| self.decode(object, &b).ok()).map_or(0, |s| s.metadata.version)
+    }
+
+    /// Paths with an upload queued and no result yet
+    fn queued(&self) -> NineSResult<Vec<String>> {
+        let mut out = Vec::new();
+        for key in self.store.list(paths::EXTERNAL_UPLOAD)? {
+            if key.ends_with(mind::RESULT_SUFFIX) || self.store.read(&format!("{}{}", key, mind::RESULT_SUFFIX))?.is_some() { continue; }
+            if let Some(path) = self.store.read(&key)?.and_then(|s| s.data["path"].as_str().map(str::to_string)) { out.push(path); }
+        }
+        Ok(out)
+    }
+}
+
+impl Namespace for RemoteObjectNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        let object = self.config.object(path);
+        self.fetch(&object)?.map(|blob| self.decode(&object, &blob)).transpose()
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        let object = self.config.object(path);
+        let mut scroll = Scroll::new(&format!("{}{}", paths::PREFIX, path), data);
+        // The bucket's head (another node may have written since we cached)
+        // and the cache (an upload of ours may still be queued)
+        let head = self.version(&object, self.remote(&object)?);
+        let local = self.version(&object, std::fs::read(self.config.cached(&object)).ok());
+        scroll.metadata.version = head.max(local) + 1;
+        let plaintext = serde_json::to_vec(&scroll).map_err(|e| NineSError::Other(e.to_string()))?;
+        let cached = self.config.cached(&object);
+        std::fs::write(&cached, seal(&self.key, &object, &plaintext)?).map_err(|e| NineSError::Other(format!("{}: {}", cached.display(), e)))?;
+
+        let id = format!("{}-{}", chrono::Utc::now().timestamp_millis(), &blake3::hash(object.as_bytes()).to_hex()[..8]);
+        self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_UPLOAD, id), json!({"path": path, "object": object})).set_type(paths::UPLOAD_TYPE))?;
+        Ok(scroll)
+    }
+
+    fn list(&self, prefix: &str) -> NineSResult<Vec<String>> {
+        let base = prefix.trim_end_matches('/');
+        let listing = self.bucket.list(self.config.object(base), None).map_err(|e| s3_error(base, e))?;
+        let within = |p: &str| p == base || p.starts_with(&format!("{}/", base));
+        let mut out: BTreeSet<String> = listing.iter()
+            .flat_map(|page| page.contents.iter())
+            .filter_map(|o| self.config.path_of(&o.key))
+            .chain(self.queued()?)
+            .filter(|p| within(p))
+            .map(|p| format!("{}{}", paths::PREFIX, p))
+            .collect();
+        out.remove(paths::PREFIX);
+        Ok(out.into_iter().collect())
+    }
+
+    fn close(&self) -> NineSResult<()> { Ok(()) }
+}
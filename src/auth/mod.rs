@@ -1,5 +1,10 @@
 //! PIN-based authentication and mnemonic encryption.
+//!
+//! An optional duress PIN unlocks a decoy instead: its own mnemonic,
+//! encrypted under the duress PIN, opened in the `{app}-decoy` namespace.
+//! To whoever typed it, the node looks normally unlocked.
 
+use crate::secret::Secret;
 use nine_s_core::errors::{NineSError, NineSResult};
 use nine_s_store::crypto::{
     decrypt_with_aad, derive_key_from_password, encrypt_with_aad, generate_argon2_salt, DerivedKey,
@@ -15,6 +20,19 @@ struct AuthFile {
     verifier: String,
     encrypted_mnemonic: String,
     nonce: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duress: Option<DuressFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DuressFile {
+    salt: String,
+    verifier: String,
+    encrypted_mnemonic: String,
+    nonce: String,
+    /// Raise `/sys/alerts/duress` in the real store on use
+    #[serde(default)]
+    alert: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -24,8 +42,8 @@ pub struct PinAuth {
 }
 
 impl PinAuth {
-    pub fn load(app: &str) -> NineSResult<Self> {
-        let path = auth_path(app)?;
+    /// Auth file at an explicit path (`NodeConfig::auth_path` for a node's)
+    pub fn open(path: PathBuf) -> NineSResult<Self> {
         let data = if path.exists() {
             let raw = std::fs::read_to_string(&path)
                 .map_err(|e| NineSError::Other(format!("auth read: {e}")))?;
@@ -47,21 +65,108 @@ impl PinAuth {
     }
 
     pub fn set_pin(&mut self, pin: &str, mnemonic: &str) -> NineSResult<()> {
+        let staged = self.with_pin(pin, mnemonic)?;
+        staged.commit()?;
+        *self = staged;
+        Ok(())
+    }
+
+    /// A copy re-encrypted for `pin` and `mnemonic`, not written until `commit`
+    pub fn with_pin(&self, pin: &str, mnemonic: &str) -> NineSResult<Self> {
         let encrypted = self.encrypt_mnemonic(mnemonic, pin)?;
         let data = AuthFile {
             salt: encode_base64(&encrypted.salt),
             verifier: encrypted.verifier,
             encrypted_mnemonic: encode_base64(&encrypted.ciphertext),
             nonce: encode_base64(&encrypted.nonce),
+            duress: self.data.as_ref().and_then(|d| d.duress.clone()),
         };
+        Ok(Self { path: self.path.clone(), data: Some(data) })
+    }
+
+    /// Configure the duress PIN and its decoy mnemonic. Requires the real PIN.
+    pub fn set_duress_pin(&mut self, pin: &str, duress_pin: &str, decoy_mnemonic: &str, alert: bool) -> NineSResult<()> {
+        if !self.verify_pin(pin)? {
+            return Err(NineSError::Other("invalid PIN".into()));
+        }
+        if self.verify_pin(duress_pin)? {
+            return Err(NineSError::Other("duress PIN must differ from the PIN".into()));
+        }
+        let encrypted = self.encrypt_mnemonic(decoy_mnemonic, duress_pin)?;
+        let mut data = self.data.clone().ok_or_else(|| NineSError::Other("auth not initialized".into()))?;
+        data.duress = Some(DuressFile {
+            salt: encode_base64(&encrypted.salt),
+            verifier: encrypted.verifier,
+            encrypted_mnemonic: encode_base64(&encrypted.ciphertext),
+            nonce: encode_base64(&encrypted.nonce),
+            alert,
+        });
+        self.save(data)
+    }
+
+    pub fn clear_duress_pin(&mut self, pin: &str) -> NineSResult<()> {
+        if !self.verify_pin(pin)? {
+            return Err(NineSError::Other("invalid PIN".into()));
+        }
+        let mut data = self.data.clone().ok_or_else(|| NineSError::Other("auth not initialized".into()))?;
+        data.duress = None;
+        self.save(data)
+    }
+
+    pub fn has_duress_pin(&self) -> bool {
+        self.data.as_ref().is_some_and(|d| d.duress.is_some())
+    }
+
+    /// True if `pin` is the duress PIN (never true for the real PIN)
+    pub fn verify_duress_pin(&self, pin: &str) -> NineSResult<bool> {
+        let Some(duress) = self.data.as_ref().and_then(|d| d.duress.as_ref()) else { return Ok(false) };
+        let key = Self::derive_key(pin, &decode_base64(&duress.salt)?)?;
+        Ok(blake3::hash(&key.0).to_hex().to_string() == duress.verifier)
+    }
+
+    pub fn duress_alert(&self) -> bool {
+        self.data.as_ref().and_then(|d| d.duress.as_ref()).is_some_and(|d| d.alert)
+    }
+
+    pub fn decrypt_duress_mnemonic(&self, pin: &str) -> NineSResult<Secret<String>> {
+        let duress = self.data.as_ref().and_then(|d| d.duress.as_ref())
+            .ok_or_else(|| NineSError::Other("no duress PIN".into()))?;
+        decrypt(pin, &duress.salt, &duress.nonce, &duress.encrypted_mnemonic)
+    }
+
+    fn save(&mut self, data: AuthFile) -> NineSResult<()> {
+        let staged = Self { path: self.path.clone(), data: Some(data) };
+        staged.commit()?;
+        *self = staged;
+        Ok(())
+    }
+
+    /// Write the file: a temp file beside it, renamed over the old one, so a
+    /// failed write leaves the previous file whole
+    pub fn commit(&self) -> NineSResult<()> {
+        let data = self.data.as_ref().ok_or_else(|| NineSError::Other("auth not initialized".into()))?;
         if let Some(parent) = self.path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| NineSError::Other(format!("auth mkdir: {e}")))?;
         }
-        std::fs::write(&self.path, serde_json::to_string_pretty(&data).unwrap())
-            .map_err(|e| NineSError::Other(format!("auth write: {e}")))?;
-        self.data = Some(data);
-        Ok(())
+        let temp = self.temp_path();
+        std::fs::write(&temp, serde_json::to_string_pretty(data).unwrap())
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .map_err(|e| NineSError::Other(format!("auth write: {e}")))
+    }
+
+    /// Where `commit` writes before the rename
+    pub(crate) fn temp_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".tmp");
+        PathBuf::from(path)
+    }
+
+    /// Key for sealed prefixes, independent of the mnemonic encryption key
+    pub fn prefix_key(&self, pin: &str) -> NineSResult<DerivedKey> {
+        let data = self.data.as_ref().ok_or_else(|| NineSError::Other("auth not initialized".into()))?;
+        let key = Self::derive_key(pin, &decode_base64(&data.salt)?)?;
+        Ok(DerivedKey(blake3::derive_key("beenode sealed prefix v1", &key.0)))
     }
 
     pub fn derive_key(pin: &str, salt: &[u8]) -> NineSResult<DerivedKey> {
@@ -76,20 +181,26 @@ impl PinAuth {
         Ok(EncryptedMnemonic { salt, verifier, nonce, ciphertext })
     }
 
-    pub fn decrypt_mnemonic(&self, pin: &str) -> NineSResult<String> {
+    pub fn decrypt_mnemonic(&self, pin: &str) -> NineSResult<Secret<String>> {
         let data = self.data.as_ref().ok_or_else(|| NineSError::Other("auth not initialized".into()))?;
-        let salt = decode_base64(&data.salt)?;
-        let nonce = decode_base64(&data.nonce)?;
-        let ciphertext = decode_base64(&data.encrypted_mnemonic)?;
-        let key = Self::derive_key(pin, &salt)?;
-        let nonce: [u8; 12] = nonce
-            .try_into()
-            .map_err(|_| NineSError::Other("auth nonce invalid".into()))?;
-        let plaintext = decrypt_with_aad(&key, &nonce, &ciphertext, AAD_MNEMONIC)?;
-        String::from_utf8(plaintext).map_err(|e| NineSError::Other(format!("mnemonic utf8: {e}")))
+        decrypt(pin, &data.salt, &data.nonce, &data.encrypted_mnemonic)
     }
 }
 
+/// App namespace a duress unlock opens instead of `app`
+pub fn decoy_app(app: &str) -> String {
+    format!("{app}-decoy")
+}
+
+fn decrypt(pin: &str, salt: &str, nonce: &str, ciphertext: &str) -> NineSResult<Secret<String>> {
+    let key = PinAuth::derive_key(pin, &decode_base64(salt)?)?;
+    let nonce: [u8; 12] = decode_base64(nonce)?
+        .try_into()
+        .map_err(|_| NineSError::Other("auth nonce invalid".into()))?;
+    let plaintext = decrypt_with_aad(&key, &nonce, &decode_base64(ciphertext)?, AAD_MNEMONIC)?;
+    String::from_utf8(plaintext).map(Secret::new).map_err(|e| NineSError::Other(format!("mnemonic utf8: {e}")))
+}
+
 pub struct EncryptedMnemonic {
     pub salt: [u8; 16],
     pub verifier: String,
@@ -97,11 +208,9 @@ pub struct EncryptedMnemonic {
     pub ciphertext: Vec<u8>,
 }
 
-fn auth_path(app: &str) -> NineSResult<PathBuf> {
-    let root = std::env::var("NINE_S_ROOT")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| dirs::data_local_dir().unwrap_or_else(|| PathBuf::from(".")));
-    Ok(root.join(app).join("data").join("auth.json"))
+/// Where `app`'s auth file lives under a data root
+pub fn path_in(root: &std::path::Path, app: &str) -> PathBuf {
+    root.join(app).join("data").join("auth.json")
 }
 
 fn encode_base64(data: &[u8]) -> String {
@@ -18,13 +18,13 @@
 //!   --pretty   Pretty-print JSON (default for tty)
 //!   --scroll   Output full scroll (key, type, metadata, data)
 
-use beenode::{AuthMode, Node, NodeConfig};
+use beenode::{AuthMode, HttpConfig, Node, NodeConfig};
 use beenode::auth::PinAuth;
 use beenode::logging::init_logging;
 use serde_json::{json, Value};
 use std::env;
 use std::io::{self, IsTerminal, Write};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 #[cfg(feature = "wallet")]
 use beenode::{Network, WalletConfig};
@@ -56,6 +56,19 @@ fn main() {
         Some("list") | Some("ls") => cmd_list(&opts),
         Some("repl") => cmd_repl(&opts),
         Some("serve") => cmd_serve(&opts),
+        Some("daemon") => cmd_daemon(&opts),
+        Some("dev") => cmd_dev(&opts),
+        Some("snapshot") => cmd_snapshot(&opts),
+        Some("diff") => cmd_diff(&opts),
+        Some("import") => cmd_import(&opts),
+        Some("export") => cmd_export(&opts),
+        Some("rotate") => cmd_rotate(&opts),
+        Some("replay") => cmd_replay(&opts),
+        Some("rescan") => cmd_rescan(&opts),
+        Some("backup") => cmd_backup(&opts),
+        Some("duress") => cmd_duress(&opts),
+        Some("mobi") => cmd_mobi(&opts),
+        Some("pair") => cmd_pair(&opts),
         Some(cmd) => Err(format!("Unknown command: {}", cmd)),
         None => {
             print_usage();
@@ -64,6 +77,8 @@ fn main() {
     };
 
     match result {
+        // Command already wrote its own output (e.g. export to stdout)
+        Ok(Value::Null) => {}
         Ok(output) => {
             let formatted = if opts.scroll {
                 serde_json::to_string_pretty(&output).unwrap()
@@ -115,6 +130,23 @@ struct ParsedArgs {
     rpc_pass: Option<String>,
     // Server options
     port: Option<u16>,
+    pidfile: Option<String>,
+    // Import options
+    scroll_type: Option<String>,
+    key_field: Option<String>,
+    // Shamir backup options
+    shares: Option<u8>,
+    threshold: Option<u8>,
+    share_words: Vec<String>,
+    // Duress options
+    no_alert: bool,
+    // Replay options
+    from: Option<u64>,
+    // Transaction export options
+    format: Option<String>,
+    currency: Option<String>,
+    // Serve the bundled web UI
+    ui: bool,
     // Output options
     json: bool,
     pretty: bool,
@@ -153,6 +185,8 @@ impl ParsedArgs {
                 "--json" => opts.json = true,
                 "--pretty" => opts.pretty = true,
                 "--scroll" => opts.scroll = true,
+                "--no-alert" => opts.no_alert = true,
+                "--ui" => opts.ui = true,
                 "--app" | "-a" => {
                     if i + 1 < args.len() {
                         opts.app = Some(args[i + 1].clone());
@@ -207,6 +241,60 @@ impl ParsedArgs {
                         i += 1;
                     }
                 }
+                "--pidfile" => {
+                    if i + 1 < args.len() {
+                        opts.pidfile = Some(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
+                "--type" | "-t" => {
+                    if i + 1 < args.len() {
+                        opts.scroll_type = Some(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
+                "--key" | "-k" => {
+                    if i + 1 < args.len() {
+                        opts.key_field = Some(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
+                "--shares" => {
+                    if i + 1 < args.len() {
+                        opts.shares = args[i + 1].parse().ok();
+                        i += 1;
+                    }
+                }
+                "--threshold" => {
+                    if i + 1 < args.len() {
+                        opts.threshold = args[i + 1].parse().ok();
+                        i += 1;
+                    }
+                }
+                "--from" => {
+                    if i + 1 < args.len() {
+                        opts.from = args[i + 1].parse().ok();
+                        i += 1;
+                    }
+                }
+                "--format" | "-f" => {
+                    if i + 1 < args.len() {
+                        opts.format = Some(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
+                "--currency" => {
+                    if i + 1 < args.len() {
+                        opts.currency = Some(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
+                "--share" => {
+                    if i + 1 < args.len() {
+                        opts.share_words.push(args[i + 1].clone());
+                        i += 1;
+                    }
+                }
                 _ if !arg.starts_with('-') => positional.push(arg.clone()),
                 _ => {} // Ignore unknown flags
             }
@@ -266,6 +354,12 @@ impl ParsedArgs {
         if opts.port.is_none() {
             opts.port = env::var("BEENODE_PORT").ok().and_then(|s| s.parse().ok());
         }
+        if opts.pidfile.is_none() {
+            opts.pidfile = env::var("BEENODE_PIDFILE").ok().filter(|s| !s.is_empty());
+        }
+        if opts.pin.is_none() {
+            opts.pin = env::var("BEENODE_PIN").ok().filter(|s| !s.is_empty());
+        }
 
         opts
     }
@@ -285,13 +379,46 @@ COMMANDS:
     list [prefix]           List paths under prefix
     repl                    Interactive mode
     serve                   Start HTTP server
+    daemon                  Headless server: sd_notify, pidfile, SIGHUP reload
+    snapshot <file> [pfx]   Save scrolls (under prefix) to an NDJSON snapshot
+    diff <a> [b]            Added/changed/removed scrolls from snapshot a to b (default: live store)
+    import <prefix> <file>  Bulk-load .jsonl/.csv records as scrolls (--type, --key)
+    export <prefix> [file]  Write scrolls under prefix as JSONL (default: stdout)
+    export transactions [file]  Wallet history for accounting (--format csv|json|koinly, --currency usd)
+    rotate --mnemonic <w>   Move to a new mnemonic: sweep, announce, re-encrypt
+    replay --from <seq>     Re-run journaled writes through the Mind in a sandbox (effects mocked)
+    rescan [--from <h>]     Drop the wallet's chain data and scan again (from height h with bitcoind)
+    duress set|clear        Duress PIN opening a decoy (--mnemonic decoy words, --no-alert)
+    backup shamir           Print SLIP-39 shares of the mnemonic (--shares, --threshold)
+    pair                    Show a one-time pairing QR for another node to scan
+    pair accept <uri>       Pair with the node whose QR/URI this is
+    pair poll               Answer pending pairing requests
+    mobi <pubkey|npub> [n]  Mobi forms of a key; with n, check that n belongs to it
+    mobi <number>           Validate a typed Mobi (12/15/18/21 digits, dashes ok)
+    dev mine <n>            Regtest: mine n blocks (bitcoind-rpc feature)
+    dev fund <sats>         Regtest: send sats to the wallet and mine 1 block
 
 SERVER OPTIONS:
     --port, -p <port>       Server port (default: 8080, env: BEENODE_PORT)
+    --pidfile <path>        Daemon pidfile (env: BEENODE_PIDFILE)
+    --ui                    Serve the web dashboard at /ui (ui feature)
+
+    BEENODE_CORS_ORIGINS    Comma-separated browser origins (default: any)
+    BEENODE_API_TOKEN       Require Authorization: Bearer <token>
+    BEENODE_JOURNAL=1       Record every write in /sys/journal (needed by replay)
+
+IMPORT OPTIONS:
+    --type, -t <type>       Scroll type for imported records
+    --key, -k <field>       Record field used as the key (default: record number)
+
+BACKUP OPTIONS:
+    --shares <n>            Number of SLIP-39 shares to print (max 16)
+    --threshold <k>         Shares needed to restore
 
 INIT OPTIONS:
     --app, -a <name>        Application name (required)
     --mnemonic, -m <words>  BIP39 mnemonic (12/24 words)
+    --share <words>         SLIP-39 share to restore from instead (repeat per share)
     --network, -n <net>     Network: bitcoin|testnet|signet|regtest
     --electrum, -e <url>    Electrum server URL
     --relay, -r <url>       Nostr relay URL (can repeat)
@@ -409,10 +536,13 @@ fn load_node_from_env() -> Result<Node, String> {
         .ok()
         .or_else(|| config_string("auth_mode"));
     let auth_mode = parse_auth_mode(auth_mode_raw.as_deref())?;
-    let mut node_config = NodeConfig::new(&app).with_auth_mode(auth_mode);
+    let mut node_config = proxy_from_env(NodeConfig::new(&app).with_auth_mode(auth_mode).with_http(HttpConfig::from_env()));
+    if env::var("BEENODE_JOURNAL").is_ok_and(|v| v == "1" || v == "true") {
+        node_config = node_config.with_journal();
+    }
 
     let auth_initialized = match auth_mode {
-        AuthMode::Pin => PinAuth::load(&app)
+        AuthMode::Pin => PinAuth::open(node_config.auth_path())
             .map(|auth| auth.is_initialized())
             .unwrap_or(false),
         AuthMode::None => false,
@@ -450,9 +580,9 @@ fn load_node_from_env() -> Result<Node, String> {
             network: net,
             electrum_url,
             data_dir,
-            #[cfg(feature = "bitcoind-rpc")]
-            rpc: None,
+            ..Default::default()
         };
+        wallet_cfg = electrum_from_env(wallet_cfg);
 
         // Use RPC if configured (takes precedence over electrum)
         #[cfg(feature = "bitcoind-rpc")]
@@ -499,14 +629,44 @@ fn load_node_from_env() -> Result<Node, String> {
     Node::from_config(node_config).map_err(|e| format!("Failed to create node: {}", e))
 }
 
+/// `BEENODE_PROXY` (`socks5://host:port`) and `BEENODE_REQUIRE_TOR=1`
+fn proxy_from_env(mut cfg: NodeConfig) -> NodeConfig {
+    if let Some(url) = env::var("BEENODE_PROXY").ok().filter(|s| !s.is_empty()) {
+        cfg = cfg.with_proxy(url);
+    }
+    if env::var("BEENODE_REQUIRE_TOR").is_ok_and(|v| v == "1" || v == "true") {
+        cfg = cfg.require_tor_on_mainnet();
+    }
+    cfg
+}
+
+/// `BEENODE_ELECTRUM_FALLBACKS` (comma separated) and `BEENODE_ELECTRUM_TIMEOUT` (seconds)
+#[cfg(feature = "wallet")]
+fn electrum_from_env(mut cfg: WalletConfig) -> WalletConfig {
+    if let Ok(list) = env::var("BEENODE_ELECTRUM_FALLBACKS") {
+        for url in list.split(',').map(str::trim).filter(|u| !u.is_empty()) {
+            cfg = cfg.with_electrum_fallback(url);
+        }
+    }
+    if let Some(secs) = env::var("BEENODE_ELECTRUM_TIMEOUT").ok().and_then(|s| s.parse().ok()) {
+        cfg = cfg.with_electrum_timeout(secs);
+    }
+    cfg
+}
+
 fn cmd_init(opts: &ParsedArgs) -> Result<Value, String> {
     let app = opts.app.as_ref().ok_or("--app <name> is required")?;
-    let mnemonic = opts.mnemonic.as_ref().ok_or("--mnemonic <words> is required")?;
+    let restored;
+    let mnemonic = match (&opts.mnemonic, opts.share_words.is_empty()) {
+        (Some(m), _) => m,
+        (None, false) => { restored = restore_shamir(&opts.share_words)?; &restored }
+        (None, true) => return Err("--mnemonic <words> (or --share <words> per SLIP-39 share) is required".into()),
+    };
     let auth_mode = parse_auth_mode(opts.auth_mode.as_deref())?;
 
     let pin = if auth_mode == AuthMode::Pin {
         let pin = prompt_pin()?;
-        let mut auth = PinAuth::load(app).map_err(|e| format!("Auth load failed: {}", e))?;
+        let mut auth = PinAuth::open(NodeConfig::new(app).auth_path()).map_err(|e| format!("Auth load failed: {}", e))?;
         auth.set_pin(&pin, mnemonic)
             .map_err(|e| format!("Auth init failed: {}", e))?;
         Some(pin)
@@ -516,7 +676,7 @@ fn cmd_init(opts: &ParsedArgs) -> Result<Value, String> {
 
     // Build and test node config
     #[allow(unused_mut)]
-    let mut node_config = NodeConfig::new(app).with_auth_mode(auth_mode);
+    let mut node_config = proxy_from_env(NodeConfig::new(app).with_auth_mode(auth_mode));
 
     if auth_mode == AuthMode::None {
         node_config = node_config.with_mnemonic(mnemonic);
@@ -536,9 +696,9 @@ fn cmd_init(opts: &ParsedArgs) -> Result<Value, String> {
             network: net,
             electrum_url: opts.electrum_url.clone(),
             data_dir: opts.data_dir.as_ref().map(std::path::PathBuf::from),
-            #[cfg(feature = "bitcoind-rpc")]
-            rpc: None,
+            ..Default::default()
         };
+        wallet_cfg = electrum_from_env(wallet_cfg);
 
         // Use RPC if configured
         #[cfg(feature = "bitcoind-rpc")]
@@ -744,8 +904,7 @@ fn cmd_repl(opts: &ParsedArgs) -> Result<Value, String> {
 
 fn cmd_serve(opts: &ParsedArgs) -> Result<Value, String> {
     use beenode::server::create_router_with_node;
-    use beenode::clock::start_clock;
-    use beenode::install_signal_handlers;
+    use beenode::{install_signal_handlers, ServicesConfig};
     use std::sync::Arc;
 
     let port = opts.port.unwrap_or(8080);
@@ -757,12 +916,6 @@ fn cmd_serve(opts: &ParsedArgs) -> Result<Value, String> {
     }
     let node = Arc::new(node);
 
-    // Create store for clock service
-    let store = Arc::new(
-        beenode::Store::open(&app_name, b"")
-            .map_err(|e| format!("Failed to open store: {}", e))?
-    );
-
     let rt = tokio::runtime::Runtime::new()
         .map_err(|e| format!("Failed to create runtime: {}", e))?;
 
@@ -770,19 +923,26 @@ fn cmd_serve(opts: &ParsedArgs) -> Result<Value, String> {
         // Install signal handlers for graceful shutdown
         let shutdown = install_signal_handlers();
 
-        // Start clock service (Layer 0 - boots first)
-        let clock_handle = start_clock(store.clone(), shutdown.subscribe())
-            .map_err(|e| format!("Failed to start clock: {}", e))?;
-        info!("Clock service started (Layer 0)");
+        // Start supervised services (clock is Layer 0 - boots first)
+        let services = node.start_services(ServicesConfig::serving(), &shutdown)
+            .map_err(|e| format!("Failed to start services: {}", e))?;
+        info!("Services started: {}", services.services().join(", "));
 
         let router = create_router_with_node(node, &app_name);
+        #[cfg(feature = "ui")]
+        let router = if opts.ui { beenode::server::ui::with_ui(router) } else { router };
+        #[cfg(not(feature = "ui"))]
+        if opts.ui { return Err("--ui requires the ui feature".to_string()); }
         let addr = format!("0.0.0.0:{}", port);
 
         info!("Beenode server listening on http://{}", addr);
         info!("Endpoints:");
         info!("  GET  /health              - Health check");
+        info!("  GET  /status              - Node overview");
+        info!("  GET  /capabilities        - Features and API version");
         info!("  GET  /scrolls?prefix=/    - List paths");
         info!("  GET  /sys/clock/tick      - Current clock tick");
+        if opts.ui { info!("  GET  /ui/                - Web dashboard"); }
         debug!("  GET  /scroll/*path        - Read scroll");
         debug!("  POST /scroll/*path        - Write scroll");
 
@@ -800,9 +960,9 @@ fn cmd_serve(opts: &ParsedArgs) -> Result<Value, String> {
             }
         }
 
-        // Wait for clock to finish
-        let _ = clock_handle.await;
-        info!("Clock service stopped");
+        // Wait for services to stop
+        services.join().await;
+        info!("Services stopped");
 
         Ok::<(), String>(())
     }).map_err(|e| format!("Server failed: {}", e))?;
@@ -810,6 +970,398 @@ fn cmd_serve(opts: &ParsedArgs) -> Result<Value, String> {
     Ok(json!({"status": "stopped"}))
 }
 
+/// Daemon failure: printed as JSON to stderr, exit code identifies the stage
+struct DaemonError { stage: &'static str, code: i32, message: String }
+
+impl DaemonError {
+    fn new(stage: &'static str, code: i32, message: impl Into<String>) -> Self {
+        Self { stage, code, message: message.into() }
+    }
+}
+
+fn cmd_daemon(opts: &ParsedArgs) -> Result<Value, String> {
+    match run_daemon(opts) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            eprintln!("{}", json!({"error": e.message, "stage": e.stage, "code": e.code}));
+            std::process::exit(e.code);
+        }
+    }
+}
+
+fn run_daemon(opts: &ParsedArgs) -> Result<Value, DaemonError> {
+    use beenode::server::create_router_with_node;
+    use beenode::{install_reload_handler, install_signal_handlers, sd_notify, Pidfile, ServicesConfig, Shutdown};
+    use std::sync::Arc;
+
+    let port = opts.port.unwrap_or(8080);
+    let app_name = opts.app.clone().unwrap_or_else(|| "beenode".to_string());
+    let _pidfile = opts.pidfile.as_ref()
+        .map(Pidfile::create)
+        .transpose()
+        .map_err(|e| DaemonError::new("pidfile", 2, e.to_string()))?;
+
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(|e| DaemonError::new("runtime", 3, e.to_string()))?;
+
+    rt.block_on(async {
+        let shutdown = install_signal_handlers();
+        let mut reload = install_reload_handler();
+        let addr = format!("0.0.0.0:{}", port);
+
+        loop {
+            // Config is re-read from the environment on every start and SIGHUP
+            let node = load_node_from_env().map_err(|e| DaemonError::new("config", 5, e))?;
+            if node.is_locked() {
+                let pin = opts.pin.as_deref()
+                    .ok_or_else(|| DaemonError::new("unlock", 6, "node is locked; set BEENODE_PIN"))?;
+                if !node.unlock(pin).map_err(|e| DaemonError::new("unlock", 6, e.to_string()))? {
+                    return Err(DaemonError::new("unlock", 6, "invalid PIN"));
+                }
+            }
+            for backend in node.degraded() {
+                warn!("Running without {} (see /sys/alerts/{})", backend, backend);
+            }
+            let node = Arc::new(node);
+
+            // Per-cycle stop signal: tears down services and server on reload or exit
+            let cycle = Shutdown::new();
+            let services = node.start_services(ServicesConfig::serving(), &cycle)
+                .map_err(|e| DaemonError::new("services", 8, e.to_string()))?;
+            let listener = tokio::net::TcpListener::bind(&addr).await
+                .map_err(|e| DaemonError::new("bind", 4, e.to_string()))?;
+            let router = create_router_with_node(node, &app_name);
+
+            sd_notify(&format!("READY=1\nSTATUS=serving {} on {}", app_name, addr));
+            info!("Beenode daemon serving {} on {}", app_name, addr);
+
+            let mut stop_rx = cycle.subscribe();
+            let server = tokio::spawn(async move {
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(async move { let _ = stop_rx.recv().await; })
+                    .await
+            });
+
+            let mut shutdown_rx = shutdown.subscribe();
+            let reloading = tokio::select! {
+                _ = shutdown_rx.recv() => false,
+                Some(()) = reload.recv() => true,
+            };
+
+            sd_notify(if reloading { "RELOADING=1" } else { "STOPPING=1" });
+            cycle.trigger().await;
+            match server.await {
+                Ok(Err(e)) => return Err(DaemonError::new("serve", 9, e.to_string())),
+                Err(e) => return Err(DaemonError::new("serve", 9, e.to_string())),
+                Ok(Ok(())) => {}
+            }
+            services.join().await;
+            if !reloading { break; }
+            info!("SIGHUP: reloading configuration");
+        }
+        Ok(json!({"status": "stopped"}))
+    })
+}
+
+/// Raw store for snapshots and diffs (no namespaces, no unlock)
+fn open_live_store(opts: &ParsedArgs) -> Result<beenode::Store, String> {
+    let app = opts.app.clone()
+        .or_else(|| load_config().ok().and_then(|c| c["app"].as_str().map(String::from)))
+        .ok_or("BEENODE_APP not set")?;
+    NodeConfig::new(&app).open_store().map_err(|e| format!("Store open failed: {}", e))
+}
+
+fn cmd_snapshot(opts: &ParsedArgs) -> Result<Value, String> {
+    let file = opts.path.as_ref().ok_or("File required: beenode snapshot <file> [prefix]")?;
+    let prefix = opts.data.as_deref().unwrap_or("/");
+    let snapshot = beenode::store::snapshot(&open_live_store(opts)?, prefix).map_err(|e| format!("Snapshot failed: {}", e))?;
+    beenode::store::save(&snapshot, file).map_err(|e| format!("Snapshot failed: {}", e))?;
+    Ok(json!({"status": "ok", "file": file, "prefix": prefix, "count": snapshot.len()}))
+}
+
+fn cmd_diff(opts: &ParsedArgs) -> Result<Value, String> {
+    let a = opts.path.as_ref().ok_or("Snapshot required: beenode diff <snapshot-a> [snapshot-b]")?;
+    let before = beenode::store::load(a).map_err(|e| format!("Diff failed: {}", e))?;
+    let after = match opts.data.as_deref() {
+        Some(b) if b != "live" => beenode::store::load(b),
+        _ => beenode::store::snapshot(&open_live_store(opts)?, "/"),
+    }.map_err(|e| format!("Diff failed: {}", e))?;
+    Ok(beenode::store::diff(&before, &after).to_json())
+}
+
+fn cmd_import(opts: &ParsedArgs) -> Result<Value, String> {
+    use beenode::store::{bulk, Format, ImportOptions};
+
+    let prefix = opts.path.as_ref().ok_or("Prefix required: beenode import <prefix> <file>")?;
+    let file = opts.data.as_ref().ok_or("File required: beenode import <prefix> <file>")?;
+    let input = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    let records = bulk::parse(&input, Format::from_path(file)).map_err(|e| format!("Import failed: {}", e))?;
+
+    let mut import_opts = ImportOptions::default();
+    if let Some(t) = &opts.scroll_type { import_opts = import_opts.with_type(t); }
+    if let Some(k) = &opts.key_field { import_opts = import_opts.with_key_field(k); }
+    let verbose = io::stderr().is_terminal();
+    let count = beenode::store::import(&open_live_store(opts)?, prefix, records, &import_opts, |p| {
+        if verbose { eprint!("\rimported {}/{}", p.written, p.total); }
+    }).map_err(|e| format!("Import failed: {}", e))?;
+    if verbose { eprintln!(); }
+    Ok(json!({"status": "ok", "prefix": prefix, "file": file, "count": count}))
+}
+
+fn cmd_export(opts: &ParsedArgs) -> Result<Value, String> {
+    let prefix = opts.path.as_deref().ok_or("Prefix required: beenode export <prefix> [file]")?;
+    if prefix == "transactions" { return cmd_export_transactions(opts); }
+    let store = open_live_store(opts)?;
+    match opts.data.as_deref() {
+        Some(file) => {
+            let mut out = std::fs::File::create(file).map_err(|e| format!("{}: {}", file, e))?;
+            let count = beenode::store::export(&store, prefix, &mut out).map_err(|e| format!("Export failed: {}", e))?;
+            Ok(json!({"status": "ok", "prefix": prefix, "file": file, "count": count}))
+        }
+        None => {
+            beenode::store::export(&store, prefix, &mut io::stdout().lock()).map_err(|e| format!("Export failed: {}", e))?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+fn cmd_export_transactions(opts: &ParsedArgs) -> Result<Value, String> {
+    let format = opts.format.as_deref().unwrap_or("csv");
+    let path = format!("/wallet/export/transactions?format={}&currency={}", format, opts.currency.as_deref().unwrap_or("usd"));
+    let node = load_node_from_env()?;
+    unlock_if_needed(&node, "/wallet/export/transactions", opts.pin.as_deref())?;
+    let export = node.get(&path).map_err(|e| format!("Export failed: {}", e))?.ok_or("No wallet")?;
+    node.close().ok();
+
+    let content = &export.data["content"];
+    let text = match content.as_str() {
+        Some(csv) => csv.to_string(),
+        None => serde_json::to_string_pretty(content).map_err(|e| e.to_string())? + "\n",
+    };
+    match opts.data.as_deref() {
+        Some(file) => {
+            std::fs::write(file, text).map_err(|e| format!("{}: {}", file, e))?;
+            Ok(json!({"status": "ok", "format": format, "file": file, "count": export.data["count"]}))
+        }
+        None => {
+            print!("{}", text);
+            Ok(Value::Null)
+        }
+    }
+}
+
+fn cmd_rotate(opts: &ParsedArgs) -> Result<Value, String> {
+    let mnemonic = opts.mnemonic.as_ref().ok_or("--mnemonic <new words> is required")?;
+    let node = load_node_from_env()?;
+    let mut rotation = beenode::Rotation::new(mnemonic);
+    if node.is_initialized() {
+        let pin = match opts.pin.clone() { Some(p) => p, None => prompt_pin()? };
+        unlock_if_needed(&node, "/", Some(&pin))?;
+        rotation = rotation.with_pin(pin);
+    } else {
+        unlock_if_needed(&node, "/", None)?;
+    }
+    let report = node.rotate(&rotation).map_err(|e| format!("Rotation failed: {}", e))?;
+    node.close().ok();
+
+    // Plain-mnemonic configs (auth none) must follow, or the next start derives the old identity
+    if let Ok(mut config) = load_config() {
+        if config["mnemonic"].is_string() {
+            config["mnemonic"] = json!(mnemonic);
+            let app = config["app"].as_str().unwrap_or_default().to_string();
+            std::fs::write(config_path(&app), serde_json::to_string_pretty(&config).unwrap())
+                .map_err(|e| format!("Failed to save config: {}", e))?;
+        }
+    }
+    Ok(report)
+}
+
+fn cmd_replay(opts: &ParsedArgs) -> Result<Value, String> {
+    let from = opts.from.ok_or("--from <journal-seq> is required")?;
+    let node = load_node_from_env()?;
+    unlock_if_needed(&node, "/sys/journal", opts.pin.as_deref())?;
+    // One journal page; continue with --from <cursor + 1>
+    let report = node.replay(from, 500).map_err(|e| format!("Replay failed: {}", e))?;
+    node.close().ok();
+    Ok(report)
+}
+
+fn cmd_rescan(opts: &ParsedArgs) -> Result<Value, String> {
+    let node = load_node_from_env()?;
+    unlock_if_needed(&node, "/wallet/rescan", opts.pin.as_deref())?;
+    let result = node.put("/wallet/rescan", json!({"from_height": opts.from})).map_err(|e| format!("Rescan failed: {}", e))?;
+    node.close().ok();
+    Ok(json!({"data": result.data}))
+}
+
+fn cmd_pair(opts: &ParsedArgs) -> Result<Value, String> {
+    let node = load_node_from_env()?;
+    let (path, data) = match opts.path.as_deref() {
+        None => ("/nostr/pair", json!({})),
+        Some("accept") => ("/nostr/pair/accept", json!({"uri": opts.data.as_deref().ok_or("Usage: beenode pair accept <uri>")?})),
+        Some("poll") => ("/nostr/pair/poll", json!({})),
+        Some(_) => return Err("Usage: beenode pair | beenode pair accept <uri> | beenode pair poll".into()),
+    };
+    unlock_if_needed(&node, path, opts.pin.as_deref())?;
+    let result = node.put(path, data).map_err(|e| format!("Pairing failed: {}", e))?;
+    node.close().ok();
+    if let Some(qr) = result.data["qr"].as_str() {
+        eprintln!("{}", qr);
+    }
+    Ok(json!({"data": result.data}))
+}
+
+fn cmd_duress(opts: &ParsedArgs) -> Result<Value, String> {
+    let app = match opts.app.clone() {
+        Some(app) => app,
+        None => load_config()?["app"].as_str().map(String::from).ok_or("BEENODE_APP not set")?,
+    };
+    let mut auth = PinAuth::open(NodeConfig::new(&app).auth_path()).map_err(|e| format!("Auth load failed: {}", e))?;
+    if !auth.is_initialized() {
+        return Err("Duress PIN needs PIN auth (beenode init --auth pin)".into());
+    }
+    let pin = match opts.pin.clone() { Some(p) => p, None => prompt_pin()? };
+    match opts.path.as_deref() {
+        Some("set") => {
+            let decoy = opts.mnemonic.as_ref().ok_or("--mnemonic <decoy words> is required")?;
+            let duress_pin = prompt_pin_as("duress PIN")?;
+            auth.set_duress_pin(&pin, &duress_pin, decoy, !opts.no_alert).map_err(|e| format!("Duress setup failed: {}", e))?;
+            Ok(json!({"status": "set", "decoy_app": beenode::auth::decoy_app(&app), "alert": !opts.no_alert}))
+        }
+        Some("clear") => {
+            auth.clear_duress_pin(&pin).map_err(|e| format!("Duress clear failed: {}", e))?;
+            Ok(json!({"status": "cleared"}))
+        }
+        _ => Err("Usage: beenode duress set --mnemonic <decoy words> [--no-alert] | beenode duress clear".into()),
+    }
+}
+
+fn cmd_mobi(opts: &ParsedArgs) -> Result<Value, String> {
+    use beenode::Mobi;
+    let input = opts.path.as_deref().ok_or("Usage: beenode mobi <pubkey|npub> [mobi] | beenode mobi <mobi>")?;
+    if input.len() <= 27 && input.chars().all(|c| c.is_ascii_digit() || c == '-' || c.is_whitespace()) {
+        let digits = Mobi::validate(input).map_err(|e| e.to_string())?;
+        return Ok(json!({"valid": true, "digits": digits, "length": digits.len()}));
+    }
+
+    let pubkey = if input.starts_with("npub1") {
+        let (hrp, bytes) = bitcoin::bech32::decode(input).map_err(|e| format!("Invalid npub: {}", e))?;
+        if hrp.to_lowercase() != "npub" { return Err(format!("Expected an npub, got {}", hrp)); }
+        hex::encode(bytes)
+    } else {
+        input.to_string()
+    };
+    let mobi = Mobi::derive(&pubkey).map_err(|e| e.to_string())?;
+    let mut out = json!({
+        "pubkey": pubkey,
+        "display": mobi.display,
+        "formatted": mobi.display_formatted(),
+        "extended": mobi.extended,
+        "long": mobi.long,
+        "full": mobi.full,
+    });
+    if let Some(typed) = opts.data.as_deref() {
+        out["matches"] = json!(Mobi::matches_pubkey(typed, &pubkey).map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "shamir")]
+fn cmd_backup(opts: &ParsedArgs) -> Result<Value, String> {
+    if opts.path.as_deref() != Some("shamir") {
+        return Err("Usage: beenode backup shamir --shares <n> --threshold <k>".into());
+    }
+    let count = opts.shares.ok_or("--shares <n> is required")?;
+    let threshold = opts.threshold.ok_or("--threshold <k> is required")?;
+    let mnemonic = current_mnemonic(opts)?;
+    let shares = beenode::identity::shamir::split(mnemonic.expose(), threshold, count).map_err(|e| e.to_string())?;
+
+    // Shown once, never written anywhere
+    eprintln!("Any {} of these {} shares restore this node. Write each on its own sheet and store them apart.", threshold, count);
+    for (i, share) in shares.iter().enumerate() {
+        println!("share {}/{}: {}", i + 1, count, share);
+    }
+    Ok(Value::Null)
+}
+
+#[cfg(not(feature = "shamir"))]
+fn cmd_backup(_opts: &ParsedArgs) -> Result<Value, String> {
+    Err("backup requires the shamir feature".into())
+}
+
+#[cfg(feature = "shamir")]
+fn restore_shamir(shares: &[String]) -> Result<String, String> {
+    beenode::identity::shamir::combine(shares).map_err(|e| format!("Restore failed: {}", e))
+}
+
+#[cfg(not(feature = "shamir"))]
+fn restore_shamir(_shares: &[String]) -> Result<String, String> {
+    Err("--share requires the shamir feature".into())
+}
+
+/// The node's mnemonic: decrypted with the PIN under PIN auth, else from config/env
+#[cfg(feature = "shamir")]
+fn current_mnemonic(opts: &ParsedArgs) -> Result<beenode::Secret<String>, String> {
+    let config = load_config().ok();
+    let app = opts.app.clone()
+        .or_else(|| config.as_ref().and_then(|c| c["app"].as_str().map(String::from)))
+        .ok_or("BEENODE_APP not set")?;
+    let auth = PinAuth::open(NodeConfig::new(&app).auth_path()).map_err(|e| format!("Auth load failed: {}", e))?;
+    if auth.is_initialized() {
+        let pin = match opts.pin.clone() { Some(p) => p, None => prompt_pin()? };
+        if !auth.verify_pin(&pin).map_err(|e| e.to_string())? {
+            return Err("Invalid PIN".into());
+        }
+        return auth.decrypt_mnemonic(&pin).map_err(|e| e.to_string());
+    }
+    opts.mnemonic.clone()
+        .or_else(|| config.as_ref().and_then(|c| c["mnemonic"].as_str().map(String::from)))
+        .map(beenode::Secret::new)
+        .ok_or_else(|| "No mnemonic: set BEENODE_MNEMONIC or init with --auth none".into())
+}
+
+#[cfg(feature = "bitcoind-rpc")]
+fn cmd_dev(opts: &ParsedArgs) -> Result<Value, String> {
+    use beenode::wallet::Regtest;
+
+    let (url, user, pass) = match (&opts.rpc_url, &opts.rpc_user, &opts.rpc_pass) {
+        (Some(url), Some(user), Some(pass)) => (url, user, pass),
+        _ => return Err("BITCOIN_RPC_URL, BITCOIN_RPC_USER and BITCOIN_RPC_PASS are required".into()),
+    };
+    let amount = |usage: &str| -> Result<u64, String> {
+        opts.data.as_deref().ok_or_else(|| usage.to_string())?
+            .parse().map_err(|_| format!("Invalid number: {}", usage))
+    };
+    let regtest = Regtest::connect(url, user, pass).map_err(|e| format!("Regtest: {}", e))?;
+
+    match opts.path.as_deref() {
+        Some("mine") => {
+            let n = amount("beenode dev mine <n>")?;
+            let blocks = regtest.mine(n, None).map_err(|e| format!("Mine failed: {}", e))?;
+            let height = regtest.height().map_err(|e| format!("Mine failed: {}", e))?;
+            Ok(json!({"mined": blocks.len(), "height": height, "tip": blocks.last()}))
+        }
+        Some("fund") => {
+            let sats = amount("beenode dev fund <sats>")?;
+            let node = load_node_from_env()?;
+            unlock_if_needed(&node, "/wallet/address", opts.pin.as_deref())?;
+            let address = node.get("/wallet/address").map_err(|e| format!("Get failed: {}", e))?
+                .and_then(|s| s.data["address"].as_str().map(String::from))
+                .ok_or("No wallet address")?;
+            node.close().ok();
+            let txid = regtest.fund(&address, sats).map_err(|e| format!("Fund failed: {}", e))?;
+            let height = regtest.height().map_err(|e| format!("Fund failed: {}", e))?;
+            Ok(json!({"txid": txid, "address": address, "amount_sat": sats, "height": height}))
+        }
+        _ => Err("Usage: beenode dev mine <n> | beenode dev fund <sats>".into()),
+    }
+}
+
+#[cfg(not(feature = "bitcoind-rpc"))]
+fn cmd_dev(_opts: &ParsedArgs) -> Result<Value, String> {
+    Err("dev commands require the bitcoind-rpc feature".into())
+}
+
 fn unlock_if_needed(node: &Node, path: &str, pin: Option<&str>) -> Result<(), String> {
     if node.is_locked() && !path.starts_with("/system/auth") {
         let pin = pin.ok_or("Node is locked. Provide --pin or call /system/auth/unlock.")?;
@@ -822,7 +1374,11 @@ fn unlock_if_needed(node: &Node, path: &str, pin: Option<&str>) -> Result<(), St
 }
 
 fn prompt_pin() -> Result<String, String> {
-    print!("Enter PIN: ");
+    prompt_pin_as("PIN")
+}
+
+fn prompt_pin_as(label: &str) -> Result<String, String> {
+    print!("Enter {}: ", label);
     io::stdout().flush().ok();
     let mut pin = String::new();
     io::stdin().read_line(&mut pin).map_err(|e| format!("PIN read failed: {}", e))?;
@@ -0,0 +1,297 @@
+//! Client - the five verbs against a remote beenode over HTTP
+//!
+//! For services that talk to a node running `beenode serve` without linking
+//! the wallet, nostr or store stacks: build with
+//! `default-features = false, features = ["client"]` and only std, serde and
+//! nine-s-core come along.
+//!
+//! | Verb | Method | HTTP |
+//! |------|--------|------|
+//! | get | `client.get(path)` | `GET /scroll{path}` (404 is `None`) |
+//! | put | `client.put(path, data)` | `POST /scroll{path}` |
+//! | all | `client.all(prefix)` | `GET /scrolls?prefix=` |
+//! | effect | `client.execute_effect(path, data, timeout)` | `POST /scroll{path}?wait=true` |
+//! | on | `client.on(pattern)` | `GET /watch?pattern=` (SSE) |
+//! | close | `client.close()` | - |
+//!
+//! `Client` also implements `NodeApi` and `Namespace`, so code written
+//! against a local `Node` or a mounted namespace can be pointed at a remote
+//! node. Plain `http://` only; put a
+//! TLS-terminating proxy in front for anything else.
+//!
+//! ```ignore
+//! use beenode::client::Client;
+//!
+//! let node = Client::new("http://127.0.0.1:8080").with_token("s3cret");
+//! node.put("/notes/1", json!({"text": "hi"}))?;
+//! for scroll in node.on("/notes/**")? {
+//!     println!("{} changed", scroll.key);
+//! }
+//! ```
+
+use crate::core::api::NodeApi;
+use nine_s_core::namespace::Namespace;
+use nine_s_core::prelude::*;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often a quiet watch checks whether the client was closed
+const WATCH_POLL: Duration = Duration::from_secs(1);
+
+/// A remote node at `http://host:port`
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: String,
+    token: Option<String>,
+    timeout: Duration,
+    closed: Arc<AtomicBool>,
+}
+
+impl Client {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            token: None,
+            timeout: Duration::from_secs(30),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    /// Bearer token sent as `Authorization` (`BEENODE_API_TOKEN` on the server)
+    pub fn with_token(mut self, token: impl Into<String>) -> Self { self.token = Some(token.into()); self }
+    /// Read timeout for get/put/all and for a watch's response head
+    pub fn with_timeout(mut self, timeout: Duration) -> Self { self.timeout = timeout; self }
+
+    pub fn url(&self) -> &str { &self.url }
+
+    pub fn get(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        let (status, body) = self.request("GET", &format!("/scroll{}", encode(&absolute(path), false)), None)?;
+        match status {
+            200 => Ok(Some(parse(&body)?)),
+            404 => Ok(None),
+            _ => Err(failed(status, &body)),
+        }
+    }
+
+    /// Write `data`; the returned scroll carries the stored key and version
+    pub fn put(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        let (status, body) = self.request("POST", &format!("/scroll{}", encode(&absolute(path), false)), Some(&data))?;
+        if status != 200 { return Err(failed(status, &body)); }
+        let written: Value = parse(&body)?;
+        let mut scroll = Scroll::new(written["key"].as_str().unwrap_or(path), data);
+        scroll.metadata.version = written["version"].as_u64().unwrap_or(0);
+        Ok(scroll)
+    }
+
+    /// Write an effect under `/external` and wait up to `timeout` for its
+    /// result (`POST /scroll{path}?wait=true`)
+    pub fn execute_effect(&self, path: &str, data: Value, timeout: Duration) -> NineSResult<Scroll> {
+        let target = format!("/scroll{}?wait=true&timeout_ms={}", encode(&absolute(path), false), timeout.as_millis());
+        let (status, body) = self.clone().with_timeout(self.timeout + timeout).request("POST", &target, Some(&data))?;
+        if status != 200 { return Err(failed(status, &body)); }
+        let written: Value = parse(&body)?;
+        let key = format!("{}{}", written["key"].as_str().unwrap_or(path), crate::core::paths::mind::RESULT_SUFFIX);
+        let mut scroll = Scroll::new(&key, written["result"].clone()).set_type(crate::core::paths::EFFECT_RESULT_TYPE);
+        scroll.metadata.version = written["version"].as_u64().unwrap_or(0);
+        Ok(scroll)
+    }
+
+    pub fn all(&self, prefix: &str) -> NineSResult<Vec<String>> {
+        let (status, body) = self.request("GET", &format!("/scrolls?prefix={}", encode(&absolute(prefix), true)), None)?;
+        if status != 200 { return Err(failed(status, &body)); }
+        let listed: Value = parse(&body)?;
+        Ok(listed["paths"].as_array().into_iter().flatten().filter_map(|p| p.as_str().map(String::from)).collect())
+    }
+
+    /// Scrolls written under `pattern` from now on. The stream ends when the
+    /// connection drops or, within `WATCH_POLL`, after the client is closed.
+    pub fn on(&self, pattern: &str) -> NineSResult<Receiver<Scroll>> {
+        self.check_open()?;
+        let stream = self.connect("GET", &format!("/watch?pattern={}", encode(pattern, true)), None, Some(self.timeout))?;
+        let mut reader = BufReader::new(stream);
+        let status = read_head(&mut reader)?;
+        if status != 200 {
+            let mut body = String::new();
+            let _ = reader.read_to_string(&mut body);
+            return Err(failed(status, &body));
+        }
+
+        // Wake up now and then so a quiet stream still notices `close`
+        reader.get_ref().set_read_timeout(Some(WATCH_POLL)).map_err(|e| NineSError::Other(format!("{}: {}", self.url, e)))?;
+
+        let (tx, rx) = mpsc::channel();
+        let closed = self.closed.clone();
+        std::thread::spawn(move || {
+            let mut line = Vec::new();
+            while !closed.load(Ordering::Relaxed) {
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    // A timeout keeps any partial line for the next read
+                    Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+                    Err(_) => break,
+                }
+                if line.last() != Some(&b'\n') { continue; }
+                let line = String::from_utf8_lossy(&std::mem::take(&mut line)).into_owned();
+                let Some(data) = line.trim_end().strip_prefix("data:") else { continue };
+                let Ok(event) = serde_json::from_str::<Value>(data.trim_start()) else { continue };
+                let Ok(scroll) = serde_json::from_value::<Scroll>(event["scroll"].clone()) else { continue };
+                if tx.send(scroll).is_err() { break; }
+            }
+        });
+        Ok(rx)
+    }
+
+    /// Unlock the remote node with its PIN
+    pub fn unlock(&self, pin: &str) -> NineSResult<bool> {
+        self.auth("PUT", "/system/auth/unlock", Some(&serde_json::json!({"pin": pin})), "success")
+    }
+
+    pub fn lock(&self) -> NineSResult<bool> { self.auth("PUT", "/system/auth/lock", None, "success") }
+
+    pub fn is_locked(&self) -> bool { self.auth("GET", "/system/auth/status", None, "locked").unwrap_or(true) }
+
+    pub fn is_initialized(&self) -> bool { self.auth("GET", "/system/auth/status", None, "initialized").unwrap_or(false) }
+
+    fn auth(&self, method: &str, target: &str, body: Option<&Value>, field: &str) -> NineSResult<bool> {
+        let (status, body) = self.request(method, target, body)?;
+        if status != 200 { return Err(failed(status, &body)); }
+        Ok(parse::<Value>(&body)?[field].as_bool().unwrap_or(false))
+    }
+
+    /// Stop open watches and refuse further calls. The remote node keeps running.
+    pub fn close(&self) -> NineSResult<()> {
+        self.closed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn check_open(&self) -> NineSResult<()> {
+        if self.closed.load(Ordering::Relaxed) { return Err(NineSError::Other("client closed".into())); }
+        Ok(())
+    }
+
+    fn request(&self, method: &str, target: &str, body: Option<&Value>) -> NineSResult<(u16, String)> {
+        self.check_open()?;
+        let mut reader = BufReader::new(self.connect(method, target, body, Some(self.timeout))?);
+        let status = read_head(&mut reader)?;
+        let mut body = String::new();
+        reader.read_to_string(&mut body).map_err(|e| NineSError::Other(format!("{}: {}", self.url, e)))?;
+        Ok((status, body))
+    }
+
+    /// Send one HTTP/1.0 request, so the response is never chunked and ends
+    /// when the server closes the connection
+    fn connect(&self, method: &str, target: &str, body: Option<&Value>, timeout: Option<Duration>) -> NineSResult<TcpStream> {
+        let rest = self.url.strip_prefix("http://").ok_or_else(|| NineSError::Other(format!("unsupported url: {}", self.url)))?;
+        let (host, base) = rest.split_once('/').map(|(h, p)| (h, format!("/{}", p))).unwrap_or((rest, String::new()));
+        let addr = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        let io = |e: std::io::Error| NineSError::Other(format!("{}: {}", addr, e));
+
+        let mut stream = TcpStream::connect(&addr).map_err(io)?;
+        stream.set_read_timeout(timeout).map_err(io)?;
+        let auth = self.token.as_ref().map(|t| format!("Authorization: Bearer {}\r\n", t)).unwrap_or_default();
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let content = if !body.is_empty() { format!("Content-Type: application/json\r\nContent-Length: {}\r\n", body.len()) } else { String::new() };
+        write!(stream, "{} {}{} HTTP/1.0\r\nHost: {}\r\n{}{}\r\n{}", method, base, target, host, auth, content, body).map_err(io)?;
+        Ok(stream)
+    }
+}
+
+impl NodeApi for Client {
+    fn get(&self, path: &str) -> NineSResult<Option<Scroll>> { Client::get(self, path) }
+    fn put(&self, path: &str, data: Value) -> NineSResult<Scroll> { Client::put(self, path, data) }
+    fn all(&self, prefix: &str) -> NineSResult<Vec<String>> { Client::all(self, prefix) }
+    fn on(&self, pattern: &str) -> NineSResult<Receiver<Scroll>> { Client::on(self, pattern) }
+    fn close(&self) -> NineSResult<()> { Client::close(self) }
+    fn unlock(&self, pin: &str) -> NineSResult<bool> { Client::unlock(self, pin) }
+    fn lock(&self) -> NineSResult<bool> { Client::lock(self) }
+    fn is_locked(&self) -> bool { Client::is_locked(self) }
+    fn is_initialized(&self) -> bool { Client::is_initialized(self) }
+    #[cfg(feature = "native")]
+    fn execute_effect(&self, path: &str, data: Value, timeout: Duration) -> NineSResult<Scroll> { Client::execute_effect(self, path, data, timeout) }
+}
+
+impl Namespace for Client {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> { Client::get(self, path) }
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> { Client::put(self, path, data) }
+    fn list(&self, prefix: &str) -> NineSResult<Vec<String>> { Client::all(self, prefix) }
+    fn close(&self) -> NineSResult<()> { Client::close(self) }
+}
+
+/// Status code from the status line; headers are skipped
+fn read_head(reader: &mut impl BufRead) -> NineSResult<u16> {
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| NineSError::Other(format!("response: {}", e)))?;
+    let status = line.split_whitespace().nth(1).and_then(|s| s.parse().ok())
+        .ok_or_else(|| NineSError::Other(format!("malformed response: {}", line.trim())))?;
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line).map_err(|e| NineSError::Other(format!("response: {}", e)))?;
+        if n == 0 || line.trim().is_empty() { return Ok(status); }
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(body: &str) -> NineSResult<T> {
+    serde_json::from_str(body).map_err(|e| NineSError::Other(format!("response: {}", e)))
+}
+
+fn failed(status: u16, body: &str) -> NineSError { NineSError::Other(format!("HTTP {}: {}", status, body.trim())) }
+
+fn absolute(path: &str) -> String { if path.starts_with('/') { path.to_string() } else { format!("/{}", path) } }
+
+/// Percent-encode everything but unreserved characters (and `/` in paths)
+fn encode(s: &str, query: bool) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'/' if !query => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_head() {
+        assert_eq!(encode("/notes/a b", false), "/notes/a%20b");
+        assert_eq!(encode("/notes/**", true), "%2Fnotes%2F%2A%2A");
+
+        let raw = "HTTP/1.1 404 Not Found\r\ncontent-type: text/plain\r\n\r\nnot found: /x";
+        let mut reader = BufReader::new(raw.as_bytes());
+        assert_eq!(read_head(&mut reader).unwrap(), 404);
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "not found: /x");
+    }
+
+    #[test]
+    fn test_closed_client_refuses() {
+        let client = Client::new("http://127.0.0.1:1/");
+        assert_eq!(client.url(), "http://127.0.0.1:1");
+        client.close().unwrap();
+        assert!(client.get("/a").unwrap_err().to_string().contains("client closed"));
+    }
+
+    #[test]
+    fn test_close_ends_quiet_watch() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = Client::new(format!("http://{}", listener.local_addr().unwrap()));
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n").unwrap();
+            std::thread::sleep(Duration::from_secs(10));
+        });
+        let events = client.on("/notes/**").unwrap();
+        client.close().unwrap();
+        assert!(matches!(events.recv_timeout(WATCH_POLL * 3), Err(mpsc::RecvTimeoutError::Disconnected)));
+    }
+}
@@ -0,0 +1,83 @@
+//! NodeApi - the five verbs (plus lock/unlock) as a trait
+//!
+//! Implemented by `Node`, `client::Client` and `testing::MockNode`, so code
+//! that only needs the verbs (including `server::create_router_with_node`)
+//! can take `Arc<dyn NodeApi>` and be tested without a store on disk.
+//!
+//! Only the verbs and lock/unlock are required. The rest of what the HTTP
+//! router serves has provided defaults: derived from the verbs where that's
+//! honest (`status`, `put_batch`, `tree`, `watch_with`, ...), otherwise an
+//! error naming what this implementation doesn't support (`journal`,
+//! `changes`, `view`, `search`, effects with `?wait=true`).
+
+use nine_s_core::prelude::*;
+use serde_json::Value;
+use std::sync::mpsc::Receiver;
+
+pub trait NodeApi: Send + Sync {
+    fn get(&self, path: &str) -> NineSResult<Option<Scroll>>;
+    fn put(&self, path: &str, data: Value) -> NineSResult<Scroll>;
+    fn all(&self, prefix: &str) -> NineSResult<Vec<String>>;
+    fn on(&self, pattern: &str) -> NineSResult<Receiver<Scroll>>;
+    fn close(&self) -> NineSResult<()>;
+
+    fn unlock(&self, pin: &str) -> NineSResult<bool>;
+    fn lock(&self) -> NineSResult<bool>;
+    fn is_locked(&self) -> bool { false }
+    /// Whether a PIN has been set up
+    fn is_initialized(&self) -> bool { false }
+
+    fn status(&self) -> NineSResult<Value> {
+        Ok(self.get(crate::core::paths::status::PATH)?.map(|s| s.data).unwrap_or_default())
+    }
+    fn capabilities(&self) -> NineSResult<Value> {
+        Ok(self.get(crate::core::paths::capabilities::PATH)?.map(|s| s.data).unwrap_or_default())
+    }
+    /// Write each pair in order, stopping at the first failure
+    fn put_batch(&self, puts: Vec<(String, Value)>) -> NineSResult<Vec<Scroll>> {
+        puts.into_iter().map(|(path, data)| self.put(&path, data)).collect()
+    }
+
+    #[cfg(feature = "native")]
+    fn http_config(&self) -> crate::node::HttpConfig { Default::default() }
+    #[cfg(feature = "native")]
+    fn tree(&self, prefix: &str, depth: usize, after: Option<&str>, limit: usize) -> NineSResult<crate::node::TreePage> {
+        Ok(crate::node::browse::tree(&self.all(prefix)?, prefix, depth, after, limit))
+    }
+    #[cfg(feature = "native")]
+    fn meta(&self, path: &str) -> NineSResult<Option<Value>> {
+        Ok(self.get(path)?.as_ref().map(crate::node::browse::meta))
+    }
+    #[cfg(feature = "native")]
+    fn type_stats(&self, prefix: &str) -> NineSResult<std::collections::BTreeMap<String, usize>> {
+        let mut stats = std::collections::BTreeMap::new();
+        for path in self.all(prefix)? {
+            if let Some(scroll) = self.get(&path)? { *stats.entry(scroll.type_).or_default() += 1; }
+        }
+        Ok(stats)
+    }
+    #[cfg(feature = "native")]
+    fn watch_with(&self, pattern: &str, options: crate::core::watch::WatchOptions) -> NineSResult<Receiver<crate::core::watch::WatchEvent>> {
+        let watch = crate::core::watch::Watch::parse(pattern).map_err(|e| NineSError::invalid_path(pattern, &e.to_string()))?;
+        let rx = self.on(&watch.glob())?;
+        Ok(crate::node::watch::deliver(move || rx.recv().ok(), options, move |scroll| watch.event(scroll)))
+    }
+    #[cfg(feature = "native")]
+    fn on_with_replay(&self, _pattern: &str, _since: u64) -> NineSResult<Receiver<Scroll>> { Err(unsupported("replay")) }
+    #[cfg(feature = "native")]
+    fn journal(&self, _since: u64, _limit: usize) -> NineSResult<crate::node::JournalPage> { Err(unsupported("journal")) }
+    #[cfg(feature = "native")]
+    fn changes(&self, _since: u64, _epoch: Option<&str>, _limit: usize) -> NineSResult<crate::node::Changes> { Err(unsupported("replication")) }
+    #[cfg(feature = "native")]
+    fn view(&self, _name: &str) -> NineSResult<Value> { Err(unsupported("views")) }
+    #[cfg(feature = "native")]
+    fn execute_effect(&self, _path: &str, _data: Value, _timeout: std::time::Duration) -> NineSResult<Scroll> { Err(unsupported("effect wait")) }
+    #[cfg(feature = "search")]
+    fn search(&self, _query: &str, _limit: usize) -> NineSResult<Vec<crate::search::SearchHit>> { Err(unsupported("search")) }
+    /// Check a peer token for a request, returning the peer's mobi
+    #[cfg(feature = "native")]
+    fn verify_peer_token(&self, _token: &str, _request: &crate::peers::Request) -> NineSResult<String> { Err(unsupported("peer tokens")) }
+}
+
+#[cfg(feature = "native")]
+fn unsupported(what: &str) -> NineSError { NineSError::Other(format!("{} not supported by this node", what)) }
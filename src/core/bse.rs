@@ -19,11 +19,18 @@
 //! - `n` - Take: limit results
 //! - `l` - Layout: wrap in container
 //!
+//! Computation:
+//! - `a` - Aggregate: `sum,field` / `avg,field` / `min,field` / `max,field` / `count` / `group,field`
+//! - `m` - Map: project and rename fields (`m/title,total=data.amount/`)
+//!
+//! After `a/group,field/`, later aggregates apply per group.
+//!
 //! ## DSL Syntax
 //!
 //! ```text
 //! x/type=hero/ c/HeroBlock/
 //! x/type=post/ g/published/ o/date,desc/ n/5/ l/stack,16/ c/PostCard/
+//! x/type=tx/ a/group,category/ a/sum,amount/ o/sum,desc/ c/CategoryRow/
 //! ```
 
 use anyhow::{anyhow, Result};
@@ -51,6 +58,42 @@ pub enum Stage {
     N { count: usize },
     /// l/mode/ { children } - Layout: wrap in container
     L { mode: LayoutMode, #[serde(default)] gap: Option<u32>, children: Pipeline },
+    /// a/kind,field/ - Aggregate: collapse (or annotate groups) with totals
+    A { kind: AggregateKind, #[serde(default)] field: Option<String> },
+    /// m/out=in,.../ - Map: keep only the listed fields, renaming as given
+    M { fields: Vec<FieldMap> },
+}
+
+/// Aggregate kinds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateKind {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+    Group,
+}
+
+impl AggregateKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Count => "count",
+            Self::Group => "group",
+        }
+    }
+}
+
+/// One projected field: `to` in the output, read from `from` (dot path)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldMap {
+    pub to: String,
+    pub from: String,
 }
 
 /// Layout modes
@@ -121,35 +164,6 @@ impl BSEEngine {
 
         for stage in pipeline {
             match stage {
-                Stage::X { pattern } => {
-                    current = current.into_iter()
-                        .filter(|b| Self::matches(b, pattern))
-                        .collect();
-                }
-                Stage::Y { pattern } => {
-                    current = current.into_iter()
-                        .filter(|b| !Self::matches(b, pattern))
-                        .collect();
-                }
-                Stage::G { predicate } => {
-                    current = current.into_iter()
-                        .filter(|b| Self::matches(b, predicate))
-                        .collect();
-                }
-                Stage::V { predicate } => {
-                    current = current.into_iter()
-                        .filter(|b| !Self::matches(b, predicate))
-                        .collect();
-                }
-                Stage::O { field, desc } => {
-                    current.sort_by(|a, b| {
-                        let ord = Self::compare_field(a, b, field);
-                        if *desc { ord.reverse() } else { ord }
-                    });
-                }
-                Stage::N { count } => {
-                    current.truncate(*count);
-                }
                 Stage::C { renderer, props } => {
                     return Ok(current.into_iter().map(|block| {
                         let key = Self::get_key(&block);
@@ -181,12 +195,133 @@ impl BSEEngine {
                         children: child_nodes,
                     }]);
                 }
+                stage => current = Self::apply(current, stage)?,
             }
         }
 
         Ok(vec![])
     }
 
+    /// Run the data stages only (no `c`/`l` rendering) and return the blocks
+    pub fn select(pipeline: &Pipeline, source: &[Value]) -> Result<Vec<Value>> {
+        let mut current: Vec<Value> = source.to_vec();
+        for stage in pipeline {
+            if matches!(stage, Stage::C { .. } | Stage::L { .. }) { break; }
+            current = Self::apply(current, stage)?;
+        }
+        Ok(current)
+    }
+
+    /// Apply one data stage
+    fn apply(mut current: Vec<Value>, stage: &Stage) -> Result<Vec<Value>> {
+        match stage {
+            Stage::X { pattern } => {
+                current.retain(|b| Self::matches(b, pattern));
+            }
+            Stage::Y { pattern } => {
+                current.retain(|b| !Self::matches(b, pattern));
+            }
+            Stage::G { predicate } => {
+                current.retain(|b| Self::matches(b, predicate));
+            }
+            Stage::V { predicate } => {
+                current.retain(|b| !Self::matches(b, predicate));
+            }
+            Stage::O { field, desc } => {
+                current.sort_by(|a, b| {
+                    let ord = Self::compare_field(a, b, field);
+                    if *desc { ord.reverse() } else { ord }
+                });
+            }
+            Stage::N { count } => {
+                current.truncate(*count);
+            }
+            Stage::A { kind, field } => {
+                current = Self::aggregate(current, *kind, field.as_deref())?;
+            }
+            Stage::M { fields } => {
+                current = current.into_iter().map(|block| {
+                    let mut out = serde_json::Map::new();
+                    for f in fields {
+                        if let Some(v) = Self::get_field(&block, &f.from) {
+                            out.insert(f.to.clone(), v.clone());
+                        }
+                    }
+                    Value::Object(out)
+                }).collect();
+            }
+            Stage::C { .. } | Stage::L { .. } => {}
+        }
+        Ok(current)
+    }
+
+    /// True if the pipeline ends in rendered output (`c` or `l`)
+    pub fn renders(pipeline: &Pipeline) -> bool {
+        pipeline.iter().any(|s| matches!(s, Stage::C { .. } | Stage::L { .. }))
+    }
+
+    /// Apply an aggregate. Group blocks (`{group, count, items}`) are annotated
+    /// in place; plain blocks collapse to a single `{kind: value}` block.
+    fn aggregate(blocks: Vec<Value>, kind: AggregateKind, field: Option<&str>) -> Result<Vec<Value>> {
+        if kind == AggregateKind::Group {
+            let field = field.ok_or_else(|| anyhow!("a/group/ needs a field"))?;
+            let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+            for block in blocks {
+                let key = Self::get_field(&block, field).cloned().unwrap_or(Value::Null);
+                match groups.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, items)) => items.push(block),
+                    None => groups.push((key, vec![block])),
+                }
+            }
+            return Ok(groups.into_iter().map(|(key, items)| serde_json::json!({
+                "group": key,
+                "count": items.len(),
+                "items": items,
+            })).collect());
+        }
+
+        let is_grouped = !blocks.is_empty() && blocks.iter().all(|b| b.get("group").is_some() && b["items"].is_array());
+        if is_grouped {
+            return Ok(blocks.into_iter().map(|mut g| {
+                let value = Self::reduce(g["items"].as_array().map(Vec::as_slice).unwrap_or(&[]), kind, field);
+                g[kind.name()] = value;
+                g
+            }).collect());
+        }
+        let mut out = serde_json::Map::new();
+        out.insert(kind.name().into(), Self::reduce(&blocks, kind, field));
+        if let Some(f) = field { out.insert("field".into(), Value::String(f.into())); }
+        Ok(vec![Value::Object(out)])
+    }
+
+    fn reduce(blocks: &[Value], kind: AggregateKind, field: Option<&str>) -> Value {
+        if kind == AggregateKind::Count {
+            let n = match field {
+                Some(f) => blocks.iter().filter(|b| Self::get_field(b, f).is_some()).count(),
+                None => blocks.len(),
+            };
+            return Value::from(n);
+        }
+        let nums: Vec<&serde_json::Number> = field.map(|f| blocks.iter()
+            .filter_map(|b| match Self::get_field(b, f) { Some(Value::Number(n)) => Some(n), _ => None })
+            .collect()).unwrap_or_default();
+        // Integer fields (sats) stay exact; anything fractional falls back to f64
+        let all_int = nums.iter().all(|n| n.is_i64());
+        let ints = || nums.iter().filter_map(|n| n.as_i64());
+        let floats = || nums.iter().filter_map(|n| n.as_f64());
+        match kind {
+            AggregateKind::Sum if all_int => Value::from(ints().sum::<i64>()),
+            AggregateKind::Sum => Value::from(floats().sum::<f64>()),
+            AggregateKind::Min if all_int => ints().min().map(Value::from).unwrap_or(Value::Null),
+            AggregateKind::Max if all_int => ints().max().map(Value::from).unwrap_or(Value::Null),
+            AggregateKind::Min => floats().reduce(f64::min).map(Value::from).unwrap_or(Value::Null),
+            AggregateKind::Max => floats().reduce(f64::max).map(Value::from).unwrap_or(Value::Null),
+            AggregateKind::Avg if nums.is_empty() => Value::Null,
+            AggregateKind::Avg => Value::from(floats().sum::<f64>() / nums.len() as f64),
+            AggregateKind::Count | AggregateKind::Group => Value::Null,
+        }
+    }
+
     /// Check if a block matches a predicate
     fn matches(block: &Value, pred: &Predicate) -> bool {
         let field_value = Self::get_field(block, &pred.field);
@@ -337,6 +472,36 @@ pub fn parse_dsl(input: &str) -> Result<Pipeline> {
                 let count = count_str.parse().map_err(|_| anyhow!("invalid count in n//"))?;
                 pipeline.push(Stage::N { count });
             }
+            'a' => {
+                chars.next();
+                expect_char(&mut chars, '/')?;
+                let agg_str = read_until(&mut chars, '/')?;
+                let mut parts = agg_str.splitn(2, ',');
+                let kind = match parts.next().unwrap_or("").trim() {
+                    "sum" => AggregateKind::Sum,
+                    "avg" => AggregateKind::Avg,
+                    "min" => AggregateKind::Min,
+                    "max" => AggregateKind::Max,
+                    "count" => AggregateKind::Count,
+                    "group" => AggregateKind::Group,
+                    other => return Err(anyhow!("unknown aggregate: {}", other)),
+                };
+                let field = parts.next().map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+                if field.is_none() && kind != AggregateKind::Count {
+                    return Err(anyhow!("a/{}/ needs a field", kind.name()));
+                }
+                pipeline.push(Stage::A { kind, field });
+            }
+            'm' => {
+                chars.next();
+                expect_char(&mut chars, '/')?;
+                let map_str = read_until(&mut chars, '/')?;
+                let fields = map_str.split(',').map(str::trim).filter(|f| !f.is_empty()).map(|f| match f.split_once('=') {
+                    Some((to, from)) => FieldMap { to: to.trim().into(), from: from.trim().into() },
+                    None => FieldMap { to: f.rsplit('.').next().unwrap_or(f).into(), from: f.into() },
+                }).collect();
+                pipeline.push(Stage::M { fields });
+            }
             'l' => {
                 chars.next();
                 expect_char(&mut chars, '/')?;
@@ -366,6 +531,21 @@ pub fn parse_dsl(input: &str) -> Result<Pipeline> {
     Ok(pipeline)
 }
 
+/// Parse a match expression: a DSL of only `x`/`y`/`g`/`v` stages.
+/// Used by Mind patterns (`when`) so querying and reacting share one syntax.
+pub fn parse_match(input: &str) -> Result<Pipeline> {
+    let pipeline = parse_dsl(input)?;
+    if let Some(stage) = pipeline.iter().find(|s| !matches!(s, Stage::X { .. } | Stage::Y { .. } | Stage::G { .. } | Stage::V { .. })) {
+        return Err(anyhow!("match expressions only allow x/y/g/v stages, got {:?}", stage));
+    }
+    Ok(pipeline)
+}
+
+/// True if a single block passes every stage of a match expression
+pub fn matches_block(pipeline: &Pipeline, block: &Value) -> bool {
+    BSEEngine::select(pipeline, std::slice::from_ref(block)).map(|r| !r.is_empty()).unwrap_or(false)
+}
+
 fn expect_char(chars: &mut std::iter::Peekable<std::str::Chars>, expected: char) -> Result<()> {
     match chars.next() {
         Some(c) if c == expected => Ok(()),
@@ -561,4 +741,56 @@ mod tests {
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].props["title"], "Post"); // Non-hero
     }
+
+    #[test]
+    fn test_aggregate_totals() {
+        let source = vec![
+            json!({"type": "tx", "amount": 1000, "category": "food"}),
+            json!({"type": "tx", "amount": 2500, "category": "rent"}),
+            json!({"type": "tx", "amount": 500, "category": "food"}),
+        ];
+        let sum = BSEEngine::evaluate(&parse_dsl("x/type=tx/ a/sum,amount/ c/Total/").unwrap(), &source).unwrap();
+        assert_eq!(sum[0].props["sum"], 4000);
+        let count = BSEEngine::evaluate(&parse_dsl("a/count/ c/Total/").unwrap(), &source).unwrap();
+        assert_eq!(count[0].props["count"], 3);
+    }
+
+    #[test]
+    fn test_group_then_sum_and_map() {
+        let source = vec![
+            json!({"amount": 1000, "category": "food"}),
+            json!({"amount": 2500, "category": "rent"}),
+            json!({"amount": 500, "category": "food"}),
+        ];
+        let pipeline = parse_dsl("a/group,category/ a/sum,amount/ o/sum,desc/ m/name=group,total=sum/ c/Row/").unwrap();
+        let result = BSEEngine::evaluate(&pipeline, &source).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].props, json!({"name": "rent", "total": 2500}));
+        assert_eq!(result[1].props, json!({"name": "food", "total": 1500}));
+    }
+
+    #[test]
+    fn test_parse_aggregate_errors() {
+        assert!(parse_dsl("a/sum/").is_err());
+        assert!(parse_dsl("a/median,x/").is_err());
+    }
+
+    #[test]
+    fn test_select_without_render() {
+        let pipeline = parse_dsl("x/type=post/ o/score,desc/ n/1/").unwrap();
+        let source = vec![
+            json!({"type": "post", "score": 1}),
+            json!({"type": "post", "score": 3}),
+        ];
+        assert!(!BSEEngine::renders(&pipeline));
+        assert_eq!(BSEEngine::select(&pipeline, &source).unwrap(), vec![json!({"type": "post", "score": 3})]);
+    }
+
+    #[test]
+    fn test_parse_match() {
+        let m = parse_match("x/type=wallet.tx/ g/data.amount>100000/").unwrap();
+        assert!(matches_block(&m, &json!({"type": "wallet.tx", "data": {"amount": 250000}})));
+        assert!(!matches_block(&m, &json!({"type": "wallet.tx", "data": {"amount": 5}})));
+        assert!(parse_match("x/type=post/ c/Card/").is_err());
+    }
 }
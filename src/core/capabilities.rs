@@ -0,0 +1,75 @@
+//! Capabilities - What this build can do, for clients to adapt to
+//!
+//! Served at `/sys/capabilities` and `GET /capabilities`. `api_version` moves
+//! only on breaking changes to paths or scroll shapes; additive changes show
+//! up as new `features` and `scroll_types`.
+
+use super::paths;
+use serde_json::{json, Value};
+
+/// HTTP and path API version
+pub const API_VERSION: u32 = 1;
+
+/// Compiled-in features
+pub fn features() -> Value {
+    json!({
+        "native": cfg!(feature = "native"),
+        "wasm": cfg!(feature = "wasm"),
+        "wallet": cfg!(feature = "wallet"),
+        "bitcoind_rpc": cfg!(feature = "bitcoind-rpc"),
+        "nostr": cfg!(feature = "nostr"),
+        "search": cfg!(feature = "search"),
+        "plugins": cfg!(feature = "plugins"),
+        "ui": cfg!(feature = "ui"),
+        "market": cfg!(feature = "market"),
+        "shamir": cfg!(feature = "shamir"),
+        "s3": cfg!(feature = "s3"),
+        "wireguard": cfg!(feature = "native"),
+        "ssh": cfg!(feature = "native"),
+        // Zap receipts via an external LNURL server; there is no Lightning node here
+        "lightning": cfg!(feature = "nostr"),
+    })
+}
+
+/// Scroll types this build writes
+pub fn scroll_types() -> Vec<&'static str> {
+    let mut types = vec![
+        paths::identity::STATUS_TYPE, paths::identity::DERIVATION_TYPE, paths::identity::MIGRATION_TYPE,
+        paths::migrations::TYPE, paths::views::TYPE, paths::views::RESULT_TYPE, paths::replication::TYPE, paths::journal::TYPE,
+        paths::logging::TYPE, paths::alerts::TYPE,
+        paths::clock::TICK_TYPE, paths::clock::PULSE_TYPE, paths::clock::STATUS_TYPE,
+        paths::market::PRICE_TYPE, paths::market::HISTORY_TYPE, paths::market::REFRESH_TYPE,
+        paths::status::TYPE, paths::capabilities::TYPE,
+        paths::effects::RECORD_TYPE, paths::effects::LIST_TYPE,
+        paths::recurring::TYPE, paths::recurring::STATE_TYPE, paths::recurring::RUN_TYPE, paths::services::TYPE, paths::crashes::TYPE,
+        paths::store_health::TYPE, paths::store_health::CORRUPT_TYPE,
+        paths::EFFECT_RESULT_TYPE,
+    ];
+    if cfg!(feature = "wallet") {
+        use paths::wallet_types as w;
+        types.extend([w::PENDING, w::CONFIRMED, w::INVOICE, w::PAID, w::RECEIPT, w::COIN, w::ADVICE]);
+    }
+    if cfg!(feature = "s3") {
+        types.push(paths::archive::UPLOAD_TYPE);
+    }
+    if cfg!(feature = "nostr") {
+        use paths::nostr_types as n;
+        types.extend([
+            n::STATUS, n::PUBKEY, n::MOBI, n::RELAYS, n::SIGNATURE, n::CONNECT, n::PUBLISH, n::VERIFY,
+            n::PROFILE, n::DM, n::RELAY_STATUS, n::OUTBOX, n::ARCHIVE, n::CONTACT, n::CONTACTS,
+            n::ZAP, n::ZAP_CONFIG, n::BUNKER, n::BACKUP, n::PAIR, n::PEER_REQUEST, n::PRESENCE,
+            paths::peers::TYPE, paths::peers::TOKEN_TYPE, paths::peers::PRESENCE_TYPE,
+        ]);
+    }
+    types
+}
+
+/// `{api_version, version, features, scroll_types}`
+pub fn describe() -> Value {
+    json!({
+        "api_version": API_VERSION,
+        "version": env!("CARGO_PKG_VERSION"),
+        "features": features(),
+        "scroll_types": scroll_types(),
+    })
+}
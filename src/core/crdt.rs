@@ -0,0 +1,167 @@
+//! CRDT scroll types - data that merges instead of conflicting
+//!
+//! | Type | Data | `value` |
+//! |------|------|---------|
+//! | `crdt/counter@v1` | `{counts: {replica: n}}` | sum of counts |
+//! | `crdt/lww-map@v1` | `{entries: {key: {value, at, replica}}}` | `{key: value}` without tombstones |
+//! | `crdt/or-set@v1` | `{adds: {elem: [tag]}, removes: {elem: [tag]}}` | elements with an unremoved add |
+//!
+//! A scroll written with one of these types is merged with what's stored at
+//! its path instead of replacing it (`Node::put_scroll`, replication), so two
+//! devices bumping a counter or tagging a note both land. Merges are
+//! commutative, associative and idempotent; `value` is recomputed on every
+//! merge for readers that don't care about the structure.
+//!
+//! Writers build the data with the types here: `GCounter::increment`,
+//! `LwwMap::set`, `OrSet::add` / `remove`, each keyed by a replica id (a
+//! device name, or the node's pubkey).
+
+use nine_s_core::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const COUNTER: &str = "crdt/counter@v1";
+pub const LWW_MAP: &str = "crdt/lww-map@v1";
+pub const OR_SET: &str = "crdt/or-set@v1";
+
+/// Grow-only counter: each replica only raises its own count
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GCounter {
+    #[serde(default)]
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl GCounter {
+    pub fn increment(&mut self, replica: &str, by: u64) { *self.counts.entry(replica.into()).or_default() += by; }
+    pub fn value(&self) -> u64 { self.counts.values().sum() }
+    pub fn merge(&mut self, other: &Self) {
+        for (replica, n) in &other.counts {
+            let mine = self.counts.entry(replica.clone()).or_default();
+            *mine = (*mine).max(*n);
+        }
+    }
+}
+
+/// One last-writer-wins register; `null` value is a tombstone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LwwEntry {
+    pub value: Value,
+    pub at: u64,
+    pub replica: String,
+}
+
+/// Map of last-writer-wins registers; ties on `at` go to the greater replica
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LwwMap {
+    #[serde(default)]
+    pub entries: BTreeMap<String, LwwEntry>,
+}
+
+impl LwwMap {
+    pub fn set(&mut self, key: &str, value: Value, at: u64, replica: &str) {
+        self.put(key, LwwEntry { value, at, replica: replica.into() });
+    }
+    pub fn remove(&mut self, key: &str, at: u64, replica: &str) { self.set(key, Value::Null, at, replica); }
+    pub fn get(&self, key: &str) -> Option<&Value> { self.entries.get(key).map(|e| &e.value).filter(|v| !v.is_null()) }
+    pub fn value(&self) -> BTreeMap<String, Value> {
+        self.entries.iter().filter(|(_, e)| !e.value.is_null()).map(|(k, e)| (k.clone(), e.value.clone())).collect()
+    }
+    pub fn merge(&mut self, other: &Self) {
+        for (key, entry) in &other.entries { self.put(key, entry.clone()); }
+    }
+    fn put(&mut self, key: &str, entry: LwwEntry) {
+        match self.entries.get(key) {
+            Some(current) if (current.at, &current.replica) >= (entry.at, &entry.replica) => {}
+            _ => { self.entries.insert(key.into(), entry); }
+        }
+    }
+}
+
+/// Observed-remove set: a remove only cancels the adds it has seen, so a
+/// concurrent add survives
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrSet {
+    #[serde(default)]
+    pub adds: BTreeMap<String, BTreeSet<String>>,
+    #[serde(default)]
+    pub removes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl OrSet {
+    /// Add `elem` under a tag unique to this add (e.g. `{replica}:{counter}`)
+    pub fn add(&mut self, elem: &str, tag: &str) { self.adds.entry(elem.into()).or_default().insert(tag.into()); }
+    pub fn remove(&mut self, elem: &str) {
+        if let Some(tags) = self.adds.get(elem) { self.removes.entry(elem.into()).or_default().extend(tags.iter().cloned()); }
+    }
+    pub fn contains(&self, elem: &str) -> bool {
+        let removed = self.removes.get(elem);
+        self.adds.get(elem).is_some_and(|tags| tags.iter().any(|t| !removed.is_some_and(|r| r.contains(t))))
+    }
+    pub fn value(&self) -> Vec<String> { self.adds.keys().filter(|e| self.contains(e)).cloned().collect() }
+    pub fn merge(&mut self, other: &Self) {
+        for (elem, tags) in &other.adds { self.adds.entry(elem.clone()).or_default().extend(tags.iter().cloned()); }
+        for (elem, tags) in &other.removes { self.removes.entry(elem.clone()).or_default().extend(tags.iter().cloned()); }
+    }
+}
+
+pub fn is_crdt(type_: &str) -> bool { matches!(type_, COUNTER | LWW_MAP | OR_SET) }
+
+/// Merge two scroll datas of CRDT `type_`, with `value` filled in
+pub fn merge(type_: &str, current: &Value, incoming: &Value) -> NineSResult<Value> {
+    match type_ {
+        COUNTER => merged::<GCounter>(type_, current, incoming, GCounter::merge, |c| json!(c.value())),
+        LWW_MAP => merged::<LwwMap>(type_, current, incoming, LwwMap::merge, |m| json!(m.value())),
+        OR_SET => merged::<OrSet>(type_, current, incoming, OrSet::merge, |s| json!(s.value())),
+        _ => Err(NineSError::Other(format!("{} is not a CRDT type", type_))),
+    }
+}
+
+fn merged<T: Serialize + DeserializeOwned + Default>(type_: &str, current: &Value, incoming: &Value, merge: fn(&mut T, &T), value: fn(&T) -> Value) -> NineSResult<Value> {
+    let decode = |v: &Value| -> NineSResult<T> {
+        if v.is_null() { return Ok(T::default()); }
+        serde_json::from_value(v.clone()).map_err(|e| NineSError::Other(format!("{}: {}", type_, e)))
+    };
+    let mut state = decode(current)?;
+    merge(&mut state, &decode(incoming)?);
+    let mut data = serde_json::to_value(&state).map_err(|e| NineSError::Other(format!("{}: {}", type_, e)))?;
+    data["value"] = value(&state);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_merge() {
+        let (mut phone, mut laptop) = (GCounter::default(), GCounter::default());
+        phone.increment("phone", 2);
+        laptop.increment("laptop", 3);
+        let once = merge(COUNTER, &json!(phone), &json!(laptop)).unwrap();
+        assert_eq!(once["value"], 5);
+        // Re-applying the same state changes nothing
+        assert_eq!(merge(COUNTER, &once, &json!(laptop)).unwrap()["value"], 5);
+    }
+
+    #[test]
+    fn test_lww_and_or_set() {
+        let mut a = LwwMap::default();
+        let mut b = LwwMap::default();
+        a.set("color", json!("red"), 10, "a");
+        b.set("color", json!("blue"), 12, "b");
+        b.remove("size", 5, "b");
+        let ab = merge(LWW_MAP, &json!(a), &json!(b)).unwrap();
+        assert_eq!(ab, merge(LWW_MAP, &json!(b), &json!(a)).unwrap());
+        assert_eq!(ab["value"], json!({"color": "blue"}));
+
+        let mut tags = OrSet::default();
+        tags.add("work", "a:1");
+        let mut other = tags.clone();
+        other.remove("work");
+        tags.add("work", "b:1");
+        let merged = merge(OR_SET, &json!(tags), &json!(other)).unwrap();
+        assert_eq!(merged["value"], json!(["work"]));
+        assert_eq!(merge(OR_SET, &json!(other), &Value::Null).unwrap()["value"], json!([]));
+    }
+}
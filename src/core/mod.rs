@@ -1,5 +1,13 @@
 //! Core abstractions for agentic nodes
 
+pub mod api;
 pub mod bse;
+pub mod capabilities;
+pub mod crdt;
 pub mod paths;
 pub mod pattern;
+pub mod proxy;
+pub mod template;
+pub mod time;
+pub mod typed;
+pub mod watch;
@@ -8,34 +8,177 @@ pub mod wallet {
     pub const STATUS: &str = "/status";
     pub const BALANCE: &str = "/balance";
     pub const ADDRESS: &str = "/address";
+    pub const ADDRESS_NEW: &str = "/address/new";
+    pub const ADDRESSES: &str = "/addresses";
     pub const NETWORK: &str = "/network";
+    pub const BACKEND: &str = "/backend";
+    pub const SCAN: &str = "/scan";
+    pub const ACCOUNT: &str = "/account";
+    pub const GAP_CHECK: &str = "/gap-check";
+    pub const RESCAN: &str = "/rescan";
     pub const TRANSACTIONS: &str = "/transactions";
     pub const SYNC: &str = "/sync";
     pub const SEND: &str = "/send";
+    pub const SWEEP: &str = "/sweep";
     pub const RECEIVE: &str = "/receive";
     pub const FEE_ESTIMATE: &str = "/fee-estimate";
     pub const UTXOS: &str = "/utxos";
+    /// `/utxos/{outpoint}/freeze` and `/utxos/{outpoint}/label`
+    pub const FREEZE: &str = "/freeze";
+    pub const LABEL: &str = "/label";
+    pub const ADVICE_CONSOLIDATION: &str = "/advice/consolidation";
+    pub const ADVICE_APPROVE: &str = "/advice/consolidation/approve";
+    pub const DESCRIPTOR: &str = "/descriptor";
+    pub const XPUB: &str = "/xpub";
+    pub const SIGN_MESSAGE: &str = "/sign-message";
+    pub const VERIFY_MESSAGE: &str = "/verify-message";
+    pub const PENDING: &str = "/pending";
+    pub const EVENTS: &str = "/events";
+    pub const INVOICES: &str = "/invoices";
+    pub const RECEIPTS: &str = "/receipts";
+    /// Address requests from payers, parked by `/nostr/pay/poll`
+    pub const PAY_REQUESTS: &str = "/pay-requests";
+    pub const PAY_ANSWER: &str = "/pay-requests/answer";
+    pub const SILENT_CODE: &str = "/silent-payment-code";
+    pub const SILENT_PAYMENTS: &str = "/silent-payments";
+    pub const SILENT_SCAN: &str = "/silent-payments/scan";
+    pub const PRIVACY_REPORT: &str = "/privacy/report";
+    /// `?format=json|csv|koinly&currency=usd`
+    pub const EXPORT_TRANSACTIONS: &str = "/export/transactions";
+
+    pub const PENDING_PREFIX: &str = "/wallet/pending";
+    /// Written after each completed sync `{synced_at, confirmed, pending}`
+    pub const SYNC_STATE: &str = "/wallet/sync/state";
+    /// Birthday and stop gap `{birthday_height, stop_gap}`, kept across restarts
+    pub const SCAN_CONFIG: &str = "/wallet/scan";
+    /// Account a migration moved to `{account, previous, sweep_txid, migrated_at}`;
+    /// opened on the next start unless `WalletConfig::account` says otherwise
+    pub const ACCOUNT_CONFIG: &str = "/wallet/account";
+    /// Latest `/gap-check` report
+    pub const GAP_CHECK_RESULT: &str = "/wallet/gap-check";
+    /// Progress, then outcome, of the latest `/rescan`
+    pub const RESCAN_STATE: &str = "/wallet/rescan";
+    pub const CONFIRMED_PREFIX: &str = "/wallet/events/confirmed";
+    pub const INVOICES_PREFIX: &str = "/wallet/invoices";
+    pub const PAID_PREFIX: &str = "/wallet/events/paid";
+    pub const RECEIPTS_PREFIX: &str = "/wallet/receipts";
+    pub const UTXOS_PREFIX: &str = "/wallet/utxos";
+    /// `/wallet/sends/{idempotency_key}`
+    pub const SENDS_PREFIX: &str = "/wallet/sends";
+    pub const CONSOLIDATION_ADVICE: &str = "/wallet/advice/consolidation";
+    /// Block scan cursor `{next_height}`, written when the code is first read
+    pub const SILENT_STATE: &str = "/wallet/silent-payments/state";
+    /// `/wallet/silent-payments/outputs/{txid}:{vout}`
+    pub const SILENT_OUTPUTS: &str = "/wallet/silent-payments/outputs";
 
     pub const EXTERNAL_SYNC: &str = "/external/bitcoin/sync";
     pub const EXTERNAL_SEND: &str = "/external/bitcoin/send";
+    pub const EXTERNAL_ADVICE: &str = "/external/bitcoin/advice";
+    pub const EXTERNAL_GAP_CHECK: &str = "/external/bitcoin/gap-check";
+    pub const EXTERNAL_RESCAN: &str = "/external/bitcoin/rescan";
+    pub const EXTERNAL_SILENT: &str = "/external/bitcoin/silent-payments";
+
+    pub const ALL: &[&str] = &[STATUS, BALANCE, ADDRESS, ADDRESSES, NETWORK, BACKEND, TRANSACTIONS, RECEIVE, UTXOS, PENDING, INVOICES, RECEIPTS];
 
-    pub const ALL: &[&str] = &[STATUS, BALANCE, ADDRESS, NETWORK, TRANSACTIONS, RECEIVE, UTXOS];
+    /// First segments of `/wallet` paths; a named wallet can't take one
+    pub const RESERVED: &[&str] = &[
+        "status", "balance", "address", "addresses", "network", "backend", "scan", "gap-check", "rescan", "account", "transactions",
+        "sync", "send", "sweep", "receive", "fee-estimate", "utxos", "advice", "descriptor", "xpub",
+        "sign-message", "verify-message", "pending", "events", "invoices", "receipts", "sends", "pay-requests",
+        "silent-payment-code", "silent-payments", "privacy", "export",
+    ];
+}
+
+/// Wallet scroll types
+pub mod wallet_types {
+    pub const PENDING: &str = "wallet/pending@v1";
+    pub const CONFIRMED: &str = "wallet/confirmed@v1";
+    /// Left at a pending path once the send has settled `{txid, moved_to}`
+    pub const MOVED: &str = "wallet/moved@v1";
+    pub const INVOICE: &str = "wallet/invoice@v1";
+    pub const PAID: &str = "wallet/paid@v1";
+    pub const RECEIPT: &str = "wallet/receipt@v1";
+    pub const COIN: &str = "wallet/coin@v1";
+    pub const ADVICE: &str = "wallet/advice@v1";
+    pub const GAP_CHECK: &str = "wallet/gap-check@v1";
+    pub const RESCAN: &str = "wallet/rescan@v1";
+    pub const SEND: &str = "wallet/send@v1";
+    pub const SILENT: &str = "wallet/silent-payment@v1";
 }
 
 /// Nostr paths
 pub mod nostr {
     pub const STATUS: &str = "/status";
     pub const PUBKEY: &str = "/pubkey";
+    pub const NPUB: &str = "/npub";
     pub const MOBI: &str = "/mobi";
     pub const RELAYS: &str = "/relays";
     pub const SIGN: &str = "/sign";
     pub const CONNECT: &str = "/connect";
     pub const PUBLISH: &str = "/publish";
+    pub const VERIFY: &str = "/verify";
+    pub const PROFILE: &str = "/profile";
+    pub const DM: &str = "/dm";
+    pub const CONTACTS: &str = "/contacts";
+    pub const CONTACTS_SYNC: &str = "/contacts/sync";
+    pub const CONTACTS_PUBLISH: &str = "/contacts/publish";
+    pub const ZAPS: &str = "/zaps";
+    pub const ZAPS_SYNC: &str = "/zaps/sync";
+    pub const BUNKER: &str = "/bunker";
+    pub const BUNKER_POLL: &str = "/bunker/poll";
+    pub const PAY_POLL: &str = "/pay/poll";
+    pub const BACKUP: &str = "/backup";
+    pub const BACKUP_RESTORE: &str = "/backup/restore";
+    pub const PAIR: &str = "/pair";
+    pub const PAIR_ACCEPT: &str = "/pair/accept";
+    pub const PAIR_POLL: &str = "/pair/poll";
+    pub const PEERS_REQUEST: &str = "/peers/request";
+    pub const PEERS_POLL: &str = "/peers/poll";
+    pub const PRESENCE: &str = "/presence";
+
+    /// Materialized kind-3 follow list (outside the /nostr mount)
+    pub const CONTACTS_PREFIX: &str = "/contacts/nostr";
+    pub const CONTACTS_STATE: &str = "/nostr/contacts/state";
+    pub const PROFILES_PREFIX: &str = "/nostr/profiles";
+    pub const RELAYS_PREFIX: &str = "/nostr/relays";
+    pub const OUTBOX_PREFIX: &str = "/nostr/outbox";
+    pub const ARCHIVE_PREFIX: &str = "/nostr/archive";
+    pub const ZAPS_CONFIG: &str = "/nostr/zaps/config";
+    pub const ZAPS_STATE: &str = "/nostr/zaps/state";
+    pub const ZAPS_RECEIVED: &str = "/nostr/zaps/received";
+    pub const BUNKER_CONFIG: &str = "/nostr/bunker/config";
+    pub const BUNKER_STATE: &str = "/nostr/bunker/state";
+    pub const BUNKER_REQUESTS: &str = "/nostr/bunker/requests";
+    pub const PAY_STATE: &str = "/nostr/pay/state";
+    /// Address requests from payers `/nostr/pay/requests/{event_id}`
+    pub const PAY_REQUESTS: &str = "/nostr/pay/requests";
+    pub const BACKUP_STATE: &str = "/nostr/backup/state";
+    /// One-time pairing offers `/nostr/pair/offers/{hash of secret}`
+    pub const PAIR_OFFERS: &str = "/nostr/pair/offers";
+    pub const PAIR_STATE: &str = "/nostr/pair/state";
+    /// Cursor of served peer requests `{since, seen}`
+    pub const PEERS_STATE: &str = "/nostr/peers/state";
+    /// Handled peer requests `/nostr/peers/served/{event_id}` `{from, op, path, status, served_at}`
+    pub const PEERS_SERVED: &str = "/nostr/peers/served";
+    /// Cursor of heartbeats read `{since, seen}`
+    pub const PRESENCE_STATE: &str = "/nostr/presence/state";
 
     pub const EXTERNAL_CONNECT: &str = "/external/nostr/connect";
     pub const EXTERNAL_PUBLISH: &str = "/external/nostr/publish";
+    pub const EXTERNAL_DM: &str = "/external/nostr/dm";
+    pub const EXTERNAL_RECEIPT: &str = "/external/nostr/receipt";
+    pub const EXTERNAL_OUTBOX: &str = "/external/nostr/outbox";
+    pub const EXTERNAL_ARCHIVE: &str = "/external/nostr/archive";
+    pub const EXTERNAL_CONTACTS: &str = "/external/nostr/contacts";
+    pub const EXTERNAL_ZAPS: &str = "/external/nostr/zaps";
+    pub const EXTERNAL_BUNKER: &str = "/external/nostr/bunker";
+    pub const EXTERNAL_PAY: &str = "/external/nostr/pay";
+    pub const EXTERNAL_BACKUP: &str = "/external/nostr/backup";
+    pub const EXTERNAL_PAIR: &str = "/external/nostr/pair";
+    pub const EXTERNAL_PEERS: &str = "/external/nostr/peers";
+    pub const EXTERNAL_PRESENCE: &str = "/external/nostr/presence";
 
-    pub const ALL: &[&str] = &[STATUS, PUBKEY, MOBI, RELAYS];
+    pub const ALL: &[&str] = &[STATUS, PUBKEY, NPUB, MOBI, RELAYS, PROFILE];
 }
 
 /// Nostr scroll types
@@ -47,10 +190,99 @@ pub mod nostr_types {
     pub const SIGNATURE: &str = "nostr/signature@v1";
     pub const CONNECT: &str = "nostr/connect@v1";
     pub const PUBLISH: &str = "nostr/publish@v1";
+    pub const VERIFY: &str = "nostr/verify@v1";
+    pub const PROFILE: &str = "nostr/profile@v1";
+    pub const DM: &str = "nostr/dm@v1";
+    pub const RELAY_STATUS: &str = "nostr/relay-status@v1";
+    pub const OUTBOX: &str = "nostr/outbox@v1";
+    pub const ARCHIVE: &str = "nostr/archive@v1";
+    pub const CONTACT: &str = "nostr/contact@v1";
+    pub const CONTACTS: &str = "nostr/contacts@v1";
+    pub const ZAP: &str = "nostr/zap@v1";
+    pub const ZAP_CONFIG: &str = "nostr/zap-config@v1";
+    pub const BUNKER: &str = "nostr/bunker@v1";
+    pub const BACKUP: &str = "nostr/backup@v1";
+    pub const PAIR: &str = "nostr/pair@v1";
+    pub const PEER_REQUEST: &str = "nostr/peer-request@v1";
+    pub const PRESENCE: &str = "nostr/presence@v1";
+    pub const PAY: &str = "nostr/pay@v1";
+}
+
+/// Identity paths
+pub mod identity {
+    pub const STATUS: &str = "/status";
+    pub const DERIVE: &str = "/derive";
+    pub const DERIVATIONS: &str = "/derivations";
+
+    pub const DERIVATIONS_PREFIX: &str = "/sys/identity/derivations";
+    /// Old → new identity after a key rotation, keyed by the old Mobi
+    pub const MIGRATIONS_PREFIX: &str = "/sys/identity/migrations";
+
+    pub const STATUS_TYPE: &str = "identity/status@v1";
+    pub const DERIVATION_TYPE: &str = "identity/derivation@v1";
+    pub const MIGRATION_TYPE: &str = "identity/migration@v1";
+
+    pub const ALL: &[&str] = &[STATUS, DERIVATIONS];
+}
+
+/// Crypto paths (`crypto::CryptoNamespace`)
+pub mod crypto {
+    pub const ENCRYPT: &str = "/crypto/encrypt";
+}
+
+/// Migration tracking paths
+pub mod migrations {
+    pub const PREFIX: &str = "/sys/migrations";
+    pub const TYPE: &str = "sys/migration@v1";
+}
+
+/// Named BSE view paths
+pub mod views {
+    pub const PREFIX: &str = "/sys/views";
+    pub const MATERIALIZED_PREFIX: &str = "/views";
+
+    pub const TYPE: &str = "sys/view@v1";
+    pub const RESULT_TYPE: &str = "view/result@v1";
+}
+
+/// Follower replication state
+pub mod replication {
+    pub const PREFIX: &str = "/sys/replication";
+    pub const STATE: &str = "/sys/replication/state";
+
+    pub const TYPE: &str = "sys/replication@v1";
+}
+
+/// Change journal: one entry per write, numbered from 1
+pub mod journal {
+    pub const PREFIX: &str = "/sys/journal";
+    pub const STATE: &str = "/sys/journal/state";
+    pub const ENTRIES: &str = "/sys/journal/entries";
+
+    pub const TYPE: &str = "sys/journal@v1";
+}
+
+/// Log mirror and runtime filter paths
+pub mod logging {
+    pub const PREFIX: &str = "/sys/logs";
+    pub const LEVEL: &str = "/sys/logging/level";
+
+    pub const TYPE: &str = "sys/log@v1";
+}
+
+/// System alert paths
+pub mod alerts {
+    pub const PREFIX: &str = "/sys/alerts";
+    pub const NOSTR: &str = "/sys/alerts/nostr";
+    /// Raised in the real store when the duress PIN is used
+    pub const DURESS: &str = "/sys/alerts/duress";
+
+    pub const TYPE: &str = "sys/alert@v1";
 }
 
 /// Clock paths (Layer 0)
 pub mod clock {
+    pub const PREFIX: &str = "/sys/clock";
     pub const STATUS: &str = "/sys/clock/status";
     pub const TICK: &str = "/sys/clock/tick";
     pub const PULSES: &str = "/sys/clock/pulses";
@@ -61,6 +293,13 @@ pub mod clock {
     pub const STATUS_TYPE: &str = "clock/status@v1";
 }
 
+/// Browser node platform state (`wasm::platform`)
+pub mod platform {
+    pub const STATUS: &str = "/sys/platform/status";
+    /// Writes waiting for connectivity: `{writes: [{path, data}]}`
+    pub const QUEUE: &str = "/sys/platform/queue";
+}
+
 /// Mind/Effects paths
 pub mod mind {
     pub const PATTERNS_PREFIX: &str = "/sys/mind/patterns";
@@ -69,6 +308,130 @@ pub mod mind {
     pub const RESULT_SUFFIX: &str = "/result";
 }
 
+/// Exchange rates
+pub mod market {
+    pub const PRICE_PREFIX: &str = "/market/price";
+    /// `/market/history/{pair}/{YYYY-MM-DD}`: the day's last recorded price
+    pub const HISTORY_PREFIX: &str = "/market/history";
+    pub const EXTERNAL_PRICE: &str = "/external/market/price";
+    /// Pairs fetched when a refresh effect names none
+    pub const DEFAULT_PAIRS: &[&str] = &["btc-usd", "btc-eur"];
+
+    pub const PRICE_TYPE: &str = "market/price@v1";
+    pub const HISTORY_TYPE: &str = "market/history@v1";
+    pub const REFRESH_TYPE: &str = "market/refresh@v1";
+}
+
+/// Composite overview computed on read
+pub mod status {
+    pub const PATH: &str = "/sys/status";
+    pub const TYPE: &str = "sys/status@v1";
+}
+
+/// Build features, scroll types and API version
+pub mod capabilities {
+    pub const PATH: &str = "/sys/capabilities";
+    pub const TYPE: &str = "sys/capabilities@v1";
+}
+
+/// Effect queue introspection (namespace at `/sys/effects`)
+pub mod effects {
+    pub const PREFIX: &str = "/sys/effects";
+    /// Worker records, keyed by effect path: `/sys/effects/records/external/...`
+    pub const RECORDS_PREFIX: &str = "/sys/effects/records";
+
+    pub const QUEUE: &str = "/queue";
+    pub const RUNNING: &str = "/running";
+    pub const HISTORY: &str = "/history";
+    pub const REQUEUE: &str = "/requeue";
+
+    pub const RECORD_TYPE: &str = "sys/effect@v1";
+    pub const LIST_TYPE: &str = "sys/effects@v1";
+}
+
+/// Recurring payments: definitions at `/sys/recurring/{name}`
+pub mod recurring {
+    pub const PREFIX: &str = "/sys/recurring";
+    /// `/sys/recurring/{name}/state`
+    pub const STATE: &str = "/state";
+    /// `/sys/recurring/{name}/runs/{seq}`
+    pub const RUNS: &str = "/runs";
+
+    pub const TYPE: &str = "sys/recurring@v1";
+    pub const STATE_TYPE: &str = "sys/recurring-state@v1";
+    pub const RUN_TYPE: &str = "sys/recurring-run@v1";
+    /// Block type seen by `policy` expressions
+    pub const PAYMENT_BLOCK: &str = "recurring.payment";
+}
+
+/// Sandboxed WASM plugins: manifests at `/sys/plugins/{name}`
+pub mod plugins {
+    pub const PREFIX: &str = "/sys/plugins";
+    /// `{digest, approved_at}` per plugin, written only by `Node::approve_plugin`
+    pub const APPROVALS: &str = "/sys/plugin-approvals";
+
+    pub const TYPE: &str = "sys/plugin@v1";
+    pub const APPROVAL_TYPE: &str = "sys/plugin-approval@v1";
+}
+
+/// Supervised background services: health at `/sys/services/{name}`
+pub mod services {
+    pub const PREFIX: &str = "/sys/services";
+
+    pub const TYPE: &str = "sys/service@v1";
+}
+
+/// Panic reports from background tasks: `/sys/crashes/{ms}-{task}`
+pub mod crashes {
+    pub const PREFIX: &str = "/sys/crashes";
+
+    pub const TYPE: &str = "sys/crash@v1";
+}
+
+/// Paired nodes (see `nostr::pair`)
+pub mod peers {
+    /// `/peers/{mobi}`: `{pubkey, mobi, name, granted, allowed, relays, paired_at, generation}`
+    pub const PREFIX: &str = "/peers";
+    /// Relative to `/peers/{mobi}`
+    pub const TOKEN: &str = "/token";
+    pub const REVOKE: &str = "/revoke";
+    /// A token the peer issued us, kept as the record's `token`
+    pub const CREDENTIAL: &str = "/credential";
+    /// `/peers/{mobi}/tokens/{nonce}`: issued tokens' caveats (never the token)
+    pub const TOKENS: &str = "/tokens";
+    /// `/peers/{mobi}/presence`: last heartbeat, `online` derived on read
+    pub const PRESENCE: &str = "/presence";
+    /// `/peers/{mobi}/external/**`: effects run on the peer (`nostr::peers`)
+    pub const EXTERNAL: &str = "/external";
+
+    pub const TYPE: &str = "peers/peer@v1";
+    pub const TOKEN_TYPE: &str = "peers/token@v1";
+    pub const PRESENCE_TYPE: &str = "peers/presence@v1";
+}
+
+/// Cold storage in an S3 bucket (`s3` feature)
+pub mod archive {
+    pub const PREFIX: &str = "/archive";
+    /// `/external/archive/upload/{id}`: `{path, object}` waiting to be uploaded
+    pub const EXTERNAL_UPLOAD: &str = "/external/archive/upload";
+
+    pub const UPLOAD_TYPE: &str = "archive/upload@v1";
+}
+
+/// Startup integrity pass (`NodeConfig::verify_on_open`)
+pub mod store_health {
+    pub const PATH: &str = "/sys/store/health";
+    /// `/sys/corrupt{path}`: what was wrong with a quarantined entry
+    pub const CORRUPT_PREFIX: &str = "/sys/corrupt";
+
+    pub const TYPE: &str = "sys/store-health@v1";
+    pub const CORRUPT_TYPE: &str = "sys/corrupt@v1";
+}
+
+/// Placeholder left where a rolled-back batch had created a path; the store
+/// has no delete, so `Node::get` reads it as absent
+pub const ABSENT_TYPE: &str = "sys/absent@v1";
+
 /// Scroll type for effect results
 pub const EFFECT_RESULT_TYPE: &str = "effect/result@v1";
 
@@ -77,4 +440,5 @@ pub mod origin {
     pub const CLOCK: &str = "clock";
     pub const MIND: &str = "mind";
     pub const EFFECTS: &str = "effects";
+    pub const RECURRING: &str = "recurring";
 }
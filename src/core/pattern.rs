@@ -1,5 +1,20 @@
 //! Pattern: Pike's structural regexp for scrolls (x/g/v/then)
+//!
+//! `x`/`g`/`v` are regexes over the serialized data. `when` is the BSE form
+//! over a structured block, e.g. `x/type=wallet.tx/ g/data.amount>100000/`:
+//!
+//! - `key`, `data`, `metadata.version`, `metadata.produced_by`
+//! - `type` - dotted name without version (`wallet/tx@v1` → `wallet.tx`)
+//! - `type_` - raw scroll type
+//!
+//! `reactions` emits further scrolls alongside (or instead of) `emit`.
+//!
+//! Templates take `${...}` substitutions and `{{expr}}` expressions (see
+//! [`template`](crate::core::template)) over the triggering scroll and its
+//! `x` captures in one pass: text in the scroll's data is never evaluated.
 
+use crate::core::bse::{self, Pipeline};
+use crate::core::template;
 use anyhow::{anyhow, Result};
 use nine_s_core::prelude::*;
 use regex::Regex;
@@ -15,10 +30,22 @@ pub struct PatternDef {
     #[serde(skip_serializing_if = "Option::is_none")] pub x: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] pub g: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] pub v: Option<String>,
+    #[serde(default)] pub emit: String,
+    #[serde(default)] pub emit_path: String,
+    #[serde(default)] pub template: Value,
+    #[serde(skip_serializing_if = "Option::is_none")] pub then: Option<String>,
+    /// BSE match expression (x/y/g/v stages) over the scroll block
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub when: Option<String>,
+    /// Additional reaction templates, emitted in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub reactions: Vec<Reaction>,
+}
+
+/// One reaction template: scroll type, path and data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
     pub emit: String,
     pub emit_path: String,
-    pub template: Value,
-    #[serde(skip_serializing_if = "Option::is_none")] pub then: Option<String>,
+    #[serde(default)] pub template: Value,
 }
 
 /// Compiled pattern with cached regexes
@@ -30,10 +57,12 @@ pub struct Pattern {
     x: Option<Arc<Regex>>,
     g: Option<Arc<Regex>>,
     v: Option<Arc<Regex>>,
+    when: Option<Pipeline>,
     pub emit: String,
     pub emit_path: String,
     pub template: Value,
     pub then: Option<String>,
+    pub reactions: Vec<Reaction>,
 }
 
 impl Pattern {
@@ -41,10 +70,16 @@ impl Pattern {
         let watch_pattern = WatchPattern::parse(&def.watch)
             .map_err(|e| anyhow!("invalid watch pattern '{}': {}", def.watch, e))?;
         let compile_re = |s: &Option<String>| s.as_ref().map(|s| Regex::new(s)).transpose().map(|r| r.map(Arc::new));
+        let when = def.when.as_deref().map(bse::parse_match).transpose()
+            .map_err(|e| anyhow!("invalid when '{}': {}", def.when.as_deref().unwrap_or(""), e))?;
+        if def.emit_path.is_empty() && def.reactions.is_empty() {
+            return Err(anyhow!("pattern '{}' has no emit_path or reactions", def.name));
+        }
         Ok(Self {
             name: def.name, watch: def.watch, watch_pattern,
-            x: compile_re(&def.x)?, g: compile_re(&def.g)?, v: compile_re(&def.v)?,
+            x: compile_re(&def.x)?, g: compile_re(&def.g)?, v: compile_re(&def.v)?, when,
             emit: def.emit, emit_path: def.emit_path, template: def.template, then: def.then,
+            reactions: def.reactions,
         })
     }
 
@@ -55,10 +90,18 @@ impl Pattern {
     pub fn matches_path(&self, path: &str) -> bool { self.watch_pattern.matches(path) }
 
     pub fn apply(&self, scroll: &Scroll, origin: Option<&str>) -> Result<Option<Scroll>> {
-        if !self.matches_path(&scroll.key) { return Ok(None); }
+        Ok(self.apply_all(scroll, origin)?.into_iter().next())
+    }
+
+    /// All reactions: the primary `emit` (if set) followed by `reactions`
+    pub fn apply_all(&self, scroll: &Scroll, origin: Option<&str>) -> Result<Vec<Scroll>> {
+        if !self.matches_path(&scroll.key) { return Ok(vec![]); }
         let data_str = serde_json::to_string(&scroll.data)?;
-        if self.g.as_ref().map(|g| !g.is_match(&data_str)).unwrap_or(false) { return Ok(None); }
-        if self.v.as_ref().map(|v| v.is_match(&data_str)).unwrap_or(false) { return Ok(None); }
+        if self.g.as_ref().map(|g| !g.is_match(&data_str)).unwrap_or(false) { return Ok(vec![]); }
+        if self.v.as_ref().map(|v| v.is_match(&data_str)).unwrap_or(false) { return Ok(vec![]); }
+        if let Some(when) = &self.when {
+            if !bse::matches_block(when, &block(scroll)) { return Ok(vec![]); }
+        }
 
         let captures: Vec<String> = self.x.as_ref()
             .and_then(|x| x.captures(&data_str))
@@ -66,38 +109,96 @@ impl Pattern {
             .unwrap_or_default();
         let segs: Vec<&str> = scroll.key.split('/').filter(|s| !s.is_empty()).collect();
 
-        let metadata = origin.map(|o| Metadata::default().with_produced_by(o)).unwrap_or_default();
-        Ok(Some(Scroll {
-            key: substitute(&self.emit_path, &captures, &segs, &scroll.data),
-            type_: self.emit.clone(),
-            metadata,
-            data: substitute_value(&self.template, &captures, &segs, &scroll.data),
-        }))
+        let ctx = context(scroll, &segs, &captures);
+        let primary = (!self.emit_path.is_empty()).then_some((&self.emit, &self.emit_path, &self.template));
+        let rest = self.reactions.iter().map(|r| (&r.emit, &r.emit_path, &r.template));
+        Ok(primary.into_iter().chain(rest).map(|(emit, path, template)| {
+            let metadata = origin.map(|o| Metadata::default().with_produced_by(o)).unwrap_or_default();
+            let (uuid, mut subst) = (short_id(), Vec::new());
+            let path = placeholders(path, &captures, &segs, &scroll.data, &uuid, &mut subst);
+            let template = placeholders_value(template, &captures, &segs, &scroll.data, &uuid, &mut subst);
+            let mut ctx = ctx.clone();
+            ctx["subst"] = Value::from(subst);
+            Scroll {
+                key: template::render_str(&path, &ctx),
+                type_: emit.clone(),
+                metadata,
+                data: template::render_value(&template, &ctx),
+            }
+        }).collect())
     }
 }
 
+/// Template context: the triggering scroll with path segments and `x` captures
+fn context(scroll: &Scroll, segs: &[&str], captures: &[String]) -> Value {
+    serde_json::json!({
+        "key": scroll.key,
+        "type": scroll.type_,
+        "data": scroll.data,
+        "metadata": {
+            "version": scroll.metadata.version,
+            "created_at": scroll.metadata.created_at,
+            "updated_at": scroll.metadata.updated_at,
+            "produced_by": scroll.metadata.produced_by,
+        },
+        "path": segs,
+        "captures": captures,
+    })
+}
 
-fn substitute(template: &str, caps: &[String], segs: &[&str], data: &Value) -> String {
-    let mut r = template.to_string();
-    for (i, c) in caps.iter().enumerate() { r = r.replace(&format!("${{{}}}", i + 1), c); }
-    for (i, s) in segs.iter().enumerate() { r = r.replace(&format!("${{path.{}}}", i), s); }
-    r = r.replace("${uuid}", &short_id());
-    if let Value::Object(obj) = data {
-        for (k, v) in obj {
-            let val = match v { Value::String(s) => s.clone(), Value::Number(n) => n.to_string(), _ => continue };
-            r = r.replace(&format!("${{data.{}}}", k), &val);
+/// Structured view of a scroll for `when` expressions
+pub(crate) fn block(scroll: &Scroll) -> Value {
+    let name = scroll.type_.split('@').next().unwrap_or("").replace('/', ".");
+    serde_json::json!({
+        "key": scroll.key,
+        "type": name,
+        "type_": scroll.type_,
+        "data": scroll.data,
+        "metadata": {
+            "version": scroll.metadata.version,
+            "produced_by": scroll.metadata.produced_by,
+        },
+    })
+}
+
+/// `${...}` placeholders in `tpl` become `{{subst.N}}` expressions over the
+/// values pushed to `subst`, so the template is rendered once and no value
+/// is scanned again. Unknown placeholders stay as written.
+fn placeholders(tpl: &str, caps: &[String], segs: &[&str], data: &Value, uuid: &str, subst: &mut Vec<String>) -> String {
+    let mut out = String::new();
+    let mut rest = tpl;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else { out.push_str(&rest[start..]); return out };
+        let name = &rest[start + 2..start + end];
+        match lookup(name, caps, segs, data, uuid) {
+            Some(value) => { out.push_str(&format!("{{{{subst.{}}}}}", subst.len())); subst.push(value); }
+            None => out.push_str(&rest[start..start + end + 1]),
         }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn lookup(name: &str, caps: &[String], segs: &[&str], data: &Value, uuid: &str) -> Option<String> {
+    if name == "uuid" { return Some(uuid.to_string()); }
+    if let Ok(i) = name.parse::<usize>() { return caps.get(i.checked_sub(1)?).cloned(); }
+    if let Some(i) = name.strip_prefix("path.") { return segs.get(i.parse::<usize>().ok()?).map(|s| s.to_string()); }
+    match &data[name.strip_prefix("data.")?] {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
     }
-    r
 }
 
-fn substitute_value(tpl: &Value, caps: &[String], segs: &[&str], data: &Value) -> Value {
+fn placeholders_value(tpl: &Value, caps: &[String], segs: &[&str], data: &Value, uuid: &str, subst: &mut Vec<String>) -> Value {
     match tpl {
-        Value::String(s) => Value::String(substitute(s, caps, segs, data)),
+        Value::String(s) => Value::String(placeholders(s, caps, segs, data, uuid, subst)),
         Value::Object(obj) => Value::Object(obj.iter()
-            .map(|(k, v)| (substitute(k, caps, segs, data), substitute_value(v, caps, segs, data)))
+            .map(|(k, v)| (placeholders(k, caps, segs, data, uuid, subst), placeholders_value(v, caps, segs, data, uuid, subst)))
             .collect()),
-        Value::Array(arr) => Value::Array(arr.iter().map(|v| substitute_value(v, caps, segs, data)).collect()),
+        Value::Array(arr) => Value::Array(arr.iter().map(|v| placeholders_value(v, caps, segs, data, uuid, subst)).collect()),
         other => other.clone(),
     }
 }
@@ -141,6 +242,8 @@ mod tests {
                 "user": "${path.1}"
             }),
             then: None,
+            when: None,
+            reactions: vec![],
         };
         let pattern = Pattern::compile(def).unwrap();
 
@@ -161,4 +264,66 @@ mod tests {
         assert!(reaction.key.starts_with("/external/apns/abc123/"));
         assert_eq!(reaction.data["user"], "abc123");
     }
+
+    #[test]
+    fn test_when_and_reactions() {
+        let def: PatternDef = serde_json::from_value(json!({
+            "name": "big-tx",
+            "watch": "/wallet/tx/*",
+            "when": "x/type=wallet.tx/ g/data.amount>100000/",
+            "reactions": [
+                {"emit": "alert@v1", "emit_path": "/alerts/${path.2}", "template": {"amount": "${data.amount}"}},
+                {"emit": "log@v1", "emit_path": "/log/${path.2}", "template": {}}
+            ]
+        })).unwrap();
+        let pattern = Pattern::compile(def).unwrap();
+
+        let big = Scroll::new("/wallet/tx/abc", json!({"amount": 250000})).set_type("wallet/tx@v1");
+        let out = pattern.apply_all(&big, None).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].key, "/alerts/abc");
+        assert_eq!(out[0].data["amount"], "250000");
+        assert_eq!(out[1].key, "/log/abc");
+
+        let small = Scroll::new("/wallet/tx/def", json!({"amount": 10})).set_type("wallet/tx@v1");
+        assert!(pattern.apply_all(&small, None).unwrap().is_empty());
+        let other = Scroll::new("/wallet/tx/ghi", json!({"amount": 250000})).set_type("note@v1");
+        assert!(pattern.apply(&other, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_template_expressions() {
+        let def: PatternDef = serde_json::from_value(json!({
+            "name": "receipt",
+            "watch": "/wallet/tx/*",
+            "emit": "receipt@v1",
+            "emit_path": "/receipts/{{path.2 | upper}}",
+            "template": {"sat": "{{data.amount_sat}}", "kbtc": "{{data.amount_sat / 1000}}", "label": "tx {{path.2}}"}
+        })).unwrap();
+        let pattern = Pattern::compile(def).unwrap();
+        let out = pattern.apply(&Scroll::new("/wallet/tx/abc", json!({"amount_sat": 21000})), None).unwrap().unwrap();
+        assert_eq!(out.key, "/receipts/ABC");
+        assert_eq!(out.data["sat"], 21000);
+        assert_eq!(out.data["kbtc"], 21);
+        assert_eq!(out.data["label"], "tx abc");
+    }
+
+    #[test]
+    fn test_data_is_not_a_template() {
+        let def: PatternDef = serde_json::from_value(json!({
+            "name": "echo",
+            "watch": "/inbox/*",
+            "x": r#""secret":"(\w+)""#,
+            "emit": "echo@v1",
+            "emit_path": "/echo/${path.1}",
+            "template": {"memo": "${data.memo}", "note": "{{data.note}}", "first": "{{captures.0}}"}
+        })).unwrap();
+        let pattern = Pattern::compile(def).unwrap();
+        let data = json!({"memo": "{{data.secret}}", "note": "{{key}} ${uuid}", "secret": "s3cret"});
+        let out = pattern.apply(&Scroll::new("/inbox/a", data), None).unwrap().unwrap();
+        assert_eq!(out.key, "/echo/a");
+        assert_eq!(out.data["memo"], "{{data.secret}}");
+        assert_eq!(out.data["note"], "{{key}} ${uuid}");
+        assert_eq!(out.data["first"], "s3cret");
+    }
 }
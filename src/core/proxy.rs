@@ -0,0 +1,60 @@
+//! Proxy - SOCKS5 for every outbound connection
+//!
+//! `NodeConfig::with_proxy("socks5://127.0.0.1:9050")` routes Electrum,
+//! bitcoind RPC, Nostr relay WebSockets and market price requests through a
+//! SOCKS5 proxy such as Tor. Hostnames are handed to the proxy unresolved, so
+//! `.onion` backends work and lookups don't leak to the local resolver.
+//!
+//! `NodeConfig::require_tor_on_mainnet` refuses to start a mainnet wallet
+//! without a proxy.
+
+use nine_s_core::errors::{NineSError, NineSResult};
+
+/// Tor's SOCKS port
+pub const TOR: &str = "socks5://127.0.0.1:9050";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Proxy {
+    /// `socks5://host:port`, `socks5h://host:port` or bare `host:port`
+    pub fn parse(url: &str) -> NineSResult<Self> {
+        let url = url.trim();
+        let rest = match url.split_once("://") {
+            Some(("socks5" | "socks5h", rest)) => rest,
+            Some((scheme, _)) => return Err(NineSError::Other(format!("proxy: unsupported scheme {}", scheme))),
+            None => url,
+        };
+        let rest = rest.trim_end_matches('/');
+        let (host, port) = rest.rsplit_once(':').ok_or_else(|| NineSError::Other(format!("proxy: missing port in {}", url)))?;
+        let port = port.parse().map_err(|_| NineSError::Other(format!("proxy: bad port in {}", url)))?;
+        if host.is_empty() { return Err(NineSError::Other(format!("proxy: missing host in {}", url))); }
+        Ok(Self { host: host.to_string(), port })
+    }
+
+    /// `host:port`
+    pub fn addr(&self) -> String { format!("{}:{}", self.host, self.port) }
+
+    /// `socks5h://host:port`, for clients that take a URL and should resolve remotely
+    pub fn url(&self) -> String { format!("socks5h://{}", self.addr()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let p = Proxy::parse(TOR).unwrap();
+        assert_eq!((p.host.as_str(), p.port), ("127.0.0.1", 9050));
+        assert_eq!(p.url(), "socks5h://127.0.0.1:9050");
+        assert_eq!(Proxy::parse("socks5h://tor:9150/").unwrap().addr(), "tor:9150");
+        assert_eq!(Proxy::parse("[::1]:9050").unwrap().host, "[::1]");
+        assert!(Proxy::parse("http://127.0.0.1:8080").is_err());
+        assert!(Proxy::parse("socks5://127.0.0.1").is_err());
+        assert!(Proxy::parse(":9050").is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! Template: `{{expr}}` interpolation for pattern reactions
+//!
+//! Expressions read from a context value (the triggering scroll as
+//! `{key, type, data, metadata, path, captures}`), support arithmetic and `|` helpers:
+//!
+//! ```text
+//! {{data.amount_sat}}                   → 150000 (number preserved)
+//! {{data.amount_sat / 100000000}}       → 0.0015
+//! {{data.name | upper}}                 → "ALICE"
+//! {{data.memo | default "none"}}        → "none" when missing
+//! sent {{data.amount_sat | div 1000}}k  → "sent 150k"
+//! ```
+//!
+//! Helpers: `upper`, `lower`, `trim`, `len`, `round N`, `floor`, `ceil`,
+//! `div N`, `mul N`, `default V`, `truncate N`, `json`.
+//! A string that is exactly one `{{...}}` keeps the result's JSON type.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Render every string in `tpl` against `ctx`
+pub fn render_value(tpl: &Value, ctx: &Value) -> Value {
+    match tpl {
+        Value::String(s) => render_typed(s, ctx),
+        Value::Object(obj) => Value::Object(obj.iter()
+            .map(|(k, v)| (render_str(k, ctx), render_value(v, ctx)))
+            .collect()),
+        Value::Array(arr) => Value::Array(arr.iter().map(|v| render_value(v, ctx)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Render a string, preserving type when it is a single expression
+fn render_typed(s: &str, ctx: &Value) -> Value {
+    let trimmed = s.trim();
+    if let Some(inner) = trimmed.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")) {
+        if !inner.contains("{{") {
+            return eval(inner, ctx).unwrap_or(Value::Null);
+        }
+    }
+    Value::String(render_str(s, ctx))
+}
+
+/// Interpolate all `{{...}}` in a string. Errors render as empty.
+pub fn render_str(s: &str, ctx: &Value) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else { out.push_str(&rest[start..]); return out };
+        let expr = &rest[start + 2..start + end];
+        out.push_str(&display(&eval(expr, ctx).unwrap_or(Value::Null)));
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn display(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Evaluate `expr | helper arg | ...`
+pub fn eval(expr: &str, ctx: &Value) -> Result<Value> {
+    let mut stages = split_pipes(expr).into_iter();
+    let head = stages.next().unwrap_or_default();
+    let mut value = Parser::new(&head, ctx)?.expression()?;
+    for stage in stages {
+        let stage = stage.trim();
+        let (name, arg) = stage.split_once(char::is_whitespace).map(|(n, a)| (n, Some(a.trim()))).unwrap_or((stage, None));
+        let arg = arg.map(|a| Parser::new(a, ctx).and_then(|mut p| p.expression())).transpose()?;
+        value = helper(name, value, arg)?;
+    }
+    Ok(value)
+}
+
+/// Split on `|` outside string literals
+fn split_pipes(expr: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    for c in expr.chars() {
+        match c {
+            '"' => { quoted = !quoted; parts.last_mut().unwrap().push(c); }
+            '|' if !quoted => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+fn helper(name: &str, v: Value, arg: Option<Value>) -> Result<Value> {
+    let arg_num = || arg.as_ref().and_then(Value::as_f64).ok_or_else(|| anyhow!("{} needs a number", name));
+    Ok(match name {
+        "upper" => Value::String(display(&v).to_uppercase()),
+        "lower" => Value::String(display(&v).to_lowercase()),
+        "trim" => Value::String(display(&v).trim().to_string()),
+        "len" => Value::from(match &v {
+            Value::Array(a) => a.len(),
+            Value::Object(o) => o.len(),
+            other => display(other).chars().count(),
+        }),
+        "json" => Value::String(v.to_string()),
+        "default" => if v.is_null() || v == "" { arg.unwrap_or(Value::Null) } else { v },
+        "truncate" => Value::String(display(&v).chars().take(arg_num()? as usize).collect()),
+        "round" => {
+            let p = 10f64.powi(arg.as_ref().and_then(Value::as_i64).unwrap_or(0) as i32);
+            number((num(&v)? * p).round() / p)
+        }
+        "floor" => number(num(&v)?.floor()),
+        "ceil" => number(num(&v)?.ceil()),
+        "div" => number(num(&v)? / arg_num()?),
+        "mul" => number(num(&v)? * arg_num()?),
+        other => return Err(anyhow!("unknown helper: {}", other)),
+    })
+}
+
+fn num(v: &Value) -> Result<f64> {
+    match v {
+        Value::Number(n) => n.as_f64().ok_or_else(|| anyhow!("bad number")),
+        Value::String(s) => s.trim().parse().map_err(|_| anyhow!("not a number: {}", s)),
+        Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        _ => Err(anyhow!("not a number: {}", v)),
+    }
+}
+
+/// Integral results stay integers (sats); everything else is f64
+fn number(f: f64) -> Value {
+    if f.fract() == 0.0 && f.abs() < 9.0e15 { Value::from(f as i64) } else { Value::from(f) }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token { Num(f64), Str(String), Path(String), Op(char) }
+
+struct Parser<'a> { tokens: Vec<Token>, pos: usize, ctx: &'a Value }
+
+impl<'a> Parser<'a> {
+    fn new(src: &str, ctx: &'a Value) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut chars = src.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() { chars.next(); continue; }
+            if c.is_ascii_digit() {
+                let mut s = String::new();
+                while let Some(&d) = chars.peek() { if d.is_ascii_digit() || d == '.' { s.push(d); chars.next(); } else { break; } }
+                tokens.push(Token::Num(s.parse().map_err(|_| anyhow!("bad number: {}", s))?));
+            } else if c == '"' {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(ch) => s.push(ch),
+                        None => return Err(anyhow!("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            } else if "+-*/%()".contains(c) {
+                tokens.push(Token::Op(c));
+                chars.next();
+            } else if c.is_alphanumeric() || c == '_' {
+                let mut s = String::new();
+                while let Some(&d) = chars.peek() { if d.is_alphanumeric() || d == '_' || d == '.' { s.push(d); chars.next(); } else { break; } }
+                tokens.push(Token::Path(s));
+            } else {
+                return Err(anyhow!("unexpected '{}' in template", c));
+            }
+        }
+        Ok(Self { tokens, pos: 0, ctx })
+    }
+
+    fn peek(&self) -> Option<&Token> { self.tokens.get(self.pos) }
+    fn next(&mut self) -> Option<Token> { let t = self.tokens.get(self.pos).cloned(); self.pos += 1; t }
+
+    fn expression(&mut self) -> Result<Value> {
+        let v = self.sum()?;
+        if self.pos < self.tokens.len() { return Err(anyhow!("trailing tokens in template")); }
+        Ok(v)
+    }
+
+    fn sum(&mut self) -> Result<Value> {
+        let mut left = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let right = self.product()?;
+            left = match (op, &left, &right) {
+                ('+', Value::String(_), _) | ('+', _, Value::String(_)) if num(&left).is_err() || num(&right).is_err() => {
+                    Value::String(display(&left) + &display(&right))
+                }
+                ('+', ..) => number(num(&left)? + num(&right)?),
+                _ => number(num(&left)? - num(&right)?),
+            };
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Value> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let (a, b) = (num(&left)?, num(&self.unary()?)?);
+            if op != '*' && b == 0.0 { return Err(anyhow!("division by zero")); }
+            left = number(match op { '*' => a * b, '/' => a / b, _ => a % b });
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Value> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.next();
+            return Ok(number(-num(&self.unary()?)?));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(number(n)),
+            Some(Token::Str(s)) => Ok(Value::String(s)),
+            Some(Token::Path(p)) => Ok(match p.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => lookup(self.ctx, &p).cloned().unwrap_or(Value::Null),
+            }),
+            Some(Token::Op('(')) => {
+                let v = self.sum()?;
+                match self.next() { Some(Token::Op(')')) => Ok(v), _ => Err(anyhow!("missing ')'")) }
+            }
+            other => Err(anyhow!("unexpected {:?} in template", other)),
+        }
+    }
+}
+
+/// Dot-path lookup; numeric segments index arrays
+fn lookup<'v>(v: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').try_fold(v, |cur, seg| match cur {
+        Value::Object(o) => o.get(seg),
+        Value::Array(a) => seg.parse::<usize>().ok().and_then(|i| a.get(i)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ctx() -> Value {
+        json!({"key": "/wallet/tx/abc", "data": {"amount_sat": 150000, "name": " Alice "}, "metadata": {"updated_at": 1700000000}, "path": ["wallet", "tx", "abc"]})
+    }
+
+    #[test]
+    fn test_typed_and_interpolated() {
+        let out = render_value(&json!({
+            "amount": "{{data.amount_sat}}",
+            "btc": "{{data.amount_sat / 100000000}}",
+            "at": "{{metadata.updated_at}}",
+            "msg": "sent {{data.amount_sat | div 1000}}k to {{data.name | trim | upper}}",
+            "id": "{{path.2}}",
+        }), &ctx());
+        assert_eq!(out["amount"], 150000);
+        assert_eq!(out["btc"], 0.0015);
+        assert_eq!(out["at"], 1700000000);
+        assert_eq!(out["msg"], "sent 150k to ALICE");
+        assert_eq!(out["id"], "abc");
+    }
+
+    #[test]
+    fn test_arithmetic_and_helpers() {
+        assert_eq!(eval("(data.amount_sat + 50000) * 2", &ctx()).unwrap(), json!(400000));
+        assert_eq!(eval("data.missing | default \"none\"", &ctx()).unwrap(), json!("none"));
+        assert_eq!(eval("10 / 3 | round 2", &ctx()).unwrap(), json!(3.33));
+        assert_eq!(eval("\"tx-\" + path.2", &ctx()).unwrap(), json!("tx-abc"));
+        assert!(eval("1 / 0", &ctx()).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! Typed scrolls - compile-time types over `scroll.data`
+//!
+//! Implement [`ScrollType`] (or use [`scroll_type!`](crate::scroll_type)) to get
+//! `node.get_as::<T>(path)` and `node.put_typed(path, &value)` with the
+//! scroll `type_` assigned automatically.
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Note { title: String }
+//! beenode::scroll_type!(Note, "app/note@v1");
+//!
+//! node.put_typed("/notes/1", &Note { title: "hi".into() })?;
+//! let note: Option<Note> = node.get_as("/notes/1")?;
+//! ```
+
+use nine_s_core::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A Rust type stored as scroll data under a fixed `type_`
+pub trait ScrollType: Serialize + DeserializeOwned {
+    /// Scroll type, e.g. `app/note@v1`
+    const TYPE: &'static str;
+
+    /// Build a scroll for `key` with this value as data
+    fn to_scroll(&self, key: &str) -> NineSResult<Scroll> {
+        let data = serde_json::to_value(self).map_err(|e| NineSError::Other(format!("serialize {}: {}", Self::TYPE, e)))?;
+        Ok(Scroll::new(key, data).set_type(Self::TYPE))
+    }
+
+    /// Decode scroll data. Rejects scrolls of a different (non-empty) type.
+    fn from_scroll(scroll: &Scroll) -> NineSResult<Self> {
+        if !scroll.type_.is_empty() && scroll.type_ != Self::TYPE {
+            return Err(NineSError::Other(format!("{}: expected {}, got {}", scroll.key, Self::TYPE, scroll.type_)));
+        }
+        serde_json::from_value(scroll.data.clone())
+            .map_err(|e| NineSError::Other(format!("{}: decode {}: {}", scroll.key, Self::TYPE, e)))
+    }
+}
+
+/// Implement [`ScrollType`] for a serde type: `scroll_type!(Note, "app/note@v1");`
+#[macro_export]
+macro_rules! scroll_type {
+    ($ty:ty, $type_:expr) => {
+        impl $crate::core::typed::ScrollType for $ty {
+            const TYPE: &'static str = $type_;
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note { title: String }
+    crate::scroll_type!(Note, "test/note@v1");
+
+    #[test]
+    fn test_roundtrip_and_type_check() {
+        let scroll = Note { title: "hi".into() }.to_scroll("/notes/1").unwrap();
+        assert_eq!(scroll.type_, "test/note@v1");
+        assert_eq!(Note::from_scroll(&scroll).unwrap(), Note { title: "hi".into() });
+
+        let other = Scroll::new("/notes/2", json!({"title": "x"})).set_type("test/other@v1");
+        assert!(Note::from_scroll(&other).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! Watch: path patterns with named captures and data predicates
+//!
+//! ```text
+//! /external/{service}/{action}/{id}        captures service, action, id
+//! /wallet/**/{txid}                         `**` spans zero or more segments
+//! /wallet/transactions/*[data.amount>10000] only scrolls passing the predicate
+//! ```
+//!
+//! Segments are literal, `*` (any one), `{name}` (any one, captured) or `**`.
+//! Each trailing `[...]` is a BSE predicate (`g/.../` stage) over the same
+//! block `when` sees in patterns: `key`, `type`, `data`, `metadata`. Several
+//! brackets must all pass. A predicate can't contain `/`.
+//!
+//! The store is subscribed with `glob()`, the pattern with captures turned
+//! into `*`; `event` then does the exact match and the filtering, so nothing
+//! reaches the subscriber that the pattern rejects.
+//!
+//! `WatchOptions` tames busy paths: with `debounce_ms` the first notification
+//! opens a window and everything in it is delivered when it closes; with
+//! `coalesce` only the latest scroll per path survives the window. A clock
+//! pulse every second watched with `{debounce_ms: 5000, coalesce: true}`
+//! arrives once every five seconds. `Window` is the shared bookkeeping; the
+//! native node and the WASM node each drive it with their own timer.
+
+use crate::core::bse::{self, Pipeline};
+use crate::core::pattern;
+use anyhow::{anyhow, Result};
+use nine_s_core::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A parsed watch pattern
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub pattern: String,
+    segments: Vec<Segment>,
+    filters: Vec<Pipeline>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Any,
+    Capture(String),
+    Rest,
+}
+
+/// A scroll that matched, with the segments its captures took
+#[derive(Debug, Clone)]
+pub struct WatchEvent {
+    pub scroll: Scroll,
+    pub captures: BTreeMap<String, String>,
+}
+
+/// Per-subscription delivery options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchOptions {
+    /// Hold notifications this long after the first, then deliver them; 0 delivers at once
+    #[serde(default)]
+    pub debounce_ms: u64,
+    /// Keep only the latest scroll per path within a window
+    #[serde(default)]
+    pub coalesce: bool,
+}
+
+impl WatchOptions {
+    pub fn debounce(ms: u64) -> Self { Self { debounce_ms: ms, coalesce: false } }
+    pub fn coalesced(ms: u64) -> Self { Self { debounce_ms: ms, coalesce: true } }
+}
+
+/// Notifications held for the current window
+#[derive(Debug)]
+pub struct Window<T> {
+    options: WatchOptions,
+    opened_at: Option<u64>,
+    held: Vec<(String, T)>,
+}
+
+impl<T> Window<T> {
+    pub fn new(options: WatchOptions) -> Self { Self { options, opened_at: None, held: Vec::new() } }
+
+    /// Hold `item` for `key`; true when it opened the window
+    pub fn push(&mut self, key: &str, item: T, now_ms: u64) -> bool {
+        if self.options.coalesce { self.held.retain(|(k, _)| k != key); }
+        self.held.push((key.into(), item));
+        let opened = self.opened_at.is_none();
+        if opened { self.opened_at = Some(now_ms); }
+        opened
+    }
+
+    /// When the open window closes
+    pub fn deadline(&self) -> Option<u64> { self.opened_at.map(|t| t + self.options.debounce_ms) }
+
+    /// Close the window, oldest first
+    pub fn flush(&mut self) -> Vec<T> {
+        self.opened_at = None;
+        self.held.drain(..).map(|(_, item)| item).collect()
+    }
+}
+
+impl Watch {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let (path, predicates) = match pattern.find('[') {
+            Some(i) => pattern.split_at(i),
+            None => (pattern, ""),
+        };
+        if !path.starts_with('/') { return Err(anyhow!("watch pattern must start with '/': {}", pattern)); }
+
+        let mut segments = Vec::new();
+        for seg in path.split('/').filter(|s| !s.is_empty()) {
+            segments.push(match seg {
+                "*" => Segment::Any,
+                "**" => Segment::Rest,
+                s if s.starts_with('{') && s.ends_with('}') && s.len() > 2 => {
+                    let name = &s[1..s.len() - 1];
+                    if segments.contains(&Segment::Capture(name.into())) {
+                        return Err(anyhow!("capture {{{}}} used twice in {}", name, pattern));
+                    }
+                    Segment::Capture(name.into())
+                }
+                s if s.contains(['{', '}', '*']) => return Err(anyhow!("bad segment '{}' in {}", s, pattern)),
+                s => Segment::Literal(s.into()),
+            });
+        }
+
+        let mut filters = Vec::new();
+        let mut rest = predicates;
+        while !rest.is_empty() {
+            let inner = rest.strip_prefix('[').and_then(|r| r.split_once(']'))
+                .ok_or_else(|| anyhow!("unclosed predicate in {}", pattern))?;
+            if inner.0.contains('/') { return Err(anyhow!("predicate can't contain '/': {}", inner.0)); }
+            filters.push(bse::parse_match(&format!("g/{}/", inner.0.trim()))?);
+            rest = inner.1;
+        }
+        Ok(Self { pattern: pattern.into(), segments, filters })
+    }
+
+    /// The plain pattern to subscribe with: captures as `*`, no predicates
+    pub fn glob(&self) -> String {
+        let segs: Vec<&str> = self.segments.iter().map(|s| match s {
+            Segment::Literal(l) => l.as_str(),
+            Segment::Any | Segment::Capture(_) => "*",
+            Segment::Rest => "**",
+        }).collect();
+        format!("/{}", segs.join("/"))
+    }
+
+    /// Captures for `path`, or `None` when it doesn't match
+    pub fn captures(&self, path: &str) -> Option<BTreeMap<String, String>> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut captures = BTreeMap::new();
+        walk(&self.segments, &parts, &mut captures).then_some(captures)
+    }
+
+    /// `scroll` as an event if its key matches and every predicate passes
+    pub fn event(&self, scroll: Scroll) -> Option<WatchEvent> {
+        let captures = self.captures(&scroll.key)?;
+        if !self.filters.is_empty() {
+            let block = pattern::block(&scroll);
+            if !self.filters.iter().all(|f| bse::matches_block(f, &block)) { return None; }
+        }
+        Some(WatchEvent { scroll, captures })
+    }
+}
+
+fn walk(segments: &[Segment], parts: &[&str], captures: &mut BTreeMap<String, String>) -> bool {
+    match (segments.first(), parts.first()) {
+        (None, None) => true,
+        (Some(Segment::Rest), _) => (0..=parts.len()).any(|skip| {
+            let mut attempt = captures.clone();
+            let ok = walk(&segments[1..], &parts[skip..], &mut attempt);
+            if ok { *captures = attempt; }
+            ok
+        }),
+        (Some(seg), Some(part)) => {
+            match seg {
+                Segment::Literal(l) if l != part => return false,
+                Segment::Capture(name) => { captures.insert(name.clone(), (*part).into()); }
+                _ => {}
+            }
+            walk(&segments[1..], &parts[1..], captures)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_captures() {
+        let watch = Watch::parse("/external/{service}/{action}/{id}").unwrap();
+        assert_eq!(watch.glob(), "/external/*/*/*");
+        let caps = watch.captures("/external/bitcoin/send/42").unwrap();
+        assert_eq!(caps["service"], "bitcoin");
+        assert_eq!(caps["id"], "42");
+        assert!(watch.captures("/external/bitcoin/send").is_none());
+
+        let deep = Watch::parse("/wallet/**/{txid}").unwrap();
+        assert_eq!(deep.captures("/wallet/pending/abc").unwrap()["txid"], "abc");
+        assert_eq!(deep.captures("/wallet/a/b/c").unwrap()["txid"], "c");
+        assert!(Watch::parse("/a/{x}/{x}").is_err());
+        assert!(Watch::parse("/a/b{x}").is_err());
+    }
+
+    #[test]
+    fn test_window_coalesce() {
+        let mut window = Window::new(WatchOptions::coalesced(1000));
+        assert!(window.push("/sys/clock/pulses/beat", 1, 0));
+        assert!(!window.push("/sys/clock/pulses/glow", 2, 10));
+        assert!(!window.push("/sys/clock/pulses/beat", 3, 20));
+        assert_eq!(window.deadline(), Some(1000));
+        assert_eq!(window.flush(), vec![2, 3]);
+        assert_eq!(window.deadline(), None);
+
+        let mut all = Window::new(WatchOptions::debounce(1000));
+        all.push("/a", 1, 0);
+        all.push("/a", 2, 5);
+        assert_eq!(all.flush(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let watch = Watch::parse("/wallet/transactions/{txid}[data.amount>10000]").unwrap();
+        assert_eq!(watch.glob(), "/wallet/transactions/*");
+        let big = watch.event(Scroll::new("/wallet/transactions/t1", json!({"amount": 50000}))).unwrap();
+        assert_eq!(big.captures["txid"], "t1");
+        assert!(watch.event(Scroll::new("/wallet/transactions/t2", json!({"amount": 500}))).is_none());
+
+        let both = Watch::parse("/notes/*[data.pinned=true][data.tag=work]").unwrap();
+        assert!(both.event(Scroll::new("/notes/1", json!({"pinned": true, "tag": "work"}))).is_some());
+        assert!(both.event(Scroll::new("/notes/1", json!({"pinned": true, "tag": "home"}))).is_none());
+        assert!(Watch::parse("/notes/*[data.pinned=true").is_err());
+    }
+}
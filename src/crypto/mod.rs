@@ -0,0 +1,123 @@
+//! Crypto - age (X25519) payload encryption
+//!
+//! Derives an age identity from the master seed (registry protocol `age`)
+//! so scrolls can be encrypted end-to-end to another node before replication.
+//!
+//! ## Key Derivation Architecture
+//!
+//! ```text
+//! Master Seed
+//!     │
+//!     └── derive_seed(seed, "age", 0) ──→ first 32 bytes as the X25519 scalar ──→ AGE-SECRET-KEY-1...
+//!         (HMAC-SHA512 keyed "beenode-age-v1"                                   └──→ age1... (recipient)
+//!          over seed ‖ index)
+//! ```
+//!
+//! Peers are addressed by age recipient (`age1...`) or a Mobi registered
+//! under `/crypto/recipients/{mobi}`. Bare 64-hex keys are refused: they are
+//! usually Nostr (secp256k1) pubkeys, which are not X25519 keys.
+
+mod namespace;
+
+pub use namespace::CryptoNamespace;
+
+use crate::identity::derive;
+use age::x25519;
+use bitcoin::bech32::{self, Bech32, Hrp};
+use std::io::Write;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Crypto errors
+#[derive(Error, Debug)]
+pub enum CryptoError {
+    #[error("Key derivation failed: {0}")]
+    DerivationFailed(String),
+
+    #[error("Invalid recipient: {0}")]
+    InvalidRecipient(String),
+
+    #[error("Encryption failed: {0}")]
+    Encrypt(String),
+
+    #[error("Decryption failed: {0}")]
+    Decrypt(String),
+}
+
+/// Node age identity (X25519)
+pub struct AgeIdentity {
+    identity: x25519::Identity,
+}
+
+impl AgeIdentity {
+    /// Derive from the 64-byte master seed via the protocol registry
+    pub fn from_seed(seed: &[u8; 64]) -> Result<Self, CryptoError> {
+        let derived = derive::derive_seed(seed, "age", 0)
+            .map_err(|e| CryptoError::DerivationFailed(e.to_string()))?;
+        let hrp = Hrp::parse("age-secret-key-").expect("valid hrp");
+        let encoded = bech32::encode::<Bech32>(hrp, &derived[..32])
+            .map_err(|e| CryptoError::DerivationFailed(e.to_string()))?;
+        let identity = x25519::Identity::from_str(&encoded.to_uppercase())
+            .map_err(|e| CryptoError::DerivationFailed(e.to_string()))?;
+        Ok(Self { identity })
+    }
+
+    /// Public recipient string (`age1...`)
+    pub fn recipient(&self) -> String {
+        self.identity.to_public().to_string()
+    }
+
+    /// Decrypt an age payload addressed to this identity
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        age::decrypt(&self.identity, ciphertext).map_err(|e| CryptoError::Decrypt(e.to_string()))
+    }
+}
+
+/// Parse an `age1...` recipient
+pub fn parse_recipient(s: &str) -> Result<x25519::Recipient, CryptoError> {
+    let s = s.trim();
+    if s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(CryptoError::InvalidRecipient("bare hex key (a Nostr pubkey is not an age recipient); use age1...".into()));
+    }
+    x25519::Recipient::from_str(s).map_err(|e| CryptoError::InvalidRecipient(e.to_string()))
+}
+
+/// Encrypt a payload to one or more recipients
+pub fn encrypt(recipients: &[x25519::Recipient], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+        .map_err(|e| CryptoError::Encrypt(e.to_string()))?;
+    let mut out = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut out).map_err(|e| CryptoError::Encrypt(e.to_string()))?;
+    writer.write_all(plaintext).map_err(|e| CryptoError::Encrypt(e.to_string()))?;
+    writer.finish().map_err(|e| CryptoError::Encrypt(e.to_string()))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_to_self() {
+        let me = AgeIdentity::from_seed(&[1u8; 64]).unwrap();
+        assert!(me.recipient().starts_with("age1"));
+        let ct = encrypt(&[parse_recipient(&me.recipient()).unwrap()], b"hello").unwrap();
+        assert_eq!(me.decrypt(&ct).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_other_identity_cannot_decrypt() {
+        let alice = AgeIdentity::from_seed(&[1u8; 64]).unwrap();
+        let bob = AgeIdentity::from_seed(&[2u8; 64]).unwrap();
+        let ct = encrypt(&[parse_recipient(&bob.recipient()).unwrap()], b"for bob").unwrap();
+        assert!(alice.decrypt(&ct).is_err());
+        assert_eq!(bob.decrypt(&ct).unwrap(), b"for bob");
+    }
+
+    #[test]
+    fn test_parse_recipient_refuses_hex() {
+        assert!(parse_recipient(&AgeIdentity::from_seed(&[3u8; 64]).unwrap().recipient()).is_ok());
+        assert!(parse_recipient(&"11".repeat(32)).is_err());
+        assert!(parse_recipient("not-a-key").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Crypto Namespace
+//!
+//! ## Paths
+//!
+//! | Path | R/W | Description |
+//! |------|-----|-------------|
+//! | `/crypto/recipient` | R | `{ recipient: "age1..." }` for peers to encrypt to |
+//! | `/crypto/recipients/{mobi}` | R/W | Known peer recipients `{ recipient }` |
+//! | `/crypto/encrypt` | W | `{ to, data? | path? }` → `{ ciphertext }` (base64 age) |
+//! | `/crypto/decrypt` | W | `{ ciphertext }` → `{ data }` |
+//!
+//! `path` is read by the node before the write reaches this namespace, with
+//! the same lock and sealed-prefix checks as `Node::get`, and handed over as
+//! `scroll` (`{key, type, data}`). `{mobi}` is a 12-digit Mobi.
+
+use super::{encrypt, parse_recipient, AgeIdentity};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const RECIPIENTS_PREFIX: &str = "/crypto/recipients";
+
+/// Crypto namespace for scroll-level encryption
+pub struct CryptoNamespace {
+    identity: AgeIdentity,
+    store: Arc<Store>,
+}
+
+impl CryptoNamespace {
+    pub fn new(identity: AgeIdentity, store: Arc<Store>) -> Self {
+        Self { identity, store }
+    }
+
+    fn read_recipient(&self) -> Scroll {
+        Scroll::typed(
+            "/crypto/recipient",
+            json!({ "recipient": self.identity.recipient() }),
+            "crypto/recipient@v1",
+        )
+    }
+
+    /// Resolve `to` (string or array): age1... or a registered Mobi
+    fn resolve(&self, to: &Value) -> NineSResult<Vec<age::x25519::Recipient>> {
+        let names: Vec<&str> = match to {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(items) => items.iter().filter_map(|v| v.as_str()).collect(),
+            _ => return Err(NineSError::Other("no 'to'".into())),
+        };
+        let mut out = Vec::new();
+        for name in names {
+            let key = if name.starts_with("age1") || (name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())) {
+                // 64-hex is refused by parse_recipient rather than looked up as a Mobi
+                name.to_string()
+            } else {
+                self.store.read(&format!("{}/{}", RECIPIENTS_PREFIX, mobi(name)?))?
+                    .and_then(|s| s.data["recipient"].as_str().map(String::from))
+                    .ok_or_else(|| NineSError::Other(format!("unknown recipient: {}", name)))?
+            };
+            out.push(parse_recipient(&key).map_err(|e| NineSError::Other(e.to_string()))?);
+        }
+        Ok(out)
+    }
+
+    fn write_encrypt(&self, data: Value) -> NineSResult<Scroll> {
+        let mut recipients = self.resolve(&data["to"])?;
+        if data["include_self"].as_bool().unwrap_or(true) {
+            recipients.push(parse_recipient(&self.identity.recipient()).map_err(|e| NineSError::Other(e.to_string()))?);
+        }
+        if data.get("path").is_some() {
+            return Err(NineSError::Other("'path' is read by the node; write /crypto/encrypt through Node::put".into()));
+        }
+        let payload = data.get("scroll").or_else(|| data.get("data")).cloned()
+            .ok_or_else(|| NineSError::Other("no 'data' or 'path'".into()))?;
+        let plaintext = serde_json::to_vec(&payload).map_err(|e| NineSError::Other(e.to_string()))?;
+        let ciphertext = encrypt(&recipients, &plaintext).map_err(|e| NineSError::Other(e.to_string()))?;
+        Ok(Scroll::typed(
+            "/crypto/encrypt",
+            json!({ "ciphertext": BASE64.encode(ciphertext), "recipients": recipients.len() }),
+            "crypto/ciphertext@v1",
+        ))
+    }
+
+    fn write_decrypt(&self, data: Value) -> NineSResult<Scroll> {
+        let b64 = data["ciphertext"].as_str().ok_or_else(|| NineSError::Other("no 'ciphertext'".into()))?;
+        let ciphertext = BASE64.decode(b64).map_err(|e| NineSError::Other(format!("base64: {}", e)))?;
+        let plaintext = self.identity.decrypt(&ciphertext).map_err(|e| NineSError::Other(e.to_string()))?;
+        let value: Value = serde_json::from_slice(&plaintext).map_err(|e| NineSError::Other(e.to_string()))?;
+        Ok(Scroll::typed("/crypto/decrypt", json!({ "data": value }), "crypto/plaintext@v1"))
+    }
+
+    fn write_recipient(&self, name: &str, data: Value) -> NineSResult<Scroll> {
+        let mobi = mobi(name)?;
+        let recipient = data["recipient"].as_str().ok_or_else(|| NineSError::Other("no 'recipient'".into()))?;
+        let parsed = parse_recipient(recipient).map_err(|e| NineSError::Other(e.to_string()))?;
+        let key = format!("{}/{}", RECIPIENTS_PREFIX, mobi);
+        self.store.write_scroll(Scroll::typed(&key, json!({ "mobi": mobi, "recipient": parsed.to_string() }), "crypto/recipient@v1"))
+    }
+}
+
+/// The 12 digits of a Mobi, written plain or with `-`/space separators
+fn mobi(name: &str) -> NineSResult<String> {
+    let digits: String = name.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+    if digits.len() == 12 && digits.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(digits)
+    } else {
+        Err(NineSError::Other(format!("unknown recipient: {} (use age1... or a 12-digit Mobi)", name)))
+    }
+}
+
+impl Namespace for CryptoNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        match path {
+            "recipient" | "/recipient" | "" | "/" => Ok(Some(self.read_recipient())),
+            p if p.starts_with("/recipients/") => self.store.read(&format!("/crypto{}", p)),
+            _ => Ok(None),
+        }
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        match path {
+            "encrypt" | "/encrypt" => self.write_encrypt(data),
+            "decrypt" | "/decrypt" => self.write_decrypt(data),
+            p if p.starts_with("/recipients/") => self.write_recipient(&p["/recipients/".len()..], data),
+            _ => Err(NineSError::invalid_path(path, "unknown crypto path")),
+        }
+    }
+
+    fn list(&self, _prefix: &str) -> NineSResult<Vec<String>> {
+        let mut paths = vec![
+            "/crypto/recipient".to_string(),
+            "/crypto/encrypt".to_string(),
+            "/crypto/decrypt".to_string(),
+        ];
+        paths.extend(self.store.list(RECIPIENTS_PREFIX)?);
+        Ok(paths)
+    }
+
+    fn close(&self) -> NineSResult<()> {
+        Ok(())
+    }
+}
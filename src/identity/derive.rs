@@ -0,0 +1,66 @@
+//! Derive - Protocol seed registry
+//!
+//! Deterministic per-protocol seeds from the 64-byte master seed.
+//! Each `(protocol, index)` gets an HMAC-SHA512 domain `beenode-{protocol}-v1`,
+//! so compromise of one protocol's key says nothing about another's.
+
+use hmac::{Hmac, Mac};
+use nine_s_core::errors::{NineSError, NineSResult};
+use sha2::{Digest, Sha256, Sha512};
+
+/// Known protocols and what they are used for
+pub const PROTOCOLS: &[(&str, &str)] = &[
+    ("nostr", "Nostr identity (secp256k1)"),
+    ("wireguard", "WireGuard tunnel keys (X25519)"),
+    ("ssh", "SSH login keys (ed25519)"),
+    ("age", "age encryption identity (X25519)"),
+    ("archive", "/archive object encryption (ChaCha20-Poly1305)"),
+    ("app", "Application-specific secrets"),
+];
+
+/// Protocol names are lowercase ascii, digits and dashes (custom names allowed)
+pub fn validate_protocol(protocol: &str) -> NineSResult<()> {
+    let ok = !protocol.is_empty()
+        && protocol.len() <= 32
+        && protocol.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+    if ok { Ok(()) } else { Err(NineSError::Other(format!("invalid protocol: {}", protocol))) }
+}
+
+/// Derive a 64-byte protocol seed. Index 0 is the protocol's default key.
+pub fn derive_seed(master: &[u8; 64], protocol: &str, index: u32) -> NineSResult<[u8; 64]> {
+    validate_protocol(protocol)?;
+    let mut hmac = Hmac::<Sha512>::new_from_slice(format!("beenode-{}-v1", protocol).as_bytes())
+        .expect("HMAC accepts any key length");
+    hmac.update(master);
+    hmac.update(&index.to_be_bytes());
+    let mut seed = [0u8; 64];
+    seed.copy_from_slice(&hmac.finalize().into_bytes());
+    Ok(seed)
+}
+
+/// Public fingerprint of a derived seed (first 8 bytes of SHA256, hex)
+pub fn fingerprint(seed: &[u8; 64]) -> String {
+    hex::encode(&Sha256::digest(seed)[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_deterministic_and_separated() {
+        let master = [7u8; 64];
+        let a = derive_seed(&master, "ssh", 0).unwrap();
+        assert_eq!(a, derive_seed(&master, "ssh", 0).unwrap());
+        assert_ne!(a, derive_seed(&master, "ssh", 1).unwrap());
+        assert_ne!(a, derive_seed(&master, "age", 0).unwrap());
+    }
+
+    #[test]
+    fn test_validate_protocol() {
+        assert!(validate_protocol("my-app2").is_ok());
+        assert!(validate_protocol("").is_err());
+        assert!(validate_protocol("SSH").is_err());
+        assert!(validate_protocol("a/b").is_err());
+    }
+}
@@ -1,8 +1,13 @@
 //! Identity - Derives keys from seed. Master mnemonic NEVER leaves layer 0.
 
 mod bip85;
+pub mod derive;
+pub mod provenance;
+#[cfg(feature = "shamir")]
+pub mod shamir;
 
 use crate::mobi::Mobi;
+use crate::secret::Secret;
 use crate::wireguard::{self, WireGuardKeypair};
 use nine_s_core::errors::{NineSError, NineSResult};
 
@@ -15,6 +20,8 @@ pub struct Identity {
     pub mobi: Mobi,
     pub pubkey_hex: String,
     pub wireguard: WireGuardKeypair,
+    /// Secret half of `pubkey_hex`, for `sign`
+    signing_key: Secret<[u8; 32]>,
 }
 
 impl Identity {
@@ -29,6 +36,7 @@ impl Identity {
         let wireguard = wireguard_from_seed(seed)?;
 
         Ok(Self {
+            signing_key: Secret::new(keys.secret_key().secret_bytes()),
             nostr_keys: keys,
             mobi: Mobi::derive(&pubkey_hex)?,
             pubkey_hex,
@@ -51,16 +59,18 @@ impl Identity {
             mobi: Mobi::derive(&pubkey_hex)?,
             pubkey_hex,
             wireguard,
+            signing_key: Secret::new(sk.secret_bytes()),
         })
     }
 
     #[cfg(feature = "nostr")]
     pub fn from_mnemonic(mnemonic_str: &str) -> NineSResult<Self> {
-        let nostr_mnemonic = derive_nostr_mnemonic(mnemonic_str, None)
+        let nostr_mnemonic = Secret::new(derive_nostr_mnemonic(mnemonic_str, None)
+            .map_err(|e| NineSError::Other(e.to_string()))?);
+        let m = bip39::Mnemonic::parse(nostr_mnemonic.expose())
             .map_err(|e| NineSError::Other(e.to_string()))?;
-        let m = bip39::Mnemonic::parse(&nostr_mnemonic)
-            .map_err(|e| NineSError::Other(e.to_string()))?;
-        let sk = nostr::SecretKey::from_slice(&m.to_seed("")[..32])
+        let seed = Secret::new(m.to_seed(""));
+        let sk = nostr::SecretKey::from_slice(&seed.expose()[..32])
             .map_err(|e| NineSError::Other(e.to_string()))?;
         let keys = nostr::Keys::new(sk);
         let pubkey_hex = keys.public_key().to_hex();
@@ -70,6 +80,7 @@ impl Identity {
             .map_err(|e| NineSError::Other(e.to_string()))?;
 
         Ok(Self {
+            signing_key: Secret::new(keys.secret_key().secret_bytes()),
             nostr_keys: keys,
             mobi: Mobi::derive(&pubkey_hex)?,
             pubkey_hex,
@@ -80,12 +91,13 @@ impl Identity {
     #[cfg(not(feature = "nostr"))]
     pub fn from_mnemonic(mnemonic_str: &str) -> NineSResult<Self> {
         use bitcoin::secp256k1::{Secp256k1, SecretKey};
-        let nostr_mnemonic = derive_nostr_mnemonic(mnemonic_str, None)
-            .map_err(|e| NineSError::Other(e.to_string()))?;
-        let m = bip39::Mnemonic::parse(&nostr_mnemonic)
+        let nostr_mnemonic = Secret::new(derive_nostr_mnemonic(mnemonic_str, None)
+            .map_err(|e| NineSError::Other(e.to_string()))?);
+        let m = bip39::Mnemonic::parse(nostr_mnemonic.expose())
             .map_err(|e| NineSError::Other(e.to_string()))?;
+        let seed = Secret::new(m.to_seed(""));
         let secp = Secp256k1::new();
-        let sk = SecretKey::from_slice(&m.to_seed("")[..32])
+        let sk = SecretKey::from_slice(&seed.expose()[..32])
             .map_err(|e| NineSError::Other(e.to_string()))?;
         let pubkey_hex = hex::encode(&sk.public_key(&secp).x_only_public_key().0.serialize());
 
@@ -97,8 +109,23 @@ impl Identity {
             mobi: Mobi::derive(&pubkey_hex)?,
             pubkey_hex,
             wireguard,
+            signing_key: Secret::new(sk.secret_bytes()),
         })
     }
+
+    /// Key peer tokens are signed with (`peers`)
+    pub fn token_key(&self) -> [u8; 32] {
+        blake3::derive_key("beenode peer tokens v1", self.signing_key.expose())
+    }
+
+    /// BIP340 signature (hex) over a 32-byte digest, verifiable against `pubkey_hex`
+    pub fn sign(&self, digest: [u8; 32]) -> NineSResult<String> {
+        use bitcoin::secp256k1::{Keypair, Message, Secp256k1};
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, self.signing_key.expose())
+            .map_err(|e| NineSError::Other(e.to_string()))?;
+        Ok(hex::encode(secp.sign_schnorr_no_aux_rand(&Message::from_digest(digest), &keypair).serialize()))
+    }
 }
 
 /// Derive WireGuard keys from a 64-byte seed using HMAC-SHA512
@@ -0,0 +1,96 @@
+//! Provenance - Producer signatures on scrolls
+//!
+//! A signed scroll carries `metadata.produced_by = "nostr:{pubkey}:{sig}"`:
+//! the writer's x-only Nostr pubkey and a BIP340 signature over `hash`, the
+//! sha256 of `{key, type, data}` as compact JSON with sorted keys. Version and
+//! timestamps stay out of the hash since the store assigns them, so a scroll
+//! replicated to another node still verifies there.
+//!
+//! Sealed paths are signed over the plaintext; verify after opening.
+
+use crate::identity::Identity;
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub const PREFIX: &str = "nostr:";
+
+/// The digest a producer signs
+pub fn hash(scroll: &Scroll) -> [u8; 32] {
+    let canonical = sorted(&json!({"key": scroll.key, "type": scroll.type_, "data": scroll.data}));
+    Sha256::digest(canonical.to_string().as_bytes()).into()
+}
+
+/// `value` with every object's keys in sorted order, so the hash doesn't
+/// depend on how serde_json orders maps (`preserve_order` keeps insertion order)
+fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(keys.into_iter().map(|k| (k.clone(), sorted(&map[k]))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+        other => other.clone(),
+    }
+}
+
+/// `scroll` with `produced_by` set to `identity`'s signature over it
+pub fn sign(identity: &Identity, mut scroll: Scroll) -> NineSResult<Scroll> {
+    let sig = identity.sign(hash(&scroll))?;
+    scroll.metadata.produced_by = Some(format!("{}{}:{}", PREFIX, identity.pubkey_hex, sig));
+    Ok(scroll)
+}
+
+/// `(pubkey, sig)` from a signed `produced_by`, without checking it
+pub fn producer(scroll: &Scroll) -> Option<(&str, &str)> {
+    scroll.metadata.produced_by.as_deref()?.strip_prefix(PREFIX)?.split_once(':')
+}
+
+/// The signer's pubkey if the signature holds, `None` for an unsigned scroll,
+/// an error if it's signed and doesn't match (edited data, wrong key, garbage)
+pub fn verify(scroll: &Scroll) -> NineSResult<Option<String>> {
+    use bitcoin::secp256k1::{schnorr::Signature, Message, Secp256k1, XOnlyPublicKey};
+    let Some((pubkey, sig)) = producer(scroll) else { return Ok(None) };
+    let bad = |what: &str| NineSError::Other(format!("{}: bad producer {}", scroll.key, what));
+    let key = hex::decode(pubkey).ok().and_then(|b| XOnlyPublicKey::from_slice(&b).ok()).ok_or_else(|| bad("pubkey"))?;
+    let sig = hex::decode(sig).ok().and_then(|b| Signature::from_slice(&b).ok()).ok_or_else(|| bad("signature"))?;
+    Secp256k1::verification_only()
+        .verify_schnorr(&sig, &Message::from_digest(hash(scroll)), &key)
+        .map_err(|_| bad("signature"))?;
+    Ok(Some(pubkey.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = Identity::from_mnemonic(TEST_MNEMONIC).expect("identity");
+        let signed = sign(&identity, Scroll::new("/notes/1", json!({"text": "hi"}))).expect("sign");
+        assert_eq!(verify(&signed).expect("verify"), Some(identity.pubkey_hex.clone()));
+        assert_eq!(verify(&Scroll::new("/notes/1", json!({"text": "hi"}))).expect("unsigned"), None);
+
+        let mut edited = signed.clone();
+        edited.data["text"] = json!("bye");
+        assert!(verify(&edited).is_err());
+        let mut moved = signed;
+        moved.key = "/notes/2".into();
+        assert!(verify(&moved).is_err());
+    }
+
+    #[test]
+    fn test_hash_ignores_key_order() {
+        let mut ab = serde_json::Map::new();
+        ab.insert("a".into(), json!(1));
+        ab.insert("b".into(), json!({"y": 2, "x": [{"q": 1, "p": 0}]}));
+        let mut ba = serde_json::Map::new();
+        ba.insert("b".into(), json!({"x": [{"p": 0, "q": 1}], "y": 2}));
+        ba.insert("a".into(), json!(1));
+        assert_eq!(hash(&Scroll::new("/n", Value::Object(ab))), hash(&Scroll::new("/n", Value::Object(ba))));
+    }
+}
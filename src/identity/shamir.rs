@@ -0,0 +1,61 @@
+//! Shamir - SLIP-39 backup shares of the master mnemonic
+//!
+//! The BIP39 entropy (16 or 32 bytes) is the SLIP-39 master secret, split
+//! into one group of `count` member shares of which any `threshold` recover
+//! it. Combining gives the entropy back, so the restored BIP39 words are the
+//! original ones. No passphrase: the node's seeds are derived with none.
+
+use nine_s_core::errors::{NineSError, NineSResult};
+
+/// SLIP-39 limit on shares in a group
+pub const MAX_SHARES: u8 = 16;
+/// PBKDF2 rounds = 10000 × 2^e (the reference implementation's default)
+const ITERATION_EXPONENT: u8 = 1;
+
+/// Split `mnemonic` into `count` SLIP-39 mnemonics, any `threshold` of which restore it
+pub fn split(mnemonic: &str, threshold: u8, count: u8) -> NineSResult<Vec<String>> {
+    if threshold == 0 || threshold > count || count > MAX_SHARES {
+        return Err(NineSError::Other(format!("need 1 <= threshold <= shares <= {}", MAX_SHARES)));
+    }
+    if threshold == 1 && count > 1 {
+        return Err(NineSError::Other("threshold 1 with several shares is just copies; use threshold >= 2".into()));
+    }
+    let entropy = bip39::Mnemonic::parse(mnemonic)
+        .map_err(|e| NineSError::Other(format!("Invalid mnemonic: {}", e)))?
+        .to_entropy();
+    let groups = sssmc39::generate_mnemonics(1, &[(threshold, count)], &entropy, "", ITERATION_EXPONENT)
+        .map_err(|e| NineSError::Other(format!("slip39: {}", e)))?;
+    let group = groups.first().ok_or_else(|| NineSError::Other("slip39: no group".into()))?;
+    Ok(group.mnemonic_list()
+        .map_err(|e| NineSError::Other(format!("slip39: {}", e)))?
+        .into_iter()
+        .map(|words| words.join(" "))
+        .collect())
+}
+
+/// Recover the BIP39 mnemonic from enough SLIP-39 shares
+pub fn combine(shares: &[String]) -> NineSResult<String> {
+    let shares: Vec<Vec<String>> = shares.iter()
+        .map(|s| s.split_whitespace().map(|w| w.to_lowercase()).collect())
+        .collect();
+    let entropy = sssmc39::combine_mnemonics(&shares, "")
+        .map_err(|e| NineSError::Other(format!("slip39: {}", e)))?;
+    let mnemonic = bip39::Mnemonic::from_entropy(&entropy)
+        .map_err(|e| NineSError::Other(format!("Invalid mnemonic: {}", e)))?;
+    Ok(mnemonic.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_combine() {
+        let mnemonic = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let shares = split(mnemonic, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert_eq!(combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap(), mnemonic);
+        assert!(combine(&shares[..2]).is_err());
+        assert!(split(mnemonic, 4, 3).is_err());
+    }
+}
@@ -33,6 +33,7 @@
 //! - `wasm` - WASM platform (browser, IndexedDB, fetch)
 //! - `wallet` - Bitcoin wallet (BDK 2.x, bdk_file_store, Electrum)
 //! - `nostr` - Nostr protocol (relay client, event signing)
+//! - `client` - HTTP client for a remote node (`client::Client`), no native stack
 //!
 //! # Usage
 //!
@@ -58,12 +59,20 @@
 // =============================================================================
 pub mod core;
 pub mod mobi;
+#[cfg(feature = "client")]
+pub mod client;
 
 // Identity requires bitcoin/bip39 (native only)
 #[cfg(feature = "native")]
 pub mod identity;
 #[cfg(feature = "native")]
 pub mod wireguard;
+#[cfg(feature = "native")]
+pub mod ssh;
+#[cfg(feature = "native")]
+pub mod crypto;
+#[cfg(feature = "native")]
+pub mod secret;
 
 // =============================================================================
 // Native-only modules (server, CLI, filesystem, tokio)
@@ -75,19 +84,33 @@ pub mod clock;
 #[cfg(feature = "native")]
 pub mod logging;
 #[cfg(feature = "native")]
+pub mod market;
+#[cfg(feature = "native")]
 pub mod mind;
 #[cfg(feature = "native")]
 pub mod namespaces;
 #[cfg(feature = "native")]
 pub mod node;
 #[cfg(feature = "native")]
+pub mod peers;
+#[cfg(feature = "native")]
 pub mod runtime;
 #[cfg(feature = "native")]
 pub mod server;
+#[cfg(feature = "native")]
+pub mod store;
 #[cfg(feature = "wallet")]
 pub mod wallet;
 #[cfg(feature = "nostr")]
 pub mod nostr;
+#[cfg(feature = "s3")]
+pub mod archive;
+#[cfg(feature = "search")]
+pub mod search;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+#[cfg(feature = "native")]
+pub mod testing;
 
 // =============================================================================
 // WASM-only modules (browser, IndexedDB, wasm-bindgen)
@@ -100,26 +123,36 @@ pub mod wasm;
 // =============================================================================
 pub use mobi::Mobi;
 pub use core::pattern::{Pattern, PatternDef};
+pub use core::api::NodeApi;
+pub use core::proxy::Proxy;
+pub use core::typed::ScrollType;
+pub use core::watch::{WatchEvent, WatchOptions};
 pub use nine_s_core::prelude::*;
 
 #[cfg(feature = "native")]
 pub use identity::Identity;
 #[cfg(feature = "native")]
 pub use wireguard::{WireGuardConfig, WireGuardKeypair, WireGuardNamespace};
+#[cfg(feature = "native")]
+pub use ssh::{SshKeypair, SshNamespace};
+#[cfg(feature = "native")]
+pub use crypto::{AgeIdentity, CryptoNamespace};
+#[cfg(feature = "native")]
+pub use secret::Secret;
 
 // =============================================================================
 // Re-exports: Native
 // =============================================================================
 #[cfg(feature = "native")]
-pub use node::{AuthMode, Node, NodeConfig};
+pub use node::{AuthMode, FollowConfig, HttpConfig, Migration, Node, NodeConfig, NodeRuntime, Rotation, ServicesConfig};
 #[cfg(feature = "native")]
 pub use clock::{ClockConfig, ClockService, UiClock, start_clock, start_clock_with_config};
 #[cfg(feature = "native")]
-pub use mind::{EffectHandler, EffectWorker, Mind, MindConfig};
+pub use mind::{EffectConfig, EffectHandler, EffectWorker, Mind, MindConfig, Priority};
 #[cfg(feature = "native")]
-pub use runtime::{Shutdown, install_signal_handlers};
+pub use runtime::{Pidfile, Shutdown, install_panic_hook, install_reload_handler, install_signal_handlers, sd_notify};
 #[cfg(feature = "native")]
-pub use server::{create_router, create_router_with_name};
+pub use server::{create_router, create_router_with_name, NodeManager};
 #[cfg(feature = "native")]
 pub use nine_s_shell::Shell;
 #[cfg(feature = "native")]
@@ -132,9 +165,13 @@ pub use node::NostrConfig;
 #[cfg(feature = "wallet")]
 pub use node::WalletConfig;
 #[cfg(feature = "wallet")]
-pub use wallet::{BitcoinEffectHandler, Network, WalletNamespace};
+pub use wallet::{AddressReuse, BitcoinEffectHandler, CoinSelection, Network, WalletNamespace};
 #[cfg(feature = "nostr")]
 pub use nostr::{NostrEffectHandler, RelayPool};
+#[cfg(feature = "market")]
+pub use market::{PriceEffectHandler, Provider};
+#[cfg(feature = "s3")]
+pub use archive::{ArchiveConfig, ArchiveEffectHandler, RemoteObjectNamespace};
 
 // =============================================================================
 // Re-exports: WASM
@@ -1,22 +1,206 @@
-use tracing_subscriber::{fmt, EnvFilter};
+//! Logging - stderr subscriber, scroll mirror and runtime level control
+//!
+//! WARN/ERROR events are mirrored into `/sys/logs/{date}/{seq}` once a store
+//! is attached with [`attach`], so remote nodes ship logs over the normal
+//! scroll transport. Writes to `/sys/logging/level` (`{"filter": "debug"}`)
+//! swap the filter without a restart.
+//!
+//! The subscriber is process-wide, so one node per process owns the mirror:
+//! events carry no node, and a second node's `/sys/logs` would otherwise
+//! fill with the first one's warnings. Other nodes log to stderr only.
+
+use crate::core::paths::logging as paths;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Map, Value};
+use std::cell::Cell;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+/// Mirrored events per minute; the rest are counted and reported as `dropped`
+pub const MAX_PER_MINUTE: u32 = 60;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static MIRROR: OnceLock<Mutex<Mirror>> = OnceLock::new();
+
+thread_local! {
+    // Store writes may log; never mirror an event raised while mirroring
+    static MIRRORING: Cell<bool> = const { Cell::new(false) };
+}
 
 pub fn init_logging() {
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(env_filter);
     let use_json = std::env::var("BEENODE_LOG_JSON")
         .map(|value| value == "1")
         .unwrap_or(false);
 
-    if use_json {
-        let _ = fmt::Subscriber::builder()
-            .with_env_filter(env_filter)
-            .json()
-            .with_writer(std::io::stderr)
-            .try_init();
+    let registry = tracing_subscriber::registry().with(filter).with(ScrollLayer);
+    let initialized = if use_json {
+        registry.with(fmt::layer().json().with_writer(std::io::stderr)).try_init()
     } else {
-        let _ = fmt::Subscriber::builder()
-            .with_env_filter(env_filter)
-            .pretty()
-            .with_writer(std::io::stderr)
-            .try_init();
+        registry.with(fmt::layer().pretty().with_writer(std::io::stderr)).try_init()
+    };
+    if initialized.is_ok() {
+        let _ = FILTER.set(handle);
+    }
+}
+
+/// Replace the active filter (`EnvFilter` directives, e.g. `beenode=debug,info`)
+pub fn set_level(directives: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directives).map_err(|e| format!("invalid filter: {}", e))?;
+    let handle = FILTER.get().ok_or("logging not initialized")?;
+    handle.reload(filter).map_err(|e| e.to_string())
+}
+
+/// Mirror WARN/ERROR into `store` and follow `/sys/logging/level` writes
+///
+/// Once per process: the first `owner` (a node's root and app) keeps the
+/// mirror. Attaching it again is a no-op; attaching another owner is an error.
+pub fn attach(store: Store, owner: &str) -> NineSResult<()> {
+    let taken = |mirror: &Mutex<Mirror>| mirror.lock().map(|m| m.owner.clone()).unwrap_or_default();
+    if let Some(mirror) = MIRROR.get() { return check_owner(&taken(mirror), owner); }
+    let rx = store.watch(&WatchPattern::parse(paths::LEVEL)?)?;
+    let mirror = Mirror { store: Some(Arc::new(store)), owner: owner.to_string(), ..Mirror::default() };
+    if MIRROR.set(Mutex::new(mirror)).is_err() {
+        return check_owner(&taken(MIRROR.get().expect("set")), owner);
+    }
+    std::thread::spawn(move || {
+        while let Ok(scroll) = rx.recv() {
+            let Some(filter) = scroll.data["filter"].as_str().or(scroll.data.as_str()) else { continue };
+            match set_level(filter) {
+                Ok(()) => tracing::info!("log filter set to {}", filter),
+                Err(e) => tracing::warn!("log filter {}: {}", filter, e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn check_owner(current: &str, owner: &str) -> NineSResult<()> {
+    if current == owner { return Ok(()); }
+    Err(NineSError::Other(format!("logs already mirror into {}: one node per process owns /sys/logs", current)))
+}
+
+#[derive(Default)]
+struct Mirror {
+    store: Option<Arc<Store>>,
+    owner: String,
+    date: String,
+    seq: u64,
+    window: u64,
+    count: u32,
+    dropped: u64,
+}
+
+impl Mirror {
+    /// Rate-limit check; returns the `dropped` count to report when allowed
+    fn admit(&mut self, now: u64) -> Option<u64> {
+        if now / 60 != self.window {
+            self.window = now / 60;
+            self.count = 0;
+        }
+        if self.count >= MAX_PER_MINUTE {
+            self.dropped += 1;
+            return None;
+        }
+        self.count += 1;
+        Some(std::mem::take(&mut self.dropped))
+    }
+
+    fn next_key(&mut self, store: &Store, date: String) -> String {
+        if date != self.date {
+            // Resume numbering after a restart instead of overwriting
+            let prefix = format!("{}/{}", paths::PREFIX, date);
+            self.seq = store.list(&prefix).map(|keys| keys.len() as u64).unwrap_or(0);
+            self.date = date;
+        }
+        self.seq += 1;
+        format!("{}/{}/{}", paths::PREFIX, self.date, self.seq)
+    }
+}
+
+struct ScrollLayer;
+
+impl<S: Subscriber> Layer<S> for ScrollLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+        if level != Level::WARN && level != Level::ERROR { return; }
+        if MIRRORING.with(|m| m.get()) { return; }
+        MIRRORING.with(|m| m.set(true));
+        record(event, level);
+        MIRRORING.with(|m| m.set(false));
+    }
+}
+
+/// Write one event; runs with `MIRRORING` set, so logging from the store
+/// (including `store.list` under the `MIRROR` lock) never re-enters
+fn record(event: &Event<'_>, level: Level) {
+    let Some(mirror) = MIRROR.get() else { return };
+    let Ok(mut mirror) = mirror.lock() else { return };
+    let Some(store) = mirror.store.clone() else { return };
+
+    let now = chrono::Utc::now();
+    let Some(dropped) = mirror.admit(now.timestamp() as u64) else { return };
+    let key = mirror.next_key(&store, now.format("%Y-%m-%d").to_string());
+    drop(mirror);
+
+    let mut fields = FieldVisitor::default();
+    event.record(&mut fields);
+    let message = fields.0.remove("message").unwrap_or(Value::Null);
+    let mut data = json!({
+        "level": level.as_str(),
+        "target": event.metadata().target(),
+        "message": message,
+        "fields": fields.0,
+        "timestamp": now.timestamp_millis(),
+    });
+    if dropped > 0 { data["dropped"] = json!(dropped); }
+
+    let _ = store.write_scroll(Scroll::new(&key, data).set_type(paths::TYPE));
+}
+
+#[derive(Default)]
+struct FieldVisitor(Map<String, Value>);
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) { self.0.insert(field.name().into(), json!(value)); }
+    fn record_i64(&mut self, field: &Field, value: i64) { self.0.insert(field.name().into(), json!(value)); }
+    fn record_u64(&mut self, field: &Field, value: u64) { self.0.insert(field.name().into(), json!(value)); }
+    fn record_bool(&mut self, field: &Field, value: bool) { self.0.insert(field.name().into(), json!(value)); }
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().into(), json!(format!("{:?}", value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_reports_dropped() {
+        let mut m = Mirror::default();
+        for _ in 0..MAX_PER_MINUTE { assert_eq!(m.admit(120), Some(0)); }
+        assert_eq!(m.admit(121), None);
+        assert_eq!(m.admit(122), None);
+        assert_eq!(m.admit(180), Some(2));
+    }
+
+    #[test]
+    fn test_attach_refuses_second_owner() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let open = |dir: &tempfile::TempDir| {
+            crate::node::NodeConfig::new("test-logging").with_root(dir.path()).open_store().unwrap()
+        };
+        let owner = first.path().display().to_string();
+        // Another test's node may own the mirror already; either way it stays put
+        let attached = attach(open(&first), &owner).is_ok();
+        assert_eq!(attach(open(&first), &owner).is_ok(), attached);
+        assert!(attach(open(&second), &second.path().display().to_string()).is_err());
     }
 }
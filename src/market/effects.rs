@@ -0,0 +1,137 @@
+//! PriceEffectHandler - fetch exchange rates for /external/market/price/**
+
+use async_trait::async_trait;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::paths::market as paths;
+use crate::core::proxy::Proxy;
+use crate::mind::EffectHandler;
+
+/// Exchange-rate source, tried in configured order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider { Mempool, Kraken, CoinGecko }
+
+impl Provider {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "mempool" | "mempool.space" => Some(Self::Mempool),
+            "kraken" => Some(Self::Kraken),
+            "coingecko" => Some(Self::CoinGecko),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self { Self::Mempool => "mempool.space", Self::Kraken => "kraken", Self::CoinGecko => "coingecko" }
+    }
+
+    /// Request URL for a `btc-{fiat}` pair
+    fn url(&self, fiat: &str) -> String {
+        match self {
+            Self::Mempool => "https://mempool.space/api/v1/prices".into(),
+            Self::Kraken => format!("https://api.kraken.com/0/public/Ticker?pair=XBT{}", fiat.to_uppercase()),
+            Self::CoinGecko => format!("https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies={}", fiat),
+        }
+    }
+
+    /// Extract the price from a provider response
+    fn extract(&self, body: &Value, fiat: &str) -> Option<f64> {
+        match self {
+            Self::Mempool => body[fiat.to_uppercase()].as_f64(),
+            // {"result": {"XXBTZUSD": {"c": ["61234.5", "0.01"]}}}
+            Self::Kraken => body["result"].as_object()?.values().next()?["c"][0].as_str()?.parse().ok(),
+            Self::CoinGecko => body["bitcoin"][fiat].as_f64(),
+        }
+    }
+}
+
+pub struct PriceEffectHandler {
+    store: Arc<Store>,
+    providers: Vec<Provider>,
+    client: reqwest::Client,
+}
+
+impl PriceEffectHandler {
+    pub fn new(store: Arc<Store>) -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+        Self { store, providers: vec![Provider::Mempool, Provider::Kraken, Provider::CoinGecko], client }
+    }
+
+    pub fn with_providers(mut self, providers: Vec<Provider>) -> Self { self.providers = providers; self }
+
+    /// Fetch through a SOCKS5 proxy (`NodeConfig::proxy`). If the proxied
+    /// client can't be built nothing is fetched, rather than going direct.
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self {
+        let Some(proxy) = proxy else { return self };
+        let client = reqwest::Proxy::all(proxy.url())
+            .and_then(|p| reqwest::Client::builder().timeout(Duration::from_secs(10)).proxy(p).build());
+        match client {
+            Ok(client) => self.client = client,
+            Err(e) => {
+                tracing::warn!("price proxy {}: {}", proxy.addr(), e);
+                self.providers.clear();
+            }
+        }
+        self
+    }
+
+    async fn fetch(&self, pair: &str) -> anyhow::Result<(f64, Provider)> {
+        let fiat = pair.strip_prefix("btc-").ok_or_else(|| anyhow::anyhow!("unsupported pair: {}", pair))?;
+        let mut last = anyhow::anyhow!("no providers configured");
+        for provider in &self.providers {
+            let attempt = async {
+                let body: Value = self.client.get(provider.url(fiat)).send().await?.error_for_status()?.json().await?;
+                provider.extract(&body, fiat).ok_or_else(|| anyhow::anyhow!("{}: no {} price", provider.name(), fiat))
+            };
+            match attempt.await {
+                Ok(price) => return Ok((price, *provider)),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+}
+
+#[async_trait]
+impl EffectHandler for PriceEffectHandler {
+    fn watches(&self) -> &str { paths::EXTERNAL_PRICE }
+
+    async fn execute(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let pairs: Vec<String> = match scroll.data["pairs"].as_array() {
+            Some(p) => p.iter().filter_map(|v| v.as_str().map(str::to_lowercase)).collect(),
+            None => paths::DEFAULT_PAIRS.iter().map(|p| p.to_string()).collect(),
+        };
+        let mut prices = serde_json::Map::new();
+        let mut errors = serde_json::Map::new();
+        for pair in pairs {
+            match self.fetch(&pair).await {
+                Ok((price, provider)) => {
+                    super::record_price(&self.store, &pair, price, provider.name()).map_err(|e| anyhow::anyhow!("{}", e))?;
+                    prices.insert(pair, json!({"price": price, "provider": provider.name()}));
+                }
+                Err(e) => { errors.insert(pair, json!(e.to_string())); }
+            }
+        }
+        if prices.is_empty() && !errors.is_empty() {
+            anyhow::bail!("no prices fetched: {}", Value::Object(errors));
+        }
+        Ok(json!({"prices": prices, "errors": errors}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(Provider::Mempool.extract(&json!({"USD": 61000, "EUR": 56000}), "eur"), Some(56000.0));
+        assert_eq!(Provider::Kraken.extract(&json!({"error": [], "result": {"XXBTZUSD": {"c": ["61234.5", "0.1"]}}}), "usd"), Some(61234.5));
+        assert_eq!(Provider::CoinGecko.extract(&json!({"bitcoin": {"usd": 61000.5}}), "usd"), Some(61000.5));
+        assert_eq!(Provider::parse("Mempool.space"), Some(Provider::Mempool));
+    }
+}
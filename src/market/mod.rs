@@ -0,0 +1,134 @@
+//! Market - exchange rates and fiat conversion
+//!
+//! Prices live at `/market/price/{pair}` (e.g. `btc-usd`) as
+//! `{pair, price, provider, fetched_at}`. Each one recorded is also kept as
+//! that day's price at `/market/history/{pair}/{YYYY-MM-DD}`, which
+//! `price_at` reads for past conversions (wallet exports); older days can be
+//! written there by hand. With the `market` feature,
+//! `PriceEffectHandler` refreshes them from mempool.space, Kraken or
+//! CoinGecko when `/external/market/price/{id}` is written; `refresh_pattern`
+//! wires that to a clock pulse. `Node::start_services` registers both (on
+//! `refresh`, else `hour`) when effects are on.
+//!
+//! The conversion helpers only read stored prices and need no network.
+
+#[cfg(feature = "market")]
+mod effects;
+
+#[cfg(feature = "market")]
+pub use effects::{PriceEffectHandler, Provider};
+
+use crate::core::paths::{market as paths, origin};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Map, Value};
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Latest stored price for `pair` (fiat per BTC)
+pub fn price(store: &Store, pair: &str) -> NineSResult<Option<f64>> {
+    Ok(store.read(&format!("{}/{}", paths::PRICE_PREFIX, pair))?.and_then(|s| s.data["price"].as_f64()))
+}
+
+/// Price for `pair` on the day of `at` (unix seconds): that day's, else the
+/// latest recorded before it
+pub fn price_at(store: &Store, pair: &str, at: u64) -> NineSResult<Option<f64>> {
+    let day = day(at);
+    let latest = store.list(&format!("{}/{}", paths::HISTORY_PREFIX, pair))?.into_iter()
+        .filter(|key| key.rsplit('/').next().is_some_and(|d| d <= day.as_str()))
+        .max();
+    let Some(key) = latest else { return Ok(None) };
+    Ok(store.read(&key)?.and_then(|s| s.data["price"].as_f64()))
+}
+
+/// Record a fetched price, and keep it as today's in the history
+pub fn record_price(store: &Store, pair: &str, price: f64, provider: &str) -> NineSResult<Scroll> {
+    let fetched_at = now_secs();
+    let data = json!({"pair": pair, "price": price, "provider": provider, "fetched_at": fetched_at});
+    store.write_scroll(Scroll {
+        key: format!("{}/{}/{}", paths::HISTORY_PREFIX, pair, day(fetched_at)),
+        type_: paths::HISTORY_TYPE.into(),
+        metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+        data: data.clone(),
+    })?;
+    store.write_scroll(Scroll {
+        key: format!("{}/{}", paths::PRICE_PREFIX, pair),
+        type_: paths::PRICE_TYPE.into(),
+        metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+        data,
+    })
+}
+
+pub fn sats_to_fiat(sats: u64, price: f64) -> f64 {
+    round2(sats as f64 / SATS_PER_BTC * price)
+}
+
+pub fn fiat_to_sats(amount: f64, price: f64) -> Option<u64> {
+    (price > 0.0 && amount >= 0.0).then(|| (amount / price * SATS_PER_BTC).round() as u64)
+}
+
+/// `{usd, eur, ...}` for every known `btc-*` price; empty when none are stored
+pub fn fiat_values(store: &Store, sats: u64) -> NineSResult<Value> {
+    let mut out = Map::new();
+    for key in store.list(paths::PRICE_PREFIX)? {
+        let Some(currency) = key.rsplit('/').next().and_then(|pair| pair.strip_prefix("btc-")) else { continue };
+        if let Some(p) = store.read(&key)?.and_then(|s| s.data["price"].as_f64()) {
+            out.insert(currency.to_string(), json!(sats_to_fiat(sats, p)));
+        }
+    }
+    Ok(Value::Object(out))
+}
+
+/// Mind pattern that refreshes prices on a clock pulse (e.g. "refresh")
+pub fn refresh_pattern(pulse: &str) -> PatternDef {
+    PatternDef {
+        name: format!("market-price-{}", pulse),
+        watch: format!("/sys/clock/pulses/{}", pulse),
+        x: None,
+        g: None,
+        v: None,
+        emit: paths::REFRESH_TYPE.into(),
+        emit_path: format!("{}/${{uuid}}", paths::EXTERNAL_PRICE),
+        template: json!({"trigger": pulse, "priority": "low"}),
+        then: None,
+        when: None,
+        reactions: vec![],
+    }
+}
+
+fn round2(v: f64) -> f64 { (v * 100.0).round() / 100.0 }
+
+/// `YYYY-MM-DD` (UTC) of a unix timestamp
+fn day(at: u64) -> String {
+    chrono::DateTime::from_timestamp(at as i64, 0).map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(sats_to_fiat(100_000, 60_000.0), 60.0);
+        assert_eq!(fiat_to_sats(60.0, 60_000.0), Some(100_000));
+        assert_eq!(fiat_to_sats(10.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_price_at_uses_day_history() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-market").with_root(dir.path()).open_store().unwrap();
+        for (day, price) in [("2024-01-01", 40_000.0), ("2024-03-01", 60_000.0)] {
+            store.write_scroll(Scroll::new(&format!("/market/history/btc-usd/{}", day), json!({"price": price}))).unwrap();
+        }
+        // 2024-02-15, 2024-03-01 12:00, 2023-12-31
+        assert_eq!(price_at(&store, "btc-usd", 1_707_955_200).unwrap(), Some(40_000.0));
+        assert_eq!(price_at(&store, "btc-usd", 1_709_294_400).unwrap(), Some(60_000.0));
+        assert_eq!(price_at(&store, "btc-usd", 1_703_980_800).unwrap(), None);
+
+        record_price(&store, "btc-eur", 50_000.0, "test").unwrap();
+        assert_eq!(price_at(&store, "btc-eur", now_secs()).unwrap(), Some(50_000.0));
+    }
+}
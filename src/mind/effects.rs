@@ -1,58 +1,377 @@
 //! Effects: /external/** side effects
+//!
+//! Effects queue into three lanes by their `data.priority` (`high`, `normal`,
+//! `low`; default `normal`). The scheduler always starts the highest-priority
+//! effect whose handler kind has a free slot, so a slow wallet scan occupying
+//! the `bitcoin` kind never delays a `nostr` publish queued behind it.
+//!
+//! # Ordering guarantees
+//!
+//! - Effects on the same resource (`EffectHandler::resource`, by default the
+//!   kind) never overlap. They start in priority order, then arrival order.
+//! - Effects on different resources run concurrently with no relative order.
+//! - At most `EffectConfig::concurrency` effects run at once across all kinds.
+//! - An effect's `/result` is written before its resource is released, so the
+//!   next effect on that resource observes it.
+//!
+//! # Dispatch
+//!
+//! An effect at `/external/{service}/...` goes to the first handler whose
+//! `watches()` covers it segment by segment: `/external/bitcoin` (or
+//! `/external/bitcoin/**`) takes `/external/bitcoin/sync/1`, never
+//! `/external/bitcoinx/...`. Effects nobody handles are left as written.
+//! Downstream crates register their own services with
+//! `EffectWorker::with_handler("myservice", ...)` and get the same
+//! scheduling, `/sys/effects` records and `/result` write as the built-ins.
 
 use anyhow::Result;
 use async_trait::async_trait;
+use crate::core::time::now_secs;
+use futures_util::FutureExt;
 use nine_s_core::prelude::*;
 use nine_s_store::Store;
 use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use crate::core::paths::{mind as paths, origin, EFFECT_RESULT_TYPE};
+use crate::core::paths::{effects, mind as paths, origin, EFFECT_RESULT_TYPE};
 
 #[async_trait]
 pub trait EffectHandler: Send + Sync {
     fn watches(&self) -> &str;
     async fn execute(&self, scroll: &Scroll) -> Result<Value>;
+
+    /// Concurrency group, limited by `EffectConfig::limits`.
+    /// Defaults to the segment after `/external` (`/external/bitcoin` → `bitcoin`).
+    fn kind(&self) -> &str {
+        self.watches().trim_start_matches(paths::EXTERNAL_PREFIX).trim_start_matches('/').split('/').next().unwrap_or("")
+    }
+
+    /// Mutual-exclusion key: effects sharing a resource run one at a time.
+    /// Defaults to `kind()`; return e.g. the scroll key to let them overlap.
+    fn resource(&self, _scroll: &Scroll) -> String {
+        self.kind().to_string()
+    }
+}
+
+/// Scheduling lane, read from an effect's `data.priority`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority { High = 0, Normal = 1, Low = 2 }
+
+impl Priority {
+    pub fn of(scroll: &Scroll) -> Self {
+        match scroll.data.get("priority").and_then(|p| p.as_str()) {
+            Some("high") | Some("urgent") => Self::High,
+            Some("low") | Some("background") => Self::Low,
+            _ => Self::Normal,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct EffectConfig { pub process_existing: bool, pub origin: String }
-impl Default for EffectConfig { fn default() -> Self { Self { process_existing: false, origin: origin::EFFECTS.into() } } }
+pub struct EffectConfig {
+    pub process_existing: bool,
+    pub origin: String,
+    /// Concurrent effects per handler kind (e.g. `"nostr" → 4`)
+    pub limits: HashMap<String, usize>,
+    /// Limit for kinds not listed in `limits`
+    pub default_limit: usize,
+    /// Worker pool size: effects in flight across all kinds
+    pub concurrency: usize,
+}
+impl Default for EffectConfig {
+    fn default() -> Self { Self { process_existing: false, origin: origin::EFFECTS.into(), limits: HashMap::new(), default_limit: 1, concurrency: 4 } }
+}
+impl EffectConfig {
+    pub fn with_limit(mut self, kind: impl Into<String>, limit: usize) -> Self { self.limits.insert(kind.into(), limit.max(1)); self }
+    pub fn with_concurrency(mut self, n: usize) -> Self { self.concurrency = n.max(1); self }
+}
 
 pub struct EffectWorker {
     store: Arc<Store>,
-    handlers: Vec<Box<dyn EffectHandler>>,
+    handlers: Vec<Arc<dyn EffectHandler>>,
     config: EffectConfig,
 }
 
 impl EffectWorker {
     pub fn new(store: Store) -> Self { Self { store: Arc::new(store), handlers: Vec::new(), config: EffectConfig::default() } }
     pub fn with_config(mut self, config: EffectConfig) -> Self { self.config = config; self }
-    pub fn add_handler(mut self, handler: Box<dyn EffectHandler>) -> Self { self.handlers.push(handler); self }
+    pub fn add_handler(mut self, handler: Box<dyn EffectHandler>) -> Self { self.handlers.push(Arc::from(handler)); self }
+
+    /// Handle `/external/{service}/**` with `handler`, whatever its own
+    /// `watches()` says. Its kind becomes `service`, so `EffectConfig::limits`
+    /// keys on the service name; a `resource` it doesn't override does too.
+    pub fn with_handler(self, service: &str, handler: Box<dyn EffectHandler>) -> Self {
+        let service = service.trim_matches('/');
+        self.add_handler(Box::new(Service { watches: format!("{}/{}", paths::EXTERNAL_PREFIX, service), inner: handler }))
+    }
 
     pub async fn run(&self) -> Result<()> {
+        crate::runtime::install_panic_hook();
         let rx = self.store.watch(&WatchPattern::parse(&format!("{}/**", paths::EXTERNAL_PREFIX))?)?;
+        let (tx, mut incoming) = tokio::sync::mpsc::unbounded_channel();
         if self.config.process_existing {
             for path in self.store.list(paths::EXTERNAL_PREFIX)? {
-                if !path.contains(paths::RESULT_SUFFIX) { if let Some(s) = self.store.read(&path)? { self.process(&s).await; } }
+                if !path.contains(paths::RESULT_SUFFIX) { if let Some(s) = self.store.read(&path)? { let _ = tx.send(s); } }
             }
         }
-        while let Ok(s) = rx.recv() {
-            if s.key.contains(paths::RESULT_SUFFIX) || s.metadata.produced_by.as_deref() == Some(&self.config.origin) { continue; }
-            self.process(&s).await;
+        // Store watches are blocking; bridge them onto the scheduler
+        let origin_marker = self.config.origin.clone();
+        std::thread::spawn(move || {
+            while let Ok(s) = rx.recv() {
+                if s.key.contains(paths::RESULT_SUFFIX) || s.metadata.produced_by.as_deref() == Some(&origin_marker) { continue; }
+                if tx.send(s).is_err() { break; }
+            }
+        });
+
+        let mut scheduler = Scheduler::new(self.config.limits.clone(), self.config.default_limit, self.config.concurrency);
+        let (done_tx, mut done) = tokio::sync::mpsc::unbounded_channel::<(String, String)>();
+        let mut closed = false;
+        loop {
+            if closed && scheduler.is_idle() { break; }
+            tokio::select! {
+                next = incoming.recv(), if !closed => match next {
+                    Some(scroll) => {
+                        if let Some(i) = self.handlers.iter().position(|h| handles(h.watches(), &scroll.key)) {
+                            let h = &self.handlers[i];
+                            scheduler.push(Job { kind: h.kind().to_string(), resource: h.resource(&scroll), handler: i, scroll });
+                        }
+                    }
+                    None => closed = true,
+                },
+                Some((kind, resource)) = done.recv() => scheduler.finish(&kind, &resource),
+            }
+            while let Some(job) = scheduler.next_ready() {
+                let handler = self.handlers[job.handler].clone();
+                let store = self.store.clone();
+                let origin = self.config.origin.clone();
+                let done_tx = done_tx.clone();
+                tokio::spawn(async move {
+                    process(&store, handler.as_ref(), &job.scroll, &origin).await;
+                    let _ = done_tx.send((job.kind, job.resource));
+                });
+            }
         }
         Ok(())
     }
+}
+
+/// Whether `watches` covers `key` on a segment boundary
+fn handles(watches: &str, key: &str) -> bool {
+    let prefix = watches.trim_end_matches("/**").trim_end_matches('/');
+    key.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// A handler registered under a service name by `with_handler`
+struct Service {
+    watches: String,
+    inner: Box<dyn EffectHandler>,
+}
+
+#[async_trait]
+impl EffectHandler for Service {
+    fn watches(&self) -> &str { &self.watches }
+    async fn execute(&self, scroll: &Scroll) -> Result<Value> { self.inner.execute(scroll).await }
+    fn resource(&self, scroll: &Scroll) -> String {
+        let resource = self.inner.resource(scroll);
+        if resource == self.inner.kind() { self.kind().to_string() } else { resource }
+    }
+}
 
-    async fn process(&self, scroll: &Scroll) {
-        for h in &self.handlers {
-            if scroll.key.starts_with(h.watches()) {
-                let data = match h.execute(scroll).await {
-                    Ok(v) => serde_json::json!({"success": true, "result": v}),
-                    Err(e) => serde_json::json!({"success": false, "error": e.to_string()}),
-                };
-                let _ = self.store.write_scroll(Scroll { key: format!("{}{}", scroll.key, paths::RESULT_SUFFIX), type_: EFFECT_RESULT_TYPE.into(), metadata: Metadata::default().with_produced_by(&self.config.origin), data });
-                return;
+async fn process(store: &Store, h: &dyn EffectHandler, scroll: &Scroll, origin: &str) {
+    let started = std::time::Instant::now();
+    let mut record = serde_json::json!({
+        "path": scroll.key,
+        "handler": h.watches(),
+        "priority": format!("{:?}", Priority::of(scroll)).to_lowercase(),
+        "effect_version": scroll.metadata.version,
+        "status": "running",
+        "started_at": now_secs(),
+    });
+    write_record(store, &scroll.key, &record, origin);
+    // A panicking handler fails this effect only; the worker and its slots carry on
+    let data = match std::panic::AssertUnwindSafe(h.execute(scroll)).catch_unwind().await {
+        Ok(Ok(v)) => serde_json::json!({"success": true, "result": v}),
+        Ok(Err(e)) => serde_json::json!({"success": false, "error": e.to_string()}),
+        Err(payload) => {
+            let message = crate::runtime::panic_message(&*payload);
+            crate::runtime::report_crash(store, &format!("effect {}", scroll.key), &message);
+            serde_json::json!({"success": false, "error": format!("handler panicked: {}", message)})
+        }
+    };
+    record["status"] = serde_json::json!(if data["success"] == true { "success" } else { "failed" });
+    record["finished_at"] = serde_json::json!(now_secs());
+    record["duration_ms"] = serde_json::json!(started.elapsed().as_millis() as u64);
+    if let Some(e) = data.get("error") { record["error"] = e.clone(); }
+    write_record(store, &scroll.key, &record, origin);
+    let _ = store.write_scroll(Scroll { key: format!("{}{}", scroll.key, paths::RESULT_SUFFIX), type_: EFFECT_RESULT_TYPE.into(), metadata: Metadata::default().with_produced_by(origin), data });
+}
+
+/// Worker bookkeeping for `/sys/effects` introspection
+fn write_record(store: &Store, effect: &str, data: &Value, origin: &str) {
+    let key = format!("{}{}", effects::RECORDS_PREFIX, effect);
+    let _ = store.write_scroll(Scroll { key, type_: effects::RECORD_TYPE.into(), metadata: Metadata::default().with_produced_by(origin), data: data.clone() });
+}
+
+struct Job { kind: String, resource: String, handler: usize, scroll: Scroll }
+
+/// Priority lanes, per-kind slots, resource locks and the pool cap
+struct Scheduler {
+    lanes: [VecDeque<Job>; 3],
+    running: HashMap<String, usize>,
+    locked: HashSet<String>,
+    limits: HashMap<String, usize>,
+    default_limit: usize,
+    concurrency: usize,
+}
+
+impl Scheduler {
+    fn new(limits: HashMap<String, usize>, default_limit: usize, concurrency: usize) -> Self {
+        Self {
+            lanes: Default::default(),
+            running: HashMap::new(),
+            locked: HashSet::new(),
+            limits,
+            default_limit: default_limit.max(1),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    fn push(&mut self, job: Job) {
+        self.lanes[Priority::of(&job.scroll) as usize].push_back(job);
+    }
+
+    fn can_start(&self, job: &Job) -> bool {
+        let limit = self.limits.get(&job.kind).copied().unwrap_or(self.default_limit);
+        self.running.get(&job.kind).copied().unwrap_or(0) < limit && !self.locked.contains(&job.resource)
+    }
+
+    /// Highest-priority, oldest job whose kind has a free slot and whose
+    /// resource is unlocked. None while the pool is full.
+    fn next_ready(&mut self) -> Option<Job> {
+        if self.running.values().sum::<usize>() >= self.concurrency { return None; }
+        for lane in 0..self.lanes.len() {
+            if let Some(i) = self.lanes[lane].iter().position(|j| self.can_start(j)) {
+                let job = self.lanes[lane].remove(i)?;
+                *self.running.entry(job.kind.clone()).or_default() += 1;
+                self.locked.insert(job.resource.clone());
+                return Some(job);
             }
         }
+        None
+    }
+
+    fn finish(&mut self, kind: &str, resource: &str) {
+        if let Some(n) = self.running.get_mut(kind) { *n = n.saturating_sub(1); }
+        self.locked.remove(resource);
+    }
+
+    fn is_idle(&self) -> bool {
+        self.lanes.iter().all(|l| l.is_empty()) && self.running.values().all(|n| *n == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn job(kind: &str, key: &str, priority: &str) -> Job {
+        job_on(kind, key, key, priority)
+    }
+
+    fn job_on(kind: &str, resource: &str, key: &str, priority: &str) -> Job {
+        Job { kind: kind.into(), resource: resource.into(), handler: 0, scroll: Scroll::new(key, json!({"priority": priority})) }
+    }
+
+    #[test]
+    fn test_priority_lanes() {
+        let mut s = Scheduler::new(HashMap::new(), 1, 4);
+        s.push(job("bitcoin", "/external/bitcoin/sync/1", "low"));
+        s.push(job("bitcoin", "/external/bitcoin/send/2", "high"));
+        assert_eq!(s.next_ready().unwrap().scroll.key, "/external/bitcoin/send/2");
+        // bitcoin slot busy: the low-priority sync waits
+        assert!(s.next_ready().is_none());
+        s.finish("bitcoin", "/external/bitcoin/send/2");
+        assert_eq!(s.next_ready().unwrap().scroll.key, "/external/bitcoin/sync/1");
+    }
+
+    #[test]
+    fn test_busy_kind_does_not_block_others() {
+        let mut s = Scheduler::new(HashMap::from([("nostr".to_string(), 2)]), 1, 4);
+        s.push(job("bitcoin", "/external/bitcoin/sync/1", "normal"));
+        s.push(job("bitcoin", "/external/bitcoin/sync/2", "high"));
+        s.push(job("nostr", "/external/nostr/publish/1", "low"));
+        s.push(job("nostr", "/external/nostr/publish/2", "low"));
+        let started: Vec<String> = std::iter::from_fn(|| s.next_ready()).map(|j| j.scroll.key).collect();
+        assert_eq!(started, vec!["/external/bitcoin/sync/2", "/external/nostr/publish/1", "/external/nostr/publish/2"]);
+        assert!(!s.is_idle());
+    }
+
+    #[test]
+    fn test_resource_lock_keeps_order() {
+        let mut s = Scheduler::new(HashMap::from([("http".to_string(), 8)]), 1, 8);
+        s.push(job_on("http", "api.example", "/external/http/1", "normal"));
+        s.push(job_on("http", "api.example", "/external/http/2", "normal"));
+        s.push(job_on("http", "other.example", "/external/http/3", "normal"));
+        let started: Vec<String> = std::iter::from_fn(|| s.next_ready()).map(|j| j.scroll.key).collect();
+        assert_eq!(started, vec!["/external/http/1", "/external/http/3"]);
+        s.finish("http", "api.example");
+        assert_eq!(s.next_ready().unwrap().scroll.key, "/external/http/2");
+    }
+
+    #[test]
+    fn test_pool_cap() {
+        let mut s = Scheduler::new(HashMap::new(), 4, 2);
+        for i in 0..3 { s.push(job("nostr", &format!("/external/nostr/{}", i), "normal")); }
+        assert_eq!(std::iter::from_fn(|| s.next_ready()).count(), 2);
+        s.finish("nostr", "/external/nostr/0");
+        assert_eq!(s.next_ready().unwrap().scroll.key, "/external/nostr/2");
+    }
+
+    #[test]
+    fn test_default_kind() {
+        struct H;
+        #[async_trait]
+        impl EffectHandler for H {
+            fn watches(&self) -> &str { "/external/bitcoin" }
+            async fn execute(&self, _: &Scroll) -> Result<Value> { Ok(Value::Null) }
+        }
+        assert_eq!(H.kind(), "bitcoin");
+        assert_eq!(H.resource(&Scroll::new("/external/bitcoin/sync/1", json!({}))), "bitcoin");
+    }
+
+    #[test]
+    fn test_with_handler_dispatch() {
+        struct Anon;
+        #[async_trait]
+        impl EffectHandler for Anon {
+            fn watches(&self) -> &str { "" }
+            async fn execute(&self, _: &Scroll) -> Result<Value> { Ok(Value::Null) }
+        }
+        let svc = Service { watches: "/external/myservice".into(), inner: Box::new(Anon) };
+        assert_eq!(svc.kind(), "myservice");
+        assert_eq!(svc.resource(&Scroll::new("/external/myservice/ping/1", json!({}))), "myservice");
+
+        assert!(handles("/external/myservice", "/external/myservice/ping/1"));
+        assert!(handles("/external/webhook/**", "/external/webhook/post/1"));
+        assert!(!handles("/external/my", "/external/myservice/ping/1"));
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_fails_effect() {
+        struct Boom;
+        #[async_trait]
+        impl EffectHandler for Boom {
+            fn watches(&self) -> &str { "/external/boom" }
+            async fn execute(&self, _: &Scroll) -> Result<Value> { panic!("handler exploded") }
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-effects").with_root(dir.path()).open_store().unwrap();
+        process(&store, &Boom, &Scroll::new("/external/boom/1", json!({})), origin::EFFECTS).await;
+
+        let result = store.read("/external/boom/1/result").unwrap().unwrap();
+        assert_eq!(result.data["success"], false);
+        assert!(result.data["error"].as_str().unwrap().contains("handler exploded"));
+        assert_eq!(store.list("/sys/crashes").unwrap().len(), 1);
     }
 }
@@ -11,8 +11,14 @@ use crate::core::pattern::Pattern;
 fn is_reserved(path: &str) -> bool { path.ends_with(paths::RESERVED_SUFFIX) }
 
 #[derive(Debug, Clone)]
-pub struct MindConfig { pub process_existing: bool, pub origin: String }
-impl Default for MindConfig { fn default() -> Self { Self { process_existing: false, origin: origin::MIND.into() } } }
+pub struct MindConfig {
+    pub process_existing: bool,
+    pub origin: String,
+    /// The node's sealed prefixes: reactions landing there are dropped, since
+    /// the Mind holds no PIN key to seal them with
+    pub sealed_prefixes: Vec<String>,
+}
+impl Default for MindConfig { fn default() -> Self { Self { process_existing: false, origin: origin::MIND.into(), sealed_prefixes: Vec::new() } } }
 
 pub struct Mind {
     store: Arc<Store>,
@@ -31,43 +37,61 @@ impl Mind {
         let rx = self.store.watch(&WatchPattern::parse("/**")?)?;
         if self.config.process_existing {
             for path in self.store.list("/")? {
-                if !self.should_skip(&path) { if let Some(s) = self.store.read(&path)? { self.apply_patterns(&s)?; } }
+                if !self.should_skip(&path) { if let Some(s) = self.store.read(&path)? { self.apply_patterns(&s, &mut Vec::new())?; } }
             }
         }
         while let Ok(scroll) = rx.recv() {
             if self.should_skip(&scroll.key) { continue; }
             if scroll.key.starts_with(paths::PATTERNS_PREFIX) { if self.check_pattern_changed(&scroll) { self.reload_patterns()?; } continue; }
-            if scroll.metadata.produced_by.as_deref() == Some(&self.config.origin) { continue; }
-            self.apply_patterns(&scroll)?;
+            self.react(&scroll)?;
         }
         Ok(())
     }
 
+    /// One step of the loop without the watch: apply the loaded patterns to
+    /// `scroll` and return every reaction written (cascades included), in order
+    pub fn react(&self, scroll: &Scroll) -> Result<Vec<Scroll>> {
+        let mut written = Vec::new();
+        if self.should_skip(&scroll.key) || scroll.metadata.produced_by.as_deref() == Some(&self.config.origin) { return Ok(written); }
+        self.apply_patterns(scroll, &mut written)?;
+        Ok(written)
+    }
+
     fn should_skip(&self, path: &str) -> bool { is_reserved(path) || path.starts_with(paths::PATTERNS_PREFIX) }
 
+    /// Reactions bound for a sealed prefix would land in plaintext
+    fn refuse(&self, pattern: &str, reaction: &Scroll) -> bool {
+        let sealed = crate::node::sealed::matching(&self.config.sealed_prefixes, &reaction.key).is_some();
+        if sealed { tracing::warn!("'{}': {} is under a sealed prefix; not written", pattern, reaction.key); }
+        sealed
+    }
+
     fn check_pattern_changed(&mut self, scroll: &Scroll) -> bool {
         let prev = self.pattern_versions.get(&scroll.key).copied().unwrap_or(0);
         if scroll.metadata.version > prev { self.pattern_versions.insert(scroll.key.clone(), scroll.metadata.version); true } else { false }
     }
 
-    fn apply_patterns(&self, scroll: &Scroll) -> Result<()> {
+    fn apply_patterns(&self, scroll: &Scroll, written: &mut Vec<Scroll>) -> Result<()> {
         for pattern in &self.patterns {
-            if let Some(reaction) = pattern.apply(scroll, Some(&self.config.origin))? {
+            for reaction in pattern.apply_all(scroll, Some(&self.config.origin))? {
+                if self.refuse(&pattern.name, &reaction) { continue; }
                 tracing::info!("'{}': {} -> {}", pattern.name, scroll.key, reaction.key);
-                self.store.write_scroll(reaction.clone())?;
-                if let Some(then) = &pattern.then { self.cascade(then, &reaction)?; }
+                let reaction = self.store.write_scroll(reaction)?;
+                written.push(reaction.clone());
+                if let Some(then) = &pattern.then { self.cascade(then, &reaction, written)?; }
             }
         }
         Ok(())
     }
 
-    fn cascade(&self, pattern_path: &str, scroll: &Scroll) -> Result<()> {
+    fn cascade(&self, pattern_path: &str, scroll: &Scroll, written: &mut Vec<Scroll>) -> Result<()> {
         let path = if pattern_path.starts_with('/') { pattern_path.to_string() } else { format!("{}/{}", paths::PATTERNS_PREFIX, pattern_path) };
         if let Some(ps) = self.store.read(&path)? {
             let p = Pattern::from_value(ps.data)?;
-            if let Some(r) = p.apply(scroll, Some(&self.config.origin))? {
-                self.store.write_scroll(r.clone())?;
-                if let Some(next) = &p.then { self.cascade(next, &r)?; }
+            if let Some(r) = p.apply(scroll, Some(&self.config.origin))?.filter(|r| !self.refuse(&p.name, r)) {
+                let r = self.store.write_scroll(r)?;
+                written.push(r.clone());
+                if let Some(next) = &p.then { self.cascade(next, &r, written)?; }
             }
         }
         Ok(())
@@ -23,5 +23,5 @@
 mod effects;
 mod mind;
 
-pub use effects::{EffectHandler, EffectWorker};
+pub use effects::{EffectConfig, EffectHandler, EffectWorker, Priority};
 pub use mind::{Mind, MindConfig};
@@ -19,6 +19,12 @@
 //! assert_eq!(mobi.display, "879044656584");
 //! assert_eq!(mobi.display_formatted(), "879-044-656-584");
 //! ```
+//!
+//! # Validation
+//!
+//! Every digit comes from the hash, so a Mobi carries no checksum: `validate`
+//! catches wrong lengths and stray characters, and only `matches_pubkey`
+//! catches a mistyped digit.
 
 use nine_s_core::errors::{NineSError, NineSResult};
 use sha2::{Digest, Sha256};
@@ -105,6 +111,28 @@ impl Mobi {
         ))
     }
 
+    /// Normalize a typed Mobi: drop `-` and spaces, then require 12, 15,
+    /// 18 or 21 digits. Returns the bare digits.
+    pub fn validate(input: &str) -> NineSResult<String> {
+        let digits: String = input.chars().filter(|c| *c != '-' && !c.is_whitespace()).collect();
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(NineSError::Other(format!("Invalid Mobi character '{}'", c)));
+        }
+        if ![12, 15, 18, 21].contains(&digits.len()) {
+            return Err(NineSError::Other(format!(
+                "Mobi must be 12, 15, 18 or 21 digits, got {}",
+                digits.len()
+            )));
+        }
+        Ok(digits)
+    }
+
+    /// Whether the typed Mobi (any form) belongs to `pubkey_hex`
+    pub fn matches_pubkey(input: &str, pubkey_hex: &str) -> NineSResult<bool> {
+        let digits = Self::validate(input)?;
+        Ok(Self::derive(pubkey_hex)?.full.starts_with(&digits))
+    }
+
     /// Format display as phone number: "879-044-656-584"
     pub fn display_formatted(&self) -> String {
         format!(
@@ -184,6 +212,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_and_match() {
+        let pubkey = "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917";
+        assert_eq!(Mobi::validate("879-044-656-584").unwrap(), "879044656584");
+        assert_eq!(Mobi::validate(" 879 044 656 584 686 ").unwrap(), "879044656584686");
+        assert!(Mobi::validate("879-044-656-58").is_err());
+        assert!(Mobi::validate("879-044-656-58a").is_err());
+
+        assert!(Mobi::matches_pubkey("879-044-656-584", pubkey).unwrap());
+        assert!(Mobi::matches_pubkey("879044656584686196443", pubkey).unwrap());
+        assert!(!Mobi::matches_pubkey("879-044-656-585", pubkey).unwrap());
+    }
+
     #[test]
     fn test_wrong_length() {
         let result = Mobi::derive("1234"); // Too short
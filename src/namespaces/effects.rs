@@ -0,0 +1,131 @@
+//! Effects namespace - queue introspection for `/external/**` effects.
+//!
+//! EffectWorker records each run under `/sys/effects/records{effect path}`.
+//! This namespace joins those records with the raw effect scrolls:
+//!
+//! | Path | R/W | Description |
+//! |------|-----|-------------|
+//! | `/sys/effects/queue` | R | Effects not yet picked up `{path, age_secs}` |
+//! | `/sys/effects/running` | R | Effects a worker is executing |
+//! | `/sys/effects/history` | R | Finished runs, newest first `{path, status, error?, duration_ms}` |
+//! | `/sys/effects/requeue` | W | `{path}` re-submits an effect so the worker runs it again |
+
+use crate::core::paths::{effects as paths, mind};
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Most recent runs returned by `/history`
+pub const HISTORY_LIMIT: usize = 100;
+
+pub struct EffectsNamespace {
+    store: Arc<Store>,
+}
+
+impl EffectsNamespace {
+    pub fn new(store: Arc<Store>) -> Self {
+        Self { store }
+    }
+
+    fn records(&self) -> NineSResult<Vec<Value>> {
+        let mut out = Vec::new();
+        for key in self.store.list(paths::RECORDS_PREFIX)? {
+            if let Some(s) = self.store.read(&key)? { out.push(s.data); }
+        }
+        Ok(out)
+    }
+
+    fn record(&self, effect: &str) -> NineSResult<Option<Value>> {
+        Ok(self.store.read(&format!("{}{}", paths::RECORDS_PREFIX, effect))?.map(|s| s.data))
+    }
+
+    fn read_queue(&self) -> NineSResult<Scroll> {
+        let now = now_secs();
+        let mut items = Vec::new();
+        for key in self.store.list(mind::EXTERNAL_PREFIX)? {
+            if key.contains(mind::RESULT_SUFFIX) || key.ends_with(mind::RESERVED_SUFFIX) { continue; }
+            let Some(effect) = self.store.read(&key)? else { continue };
+            let picked_up = match self.record(&key)? {
+                Some(r) => r["effect_version"].as_u64().is_some_and(|v| v >= effect.metadata.version),
+                // Ran before records existed
+                None => self.store.read(&format!("{}{}", key, mind::RESULT_SUFFIX))?.is_some(),
+            };
+            if picked_up { continue; }
+            let age_secs = written_at(&effect).map(|t| now.saturating_sub(t));
+            items.push(json!({"path": key, "type": effect.type_, "version": effect.metadata.version, "age_secs": age_secs}));
+        }
+        Ok(list("/sys/effects/queue", items))
+    }
+
+    fn read_running(&self) -> NineSResult<Scroll> {
+        let now = now_secs();
+        let items: Vec<Value> = self.records()?.into_iter()
+            .filter(|r| r["status"] == "running")
+            .map(|mut r| { r["running_secs"] = json!(now.saturating_sub(r["started_at"].as_u64().unwrap_or(now))); r })
+            .collect();
+        Ok(list("/sys/effects/running", items))
+    }
+
+    fn read_history(&self) -> NineSResult<Scroll> {
+        let mut items: Vec<Value> = self.records()?.into_iter().filter(|r| r["status"] != "running").collect();
+        items.sort_by_key(|r| std::cmp::Reverse(r["finished_at"].as_u64().unwrap_or(0)));
+        items.truncate(HISTORY_LIMIT);
+        Ok(list("/sys/effects/history", items))
+    }
+
+    fn write_requeue(&self, data: Value) -> NineSResult<Scroll> {
+        let path = data["path"].as_str().ok_or_else(|| NineSError::Other("no 'path'".into()))?;
+        if !path.starts_with(mind::EXTERNAL_PREFIX) || path.contains(mind::RESULT_SUFFIX) {
+            return Err(NineSError::invalid_path(path, "not an effect path"));
+        }
+        if self.record(path)?.is_some_and(|r| r["status"] == "running") {
+            return Err(NineSError::Other(format!("effect is running: {}", path)));
+        }
+        let effect = self.store.read(path)?.ok_or_else(|| NineSError::Other(format!("no effect at {}", path)))?;
+        // Fresh metadata: the worker skips scrolls it produced itself
+        let requeued = self.store.write_scroll(Scroll::new(path, effect.data).set_type(&effect.type_))?;
+        Ok(Scroll::new("/sys/effects/requeue", json!({"path": path, "version": requeued.metadata.version, "status": "queued"}))
+            .set_type(paths::LIST_TYPE))
+    }
+}
+
+impl Namespace for EffectsNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        Ok(Some(match path {
+            paths::QUEUE | "" | "/" => self.read_queue()?,
+            paths::RUNNING => self.read_running()?,
+            paths::HISTORY => self.read_history()?,
+            p if p.starts_with("/records/") => return self.store.read(&format!("{}{}", paths::PREFIX, p)),
+            _ => return Ok(None),
+        }))
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        match path {
+            paths::REQUEUE => self.write_requeue(data),
+            _ => Err(NineSError::invalid_path(path, "effects namespace only accepts /requeue")),
+        }
+    }
+
+    fn list(&self, _: &str) -> NineSResult<Vec<String>> {
+        Ok(vec![paths::QUEUE.into(), paths::RUNNING.into(), paths::HISTORY.into(), paths::REQUEUE.into()])
+    }
+}
+
+fn list(key: &str, items: Vec<Value>) -> Scroll {
+    Scroll::new(key, json!({"count": items.len(), "items": items})).set_type(paths::LIST_TYPE)
+}
+
+/// Seconds since epoch from scroll metadata, whether stored as a number
+/// (seconds or milliseconds) or an RFC 3339 string
+fn written_at(scroll: &Scroll) -> Option<u64> {
+    let meta = serde_json::to_value(&scroll.metadata).ok()?;
+    let at = meta.get("updated_at").filter(|v| !v.is_null()).or_else(|| meta.get("created_at"))?;
+    match at {
+        Value::Number(n) => n.as_u64().map(|t| if t > 10_000_000_000 { t / 1000 } else { t }),
+        Value::String(s) => chrono::DateTime::parse_from_rfc3339(s).ok().map(|d| d.timestamp().max(0) as u64),
+        _ => None,
+    }
+}
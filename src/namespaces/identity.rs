@@ -0,0 +1,93 @@
+//! Identity namespace - protocol seed derivation registry.
+//!
+//! Applications register deterministic seeds with `/derive {protocol, index}`.
+//! The write answers with public metadata only (the seed's fingerprint) and
+//! records it under `/sys/identity/derivations/{protocol}/{index}`; the seed
+//! itself never leaves the process. In-process code derives it with
+//! `identity::derive::derive_seed`, and protocol modules (`ssh`, `nostr`)
+//! publish their own public keys.
+
+use crate::core::paths::identity as paths;
+use crate::core::time::now_secs;
+use crate::identity::{derive, Identity};
+use crate::secret::Secret;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+pub struct IdentityNamespace {
+    seed: Secret<[u8; 64]>,
+    identity: Identity,
+    store: Arc<Store>,
+}
+
+impl IdentityNamespace {
+    pub fn new(seed: Secret<[u8; 64]>, identity: Identity, store: Arc<Store>) -> Self {
+        Self { seed, identity, store }
+    }
+
+    fn read_status(&self) -> NineSResult<Scroll> {
+        let protocols: Vec<Value> = derive::PROTOCOLS.iter()
+            .map(|(name, about)| json!({"name": name, "description": about}))
+            .collect();
+        Ok(Scroll::new("/identity/status", json!({
+            "mobi": self.identity.mobi.display,
+            "pubkey": self.identity.pubkey_hex,
+            "protocols": protocols,
+        })).set_type(paths::STATUS_TYPE))
+    }
+
+    fn read_derivations(&self) -> NineSResult<Scroll> {
+        let mut items = Vec::new();
+        for key in self.store.list(paths::DERIVATIONS_PREFIX)? {
+            if let Some(s) = self.store.read(&key)? { items.push(s.data); }
+        }
+        Ok(Scroll::new("/identity/derivations", json!({"derivations": items, "count": items.len()}))
+            .set_type(paths::DERIVATION_TYPE))
+    }
+
+    fn write_derive(&self, data: Value) -> NineSResult<Scroll> {
+        let protocol = data["protocol"].as_str()
+            .ok_or_else(|| NineSError::Other("no 'protocol'".into()))?;
+        let index = data["index"].as_u64().unwrap_or(0);
+        let index = u32::try_from(index).map_err(|_| NineSError::Other("index out of range".into()))?;
+        let seed = derive::derive_seed(self.seed.expose(), protocol, index)?;
+        let fingerprint = derive::fingerprint(&seed);
+
+        let key = format!("{}/{}/{}", paths::DERIVATIONS_PREFIX, protocol, index);
+        let derived_at = match self.store.read(&key)? {
+            Some(existing) => existing.data["derived_at"].clone(),
+            None => json!(now_secs()),
+        };
+        let meta = json!({
+            "protocol": protocol,
+            "index": index,
+            "fingerprint": fingerprint,
+            "known": derive::PROTOCOLS.iter().any(|(p, _)| *p == protocol),
+            "derived_at": derived_at,
+        });
+        self.store.write_scroll(Scroll::new(&key, meta).set_type(paths::DERIVATION_TYPE))
+    }
+}
+
+impl Namespace for IdentityNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        Ok(Some(match path {
+            paths::STATUS | "" | "/" => self.read_status()?,
+            paths::DERIVATIONS => self.read_derivations()?,
+            _ => return Ok(None),
+        }))
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        match path {
+            paths::DERIVE => self.write_derive(data),
+            _ => Err(NineSError::Other(format!("unknown: {}", path))),
+        }
+    }
+
+    fn list(&self, _: &str) -> NineSResult<Vec<String>> {
+        Ok(vec![paths::STATUS.into(), paths::DERIVE.into(), paths::DERIVATIONS.into()])
+    }
+}
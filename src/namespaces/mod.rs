@@ -1 +1,3 @@
 pub mod auth;
+pub mod effects;
+pub mod identity;
@@ -0,0 +1,94 @@
+//! Browse - file-manager views over the path tree
+//!
+//! `tree` folds a listing into the entries `depth` levels under a prefix,
+//! each with how many scrolls sit below it, and pages through them by path.
+//! Only paths are touched, so a prefix with tens of thousands of scrolls is
+//! cheap. `meta` and `type_stats` read scrolls but return no data.
+
+use nine_s_core::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Entries per page unless the caller asks otherwise
+pub const DEFAULT_PAGE: usize = 500;
+
+/// One node of the tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeEntry {
+    pub path: String,
+    pub name: String,
+    /// A scroll lives at `path` itself
+    pub scroll: bool,
+    /// Scrolls anywhere below `path`
+    pub descendants: usize,
+}
+
+/// One page of a tree listing
+#[derive(Debug, Clone, Serialize)]
+pub struct TreePage {
+    pub prefix: String,
+    pub entries: Vec<TreeEntry>,
+    /// Pass back as `after` for the next page
+    pub next: Option<String>,
+}
+
+/// Entries up to `depth` levels below `prefix`, by path, after `after`
+pub fn tree(paths: &[String], prefix: &str, depth: usize, after: Option<&str>, limit: usize) -> TreePage {
+    let prefix = format!("/{}", prefix.trim_matches('/'));
+    let base: Vec<&str> = prefix.split('/').filter(|s| !s.is_empty()).collect();
+    let mut entries: BTreeMap<String, TreeEntry> = BTreeMap::new();
+    for path in paths {
+        let segs: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segs.len() <= base.len() || segs[..base.len()] != base[..] { continue; }
+        let rel = &segs[base.len()..];
+        for level in 1..=rel.len().min(depth.max(1)) {
+            let at = format!("/{}", segs[..base.len() + level].join("/"));
+            let entry = entries.entry(at.clone()).or_insert_with(|| TreeEntry { path: at, name: rel[level - 1].into(), scroll: false, descendants: 0 });
+            if level == rel.len() { entry.scroll = true; } else { entry.descendants += 1; }
+        }
+    }
+    let mut rest = entries.into_values().filter(|e| after.map_or(true, |a| e.path.as_str() > a));
+    let entries: Vec<TreeEntry> = rest.by_ref().take(limit.max(1)).collect();
+    let next = rest.next().and(entries.last().map(|e| e.path.clone()));
+    TreePage { prefix, entries, next }
+}
+
+/// A scroll without its data
+pub fn meta(scroll: &Scroll) -> Value {
+    json!({
+        "key": scroll.key,
+        "type": scroll.type_,
+        "version": scroll.metadata.version,
+        "created_at": scroll.metadata.created_at,
+        "updated_at": scroll.metadata.updated_at,
+        "produced_by": scroll.metadata.produced_by,
+        "size": scroll.data.to_string().len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_levels_and_paging() {
+        let paths: Vec<String> = ["/notes", "/notes/a", "/notes/b/1", "/notes/b/2", "/notesx/c"].iter().map(|s| s.to_string()).collect();
+        let page = tree(&paths, "/notes", 1, None, 10);
+        assert_eq!(page.entries, vec![
+            TreeEntry { path: "/notes/a".into(), name: "a".into(), scroll: true, descendants: 0 },
+            TreeEntry { path: "/notes/b".into(), name: "b".into(), scroll: false, descendants: 2 },
+        ]);
+        assert_eq!(page.next, None);
+
+        let deep = tree(&paths, "/", 2, None, 3);
+        let listed: Vec<&str> = deep.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(listed, vec!["/notes", "/notes/a", "/notes/b"]);
+        assert_eq!(deep.entries[0].descendants, 3);
+        assert_eq!(deep.next.as_deref(), Some("/notes/b"));
+        let rest = tree(&paths, "/", 2, deep.next.as_deref(), 3);
+        let listed: Vec<&str> = rest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(listed, vec!["/notesx", "/notesx/c"]);
+        assert_eq!(rest.next, None);
+    }
+}
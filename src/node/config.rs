@@ -1,8 +1,17 @@
 //! Node Configuration - passed from higher layers
 
+use super::{FollowConfig, Migration};
 use crate::core::pattern::PatternDef;
+use crate::core::proxy::Proxy;
+use crate::secret::Secret;
+use nine_s_core::errors::NineSResult;
+use nine_s_core::namespace::Namespace;
+use std::path::PathBuf;
+use std::sync::Arc;
 #[cfg(feature = "wallet")]
-use crate::wallet::Network;
+use crate::wallet::{AddressReuse, CoinSelection, ElectrumOptions, Network, DEFAULT_LOOKAHEAD};
+#[cfg(feature = "wallet")]
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuthMode {
@@ -31,33 +40,236 @@ impl AuthMode {
     }
 }
 
+/// Read-only prefixes served while the node is locked, so a UI can render
+/// before unlock. `/nostr/*` entries resolve once the namespace has been
+/// mounted by a first unlock.
+pub const DEFAULT_PUBLIC_PREFIXES: &[&str] = &["/sys/clock", "/sys/status", "/sys/capabilities", "/nostr/pubkey", "/nostr/mobi"];
+
+/// What `NodeConfig::lock_sensitive_only` keeps behind the PIN: spending,
+/// signing and key-derived namespaces
+pub const SENSITIVE_PREFIXES: &[&str] = &[
+    "/wallet", "/external/bitcoin", "/sys/recurring",
+    "/nostr/sign", "/nostr/publish", "/nostr/dm", "/nostr/bunker", "/external/nostr",
+    "/identity", "/ssh", "/crypto", "/sys/plugins",
+];
+
+/// Mount points `with_namespace` can't take
+pub const BUILTIN_MOUNTS: &[&str] = &["/system/auth", "/sys", "/wallet", "/nostr", "/identity", "/ssh", "/crypto", "/external", "/archive"];
+
+/// Builds an embedder's namespace from the node's store. Called at startup
+/// and again whenever the node rebuilds its shell (a duress PIN opening the
+/// decoy, the real PIN coming back), so each shell gets its own instance.
+#[derive(Clone)]
+pub struct NamespaceFactory(Arc<dyn Fn(nine_s_store::Store) -> NineSResult<Box<dyn Namespace>> + Send + Sync>);
+
+impl NamespaceFactory {
+    pub fn build(&self, store: nine_s_store::Store) -> NineSResult<Box<dyn Namespace>> { (self.0)(store) }
+}
+
+impl std::fmt::Debug for NamespaceFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str("NamespaceFactory") }
+}
+
 /// Node configuration. Higher layers construct this.
 #[derive(Debug, Clone, Default)]
 pub struct NodeConfig {
     pub app: String,
     pub master_key: Vec<u8>,
-    pub mnemonic: Option<String>,
+    pub mnemonic: Option<Secret<String>>,
     pub auth_mode: AuthMode,
     #[cfg(feature = "wallet")]
     pub wallet: Option<WalletConfig>,
+    /// Named wallets mounted at `/wallet/{name}`, each on its own BIP84 account
+    #[cfg(feature = "wallet")]
+    pub wallets: BTreeMap<String, WalletConfig>,
     #[cfg(feature = "nostr")]
     pub nostr: Option<NostrConfig>,
+    /// Bucket mounted at `/archive` once the node has a seed
+    #[cfg(feature = "s3")]
+    pub archive: Option<crate::archive::ArchiveConfig>,
     pub enable_mind: bool,
     pub patterns: Vec<PatternDef>,
+    /// Prefixes sealed with a PIN-derived key (e.g. `/journal/**`)
+    pub encrypted_prefixes: Vec<String>,
+    /// Scroll format migrations, applied once at startup in order
+    pub migrations: Vec<Migration>,
+    /// Prefixes indexed for `node.search`
+    #[cfg(feature = "search")]
+    pub search_prefixes: Vec<String>,
+    /// Run approved plugins from `/sys/plugins` (see `crate::plugins`)
+    #[cfg(feature = "plugins")]
+    pub plugins: bool,
+    /// Fail node creation when a backend (wallet, nostr) can't start,
+    /// instead of running degraded with an alert under `/sys/alerts`
+    pub strict: bool,
+    /// Keep a change log and serve it from `GET /replicate`
+    pub replication: bool,
+    /// Record every write in `/sys/journal` (see `Node::journal`)
+    pub journal: bool,
+    /// Read every scroll at startup and quarantine the corrupt ones under
+    /// `/sys/corrupt` (report at `/sys/store/health`)
+    pub verify_on_open: bool,
+    /// Run as a read-only follower of another node
+    pub follow: Option<FollowConfig>,
+    pub http: HttpConfig,
+    /// Readable while locked (see `DEFAULT_PUBLIC_PREFIXES`); never writable
+    pub public_prefixes: Vec<String>,
+    /// Prefixes the lock covers; empty locks everything
+    pub lock_prefixes: Vec<String>,
+    /// Data root for the store, auth file and wallet; `None` uses `NINE_S_ROOT`
+    pub root: Option<PathBuf>,
+    /// Auth file outside the root
+    pub auth_file: Option<PathBuf>,
+    /// SOCKS5 proxy for Electrum, RPC, Nostr relays and price fetches
+    pub proxy: Option<String>,
+    /// Refuse to open a mainnet wallet without `proxy`
+    pub require_tor: bool,
+    /// Embedder namespaces, mounted in order after the built-in ones
+    pub namespaces: Vec<(String, NamespaceFactory)>,
+    /// Sign scrolls written through `put` with the node's Nostr key
+    /// (`identity::provenance`) unless they already name a producer
+    pub sign_writes: bool,
 }
 
 impl NodeConfig {
     pub fn new(app: impl Into<String>) -> Self {
-        Self { app: app.into(), ..Default::default() }
+        let public_prefixes = DEFAULT_PUBLIC_PREFIXES.iter().map(|p| p.to_string()).collect();
+        Self { app: app.into(), public_prefixes, ..Default::default() }
     }
     pub fn with_master_key(mut self, key: Vec<u8>) -> Self { self.master_key = key; self }
-    pub fn with_mnemonic(mut self, m: impl Into<String>) -> Self { self.mnemonic = Some(m.into()); self }
+    pub fn with_mnemonic(mut self, m: impl Into<String>) -> Self { self.mnemonic = Some(Secret::new(m.into())); self }
     pub fn with_auth_mode(mut self, mode: AuthMode) -> Self { self.auth_mode = mode; self }
     #[cfg(feature = "wallet")]
     pub fn with_wallet(mut self, c: WalletConfig) -> Self { self.wallet = Some(c); self }
+    /// Mount each wallet at `/wallet/{name}`; give each a distinct `account`
+    #[cfg(feature = "wallet")]
+    pub fn with_wallets(mut self, wallets: BTreeMap<String, WalletConfig>) -> Self { self.wallets = wallets; self }
     #[cfg(feature = "nostr")]
     pub fn with_nostr(mut self, c: NostrConfig) -> Self { self.nostr = Some(c); self }
+    #[cfg(feature = "s3")]
+    pub fn with_archive(mut self, c: crate::archive::ArchiveConfig) -> Self { self.archive = Some(c); self }
     pub fn with_mind(mut self, patterns: Vec<PatternDef>) -> Self { self.enable_mind = true; self.patterns = patterns; self }
+    /// Seal scrolls under `prefix` with a PIN-derived key. Needs `AuthMode::Pin`;
+    /// `Node::from_config` refuses the config otherwise, and refuses prefixes
+    /// overlapping `BUILTIN_MOUNTS`, which the node's services write unsealed.
+    pub fn with_encrypted_prefix(mut self, prefix: impl Into<String>) -> Self { self.encrypted_prefixes.push(prefix.into()); self }
+    pub fn with_migration(mut self, m: Migration) -> Self { self.migrations.push(m); self }
+    pub fn strict(mut self) -> Self { self.strict = true; self }
+    pub fn with_replication(mut self) -> Self { self.replication = true; self }
+    pub fn with_journal(mut self) -> Self { self.journal = true; self }
+    pub fn with_verify_on_open(mut self) -> Self { self.verify_on_open = true; self }
+    pub fn with_follow(mut self, c: FollowConfig) -> Self { self.follow = Some(c); self }
+    pub fn with_http(mut self, c: HttpConfig) -> Self { self.http = c; self }
+    pub fn with_public_prefix(mut self, prefix: impl Into<String>) -> Self { self.public_prefixes.push(prefix.into()); self }
+    /// Nothing but `/system/auth` while locked
+    pub fn without_public_prefixes(mut self) -> Self { self.public_prefixes.clear(); self }
+    pub fn with_lock_prefix(mut self, prefix: impl Into<String>) -> Self { self.lock_prefixes.push(prefix.into()); self }
+    /// Lock only `SENSITIVE_PREFIXES`; other scrolls stay usable while locked
+    pub fn lock_sensitive_only(mut self) -> Self {
+        self.lock_prefixes = SENSITIVE_PREFIXES.iter().map(|p| p.to_string()).collect();
+        self
+    }
+    #[cfg(feature = "search")]
+    pub fn with_search(mut self, prefixes: Vec<String>) -> Self { self.search_prefixes = prefixes; self }
+    /// Start approved pattern plugins at startup and register approved
+    /// effect plugins when `start_services` runs with effects on
+    #[cfg(feature = "plugins")]
+    pub fn with_plugins(mut self) -> Self { self.plugins = true; self }
+    /// Keep everything under `path` instead of `NINE_S_ROOT`
+    pub fn with_root(mut self, path: impl Into<PathBuf>) -> Self { self.root = Some(path.into()); self }
+    pub fn with_auth_file(mut self, path: impl Into<PathBuf>) -> Self { self.auth_file = Some(path.into()); self }
+    /// Route outbound connections through `socks5://host:port` (see `core::proxy`)
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self { self.proxy = Some(url.into()); self }
+    pub fn require_tor_on_mainnet(mut self) -> Self { self.require_tor = true; self }
+    pub fn with_signed_writes(mut self) -> Self { self.sign_writes = true; self }
+    /// Mount a custom namespace at `path`. Reads and writes go through the
+    /// same lock, public-prefix and follower checks as every other path.
+    pub fn with_namespace<F>(mut self, path: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(nine_s_store::Store) -> NineSResult<Box<dyn Namespace>> + Send + Sync + 'static,
+    {
+        self.namespaces.push((path.into(), NamespaceFactory(Arc::new(factory))));
+        self
+    }
+
+    /// Parsed `proxy`; errors when it is malformed, or missing on a mainnet
+    /// wallet with `require_tor`
+    pub fn proxy(&self) -> NineSResult<Option<Proxy>> {
+        let proxy = self.proxy.as_deref().map(Proxy::parse).transpose()?;
+        #[cfg(feature = "wallet")]
+        if proxy.is_none() && self.require_tor && self.wallet.iter().chain(self.wallets.values()).any(|w| w.network == Network::Bitcoin) {
+            return Err(nine_s_core::errors::NineSError::Other("mainnet wallet requires a proxy (with_proxy)".into()));
+        }
+        Ok(proxy)
+    }
+
+    /// Resolved data root
+    pub fn data_root(&self) -> PathBuf { super::root::resolve(self.root.as_deref()) }
+
+    pub fn auth_path(&self) -> PathBuf {
+        self.auth_file.clone().unwrap_or_else(|| crate::auth::path_in(&self.data_root(), &self.app))
+    }
+
+    /// This app's store under the configured root
+    pub fn open_store(&self) -> NineSResult<nine_s_store::Store> {
+        super::root::scoped(self.root.as_deref(), || nine_s_store::Store::open(&self.app, &self.master_key))
+    }
+
+    pub(crate) fn open_shell(&self) -> NineSResult<nine_s_shell::Shell> {
+        super::root::scoped(self.root.as_deref(), || nine_s_shell::Shell::open(&self.app, &self.master_key))
+    }
+
+    /// Keychain holding the seed, opened under the configured root
+    #[cfg(feature = "wallet")]
+    pub(crate) fn open_keychain(&self) -> NineSResult<nine_s_store::PersistentKeychain> {
+        super::root::scoped(self.root.as_deref(), || nine_s_store::PersistentKeychain::new().map_err(Into::into))
+    }
+
+    /// `WalletConfig::data_dir`, else `{root}/{app}`, plus `wallet.sqlite`
+    #[cfg(feature = "wallet")]
+    pub fn wallet_db_path(&self) -> Option<PathBuf> {
+        let wallet = self.wallet.as_ref()?;
+        let dir = wallet.data_dir.clone().unwrap_or_else(|| self.data_root().join(&self.app));
+        Some(dir.join("wallet.sqlite"))
+    }
+
+    /// Like `wallet_db_path` for a named wallet: `wallet-{name}.sqlite`
+    #[cfg(feature = "wallet")]
+    pub fn named_wallet_db_path(&self, name: &str) -> Option<PathBuf> {
+        let wallet = self.wallets.get(name)?;
+        let dir = wallet.data_dir.clone().unwrap_or_else(|| self.data_root().join(&self.app));
+        Some(dir.join(format!("wallet-{}.sqlite", name)))
+    }
+
+    /// Store for a named wallet's pending sends, invoices, coin labels and
+    /// scan settings, kept apart so wallets never see each other's records
+    #[cfg(feature = "wallet")]
+    pub fn open_wallet_store(&self, name: &str) -> NineSResult<nine_s_store::Store> {
+        super::root::scoped(self.root.as_deref(), || nine_s_store::Store::open(&format!("{}.wallet-{}", self.app, name), &self.master_key))
+    }
+
+    /// Named wallets are `[a-z0-9_-]`, don't shadow a `/wallet` path, and
+    /// no two wallets on one network share an account
+    #[cfg(feature = "wallet")]
+    pub(crate) fn check_wallets(&self) -> NineSResult<()> {
+        use nine_s_core::errors::NineSError;
+        let mut accounts = self.wallet.iter().map(|w| (w.network, w.account.unwrap_or(0), "/wallet".to_string())).collect::<Vec<_>>();
+        for (name, w) in &self.wallets {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+                return Err(NineSError::Other(format!("wallet name {:?}: use a-z, 0-9, - and _", name)));
+            }
+            if crate::core::paths::wallet::RESERVED.contains(&name.as_str()) {
+                return Err(NineSError::Other(format!("wallet name {:?} is a /wallet path", name)));
+            }
+            let path = format!("/wallet/{}", name);
+            let account = w.account.unwrap_or(0);
+            if let Some((_, _, other)) = accounts.iter().find(|(n, a, _)| *n == w.network && *a == account) {
+                return Err(NineSError::Other(format!("{} and {} both use account {}", other, path, account)));
+            }
+            accounts.push((w.network, account, path));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "wallet")]
@@ -65,7 +277,31 @@ impl NodeConfig {
 pub struct WalletConfig {
     pub network: Network,
     pub electrum_url: Option<String>,
+    /// Tried in order after `electrum_url` when it stops answering
+    pub electrum_fallbacks: Vec<String>,
+    /// Electrum connect and read timeout
+    pub electrum_timeout_secs: u8,
+    /// First block that can hold wallet history; `None` keeps the stored value
+    pub birthday_height: Option<u32>,
+    /// Unused addresses a full scan checks past the last used one; `None`
+    /// keeps the stored value, else `DEFAULT_STOP_GAP`
+    pub stop_gap: Option<usize>,
+    /// Addresses derived and watched past the last revealed one per keychain
+    pub lookahead: u32,
+    /// BIP84 `account'` index; `None` opens the account the last
+    /// `/wallet/account` migration moved to, else 0
+    pub account: Option<u32>,
     pub data_dir: Option<std::path::PathBuf>,
+    /// Confirmations before a pending send moves to `/wallet/events/confirmed`
+    pub confirmations: u32,
+    /// Add `fiat: {usd, eur}` to `/wallet/balance` from `/market/price/*`
+    pub fiat: bool,
+    /// Build and sign sends but never broadcast them
+    pub simulate: bool,
+    /// Input selection for sends that don't name one
+    pub coin_selection: CoinSelection,
+    /// Warn about (default) or refuse reusing an address with history
+    pub address_reuse: AddressReuse,
     /// Bitcoin RPC config (for regtest/Polar testing)
     #[cfg(feature = "bitcoind-rpc")]
     pub rpc: Option<RpcConfig>,
@@ -77,7 +313,18 @@ impl Default for WalletConfig {
         Self {
             network: Network::default(),
             electrum_url: None,
+            electrum_fallbacks: vec![],
+            electrum_timeout_secs: ElectrumOptions::default().timeout_secs,
+            birthday_height: None,
+            stop_gap: None,
+            lookahead: DEFAULT_LOOKAHEAD,
+            account: None,
             data_dir: None,
+            confirmations: 6,
+            fiat: false,
+            simulate: false,
+            coin_selection: CoinSelection::default(),
+            address_reuse: AddressReuse::default(),
             #[cfg(feature = "bitcoind-rpc")]
             rpc: None,
         }
@@ -95,10 +342,29 @@ pub struct RpcConfig {
 
 #[cfg(feature = "wallet")]
 impl WalletConfig {
-    pub fn mainnet() -> Self { Self { network: Network::Bitcoin, electrum_url: None, data_dir: None, #[cfg(feature = "bitcoind-rpc")] rpc: None } }
-    pub fn testnet() -> Self { Self { network: Network::Testnet, electrum_url: None, data_dir: None, #[cfg(feature = "bitcoind-rpc")] rpc: None } }
+    pub fn mainnet() -> Self { Self { network: Network::Bitcoin, ..Default::default() } }
+    pub fn testnet() -> Self { Self { network: Network::Testnet, ..Default::default() } }
     pub fn with_electrum(mut self, url: impl Into<String>) -> Self { self.electrum_url = Some(url.into()); self }
+    pub fn with_electrum_fallback(mut self, url: impl Into<String>) -> Self { self.electrum_fallbacks.push(url.into()); self }
+    pub fn with_electrum_timeout(mut self, secs: u8) -> Self { self.electrum_timeout_secs = secs; self }
     pub fn with_data_dir(mut self, path: impl Into<std::path::PathBuf>) -> Self { self.data_dir = Some(path.into()); self }
+    pub fn with_birthday(mut self, height: u32) -> Self { self.birthday_height = Some(height); self }
+    pub fn with_stop_gap(mut self, gap: usize) -> Self { self.stop_gap = Some(gap); self }
+    pub fn with_lookahead(mut self, n: u32) -> Self { self.lookahead = n; self }
+    pub fn with_account(mut self, n: u32) -> Self { self.account = Some(n); self }
+    pub fn with_confirmations(mut self, n: u32) -> Self { self.confirmations = n; self }
+    pub fn with_fiat(mut self) -> Self { self.fiat = true; self }
+    pub fn with_simulate(mut self) -> Self { self.simulate = true; self }
+    pub fn with_coin_selection(mut self, selection: CoinSelection) -> Self { self.coin_selection = selection; self }
+    pub fn with_address_reuse(mut self, policy: AddressReuse) -> Self { self.address_reuse = policy; self }
+    pub fn electrum(&self) -> ElectrumOptions {
+        ElectrumOptions {
+            url: self.electrum_url.clone(),
+            fallbacks: self.electrum_fallbacks.clone(),
+            timeout_secs: self.electrum_timeout_secs,
+            ..Default::default()
+        }
+    }
     #[cfg(feature = "bitcoind-rpc")]
     pub fn with_rpc(mut self, url: impl Into<String>, user: impl Into<String>, pass: impl Into<String>) -> Self {
         self.rpc = Some(RpcConfig { url: url.into(), user: user.into(), pass: pass.into() });
@@ -106,6 +372,28 @@ impl WalletConfig {
     }
 }
 
+/// HTTP server options for `create_router_with_node`
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+    /// Allowed browser origins. Empty allows any origin.
+    pub cors_origins: Vec<String>,
+    /// Require `Authorization: Bearer {token}` on every route but `/health`
+    pub token: Option<String>,
+}
+
+impl HttpConfig {
+    /// `BEENODE_CORS_ORIGINS` (comma separated) and `BEENODE_API_TOKEN`
+    pub fn from_env() -> Self {
+        let cors_origins = std::env::var("BEENODE_CORS_ORIGINS")
+            .map(|s| s.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect())
+            .unwrap_or_default();
+        let token = std::env::var("BEENODE_API_TOKEN").ok().filter(|t| !t.is_empty());
+        Self { cors_origins, token }
+    }
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self { self.cors_origins.push(origin.into()); self }
+    pub fn with_token(mut self, token: impl Into<String>) -> Self { self.token = Some(token.into()); self }
+}
+
 #[cfg(feature = "nostr")]
 #[derive(Debug, Clone)]
 pub struct NostrConfig {
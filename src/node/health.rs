@@ -0,0 +1,88 @@
+//! Health - Integrity pass over the store at startup
+//!
+//! With `NodeConfig::verify_on_open` set, every scroll is read once before the
+//! node touches any of them. An entry that fails to load (truncated,
+//! unparsable, undecryptable) or comes back under another key is quarantined
+//! instead of failing the open: `/sys/corrupt{path}` records
+//! `{path, error, detected_at}` and the entry itself is replaced by a
+//! placeholder of type `sys/corrupt@v1` pointing there, so startup code that
+//! reads it gets a scroll rather than an error. What the entry held can't be
+//! read back through the store and is lost.
+//!
+//! The outcome is kept at `/sys/store/health`:
+//! `{ok, checked, corrupt, quarantined: [path], error?, checked_at, duration_ms}`,
+//! and any quarantine raises `/sys/alerts/store`. `error` is set only when
+//! the store can't even be listed; the node still opens.
+
+use crate::core::paths::{alerts, store_health as paths};
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Read every entry, quarantine the bad ones and write the report
+pub(crate) fn verify(store: &Store) -> NineSResult<Value> {
+    let started = std::time::Instant::now();
+    let (mut checked, mut quarantined) = (0, Vec::new());
+    let mut report = json!({"ok": true, "checked_at": now_secs()});
+
+    match store.list("/") {
+        Ok(keys) => {
+            for key in keys.into_iter().filter(|k| !k.starts_with(paths::CORRUPT_PREFIX) && k != paths::PATH) {
+                checked += 1;
+                let error = match store.read(&key) {
+                    Ok(Some(scroll)) if scroll.key != key => format!("entry holds {}", scroll.key),
+                    Ok(_) => continue,
+                    Err(e) => e.to_string(),
+                };
+                tracing::warn!("store: quarantining {}: {}", key, error);
+                quarantine(store, &key, &error)?;
+                quarantined.push(key);
+            }
+        }
+        Err(e) => {
+            tracing::warn!("store: integrity pass could not list entries: {}", e);
+            report["ok"] = json!(false);
+            report["error"] = json!(e.to_string());
+        }
+    }
+
+    if !quarantined.is_empty() {
+        report["ok"] = json!(false);
+        let alert = json!({"active": true, "level": "warning", "message": format!("{} corrupt store entries quarantined under {}", quarantined.len(), paths::CORRUPT_PREFIX)});
+        store.write_scroll(Scroll::new(&format!("{}/store", alerts::PREFIX), alert).set_type(alerts::TYPE))?;
+    }
+    report["checked"] = json!(checked);
+    report["corrupt"] = json!(quarantined.len());
+    report["quarantined"] = json!(quarantined);
+    report["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+    store.write_scroll(Scroll::new(paths::PATH, report.clone()).set_type(paths::TYPE))?;
+    Ok(report)
+}
+
+fn quarantine(store: &Store, key: &str, error: &str) -> NineSResult<()> {
+    let record = format!("{}{}", paths::CORRUPT_PREFIX, key);
+    store.write_scroll(Scroll::new(&record, json!({"path": key, "error": error, "detected_at": now_secs()})).set_type(paths::CORRUPT_TYPE))?;
+    store.write_scroll(Scroll::new(key, json!({"quarantined": record})).set_type(paths::CORRUPT_TYPE))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_reports_healthy_store() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-health").with_root(dir.path()).open_store().unwrap();
+        store.write_scroll(Scroll::new("/notes/1", json!({"text": "hi"}))).unwrap();
+
+        let report = verify(&store).unwrap();
+        assert_eq!((report["ok"].as_bool(), report["checked"].as_u64(), report["corrupt"].as_u64()), (Some(true), Some(1), Some(0)));
+        assert_eq!(store.read("/sys/store/health").unwrap().unwrap().data["checked"], 1);
+
+        quarantine(&store, "/notes/1", "truncated").unwrap();
+        assert_eq!(store.read("/sys/corrupt/notes/1").unwrap().unwrap().data["error"], "truncated");
+        assert_eq!(store.read("/notes/1").unwrap().unwrap().data["quarantined"], "/sys/corrupt/notes/1");
+    }
+}
@@ -0,0 +1,95 @@
+//! Journal - persistent, ordered record of every write
+//!
+//! With `NodeConfig::with_journal` each stored write appends
+//! `/sys/journal/entries/{seq}` = `{seq, path, version, hash, at}`, where
+//! `hash` is `identity::provenance::hash` of the scroll as written. Sequence
+//! numbers start at 1, never repeat and survive restarts (the next one is kept
+//! in `/sys/journal/state`). Unlike the replication ChangeLog nothing is
+//! compacted: two writes to a path are two entries. Writes made while the
+//! node is down aren't seen.
+//!
+//! Inbound writes (anything not produced by the Mind or the effect worker)
+//! also carry the scroll itself, so `Node::replay` can re-feed them; the
+//! derived writes are what a replay reproduces.
+//!
+//! `Node::journal(since, limit)` and `GET /journal?since=&limit=` page it;
+//! `Node::on_with_replay` replays from it when it's on.
+
+use crate::core::paths::{journal as paths, origin};
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Entries per page unless the caller asks otherwise
+pub const DEFAULT_PAGE: usize = 500;
+
+/// One recorded write
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub seq: u64,
+    pub path: String,
+    pub version: u64,
+    /// sha256 hex of `{key, type, data}`
+    pub hash: String,
+    pub at: u64,
+    /// The scroll as written, for inbound writes only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<Value>,
+}
+
+/// One page of the journal
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalPage {
+    /// Sequence of the last entry returned (pass back as `since`)
+    pub cursor: u64,
+    pub entries: Vec<Entry>,
+}
+
+/// Append an entry for every write to `store` from now on
+pub fn start(store: Store) -> NineSResult<()> {
+    let rx = store.watch(&WatchPattern::parse("/**")?)?;
+    let mut next = store.read(paths::STATE)?.and_then(|s| s.data["next"].as_u64()).unwrap_or(1);
+    std::thread::spawn(move || {
+        while let Ok(scroll) = rx.recv() {
+            if scroll.key.starts_with(paths::PREFIX) { continue; }
+            let entry = Entry {
+                seq: next,
+                path: scroll.key.clone(),
+                version: scroll.metadata.version,
+                hash: hex::encode(crate::identity::provenance::hash(&scroll)),
+                at: now_secs(),
+                scroll: if inbound(&scroll) { serde_json::to_value(&scroll).ok() } else { None },
+            };
+            let written = serde_json::to_value(&entry).map_err(|e| NineSError::Other(e.to_string()))
+                .and_then(|data| store.write_scroll(Scroll::new(&entry_key(next), data).set_type(paths::TYPE)))
+                .and_then(|_| store.write_scroll(Scroll::new(paths::STATE, json!({"next": next + 1})).set_type(paths::TYPE)));
+            match written {
+                Ok(_) => next += 1,
+                Err(e) => tracing::warn!("journal {}: {}", scroll.key, e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Up to `limit` entries after `since`, in order
+pub fn page(store: &Store, since: u64, limit: usize) -> NineSResult<JournalPage> {
+    let mut entries = Vec::new();
+    let mut cursor = since;
+    while entries.len() < limit.max(1) {
+        let Some(scroll) = store.read(&entry_key(cursor + 1))? else { break };
+        let entry: Entry = serde_json::from_value(scroll.data).map_err(|e| NineSError::Other(format!("journal {}: {}", cursor + 1, e)))?;
+        cursor = entry.seq;
+        entries.push(entry);
+    }
+    Ok(JournalPage { cursor, entries })
+}
+
+/// Not a reaction the Mind or an effect handler would reproduce
+fn inbound(scroll: &Scroll) -> bool {
+    !matches!(scroll.metadata.produced_by.as_deref(), Some(origin::MIND) | Some(origin::EFFECTS))
+}
+
+fn entry_key(seq: u64) -> String { format!("{}/{}", paths::ENTRIES, seq) }
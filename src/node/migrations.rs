@@ -0,0 +1,68 @@
+//! Migrations - one-shot scroll format upgrades at node startup
+//!
+//! Each migration rewrites scrolls of `from` type under `prefix` to the `to`
+//! type. Applied migrations are recorded at `/sys/migrations/{id}` and never
+//! run again.
+
+use crate::core::paths::migrations as paths;
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_shell::Shell;
+use serde_json::{json, Value};
+
+/// A versioned scroll transform, registered with `NodeConfig::with_migration`
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Unique, stable id (e.g. `2024-06-notes-v2`)
+    pub id: String,
+    /// Prefix to scan (e.g. `/notes`)
+    pub prefix: String,
+    /// Source type (e.g. `app/note@v1`)
+    pub from: String,
+    /// Target type (e.g. `app/note@v2`)
+    pub to: String,
+    /// Data transform
+    pub transform: fn(Value) -> NineSResult<Value>,
+}
+
+impl Migration {
+    pub fn new(
+        id: impl Into<String>,
+        prefix: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        transform: fn(Value) -> NineSResult<Value>,
+    ) -> Self {
+        Self { id: id.into(), prefix: prefix.into(), from: from.into(), to: to.into(), transform }
+    }
+}
+
+/// Run pending migrations in order. Returns ids applied in this pass.
+pub fn run(shell: &Shell, migrations: &[Migration]) -> NineSResult<Vec<String>> {
+    let mut applied = Vec::new();
+    for m in migrations {
+        let record = format!("{}/{}", paths::PREFIX, m.id);
+        if shell.get(&record)?.is_some() { continue; }
+
+        let mut migrated = 0u64;
+        for key in shell.all(&m.prefix)? {
+            let Some(scroll) = shell.get(&key)? else { continue };
+            if scroll.type_ != m.from { continue; }
+            let data = (m.transform)(scroll.data)
+                .map_err(|e| NineSError::Other(format!("migration {} failed at {}: {}", m.id, key, e)))?;
+            shell.put_scroll(Scroll { key, type_: m.to.clone(), metadata: scroll.metadata, data })?;
+            migrated += 1;
+        }
+
+        shell.put_scroll(Scroll::new(&record, json!({
+            "id": m.id,
+            "prefix": m.prefix,
+            "from": m.from,
+            "to": m.to,
+            "migrated": migrated,
+            "applied_at": now_secs(),
+        })).set_type(paths::TYPE))?;
+        applied.push(m.id.clone());
+    }
+    Ok(applied)
+}
@@ -3,29 +3,65 @@
 //! BIP39 seed used directly for BIP84 wallet (standard derivation).
 //! HKDF-derived seeds used for other protocols (Nostr, etc).
 
+pub(crate) mod browse;
 mod config;
+mod health;
+pub(crate) mod journal;
+mod migrations;
+pub(crate) mod replication;
+mod recurring;
+mod replay;
+pub(crate) mod root;
+mod rotation;
+pub(crate) mod sealed;
+mod services;
+mod status;
+mod views;
+pub(crate) mod watch;
 
 pub use config::NodeConfig;
 pub use config::AuthMode;
+pub use config::HttpConfig;
+pub use config::{NamespaceFactory, BUILTIN_MOUNTS, DEFAULT_PUBLIC_PREFIXES, SENSITIVE_PREFIXES};
+pub use browse::{TreeEntry, TreePage};
+pub use journal::{Entry as JournalEntry, JournalPage};
+pub use migrations::Migration;
+pub use replication::{Changes, FollowConfig};
+pub use rotation::{rekey, Rotation};
+pub use services::{NodeRuntime, ServicesConfig};
 #[cfg(feature = "nostr")]
 pub use config::NostrConfig;
 #[cfg(feature = "wallet")]
 pub use config::WalletConfig;
 
 use crate::auth::PinAuth;
+use crate::core::api::NodeApi;
+use crate::core::paths::alerts;
+use crate::core::paths::ABSENT_TYPE;
+use crate::core::time::now_secs;
 use crate::identity::Identity;
 use crate::namespaces::auth::{AuthController, AuthNamespace, AuthStatus};
+use crate::namespaces::effects::EffectsNamespace;
+use crate::secret::Secret;
 use nine_s_core::prelude::*;
 use nine_s_shell::Shell;
 use serde_json::Value;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 
 #[cfg(feature = "wallet")]
-use nine_s_store::{Keychain, PersistentKeychain, Protocol};
+use nine_s_store::{Keychain, Protocol};
 
 /// Node wraps Shell with identity, wallet, and nostr namespaces.
+///
+/// Verbs share a read lock on the inner state, so concurrent reads (and
+/// namespace writes such as a slow wallet sync) don't queue behind each other.
+/// Unlock, lock, rotation and `put_batch` take it exclusively, so no other
+/// write lands between a batch's writes or its rollback.
 pub struct Node {
-    inner: Arc<Mutex<NodeInner>>,
+    inner: Arc<RwLock<NodeInner>>,
+    #[cfg(feature = "search")]
+    search: Arc<RwLock<crate::search::SearchIndex>>,
+    replication: Option<(Arc<RwLock<replication::ChangeLog>>, nine_s_store::Store)>,
 }
 
 struct NodeInner {
@@ -36,25 +72,58 @@ struct NodeInner {
     auth_initialized: bool,
     locked: bool,
     auth_mode: AuthMode,
+    sealed_prefixes: Vec<String>,
+    /// Normalized `NodeConfig::public_prefixes`
+    public_prefixes: Vec<String>,
+    /// Normalized `NodeConfig::lock_prefixes`
+    lock_prefixes: Vec<String>,
+    degraded: Vec<String>,
+    views: views::Watchers,
+    scrolls: status::ScrollCounts,
+    prefix_key: Option<nine_s_store::crypto::DerivedKey>,
+    /// Remounted on `/system/auth` whenever the shell is swapped
+    controller: Option<AuthController>,
+    /// The real config, stashed while a duress PIN has the decoy open
+    real_config: Option<NodeConfig>,
+    started: std::time::Instant,
     #[cfg(feature = "wallet")]
     wallet_mounted: bool,
+    /// `/wallet`, kept across unlocks so the effect handler has one wallet to run against
+    #[cfg(feature = "wallet")]
+    wallet: Option<crate::wallet::LazyWallet>,
 }
 
 impl Node {
     /// Create Node from config. Keychain handles seed, derives protocol seeds.
     pub fn from_config(config: NodeConfig) -> NineSResult<Self> {
-        let shell = Shell::open(&config.app, &config.master_key)?;
+        config.proxy()?;
+        if config.auth_mode == AuthMode::None && !config.encrypted_prefixes.is_empty() {
+            return Err(NineSError::Other("encrypted prefixes need AuthMode::Pin: their key is derived from the PIN".into()));
+        }
+        for prefix in &config.encrypted_prefixes {
+            if let Some(root) = sealed::reserved(&sealed::normalize(prefix), BUILTIN_MOUNTS) {
+                return Err(NineSError::Other(format!("encrypted prefix {} overlaps {}, which the node writes without the PIN", prefix, root)));
+            }
+        }
+        if config.verify_on_open { health::verify(&config.open_store()?)?; }
+        let shell = config.open_shell()?;
+        migrations::run(&shell, &config.migrations)?;
         let auth_mode = config.auth_mode;
         let (auth, auth_initialized, locked) = match auth_mode {
             AuthMode::Pin => {
-                let auth = PinAuth::load(&config.app)?;
+                let auth = PinAuth::open(config.auth_path())?;
                 let auth_initialized = auth.is_initialized();
                 (Some(auth), auth_initialized, auth_initialized)
             }
             AuthMode::None => (None, false, false),
         };
 
-        let inner = Arc::new(Mutex::new(NodeInner {
+        let sealed_prefixes = config.encrypted_prefixes.iter().map(|p| sealed::normalize(p)).collect();
+        let public_prefixes = config.public_prefixes.iter().map(|p| sealed::normalize(p)).collect();
+        let lock_prefixes = config.lock_prefixes.iter().map(|p| sealed::normalize(p)).collect();
+        #[cfg(feature = "wallet")]
+        let wallet = config.wallet.as_ref().map(|w| crate::wallet::LazyWallet::new(w.network));
+        let inner = Arc::new(RwLock::new(NodeInner {
             shell,
             identity: None,
             config,
@@ -62,139 +131,621 @@ impl Node {
             auth_initialized,
             locked,
             auth_mode,
+            sealed_prefixes,
+            public_prefixes,
+            lock_prefixes,
+            degraded: Vec::new(),
+            views: views::Watchers::default(),
+            scrolls: status::ScrollCounts::default(),
+            prefix_key: None,
+            controller: None,
+            real_config: None,
+            started: std::time::Instant::now(),
             #[cfg(feature = "wallet")]
             wallet_mounted: false,
+            #[cfg(feature = "wallet")]
+            wallet,
         }));
 
         let controller = Self::auth_controller(inner.clone());
         {
             let mut guard = inner
-                .lock()
+                .write()
                 .map_err(|_| NineSError::Other("node lock".into()))?;
-            guard.shell.mount("/system/auth", Box::new(AuthNamespace::new(controller)))?;
+            guard.controller = Some(controller);
+            guard.mount_base()?;
         }
 
         {
             let mut guard = inner
-                .lock()
+                .write()
                 .map_err(|_| NineSError::Other("node lock".into()))?;
             if !guard.locked {
-                if let Some(ref mnemonic) = guard.config.mnemonic.clone() {
-                    guard.initialize_with_mnemonic(mnemonic)?;
+                if let Some(mnemonic) = guard.config.mnemonic.clone() {
+                    guard.initialize_with_mnemonic(&mnemonic)?;
                 }
             }
         }
 
-        Ok(Self { inner })
+        {
+            let guard = inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+            for name in views::materialized(&guard.shell)? {
+                views::watch(guard.config.open_store()?, name, &guard.views)?;
+            }
+            let owner = format!("{}/{}", guard.config.data_root().display(), guard.config.app);
+            if let Err(e) = crate::logging::attach(guard.config.open_store()?, &owner) {
+                // info, not warn: a warning would land in the owning node's logs
+                tracing::info!("{}: not mirroring logs: {}", owner, e);
+            }
+            if guard.config.follow.is_none() {
+                recurring::start(guard.config.open_store()?)?;
+                #[cfg(feature = "nostr")]
+                if guard.config.nostr.is_some() {
+                    crate::nostr::contacts::watch(guard.config.open_store()?)?;
+                    crate::nostr::peers::watch(guard.config.open_store()?)?;
+                }
+            }
+        }
+
+        let replication = {
+            let guard = inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+            let open = || guard.config.open_store();
+            if let Some(ref follow) = guard.config.follow {
+                replication::follow(open()?, follow.clone());
+            }
+            if guard.config.journal { journal::start(open()?)?; }
+            #[cfg(feature = "plugins")]
+            if guard.config.plugins && guard.config.follow.is_none() {
+                crate::plugins::start(open()?).map_err(|e| NineSError::Other(e.to_string()))?;
+            }
+            if guard.config.replication { Some((replication::start(open()?)?, open()?)) } else { None }
+        };
+
+        #[cfg(feature = "search")]
+        let search = {
+            let guard = inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+            guard.start_search_index()?
+        };
+
+        Ok(Self {
+            inner,
+            #[cfg(feature = "search")]
+            search,
+            replication,
+        })
     }
 
     // Five verbs
     pub fn get(&self, path: &str) -> NineSResult<Option<Scroll>> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(path)?;
-        guard.shell.get(path)
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        if path == crate::core::paths::status::PATH { guard.check_readable(path)?; return guard.status().map(Some); }
+        if path == crate::core::paths::capabilities::PATH { guard.check_readable(path)?; return Ok(Some(guard.capabilities())); }
+        guard.read_checked(path)
     }
     pub fn put(&self, path: &str, data: Value) -> NineSResult<Scroll> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let key = guard.sealed_key(path)?;
         guard.check_locked(path)?;
-        guard.shell.put(path, data)
+        guard.check_writable(path)?;
+        let data = guard.resolve_encrypt(path, data)?;
+        if guard.config.sign_writes && guard.identity.is_some() {
+            drop(guard);
+            return self.put_scroll(Scroll::new(path, data));
+        }
+        match key {
+            Some(key) => {
+                let stored = guard.shell.put(path, sealed::seal(key, path, &data)?)?;
+                Ok(Scroll { data, ..stored })
+            }
+            None => guard.shell.put(path, data),
+        }
     }
     pub fn put_scroll(&self, scroll: Scroll) -> NineSResult<Scroll> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let key = guard.sealed_key(&scroll.key)?;
         guard.check_locked(&scroll.key)?;
-        guard.shell.put_scroll(scroll)
+        guard.check_writable(&scroll.key)?;
+        let scroll = Scroll { data: guard.resolve_encrypt(&scroll.key, scroll.data.clone())?, ..scroll };
+        let scroll = guard.merge_crdt(scroll, key)?;
+        let scroll = guard.sign(scroll)?;
+        match key {
+            Some(key) => {
+                let data = scroll.data;
+                let sealed_data = sealed::seal(key, &scroll.key, &data)?;
+                let stored = guard.shell.put_scroll(Scroll { data: sealed_data, ..scroll })?;
+                Ok(Scroll { data, ..stored })
+            }
+            None => guard.shell.put_scroll(scroll),
+        }
+    }
+
+    /// Write several paths under the exclusive lock. Every path is checked,
+    /// then merged, signed and sealed as `put_scroll` would, before the first
+    /// write; if a write still fails, earlier writes in the batch are
+    /// restored to their previous scroll, and paths the batch created are
+    /// marked absent (`get`/`exists` see nothing; the store has no delete, so
+    /// the key stays listed until it is written again).
+    pub fn put_batch(&self, puts: Vec<(String, Value)>) -> NineSResult<Vec<Scroll>> {
+        let guard = self.inner.write().map_err(|_| NineSError::Other("node lock".into()))?;
+        let mut staged = Vec::with_capacity(puts.len());
+        for (path, data) in puts {
+            guard.check_locked(&path)?;
+            guard.check_writable(&path)?;
+            let key = guard.sealed_key(&path)?;
+            let data = guard.resolve_encrypt(&path, data)?;
+            let scroll = guard.merge_crdt(Scroll::new(&path, data), key)?;
+            let scroll = guard.sign(scroll)?;
+            let sealed = match key {
+                Some(key) => Some(sealed::seal(key, &path, &scroll.data)?),
+                None => None,
+            };
+            staged.push((scroll, sealed));
+        }
+        let mut written: Vec<(String, Option<Scroll>)> = Vec::new();
+        let mut out = Vec::with_capacity(staged.len());
+        for (scroll, sealed) in staged {
+            let path = scroll.key.clone();
+            let previous = guard.shell.get(&path)?;
+            let result = match sealed {
+                Some(sealed) => {
+                    let data = scroll.data.clone();
+                    guard.shell.put_scroll(Scroll { data: sealed, ..scroll }).map(|stored| Scroll { data, ..stored })
+                }
+                None => guard.shell.put_scroll(scroll),
+            };
+            match result {
+                Ok(scroll) => { written.push((path, previous)); out.push(scroll); }
+                Err(e) => {
+                    for (path, previous) in written.into_iter().rev() {
+                        let restored = match previous {
+                            Some(prev) => guard.shell.put_scroll(prev),
+                            None => guard.shell.put_scroll(Scroll::new(&path, Value::Null).set_type(ABSENT_TYPE)),
+                        };
+                        if let Err(re) = restored { tracing::warn!("batch rollback {}: {}", path, re); }
+                    }
+                    return Err(NineSError::Other(format!("batch put {}: {}", path, e)));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Write an effect request and block until its `{path}/result` lands.
+    /// Returns the result scroll (`{success, result | error}`).
+    pub fn execute_effect(&self, path: &str, data: Value, timeout: std::time::Duration) -> NineSResult<Scroll> {
+        use crate::core::paths::mind;
+        if !path.starts_with(mind::EXTERNAL_PREFIX) {
+            return Err(NineSError::invalid_path(path, "effects live under /external"));
+        }
+        let result_path = format!("{}{}", path, mind::RESULT_SUFFIX);
+        // Subscribe before writing so a fast handler can't be missed
+        let rx = {
+            let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+            guard.check_locked(path)?;
+            guard.shell.on(&result_path)?
+        };
+        self.put(path, data)?;
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok(scroll) if scroll.key == result_path => return Ok(scroll),
+                Ok(_) => continue,
+                Err(_) => return Err(NineSError::Other(format!("effect timed out: {}", path))),
+            }
+        }
+    }
+    /// Typed get: decode scroll data as `T`, checking `type_`
+    pub fn get_as<T: crate::core::typed::ScrollType>(&self, path: &str) -> NineSResult<Option<T>> {
+        self.get(path)?.map(|s| T::from_scroll(&s)).transpose()
+    }
+    /// Typed put: serialize `value` and tag the scroll with `T::TYPE`
+    pub fn put_typed<T: crate::core::typed::ScrollType>(&self, path: &str, value: &T) -> NineSResult<Scroll> {
+        self.put_scroll(value.to_scroll(path)?)
     }
     pub fn all(&self, prefix: &str) -> NineSResult<Vec<String>> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(prefix)?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_readable(prefix)?;
         guard.shell.all(prefix)
     }
     pub fn on(&self, pattern: &str) -> NineSResult<nine_s_core::watch::WatchReceiver> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(pattern)?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_readable(pattern)?;
         guard.shell.on(pattern)
     }
+    /// `on` with delivery options: debounced windows, latest per path
+    pub fn on_with(&self, pattern: &str, options: crate::core::watch::WatchOptions) -> NineSResult<std::sync::mpsc::Receiver<Scroll>> {
+        let rx = self.on(pattern)?;
+        Ok(watch::deliver(move || rx.recv().ok(), options, Some))
+    }
+    /// `on` that first delivers what changed after `since`, then live writes.
+    /// `since` is a `journal` cursor when the journal is on, else a `changes`
+    /// cursor (needs `with_replication`). Replayed paths come once each, at
+    /// their current version; the live side skips anything already delivered.
+    pub fn on_with_replay(&self, pattern: &str, since: u64) -> NineSResult<std::sync::mpsc::Receiver<Scroll>> {
+        let matcher = WatchPattern::parse(pattern)?;
+        let rx = self.on(pattern)?;
+        let (tx, out) = std::sync::mpsc::channel();
+        let mut replayed = std::collections::HashMap::new();
+        for scroll in self.missed(since)?.into_iter().filter(|s| matcher.matches(&s.key)) {
+            replayed.insert(scroll.key.clone(), scroll.metadata.version);
+            let _ = tx.send(scroll);
+        }
+        std::thread::spawn(move || {
+            while let Ok(scroll) = rx.recv() {
+                if replayed.get(&scroll.key).is_some_and(|v| *v >= scroll.metadata.version) { continue; }
+                if tx.send(scroll).is_err() { break; }
+            }
+        });
+        Ok(out)
+    }
+
+    /// Current scrolls for every path written after `since`, oldest change first
+    fn missed(&self, since: u64) -> NineSResult<Vec<Scroll>> {
+        let mut scrolls = Vec::new();
+        let mut cursor = since;
+        if self.journal_enabled()? {
+            // Each path once, at its last entry
+            let mut last: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+            loop {
+                let page = self.journal(cursor, journal::DEFAULT_PAGE)?;
+                for entry in page.entries { last.insert(entry.path, entry.seq); }
+                if page.cursor == cursor { break; }
+                cursor = page.cursor;
+            }
+            let mut paths: Vec<(String, u64)> = last.into_iter().collect();
+            paths.sort_by_key(|(_, seq)| *seq);
+            for (path, _) in paths {
+                if let Some(scroll) = self.get(&path)? { scrolls.push(scroll); }
+            }
+            return Ok(scrolls);
+        }
+        loop {
+            let page = self.changes(cursor, None, replication::DEFAULT_BATCH)?;
+            scrolls.extend(page.scrolls);
+            if page.cursor == cursor { break; }
+            cursor = page.cursor;
+        }
+        Ok(scrolls)
+    }
+
+    /// `on` with `{name}` captures and `[predicate]` filters (`core::watch`).
+    /// Filtering happens here, so rejected writes never reach the receiver.
+    pub fn watch(&self, pattern: &str) -> NineSResult<std::sync::mpsc::Receiver<crate::core::watch::WatchEvent>> {
+        self.watch_with(pattern, Default::default())
+    }
+    pub fn watch_with(&self, pattern: &str, options: crate::core::watch::WatchOptions) -> NineSResult<std::sync::mpsc::Receiver<crate::core::watch::WatchEvent>> {
+        let watch = crate::core::watch::Watch::parse(pattern).map_err(|e| NineSError::invalid_path(pattern, &e.to_string()))?;
+        let rx = self.on(&watch.glob())?;
+        Ok(watch::deliver(move || rx.recv().ok(), options, move |scroll| watch.event(scroll)))
+    }
+    /// Evaluate the named view at `/sys/views/{name}`
+    pub fn view(&self, name: &str) -> NineSResult<Value> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let path = format!("{}/{}", crate::core::paths::views::PREFIX, name);
+        guard.check_readable(&path)?;
+        if let Some(source) = guard.shell.get(&path)?.map(|d| d.data["source"].as_str().unwrap_or("/").to_string()) {
+            guard.check_readable(&source)?;
+        }
+        views::evaluate(&guard.shell, name)
+    }
+
+    /// Persist a view definition; materialized views start refreshing immediately
+    pub fn define_view(&self, name: &str, dsl: &str, source: &str, materialize: bool) -> NineSResult<Scroll> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked(&format!("{}/{}", crate::core::paths::views::PREFIX, name))?;
+        guard.check_readable(source)?;
+        let scroll = guard.shell.put_scroll(views::definition(name, dsl, source, materialize)?)?;
+        if materialize {
+            views::watch(guard.config.open_store()?, name.to_string(), &guard.views)?;
+        } else {
+            guard.views.stop(name);
+        }
+        Ok(scroll)
+    }
+
+    /// Persist a recurring payment at `/sys/recurring/{name}`
+    pub fn define_recurring(&self, name: &str, data: Value) -> NineSResult<Scroll> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let path = format!("{}/{}", crate::core::paths::recurring::PREFIX, name);
+        guard.check_locked(&path)?;
+        guard.check_writable(&path)?;
+        guard.shell.put_scroll(recurring::definition(name, data)?)
+    }
+
+    /// Pause or resume a recurring payment
+    pub fn pause_recurring(&self, name: &str, paused: bool) -> NineSResult<Scroll> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let path = format!("{}/{}", crate::core::paths::recurring::PREFIX, name);
+        guard.check_locked(&path)?;
+        guard.check_writable(&path)?;
+        recurring::control(&guard.shell, name, "paused", Value::Bool(paused))
+    }
+
+    /// Pass over the next `count` due runs of a recurring payment
+    pub fn skip_recurring(&self, name: &str, count: u64) -> NineSResult<Scroll> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let path = format!("{}/{}", crate::core::paths::recurring::PREFIX, name);
+        guard.check_locked(&path)?;
+        guard.check_writable(&path)?;
+        recurring::control(&guard.shell, name, "skip", Value::from(count))
+    }
+
+    /// Full-text search over `NodeConfig::search_prefixes`
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str, limit: usize) -> NineSResult<Vec<crate::search::SearchHit>> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked("/search")?;
+        // The index covers the real store only
+        if guard.real_config.is_some() { return Ok(Vec::new()); }
+        drop(guard);
+        let index = self.search.read().map_err(|_| NineSError::Other("search lock".into()))?;
+        Ok(index.search(query, limit))
+    }
+    /// Another handle on this node's state, for services that call back into it
+    #[cfg_attr(not(feature = "nostr"), allow(dead_code))]
+    fn handle(&self) -> Node {
+        Node {
+            inner: self.inner.clone(),
+            #[cfg(feature = "search")]
+            search: self.search.clone(),
+            replication: self.replication.clone(),
+        }
+    }
+
+    /// Spawn and supervise the clock, Mind and effect worker (see `ServicesConfig`),
+    /// stopping them with `shutdown`. Followers get only the clock. Needs a
+    /// tokio runtime.
+    pub fn start_services(&self, mut config: ServicesConfig, shutdown: &crate::runtime::Shutdown) -> NineSResult<NodeRuntime> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked(crate::core::paths::services::PREFIX)?;
+        let store = guard.config.open_store()?;
+        config.sealed_prefixes = guard.sealed_prefixes.clone();
+        if guard.config.follow.is_some() {
+            config.mind = false;
+            config.effects = None;
+        }
+        // Built-in patterns that keep queues moving, on the first clock pulse they know
+        #[allow(unused_mut)]
+        let mut patterns = guard.config.patterns.clone();
+        #[cfg(feature = "nostr")]
+        if guard.config.nostr.is_some() && config.effects.is_some() {
+            if let Some(pulse) = config.pulse(&["sync", "minute"]) {
+                patterns.push(crate::nostr::outbox::drain_pattern(&pulse));
+            }
+            if let Some(pulse) = config.pulse(&["refresh", "hour"]) {
+                patterns.push(crate::nostr::archive::backfill_pattern(&pulse));
+            }
+        }
+        #[cfg(feature = "market")]
+        if config.effects.is_some() {
+            if let Some(pulse) = config.pulse(&["refresh", "hour"]) {
+                patterns.push(crate::market::refresh_pattern(&pulse));
+            }
+            let handler = crate::market::PriceEffectHandler::new(Arc::new(store.clone())).with_proxy(guard.config.proxy()?);
+            config.handlers.push((None, Box::new(handler)));
+        }
+        if config.mind {
+            for def in &patterns {
+                let data = serde_json::to_value(def).map_err(|e| NineSError::Other(format!("pattern {}: {}", def.name, e)))?;
+                store.write_scroll(Scroll::new(&format!("{}/{}", crate::core::paths::mind::PATTERNS_PREFIX, def.name), data))?;
+            }
+        }
+        #[cfg(feature = "nostr")]
+        if let (Some(nostr), None) = (guard.config.nostr.as_ref(), config.relays.as_ref()) {
+            let mut pool = crate::nostr::RelayPool::new(nostr.relays.clone())
+                .with_store(Arc::new(store.clone()))
+                .with_proxy(guard.config.proxy()?);
+            if let Some(identity) = guard.identity.as_ref() { pool = pool.with_archive(identity.pubkey_hex.clone()); }
+            config = config.with_relays(pool);
+        }
+        #[cfg(feature = "nostr")]
+        if config.effects.is_some() {
+            if let (Some(nostr), Some(identity)) = (guard.config.nostr.as_ref(), guard.identity.as_ref()) {
+                let handler = crate::nostr::NostrEffectHandler::new(Arc::new(identity.clone()), nostr.relays.clone())
+                    .with_store(Arc::new(store.clone()))
+                    .with_proxy(guard.config.proxy()?)
+                    .with_node(Arc::new(self.handle()))
+                    .with_sealed_prefixes(guard.sealed_prefixes.clone());
+                config.handlers.push((None, Box::new(handler)));
+            }
+        }
+        #[cfg(feature = "plugins")]
+        if guard.config.plugins && config.effects.is_some() && guard.real_config.is_none() {
+            for (service, handler) in crate::plugins::handlers(&store).map_err(|e| NineSError::Other(e.to_string()))? {
+                config.handlers.push((Some(service), handler));
+            }
+        }
+        #[cfg(feature = "wallet")]
+        if config.effects.is_some() {
+            if let (Some(wallet), Some(wallet_cfg)) = (guard.wallet.clone(), guard.config.wallet.as_ref()) {
+                let handler = crate::wallet::BitcoinEffectHandler::new(wallet, Arc::new(store.clone()))
+                    .with_confirmations(wallet_cfg.confirmations)
+                    .with_simulate(wallet_cfg.simulate)
+                    .with_coin_selection(wallet_cfg.coin_selection);
+                config.handlers.push((None, Box::new(handler)));
+            }
+        }
+        drop(guard);
+        Ok(services::start(store, config, shutdown))
+    }
+
+    /// Page of the replication stream after `since` (see `NodeConfig::with_replication`)
+    pub fn changes(&self, since: u64, epoch: Option<&str>, limit: usize) -> NineSResult<Changes> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked("/replicate")?;
+        let real = guard.real_config.is_none();
+        drop(guard);
+        let (log, store) = self.replication.as_ref().filter(|_| real).ok_or_else(|| NineSError::Other("replication not enabled".into()))?;
+        replication::changes(log, store, since, epoch, limit)
+    }
+
+    /// Paths `depth` levels under `prefix` with their scroll counts, paged by
+    /// path after `after`; no scroll is read
+    pub fn tree(&self, prefix: &str, depth: usize, after: Option<&str>, limit: usize) -> NineSResult<TreePage> {
+        NodeApi::tree(self, prefix, depth, after, limit)
+    }
+
+    /// `path`'s key, type, version, timestamps, producer and data size
+    pub fn meta(&self, path: &str) -> NineSResult<Option<serde_json::Value>> {
+        NodeApi::meta(self, path)
+    }
+
+    /// Scroll count per type under `prefix`
+    pub fn type_stats(&self, prefix: &str) -> NineSResult<std::collections::BTreeMap<String, usize>> {
+        NodeApi::type_stats(self, prefix)
+    }
+
+    /// Page of the change journal after `since` (see `NodeConfig::with_journal`)
+    pub fn journal(&self, since: u64, limit: usize) -> NineSResult<JournalPage> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked(crate::core::paths::journal::PREFIX)?;
+        if !guard.config.journal || guard.real_config.is_some() {
+            return Err(NineSError::Other("journal not enabled".into()));
+        }
+        journal::page(&guard.config.open_store()?, since, limit)
+    }
+    /// Check a peer token (`peers::verify`) for a request; returns the peer's mobi
+    pub fn verify_peer_token(&self, token: &str, request: &crate::peers::Request) -> NineSResult<String> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked(request.path)?;
+        let id = guard.identity.as_ref().ok_or_else(|| NineSError::Other("no identity".into()))?;
+        crate::peers::verify(&id.token_key(), &guard.config.open_store()?, token, request)
+    }
+    /// Re-feed journaled inbound writes from `from` through the Mind in a
+    /// sandbox store, effects mocked, and report what it wrote (see `replay`)
+    pub fn replay(&self, from: u64, limit: usize) -> NineSResult<Value> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_locked(crate::core::paths::journal::PREFIX)?;
+        replay::run(&guard.config.open_store()?, from, limit)
+    }
+    fn journal_enabled(&self) -> NineSResult<bool> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        Ok(guard.config.journal && guard.real_config.is_none())
+    }
+
     pub fn close(&self) -> NineSResult<()> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
         guard.shell.drop()
     }
 
     // Identity
     pub fn identity(&self) -> Option<Identity> {
-        let guard = self.inner.lock().ok()?;
+        let guard = self.inner.read().ok()?;
         if guard.locked { return None; }
         guard.identity.clone()
     }
     pub fn mobi(&self) -> Option<crate::mobi::Mobi> {
-        let guard = self.inner.lock().ok()?;
+        let guard = self.inner.read().ok()?;
         if guard.locked { return None; }
         guard.identity.as_ref().map(|i| i.mobi.clone())
     }
     pub fn pubkey_hex(&self) -> Option<String> {
-        let guard = self.inner.lock().ok()?;
+        let guard = self.inner.read().ok()?;
         if guard.locked { return None; }
         guard.identity.as_ref().map(|i| i.pubkey_hex.clone())
     }
 
+    /// Overview for dashboards; the same scroll `get("/sys/status")` returns
+    pub fn status(&self) -> NineSResult<Value> {
+        NodeApi::status(self)
+    }
+
+    /// Features, scroll types and API version; see `core::capabilities`
+    pub fn capabilities(&self) -> NineSResult<Value> {
+        NodeApi::capabilities(self)
+    }
+
+    pub(crate) fn http_config(&self) -> HttpConfig {
+        self.inner.read().map(|g| g.config.http.clone()).unwrap_or_default()
+    }
+
+    /// Backends that failed to start (e.g. `wallet`, `nostr`)
+    pub fn degraded(&self) -> Vec<String> {
+        self.inner.read().map(|g| g.degraded.clone()).unwrap_or_default()
+    }
+
     pub fn is_locked(&self) -> bool {
-        self.inner.lock().map(|g| g.locked).unwrap_or(true)
+        self.inner.read().map(|g| g.locked).unwrap_or(true)
     }
 
     pub fn is_initialized(&self) -> bool {
-        self.inner.lock().map(|g| g.auth_initialized).unwrap_or(false)
+        self.inner.read().map(|g| g.auth_initialized).unwrap_or(false)
     }
 
     pub fn unlock(&self, pin: &str) -> NineSResult<bool> {
-        let mut guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
+        let mut guard = self.inner.write().map_err(|_| NineSError::Other("node lock".into()))?;
         guard.unlock(pin)
     }
 
     pub fn lock(&self) -> NineSResult<bool> {
-        let mut guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
+        let mut guard = self.inner.write().map_err(|_| NineSError::Other("node lock".into()))?;
         guard.lock()
     }
 
+    /// Migrate to a new mnemonic: sweep funds, announce, re-encrypt and
+    /// record old → new. Close and reopen the node afterwards.
+    pub fn rotate(&self, rotation: &Rotation) -> NineSResult<Value> {
+        let mut guard = self.inner.write().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_writable("/sys/identity")?;
+        guard.rotate(rotation)
+    }
+
+    /// Approve the plugin at `/sys/plugins/{name}` as it stands now. Needs
+    /// PIN auth and the PIN; the approval is written past the node's own
+    /// write path, which refuses `/sys/plugin-approvals`. Takes effect when
+    /// plugins next load.
+    #[cfg(feature = "plugins")]
+    pub fn approve_plugin(&self, name: &str, pin: &str) -> NineSResult<Scroll> {
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        let auth = guard.auth.as_ref()
+            .filter(|_| guard.auth_mode == AuthMode::Pin && guard.auth_initialized)
+            .ok_or_else(|| NineSError::Other("plugin approval needs a PIN".into()))?;
+        if !auth.verify_pin(pin)? { return Err(NineSError::Other("invalid PIN".into())); }
+        crate::plugins::approve(&guard.config.open_store()?, name).map_err(|e| NineSError::Other(e.to_string()))
+    }
+
     // Convenience
     pub fn exists(&self, path: &str) -> NineSResult<bool> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(path)?;
-        guard.shell.exists(path)
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_readable(path)?;
+        Ok(guard.shell.get(path)?.is_some_and(|s| s.type_ != ABSENT_TYPE))
     }
     pub fn require(&self, path: &str) -> NineSResult<Scroll> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(path)?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_readable(path)?;
         guard.shell.require(path)
     }
     pub fn count(&self, prefix: &str) -> NineSResult<usize> {
-        let guard = self.inner.lock().map_err(|_| NineSError::Other("node lock".into()))?;
-        guard.check_locked(prefix)?;
+        let guard = self.inner.read().map_err(|_| NineSError::Other("node lock".into()))?;
+        guard.check_readable(prefix)?;
         guard.shell.count(prefix)
     }
 
     pub fn create_store(config: &NodeConfig) -> NineSResult<nine_s_store::Store> {
-        nine_s_store::Store::open(&config.app, &config.master_key)
+        config.open_store()
     }
 
-    fn auth_controller(inner: Arc<Mutex<NodeInner>>) -> AuthController {
+    fn auth_controller(inner: Arc<RwLock<NodeInner>>) -> AuthController {
         let status_inner = inner.clone();
         let unlock_inner = inner.clone();
         let lock_inner = inner;
         AuthController::new(
             Arc::new(move || {
                 let guard = status_inner
-                    .lock()
+                    .read()
                     .map_err(|_| NineSError::Other("node lock".into()))?;
                 Ok(AuthStatus { locked: guard.locked, initialized: guard.auth_initialized })
             }),
             Arc::new(move |pin| {
                 let mut guard = unlock_inner
-                    .lock()
+                    .write()
                     .map_err(|_| NineSError::Other("node lock".into()))?;
                 guard.unlock(pin)
             }),
             Arc::new(move || {
                 let mut guard = lock_inner
-                    .lock()
+                    .write()
                     .map_err(|_| NineSError::Other("node lock".into()))?;
                 guard.lock()
             }),
@@ -203,18 +754,149 @@ impl Node {
 }
 
 impl NodeInner {
+    /// `/system/auth`, `/sys/effects` and embedder namespaces on the current shell
+    fn mount_base(&mut self) -> NineSResult<()> {
+        if let Some(controller) = self.controller.clone() {
+            self.shell.mount("/system/auth", Box::new(AuthNamespace::new(controller)))?;
+        }
+        let store = Arc::new(self.config.open_store()?);
+        self.shell.mount(crate::core::paths::effects::PREFIX, Box::new(EffectsNamespace::new(store)))?;
+        for (path, factory) in self.config.namespaces.clone() {
+            let path = sealed::normalize(&path);
+            if !path.starts_with('/') || BUILTIN_MOUNTS.iter().any(|b| path == *b || path.starts_with(&format!("{}/", b))) {
+                return Err(NineSError::invalid_path(&path, "reserved for a built-in namespace"));
+            }
+            self.shell.mount(&path, factory.build(self.config.open_store()?)?)?;
+        }
+        Ok(())
+    }
+
+    /// PIN-derived key for a sealed path; `Ok(None)` for ordinary paths
+    /// A scroll as `Node::get` returns it: lock and public-prefix checks,
+    /// sealed data opened, absent placeholders hidden
+    fn read_checked(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        let key = self.sealed_key(path)?;
+        self.check_readable(path)?;
+        let scroll = self.shell.get(path)?.filter(|s| s.type_ != ABSENT_TYPE);
+        match key {
+            Some(key) => scroll.map(|s| sealed::open(key, s)).transpose(),
+            None => Ok(scroll),
+        }
+    }
+
+    /// `/crypto/encrypt` by `path`: read the scroll here, through the same
+    /// checks as `get`, and hand it to the namespace as `scroll`
+    fn resolve_encrypt(&self, path: &str, mut data: Value) -> NineSResult<Value> {
+        if sealed::normalize(path) != crate::core::paths::crypto::ENCRYPT { return Ok(data); }
+        let Some(source) = data.get("path").and_then(|p| p.as_str()).map(String::from) else { return Ok(data) };
+        let scroll = self.read_checked(&source)?.ok_or_else(|| NineSError::Other(format!("not found: {}", source)))?;
+        if let Some(fields) = data.as_object_mut() {
+            fields.remove("path");
+            fields.insert("scroll".into(), serde_json::json!({"key": scroll.key, "type": scroll.type_, "data": scroll.data}));
+        }
+        Ok(data)
+    }
+
+    fn sealed_key(&self, path: &str) -> NineSResult<Option<&nine_s_store::crypto::DerivedKey>> {
+        match sealed::matching(&self.sealed_prefixes, path) {
+            None => Ok(None),
+            Some(prefix) => match (self.locked, self.prefix_key.as_ref()) {
+                (false, Some(key)) => Ok(Some(key)),
+                _ => Err(sealed::locked(prefix)),
+            },
+        }
+    }
+
+    /// Index configured prefixes and keep them current from store watches.
+    /// Sealed prefixes are never indexed.
+    #[cfg(feature = "search")]
+    fn start_search_index(&self) -> NineSResult<Arc<std::sync::RwLock<crate::search::SearchIndex>>> {
+        let index = Arc::new(std::sync::RwLock::new(crate::search::SearchIndex::new()));
+        for prefix in self.config.search_prefixes.iter().map(|p| sealed::normalize(p)) {
+            if sealed::matching(&self.sealed_prefixes, &prefix).is_some() { continue; }
+            {
+                let mut idx = index.write().map_err(|_| NineSError::Other("search lock".into()))?;
+                for key in self.shell.all(&prefix)? {
+                    if let Some(scroll) = self.shell.get(&key)? { idx.index(&scroll); }
+                }
+            }
+            let rx = self.shell.on(&format!("{}/**", prefix))?;
+            let index = index.clone();
+            std::thread::spawn(move || {
+                while let Ok(scroll) = rx.recv() {
+                    let Ok(mut idx) = index.write() else { break };
+                    idx.index(&scroll);
+                }
+            });
+        }
+        Ok(index)
+    }
+
+    /// A CRDT scroll merged with what's stored at its path (`core::crdt`)
+    fn merge_crdt(&self, mut scroll: Scroll, key: Option<&nine_s_store::crypto::DerivedKey>) -> NineSResult<Scroll> {
+        if !crate::core::crdt::is_crdt(&scroll.type_) { return Ok(scroll); }
+        let current = match key {
+            Some(key) => self.shell.get(&scroll.key)?.filter(|s| s.type_ != ABSENT_TYPE).map(|s| sealed::open(key, s)).transpose()?,
+            None => self.shell.get(&scroll.key)?,
+        };
+        let current = current.filter(|c| c.type_ == scroll.type_).map(|c| c.data).unwrap_or(Value::Null);
+        scroll.data = crate::core::crdt::merge(&scroll.type_, &current, &scroll.data)?;
+        Ok(scroll)
+    }
+
+    /// Signed by this node when `sign_writes` is on and no producer is named yet
+    fn sign(&self, scroll: Scroll) -> NineSResult<Scroll> {
+        match &self.identity {
+            Some(identity) if self.config.sign_writes && scroll.metadata.produced_by.is_none() => {
+                crate::identity::provenance::sign(identity, scroll)
+            }
+            _ => Ok(scroll),
+        }
+    }
+
+    /// Followers only take writes from the replication stream; plugin
+    /// approvals only come from `approve_plugin`
+    fn check_writable(&self, path: &str) -> NineSResult<()> {
+        let approvals = [crate::core::paths::plugins::APPROVALS.to_string()];
+        if sealed::matching(&approvals, &sealed::normalize(path)).is_some() {
+            return Err(NineSError::Other(format!("plugin approvals are written by approve_plugin: {}", path)));
+        }
+        if self.config.follow.is_none() || path.starts_with("/system/auth") {
+            return Ok(());
+        }
+        Err(NineSError::Other(format!("read-only follower: {}", path)))
+    }
+
     fn check_locked(&self, path: &str) -> NineSResult<()> {
         if !self.locked || path.starts_with("/system/auth") {
             return Ok(());
         }
+        // Scoped lock: a path is only blocked when it is inside a locked prefix
+        // or would reach into one (`all("/")`, `on("/**")`)
+        let path = sealed::normalize(path);
+        let reaches = |p: &String| path.is_empty() || p.strip_prefix(path.as_str()).is_some_and(|rest| rest.starts_with('/'));
+        if !self.lock_prefixes.is_empty()
+            && sealed::matching(&self.lock_prefixes, &path).is_none()
+            && !self.lock_prefixes.iter().any(reaches)
+        {
+            return Ok(());
+        }
         Err(NineSError::Other("node locked".into()))
     }
 
+    /// Reads also pass under a public prefix while locked
+    fn check_readable(&self, path: &str) -> NineSResult<()> {
+        if self.locked && sealed::matching(&self.public_prefixes, &sealed::normalize(path)).is_some() {
+            return Ok(());
+        }
+        self.check_locked(path)
+    }
+
     fn unlock(&mut self, pin: &str) -> NineSResult<bool> {
         if self.auth_mode == AuthMode::None {
             if self.identity.is_none() {
-                if let Some(ref mnemonic) = self.config.mnemonic.clone() {
-                    self.initialize_with_mnemonic(mnemonic)?;
+                if let Some(mnemonic) = self.config.mnemonic.clone() {
+                    self.initialize_with_mnemonic(&mnemonic)?;
                 }
             }
             self.locked = false;
@@ -223,10 +905,17 @@ impl NodeInner {
         if !self.auth_initialized {
             return Err(NineSError::Other("auth not initialized".into()));
         }
-        let auth = self.auth.as_ref().ok_or_else(|| NineSError::Other("auth not available".into()))?;
+        let auth = self.auth.clone().ok_or_else(|| NineSError::Other("auth not available".into()))?;
         if !auth.verify_pin(pin)? {
+            if auth.verify_duress_pin(pin)? {
+                return self.enter_decoy(&auth, pin);
+            }
             return Ok(false);
         }
+        self.leave_decoy()?;
+        if !self.sealed_prefixes.is_empty() && self.prefix_key.is_none() {
+            self.prefix_key = Some(auth.prefix_key(pin)?);
+        }
         if self.locked {
             if self.identity.is_none() {
                 let mnemonic = auth.decrypt_mnemonic(pin)?;
@@ -237,111 +926,259 @@ impl NodeInner {
         Ok(true)
     }
 
+    /// Duress PIN: raise the alert in the real store, then swap the shell for
+    /// the decoy app and come up unlocked there as if nothing happened
+    fn enter_decoy(&mut self, auth: &PinAuth, pin: &str) -> NineSResult<bool> {
+        if self.real_config.is_none() {
+            if auth.duress_alert() {
+                let alert = serde_json::json!({"active": true, "level": "critical", "message": "duress PIN used", "at": now_secs()});
+                self.config.open_store()?
+                    .write_scroll(Scroll::new(alerts::DURESS, alert).set_type(alerts::TYPE))?;
+            }
+            let mnemonic = auth.decrypt_duress_mnemonic(pin)?;
+            let mut decoy = self.config.clone();
+            decoy.app = crate::auth::decoy_app(&self.config.app);
+            decoy.mnemonic = None;
+            // Never let the decoy wallet open (and reset) the real wallet file
+            #[cfg(feature = "wallet")]
+            for w in decoy.wallet.iter_mut().chain(decoy.wallets.values_mut()) { w.data_dir = w.data_dir.as_ref().map(|d| d.join("decoy")); }
+            self.real_config = Some(std::mem::replace(&mut self.config, decoy));
+            self.shell = self.config.open_shell()?;
+            self.mount_base()?;
+            self.identity = None;
+            #[cfg(feature = "wallet")]
+            { self.wallet_mounted = false; }
+            self.initialize_with_mnemonic(&mnemonic)?;
+        }
+        if !self.sealed_prefixes.is_empty() && self.prefix_key.is_none() {
+            self.prefix_key = Some(auth.prefix_key(pin)?);
+        }
+        self.locked = false;
+        Ok(true)
+    }
+
+    /// Back to the real shell after the real PIN; identity is rebuilt on unlock
+    fn leave_decoy(&mut self) -> NineSResult<()> {
+        let Some(real) = self.real_config.take() else { return Ok(()) };
+        self.config = real;
+        self.shell = self.config.open_shell()?;
+        self.mount_base()?;
+        self.identity = None;
+        self.prefix_key = None;
+        self.locked = true;
+        #[cfg(feature = "wallet")]
+        { self.wallet_mounted = false; }
+        Ok(())
+    }
+
     fn lock(&mut self) -> NineSResult<bool> {
         if self.auth_mode == AuthMode::None {
             return Ok(false);
         }
         if self.auth_initialized {
             self.locked = true;
+            self.prefix_key = None;
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn initialize_with_mnemonic(&mut self, mnemonic: &str) -> NineSResult<()> {
+    fn initialize_with_mnemonic(&mut self, mnemonic: &Secret<String>) -> NineSResult<()> {
         if self.identity.is_some() {
             return Ok(());
         }
 
+        // The persistent keychain holds the real seed: a decoy never touches it
+        let decoy = self.real_config.is_some();
+
         #[cfg(feature = "wallet")]
-        let keychain = {
-            let kc = PersistentKeychain::new()?;
-            if !kc.has_seed()? { kc.import_seed(mnemonic)?; }
-            kc
+        let keychain = if decoy { None } else {
+            let kc = self.config.open_keychain()?;
+            if !kc.has_seed()? { kc.import_seed(mnemonic.expose())?; }
+            Some(kc)
         };
 
         #[cfg(feature = "wallet")]
-        let has_seed = keychain.has_seed()?;
+        let has_seed = match keychain { Some(ref kc) => kc.has_seed()?, None => true };
         #[cfg(not(feature = "wallet"))]
         let has_seed = true;
 
         if has_seed {
             #[cfg(feature = "wallet")]
-            { self.identity = Some(Identity::from_seed(&keychain.derive_protocol_seed(Protocol::Nostr)?)?) }
-            #[cfg(not(feature = "wallet"))]
-            { self.identity = Some(Identity::from_mnemonic(mnemonic)?); }
+            if let Some(ref kc) = keychain {
+                self.identity = Some(Identity::from_seed(&kc.derive_protocol_seed(Protocol::Nostr)?)?);
+            }
+            if self.identity.is_none() {
+                self.identity = Some(Identity::from_mnemonic(mnemonic.expose())?);
+            }
         }
 
         #[cfg(feature = "wallet")]
-        if let Some(ref wallet_cfg) = self.config.wallet {
-            if has_seed && !self.wallet_mounted {
-                use crate::wallet::WalletNamespace;
-                let store = Arc::new(nine_s_store::Store::open(&self.config.app, &self.config.master_key)?);
-
-                let db_path = wallet_cfg.data_dir.clone().unwrap_or_else(|| {
-                    let root = std::env::var("NINE_S_ROOT").map(std::path::PathBuf::from)
-                        .unwrap_or_else(|_| dirs::data_local_dir().unwrap_or_else(|| std::path::PathBuf::from(".")));
-                    root.join(&self.config.app)
-                }).join("wallet.sqlite");
-
-                if let Some(parent) = db_path.parent() {
-                    std::fs::create_dir_all(parent).map_err(|e| NineSError::Other(format!("mkdir: {}", e)))?;
-                }
+        if has_seed && !self.wallet_mounted && (self.config.wallet.is_some() || !self.config.wallets.is_empty()) {
+            if let Err(e) = self.mount_wallet(mnemonic) { self.degrade("wallet", &e)?; }
+        }
 
-                let seed = mnemonic_to_seed(mnemonic)?;
-                #[cfg(feature = "bitcoind-rpc")]
-                let wallet_ns = if let Some(ref rpc) = wallet_cfg.rpc {
-                    WalletNamespace::open_rpc(&seed, store, wallet_cfg.network, &db_path, &rpc.url, &rpc.user, &rpc.pass)?
-                } else {
-                    WalletNamespace::open(&seed, store, wallet_cfg.network, &db_path, wallet_cfg.electrum_url.as_deref())?
-                };
-                #[cfg(not(feature = "bitcoind-rpc"))]
-                let wallet_ns = WalletNamespace::open(&seed, store, wallet_cfg.network, &db_path, wallet_cfg.electrum_url.as_deref())?;
-                self.shell.mount("/wallet", Box::new(wallet_ns))?;
-                self.wallet_mounted = true;
+        if let Some(ref id) = self.identity {
+            use crate::namespaces::identity::IdentityNamespace;
+            let store = Arc::new(self.config.open_store()?);
+            let seed = mnemonic_to_seed(mnemonic.expose())?;
+            let ssh = crate::ssh::derive_from_seed(seed.expose(), 0).map_err(|e| NineSError::Other(e.to_string()))?;
+            self.shell.mount("/ssh", Box::new(crate::ssh::SshNamespace::new(ssh, format!("{}@beenode", id.mobi.display))))?;
+            let age = crate::crypto::AgeIdentity::from_seed(seed.expose()).map_err(|e| NineSError::Other(e.to_string()))?;
+            self.shell.mount("/crypto", Box::new(crate::crypto::CryptoNamespace::new(age, store.clone())))?;
+            #[cfg(feature = "s3")]
+            if let Some(archive) = self.config.archive.clone() {
+                let mounted = crate::archive::derive_key(seed.expose())
+                    .and_then(|key| crate::archive::RemoteObjectNamespace::new(archive, key, store.clone()))
+                    .and_then(|ns| self.shell.mount(crate::core::paths::archive::PREFIX, Box::new(ns)));
+                if let Err(e) = mounted { self.degrade("archive", &e)?; }
             }
+            self.shell.mount(crate::core::paths::peers::PREFIX, Box::new(crate::peers::PeersNamespace::new(id.token_key(), store.clone())))?;
+            self.shell.mount("/identity", Box::new(IdentityNamespace::new(seed, id.clone(), store)))?;
         }
 
         #[cfg(feature = "nostr")]
         if let (Some(ref nostr_cfg), Some(ref id)) = (&self.config.nostr, &self.identity) {
             use crate::nostr::NostrNamespace;
-            self.shell.mount("/nostr", Box::new(NostrNamespace::new(id.clone(), nostr_cfg.clone())))?;
+            let mounted = self.config.open_store().and_then(|store| {
+                let mut ns = NostrNamespace::new(id.clone(), nostr_cfg.clone()).with_store(Arc::new(store)).with_proxy(self.config.proxy()?);
+                if self.auth_mode == AuthMode::Pin { ns = ns.with_pin_auth(self.config.auth_path()); }
+                self.shell.mount("/nostr", Box::new(ns))
+            });
+            if let Err(e) = mounted { self.degrade("nostr", &e)?; }
         }
 
         Ok(())
     }
+
+    #[cfg(feature = "wallet")]
+    /// Mount `/wallet` and each named `/wallet/{name}`. The BDK open runs on
+    /// a background thread behind a `LazyWallet` so unlock doesn't wait on
+    /// it; `strict` nodes open inline so a failure still fails node creation.
+    fn mount_wallet(&mut self, mnemonic: &Secret<String>) -> NineSResult<()> {
+        self.config.check_wallets()?;
+        if let Some(wallet_cfg) = self.config.wallet.clone() {
+            let db_path = self.config.wallet_db_path().unwrap_or_default();
+            self.mount_one_wallet("/wallet", wallet_cfg, db_path, self.config.open_store()?, mnemonic)?;
+        }
+        for (name, wallet_cfg) in self.config.wallets.clone() {
+            let db_path = self.config.named_wallet_db_path(&name).unwrap_or_default();
+            self.mount_one_wallet(&format!("/wallet/{}", name), wallet_cfg, db_path, self.config.open_wallet_store(&name)?, mnemonic)?;
+        }
+        self.wallet_mounted = true;
+        Ok(())
+    }
+
+    #[cfg(feature = "wallet")]
+    fn mount_one_wallet(&mut self, at: &str, wallet_cfg: WalletConfig, db_path: std::path::PathBuf, store: nine_s_store::Store, mnemonic: &Secret<String>) -> NineSResult<()> {
+        use crate::wallet::{Backend, LazyWallet, WalletNamespace};
+        let store = Arc::new(store);
+
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| NineSError::Other(format!("mkdir: {}", e)))?;
+        }
+
+        let seed = mnemonic_to_seed(mnemonic.expose())?;
+        let network = wallet_cfg.network;
+        let migrated = store.read(crate::core::paths::wallet::ACCOUNT_CONFIG)?.and_then(|s| s.data["account"].as_u64()).map(|a| a as u32);
+        let account = wallet_cfg.account.or(migrated).unwrap_or(0);
+        let proxy = self.config.proxy()?.map(|p| p.addr());
+        let mut electrum = wallet_cfg.electrum();
+        electrum.proxy = proxy.clone();
+        let open = move || -> NineSResult<WalletNamespace> {
+            #[cfg(feature = "bitcoind-rpc")]
+            let backend = match wallet_cfg.rpc {
+                Some(ref rpc) => Backend::Rpc { url: rpc.url.clone(), user: rpc.user.clone(), pass: rpc.pass.clone(), proxy },
+                None => Backend::Electrum(electrum),
+            };
+            #[cfg(not(feature = "bitcoind-rpc"))]
+            let backend = Backend::Electrum(electrum);
+            let wallet_ns = WalletNamespace::open_backend(seed.expose(), store, wallet_cfg.network, &db_path, backend, account, wallet_cfg.lookahead)?;
+            Ok(wallet_ns
+                .with_confirmations(wallet_cfg.confirmations)
+                .with_fiat(wallet_cfg.fiat)
+                .with_simulate(wallet_cfg.simulate)
+                .with_coin_selection(wallet_cfg.coin_selection)
+                .with_address_reuse(wallet_cfg.address_reuse)
+                .with_scan(wallet_cfg.birthday_height, wallet_cfg.stop_gap))
+        };
+        let lazy = match self.wallet.clone().filter(|_| at == "/wallet") {
+            Some(lazy) => { lazy.reset(); lazy }
+            None => LazyWallet::new(network),
+        };
+        if self.config.strict {
+            lazy.set(Ok(open()?));
+            self.shell.mount(at, Box::new(lazy))?;
+        } else {
+            self.shell.mount(at, Box::new(lazy.clone()))?;
+            lazy.spawn(self.config.open_store()?, open);
+        }
+        Ok(())
+    }
+
+    /// A backend failed to mount: keep the node up, raise `/sys/alerts/{name}`.
+    /// With `NodeConfig::strict` the error is returned instead.
+    #[cfg_attr(not(any(feature = "wallet", feature = "nostr")), allow(dead_code))]
+    fn degrade(&mut self, name: &str, err: &NineSError) -> NineSResult<()> {
+        if self.config.strict {
+            return Err(NineSError::Other(format!("{}: {}", name, err)));
+        }
+        tracing::warn!("{} unavailable, continuing degraded: {}", name, err);
+        if !self.degraded.iter().any(|d| d == name) { self.degraded.push(name.to_string()); }
+        let alert = serde_json::json!({"active": true, "level": "error", "message": format!("{} unavailable: {}", name, err)});
+        self.shell.put_scroll(Scroll::new(&format!("{}/{}", alerts::PREFIX, name), alert).set_type(alerts::TYPE))?;
+        Ok(())
+    }
+}
+
+impl NodeApi for Node {
+    fn get(&self, path: &str) -> NineSResult<Option<Scroll>> { Node::get(self, path) }
+    fn put(&self, path: &str, data: Value) -> NineSResult<Scroll> { Node::put(self, path, data) }
+    fn all(&self, prefix: &str) -> NineSResult<Vec<String>> { Node::all(self, prefix) }
+    fn on(&self, pattern: &str) -> NineSResult<std::sync::mpsc::Receiver<Scroll>> { self.on_with(pattern, Default::default()) }
+    fn close(&self) -> NineSResult<()> { Node::close(self) }
+    fn unlock(&self, pin: &str) -> NineSResult<bool> { Node::unlock(self, pin) }
+    fn lock(&self) -> NineSResult<bool> { Node::lock(self) }
+    fn is_locked(&self) -> bool { Node::is_locked(self) }
+    fn is_initialized(&self) -> bool { Node::is_initialized(self) }
+    fn put_batch(&self, puts: Vec<(String, Value)>) -> NineSResult<Vec<Scroll>> { Node::put_batch(self, puts) }
+    fn http_config(&self) -> HttpConfig { Node::http_config(self) }
+    fn watch_with(&self, pattern: &str, options: crate::core::watch::WatchOptions) -> NineSResult<std::sync::mpsc::Receiver<crate::core::watch::WatchEvent>> {
+        Node::watch_with(self, pattern, options)
+    }
+    fn on_with_replay(&self, pattern: &str, since: u64) -> NineSResult<std::sync::mpsc::Receiver<Scroll>> { Node::on_with_replay(self, pattern, since) }
+    fn journal(&self, since: u64, limit: usize) -> NineSResult<JournalPage> { Node::journal(self, since, limit) }
+    fn changes(&self, since: u64, epoch: Option<&str>, limit: usize) -> NineSResult<Changes> { Node::changes(self, since, epoch, limit) }
+    fn view(&self, name: &str) -> NineSResult<Value> { Node::view(self, name) }
+    fn execute_effect(&self, path: &str, data: Value, timeout: std::time::Duration) -> NineSResult<Scroll> { Node::execute_effect(self, path, data, timeout) }
+    #[cfg(feature = "search")]
+    fn search(&self, query: &str, limit: usize) -> NineSResult<Vec<crate::search::SearchHit>> { Node::search(self, query, limit) }
+    fn verify_peer_token(&self, token: &str, request: &crate::peers::Request) -> NineSResult<String> { Node::verify_peer_token(self, token, request) }
 }
 
 /// Convert BIP39 mnemonic to 64-byte seed (standard, no HKDF)
-#[cfg(feature = "wallet")]
-fn mnemonic_to_seed(mnemonic: &str) -> NineSResult<[u8; 64]> {
+fn mnemonic_to_seed(mnemonic: &str) -> NineSResult<Secret<[u8; 64]>> {
     use bip39::Mnemonic;
     let m = Mnemonic::parse(mnemonic)
         .map_err(|e| NineSError::Other(format!("Invalid mnemonic: {}", e)))?;
-    Ok(m.to_seed(""))
+    Ok(Secret::new(m.to_seed("")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use once_cell::sync::Lazy;
     use serde_json::json;
-    use std::sync::Mutex;
     use tempfile::TempDir;
 
-    static ENV_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-
-    fn temp_node(app: &str) -> (TempDir, Node, std::sync::MutexGuard<'static, ()>) {
-        let guard = ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    fn temp_node(app: &str) -> (TempDir, Node) {
         let dir = TempDir::new().expect("tempdir");
-        std::env::set_var("NINE_S_ROOT", dir.path());
-        let node = Node::from_config(NodeConfig::new(app)).expect("node");
-        (dir, node, guard)
+        let node = Node::from_config(NodeConfig::new(app).with_root(dir.path())).expect("node");
+        (dir, node)
     }
 
     #[test]
     fn test_five_verbs() {
-        let (_dir, node, _guard) = temp_node("test-five-verbs");
+        let (_dir, node) = temp_node("test-five-verbs");
         let scroll = node.put("/notes/1", json!({"title": "Hello"})).unwrap();
         assert_eq!(scroll.key, "/notes/1");
         let retrieved = node.get("/notes/1").unwrap().unwrap();
@@ -350,16 +1187,360 @@ mod tests {
         node.close().unwrap();
     }
 
+    #[test]
+    fn test_reads_share_the_lock() {
+        let (_dir, node) = temp_node("test-shared-reads");
+        node.put("/notes/1", json!({})).unwrap();
+        let node = Arc::new(node);
+        let held = node.inner.read().unwrap();
+        let reader = node.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || { let _ = tx.send(reader.get("/notes/1").map(|s| s.is_some())); });
+        assert!(rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap().unwrap());
+        drop(held);
+    }
+
+    #[test]
+    fn test_put_batch() {
+        let (_dir, node) = temp_node("test-batch");
+        let written = node.put_batch(vec![("/a".into(), json!({"n": 1})), ("/b".into(), json!({"n": 2}))]).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(node.get("/b").unwrap().unwrap().data["n"], 2);
+    }
+
+    #[test]
+    fn test_execute_effect_waits_for_result() {
+        let (_dir, node) = temp_node("test-exec-effect");
+        let node = Arc::new(node);
+        let responder = node.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            responder.put("/external/test/1/result", json!({"success": true, "result": 42})).unwrap();
+        });
+        let result = node.execute_effect("/external/test/1", json!({}), std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(result.data["result"], 42);
+        assert!(node.execute_effect("/external/test/2", json!({}), std::time::Duration::from_millis(50)).is_err());
+        assert!(node.execute_effect("/notes/1", json!({}), std::time::Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_recurring_runs() {
+        let (dir, node) = temp_node("test-recurring");
+        let store = NodeConfig::new("test-recurring").with_root(dir.path()).open_store().unwrap();
+        assert!(node.define_recurring("dca", json!({"to": "bc1q", "schedule": "daily"})).is_err());
+        node.define_recurring("dca", json!({"to": "bc1q", "amount_sat": 5000, "schedule": "daily", "policy": "g/data.amount_sat<10000/"})).unwrap();
+
+        let runs = recurring::on_pulse(&store, "daily").unwrap();
+        assert_eq!(runs[0]["status"], "queued");
+        assert_eq!(node.get(runs[0]["effect"].as_str().unwrap()).unwrap().unwrap().data["amount_sat"], 5000);
+        assert!(recurring::on_pulse(&store, "hourly").unwrap().is_empty());
+
+        node.skip_recurring("dca", 1).unwrap();
+        assert_eq!(recurring::on_pulse(&store, "daily").unwrap()[0]["status"], "skipped");
+        node.pause_recurring("dca", true).unwrap();
+        assert!(recurring::on_pulse(&store, "daily").unwrap().is_empty());
+
+        node.pause_recurring("dca", false).unwrap();
+        node.define_recurring("big", json!({"to": "bc1q", "amount_sat": 50000, "schedule": "weekly", "policy": "g/data.amount_sat<10000/"})).unwrap();
+        assert_eq!(recurring::on_pulse(&store, "weekly").unwrap()[0]["status"], "blocked");
+        assert_eq!(node.get("/sys/recurring/dca/state").unwrap().unwrap().data["queued"], 1);
+        assert_eq!(node.all("/sys/recurring/dca/runs").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_typed_access() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Note { title: String }
+        crate::scroll_type!(Note, "test/note@v1");
+
+        let (_dir, node) = temp_node("test-typed");
+        let scroll = node.put_typed("/notes/1", &Note { title: "Hello".into() }).unwrap();
+        assert_eq!(scroll.type_, "test/note@v1");
+        assert_eq!(node.get_as::<Note>("/notes/1").unwrap(), Some(Note { title: "Hello".into() }));
+        assert_eq!(node.get_as::<Note>("/notes/missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_migrations_run_once() {
+        fn add_body(mut data: Value) -> NineSResult<Value> {
+            data["body"] = json!("");
+            Ok(data)
+        }
+        let dir = TempDir::new().expect("tempdir");
+
+        let node = Node::from_config(NodeConfig::new("test-migrate").with_root(dir.path())).unwrap();
+        node.put_scroll(Scroll::new("/notes/1", json!({"title": "a"})).set_type("test/note@v1")).unwrap();
+        node.close().unwrap();
+
+        let config = NodeConfig::new("test-migrate").with_root(dir.path())
+            .with_migration(Migration::new("notes-v2", "/notes", "test/note@v1", "test/note@v2", add_body));
+        let node = Node::from_config(config.clone()).unwrap();
+        let note = node.get("/notes/1").unwrap().unwrap();
+        assert_eq!(note.type_, "test/note@v2");
+        assert_eq!(note.data["body"], "");
+        assert_eq!(node.get("/sys/migrations/notes-v2").unwrap().unwrap().data["migrated"], 1);
+
+        // Re-running is a no-op even for new v1 scrolls
+        node.put_scroll(Scroll::new("/notes/2", json!({"title": "b"})).set_type("test/note@v1")).unwrap();
+        node.close().unwrap();
+        let node = Node::from_config(config).unwrap();
+        assert_eq!(node.get("/notes/2").unwrap().unwrap().type_, "test/note@v1");
+    }
+
     #[test]
     fn test_with_mnemonic() {
-        let guard = ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner());
         let dir = TempDir::new().expect("tempdir");
-        std::env::set_var("NINE_S_ROOT", dir.path());
         let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
-        let node = Node::from_config(NodeConfig::new("test").with_mnemonic(mnemonic)).expect("node");
+        let node = Node::from_config(NodeConfig::new("test").with_root(dir.path()).with_mnemonic(mnemonic)).expect("node");
         assert!(node.identity().is_some());
         assert!(node.mobi().is_some());
         assert_eq!(node.mobi().unwrap().display.len(), 12);
-        drop(guard);
+    }
+
+    #[test]
+    fn test_public_prefixes_while_locked() {
+        let dir = TempDir::new().expect("tempdir");
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let config = NodeConfig::new("test-public").with_root(dir.path());
+        PinAuth::open(config.auth_path()).unwrap().set_pin("1234", mnemonic).unwrap();
+        let store = config.open_store().unwrap();
+        store.write("/sys/clock/tick", json!({"tick": 1})).unwrap();
+        store.write("/notes/1", json!({"body": "private"})).unwrap();
+
+        let node = Node::from_config(config.clone()).expect("node");
+        assert!(node.is_locked());
+        assert_eq!(node.get("/sys/clock/tick").unwrap().unwrap().data["tick"], 1);
+        assert_eq!(node.all("/sys/clock").unwrap(), vec!["/sys/clock/tick"]);
+        assert!(node.put("/sys/clock/tick", json!({"tick": 2})).is_err());
+        assert!(node.get("/notes/1").is_err());
+        assert!(node.all("/sys").is_err());
+
+        let strict = Node::from_config(config.without_public_prefixes()).expect("node");
+        assert!(strict.get("/sys/clock/tick").is_err());
+    }
+
+    #[test]
+    fn test_lock_sensitive_only() {
+        let dir = TempDir::new().expect("tempdir");
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let config = NodeConfig::new("test-scoped").with_root(dir.path());
+        PinAuth::open(config.auth_path()).unwrap().set_pin("1234", mnemonic).unwrap();
+
+        let node = Node::from_config(config.lock_sensitive_only()).expect("node");
+        assert!(node.is_locked());
+        node.put("/notes/1", json!({"body": "still here"})).unwrap();
+        assert_eq!(node.all("/notes").unwrap(), vec!["/notes/1"]);
+        assert!(node.get("/wallet/balance").is_err());
+        assert!(node.put("/external/bitcoin/send/1", json!({})).is_err());
+        assert!(node.define_recurring("rent", json!({})).is_err());
+        assert!(node.all("/").is_err());
+        // Views read their source: a locked one is refused however the view was defined
+        let dsl = "o/key,asc/";
+        assert!(node.define_view("funds", dsl, "/wallet", false).is_err());
+        node.put("/sys/views/funds", json!({"dsl": dsl, "source": "/wallet"})).unwrap();
+        assert!(node.view("funds").is_err());
+        node.define_view("notes", dsl, "/notes", false).unwrap();
+        assert_eq!(node.view("notes").unwrap()["count"], 1);
+    }
+
+    #[test]
+    fn test_status() {
+        let (_dir, node) = temp_node("test-status");
+        node.put("/notes/1", json!({})).unwrap();
+        let status = node.status().unwrap();
+        assert_eq!(status["locked"], false);
+        assert_eq!(status["version"], env!("CARGO_PKG_VERSION"));
+        let scrolls = status["store"]["scrolls"].as_u64().unwrap();
+        assert!(scrolls >= 1);
+        assert_eq!(status["effects"]["pending"], 0);
+
+        // New keys are counted as they land; a second version of one is not
+        node.put("/notes/2", json!({})).unwrap();
+        node.put("/notes/2", json!({"edited": true})).unwrap();
+        for _ in 0..50 {
+            if node.status().unwrap()["store"]["scrolls"].as_u64().unwrap() > scrolls { break; }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(node.status().unwrap()["store"]["scrolls"].as_u64().unwrap(), scrolls + 1);
+        let caps = node.capabilities().unwrap();
+        assert_eq!(caps["api_version"], crate::core::capabilities::API_VERSION);
+        assert_eq!(caps["features"]["wallet"], cfg!(feature = "wallet"));
+        assert_eq!(caps["features"]["lightning"], cfg!(feature = "nostr"));
+        assert_eq!(caps["enabled"]["lightning"], false);
+        assert!(caps["scroll_types"].as_array().unwrap().iter().any(|t| t == "sys/status@v1"));
+    }
+
+    #[test]
+    fn test_roots_coexist() {
+        let (a, b) = (TempDir::new().expect("tempdir"), TempDir::new().expect("tempdir"));
+        let node_a = Node::from_config(NodeConfig::new("test-root").with_root(a.path())).expect("node");
+        let node_b = Node::from_config(NodeConfig::new("test-root").with_root(b.path())).expect("node");
+        node_a.put("/notes/1", json!({"in": "a"})).unwrap();
+        assert!(node_b.get("/notes/1").unwrap().is_none());
+        assert!(a.path().join("test-root").exists());
+        assert_eq!(NodeConfig::new("x").with_root(a.path()).auth_path(), a.path().join("x/data/auth.json"));
+    }
+
+    #[test]
+    fn test_proxy_config() {
+        let dir = TempDir::new().expect("tempdir");
+        assert!(Node::from_config(NodeConfig::new("test-proxy").with_root(dir.path()).with_proxy("http://127.0.0.1:8080")).is_err());
+        let config = NodeConfig::new("test-proxy").with_root(dir.path()).with_proxy(crate::core::proxy::TOR);
+        assert_eq!(config.proxy().unwrap().unwrap().addr(), "127.0.0.1:9050");
+        #[cfg(feature = "wallet")]
+        {
+            let mainnet = NodeConfig::new("test-proxy").with_wallet(WalletConfig::mainnet()).require_tor_on_mainnet();
+            assert!(mainnet.proxy().is_err());
+            assert!(mainnet.with_proxy(crate::core::proxy::TOR).proxy().is_ok());
+            assert!(NodeConfig::new("test-proxy").with_wallet(WalletConfig::testnet()).require_tor_on_mainnet().proxy().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rotate_records_mapping() {
+        let dir = TempDir::new().expect("tempdir");
+        let old = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let new = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let node = Node::from_config(NodeConfig::new("test-rotate").with_root(dir.path()).with_mnemonic(old)).expect("node");
+        let before = node.mobi().unwrap();
+        assert!(node.rotate(&Rotation::new(old)).is_err());
+
+        let report = node.rotate(&Rotation::new(new)).unwrap();
+        assert_eq!(report["restart_required"], true);
+        let after = node.mobi().unwrap();
+        assert_ne!(after.display, before.display);
+        let mapping = node.get(&format!("/sys/identity/migrations/{}", before.display)).unwrap().unwrap();
+        assert_eq!(mapping.data["new_mobi"], after.display);
+    }
+
+    #[test]
+    fn test_rotate_rolls_back() {
+        let dir = TempDir::new().expect("tempdir");
+        let old = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let new = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let config = NodeConfig::new("test-rotate-rollback").with_root(dir.path()).with_encrypted_prefix("/journal");
+        let mut auth = PinAuth::open(config.auth_path()).unwrap();
+        auth.set_pin("1234", old).unwrap();
+        let node = Node::from_config(config.clone()).expect("node");
+        assert!(node.unlock("1234").unwrap());
+        node.put("/journal/a", json!({"entry": "kept"})).unwrap();
+        let before = node.mobi().unwrap();
+
+        // The auth file can't be renamed into place: everything goes back
+        std::fs::create_dir_all(auth.temp_path()).unwrap();
+        let rotation = Rotation::new(new).with_pin("1234").without_sweep().without_announce();
+        assert!(node.rotate(&rotation).unwrap_err().to_string().contains("rolled back"));
+        assert_eq!(node.mobi().unwrap().display, before.display);
+        assert_eq!(node.get("/journal/a").unwrap().unwrap().data["entry"], "kept");
+        assert_eq!(PinAuth::open(config.auth_path()).unwrap().decrypt_mnemonic("1234").unwrap().expose(), old);
+        node.close().unwrap();
+        let reopened = Node::from_config(config.clone()).expect("node");
+        assert!(reopened.unlock("1234").unwrap());
+        assert_eq!(reopened.mobi().unwrap().display, before.display);
+        assert_eq!(reopened.get("/journal/a").unwrap().unwrap().data["entry"], "kept");
+
+        std::fs::remove_dir(auth.temp_path()).unwrap();
+        let report = reopened.rotate(&rotation).unwrap();
+        assert_eq!(report["resealed"], 1);
+        assert_ne!(reopened.mobi().unwrap().display, before.display);
+        assert_eq!(reopened.get("/journal/a").unwrap().unwrap().data["entry"], "kept");
+    }
+
+    #[cfg(feature = "market")]
+    #[tokio::test]
+    async fn test_services_refresh_prices() {
+        let dir = TempDir::new().expect("tempdir");
+        let node = Node::from_config(NodeConfig::new("test-market-services").with_root(dir.path())).expect("node");
+        let shutdown = crate::runtime::Shutdown::new();
+        let config = ServicesConfig::new().with_mind().with_effects(crate::mind::EffectConfig { process_existing: true, ..Default::default() });
+        let services = node.start_services(config, &shutdown).unwrap();
+        assert!(node.get("/sys/mind/patterns/market-price-hour").unwrap().is_some());
+
+        // An unsupported pair fails before any request goes out
+        node.put("/external/market/price/t1", json!({"pairs": ["eth-usd"]})).unwrap();
+        let mut result = None;
+        for _ in 0..100 {
+            result = node.get("/external/market/price/t1/result").unwrap();
+            if result.is_some() { break; }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let result = result.expect("price handler answered");
+        assert_eq!(result.data["success"], false);
+        assert!(result.data["error"].as_str().unwrap().contains("unsupported pair"));
+
+        shutdown.trigger().await;
+        services.join().await;
+    }
+
+    #[cfg(feature = "market")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_serving_services_answer_effects() {
+        let dir = TempDir::new().expect("tempdir");
+        let node = Node::from_config(NodeConfig::new("test-serving").with_root(dir.path())).expect("node");
+        let shutdown = crate::runtime::Shutdown::new();
+        let services = node.start_services(ServicesConfig::serving(), &shutdown).unwrap();
+        assert!(services.services().contains(&"mind"));
+        assert!(services.services().contains(&"effects"));
+
+        node.put("/external/market/price/s1", json!({"pairs": ["eth-usd"]})).unwrap();
+        let mut result = None;
+        for _ in 0..100 {
+            result = node.get("/external/market/price/s1/result").unwrap();
+            if result.is_some() { break; }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(result.expect("handler answered").data["success"], false);
+
+        shutdown.trigger().await;
+        services.join().await;
+    }
+
+    #[cfg(feature = "plugins")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_approved_effect_plugin_runs() {
+        use base64::Engine as _;
+        // Answers every call with {"ok":true}
+        const HELLO: &str = r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{\22ok\22:true}")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "run") (param i32 i32) (result i64) (i64.const 11)))"#;
+        let dir = TempDir::new().expect("tempdir");
+        let config = NodeConfig::new("test-plugin-approval").with_root(dir.path()).with_plugins();
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        PinAuth::open(config.auth_path()).unwrap().set_pin("1234", mnemonic).unwrap();
+        let node = Node::from_config(config).expect("node");
+        assert!(node.unlock("1234").unwrap());
+
+        let wasm = base64::engine::general_purpose::STANDARD.encode(HELLO);
+        node.put("/sys/plugins/hello", json!({"kind": "effect", "service": "hello", "wasm": wasm})).unwrap();
+        assert!(node.put("/sys/plugin-approvals/hello", json!({"digest": "forged"})).is_err());
+        assert!(node.approve_plugin("hello", "0000").is_err());
+        node.approve_plugin("hello", "1234").unwrap();
+
+        let shutdown = crate::runtime::Shutdown::new();
+        let config = ServicesConfig::new().without_clock().with_effects(crate::mind::EffectConfig { process_existing: true, ..Default::default() });
+        let services = node.start_services(config, &shutdown).unwrap();
+        node.put("/external/hello/1", json!({})).unwrap();
+        let mut result = None;
+        for _ in 0..100 {
+            result = node.get("/external/hello/1/result").unwrap();
+            if result.is_some() { break; }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let result = result.expect("plugin answered");
+        assert_eq!(result.data["success"], true);
+        assert_eq!(result.data["result"]["ok"], true);
+
+        shutdown.trigger().await;
+        services.join().await;
+    }
+
+    #[test]
+    fn test_config_redacts_mnemonic() {
+        let words = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let config = NodeConfig::new("test-redact").with_mnemonic(words);
+        assert!(!format!("{:?}", config).contains("abandon"));
+        assert_eq!(config.mnemonic.as_ref().map(|m| m.expose().as_str()), Some(words));
     }
 }
@@ -0,0 +1,179 @@
+//! Recurring payments (DCA, subscriptions)
+//!
+//! A definition at `/sys/recurring/{name}` is
+//! `{to, amount_sat | fiat_amount: {currency, amount}, schedule, every?, fee_rate?,
+//! max_amount_sat?, policy?, paused?, skip?}`. `schedule` names a clock pulse;
+//! every `every`-th pulse the payment is checked and queued on
+//! `/external/bitcoin/send/{id}` for the bitcoin effect handler, which
+//! `Node::start_services` registers when a wallet is configured.
+//!
+//! `policy` is a BSE match expression over the payment block
+//! `{type: "recurring.payment", data: {name, to, amount_sat, runs}}`, the same
+//! form Mind patterns use for `when`. A failing policy blocks the run.
+//!
+//! Controls: `paused: true` ignores pulses, `skip: n` passes over the next n
+//! due runs. Every run is recorded at `/sys/recurring/{name}/runs/{seq}`,
+//! counters at `/sys/recurring/{name}/state`.
+
+use crate::core::bse::{matches_block, parse_match};
+use crate::core::paths::{clock, origin, recurring as paths, wallet};
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_shell::Shell;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Validate a definition and build its scroll
+pub fn definition(name: &str, data: Value) -> NineSResult<Scroll> {
+    let key = format!("{}/{}", paths::PREFIX, name);
+    if name.is_empty() || name.contains('/') {
+        return Err(NineSError::invalid_path(&key, "recurring name must be a single segment"));
+    }
+    let invalid = |msg: &str| NineSError::Other(format!("recurring {}: {}", name, msg));
+    if data["to"].as_str().map_or(true, str::is_empty) { return Err(invalid("no 'to'")); }
+    if data["schedule"].as_str().map_or(true, str::is_empty) { return Err(invalid("no 'schedule' pulse")); }
+    match (data["amount_sat"].as_u64(), data["fiat_amount"]["amount"].as_f64()) {
+        (Some(a), None) if a > 0 => {}
+        (None, Some(f)) if f > 0.0 && data["fiat_amount"]["currency"].is_string() => {}
+        _ => return Err(invalid("exactly one of 'amount_sat' or 'fiat_amount: {currency, amount}' required")),
+    }
+    if let Some(policy) = data["policy"].as_str() {
+        parse_match(policy).map_err(|e| invalid(&format!("policy: {}", e)))?;
+    }
+    Ok(Scroll::new(&key, data).set_type(paths::TYPE))
+}
+
+/// Set a control field (`paused`, `skip`) on an existing definition
+pub fn control(shell: &Shell, name: &str, field: &str, value: Value) -> NineSResult<Scroll> {
+    let key = format!("{}/{}", paths::PREFIX, name);
+    let mut def = shell.get(&key)?.ok_or_else(|| NineSError::Other(format!("no recurring payment: {}", name)))?;
+    def.data[field] = value;
+    shell.put_scroll(def)
+}
+
+/// Names of all definitions (state and run records excluded)
+pub fn names(store: &Store) -> NineSResult<Vec<String>> {
+    let prefix = format!("{}/", paths::PREFIX);
+    Ok(store.list(paths::PREFIX)?.into_iter()
+        .filter_map(|k| k.strip_prefix(&prefix).filter(|n| !n.contains('/')).map(str::to_string))
+        .collect())
+}
+
+/// Run every definition scheduled on `pulse`; returns the recorded runs
+pub fn on_pulse(store: &Store, pulse: &str) -> NineSResult<Vec<Value>> {
+    let mut runs = Vec::new();
+    for name in names(store)? {
+        let Some(def) = store.read(&format!("{}/{}", paths::PREFIX, name))? else { continue };
+        if def.data["schedule"].as_str() != Some(pulse) || def.data["paused"].as_bool().unwrap_or(false) { continue; }
+        let mut state = store.read(&state_key(&name))?.map(|s| s.data).unwrap_or_else(|| json!({}));
+        let pulses = state["pulses"].as_u64().unwrap_or(0) + 1;
+        state["pulses"] = json!(pulses);
+        if pulses % def.data["every"].as_u64().unwrap_or(1).max(1) == 0 {
+            let run = execute(store, &name, def, &state)?;
+            let status = run["status"].as_str().unwrap_or("failed").to_string();
+            state[&status] = json!(state[&status].as_u64().unwrap_or(0) + 1);
+            state["last_status"] = json!(status);
+            state["last_run_at"] = run["at"].clone();
+            runs.push(run);
+        }
+        store.write_scroll(scroll(&state_key(&name), paths::STATE_TYPE, state))?;
+    }
+    Ok(runs)
+}
+
+/// Run `on_pulse` for every clock pulse on a background thread
+pub fn start(store: Store) -> NineSResult<()> {
+    let rx = store.watch(&WatchPattern::parse(&format!("{}/**", clock::PULSES))?)?;
+    std::thread::spawn(move || {
+        while let Ok(pulse) = rx.recv() {
+            let Some(name) = pulse.key.rsplit('/').next() else { continue };
+            if let Err(e) = on_pulse(&store, name) {
+                tracing::warn!("recurring {}: {}", name, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// One due run: skip, block or queue the send effect
+fn execute(store: &Store, name: &str, mut def: Scroll, state: &Value) -> NineSResult<Value> {
+    let seq = ["queued", "skipped", "blocked", "failed"].iter().map(|s| state[*s].as_u64().unwrap_or(0)).sum::<u64>() + 1;
+    let mut run = json!({"name": name, "seq": seq, "at": now_secs()});
+
+    let skip = def.data["skip"].as_u64().unwrap_or(0);
+    if skip > 0 {
+        def.data["skip"] = json!(skip - 1);
+        def.metadata = Metadata::default().with_produced_by(origin::RECURRING);
+        store.write_scroll(def)?;
+        run["status"] = json!("skipped");
+        return record(store, name, seq, run);
+    }
+
+    let amount_sat = match amount(store, &def.data) {
+        Ok(a) => a,
+        Err(e) => {
+            run["status"] = json!("failed");
+            run["reason"] = json!(e.to_string());
+            return record(store, name, seq, run);
+        }
+    };
+    run["amount_sat"] = json!(amount_sat);
+
+    if let Some(reason) = blocked(&def.data, name, amount_sat, state) {
+        run["status"] = json!("blocked");
+        run["reason"] = json!(reason);
+        return record(store, name, seq, run);
+    }
+
+    let effect = format!("{}/recurring-{}-{}", wallet::EXTERNAL_SEND, name, seq);
+    let mut send = Scroll::new(&effect, json!({
+        "to": def.data["to"],
+        "amount_sat": amount_sat,
+        "fee_rate": def.data["fee_rate"],
+        "recurring": name,
+    }));
+    send.metadata = Metadata::default().with_produced_by(origin::RECURRING);
+    store.write_scroll(send)?;
+    run["status"] = json!("queued");
+    run["effect"] = json!(effect);
+    record(store, name, seq, run)
+}
+
+/// `amount_sat`, or `fiat_amount` converted at the stored `/market/price/btc-{currency}`
+fn amount(store: &Store, def: &Value) -> NineSResult<u64> {
+    if let Some(sats) = def["amount_sat"].as_u64() { return Ok(sats); }
+    let currency = def["fiat_amount"]["currency"].as_str().unwrap_or("").to_lowercase();
+    let fiat = def["fiat_amount"]["amount"].as_f64().unwrap_or(0.0);
+    let price = crate::market::price(store, &format!("btc-{}", currency))?
+        .ok_or_else(|| NineSError::Other(format!("no btc-{} price", currency)))?;
+    crate::market::fiat_to_sats(fiat, price).ok_or_else(|| NineSError::Other(format!("invalid btc-{} price", currency)))
+}
+
+/// Reason the policy or limits reject this payment
+fn blocked(def: &Value, name: &str, amount_sat: u64, state: &Value) -> Option<String> {
+    if let Some(max) = def["max_amount_sat"].as_u64().filter(|max| amount_sat > *max) {
+        return Some(format!("amount {} exceeds max_amount_sat {}", amount_sat, max));
+    }
+    let policy = def["policy"].as_str()?;
+    let block = json!({
+        "key": format!("{}/{}", paths::PREFIX, name),
+        "type": paths::PAYMENT_BLOCK,
+        "data": {"name": name, "to": def["to"], "amount_sat": amount_sat, "runs": state["queued"].as_u64().unwrap_or(0)},
+    });
+    match parse_match(policy) {
+        Ok(m) if matches_block(&m, &block) => None,
+        Ok(_) => Some(format!("policy rejected: {}", policy)),
+        Err(e) => Some(format!("policy: {}", e)),
+    }
+}
+
+fn record(store: &Store, name: &str, seq: u64, run: Value) -> NineSResult<Value> {
+    store.write_scroll(scroll(&format!("{}/{}{}/{:08}", paths::PREFIX, name, paths::RUNS, seq), paths::RUN_TYPE, run.clone()))?;
+    Ok(run)
+}
+
+fn state_key(name: &str) -> String { format!("{}/{}{}", paths::PREFIX, name, paths::STATE) }
+
+fn scroll(key: &str, type_: &str, data: Value) -> Scroll {
+    Scroll { key: key.into(), type_: type_.into(), metadata: Metadata::default().with_produced_by(origin::RECURRING), data }
+}
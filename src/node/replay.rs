@@ -0,0 +1,121 @@
+//! Replay - re-run recorded writes through the Mind in a sandbox
+//!
+//! `Node::replay(from, limit)` opens a throwaway store, copies the node's
+//! current Mind patterns into it and re-feeds the journal's inbound writes
+//! from sequence `from` on, one at a time, in journal order. After each write
+//! the Mind reacts synchronously. Effects it queues under `/external/**` are
+//! not executed: each gets a mocked `/result`
+//! (`{"success": true, "result": {"replayed": true}}`) that is fed back in, so
+//! patterns reacting to results run too. Nothing touches the node's store or
+//! the network.
+//!
+//! The report lists every derived write per input, mocked results included:
+//! `{from, cursor, replayed, skipped, steps: [{seq, path, writes: [{path, type, data}]}]}`.
+//! Only entries journaled with their scroll can be replayed; derived entries
+//! and ones recorded before scrolls were kept count as `skipped`. Pattern
+//! writes in the journal replace the sandbox's patterns as they come.
+//! Templates that read the clock or random values won't reproduce exactly.
+
+use super::journal;
+use crate::core::paths::{mind as paths, origin, EFFECT_RESULT_TYPE};
+use crate::core::time::now_nanos;
+use crate::mind::Mind;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+/// Derived writes followed per input before the step is cut short
+const MAX_STEP_WRITES: usize = 1000;
+
+/// Replay up to `limit` journal entries from `from` (inclusive) of `source`
+pub(crate) fn run(source: &Store, from: u64, limit: usize) -> NineSResult<Value> {
+    let dir = std::env::temp_dir().join(format!("beenode-replay-{}-{}", std::process::id(), now_nanos()));
+    let sandbox = super::NodeConfig::new("replay").with_root(&dir).open_store()?;
+    let report = replay(source, &sandbox, from, limit);
+    drop(sandbox);
+    let _ = std::fs::remove_dir_all(&dir);
+    report
+}
+
+fn replay(source: &Store, sandbox: &Store, from: u64, limit: usize) -> NineSResult<Value> {
+    for path in source.list(paths::PATTERNS_PREFIX)? {
+        if let Some(scroll) = source.read(&path)? { sandbox.write_scroll(scroll)?; }
+    }
+    let mut mind = Mind::new(sandbox.clone());
+    mind.reload_patterns().map_err(other)?;
+
+    let page = journal::page(source, from.saturating_sub(1), limit)?;
+    let (mut steps, mut skipped) = (Vec::new(), 0);
+    for entry in page.entries {
+        let Some(scroll) = entry.scroll.and_then(|s| serde_json::from_value::<Scroll>(s).ok()) else { skipped += 1; continue };
+        let input = sandbox.write_scroll(scroll)?;
+        if input.key.starts_with(paths::PATTERNS_PREFIX) {
+            mind.reload_patterns().map_err(other)?;
+            steps.push(json!({"seq": entry.seq, "path": entry.path, "writes": []}));
+            continue;
+        }
+
+        let (mut writes, mut queue) = (Vec::new(), VecDeque::from([input]));
+        while let Some(scroll) = queue.pop_front() {
+            if writes.len() >= MAX_STEP_WRITES { break; }
+            for reaction in mind.react(&scroll).map_err(other)? {
+                let result = if is_effect(&reaction.key) { Some(mock_result(sandbox, &reaction.key)?) } else { None };
+                writes.push(reaction);
+                if let Some(result) = result {
+                    writes.push(result.clone());
+                    queue.push_back(result);
+                }
+            }
+        }
+        let mut step = json!({"seq": entry.seq, "path": entry.path, "writes": writes.iter().map(describe).collect::<Vec<_>>()});
+        if !queue.is_empty() { step["truncated"] = json!(true); }
+        steps.push(step);
+    }
+    Ok(json!({"from": from, "cursor": page.cursor, "replayed": steps.len(), "skipped": skipped, "steps": steps}))
+}
+
+fn is_effect(key: &str) -> bool {
+    key.starts_with(&format!("{}/", paths::EXTERNAL_PREFIX)) && !key.ends_with(paths::RESULT_SUFFIX)
+}
+
+/// What the effect worker would write, minus the side effect
+fn mock_result(sandbox: &Store, effect: &str) -> NineSResult<Scroll> {
+    sandbox.write_scroll(Scroll {
+        key: format!("{}{}", effect, paths::RESULT_SUFFIX),
+        type_: EFFECT_RESULT_TYPE.into(),
+        metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+        data: json!({"success": true, "result": {"replayed": true}}),
+    })
+}
+
+fn describe(scroll: &Scroll) -> Value { json!({"path": scroll.key, "type": scroll.type_, "data": scroll.data}) }
+
+fn other(e: anyhow::Error) -> NineSError { NineSError::Other(e.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_reproduces_reactions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (source, sandbox) = (crate::node::NodeConfig::new("test-replay").with_root(dir.path()).open_store().unwrap(), crate::node::NodeConfig::new("test-replay-sandbox").with_root(dir.path()).open_store().unwrap());
+        source.write_scroll(Scroll::new("/sys/mind/patterns/ping", json!({
+            "name": "ping", "watch": "/inbox/*", "emit": "nostr/publish@v1",
+            "emit_path": "/external/nostr/publish/${path.1}", "template": {"text": "${data.text}"}
+        }))).unwrap();
+        let inbound = Scroll::new("/inbox/1", json!({"text": "hi"}));
+        let entries = [
+            json!({"seq": 1, "path": "/inbox/1", "version": 1, "hash": "", "at": 0, "scroll": inbound}),
+            json!({"seq": 2, "path": "/external/nostr/publish/1", "version": 1, "hash": "", "at": 0}),
+        ];
+        for entry in entries { source.write_scroll(Scroll::new(&format!("/sys/journal/entries/{}", entry["seq"]), entry)).unwrap(); }
+
+        let report = replay(&source, &sandbox, 1, 10).unwrap();
+        assert_eq!((report["replayed"].as_u64(), report["skipped"].as_u64(), report["cursor"].as_u64()), (Some(1), Some(1), Some(2)));
+        assert_eq!(report["steps"][0]["writes"][0]["path"], "/external/nostr/publish/1");
+        assert_eq!(sandbox.read("/external/nostr/publish/1/result").unwrap().unwrap().data["result"]["replayed"], true);
+        assert!(source.read("/inbox/1").unwrap().is_none());
+    }
+}
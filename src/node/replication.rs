@@ -0,0 +1,242 @@
+//! Replication - ordered change stream and read-only followers
+//!
+//! A source node (`NodeConfig::with_replication`) numbers every stored key in
+//! a ChangeLog: existing keys at startup, then each write as it lands. Only the
+//! latest sequence per key is kept, so `changes(since)` is a compacted stream.
+//! The log lives in memory under a per-process `epoch`; a follower presenting
+//! a stale epoch gets a full snapshot from sequence 0.
+//!
+//! A follower (`NodeConfig::with_follow`) polls `GET {url}/replicate` as NDJSON
+//! (a header line `{epoch, cursor, snapshot}` then one scroll per line), writes
+//! the scrolls into its own store and tracks progress in `/sys/replication/state`.
+//! Deletes are not replicated. CRDT scrolls (`core::crdt`) are merged into the
+//! follower's copy rather than overwriting it.
+
+use crate::core::paths::replication as paths;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Scrolls per `/replicate` response unless the caller asks otherwise
+pub const DEFAULT_BATCH: usize = 500;
+
+/// Where a follower pulls from
+#[derive(Debug, Clone)]
+pub struct FollowConfig {
+    /// Source base URL, `http://host:port` (https is not supported)
+    pub url: String,
+    /// Bearer token sent as `Authorization`
+    pub token: Option<String>,
+    pub interval: Duration,
+}
+
+impl FollowConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into().trim_end_matches('/').to_string(), token: None, interval: Duration::from_secs(5) }
+    }
+    pub fn with_token(mut self, token: impl Into<String>) -> Self { self.token = Some(token.into()); self }
+    pub fn with_interval(mut self, interval: Duration) -> Self { self.interval = interval; self }
+}
+
+/// One page of the change stream
+#[derive(Debug, Clone)]
+pub struct Changes {
+    pub epoch: String,
+    /// Sequence of the last scroll returned (pass back as `since`)
+    pub cursor: u64,
+    /// True when the page starts from sequence 0 (new follower or stale epoch)
+    pub snapshot: bool,
+    pub scrolls: Vec<Scroll>,
+}
+
+#[derive(Debug)]
+pub struct ChangeLog {
+    epoch: String,
+    next: u64,
+    by_seq: BTreeMap<u64, String>,
+    by_key: HashMap<String, u64>,
+}
+
+impl ChangeLog {
+    pub fn new() -> Self {
+        let nanos = crate::core::time::now_nanos();
+        Self { epoch: format!("{:x}-{:x}", nanos, std::process::id()), next: 0, by_seq: BTreeMap::new(), by_key: HashMap::new() }
+    }
+
+    pub fn epoch(&self) -> &str { &self.epoch }
+
+    /// Move `key` to the head of the log
+    pub fn record(&mut self, key: &str) {
+        if !replicated(key) { return; }
+        self.next += 1;
+        if let Some(old) = self.by_key.insert(key.to_string(), self.next) {
+            self.by_seq.remove(&old);
+        }
+        self.by_seq.insert(self.next, key.to_string());
+    }
+
+    /// Up to `limit` `(seq, key)` pairs after `since`, in order
+    pub fn after(&self, since: u64, limit: usize) -> Vec<(u64, String)> {
+        self.by_seq.range(since + 1..).take(limit).map(|(s, k)| (*s, k.clone())).collect()
+    }
+}
+
+impl Default for ChangeLog {
+    fn default() -> Self { Self::new() }
+}
+
+/// Whether `key` travels to followers: each side keeps its own replication
+/// state and journal
+fn replicated(key: &str) -> bool {
+    !key.starts_with(paths::PREFIX) && !key.starts_with(crate::core::paths::journal::PREFIX)
+}
+
+/// Seed a ChangeLog from `store` and keep it current from store watches
+pub fn start(store: Store) -> NineSResult<Arc<RwLock<ChangeLog>>> {
+    let log = Arc::new(RwLock::new(ChangeLog::new()));
+    let rx = store.watch(&WatchPattern::parse("/**")?)?;
+    {
+        let mut keys = store.list("/")?;
+        keys.sort();
+        let mut l = log.write().map_err(|_| NineSError::Other("replication lock".into()))?;
+        for key in keys { l.record(&key); }
+    }
+    let watched = log.clone();
+    std::thread::spawn(move || {
+        while let Ok(scroll) = rx.recv() {
+            let Ok(mut l) = watched.write() else { break };
+            l.record(&scroll.key);
+        }
+    });
+    Ok(log)
+}
+
+/// Read the page after `since` from `store`
+pub fn changes(log: &RwLock<ChangeLog>, store: &Store, since: u64, epoch: Option<&str>, limit: usize) -> NineSResult<Changes> {
+    let log = log.read().map_err(|_| NineSError::Other("replication lock".into()))?;
+    let since = if epoch.is_some_and(|e| e != log.epoch()) { 0 } else { since };
+    let snapshot = since == 0;
+    let mut cursor = since;
+    let mut scrolls = Vec::new();
+    for (seq, key) in log.after(since, limit.max(1)) {
+        cursor = seq;
+        if let Some(scroll) = store.read(&key)? { scrolls.push(scroll); }
+    }
+    Ok(Changes { epoch: log.epoch().to_string(), cursor, snapshot, scrolls })
+}
+
+/// Encode a page as NDJSON
+pub(crate) fn to_ndjson(changes: &Changes) -> String {
+    let mut out = json!({"epoch": changes.epoch, "cursor": changes.cursor, "snapshot": changes.snapshot, "count": changes.scrolls.len()}).to_string();
+    for scroll in &changes.scrolls {
+        out.push('\n');
+        out.push_str(&serde_json::to_string(scroll).unwrap_or_default());
+    }
+    out.push('\n');
+    out
+}
+
+/// Poll the source forever, applying pages into `store`
+pub fn follow(store: Store, cfg: FollowConfig) {
+    std::thread::spawn(move || loop {
+        match pull(&store, &cfg) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("replication from {}: {}", cfg.url, e),
+        }
+        std::thread::sleep(cfg.interval);
+    });
+}
+
+/// Apply one page. Returns true when the page was full (more to fetch).
+fn pull(store: &Store, cfg: &FollowConfig) -> NineSResult<bool> {
+    let state = store.read(paths::STATE)?.map(|s| s.data).unwrap_or(Value::Null);
+    let since = state["cursor"].as_u64().unwrap_or(0);
+    let mut url = format!("{}/replicate?since={}&limit={}", cfg.url, since, DEFAULT_BATCH);
+    if let Some(epoch) = state["epoch"].as_str() { url.push_str(&format!("&epoch={}", epoch)); }
+
+    let body = http_get(&url, cfg.token.as_deref())?;
+    let mut lines = body.lines().filter(|l| !l.trim().is_empty());
+    let header: Value = serde_json::from_str(lines.next().ok_or_else(|| NineSError::Other("empty replication response".into()))?)
+        .map_err(|e| NineSError::Other(format!("replication header: {}", e)))?;
+    let mut count = 0;
+    for line in lines {
+        let mut scroll: Scroll = serde_json::from_str(line).map_err(|e| NineSError::Other(format!("replication scroll: {}", e)))?;
+        if !replicated(&scroll.key) { continue; }
+        // CRDTs merge with what's stored (sealed ones can't be read here and replace it)
+        if crate::core::crdt::is_crdt(&scroll.type_) && scroll.data["sealed"].is_null() {
+            if let Some(current) = store.read(&scroll.key)?.filter(|c| c.type_ == scroll.type_ && c.data["sealed"].is_null()) {
+                scroll.data = crate::core::crdt::merge(&scroll.type_, &current.data, &scroll.data)?;
+            }
+        }
+        store.write_scroll(scroll)?;
+        count += 1;
+    }
+    let data = json!({"source": cfg.url, "epoch": header["epoch"], "cursor": header["cursor"], "applied": count});
+    store.write_scroll(Scroll::new(paths::STATE, data).set_type(paths::TYPE))?;
+    Ok(count >= DEFAULT_BATCH)
+}
+
+/// Minimal HTTP/1.1 GET over plain TCP
+fn http_get(url: &str, token: Option<&str>) -> NineSResult<String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| NineSError::Other(format!("unsupported url: {}", url)))?;
+    let (host, path) = rest.split_once('/').map(|(h, p)| (h, format!("/{}", p))).unwrap_or((rest, "/".into()));
+    let addr = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    let io = |e: std::io::Error| NineSError::Other(format!("{}: {}", addr, e));
+
+    let mut stream = std::net::TcpStream::connect(&addr).map_err(io)?;
+    stream.set_read_timeout(Some(Duration::from_secs(30))).map_err(io)?;
+    let auth = token.map(|t| format!("Authorization: Bearer {}\r\n", t)).unwrap_or_default();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n", path, host, auth).map_err(io)?;
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).map_err(io)?;
+
+    let raw = String::from_utf8_lossy(&raw);
+    let (head, body) = raw.split_once("\r\n\r\n").ok_or_else(|| NineSError::Other("malformed response".into()))?;
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {
+        return Err(NineSError::Other(format!("HTTP {}: {}", status, body.trim())));
+    }
+    if head.to_ascii_lowercase().contains("transfer-encoding: chunked") {
+        return Ok(dechunk(body));
+    }
+    Ok(body.to_string())
+}
+
+fn dechunk(mut body: &str) -> String {
+    let mut out = String::new();
+    while let Some((size, rest)) = body.split_once("\r\n") {
+        let size = usize::from_str_radix(size.trim(), 16).unwrap_or(0);
+        if size == 0 || rest.len() < size { break; }
+        out.push_str(&rest[..size]);
+        body = rest[size..].trim_start_matches("\r\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changelog_compacts() {
+        let mut log = ChangeLog::new();
+        log.record("/a");
+        log.record("/b");
+        log.record("/a");
+        log.record("/sys/replication/state");
+        log.record("/sys/journal/entries/00000001");
+        assert_eq!(log.after(0, 10), vec![(2, "/b".to_string()), (3, "/a".to_string())]);
+        assert_eq!(log.after(2, 10), vec![(3, "/a".to_string())]);
+        assert_eq!(log.after(0, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_dechunk() {
+        assert_eq!(dechunk("5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"), "hello world");
+    }
+}
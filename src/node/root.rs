@@ -0,0 +1,47 @@
+//! Root - Where a node keeps its files
+//!
+//! Everything is opened from an explicit root: `NodeConfig::with_root` (or
+//! `NodeConfig::data_root`) names the directory, and the store, shell,
+//! keychain, auth file and wallet are opened through the config's
+//! `open_*`/`*_path` methods. `NINE_S_ROOT` is only the fallback for a
+//! config without a root.
+//!
+//! The auth file and the wallet database take their paths directly. 9S
+//! stores and the keychain only look their directory up in `NINE_S_ROOT` at
+//! open time and have no way to be given one, so `NodeConfig` alone calls
+//! `scoped`, which points the variable at the root under a lock for the
+//! duration of the open. Open handles keep the directory they were opened with.
+//!
+//! Layout under a root: `{root}/{app}/` for the store, `{root}/{app}/data/auth.json`
+//! for the PIN file and `{root}/{app}/wallet.sqlite` for the wallet, each
+//! overridable on its own.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+static ENV: Mutex<()> = Mutex::new(());
+
+/// `root`, else `NINE_S_ROOT`, else the platform data directory
+pub fn resolve(root: Option<&Path>) -> PathBuf {
+    match root {
+        Some(r) => r.to_path_buf(),
+        None => std::env::var("NINE_S_ROOT").map(PathBuf::from)
+            .unwrap_or_else(|_| dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."))),
+    }
+}
+
+/// Run `f` with `NINE_S_ROOT` set to `root`. With `None` the environment is
+/// left alone, but the lock is still taken so a scoped open elsewhere can't
+/// leak into this one.
+pub(super) fn scoped<T>(root: Option<&Path>, f: impl FnOnce() -> T) -> T {
+    let _guard = ENV.lock().unwrap_or_else(|p| p.into_inner());
+    let Some(root) = root else { return f() };
+    let previous = std::env::var_os("NINE_S_ROOT");
+    std::env::set_var("NINE_S_ROOT", root);
+    let out = f();
+    match previous {
+        Some(p) => std::env::set_var("NINE_S_ROOT", p),
+        None => std::env::remove_var("NINE_S_ROOT"),
+    }
+    out
+}
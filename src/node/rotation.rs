@@ -0,0 +1,256 @@
+//! Rotation - Move a node to a new mnemonic after compromise
+//!
+//! ```ignore
+//! let report = node.rotate(&Rotation::new(new_words).with_pin("1234"))?;
+//! node.close()?;  // reopen with the new mnemonic (and master key)
+//! ```
+//!
+//! Steps, in order, each skipped when it does not apply:
+//! 1. stage: the PIN file re-encrypted with the new mnemonic and every sealed
+//!    scroll resealed under the new PIN-derived key, in memory only
+//! 2. sweep: every wallet UTXO goes to the first receive address of the new
+//!    mnemonic's wallet (`/wallet/sweep`)
+//! 3. switch: the new mnemonic becomes the keychain seed, the resealed
+//!    scrolls are written and the PIN file is renamed into place. If any of
+//!    it fails, the scrolls and the keychain are put back and the node stays
+//!    on its old identity (a sweep already sent stays sent)
+//! 4. announce: a kind-1776 event from the old key, tagging the new pubkey
+//!    and carrying a counter-signed event from the new key, is queued at
+//!    `/external/nostr/publish/{id}` (the effect worker still signs with the
+//!    old key)
+//! 5. mapping: `/sys/identity/migrations/{old_mobi}` records old → new
+//! 6. rekey: with `with_master_key`, every scroll is rewritten into the store
+//!    opened under the new master key (history written under the old key is
+//!    not carried over)
+//!
+//! Mounted namespaces keep the old identity until the node is reopened.
+
+use super::{mnemonic_to_seed, sealed, AuthMode, NodeInner};
+use crate::core::paths::{identity as paths, ABSENT_TYPE};
+use crate::core::time::{now_nanos, now_secs};
+use crate::auth::PinAuth;
+use crate::identity::Identity;
+use crate::secret::Secret;
+use nine_s_core::prelude::*;
+use nine_s_store::crypto::DerivedKey;
+use serde_json::{json, Value};
+
+#[cfg(feature = "wallet")]
+use nine_s_store::{Keychain, Protocol};
+
+/// Auth file and sealed scrolls for the new words, held until the commit
+struct Staged {
+    auth: PinAuth,
+    new_key: DerivedKey,
+    scrolls: Vec<(Scroll, Value)>,
+}
+
+/// What to rotate to, built like `NodeConfig`
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub mnemonic: Secret<String>,
+    /// Required under PIN auth: re-encrypts the auth file and sealed prefixes
+    pub pin: Option<String>,
+    /// New store master key; `None` keeps the current one
+    pub master_key: Option<Vec<u8>>,
+    pub sweep: bool,
+    pub fee_rate: Option<f64>,
+    pub announce: bool,
+}
+
+impl Rotation {
+    pub fn new(mnemonic: impl Into<String>) -> Self {
+        Self { mnemonic: Secret::new(mnemonic.into()), pin: None, master_key: None, sweep: true, fee_rate: None, announce: true }
+    }
+    pub fn with_pin(mut self, pin: impl Into<String>) -> Self { self.pin = Some(pin.into()); self }
+    pub fn with_master_key(mut self, key: Vec<u8>) -> Self { self.master_key = Some(key); self }
+    pub fn with_fee_rate(mut self, rate: f64) -> Self { self.fee_rate = Some(rate); self }
+    /// Leave wallet funds where they are
+    pub fn without_sweep(mut self) -> Self { self.sweep = false; self }
+    /// Don't publish the migration event
+    pub fn without_announce(mut self) -> Self { self.announce = false; self }
+}
+
+impl NodeInner {
+    pub(super) fn rotate(&mut self, r: &Rotation) -> NineSResult<Value> {
+        if self.locked { return Err(NineSError::Other("node locked".into())); }
+        let old = self.identity.clone().ok_or_else(|| NineSError::Other("no identity to rotate".into()))?;
+        let new_seed = mnemonic_to_seed(r.mnemonic.expose())?;
+        if self.config.mnemonic.as_ref().map(|m| m.expose()) == Some(r.mnemonic.expose()) {
+            return Err(NineSError::Other("new mnemonic is the current one".into()));
+        }
+        // A PIN file that was never written has nothing to re-encrypt
+        let auth = match self.auth.clone().filter(|a| self.auth_mode == AuthMode::Pin && a.is_initialized()) {
+            Some(auth) => {
+                let pin = r.pin.as_deref().ok_or_else(|| NineSError::Other("rotation needs the PIN".into()))?;
+                if !auth.verify_pin(pin)? { return Err(NineSError::Other("invalid PIN".into())); }
+                Some((auth, pin))
+            }
+            None => None,
+        };
+        // The keychain is switched in place; these words put it back on failure
+        let old_mnemonic = match (&auth, &self.config.mnemonic) {
+            (Some((auth, pin)), _) => auth.decrypt_mnemonic(pin)?,
+            (None, Some(mnemonic)) => mnemonic.clone(),
+            (None, None) => return Err(NineSError::Other("rotation without PIN auth needs the current mnemonic in the config".into())),
+        };
+        let mut report = json!({"old": {"pubkey": old.pubkey_hex, "mobi": old.mobi.display_formatted()}});
+
+        // 1. Stage the auth file and resealed prefixes; nothing is written yet
+        let staged = match &auth {
+            Some((auth, pin)) => Some(self.stage_auth(auth, pin, r.mnemonic.expose())?),
+            None => None,
+        };
+
+        // 2. Funds: the new wallet is reachable from the new words alone
+        report["sweep"] = if r.sweep { self.sweep(new_seed.expose(), r.fee_rate)? } else { Value::Null };
+
+        // 3. Switch keychain, sealed scrolls and auth file together
+        let committed = derive_identity(&self.config, r.mnemonic.expose()).and_then(|new| {
+            if new.pubkey_hex == old.pubkey_hex {
+                return Err(NineSError::Other("keychain still holds the previous seed".into()));
+            }
+            Ok((new, self.commit_auth(staged)?))
+        });
+        let (new, staged) = match committed {
+            Ok(staged) => staged,
+            Err(e) => {
+                let restored = derive_identity(&self.config, old_mnemonic.expose()).map(|_| ());
+                return Err(match restored {
+                    Ok(()) => NineSError::Other(format!("rotation rolled back: {}", e)),
+                    Err(re) => NineSError::Other(format!("rotation failed ({}) and the keychain could not be restored: {}", e, re)),
+                });
+            }
+        };
+        report["new"] = json!({"pubkey": new.pubkey_hex, "mobi": new.mobi.display_formatted()});
+        if let Some((auth, new_key, resealed)) = staged {
+            if self.prefix_key.is_some() { self.prefix_key = Some(new_key); }
+            self.auth = Some(auth);
+            report["resealed"] = json!(resealed);
+        }
+        if self.config.mnemonic.is_some() { self.config.mnemonic = Some(r.mnemonic.clone()); }
+        self.identity = Some(new.clone());
+
+        // 4. Announce
+        report["announce"] = if r.announce { self.announce(&old, &new)? } else { Value::Null };
+
+        // 5. Mapping
+        let mapping = json!({
+            "old_pubkey": old.pubkey_hex,
+            "old_mobi": old.mobi.display,
+            "new_pubkey": new.pubkey_hex,
+            "new_mobi": new.mobi.display,
+            "sweep_txid": report["sweep"]["txid"],
+            "announce": report["announce"]["request"],
+            "rotated_at": now_secs(),
+        });
+        let key = format!("{}/{}", paths::MIGRATIONS_PREFIX, old.mobi.display);
+        self.shell.put_scroll(Scroll::new(&key, mapping).set_type(paths::MIGRATION_TYPE))?;
+        report["mapping"] = json!(key);
+
+        // 6. Store master key
+        if let Some(ref master_key) = r.master_key {
+            let moved = rekey(&self.config, master_key)?;
+            report["rekeyed"] = json!(moved);
+            self.config.master_key = master_key.clone();
+        }
+
+        report["restart_required"] = json!(true);
+        Ok(report)
+    }
+
+    /// The auth file re-encrypted for the new words and every sealed scroll
+    /// opened under the old prefix key and sealed under the new one
+    fn stage_auth(&self, auth: &PinAuth, pin: &str, mnemonic: &str) -> NineSResult<Staged> {
+        let old_key = auth.prefix_key(pin)?;
+        let staged = auth.with_pin(pin, mnemonic)?;
+        let new_key = staged.prefix_key(pin)?;
+        let mut scrolls = Vec::new();
+        for prefix in &self.sealed_prefixes {
+            for key in self.shell.all(prefix)? {
+                let Some(scroll) = self.shell.get(&key)?.filter(|s| s.type_ != ABSENT_TYPE) else { continue };
+                let data = sealed::seal(&new_key, &key, &sealed::open(&old_key, scroll.clone())?.data)?;
+                scrolls.push((scroll, data));
+            }
+        }
+        Ok(Staged { auth: staged, new_key, scrolls })
+    }
+
+    /// Write the resealed scrolls, then rename the new auth file into place.
+    /// On failure the scrolls written so far get their old sealed data back.
+    fn commit_auth(&self, staged: Option<Staged>) -> NineSResult<Option<(PinAuth, DerivedKey, usize)>> {
+        let Some(Staged { auth, new_key, scrolls }) = staged else { return Ok(None) };
+        let mut written = Vec::new();
+        let result = scrolls.iter().try_for_each(|(scroll, data)| -> NineSResult<()> {
+            self.shell.put_scroll(Scroll { data: data.clone(), ..scroll.clone() })?;
+            written.push(scroll);
+            Ok(())
+        }).and_then(|_| auth.commit());
+        if let Err(e) = result {
+            for scroll in written { let _ = self.shell.put_scroll(scroll.clone()); }
+            return Err(e);
+        }
+        Ok(Some((auth, new_key, scrolls.len())))
+    }
+
+    #[cfg(feature = "wallet")]
+    fn sweep(&self, new_seed: &[u8; 64], fee_rate: Option<f64>) -> NineSResult<Value> {
+        let Some(ref wallet_cfg) = self.config.wallet else { return Ok(Value::Null) };
+        if !self.wallet_mounted { return Ok(Value::Null); }
+        let to = crate::wallet::bdk::BdkWallet::first_address(new_seed, wallet_cfg.network.to_bdk())?;
+        self.shell.put("/wallet/sync", json!({"now": true}))?;
+        Ok(self.shell.put("/wallet/sweep", json!({"to": to, "fee_rate": fee_rate}))?.data)
+    }
+
+    #[cfg(not(feature = "wallet"))]
+    fn sweep(&self, _: &[u8; 64], _: Option<f64>) -> NineSResult<Value> { Ok(Value::Null) }
+
+    #[cfg(feature = "nostr")]
+    fn announce(&self, old: &Identity, new: &Identity) -> NineSResult<Value> {
+        use crate::core::paths::nostr as nostr_paths;
+        use crate::nostr::kinds;
+        if self.config.nostr.is_none() { return Ok(Value::Null); }
+        let tag = |name: &str, value: &str| nostr::Tag::parse(&[name.to_string(), value.to_string()]).map_err(|e| NineSError::Other(e.to_string()));
+        // The new key vouches for the old one, so the pair can't be forged from either side alone
+        let counter = nostr::EventBuilder::new(nostr::Kind::Custom(kinds::KEY_MIGRATION), "", [tag("p", &old.pubkey_hex)?])
+            .sign_with_keys(&new.nostr_keys)
+            .map_err(|e| NineSError::Other(e.to_string()))?;
+        let request = format!("{}/{}", nostr_paths::EXTERNAL_PUBLISH, now_nanos());
+        self.shell.put(&request, json!({
+            "kind": kinds::KEY_MIGRATION,
+            "content": serde_json::to_string(&counter).map_err(|e| NineSError::Other(e.to_string()))?,
+            "tags": [["p", new.pubkey_hex], ["mobi", new.mobi.display]],
+        }))?;
+        Ok(json!({"request": request, "counter_signed": counter.id.to_hex()}))
+    }
+
+    #[cfg(not(feature = "nostr"))]
+    fn announce(&self, _: &Identity, _: &Identity) -> NineSResult<Value> { Ok(Value::Null) }
+}
+
+/// Identity the node would derive from `mnemonic` on its next start. With a
+/// wallet the keychain seed is replaced first.
+#[cfg_attr(not(feature = "wallet"), allow(unused_variables))]
+fn derive_identity(config: &super::NodeConfig, mnemonic: &str) -> NineSResult<Identity> {
+    #[cfg(feature = "wallet")]
+    {
+        let kc = config.open_keychain()?;
+        kc.import_seed(mnemonic)?;
+        Identity::from_seed(&kc.derive_protocol_seed(Protocol::Nostr)?)
+    }
+    #[cfg(not(feature = "wallet"))]
+    { Identity::from_mnemonic(mnemonic) }
+}
+
+/// Rewrite every scroll of `config`'s store into the same store under
+/// `new_key`. Returns the number of scrolls moved.
+pub fn rekey(config: &super::NodeConfig, new_key: &[u8]) -> NineSResult<usize> {
+    let scrolls = crate::store::snapshot(&config.open_store()?, "/")?;
+    let target = super::NodeConfig { master_key: new_key.to_vec(), ..config.clone() }.open_store()?;
+    for scroll in scrolls.values() {
+        target.write_scroll(scroll.clone())?;
+    }
+    Ok(scrolls.len())
+}
+
+#[cfg_attr(not(feature = "nostr"), allow(dead_code))]
@@ -0,0 +1,80 @@
+//! Sealed prefixes - PIN-keyed encryption on top of the store master key
+//!
+//! Scrolls under a sealed prefix are stored as `{"sealed": base64(nonce || ct)}`
+//! with the scroll key as AAD. The key only exists in memory while unlocked,
+//! so the store master key alone cannot read them.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use nine_s_core::prelude::*;
+use nine_s_store::crypto::{decrypt_with_aad, encrypt_with_aad, DerivedKey};
+use serde_json::{json, Value};
+
+/// Normalize `/journal/**`, `/journal/*` and `/journal/` to `/journal`
+pub fn normalize(prefix: &str) -> String {
+    prefix.trim_end_matches("**").trim_end_matches('*').trim_end_matches('/').to_string()
+}
+
+/// First configured prefix covering `path`
+pub fn matching<'a>(prefixes: &'a [String], path: &str) -> Option<&'a str> {
+    prefixes.iter()
+        .find(|p| path == p.as_str() || path.strip_prefix(p.as_str()).is_some_and(|rest| rest.starts_with('/')))
+        .map(|p| p.as_str())
+}
+
+/// The first of `roots` that `prefix` covers or sits under: paths the node's
+/// own services write without the PIN key, so they can't be sealed
+pub fn reserved<'a>(prefix: &str, roots: &[&'a str]) -> Option<&'a str> {
+    roots.iter().copied().find(|root| matching(&[prefix.to_string()], root).is_some() || matching(&[root.to_string()], prefix).is_some())
+}
+
+pub fn locked(prefix: &str) -> NineSError {
+    NineSError::Other(format!("locked prefix: {}", prefix))
+}
+
+pub fn seal(key: &DerivedKey, path: &str, data: &Value) -> NineSResult<Value> {
+    let plaintext = serde_json::to_vec(data).map_err(|e| NineSError::Other(e.to_string()))?;
+    let (nonce, ciphertext) = encrypt_with_aad(key, &plaintext, path.as_bytes())?;
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(json!({"sealed": BASE64.encode(blob)}))
+}
+
+pub fn open(key: &DerivedKey, mut scroll: Scroll) -> NineSResult<Scroll> {
+    let Some(b64) = scroll.data["sealed"].as_str() else {
+        return Err(NineSError::Other(format!("unsealed data under a sealed prefix: {}", scroll.key)));
+    };
+    let blob = BASE64.decode(b64).map_err(|e| NineSError::Other(format!("base64: {}", e)))?;
+    if blob.len() < 12 { return Err(NineSError::Other("sealed scroll truncated".into())); }
+    let nonce: [u8; 12] = blob[..12].try_into().expect("12 bytes");
+    let plaintext = decrypt_with_aad(key, &nonce, &blob[12..], scroll.key.as_bytes())?;
+    scroll.data = serde_json::from_slice(&plaintext).map_err(|e| NineSError::Other(e.to_string()))?;
+    Ok(scroll)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching() {
+        let prefixes = vec![normalize("/journal/**")];
+        assert_eq!(matching(&prefixes, "/journal/2024/01"), Some("/journal"));
+        assert_eq!(matching(&prefixes, "/journal"), Some("/journal"));
+        assert_eq!(matching(&prefixes, "/journals/x"), None);
+    }
+
+    #[test]
+    fn test_open_rejects_plaintext() {
+        let key = DerivedKey([7u8; 32]);
+        let sealed = Scroll::new("/journal/day1", seal(&key, "/journal/day1", &json!({"text": "hi"})).unwrap());
+        assert_eq!(open(&key, sealed).unwrap().data["text"], "hi");
+        assert!(open(&key, Scroll::new("/journal/day1", json!({"text": "hi"}))).is_err());
+    }
+
+    #[test]
+    fn test_reserved() {
+        assert_eq!(reserved("/sys/notes", &["/sys"]), Some("/sys"));
+        assert_eq!(reserved("", &["/sys"]), Some("/sys"));
+        assert_eq!(reserved("/journal", &["/sys", "/wallet"]), None);
+    }
+}
@@ -0,0 +1,247 @@
+//! Services - the background tasks a serving node runs, supervised
+//!
+//! `Node::start_services(ServicesConfig, &Shutdown)` spawns the clock, the
+//! Mind, an EffectWorker (with the nostr handler when the node has nostr) and
+//! the nostr `RelayPool` (relay status and `/sys/alerts/nostr`), each under a supervisor that restarts it when it panics or returns an
+//! error, waiting `restart_delay` (doubling, capped at a minute) in between.
+//! A service that fails more than `max_restarts` times is left `failed`.
+//! Panics also leave a crash report under `/sys/crashes` (`runtime`).
+//! Shutdown aborts every service.
+//!
+//! Each service reports at `/sys/services/{name}`:
+//! `{name, state, restarts, error?, at}`, `state` being `running`,
+//! `restarting`, `failed` or `stopped`.
+//!
+//! Must be called from within a tokio runtime.
+
+use crate::clock::{start_clock_with_config, ClockConfig};
+use crate::core::paths::services as paths;
+use crate::core::time::now_secs;
+use crate::mind::{EffectConfig, EffectHandler, EffectWorker, Mind, MindConfig};
+use crate::runtime::{backoff, install_panic_hook, panic_message, report_crash, Shutdown};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Which services to run and how to restart them
+pub struct ServicesConfig {
+    /// Clock (Layer 0); on unless `without_clock`
+    pub clock: Option<ClockConfig>,
+    /// Mind over `/sys/mind/patterns` (plus `NodeConfig::with_mind` patterns)
+    pub mind: bool,
+    /// EffectWorker over `/external/**`
+    pub effects: Option<EffectConfig>,
+    pub handlers: Vec<(Option<String>, Box<dyn EffectHandler>)>,
+    /// Relay pool kept connected and reported on
+    #[cfg(feature = "nostr")]
+    pub relays: Option<Arc<crate::nostr::RelayPool>>,
+    pub restart_delay: Duration,
+    pub max_restarts: u32,
+    /// The node's sealed prefixes, kept out of the Mind's reach
+    pub(crate) sealed_prefixes: Vec<String>,
+}
+
+impl Default for ServicesConfig {
+    fn default() -> Self {
+        Self {
+            clock: Some(ClockConfig::default()),
+            mind: false,
+            effects: None,
+            handlers: Vec::new(),
+            #[cfg(feature = "nostr")]
+            relays: None,
+            restart_delay: Duration::from_secs(1),
+            max_restarts: 5,
+            sealed_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl ServicesConfig {
+    pub fn new() -> Self { Self::default() }
+    /// What `beenode serve` and the daemon run: the clock, the Mind and the
+    /// effect worker, which picks up requests queued while the node was down.
+    /// `Node::start_services` adds the handlers of every configured backend.
+    pub fn serving() -> Self {
+        Self::default().with_mind().with_effects(EffectConfig { process_existing: true, ..Default::default() })
+    }
+    pub fn with_clock(mut self, config: ClockConfig) -> Self { self.clock = Some(config); self }
+    pub fn without_clock(mut self) -> Self { self.clock = None; self }
+    pub fn with_mind(mut self) -> Self { self.mind = true; self }
+    pub fn with_effects(mut self, config: EffectConfig) -> Self { self.effects = Some(config); self }
+    /// Add an effect handler (turns effects on). See `EffectWorker::add_handler`.
+    pub fn with_handler(mut self, handler: Box<dyn EffectHandler>) -> Self {
+        self.effects.get_or_insert_with(EffectConfig::default);
+        self.handlers.push((None, handler));
+        self
+    }
+    /// Add a handler for `/external/{service}/**`. See `EffectWorker::with_handler`.
+    pub fn with_service(mut self, service: &str, handler: Box<dyn EffectHandler>) -> Self {
+        self.effects.get_or_insert_with(EffectConfig::default);
+        self.handlers.push((Some(service.to_string()), handler));
+        self
+    }
+    #[cfg(feature = "nostr")]
+    pub fn with_relays(mut self, pool: crate::nostr::RelayPool) -> Self { self.relays = Some(Arc::new(pool)); self }
+    /// The first of `names` that the clock pulses, to hang built-in patterns on
+    pub(crate) fn pulse(&self, names: &[&str]) -> Option<String> {
+        let clock = self.clock.as_ref()?;
+        names.iter().find(|n| clock.pulses.iter().any(|(p, _)| p == *n)).map(|n| n.to_string())
+    }
+    pub fn with_restarts(mut self, delay: Duration, max: u32) -> Self { self.restart_delay = delay; self.max_restarts = max; self }
+}
+
+/// Handles to the supervisors started by `Node::start_services`
+pub struct NodeRuntime {
+    services: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl NodeRuntime {
+    /// Names of the services being supervised
+    pub fn services(&self) -> Vec<&'static str> { self.services.iter().map(|(name, _)| *name).collect() }
+
+    /// Wait for every supervisor to stop (after shutdown is triggered)
+    pub async fn join(self) {
+        for (_, handle) in self.services { let _ = handle.await; }
+    }
+}
+
+/// Spawn the configured services on `store`
+pub(crate) fn start(store: Store, config: ServicesConfig, shutdown: &Shutdown) -> NodeRuntime {
+    install_panic_hook();
+    let mut services = Vec::new();
+    let policy = (config.restart_delay, config.max_restarts);
+
+    if let Some(clock) = config.clock {
+        let (arc, shutdown_for) = (Arc::new(store.clone()), shutdown.clone());
+        services.push(("clock", supervise(store.clone(), "clock", shutdown, policy, move || {
+            match start_clock_with_config(arc.clone(), clock.clone(), shutdown_for.subscribe()) {
+                Ok(handle) => tokio::spawn(async move { handle.await.map_err(|e| anyhow::anyhow!(e)) }),
+                Err(e) => tokio::spawn(async move { Err(anyhow::anyhow!("clock: {:?}", e)) }),
+            }
+        })));
+    }
+
+    if config.mind {
+        let (mind_store, mind_config) = (store.clone(), MindConfig { sealed_prefixes: config.sealed_prefixes.clone(), ..Default::default() });
+        services.push(("mind", supervise(store.clone(), "mind", shutdown, policy, move || {
+            // Mind's watch loop blocks, so it gets a thread of its own
+            let (store, config, runtime) = (mind_store.clone(), mind_config.clone(), tokio::runtime::Handle::current());
+            tokio::task::spawn_blocking(move || runtime.block_on(Mind::with_config(store, config).run()))
+        })));
+    }
+
+    if let Some(effects) = config.effects {
+        let mut worker = EffectWorker::new(store.clone()).with_config(effects);
+        for (service, handler) in config.handlers {
+            worker = match service {
+                Some(service) => worker.with_handler(&service, handler),
+                None => worker.add_handler(handler),
+            };
+        }
+        let worker = Arc::new(worker);
+        services.push(("effects", supervise(store.clone(), "effects", shutdown, policy, move || {
+            let worker = worker.clone();
+            tokio::spawn(async move { worker.run().await })
+        })));
+    }
+
+    #[cfg(feature = "nostr")]
+    if let Some(pool) = config.relays {
+        services.push(("relays", supervise(store.clone(), "relays", shutdown, policy, move || {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.run().await })
+        })));
+    }
+
+    NodeRuntime { services }
+}
+
+/// Run `spawn`'s task until shutdown, restarting it when it fails
+fn supervise(store: Store, name: &'static str, shutdown: &Shutdown, (delay, max): (Duration, u32), mut spawn: impl FnMut() -> JoinHandle<anyhow::Result<()>> + Send + 'static) -> JoinHandle<()> {
+    let mut stop = shutdown.subscribe();
+    tokio::spawn(async move {
+        let mut restarts = 0u32;
+        loop {
+            report(&store, name, "running", restarts, None);
+            let mut task = spawn();
+            let error = tokio::select! {
+                _ = stop.recv() => {
+                    task.abort();
+                    report(&store, name, "stopped", restarts, None);
+                    return;
+                }
+                result = &mut task => match result {
+                    Ok(Ok(())) => "exited".to_string(),
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) if e.is_panic() => {
+                        let message = panic_message(&*e.into_panic());
+                        report_crash(&store, name, &message);
+                        format!("panicked: {}", message)
+                    }
+                    Err(e) => e.to_string(),
+                },
+            };
+            tracing::warn!("service {} failed: {}", name, error);
+            if restarts >= max {
+                report(&store, name, "failed", restarts, Some(&error));
+                return;
+            }
+            report(&store, name, "restarting", restarts, Some(&error));
+            tokio::select! {
+                _ = stop.recv() => {
+                    report(&store, name, "stopped", restarts, Some(&error));
+                    return;
+                }
+                _ = tokio::time::sleep(backoff(delay, restarts)) => {}
+            }
+            restarts += 1;
+        }
+    })
+}
+
+fn report(store: &Store, name: &str, state: &str, restarts: u32, error: Option<&str>) {
+    let mut data = json!({"name": name, "state": state, "restarts": restarts, "at": now_secs()});
+    if let Some(error) = error { data["error"] = json!(error); }
+    if let Err(e) = store.write_scroll(Scroll::new(&format!("{}/{}", paths::PREFIX, name), data).set_type(paths::TYPE)) {
+        tracing::warn!("service {} status: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_then_stops() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-services").with_root(dir.path()).open_store().unwrap();
+        let shutdown = Shutdown::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        let handle = supervise(store.clone(), "flaky", &shutdown, (Duration::from_millis(1), 5), move || {
+            let run = counted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                if run < 2 { panic!("boom {}", run); }
+                std::future::pending::<()>().await;
+                Ok(())
+            })
+        });
+
+        for _ in 0..100 {
+            if runs.load(Ordering::SeqCst) >= 3 { break; }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = store.read("/sys/services/flaky").unwrap().unwrap();
+        assert_eq!((status.data["state"].as_str(), status.data["restarts"].as_u64()), (Some("running"), Some(2)));
+        assert_eq!(store.list("/sys/crashes").unwrap().len(), 2);
+
+        shutdown.trigger().await;
+        handle.await.unwrap();
+        assert_eq!(store.read("/sys/services/flaky").unwrap().unwrap().data["state"], "stopped");
+    }
+}
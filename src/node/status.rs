@@ -0,0 +1,140 @@
+//! Status - One scroll a UI can poll for the overview screen
+//!
+//! `/sys/status` is computed on every read:
+//!
+//! | Field | Source |
+//! |-------|--------|
+//! | `version`, `uptime_secs` | build, node start |
+//! | `locked`, `initialized`, `auth_mode` | auth state |
+//! | `degraded` | backends that failed to mount |
+//! | `clock` | `/sys/clock/tick` |
+//! | `store` | `{scrolls, corrupt}`; `scrolls` is counted once, then kept up to date from the store's writes; `corrupt` from `/sys/store/health` when `verify_on_open` ran |
+//! | `wallet` | `{network, ready, last_sync, named}` from `/wallet/status` and `/wallet/sync/state` |
+//! | `nostr` | `{relays, connected}` from `/nostr/relays/*/status` |
+//! | `effects` | `{pending}` from `/sys/effects/queue` |
+//!
+//! While locked only the first four rows are filled in.
+//!
+//! `/sys/capabilities` is `core::capabilities::describe()` plus which
+//! backends this node was configured with (`enabled`); `lightning` is on
+//! once zaps have an LNURL server in `/nostr/zaps/config`.
+
+use super::NodeInner;
+use crate::core::paths::{capabilities, clock, effects, status as paths, store_health};
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Scrolls per app. The first status poll lists the store once; after that a
+/// watcher counts each key's first version so polling stays cheap.
+#[derive(Default)]
+pub struct ScrollCounts(Mutex<HashMap<String, Arc<AtomicUsize>>>);
+
+impl ScrollCounts {
+    fn get(&self, config: &super::NodeConfig) -> NineSResult<usize> {
+        let mut counts = self.0.lock().map_err(|_| NineSError::Other("status lock".into()))?;
+        if let Some(count) = counts.get(&config.app) { return Ok(count.load(Ordering::Relaxed)); }
+        let store = config.open_store()?;
+        let rx = store.watch(&WatchPattern::parse("/**")?)?;
+        let count = Arc::new(AtomicUsize::new(store.list("/")?.len()));
+        let counter = count.clone();
+        std::thread::spawn(move || {
+            let _store = store;
+            while let Ok(scroll) = rx.recv() {
+                if scroll.metadata.version == 1 { counter.fetch_add(1, Ordering::Relaxed); }
+            }
+        });
+        counts.insert(config.app.clone(), count.clone());
+        Ok(count.load(Ordering::Relaxed))
+    }
+}
+
+impl NodeInner {
+    pub(super) fn status(&self) -> NineSResult<Scroll> {
+        let mut status = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "locked": self.locked,
+            "initialized": self.auth_initialized,
+            "auth_mode": self.auth_mode.as_str(),
+            "degraded": self.degraded,
+            "clock": self.shell.get(clock::TICK)?.map(|s| s.data),
+        });
+        if !self.locked {
+            let store = self.config.open_store()?;
+            let corrupt = store.read(store_health::PATH)?.map(|s| s.data["corrupt"].clone());
+            status["store"] = json!({"scrolls": self.scrolls.get(&self.config)?, "corrupt": corrupt});
+            status["wallet"] = self.wallet_status(&store)?;
+            status["nostr"] = self.nostr_status(&store)?;
+            let queue = self.shell.get(&format!("{}{}", effects::PREFIX, effects::QUEUE))?;
+            status["effects"] = json!({"pending": queue.map(|q| q.data["count"].clone()).unwrap_or(json!(0))});
+        }
+        Ok(Scroll::new(paths::PATH, status).set_type(paths::TYPE))
+    }
+
+    pub(super) fn capabilities(&self) -> Scroll {
+        let mut caps = crate::core::capabilities::describe();
+        #[cfg(feature = "wallet")]
+        let wallet = (self.config.wallet.is_some() || !self.config.wallets.is_empty()) && !self.degraded.iter().any(|d| d == "wallet");
+        #[cfg(not(feature = "wallet"))]
+        let wallet = false;
+        #[cfg(feature = "nostr")]
+        let nostr = self.config.nostr.is_some() && !self.degraded.iter().any(|d| d == "nostr");
+        #[cfg(not(feature = "nostr"))]
+        let nostr = false;
+        // Lightning means zaps, which need the LNURL server configured
+        #[cfg(feature = "nostr")]
+        let lightning = nostr && self.shell.get(crate::core::paths::nostr::ZAPS_CONFIG).ok().flatten().is_some();
+        #[cfg(not(feature = "nostr"))]
+        let lightning = false;
+        caps["enabled"] = json!({
+            "wallet": wallet,
+            "nostr": nostr,
+            "lightning": lightning,
+            "mind": self.config.enable_mind,
+            "replication": self.config.replication,
+            "journal": self.config.journal,
+            "follower": self.config.follow.is_some(),
+            "pin": self.auth_mode == super::AuthMode::Pin,
+            "proxy": self.config.proxy.is_some(),
+        });
+        Scroll::new(capabilities::PATH, caps).set_type(capabilities::TYPE)
+    }
+
+    #[cfg(feature = "wallet")]
+    fn wallet_status(&self, store: &nine_s_store::Store) -> NineSResult<Value> {
+        use crate::core::paths::wallet;
+        let named: Vec<_> = self.config.wallets.keys().collect();
+        let Some(ref cfg) = self.config.wallet else {
+            return Ok(if named.is_empty() { Value::Null } else { json!({"mounted": self.wallet_mounted, "named": named}) });
+        };
+        let last = store.read(wallet::SYNC_STATE)?.map(|s| s.data);
+        Ok(json!({
+            "named": named,
+            "network": cfg.network.as_str(),
+            "mounted": self.wallet_mounted,
+            "ready": self.shell.get("/wallet/status").ok().flatten().is_some_and(|s| s.data["ready"] == true),
+            "last_sync": last.as_ref().map(|l| l["synced_at"].clone()),
+            "balance": last.map(|l| json!({"confirmed": l["confirmed"], "pending": l["pending"]})),
+        }))
+    }
+
+    #[cfg(not(feature = "wallet"))]
+    fn wallet_status(&self, _: &nine_s_store::Store) -> NineSResult<Value> { Ok(Value::Null) }
+
+    #[cfg(feature = "nostr")]
+    fn nostr_status(&self, store: &nine_s_store::Store) -> NineSResult<Value> {
+        use crate::core::paths::nostr;
+        let Some(ref cfg) = self.config.nostr else { return Ok(Value::Null) };
+        let mut connected = 0;
+        for key in store.list(nostr::RELAYS_PREFIX)?.into_iter().filter(|k| k.ends_with("/status")) {
+            if store.read(&key)?.is_some_and(|s| s.data["connected"] == true) { connected += 1; }
+        }
+        Ok(json!({"relays": cfg.relays.len(), "connected": connected}))
+    }
+
+    #[cfg(not(feature = "nostr"))]
+    fn nostr_status(&self, _: &nine_s_store::Store) -> NineSResult<Value> { Ok(Value::Null) }
+}
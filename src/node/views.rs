@@ -0,0 +1,156 @@
+//! Views - persisted, named BSE pipelines
+//!
+//! A view at `/sys/views/{name}` is `{dsl, source, materialize?, pulse?}`.
+//! Source scrolls become blocks `{key, type, data, version, updated_at}`, so
+//! DSLs address fields as `type` and `data.amount`. Materialized views are
+//! re-evaluated on source writes (or a clock pulse) into `/views/{name}`.
+//! Each view has at most one watcher: redefining it retires the old one.
+
+use crate::core::bse::{parse_dsl, BSEEngine};
+use crate::core::paths::{clock, views as paths};
+use nine_s_core::prelude::*;
+use nine_s_shell::Shell;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Anything that can list and read scrolls
+pub trait ScrollSource {
+    fn keys(&self, prefix: &str) -> NineSResult<Vec<String>>;
+    fn scroll(&self, key: &str) -> NineSResult<Option<Scroll>>;
+}
+
+impl ScrollSource for Shell {
+    fn keys(&self, prefix: &str) -> NineSResult<Vec<String>> { self.all(prefix) }
+    fn scroll(&self, key: &str) -> NineSResult<Option<Scroll>> { self.get(key) }
+}
+
+impl ScrollSource for Store {
+    fn keys(&self, prefix: &str) -> NineSResult<Vec<String>> { self.list(prefix) }
+    fn scroll(&self, key: &str) -> NineSResult<Option<Scroll>> { self.read(key) }
+}
+
+/// View definition scroll
+pub fn definition(name: &str, dsl: &str, source: &str, materialize: bool) -> NineSResult<Scroll> {
+    parse_dsl(dsl).map_err(|e| NineSError::Other(format!("view {}: {}", name, e)))?;
+    Ok(Scroll::new(&format!("{}/{}", paths::PREFIX, name), json!({
+        "dsl": dsl,
+        "source": source,
+        "materialize": materialize,
+    })).set_type(paths::TYPE))
+}
+
+/// Evaluate a named view: `{name, source, count, nodes}` or `{..., rows}` when the DSL has no `c//`
+pub fn evaluate(src: &dyn ScrollSource, name: &str) -> NineSResult<Value> {
+    let def = src.scroll(&format!("{}/{}", paths::PREFIX, name))?
+        .ok_or_else(|| NineSError::Other(format!("no view: {}", name)))?;
+    let dsl = def.data["dsl"].as_str().ok_or_else(|| NineSError::Other(format!("view {}: no 'dsl'", name)))?;
+    let source = def.data["source"].as_str().unwrap_or("/");
+    let pipeline = parse_dsl(dsl).map_err(|e| NineSError::Other(format!("view {}: {}", name, e)))?;
+
+    let mut blocks = Vec::new();
+    for key in src.keys(source)? {
+        if let Some(s) = src.scroll(&key)? {
+            blocks.push(json!({
+                "key": s.key,
+                "type": s.type_,
+                "data": s.data,
+                "version": s.metadata.version,
+                "updated_at": s.metadata.updated_at,
+            }));
+        }
+    }
+
+    let mut out = json!({"name": name, "source": source});
+    let err = |e: anyhow::Error| NineSError::Other(format!("view {}: {}", name, e));
+    if BSEEngine::renders(&pipeline) {
+        let nodes = BSEEngine::evaluate(&pipeline, &blocks).map_err(err)?;
+        out["count"] = json!(nodes.len());
+        out["nodes"] = serde_json::to_value(nodes).map_err(|e| NineSError::Other(e.to_string()))?;
+    } else {
+        let rows = BSEEngine::select(&pipeline, &blocks).map_err(err)?;
+        out["count"] = json!(rows.len());
+        out["rows"] = Value::Array(rows);
+    }
+    Ok(out)
+}
+
+/// Evaluate and write `/views/{name}`
+pub fn materialize(store: &Store, name: &str) -> NineSResult<Scroll> {
+    let result = evaluate(store, name)?;
+    store.write_scroll(Scroll::new(&format!("{}/{}", paths::MATERIALIZED_PREFIX, name), result).set_type(paths::RESULT_TYPE))
+}
+
+/// The live watcher of each materialized view, by name
+#[derive(Default)]
+pub struct Watchers(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl Watchers {
+    /// Retire `name`'s watcher, if any, and hand out the stop flag for the next one
+    fn replace(&self, name: &str) -> NineSResult<Arc<AtomicBool>> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut live = self.0.lock().map_err(|_| NineSError::Other("views lock".into()))?;
+        if let Some(old) = live.insert(name.to_string(), stop.clone()) { old.store(true, Ordering::SeqCst); }
+        Ok(stop)
+    }
+
+    /// Retire `name`'s watcher (the view is no longer materialized)
+    pub fn stop(&self, name: &str) {
+        if let Some(old) = self.0.lock().ok().and_then(|mut live| live.remove(name)) { old.store(true, Ordering::SeqCst); }
+    }
+}
+
+/// Keep a materialized view current: re-evaluate on source writes or on `pulse`.
+/// Replaces any watcher `watchers` already holds for `name`.
+pub fn watch(store: Store, name: String, watchers: &Watchers) -> NineSResult<()> {
+    let Some(def) = store.read(&format!("{}/{}", paths::PREFIX, name))? else { return Ok(()) };
+    let trigger = match def.data["pulse"].as_str() {
+        Some(pulse) => format!("{}/{}", clock::PULSES, pulse),
+        None => format!("{}/**", def.data["source"].as_str().unwrap_or("/").trim_end_matches('/')),
+    };
+    let rx = store.watch(&WatchPattern::parse(&trigger)?)?;
+    let stop = watchers.replace(&name)?;
+    materialize(&store, &name)?;
+    std::thread::spawn(move || {
+        while let Ok(scroll) = rx.recv() {
+            if stop.load(Ordering::SeqCst) { break; }
+            if scroll.key.starts_with(paths::MATERIALIZED_PREFIX) { continue; }
+            if let Err(e) = materialize(&store, &name) {
+                tracing::warn!("view {}: {}", name, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Names of views flagged `materialize: true`
+pub fn materialized(src: &dyn ScrollSource) -> NineSResult<Vec<String>> {
+    let mut names = Vec::new();
+    for key in src.keys(paths::PREFIX)? {
+        let Some(s) = src.scroll(&key)? else { continue };
+        if s.data["materialize"].as_bool().unwrap_or(false) {
+            names.push(key.trim_start_matches(paths::PREFIX).trim_start_matches('/').to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redefining_retires_the_old_watcher() {
+        let watchers = Watchers::default();
+        let first = watchers.replace("by-category").unwrap();
+        let second = watchers.replace("by-category").unwrap();
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!second.load(Ordering::SeqCst));
+
+        watchers.stop("by-category");
+        assert!(second.load(Ordering::SeqCst));
+        assert!(watchers.0.lock().unwrap().is_empty());
+    }
+}
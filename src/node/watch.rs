@@ -0,0 +1,52 @@
+//! Delivery threads for `Node::on_with` / `Node::watch_with`
+
+use crate::core::watch::{WatchOptions, Window};
+use nine_s_core::prelude::*;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Forward what `next` yields through `map`, held in `options` windows when debounced
+pub(crate) fn deliver<T: Send + 'static>(mut next: impl FnMut() -> Option<Scroll> + Send + 'static, options: WatchOptions, map: impl Fn(Scroll) -> Option<T> + Send + 'static) -> Receiver<T> {
+    let (tx, out) = mpsc::channel();
+    if options.debounce_ms == 0 {
+        std::thread::spawn(move || {
+            while let Some(scroll) = next() {
+                let Some(item) = map(scroll) else { continue };
+                if tx.send(item).is_err() { break; }
+            }
+        });
+        return out;
+    }
+
+    let (held_tx, held) = mpsc::channel();
+    std::thread::spawn(move || {
+        while let Some(scroll) = next() {
+            let key = scroll.key.clone();
+            let Some(item) = map(scroll) else { continue };
+            if held_tx.send((key, item)).is_err() { break; }
+        }
+    });
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        let now = || start.elapsed().as_millis() as u64;
+        let mut window = Window::new(options);
+        loop {
+            let next = match window.deadline() {
+                Some(deadline) => held.recv_timeout(Duration::from_millis(deadline.saturating_sub(now()))),
+                None => held.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let closed = match next {
+                Ok((key, item)) => { window.push(&key, item, now()); false }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+            if closed || window.deadline().is_some_and(|d| now() >= d) {
+                for item in window.flush() {
+                    if tx.send(item).is_err() { return; }
+                }
+            }
+            if closed { break; }
+        }
+    });
+    out
+}
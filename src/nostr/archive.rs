@@ -0,0 +1,72 @@
+//! Archive - Local mirror of the node's own Nostr events
+//!
+//! Every authored event is kept at `/nostr/archive/{kind}/{event_id}`.
+//! Publishes archive immediately, and the node's `RelayPool` (`with_archive`)
+//! keeps a subscription to its own pubkey open on every relay, so events
+//! published elsewhere with the same key land here too. `/external/nostr/archive/{id}`
+//! back-fills from relays since the last cursor (`backfill_pattern`, which
+//! `Node::start_services` registers on `refresh`, else `hour`), so history
+//! survives relay loss.
+
+use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::pattern::PatternDef;
+use crate::nostr::state::{pulse_pattern, scroll, Cursor};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+const CURSOR: &str = "/nostr/archive/_cursor";
+
+/// Persist one event. Returns false if it was already archived.
+pub fn store_event(store: &Store, event: &nostr::Event) -> NineSResult<bool> {
+    let key = format!("{}/{}/{}", paths::ARCHIVE_PREFIX, event.kind.as_u16(), event.id.to_hex());
+    if store.read(&key)?.is_some() { return Ok(false); }
+    let data = serde_json::to_value(event).map_err(|e| NineSError::Other(format!("event: {}", e)))?;
+    store.write_scroll(scroll(&key, types::ARCHIVE, data))?;
+    Ok(true)
+}
+
+/// Archive `event` if `author` signed it, moving the cursor forward.
+/// Returns false for duplicates and other authors' events.
+pub fn keep(store: &Store, author: &str, event: &nostr::Event) -> NineSResult<bool> {
+    if event.pubkey.to_hex() != author || event.verify().is_err() { return Ok(false); }
+    let kept = store_event(store, event)?;
+    advance(store, std::slice::from_ref(event))?;
+    Ok(kept)
+}
+
+/// REQ filter for `author`'s events since `since`
+pub fn filter(author: &str, since: Option<u64>) -> Value {
+    let mut filter = json!({"authors": [author]});
+    if let Some(since) = since { filter["since"] = json!(since); }
+    filter
+}
+
+/// Newest `created_at` archived
+pub fn cursor(store: &Store) -> NineSResult<Option<u64>> {
+    Ok(Cursor::load(store, CURSOR, types::ARCHIVE)?.since())
+}
+
+/// Move the cursor past `events`
+pub fn advance(store: &Store, events: &[nostr::Event]) -> NineSResult<()> {
+    Cursor::load(store, CURSOR, types::ARCHIVE)?.advance(store, events)
+}
+
+/// Archived event counts by kind
+pub fn summary(store: &Store) -> NineSResult<Value> {
+    let mut kinds = serde_json::Map::new();
+    let mut total = 0;
+    for key in store.list(paths::ARCHIVE_PREFIX)? {
+        let rest = &key[paths::ARCHIVE_PREFIX.len()..];
+        let Some(kind) = rest.trim_start_matches('/').split('/').next().filter(|k| k.parse::<u16>().is_ok()) else { continue };
+        let n = kinds.entry(kind.to_string()).or_insert(json!(0));
+        *n = json!(n.as_u64().unwrap_or(0) + 1);
+        total += 1;
+    }
+    Ok(json!({"kinds": kinds, "count": total, "since": cursor(store)?}))
+}
+
+/// Mind pattern that back-fills the archive on a clock pulse
+pub fn backfill_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("archive", pulse, types::ARCHIVE, paths::EXTERNAL_ARCHIVE, json!({"trigger": pulse}))
+}
@@ -0,0 +1,192 @@
+//! Backup - Encrypted store snapshots on the node's relays
+//!
+//! `/nostr/backup` snapshots the store (minus `/external` and this module's
+//! own state) and publishes it as NIP-78 application-data events (kind
+//! 30078) signed by the node's key and NIP-44 encrypted to itself, so the
+//! mnemonic alone is enough to find and read it again.
+//!
+//! Scrolls are spread over 256 buckets by key hash; a bucket is one or more
+//! `beenode-backup/{hash}/{n}` events of whole scrolls, at most `PART_MAX`
+//! bytes each, `hash` covering the bucket's content. The manifest
+//! (`beenode-backup/manifest`) maps buckets to hashes. Backups are
+//! differential: a bucket whose hash is in the previous manifest isn't
+//! published again (`full: true` republishes everything, e.g. after
+//! changing relays). Relays keep the newest event per `d` tag.
+//!
+//! `/nostr/backup/restore` fetches the newest manifest and its chunks and
+//! writes the scrolls back, skipping paths that already exist unless
+//! `overwrite: true`. On a fresh node: `beenode init --mnemonic ...` with the
+//! same relays, then write `/nostr/backup/restore`.
+//!
+//! The outcome of the last run is at `/nostr/backup/state`.
+
+use crate::core::paths::{mind, nostr as paths, nostr_types as types, origin};
+use crate::core::time::now_secs;
+use crate::store::Snapshot;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// NIP-78 application-specific data (parameterized replaceable)
+pub const KIND: u16 = 30078;
+/// `d` tag of the manifest; chunks are `{D_PREFIX}/{hash}/{n}`
+pub const MANIFEST_D: &str = "beenode-backup/manifest";
+pub const D_PREFIX: &str = "beenode-backup";
+/// Largest chunk before encryption (NIP-44 takes up to 64 KiB)
+pub const PART_MAX: usize = 32 * 1024;
+
+/// What a backup run has to publish
+#[derive(Debug)]
+pub struct Plan {
+    pub manifest: Value,
+    /// `(d tag, plaintext)` of each chunk to publish
+    pub chunks: Vec<(String, String)>,
+    /// Buckets already on relays from the previous backup
+    pub reused: usize,
+}
+
+/// Paths left out of backups
+pub fn skipped(key: &str) -> bool {
+    key == mind::EXTERNAL_PREFIX || key.starts_with(&format!("{}/", mind::EXTERNAL_PREFIX)) || key.starts_with(paths::BACKUP_STATE)
+}
+
+/// Split `snapshot` into buckets and chunks, keeping buckets unchanged since `previous`
+pub fn plan(snapshot: &Snapshot, previous: Option<&Value>, full: bool) -> NineSResult<Plan> {
+    let mut buckets: BTreeMap<String, Vec<&Scroll>> = BTreeMap::new();
+    for scroll in snapshot.values().filter(|s| !skipped(&s.key)) {
+        buckets.entry(blake3::hash(scroll.key.as_bytes()).to_hex()[..2].to_string()).or_default().push(scroll);
+    }
+    let (mut entries, mut chunks, mut reused) = (serde_json::Map::new(), Vec::new(), 0);
+    for (bucket, scrolls) in buckets {
+        let parts = parts(&scrolls)?;
+        let hash = blake3::hash(parts.join("\n").as_bytes()).to_hex()[..32].to_string();
+        if !full && previous.is_some_and(|m| m["buckets"][&bucket]["hash"] == hash.as_str()) {
+            reused += 1;
+        } else {
+            chunks.extend(parts.iter().enumerate().map(|(n, p)| (chunk_d(&hash, n), p.clone())));
+        }
+        entries.insert(bucket, json!({"hash": hash, "parts": parts.len(), "scrolls": scrolls.len()}));
+    }
+    let count: usize = entries.values().map(|b| b["scrolls"].as_u64().unwrap_or(0) as usize).sum();
+    Ok(Plan { manifest: json!({"version": 1, "created_at": now_secs(), "scrolls": count, "buckets": entries}), chunks, reused })
+}
+
+/// Whole scrolls as JSON arrays of at most `PART_MAX` bytes (a larger scroll gets a part alone)
+fn parts(scrolls: &[&Scroll]) -> NineSResult<Vec<String>> {
+    let mut out: Vec<String> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut size = 0;
+    for scroll in scrolls {
+        let s = serde_json::to_string(scroll).map_err(|e| NineSError::Other(format!("backup {}: {}", scroll.key, e)))?;
+        if !current.is_empty() && size + s.len() + 1 > PART_MAX {
+            out.push(format!("[{}]", current.join(",")));
+            current.clear();
+            size = 0;
+        }
+        size += s.len() + 1;
+        current.push(s);
+    }
+    if !current.is_empty() { out.push(format!("[{}]", current.join(","))); }
+    Ok(out)
+}
+
+pub fn chunk_d(hash: &str, n: usize) -> String { format!("{}/{}/{}", D_PREFIX, hash, n) }
+
+/// Every chunk `d` tag a manifest refers to
+pub fn chunk_tags(manifest: &Value) -> Vec<String> {
+    manifest["buckets"].as_object().into_iter().flatten()
+        .flat_map(|(_, b)| {
+            let hash = b["hash"].as_str().unwrap_or_default().to_string();
+            (0..b["parts"].as_u64().unwrap_or(0) as usize).map(move |n| chunk_d(&hash, n))
+        })
+        .collect()
+}
+
+/// Scrolls of a manifest from its decrypted chunks (by `d` tag), plus the
+/// buckets that were missing or didn't match their hash
+pub fn assemble(manifest: &Value, chunks: &HashMap<String, String>) -> (Vec<Scroll>, Vec<String>) {
+    let (mut scrolls, mut missing) = (Vec::new(), Vec::new());
+    for (bucket, b) in manifest["buckets"].as_object().into_iter().flatten() {
+        let hash = b["hash"].as_str().unwrap_or_default();
+        let parts: Option<Vec<&String>> = (0..b["parts"].as_u64().unwrap_or(0) as usize).map(|n| chunks.get(&chunk_d(hash, n))).collect();
+        let Some(parts) = parts else { missing.push(bucket.clone()); continue };
+        let joined = parts.iter().map(|p| p.as_str()).collect::<Vec<_>>().join("\n");
+        if blake3::hash(joined.as_bytes()).to_hex()[..32] != *hash { missing.push(bucket.clone()); continue; }
+        for part in parts {
+            match serde_json::from_str::<Vec<Scroll>>(part) {
+                Ok(s) => scrolls.extend(s),
+                Err(_) => { missing.push(bucket.clone()); break; }
+            }
+        }
+    }
+    (scrolls, missing)
+}
+
+/// NIP-44 to our own key
+pub fn seal(keys: &nostr::Keys, plaintext: &str) -> NineSResult<String> {
+    nostr::nips::nip44::encrypt(keys.secret_key(), &keys.public_key(), plaintext, nostr::nips::nip44::Version::V2)
+        .map_err(|e| NineSError::Other(format!("backup encrypt: {}", e)))
+}
+
+pub fn open(keys: &nostr::Keys, content: &str) -> NineSResult<String> {
+    nostr::nips::nip44::decrypt(keys.secret_key(), &keys.public_key(), content)
+        .map_err(|e| NineSError::Other(format!("backup decrypt: {}", e)))
+}
+
+/// Last run's outcome; `manifest` is what the next backup diffs against
+pub fn state(store: &Store) -> NineSResult<Option<Value>> {
+    Ok(store.read(paths::BACKUP_STATE)?.map(|s| s.data))
+}
+
+pub fn set_state(store: &Store, data: Value) -> NineSResult<Scroll> {
+    store.write_scroll(Scroll {
+        key: paths::BACKUP_STATE.into(),
+        type_: types::BACKUP.into(),
+        metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(n: usize) -> Snapshot {
+        (0..n).map(|i| {
+            let key = format!("/notes/{}", i);
+            (key.clone(), Scroll::new(&key, json!({"text": "x".repeat(100)})))
+        }).chain([("/external/nostr/dm/1".to_string(), Scroll::new("/external/nostr/dm/1", json!({}))) ]).collect()
+    }
+
+    #[test]
+    fn test_plan_is_differential_and_assembles() {
+        let mut snap = snapshot(600);
+        let first = plan(&snap, None, false).unwrap();
+        assert_eq!(first.manifest["scrolls"], 600);
+        assert_eq!(first.reused, 0);
+        assert_eq!(chunk_tags(&first.manifest).len(), first.chunks.len());
+
+        snap.insert("/notes/0".into(), Scroll::new("/notes/0", json!({"text": "changed"})));
+        let second = plan(&snap, Some(&first.manifest), false).unwrap();
+        assert_eq!(second.reused, first.manifest["buckets"].as_object().unwrap().len() - 1);
+        assert_eq!(plan(&snap, Some(&first.manifest), true).unwrap().reused, 0);
+
+        let mut chunks: HashMap<String, String> = first.chunks.into_iter().collect();
+        chunks.extend(second.chunks);
+        let (scrolls, missing) = assemble(&second.manifest, &chunks);
+        assert!(missing.is_empty());
+        assert_eq!(scrolls.len(), 600);
+        assert!(scrolls.iter().any(|s| s.key == "/notes/0" && s.data["text"] == "changed"));
+
+        chunks.retain(|d, _| !d.ends_with("/0"));
+        assert!(!assemble(&second.manifest, &chunks).1.is_empty());
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let keys = nostr::Keys::generate();
+        assert_eq!(open(&keys, &seal(&keys, "[]").unwrap()).unwrap(), "[]");
+        assert!(open(&nostr::Keys::generate(), &seal(&keys, "[]").unwrap()).is_err());
+    }
+}
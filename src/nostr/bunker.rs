@@ -0,0 +1,183 @@
+//! Bunker - NIP-46 remote signing
+//!
+//! Other Nostr clients connect with the `bunker://{pubkey}?relay=...&secret=...`
+//! URI from `/nostr/bunker`. Polling `/external/nostr/bunker/{id}` reads kind
+//! 24133 requests addressed to us (NIP-44), answers `connect`, `ping` and
+//! `get_public_key` directly, and parks every `sign_event` at
+//! `/nostr/bunker/requests/{request_event_id}` with `status: pending` unless the
+//! client is connected and the config `policy` (a BSE match over a
+//! `nostr.sign` block `{client, kind, content}`) allows it. Pending requests are
+//! signed only on `/nostr/bunker/requests/{id}/approve` (with `pin` when the
+//! node uses PIN auth) and dropped on `/reject`.
+
+use crate::core::bse::{matches_block, parse_match};
+use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::time::now_secs;
+use crate::nostr::state::scroll;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use nostr::Tag;
+use serde_json::{json, Value};
+
+/// NIP-46 request/response kind
+pub const KIND: u16 = 24133;
+/// Block type policies match against
+pub const SIGN_BLOCK: &str = "nostr.sign";
+
+/// A decrypted NIP-46 request
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub id: String,
+    pub method: String,
+    pub params: Vec<String>,
+}
+
+impl Request {
+    pub fn parse(plaintext: &str) -> Result<Self, String> {
+        let v: Value = serde_json::from_str(plaintext).map_err(|e| format!("request: {}", e))?;
+        Ok(Self {
+            id: v["id"].as_str().ok_or("request has no id")?.to_string(),
+            method: v["method"].as_str().ok_or("request has no method")?.to_string(),
+            params: v["params"].as_array().map(|a| a.iter().map(|p| p.as_str().map(String::from).unwrap_or_else(|| p.to_string())).collect()).unwrap_or_default(),
+        })
+    }
+}
+
+/// Response payload for `id`
+pub fn response(id: &str, result: Result<String, String>) -> String {
+    match result {
+        Ok(r) => json!({"id": id, "result": r}).to_string(),
+        Err(e) => json!({"id": id, "result": "", "error": e}).to_string(),
+    }
+}
+
+/// `{enabled, secret, policy, clients}`
+pub fn config(store: &Store) -> NineSResult<Value> {
+    Ok(store.read(paths::BUNKER_CONFIG)?.map(|s| s.data).unwrap_or_else(|| json!({"enabled": false, "clients": []})))
+}
+
+/// Enable or reconfigure. `secret` defaults to a fresh random one; `policy` is validated.
+pub fn configure(store: &Store, data: &Value) -> NineSResult<Value> {
+    let mut cfg = config(store)?;
+    if let Some(policy) = data["policy"].as_str() {
+        parse_match(policy).map_err(|e| NineSError::Other(format!("policy: {}", e)))?;
+        cfg["policy"] = json!(policy);
+    }
+    cfg["enabled"] = json!(data["enabled"].as_bool().unwrap_or(true));
+    cfg["secret"] = match data["secret"].as_str() {
+        Some(s) => json!(s),
+        None if cfg["secret"].is_string() => cfg["secret"].clone(),
+        None => json!(crate::secret::random_hex(16)),
+    };
+    store.write_scroll(scroll(paths::BUNKER_CONFIG, types::BUNKER, cfg.clone()))?;
+    Ok(cfg)
+}
+
+pub fn is_client(cfg: &Value, pubkey: &str) -> bool {
+    cfg["clients"].as_array().is_some_and(|c| c.iter().any(|p| p == pubkey))
+}
+
+pub fn add_client(store: &Store, pubkey: &str) -> NineSResult<()> {
+    let mut cfg = config(store)?;
+    if is_client(&cfg, pubkey) { return Ok(()); }
+    let mut clients = cfg["clients"].as_array().cloned().unwrap_or_default();
+    clients.push(json!(pubkey));
+    cfg["clients"] = json!(clients);
+    store.write_scroll(scroll(paths::BUNKER_CONFIG, types::BUNKER, cfg))?;
+    Ok(())
+}
+
+/// True when a connected client's signing request passes the policy
+pub fn auto_approved(cfg: &Value, client: &str, template: &Value) -> bool {
+    let Some(policy) = cfg["policy"].as_str() else { return false };
+    if !is_client(cfg, client) { return false; }
+    let block = json!({
+        "key": format!("{}/{}", paths::BUNKER_REQUESTS, client),
+        "type": SIGN_BLOCK,
+        "data": {"client": client, "kind": template["kind"], "content": template["content"]},
+    });
+    parse_match(policy).is_ok_and(|m| matches_block(&m, &block))
+}
+
+/// Park a signing request for approval
+pub fn park(store: &Store, event_id: &str, client: &str, request: &Request, template: Value) -> NineSResult<Scroll> {
+    store.write_scroll(scroll(&request_key(event_id), types::BUNKER, json!({
+        "request_id": request.id,
+        "client": client,
+        "method": request.method,
+        "event": template,
+        "status": "pending",
+        "received_at": now_secs(),
+    })))
+}
+
+/// True once a request event has been parked (or settled)
+pub fn known(store: &Store, event_id: &str) -> NineSResult<bool> {
+    Ok(store.read(&request_key(event_id))?.is_some())
+}
+
+pub fn pending(store: &Store, event_id: &str) -> NineSResult<Value> {
+    let req = store.read(&request_key(event_id))?.ok_or_else(|| NineSError::Other(format!("no bunker request {}", event_id)))?.data;
+    if req["status"] != "pending" {
+        return Err(NineSError::Other(format!("request already {}", req["status"].as_str().unwrap_or("settled"))));
+    }
+    Ok(req)
+}
+
+pub fn settle(store: &Store, event_id: &str, status: &str, signed: Option<&str>) -> NineSResult<Scroll> {
+    let mut req = store.read(&request_key(event_id))?.map(|s| s.data).unwrap_or_else(|| json!({}));
+    req["status"] = json!(status);
+    req["signed_id"] = json!(signed);
+    req["settled_at"] = json!(now_secs());
+    store.write_scroll(scroll(&request_key(event_id), types::BUNKER, req))
+}
+
+/// Unsigned template `{kind, content, tags, created_at}` from `sign_event` params
+pub fn template(request: &Request) -> Result<Value, String> {
+    let raw = request.params.first().ok_or("sign_event needs an event")?;
+    let t: Value = serde_json::from_str(raw).map_err(|e| format!("event: {}", e))?;
+    if t["kind"].as_u64().is_none() { return Err("event has no kind".into()); }
+    Ok(t)
+}
+
+/// Sign a template with `keys`
+pub fn sign(keys: &nostr::Keys, t: &Value) -> Result<nostr::Event, String> {
+    let tags = t["tags"].as_array().into_iter().flatten()
+        .filter_map(|tag| tag.as_array())
+        .map(|parts| Tag::parse(&parts.iter().filter_map(|p| p.as_str().map(String::from)).collect::<Vec<_>>()).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let created_at = t["created_at"].as_u64().map(nostr::Timestamp::from).unwrap_or_else(nostr::Timestamp::now);
+    nostr::UnsignedEvent::new(keys.public_key(), created_at, nostr::Kind::Custom(t["kind"].as_u64().unwrap_or(1) as u16), tags, t["content"].as_str().unwrap_or_default())
+        .sign_with_keys(keys)
+        .map_err(|e| e.to_string())
+}
+
+/// `bunker://` connection URI
+pub fn uri(pubkey: &str, relays: &[String], secret: Option<&str>) -> String {
+    let mut query: Vec<String> = relays.iter().map(|r| format!("relay={}", r)).collect();
+    if let Some(s) = secret { query.push(format!("secret={}", s)); }
+    format!("bunker://{}?{}", pubkey, query.join("&"))
+}
+
+fn request_key(event_id: &str) -> String { format!("{}/{}", paths::BUNKER_REQUESTS, event_id) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_and_sign() {
+        let client = "c".repeat(64);
+        let cfg = json!({"policy": "x/type=nostr.sign/ g/data.kind=1/", "clients": [client]});
+        let req = Request::parse(&json!({"id": "1", "method": "sign_event", "params": [json!({"kind": 1, "content": "hi", "tags": [["t", "bee"]]}).to_string()]}).to_string()).unwrap();
+        let t = template(&req).unwrap();
+        assert!(auto_approved(&cfg, &client, &t));
+        assert!(!auto_approved(&cfg, &"d".repeat(64), &t));
+        assert!(!auto_approved(&cfg, &client, &json!({"kind": 4, "content": ""})));
+
+        let keys = nostr::Keys::generate();
+        let event = sign(&keys, &t).unwrap();
+        assert!(event.verify().is_ok());
+        assert_eq!(event.content, "hi");
+    }
+}
@@ -1,12 +1,22 @@
 //! Nostr relay client - tokio-tungstenite WebSocket
 //!
 //! Minimal implementation for connecting to relays and publishing events.
+//! With a proxy (`with_proxy`) the TCP connection is made through SOCKS5 and
+//! the relay hostname is resolved by the proxy.
 
+use crate::core::paths::{alerts, nostr as paths, nostr_types as types, origin};
 use futures_util::{SinkExt, StreamExt};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
 use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
+use crate::core::proxy::Proxy;
+use tokio::net::TcpStream;
 use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::tungstenite::{http::Uri, Message};
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
 
 /// Relay connection state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,11 +26,31 @@ pub enum RelayState {
     Connected,
 }
 
+/// Per-relay counters, shared with the reader/writer tasks
+#[derive(Debug, Default)]
+pub struct RelayStats {
+    pub events_sent: AtomicU64,
+    pub events_received: AtomicU64,
+    pub connects: AtomicU64,
+    /// Round trip of the last answered ping
+    pub latency_ms: AtomicU64,
+    pub last_error: std::sync::Mutex<Option<String>>,
+    ping_sent: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RelayStats {
+    fn set_error(&self, e: impl ToString) {
+        if let Ok(mut g) = self.last_error.lock() { *g = Some(e.to_string()); }
+    }
+}
+
 /// Nostr relay client
 pub struct RelayClient {
     url: String,
     state: Arc<RwLock<RelayState>>,
-    tx: Option<mpsc::Sender<String>>,
+    tx: Option<mpsc::Sender<Message>>,
+    stats: Arc<RelayStats>,
+    proxy: Option<Proxy>,
 }
 
 impl RelayClient {
@@ -29,22 +59,61 @@ impl RelayClient {
             url: url.into(),
             state: Arc::new(RwLock::new(RelayState::Disconnected)),
             tx: None,
+            stats: Arc::new(RelayStats::default()),
+            proxy: None,
         }
     }
 
+    /// Connect through a SOCKS5 proxy
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self { self.proxy = proxy; self }
+
     pub async fn state(&self) -> RelayState {
         *self.state.read().await
     }
 
+    pub fn stats(&self) -> Arc<RelayStats> { self.stats.clone() }
+
+    /// `{url, state, last_error, events_sent, events_received, latency_ms, reconnects}`
+    pub async fn status(&self) -> Value {
+        let s = &self.stats;
+        json!({
+            "url": self.url,
+            "state": match self.state().await { RelayState::Connected => "connected", RelayState::Connecting => "connecting", RelayState::Disconnected => "disconnected" },
+            "connected": self.state().await == RelayState::Connected,
+            "last_error": s.last_error.lock().ok().and_then(|g| g.clone()),
+            "events_sent": s.events_sent.load(Ordering::Relaxed),
+            "events_received": s.events_received.load(Ordering::Relaxed),
+            "latency_ms": s.latency_ms.load(Ordering::Relaxed),
+            "reconnects": s.connects.load(Ordering::Relaxed).saturating_sub(1)
+        })
+    }
+
+    async fn open(&self) -> anyhow::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let Some(ref proxy) = self.proxy else { return Ok(connect_async(&self.url).await?.0) };
+        let uri: Uri = self.url.parse()?;
+        let host = uri.host().ok_or_else(|| anyhow::anyhow!("relay url without host: {}", self.url))?;
+        let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+        let tcp = tokio_socks::tcp::Socks5Stream::connect(proxy.addr().as_str(), (host, port)).await?.into_inner();
+        Ok(client_async_tls(&self.url, tcp).await?.0)
+    }
+
     /// Connect to relay
     pub async fn connect(&mut self) -> anyhow::Result<mpsc::Receiver<String>> {
         *self.state.write().await = RelayState::Connecting;
 
-        let (ws, _) = connect_async(&self.url).await?;
+        let ws = match self.open().await {
+            Ok(ws) => ws,
+            Err(e) => {
+                self.stats.set_error(&e);
+                *self.state.write().await = RelayState::Disconnected;
+                return Err(e);
+            }
+        };
+        self.stats.connects.fetch_add(1, Ordering::Relaxed);
         let (mut write, mut read) = ws.split();
 
         // Channel for outgoing messages
-        let (out_tx, mut out_rx) = mpsc::channel::<String>(32);
+        let (out_tx, mut out_rx) = mpsc::channel::<Message>(32);
         self.tx = Some(out_tx);
 
         // Channel for incoming messages
@@ -55,23 +124,39 @@ impl RelayClient {
 
         // Spawn writer task
         let state_w = state.clone();
+        let stats_w = self.stats.clone();
         tokio::spawn(async move {
             while let Some(msg) = out_rx.recv().await {
-                if write.send(Message::Text(msg)).await.is_err() {
+                let is_event = matches!(&msg, Message::Text(t) if t.starts_with("[\"EVENT\""));
+                if let Err(e) = write.send(msg).await {
+                    stats_w.set_error(e);
                     break;
                 }
+                if is_event { stats_w.events_sent.fetch_add(1, Ordering::Relaxed); }
             }
             *state_w.write().await = RelayState::Disconnected;
         });
 
         // Spawn reader task
         let state_r = state.clone();
+        let stats_r = self.stats.clone();
         tokio::spawn(async move {
-            while let Some(Ok(msg)) = read.next().await {
-                if let Message::Text(txt) = msg {
-                    if in_tx.send(txt).await.is_err() {
-                        break;
+            loop {
+                match read.next().await {
+                    Some(Ok(Message::Text(txt))) => {
+                        if txt.starts_with("[\"EVENT\"") { stats_r.events_received.fetch_add(1, Ordering::Relaxed); }
+                        if in_tx.send(txt).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Pong(_))) => {
+                        if let Some(sent) = stats_r.ping_sent.lock().ok().and_then(|mut g| g.take()) {
+                            stats_r.latency_ms.store(sent.elapsed().as_millis() as u64, Ordering::Relaxed);
+                        }
                     }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => { stats_r.set_error(e); break; }
+                    None => break,
                 }
             }
             *state_r.write().await = RelayState::Disconnected;
@@ -82,8 +167,18 @@ impl RelayClient {
 
     /// Send raw message
     pub async fn send(&self, msg: &str) -> anyhow::Result<()> {
+        self.send_message(Message::Text(msg.to_string())).await
+    }
+
+    /// Ping the relay; the pong sets `latency_ms`
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        if let Ok(mut sent) = self.stats.ping_sent.lock() { *sent = Some(Instant::now()); }
+        self.send_message(Message::Ping(Vec::new())).await
+    }
+
+    async fn send_message(&self, msg: Message) -> anyhow::Result<()> {
         if let Some(tx) = &self.tx {
-            tx.send(msg.to_string()).await?;
+            tx.send(msg).await?;
             Ok(())
         } else {
             anyhow::bail!("Not connected")
@@ -147,10 +242,15 @@ pub enum RelayMessage {
     Notice { message: String },
 }
 
+/// Subscription id of the pool's own-events subscription (`with_archive`)
+const ARCHIVE_SUB: &str = "archive";
+
 /// Auto-reconnecting relay pool
 pub struct RelayPool {
     relays: Arc<RwLock<Vec<(String, RelayClient)>>>,
     shutdown: Arc<RwLock<bool>>,
+    store: Option<Arc<Store>>,
+    archive: Option<String>,
 }
 
 impl RelayPool {
@@ -159,30 +259,140 @@ impl RelayPool {
         Self {
             relays: Arc::new(RwLock::new(relays)),
             shutdown: Arc::new(RwLock::new(false)),
+            store: None,
+            archive: None,
         }
     }
 
-    /// Start pool with automatic reconnection
+    /// Write relay health to `/nostr/relays/{host}/status` and alerts to `/sys/alerts/nostr`
+    pub fn with_store(mut self, store: Arc<Store>) -> Self { self.store = Some(store); self }
+
+    /// Keep a subscription to events authored by `pubkey_hex` open on every
+    /// relay and mirror them into `/nostr/archive` (needs `with_store`)
+    pub fn with_archive(mut self, pubkey_hex: impl Into<String>) -> Self { self.archive = Some(pubkey_hex.into()); self }
+
+    /// Connect every relay through a SOCKS5 proxy
+    pub fn with_proxy(self, proxy: Option<Proxy>) -> Self {
+        if let Ok(mut relays) = self.relays.try_write() {
+            for (_, client) in relays.iter_mut() { client.proxy = proxy.clone(); }
+        }
+        self
+    }
+
+    /// Start pool with automatic reconnection. The maintenance task is
+    /// restarted (with backoff) if it panics, leaving a crash report.
     pub async fn start(&self) {
+        crate::runtime::install_panic_hook();
         let relays = self.relays.clone();
         let shutdown = self.shutdown.clone();
+        let store = self.store.clone();
+        let archive = self.archive.clone();
 
         tokio::spawn(async move {
+            let mut restarts = 0u32;
             loop {
-                if *shutdown.read().await { break; }
+                let task = tokio::spawn(Self::maintain(relays.clone(), shutdown.clone(), store.clone(), archive.clone()));
+                match task.await {
+                    Err(e) if e.is_panic() => {
+                        let message = crate::runtime::panic_message(&*e.into_panic());
+                        match &store {
+                            Some(store) => crate::runtime::report_crash(store, "nostr relays", &message),
+                            None => tracing::error!("nostr relays panicked: {}", message),
+                        }
+                        tokio::time::sleep(crate::runtime::backoff(std::time::Duration::from_secs(1), restarts)).await;
+                        restarts += 1;
+                    }
+                    _ => break,
+                }
+            }
+        });
+    }
+
+    /// Run the maintenance loop in place, for callers that supervise it
+    /// themselves (`ServicesConfig::with_relays`)
+    pub async fn run(&self) -> anyhow::Result<()> {
+        Self::maintain(self.relays.clone(), self.shutdown.clone(), self.store.clone(), self.archive.clone()).await;
+        Ok(())
+    }
+
+    /// Reconnect dropped relays and publish their status every 30s until shutdown
+    async fn maintain(relays: Arc<RwLock<Vec<(String, RelayClient)>>>, shutdown: Arc<RwLock<bool>>, store: Option<Arc<Store>>, archive: Option<String>) {
+        let mut all_down = false;
+        loop {
+            if *shutdown.read().await { break; }
 
-                let mut clients = relays.write().await;
-                for (url, client) in clients.iter_mut() {
-                    if client.state().await == RelayState::Disconnected {
-                        tracing::info!("Reconnecting to {}", url);
-                        let _ = client.connect().await;
+            let mut reconnected = false;
+            let mut clients = relays.write().await;
+            for (url, client) in clients.iter_mut() {
+                if client.state().await == RelayState::Disconnected {
+                    tracing::info!("Reconnecting to {}", url);
+                    match client.connect().await {
+                        Ok(inbox) => {
+                            reconnected = true;
+                            let follow = match (&store, &archive) {
+                                (Some(store), Some(author)) => {
+                                    let since = crate::nostr::archive::cursor(store).ok().flatten();
+                                    let _ = client.subscribe(ARCHIVE_SUB, vec![crate::nostr::archive::filter(author, since)]).await;
+                                    Some((store.clone(), author.clone()))
+                                }
+                                _ => None,
+                            };
+                            tokio::spawn(Self::follow(inbox, follow));
+                        }
+                        Err(e) => tracing::warn!("Relay {} unreachable: {}", url, e),
                     }
                 }
-                drop(clients);
+                if client.state().await == RelayState::Connected {
+                    let _ = client.ping().await;
+                }
+            }
+            drop(clients);
 
-                tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            // Reconnect drains the outbox via the effect worker (no origin, so the worker picks it up)
+            if let (true, Some(ref store)) = (reconnected, &store) {
+                let key = format!("{}/{:x}", paths::EXTERNAL_OUTBOX, nostr::Timestamp::now().as_u64());
+                let _ = store.write_scroll(Scroll::new(&key, json!({"trigger": "reconnect"})));
             }
-        });
+
+            if let Some(ref store) = store {
+                let statuses = Self::statuses(&relays).await;
+                for s in &statuses {
+                    let key = format!("{}/{}/status", paths::RELAYS_PREFIX, relay_host(s["url"].as_str().unwrap_or_default()));
+                    let _ = store.write_scroll(scroll(&key, types::RELAY_STATUS, s.clone()));
+                }
+                let down = !statuses.is_empty() && statuses.iter().all(|s| s["connected"] != true);
+                if down != all_down {
+                    all_down = down;
+                    let alert = json!({"active": down, "level": if down { "error" } else { "info" }, "message": if down { "all relays down" } else { "relays recovered" }, "relays": statuses.len()});
+                    let _ = store.write_scroll(scroll(alerts::NOSTR, alerts::TYPE, alert));
+                }
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        }
+    }
+
+    /// Read a relay's inbound stream until it closes, archiving our own events
+    async fn follow(mut inbox: mpsc::Receiver<String>, archive: Option<(Arc<Store>, String)>) {
+        while let Some(msg) = inbox.recv().await {
+            let (Some(RelayMessage::Event { sub_id, event }), Some((store, author))) = (parse_relay_message(&msg), &archive) else { continue };
+            if sub_id != ARCHIVE_SUB { continue; }
+            if let Err(e) = crate::nostr::archive::keep(store, author, &event) {
+                tracing::warn!("nostr archive: {}", e);
+            }
+        }
+    }
+
+    /// Current status of every relay in the pool
+    pub async fn status(&self) -> Vec<Value> {
+        Self::statuses(&self.relays).await
+    }
+
+    async fn statuses(relays: &RwLock<Vec<(String, RelayClient)>>) -> Vec<Value> {
+        let clients = relays.read().await;
+        let mut out = Vec::with_capacity(clients.len());
+        for (_, client) in clients.iter() { out.push(client.status().await); }
+        out
     }
 
     /// Publish to all connected relays
@@ -202,3 +412,41 @@ impl RelayPool {
         *self.shutdown.write().await = true;
     }
 }
+
+/// `wss://relay.damus.io/` → `relay.damus.io`
+pub fn relay_host(url: &str) -> &str {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+fn scroll(key: &str, type_: &str, data: Value) -> Scroll {
+    Scroll { key: key.into(), type_: type_.into(), metadata: Metadata::default().with_produced_by(origin::EFFECTS), data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys, Kind};
+
+    #[tokio::test]
+    async fn test_pool_archives_own_events() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(crate::node::NodeConfig::new("test-relay-archive").with_root(dir.path()).open_store().unwrap());
+        let (me, other) = (Keys::generate(), Keys::generate());
+        let mine = EventBuilder::new(Kind::TextNote, "mine", []).sign_with_keys(&me).unwrap();
+        let theirs = EventBuilder::new(Kind::TextNote, "theirs", []).sign_with_keys(&other).unwrap();
+
+        let (tx, rx) = mpsc::channel(8);
+        for (sub, event) in [(ARCHIVE_SUB, &mine), (ARCHIVE_SUB, &theirs), ("q1", &mine)] {
+            tx.send(json!(["EVENT", sub, event]).to_string()).await.unwrap();
+        }
+        tx.send(json!(["EOSE", ARCHIVE_SUB]).to_string()).await.unwrap();
+        drop(tx);
+        RelayPool::follow(rx, Some((store.clone(), me.public_key().to_hex()))).await;
+
+        let keys = store.list(paths::ARCHIVE_PREFIX).unwrap();
+        assert!(keys.contains(&format!("{}/1/{}", paths::ARCHIVE_PREFIX, mine.id.to_hex())));
+        assert!(!keys.iter().any(|k| k.ends_with(&theirs.id.to_hex())));
+        assert_eq!(crate::nostr::archive::cursor(&store).unwrap(), Some(mine.created_at.as_u64()));
+    }
+}
@@ -0,0 +1,133 @@
+//! Contacts - Kind-3 follow list mirrored into `/contacts/nostr/*`
+//!
+//! Syncing (`/external/nostr/contacts/{id}`) fetches our newest kind-3 event
+//! and writes one `/contacts/nostr/{pubkey}` scroll per `p` tag
+//! `{pubkey, relay, petname, mobi, profile, following}`; pubkeys dropped from
+//! the list are kept with `following: false`. Older events than the last one
+//! applied are ignored.
+//!
+//! Edits going the other way: any write under `/contacts/nostr/` not made by
+//! effects queues `{publish: true}`, which republishes the whole list (entries
+//! with `following: false` or null data are left out).
+
+use crate::core::paths::{nostr as paths, nostr_types as types, origin};
+use crate::core::pattern::PatternDef;
+use crate::core::time::{now_nanos, now_secs};
+use crate::nostr::state::{pulse_pattern, scroll};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// `created_at` of the last applied (or published) kind-3 event
+pub fn last_applied(store: &Store) -> NineSResult<Option<u64>> {
+    Ok(store.read(paths::CONTACTS_STATE)?.and_then(|s| s.data["created_at"].as_u64()))
+}
+
+pub fn set_applied(store: &Store, created_at: u64, count: usize) -> NineSResult<()> {
+    store.write_scroll(scroll(paths::CONTACTS_STATE, types::CONTACTS, json!({"created_at": created_at, "count": count, "synced_at": now_secs()})))?;
+    Ok(())
+}
+
+/// Materialize a kind-3 event. Returns the number of contacts followed, or
+/// `None` when the event is not newer than what we already have.
+pub fn apply(store: &Store, event: &nostr::Event) -> NineSResult<Option<usize>> {
+    let created_at = event.created_at.as_u64();
+    if last_applied(store)?.is_some_and(|seen| seen >= created_at) { return Ok(None); }
+
+    let mut followed = HashSet::new();
+    for tag in event.tags.iter() {
+        let t = tag.as_slice();
+        if t.first().map(String::as_str) != Some("p") { continue; }
+        let Some(pubkey) = t.get(1).filter(|pk| pk.len() == 64 && pk.chars().all(|c| c.is_ascii_hexdigit())) else { continue };
+        let relay = t.get(2).filter(|r| !r.is_empty());
+        let petname = t.get(3).filter(|p| !p.is_empty());
+        let mobi = crate::mobi::Mobi::derive(pubkey)?;
+        let profile = store.read(&format!("{}/{}", paths::PROFILES_PREFIX, pubkey))?.map(|s| s.data["metadata"].clone());
+        store.write_scroll(scroll(&key(pubkey), types::CONTACT, json!({
+            "pubkey": pubkey,
+            "relay": relay,
+            "petname": petname,
+            "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()},
+            "profile": profile,
+            "following": true,
+        })))?;
+        followed.insert(pubkey.clone());
+    }
+    for entry in entries(store)? {
+        let Some(pk) = entry.data["pubkey"].as_str() else { continue };
+        if !followed.contains(pk) && entry.data["following"] != false {
+            let mut data = entry.data.clone();
+            data["following"] = json!(false);
+            store.write_scroll(scroll(&entry.key, types::CONTACT, data))?;
+        }
+    }
+    set_applied(store, created_at, followed.len())?;
+    Ok(Some(followed.len()))
+}
+
+/// Followed contacts as `template` expects them: `[{pubkey, relay, petname}]`
+pub fn following(store: &Store) -> NineSResult<Vec<Value>> {
+    Ok(entries(store)?.into_iter()
+        .filter(|s| s.data.is_object() && s.data["following"] != false)
+        .map(|s| {
+            let raw = s.data["pubkey"].as_str().unwrap_or_else(|| s.key.rsplit('/').next().unwrap_or_default());
+            let pubkey = super::pubkey_hex(raw).unwrap_or_else(|_| raw.to_string());
+            json!({"pubkey": pubkey, "relay": s.data["relay"], "petname": s.data["petname"]})
+        })
+        .collect())
+}
+
+/// Contacts lacking a cached profile
+pub fn missing_profiles(store: &Store) -> NineSResult<Vec<String>> {
+    Ok(entries(store)?.into_iter()
+        .filter(|s| s.data["following"] != false && s.data["profile"].is_null())
+        .filter_map(|s| s.data["pubkey"].as_str().map(|pk| super::pubkey_hex(pk).unwrap_or_else(|_| pk.to_string())))
+        .collect())
+}
+
+/// Cache a fetched kind-0 on the contact and in `/nostr/profiles/{pubkey}`
+pub fn set_profile(store: &Store, event: &nostr::Event) -> NineSResult<()> {
+    let pubkey = event.pubkey.to_hex();
+    let metadata: Value = serde_json::from_str(&event.content).unwrap_or_else(|_| json!({}));
+    let mobi = crate::mobi::Mobi::derive(&pubkey)?;
+    store.write_scroll(scroll(&format!("{}/{}", paths::PROFILES_PREFIX, pubkey), types::PROFILE, json!({
+        "pubkey": pubkey,
+        "metadata": metadata,
+        "created_at": event.created_at.as_u64(),
+        "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()},
+    })))?;
+    if let Some(mut contact) = store.read(&key(&pubkey))? {
+        contact.data["profile"] = metadata;
+        store.write_scroll(scroll(&contact.key, types::CONTACT, contact.data))?;
+    }
+    Ok(())
+}
+
+/// Queue a republish whenever someone other than effects edits a contact
+pub fn watch(store: Store) -> NineSResult<()> {
+    let rx = store.watch(&WatchPattern::parse(&format!("{}/**", paths::CONTACTS_PREFIX))?)?;
+    std::thread::spawn(move || {
+        while let Ok(s) = rx.recv() {
+            if s.metadata.produced_by.as_deref() == Some(origin::EFFECTS) { continue; }
+            let req = Scroll::new(&format!("{}/{}", paths::EXTERNAL_CONTACTS, now_nanos()), json!({"publish": true, "changed": s.key}));
+            if let Err(e) = store.write_scroll(req) { tracing::warn!("contacts publish: {}", e); }
+        }
+    });
+    Ok(())
+}
+
+/// Mind pattern that re-syncs the follow list on a clock pulse
+pub fn sync_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("contacts", pulse, types::CONTACTS, paths::EXTERNAL_CONTACTS, json!({"trigger": pulse}))
+}
+
+fn entries(store: &Store) -> NineSResult<Vec<Scroll>> {
+    let mut out = Vec::new();
+    for k in store.list(paths::CONTACTS_PREFIX)? {
+        if let Some(s) = store.read(&k)? { out.push(s); }
+    }
+    Ok(out)
+}
+
+fn key(pubkey: &str) -> String { format!("{}/{}", paths::CONTACTS_PREFIX, pubkey) }
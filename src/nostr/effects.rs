@@ -3,18 +3,41 @@
 use async_trait::async_trait;
 use nine_s_core::prelude::*;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use crate::core::paths::{nostr as nostr_paths, nostr_types, origin, wallet as wallet_paths, wallet_types};
+use crate::core::proxy::Proxy;
 use crate::identity::Identity;
 use crate::mind::EffectHandler;
-use crate::nostr::client::{RelayClient, RelayState};
+use crate::nostr::client::{parse_relay_message, RelayClient, RelayMessage, RelayState};
+use crate::nostr::{archive, backup, bunker, contacts, kinds, outbox, pair, pay, peers, presence, zaps};
+use crate::nostr::state::Cursor;
+use nine_s_store::Store;
 use nostr::Tag;
 
+/// How long to wait for a relay's OK after publishing
+const OK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause between looks for a peer's encrypted reply
+const REPLY_POLL: Duration = Duration::from_secs(5);
+
+/// Connected relay with its inbound message stream
+struct Relay {
+    url: String,
+    client: RelayClient,
+    inbox: Mutex<mpsc::Receiver<String>>,
+}
+
 /// Nostr effect handler for relay operations
 pub struct NostrEffectHandler {
     identity: Arc<Identity>,
-    clients: Arc<RwLock<Vec<RelayClient>>>,
+    clients: Arc<RwLock<Vec<Relay>>>,
     relays: Vec<String>,
+    store: Option<Arc<Store>>,
+    proxy: Option<Proxy>,
+    node: Option<Arc<dyn crate::core::api::NodeApi>>,
+    sealed_prefixes: Vec<String>,
 }
 
 impl NostrEffectHandler {
@@ -23,62 +46,689 @@ impl NostrEffectHandler {
             identity,
             clients: Arc::new(RwLock::new(Vec::new())),
             relays,
+            store: None,
+            proxy: None,
+            node: None,
+            sealed_prefixes: Vec::new(),
         }
     }
 
+    /// Queue undeliverable publishes in `/nostr/outbox/*`
+    pub fn with_store(mut self, store: Arc<Store>) -> Self { self.store = Some(store); self }
+
+    /// Reach relays through a SOCKS5 proxy
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self { self.proxy = proxy; self }
+
+    /// Serve peers' requests (`/nostr/peers/poll`) against this node
+    pub fn with_node(mut self, node: Arc<dyn crate::core::api::NodeApi>) -> Self { self.node = Some(node); self }
+
+    /// The node's sealed prefixes: restore brings back only sealed data there
+    pub fn with_sealed_prefixes(mut self, prefixes: Vec<String>) -> Self { self.sealed_prefixes = prefixes; self }
+
     async fn do_connect(&self) -> anyhow::Result<Value> {
         let mut clients = self.clients.write().await;
         let mut connected = Vec::new();
 
         for url in &self.relays {
-            let mut client = RelayClient::new(url.clone());
-            if client.connect().await.is_ok() {
+            let mut client = RelayClient::new(url.clone()).with_proxy(self.proxy.clone());
+            if let Ok(inbox) = client.connect().await {
                 connected.push(url.clone());
-                clients.push(client);
+                clients.push(Relay { url: url.clone(), client, inbox: Mutex::new(inbox) });
             }
         }
 
+        drop(clients);
+
+        let outbox = if connected.is_empty() { json!(null) } else { self.do_drain().await? };
         Ok(json!({
             "status": "connected",
             "relays": connected,
-            "count": connected.len()
+            "count": connected.len(),
+            "outbox": outbox
         }))
     }
 
     async fn do_publish(&self, scroll: &Scroll) -> anyhow::Result<Value> {
-        let content = scroll.data["content"].as_str()
-            .ok_or_else(|| anyhow::anyhow!("no 'content'"))?;
         let kind = scroll.data["kind"].as_u64().unwrap_or(1) as u16;
+        let (content, tags) = template(kind, &scroll.data)?;
 
         // Build and sign event
-        let tags = parse_tags(&scroll.data);
         let unsigned = nostr::UnsignedEvent::new(
             self.identity.nostr_keys.public_key(),
             nostr::Timestamp::now(),
             nostr::Kind::Custom(kind),
             tags,
-            content.to_string(),
+            content,
         );
         let event = unsigned.sign_with_keys(&self.identity.nostr_keys)?;
+        let event_id = event.id.to_string();
 
-        // Publish to all connected relays
-        let clients = self.clients.read().await;
-        let mut published = 0;
-        for client in clients.iter() {
-            if client.state().await == RelayState::Connected {
-                if client.publish(&event).await.is_ok() {
-                    published += 1;
-                }
-            }
+        let results = self.broadcast(&event).await;
+        let published = results.iter().filter(|r| r["accepted"] == true).count();
+        if let Some(ref store) = self.store {
+            archive::store_event(store, &event).map_err(|e| anyhow::anyhow!("{}", e))?;
         }
+        let queued = match (&self.store, published) {
+            (Some(store), 0) => { outbox::enqueue(store, &event, "no relay accepted").map_err(|e| anyhow::anyhow!("{}", e))?; true }
+            _ => false,
+        };
 
         Ok(json!({
-            "status": if published > 0 { "published" } else { "failed" },
-            "event_id": event.id.to_string(),
+            "status": if published > 0 { "published" } else if queued { "queued" } else { "failed" },
+            "event_id": event_id,
             "relays_count": published,
+            "relays": results,
             "kind": kind
         }))
     }
+
+    /// Retry queued outbox events against connected relays
+    async fn do_drain(&self) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { return Ok(json!({"sent": 0, "remaining": 0})) };
+        let (mut sent, mut remaining, mut dropped) = (0, 0, 0);
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        for entry in outbox::queued(&store).map_err(err)? {
+            let event: nostr::Event = match serde_json::from_value(entry.data["event"].clone()) {
+                Ok(e) => e,
+                Err(e) => { outbox::settle(&store, entry, false, Some(&format!("corrupt: {}", e))).map_err(err)?; continue; }
+            };
+            let results = self.broadcast(&event).await;
+            let delivered = results.iter().any(|r| r["accepted"] == true);
+            let error = results.iter().find_map(|r| r["message"].as_str().map(String::from));
+            match outbox::settle(&store, entry, delivered, error.as_deref()).map_err(err)? {
+                "sent" => sent += 1,
+                "queued" => remaining += 1,
+                _ => dropped += 1,
+            }
+        }
+        Ok(json!({"sent": sent, "remaining": remaining, "dropped": dropped}))
+    }
+
+    /// Back-fill `/nostr/archive` with our own events from relays
+    async fn do_archive(&self) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { return Ok(json!({"archived": 0})) };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        let since = archive::cursor(&store).map_err(err)?;
+        let events = self.query(archive::filter(&self.identity.pubkey_hex, since)).await;
+        let mut archived = 0;
+        for event in events.iter().filter(|e| e.pubkey == self.identity.nostr_keys.public_key()) {
+            if archive::store_event(&store, event).map_err(err)? { archived += 1; }
+        }
+        archive::advance(&store, &events).map_err(err)?;
+        Ok(json!({"archived": archived, "seen": events.len()}))
+    }
+
+    /// Sync our kind-3 into `/contacts/nostr/*`, or publish it from there (`publish: true`)
+    async fn do_contacts(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("contacts need a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        if scroll.data["publish"] == true {
+            let list = contacts::following(&store).map_err(err)?;
+            let mut result = self.do_publish(&Scroll::new(&scroll.key, json!({"kind": 3, "contacts": list}))).await?;
+            contacts::set_applied(&store, nostr::Timestamp::now().as_u64(), list.len()).map_err(err)?;
+            result["count"] = json!(list.len());
+            return Ok(result);
+        }
+
+        let filter = json!({"authors": [self.identity.pubkey_hex], "kinds": [3], "limit": 1});
+        let newest = self.query(filter).await.into_iter().find(|e| e.pubkey == self.identity.nostr_keys.public_key());
+        let applied = match newest {
+            Some(ref event) => contacts::apply(&store, event).map_err(err)?,
+            None => None,
+        };
+        let missing = contacts::missing_profiles(&store).map_err(err)?;
+        let mut profiles = 0;
+        if !missing.is_empty() {
+            for event in self.query(json!({"authors": missing, "kinds": [0]})).await {
+                contacts::set_profile(&store, &event).map_err(err)?;
+                profiles += 1;
+            }
+        }
+        Ok(json!({
+            "status": if applied.is_some() { "updated" } else if newest.is_some() { "unchanged" } else { "not_found" },
+            "count": applied,
+            "created_at": newest.map(|e| e.created_at.as_u64()),
+            "profiles": profiles,
+        }))
+    }
+
+    /// Collect zap receipts tagging us, keeping only those that verify
+    async fn do_zaps(&self) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("zaps need a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        let config = zaps::config(&store).map_err(err)?.ok_or_else(|| anyhow::anyhow!("no zap config; write /nostr/zaps/config first"))?;
+        let zapper = config["zapper_pubkey"].as_str().unwrap_or_default().to_string();
+        let (mut cursor, events) = self.poll(&store, nostr_paths::ZAPS_STATE, nostr_types::ZAP, kinds::ZAP_RECEIPT).await.map_err(err)?;
+        let (mut received, mut sats, mut rejected) = (0, 0, Vec::new());
+        for event in &events {
+            match zaps::verify_receipt(event, &self.identity.pubkey_hex, &zapper) {
+                Ok(zap) => {
+                    let amount = zap["amount_sat"].as_u64().unwrap_or(0);
+                    if zaps::record(&store, zap).map_err(err)? { received += 1; sats += amount; }
+                }
+                Err(reason) => rejected.push(json!({"id": event.id.to_hex(), "reason": reason})),
+            }
+        }
+        cursor.advance(&store, &events).map_err(err)?;
+        Ok(json!({"received": received, "amount_sat": sats, "rejected": rejected, "seen": events.len()}))
+    }
+
+    /// NIP-46: `{approve: id}` / `{reject: id}` settle a parked request, anything else polls
+    async fn do_bunker(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("bunker needs a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        if let Some(id) = scroll.data["approve"].as_str() {
+            let req = bunker::pending(&store, id).map_err(err)?;
+            let event = bunker::sign(&self.identity.nostr_keys, &req["event"]).map_err(|e| anyhow::anyhow!(e))?;
+            let client = req["client"].as_str().unwrap_or_default();
+            let delivery = self.bunker_reply(client, &bunker::response(req["request_id"].as_str().unwrap_or_default(), Ok(serde_json::to_string(&event)?))).await?;
+            bunker::settle(&store, id, "approved", Some(&event.id.to_hex())).map_err(err)?;
+            return Ok(json!({"status": "approved", "event_id": event.id.to_hex(), "delivery": delivery}));
+        }
+        if let Some(id) = scroll.data["reject"].as_str() {
+            let req = bunker::pending(&store, id).map_err(err)?;
+            let client = req["client"].as_str().unwrap_or_default();
+            let delivery = self.bunker_reply(client, &bunker::response(req["request_id"].as_str().unwrap_or_default(), Err("rejected".into()))).await?;
+            bunker::settle(&store, id, "rejected", None).map_err(err)?;
+            return Ok(json!({"status": "rejected", "delivery": delivery}));
+        }
+
+        let cfg = bunker::config(&store).map_err(err)?;
+        if cfg["enabled"] != true { anyhow::bail!("bunker disabled; write /nostr/bunker first"); }
+        let (mut cursor, events) = self.poll(&store, nostr_paths::BUNKER_STATE, nostr_types::BUNKER, bunker::KIND).await.map_err(err)?;
+        let (mut answered, mut parked, mut signed) = (0, 0, 0);
+        for event in &events {
+            let event_id = event.id.to_hex();
+            if bunker::known(&store, &event_id).map_err(err)? { continue; }
+            let client = event.pubkey.to_hex();
+            let Ok(plaintext) = nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &event.pubkey, &event.content) else { continue };
+            let Ok(request) = bunker::Request::parse(&plaintext) else { continue };
+            // Re-read: a connect earlier in this batch may have added the client
+            let cfg = bunker::config(&store).map_err(err)?;
+            let result = match request.method.as_str() {
+                "connect" if cfg["secret"].is_string() && request.params.get(1).map(String::as_str) == cfg["secret"].as_str() => {
+                    bunker::add_client(&store, &client).map_err(err)?;
+                    Ok("ack".to_string())
+                }
+                "connect" => Err("invalid secret".to_string()),
+                "ping" => Ok("pong".to_string()),
+                _ if !bunker::is_client(&cfg, &client) => Err("not connected".to_string()),
+                "get_public_key" => Ok(self.identity.pubkey_hex.clone()),
+                "sign_event" => match bunker::template(&request) {
+                    Ok(t) if bunker::auto_approved(&cfg, &client, &t) => {
+                        signed += 1;
+                        bunker::sign(&self.identity.nostr_keys, &t).and_then(|e| serde_json::to_string(&e).map_err(|e| e.to_string()))
+                    }
+                    Ok(t) => {
+                        bunker::park(&store, &event_id, &client, &request, t).map_err(err)?;
+                        parked += 1;
+                        continue;
+                    }
+                    Err(e) => Err(e),
+                },
+                m => Err(format!("unsupported method: {}", m)),
+            };
+            self.bunker_reply(&client, &bunker::response(&request.id, result)).await?;
+            answered += 1;
+        }
+        cursor.advance(&store, &events).map_err(err)?;
+        Ok(json!({"answered": answered, "signed": signed, "pending": parked, "seen": events.len()}))
+    }
+
+    /// NIP-44 encrypted kind-24133 reply to a NIP-46 client
+    async fn bunker_reply(&self, client: &str, payload: &str) -> anyhow::Result<Value> {
+        let pk = nostr::PublicKey::from_hex(client)?;
+        let encrypted = nostr::nips::nip44::encrypt(self.identity.nostr_keys.secret_key(), &pk, payload, nostr::nips::nip44::Version::V2)?;
+        self.do_publish(&Scroll::new(&format!("{}/reply", nostr_paths::EXTERNAL_BUNKER), json!({"kind": bunker::KIND, "content": encrypted, "tags": [["p", client]]}))).await
+    }
+
+    /// Address requests: `{poll: true}` parks incoming ones, `{answer: event_id}`
+    /// replies to one, anything else asks `pubkey` for an address and queues the send
+    async fn do_pay(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("pay needs a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        if scroll.data["poll"] == true {
+            let (mut cursor, events) = self.poll(&store, nostr_paths::PAY_STATE, nostr_types::PAY, kinds::PAY).await.map_err(err)?;
+            let mut parked = 0;
+            for event in &events {
+                let event_id = event.id.to_hex();
+                if pay::known(&store, &event_id).map_err(err)? { continue; }
+                let Ok(plaintext) = nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &event.pubkey, &event.content) else { continue };
+                let Some(request) = pay::parse(&plaintext, pay::ADDRESS_REQUEST) else { continue };
+                pay::park(&store, &event_id, &event.pubkey.to_hex(), &request).map_err(err)?;
+                parked += 1;
+            }
+            cursor.advance(&store, &events).map_err(err)?;
+            return Ok(json!({"pending": parked, "seen": events.len()}));
+        }
+        if let Some(id) = scroll.data["answer"].as_str() {
+            let req = pay::answered(&store, id).map_err(err)?;
+            let payload = pay::response(req["request_id"].as_str().unwrap_or_default(), req["address"].as_str().unwrap_or_default());
+            let delivery = self.pay_message(req["from"].as_str().unwrap_or_default(), &payload, &scroll.key).await?;
+            pay::delivered(&store, id, delivery.clone()).map_err(err)?;
+            return Ok(json!({"status": "answered", "address": req["address"], "delivery": delivery}));
+        }
+
+        let payee = nostr::PublicKey::parse(scroll.data["pubkey"].as_str().ok_or_else(|| anyhow::anyhow!("no 'pubkey'"))?)?;
+        let pubkey = &payee.to_hex();
+        let amount = scroll.data["amount_sat"].as_u64().ok_or_else(|| anyhow::anyhow!("no 'amount_sat'"))?;
+        let id = scroll.key.rsplit('/').next().unwrap_or_default();
+        let since = nostr::Timestamp::now().as_u64();
+        let delivery = self.pay_message(pubkey, &pay::request(id, amount), &scroll.key).await?;
+        if delivery["status"] != "published" { anyhow::bail!("address request not delivered: {}", delivery["status"]); }
+
+        let filter = json!({"kinds": [kinds::PAY], "authors": [pubkey], "#p": [self.identity.pubkey_hex], "since": since});
+        let deadline = tokio::time::Instant::now() + pay::WAIT;
+        let address = loop {
+            let found = self.query(filter.clone()).await.into_iter()
+                .filter_map(|e| nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &payee, &e.content).ok())
+                .filter_map(|p| pay::parse(&p, pay::ADDRESS_RESPONSE))
+                .find(|r| r["id"] == id)
+                .and_then(|r| r["address"].as_str().map(String::from));
+            if let Some(address) = found { break address; }
+            if tokio::time::Instant::now() + pay::POLL_INTERVAL > deadline {
+                anyhow::bail!("no address from {} within {}s", pubkey, pay::WAIT.as_secs());
+            }
+            tokio::time::sleep(pay::POLL_INTERVAL).await;
+        };
+
+        // A plain scroll, so the effect worker picks the send up
+        store.write_scroll(Scroll::new(&format!("{}/{}", wallet_paths::EXTERNAL_SEND, id), json!({
+            "to": address,
+            "amount_sat": amount,
+            "fee_rate": scroll.data["fee_rate"],
+            "memo": scroll.data["memo"],
+            "recipient": pubkey,
+            "simulate": scroll.data["simulate"],
+            "idempotency_key": scroll.data["idempotency_key"],
+            "coin_selection": scroll.data["coin_selection"],
+        }))).map_err(err)?;
+        Ok(json!({"status": "resolved", "address": address, "recipient": pubkey, "amount_sat": amount, "send": format!("{}/{}", wallet_paths::EXTERNAL_SEND, id)}))
+    }
+
+    /// NIP-44 encrypted kind-9005 message to `to`
+    async fn pay_message(&self, to: &str, payload: &str, key: &str) -> anyhow::Result<Value> {
+        let pk = nostr::PublicKey::parse(to)?;
+        let encrypted = nostr::nips::nip44::encrypt(self.identity.nostr_keys.secret_key(), &pk, payload, nostr::nips::nip44::Version::V2)?;
+        self.do_publish(&Scroll::new(key, json!({"kind": kinds::PAY, "content": encrypted, "tags": [["p", pk.to_hex()]]}))).await
+    }
+
+    /// NIP-44 encrypt `content` to `to` and publish it (BeeBase scroll kind by default)
+    async fn do_dm(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let recipient = nostr::PublicKey::parse(scroll.data["to"].as_str().ok_or_else(|| anyhow::anyhow!("no 'to'"))?)?;
+        let to = &recipient.to_hex();
+        let plaintext = match &scroll.data["content"] {
+            Value::String(s) => s.clone(),
+            Value::Null => anyhow::bail!("no 'content'"),
+            v => v.to_string(),
+        };
+        let encrypted = nostr::nips::nip44::encrypt(
+            self.identity.nostr_keys.secret_key(),
+            &recipient,
+            &plaintext,
+            nostr::nips::nip44::Version::V2,
+        )?;
+        let mut tags = scroll.data["tags"].as_array().cloned().unwrap_or_default();
+        tags.push(json!(["p", to]));
+        let kind = scroll.data["kind"].as_u64().unwrap_or(kinds::SCROLL as u64);
+        let mut result = self.do_publish(&Scroll::new(&scroll.key, json!({"kind": kind, "content": encrypted, "tags": tags}))).await?;
+        result["to"] = json!(to);
+        Ok(result)
+    }
+
+    /// Sign a payment receipt, keep it at `/wallet/receipts/{txid}` and DM it to `recipient`
+    async fn do_receipt(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let txid = scroll.data["txid"].as_str().ok_or_else(|| anyhow::anyhow!("no 'txid'"))?;
+        let recipient = scroll.data["recipient"].as_str();
+        let mut receipt = json!({
+            "txid": txid,
+            "amount_sat": scroll.data["amount_sat"],
+            "to": scroll.data["to"],
+            "memo": scroll.data["memo"],
+            "confirmed_at": scroll.data["confirmed_at"],
+        });
+        let mut tags = vec![Tag::parse(&["x".to_string(), txid.to_string()])?];
+        if let Some(pk) = recipient { tags.push(Tag::parse(&["p".to_string(), pk.to_string()])?); }
+        let event = nostr::UnsignedEvent::new(
+            self.identity.nostr_keys.public_key(),
+            nostr::Timestamp::now(),
+            nostr::Kind::Custom(kinds::RECEIPT),
+            tags,
+            receipt.to_string(),
+        ).sign_with_keys(&self.identity.nostr_keys)?;
+        receipt["signer"] = json!(self.identity.pubkey_hex);
+        receipt["event"] = serde_json::to_value(&event)?;
+
+        if let Some(ref store) = self.store {
+            store.write_scroll(Scroll {
+                key: format!("{}/{}", wallet_paths::RECEIPTS_PREFIX, txid),
+                type_: wallet_types::RECEIPT.into(),
+                metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+                data: receipt.clone(),
+            }).map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+        if let Some(pk) = recipient {
+            let dm = Scroll::new(&scroll.key, json!({"to": pk, "content": receipt["event"].to_string(), "kind": kinds::RECEIPT}));
+            receipt["delivery"] = self.do_dm(&dm).await.unwrap_or_else(|e| json!({"status": "failed", "error": e.to_string()}));
+        }
+        Ok(receipt)
+    }
+
+    /// Publish the store's changed buckets and a new manifest (`{full: true}`: every bucket)
+    async fn do_backup(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("backup needs a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        if scroll.data["restore"] == true { return self.do_restore(&store, scroll.data["overwrite"] == true).await; }
+
+        let previous = backup::state(&store).map_err(err)?.map(|s| s["manifest"].clone());
+        let snapshot = crate::store::snapshot(&store, "/").map_err(err)?;
+        let plan = backup::plan(&snapshot, previous.as_ref(), scroll.data["full"] == true).map_err(err)?;
+        let keys = &self.identity.nostr_keys;
+        for (d, plaintext) in &plan.chunks {
+            let results = self.broadcast(&self.app_data(d, &backup::seal(keys, plaintext).map_err(err)?)?).await;
+            if !results.iter().any(|r| r["accepted"] == true) { anyhow::bail!("no relay accepted backup chunk {}: {}", d, Value::Array(results)); }
+        }
+        let manifest = self.app_data(backup::MANIFEST_D, &backup::seal(keys, &plan.manifest.to_string()).map_err(err)?)?;
+        let results = self.broadcast(&manifest).await;
+        let relays: Vec<&Value> = results.iter().filter(|r| r["accepted"] == true).map(|r| &r["url"]).collect();
+        if relays.is_empty() { anyhow::bail!("no relay accepted the backup manifest: {}", Value::Array(results)); }
+
+        let state = json!({
+            "manifest": plan.manifest,
+            "event_id": manifest.id.to_hex(),
+            "scrolls": plan.manifest["scrolls"],
+            "published": plan.chunks.len(),
+            "reused": plan.reused,
+            "relays": relays,
+            "backed_up_at": nostr::Timestamp::now().as_u64(),
+        });
+        backup::set_state(&store, state.clone()).map_err(err)?;
+        Ok(state)
+    }
+
+    /// Newest manifest and its chunks back into the store
+    async fn do_restore(&self, store: &Store, overwrite: bool) -> anyhow::Result<Value> {
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        let keys = &self.identity.nostr_keys;
+        let author = self.identity.pubkey_hex.clone();
+        let filter = json!({"authors": [author], "kinds": [backup::KIND], "#d": [backup::MANIFEST_D]});
+        let newest = self.query(filter).await.into_iter().find(|e| e.pubkey == keys.public_key())
+            .ok_or_else(|| anyhow::anyhow!("no backup found on the connected relays"))?;
+        let manifest: Value = serde_json::from_str(&backup::open(keys, &newest.content).map_err(err)?)?;
+
+        let mut chunks = HashMap::new();
+        for batch in backup::chunk_tags(&manifest).chunks(100) {
+            for event in self.query(json!({"authors": [author], "kinds": [backup::KIND], "#d": batch})).await {
+                if event.pubkey != keys.public_key() { continue; }
+                let Some(d) = event.tags.iter().find_map(|t| match t.as_slice() { [k, v, ..] if k == "d" => Some(v.clone()), _ => None }) else { continue };
+                if chunks.contains_key(&d) { continue; }
+                if let Ok(plaintext) = backup::open(keys, &event.content) { chunks.insert(d, plaintext); }
+            }
+        }
+        let (scrolls, missing) = backup::assemble(&manifest, &chunks);
+        let (mut restored, mut skipped) = (0, 0);
+        for scroll in scrolls {
+            if !overwrite && store.read(&scroll.key).map_err(err)?.is_some() { skipped += 1; continue; }
+            // The handler has no PIN key: plaintext can't go under a sealed prefix
+            if scroll.data["sealed"].is_null() && crate::node::sealed::matching(&self.sealed_prefixes, &scroll.key).is_some() { skipped += 1; continue; }
+            store.write_scroll(scroll).map_err(err)?;
+            restored += 1;
+        }
+        Ok(json!({"status": if missing.is_empty() { "restored" } else { "partial" }, "restored": restored, "skipped": skipped, "missing": missing, "created_at": manifest["created_at"]}))
+    }
+
+    /// `{poll: true}` answers requests for our offers; `{uri}` pairs with the offering node
+    async fn do_pair(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("pairing needs a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        let keys = &self.identity.nostr_keys;
+        if scroll.data["poll"] == true {
+            self.ensure_relays(&self.relays).await;
+            let (mut cursor, events) = self.poll(&store, nostr_paths::PAIR_STATE, nostr_types::PAIR, kinds::PAIR).await.map_err(err)?;
+            let (mut paired, mut rejected) = (Vec::new(), 0);
+            for event in &events {
+                let Ok(plaintext) = nostr::nips::nip44::decrypt(keys.secret_key(), &event.pubkey, &event.content) else { continue };
+                let Some(request) = pair::parse(&plaintext, &[pair::PAIR_REQUEST]) else { continue };
+                let peer = event.pubkey.to_hex();
+                let reply = match pair::claim(&store, request["secret"].as_str().unwrap_or_default()) {
+                    Ok(offer) => {
+                        let record = pair::record_peer(&store, &peer, &request["name"], &offer["capabilities"], &request["capabilities"], &self.relays).map_err(err)?;
+                        paired.push(record.key);
+                        pair::message(pair::PAIR_ACCEPT, json!({"id": request["id"], "name": offer["name"], "granted": offer["capabilities"]}))
+                    }
+                    Err(e) => {
+                        rejected += 1;
+                        pair::message(pair::PAIR_REJECT, json!({"id": request["id"], "error": e.to_string()}))
+                    }
+                };
+                self.send_encrypted(kinds::PAIR, &peer, &reply, &scroll.key).await?;
+            }
+            cursor.advance(&store, &events).map_err(err)?;
+            return Ok(json!({"paired": paired, "rejected": rejected, "seen": events.len()}));
+        }
+
+        let (offerer, relays, secret) = pair::parse_uri(scroll.data["uri"].as_str().ok_or_else(|| anyhow::anyhow!("no 'uri'"))?).map_err(err)?;
+        self.ensure_relays(&relays).await;
+        let id = scroll.key.rsplit('/').next().unwrap_or_default().to_string();
+        let since = nostr::Timestamp::now().as_u64();
+        let request = pair::message(pair::PAIR_REQUEST, json!({"id": id, "secret": secret, "name": scroll.data["name"], "capabilities": scroll.data["capabilities"]}));
+        let delivery = self.send_encrypted(kinds::PAIR, &offerer, &request, &scroll.key).await?;
+        if delivery["status"] != "published" { anyhow::bail!("pairing request not delivered: {}", delivery["status"]); }
+
+        let answer = self.await_reply(kinds::PAIR, &offerer, since, &id, &[pair::PAIR_ACCEPT, pair::PAIR_REJECT], pair::WAIT).await?
+            .ok_or_else(|| anyhow::anyhow!("no answer from {} within {}s (is it polling /nostr/pair/poll?)", offerer, pair::WAIT.as_secs()))?;
+        if answer["type"] == pair::PAIR_REJECT { anyhow::bail!("pairing rejected: {}", answer["error"].as_str().unwrap_or("no reason")); }
+        let record = pair::record_peer(&store, &offerer, &answer["name"], &scroll.data["capabilities"], &answer["granted"], &relays).map_err(err)?;
+        Ok(json!({"status": "paired", "peer": record.key, "record": record.data}))
+    }
+
+    async fn do_peers(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("peer requests need a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        if scroll.data["poll"] == true {
+            let Some(node) = self.node.clone() else { anyhow::bail!("serving peers needs the node (NostrEffectHandler::with_node)") };
+            self.ensure_relays(&self.relays).await;
+            let (mut cursor, events) = self.poll(&store, nostr_paths::PEERS_STATE, nostr_types::PEER_REQUEST, kinds::PEER).await.map_err(err)?;
+            let (mut served, mut refused) = (0, 0);
+            for event in &events {
+                let event_id = event.id.to_hex();
+                if !peers::fresh(event.created_at.as_u64()) || peers::served(&store, &event_id).map_err(err)? { continue; }
+                let Ok(plaintext) = nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &event.pubkey, &event.content) else { continue };
+                let Some(request) = pair::parse(&plaintext, &[peers::PEER_REQUEST]) else { continue };
+                let (sender, id, node, served_store) = (event.pubkey.to_hex(), request["id"].clone(), node.clone(), store.clone());
+                let (from, req) = (sender.clone(), request.clone());
+                let outcome = tokio::task::spawn_blocking(move || peers::serve(&*node, &served_store, &from, &req)).await?;
+                peers::mark_served(&store, &event_id, &sender, &request, if outcome.is_ok() { "served" } else { "refused" }).map_err(err)?;
+                let reply = match outcome {
+                    Ok(result) => { served += 1; pair::message(peers::PEER_RESPONSE, json!({"id": id, "result": result})) }
+                    Err(e) => { refused += 1; pair::message(peers::PEER_RESPONSE, json!({"id": id, "error": e.to_string()})) }
+                };
+                self.send_encrypted(kinds::PEER, &sender, &reply, &scroll.key).await?;
+            }
+            cursor.advance(&store, &events).map_err(err)?;
+            return Ok(json!({"served": served, "refused": refused, "seen": events.len()}));
+        }
+
+        let outcome = self.peer_request(&store, scroll).await;
+        if let Some(key) = peers::result_key(&scroll.data) {
+            let data = match &outcome {
+                Ok(answer) => answer["result"]["data"].clone(),
+                Err(e) => json!({"success": false, "error": e.to_string()}),
+            };
+            store.write_scroll(Scroll {
+                key,
+                type_: crate::core::paths::EFFECT_RESULT_TYPE.into(),
+                metadata: Metadata::default().with_produced_by(origin::EFFECTS),
+                data,
+            }).map_err(err)?;
+        }
+        outcome
+    }
+
+    /// Send a request to a peer and wait for the answer
+    async fn peer_request(&self, store: &Store, scroll: &Scroll) -> anyhow::Result<Value> {
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        let mobi: String = scroll.data["peer"].as_str().ok_or_else(|| anyhow::anyhow!("no 'peer'"))?.chars().filter(|c| c.is_ascii_digit()).collect();
+        let record = store.read(&format!("{}/{}", crate::core::paths::peers::PREFIX, mobi)).map_err(err)?
+            .ok_or_else(|| anyhow::anyhow!("unknown peer: {}", mobi))?.data;
+        let token = scroll.data["token"].as_str().or(record["token"].as_str())
+            .ok_or_else(|| anyhow::anyhow!("no token for peer {} (write /peers/{}/credential)", mobi, mobi))?;
+        let (op, path) = (scroll.data["op"].clone(), scroll.data["path"].clone());
+        if let (Some(url), "effect") = (record["url"].as_str(), op.as_str().unwrap_or_default()) {
+            let client = crate::client::Client::new(url).with_token(token);
+            let (effect, data) = (path.as_str().unwrap_or_default().to_string(), scroll.data["data"].clone());
+            let result = tokio::task::spawn_blocking(move || client.execute_effect(&effect, data, peers::EFFECT_WAIT)).await?
+                .map_err(|e| anyhow::anyhow!("peer {} at {}: {}", mobi, url, e))?;
+            let result = serde_json::to_value(result)?;
+            return Ok(json!({"peer": mobi, "op": op, "path": path, "via": url, "result": result}));
+        }
+        let pubkey = record["pubkey"].as_str().ok_or_else(|| anyhow::anyhow!("peer {} has no pubkey", mobi))?;
+        let relays: Vec<String> = record["relays"].as_array().into_iter().flatten().filter_map(|r| r.as_str().map(String::from)).collect();
+        self.ensure_relays(&relays).await;
+
+        let id = scroll.key.rsplit('/').next().unwrap_or_default().to_string();
+        let since = nostr::Timestamp::now().as_u64();
+        let request = pair::message(peers::PEER_REQUEST, json!({
+            "id": id, "token": token, "op": op, "path": path, "data": scroll.data["data"],
+        }));
+        let delivery = self.send_encrypted(kinds::PEER, pubkey, &request, &scroll.key).await?;
+        if delivery["status"] != "published" { anyhow::bail!("peer request not delivered: {}", delivery["status"]); }
+        let answer = self.await_reply(kinds::PEER, pubkey, since, &id, &[peers::PEER_RESPONSE], peers::WAIT).await?
+            .ok_or_else(|| anyhow::anyhow!("no answer from peer {} within {}s (is it polling /nostr/peers/poll?)", mobi, peers::WAIT.as_secs()))?;
+        if let Some(e) = answer["error"].as_str() { anyhow::bail!("peer {} refused: {}", mobi, e); }
+        Ok(json!({"peer": mobi, "op": op, "path": path, "result": answer["result"]}))
+    }
+
+    async fn do_presence(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let Some(store) = self.store.clone() else { anyhow::bail!("presence needs a store") };
+        let err = |e: NineSError| anyhow::anyhow!("{}", e);
+        self.ensure_relays(&self.relays).await;
+        let tick = store.read(crate::core::paths::clock::TICK).map_err(err)?.and_then(|s| s.data["tick"].as_u64()).unwrap_or(0);
+        let beat = presence::heartbeat(tick);
+        let mut sent = 0;
+        for key in store.list(crate::core::paths::peers::PREFIX).map_err(err)? {
+            let Some(peer) = store.read(&key).map_err(err)? else { continue };
+            let Some(pubkey) = peer.data["pubkey"].as_str().filter(|_| peer.type_ == crate::core::paths::peers::TYPE) else { continue };
+            if peer.data["presence"] == false { continue; }
+            if self.send_encrypted(kinds::PRESENCE, pubkey, &beat, &scroll.key).await?["status"] == "published" { sent += 1; }
+        }
+
+        let (mut cursor, events) = self.poll(&store, nostr_paths::PRESENCE_STATE, nostr_types::PRESENCE, kinds::PRESENCE).await.map_err(err)?;
+        let mut seen = Vec::new();
+        for event in &events {
+            let Ok(plaintext) = nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &event.pubkey, &event.content) else { continue };
+            let Some(heartbeat) = pair::parse(&plaintext, &[presence::HEARTBEAT]) else { continue };
+            let mobi = crate::mobi::Mobi::derive(&event.pubkey.to_hex()).map_err(err)?.display;
+            if store.read(&format!("{}/{}", crate::core::paths::peers::PREFIX, mobi)).map_err(err)?.is_none() { continue; }
+            if presence::record(&store, &mobi, &heartbeat).map_err(err)?.is_some() && !seen.contains(&mobi) { seen.push(mobi); }
+        }
+        cursor.advance(&store, &events).map_err(err)?;
+        Ok(json!({"sent": sent, "seen": seen, "tick": tick}))
+    }
+
+    /// `kind` events tagging us from the poll cursor at `state` on, less those already handled
+    async fn poll(&self, store: &Store, state: &'static str, type_: &'static str, kind: u16) -> NineSResult<(Cursor, Vec<nostr::Event>)> {
+        let cursor = Cursor::load(store, state, type_)?;
+        let events = self.query(cursor.filter(json!({"kinds": [kind], "#p": [self.identity.pubkey_hex]}))).await;
+        let fresh = events.into_iter().filter(|e| cursor.is_new(e)).collect();
+        Ok((cursor, fresh))
+    }
+
+    /// NIP-44 encrypted message of `kind` to `to`
+    async fn send_encrypted(&self, kind: u16, to: &str, payload: &str, key: &str) -> anyhow::Result<Value> {
+        let pk = nostr::PublicKey::parse(to)?;
+        let encrypted = nostr::nips::nip44::encrypt(self.identity.nostr_keys.secret_key(), &pk, payload, nostr::nips::nip44::Version::V2)?;
+        self.do_publish(&Scroll::new(key, json!({"kind": kind, "content": encrypted, "tags": [["p", pk.to_hex()]]}))).await
+    }
+
+    /// First `kind` message from `from` since `since` answering `id` with one
+    /// of `expected`, looking every `REPLY_POLL` for up to `wait`
+    async fn await_reply(&self, kind: u16, from: &str, since: u64, id: &str, expected: &[&str], wait: Duration) -> anyhow::Result<Option<Value>> {
+        let from_pk = nostr::PublicKey::from_hex(from)?;
+        let filter = json!({"kinds": [kind], "authors": [from], "#p": [self.identity.pubkey_hex], "since": since});
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let found = self.query(filter.clone()).await.into_iter()
+                .filter_map(|e| nostr::nips::nip44::decrypt(self.identity.nostr_keys.secret_key(), &from_pk, &e.content).ok())
+                .filter_map(|p| pair::parse(&p, expected))
+                .find(|r| r["id"] == id);
+            if found.is_some() { return Ok(found); }
+            if tokio::time::Instant::now() + REPLY_POLL > deadline { return Ok(None); }
+            tokio::time::sleep(REPLY_POLL).await;
+        }
+    }
+
+    /// Connect to any of `urls` we aren't connected to yet
+    async fn ensure_relays(&self, urls: &[String]) {
+        let mut clients = self.clients.write().await;
+        for url in urls {
+            if clients.iter().any(|r| &r.url == url) { continue; }
+            let mut client = RelayClient::new(url.clone()).with_proxy(self.proxy.clone());
+            if let Ok(inbox) = client.connect().await {
+                clients.push(Relay { url: url.clone(), client, inbox: Mutex::new(inbox) });
+            }
+        }
+    }
+
+    /// Signed kind-30078 event with `d` tag `d`
+    fn app_data(&self, d: &str, content: &str) -> anyhow::Result<nostr::Event> {
+        Ok(nostr::UnsignedEvent::new(
+            self.identity.nostr_keys.public_key(),
+            nostr::Timestamp::now(),
+            nostr::Kind::Custom(backup::KIND),
+            vec![Tag::parse(&["d".to_string(), d.to_string()])?],
+            content.to_string(),
+        ).sign_with_keys(&self.identity.nostr_keys)?)
+    }
+
+    /// Publish to every connected relay, collecting each relay's OK
+    async fn broadcast(&self, event: &nostr::Event) -> Vec<Value> {
+        let event_id = event.id.to_string();
+        let clients = self.clients.read().await;
+        let mut results = Vec::new();
+        for relay in clients.iter() {
+            let outcome = if relay.client.state().await != RelayState::Connected {
+                json!({"url": relay.url, "accepted": false, "message": "disconnected"})
+            } else if let Err(e) = relay.client.publish(event).await {
+                json!({"url": relay.url, "accepted": false, "message": e.to_string()})
+            } else {
+                let mut inbox = relay.inbox.lock().await;
+                match wait_ok(&mut inbox, &event_id).await {
+                    Some((accepted, message)) => json!({"url": relay.url, "accepted": accepted, "message": message}),
+                    None => json!({"url": relay.url, "accepted": false, "message": "no OK before timeout"}),
+                }
+            };
+            results.push(outcome);
+        }
+        results
+    }
+}
+
+impl NostrEffectHandler {
+    /// One-shot REQ across connected relays; collects events until EOSE or timeout
+    pub async fn query(&self, filter: Value) -> Vec<nostr::Event> {
+        let sub_id = format!("q{:x}", nostr::Timestamp::now().as_u64());
+        let clients = self.clients.read().await;
+        let mut events: Vec<nostr::Event> = Vec::new();
+        for relay in clients.iter() {
+            if relay.client.state().await != RelayState::Connected { continue; }
+            if relay.client.subscribe(&sub_id, vec![filter.clone()]).await.is_err() { continue; }
+            let mut inbox = relay.inbox.lock().await;
+            let deadline = tokio::time::Instant::now() + OK_TIMEOUT;
+            while let Ok(Some(msg)) = tokio::time::timeout_at(deadline, inbox.recv()).await {
+                match parse_relay_message(&msg) {
+                    Some(RelayMessage::Event { sub_id: id, event }) if id == sub_id => {
+                        if event.verify().is_ok() && !events.iter().any(|e| e.id == event.id) { events.push(event); }
+                    }
+                    Some(RelayMessage::Eose { sub_id: id }) if id == sub_id => break,
+                    _ => {}
+                }
+            }
+            let _ = relay.client.unsubscribe(&sub_id).await;
+        }
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        events
+    }
 }
 
 #[async_trait]
@@ -90,12 +740,75 @@ impl EffectHandler for NostrEffectHandler {
             self.do_connect().await
         } else if scroll.key.contains("/publish/") {
             self.do_publish(scroll).await
+        } else if scroll.key.contains("/dm/") {
+            self.do_dm(scroll).await
+        } else if scroll.key.contains("/receipt/") {
+            self.do_receipt(scroll).await
+        } else if scroll.key.contains("/outbox/") {
+            self.do_drain().await
+        } else if scroll.key.contains("/archive/") {
+            self.do_archive().await
+        } else if scroll.key.contains("/contacts/") {
+            self.do_contacts(scroll).await
+        } else if scroll.key.contains("/zaps/") {
+            self.do_zaps().await
+        } else if scroll.key.contains("/bunker/") {
+            self.do_bunker(scroll).await
+        } else if scroll.key.contains("/pay/") {
+            self.do_pay(scroll).await
+        } else if scroll.key.contains("/pair/") {
+            self.do_pair(scroll).await
+        } else if scroll.key.contains("/peers/") {
+            self.do_peers(scroll).await
+        } else if scroll.key.contains("/presence/") {
+            self.do_presence(scroll).await
+        } else if scroll.key.contains("/backup/") {
+            self.do_backup(scroll).await
         } else {
             Err(anyhow::anyhow!("Unknown: {}", scroll.key))
         }
     }
 }
 
+/// Wait for the relay's `["OK", event_id, accepted, message]`
+async fn wait_ok(inbox: &mut mpsc::Receiver<String>, event_id: &str) -> Option<(bool, Option<String>)> {
+    let deadline = tokio::time::Instant::now() + OK_TIMEOUT;
+    loop {
+        let msg = tokio::time::timeout_at(deadline, inbox.recv()).await.ok()??;
+        if let Some(RelayMessage::Ok { event_id: id, accepted, message }) = parse_relay_message(&msg) {
+            if id == event_id { return Some((accepted, message)); }
+        }
+    }
+}
+
+/// Content and tags for a publish request.
+///
+/// Kind 0 accepts `content` (or `metadata`) as an object and serializes it.
+/// Kind 3 accepts `contacts: [hex | {pubkey, relay, petname}]` and emits `p` tags.
+fn template(kind: u16, data: &Value) -> anyhow::Result<(String, Vec<Tag>)> {
+    let mut tags = parse_tags(data);
+    let content = match (kind, data.get("content").or_else(|| data.get("metadata"))) {
+        (_, Some(Value::String(s))) => s.clone(),
+        (0, Some(obj @ Value::Object(_))) => obj.to_string(),
+        (3, None) => String::new(),
+        _ => anyhow::bail!("no 'content'"),
+    };
+    if kind == 3 {
+        for c in data.get("contacts").and_then(|v| v.as_array()).into_iter().flatten() {
+            let (pubkey, relay, petname) = match c {
+                Value::String(pk) => (pk.as_str(), "", ""),
+                v => (v["pubkey"].as_str().unwrap_or_default(), v["relay"].as_str().unwrap_or_default(), v["petname"].as_str().unwrap_or_default()),
+            };
+            if pubkey.is_empty() { continue; }
+            let mut parts = vec!["p".to_string(), pubkey.to_string()];
+            if !relay.is_empty() || !petname.is_empty() { parts.push(relay.to_string()); }
+            if !petname.is_empty() { parts.push(petname.to_string()); }
+            tags.push(Tag::parse(&parts)?);
+        }
+    }
+    Ok((content, tags))
+}
+
 fn parse_tags(data: &Value) -> Vec<Tag> {
     let tags = data.get("tags").and_then(|v| v.as_array()).cloned().unwrap_or_default();
     tags
@@ -114,3 +827,91 @@ fn parse_tags(data: &Value) -> Vec<Tag> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_metadata_object() {
+        let (content, tags) = template(0, &json!({"content": {"name": "bee"}})).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap()["name"], "bee");
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn test_template_contacts() {
+        let pk = "a".repeat(64);
+        let (content, tags) = template(3, &json!({"contacts": [pk, {"pubkey": "b".repeat(64), "petname": "bob"}]})).unwrap();
+        assert!(content.is_empty());
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[1].as_slice(), &["p".to_string(), "b".repeat(64), String::new(), "bob".to_string()]);
+    }
+
+    #[test]
+    fn test_template_requires_content() {
+        assert!(template(1, &json!({})).is_err());
+        assert!(template(1, &json!({"content": {"a": 1}})).is_err());
+    }
+    /// Relay on localhost that OKs every EVENT and answers REQs with all it holds
+    async fn local_relay(events: Vec<nostr::Event>) -> String {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let held = Arc::new(Mutex::new(events));
+            while let Ok((tcp, _)) = listener.accept().await {
+                let held = held.clone();
+                tokio::spawn(async move {
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(tcp).await else { return };
+                    while let Some(Ok(Message::Text(txt))) = ws.next().await {
+                        let msg: Vec<Value> = serde_json::from_str(&txt).unwrap_or_default();
+                        let replies = match msg.first().and_then(|v| v.as_str()) {
+                            Some("EVENT") => {
+                                let event: nostr::Event = serde_json::from_value(msg[1].clone()).unwrap();
+                                held.lock().await.push(event.clone());
+                                vec![json!(["OK", event.id.to_hex(), true, ""])]
+                            }
+                            Some("REQ") => {
+                                let mut out: Vec<Value> = held.lock().await.iter().map(|e| json!(["EVENT", msg[1], e])).collect();
+                                out.push(json!(["EOSE", msg[1]]));
+                                out
+                            }
+                            _ => vec![],
+                        };
+                        for reply in replies {
+                            if ws.send(Message::Text(reply.to_string())).await.is_err() { return; }
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    fn test_identity() -> Arc<Identity> {
+        Arc::new(Identity::from_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_outbox_drains_on_connect() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(crate::node::NodeConfig::new("test-outbox").with_root(dir.path()).open_store().unwrap());
+        let identity = test_identity();
+        let event = nostr::EventBuilder::new(nostr::Kind::TextNote, "offline note", []).sign_with_keys(&identity.nostr_keys).unwrap();
+        outbox::enqueue(&store, &event, "no relay accepted").unwrap();
+        assert_eq!(outbox::queued(&store).unwrap().len(), 1);
+
+        let handler = NostrEffectHandler::new(identity, vec![local_relay(vec![]).await]).with_store(store.clone());
+        let result = handler.execute(&Scroll::new("/external/nostr/connect/1", json!({}))).await.unwrap();
+        assert_eq!((result["count"].as_u64(), result["outbox"]["sent"].as_u64()), (Some(1), Some(1)));
+        assert!(outbox::queued(&store).unwrap().is_empty());
+        let entry = store.read(&format!("{}/{}", nostr_paths::OUTBOX_PREFIX, event.id.to_hex())).unwrap().unwrap();
+        assert_eq!(entry.data["status"], "sent");
+
+        // Nothing left: a later drain (the pattern's pulse) is a no-op
+        let drained = handler.execute(&Scroll::new("/external/nostr/outbox/2", json!({"trigger": "sync"}))).await.unwrap();
+        assert_eq!(drained, json!({"sent": 0, "remaining": 0, "dropped": 0}));
+    }
+}
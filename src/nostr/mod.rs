@@ -12,23 +12,86 @@
 //! | Path | Method | Description |
 //! |------|--------|-------------|
 //! | `/status` | read | `{initialized, relays, auto_connect}` |
-//! | `/pubkey` | read | `{hex}` - 32-byte x-only pubkey |
+//! | `/pubkey` | read | `{hex, npub}` - 32-byte x-only pubkey |
+//! | `/npub` | read | `{npub, hex}` - the same key, NIP-19 first |
 //! | `/mobi` | read | `{display, formatted, extended, long, full}` |
 //! | `/relays` | read | `{urls, beebase}` - configured relays |
+//! | `/relays/{host}/status` | read | `{state, last_error, events_sent, events_received, latency_ms, reconnects}` |
 //! | `/sign` | write | Sign message → `{signature, event_id, pubkey}` |
 //! | `/connect` | write | Queue connect → `/external/nostr/connect/{id}` |
-//! | `/publish` | write | Queue publish → `/external/nostr/publish/{id}` |
+//! | `/publish` | write | `{kind, content, tags}` → `/external/nostr/publish/{id}`, per-relay OK in result |
+//! | `/dm` | write | `{to, content, kind?}` → NIP-44 encrypted to `to`, published as kind 9000 by default |
+//! | `/profile` | read | Own kind-0 profile `{pubkey, metadata, mobi}` |
+//! | `/profile/{pubkey}` | read | Cached (or relay-fetched) profile for any pubkey |
+//! | `/profile` | write | `{name, about, picture}` → publish kind 0, cache locally |
+//! | `/outbox` | read | Queued publishes awaiting a reachable relay |
+//! | `/outbox/drain` | write | Retry queued publishes now |
+//! | `/archive` | read | Archived own events `{kinds, count, since}` |
+//! | `/archive/{kind}/{event_id}` | read | Archived event JSON |
+//! | `/archive/sync` | write | Back-fill own events from relays |
+//! | `/contacts` | read | Followed pubkeys from `/contacts/nostr/*` plus last sync `{contacts, count, created_at}` |
+//! | `/contacts/sync` | write | Fetch own kind-3 and materialize `/contacts/nostr/{pubkey}` `{pubkey, petname, mobi, profile}` |
+//! | `/contacts/publish` | write | Publish `/contacts/nostr/*` as kind 3 (also queued automatically on edits) |
+//! | `/zaps` | read | `{count, total_sat, since, config}` over received zaps |
+//! | `/zaps/config` | read/write | `{lud16, zapper_pubkey}` external LNURL server; write also sets `lud16` on the profile |
+//! | `/zaps/sync` | write | Fetch and verify kind-9735 receipts → `/nostr/zaps/received/{event_id}` `{amount_sat, sender, comment}` |
+//! | `/bunker` | read/write | NIP-46 signer `{enabled, policy?, secret?}` → `{uri: bunker://...}` |
+//! | `/bunker/poll` | write | Answer pending NIP-46 requests; `sign_event` parks at `/nostr/bunker/requests/{id}` unless policy allows |
+//! | `/bunker/requests/{id}/approve` | write | Sign and return a parked request `{pin?}` (PIN required under PIN auth) |
+//! | `/bunker/requests/{id}/reject` | write | Refuse a parked request |
+//! | `/pay/poll` | write | Park payers' address requests at `/nostr/pay/requests/{id}`; answered by `/wallet/pay-requests/answer` |
+//! | `/backup` | read/write | Last backup `{manifest, published, reused, scrolls, relays}`; write `{full?}` publishes an encrypted snapshot to relays |
+//! | `/backup/restore` | write | `{overwrite?}` fetch the newest backup and write its scrolls back → `{restored, skipped, missing}` |
+//! | `/pair` | write | `{capabilities?, ttl_secs?, name?}` → one-time `{uri: beenode-pair://..., qr, expires_at}` |
+//! | `/pair/accept` | write | `{uri, capabilities?, name?}` pair with the offering node → its `/peers/{mobi}` record |
+//! | `/pair/poll` | write | Answer pairing requests for our offers, recording each peer at `/peers/{mobi}` |
+//! | `/peers/request` | write | `{peer, op: get/put/list, path, data?, token?}` run on a paired node → its answer |
+//! | `/peers/poll` | write | Queue serving peers' requests whose token (`/peers/{mobi}/token`) allows them (effect worker) |
+//! | `/presence` | write | Send paired peers a heartbeat and record theirs at `/peers/{mobi}/presence` |
+//! | `/verify` | write | Check event id + signature → `{valid, pubkey, mobi}` (also checks receipt `event`s) |
+//!
+//! Wherever a pubkey goes in (`/profile/{pubkey}`, `/dm` `to`, contacts,
+//! pay requests) hex, `npub1...` and `nostr:npub1...` are all accepted and
+//! stored as hex (`pubkey_hex`).
 
 mod namespace;
 pub mod client;
 mod effects;
+pub mod archive;
+pub mod backup;
+pub mod bunker;
+pub mod contacts;
+pub mod outbox;
+pub mod pair;
+pub mod peers;
+pub mod presence;
+pub mod pay;
+mod state;
+pub mod zaps;
 
 pub use namespace::NostrNamespace;
-pub use client::{RelayClient, RelayMessage, RelayPool, RelayState, parse_relay_message};
+pub use client::{RelayClient, RelayMessage, RelayPool, RelayState, RelayStats, parse_relay_message, relay_host};
 pub use effects::NostrEffectHandler;
 
+use nine_s_core::prelude::*;
+use nostr::nips::nip19::ToBech32;
 use serde::{Deserialize, Serialize};
 
+/// Hex pubkey from hex, `npub1...` or a `nostr:` URI
+pub fn pubkey_hex(input: &str) -> NineSResult<String> {
+    nostr::PublicKey::parse(input.trim())
+        .map(|pk| pk.to_hex())
+        .map_err(|e| NineSError::Other(format!("invalid pubkey '{}': {}", input, e)))
+}
+
+/// NIP-19 `npub1...` for a pubkey in any form `pubkey_hex` takes
+pub fn npub(input: &str) -> NineSResult<String> {
+    nostr::PublicKey::parse(input.trim())
+        .map_err(|e| NineSError::Other(format!("invalid pubkey '{}': {}", input, e)))?
+        .to_bech32()
+        .map_err(|e| NineSError::Other(format!("npub: {}", e)))
+}
+
 /// BeeBase 9S Protocol event kinds
 pub mod kinds {
     /// Universal Scroll transport
@@ -39,6 +102,23 @@ pub mod kinds {
     pub const RESPONSE: u16 = 9002;
     /// Watch notification
     pub const WATCH: u16 = 9003;
+    /// Signed proof-of-payment receipt
+    pub const RECEIPT: u16 = 9004;
+    /// Encrypted payment address request / response
+    pub const PAY: u16 = 9005;
+    /// Encrypted pairing handshake
+    pub const PAIR: u16 = 9006;
+    /// Encrypted token-bearing request to a paired node, and its response
+    pub const PEER: u16 = 9007;
+    /// Encrypted heartbeat to a paired node
+    pub const PRESENCE: u16 = 9008;
+    /// NIP-57 zap request (embedded in receipts)
+    pub const ZAP_REQUEST: u16 = 9734;
+    /// NIP-57 zap receipt
+    pub const ZAP_RECEIPT: u16 = 9735;
+    /// Key migration: the old key points at its successor, whose
+    /// counter-signed event is the content
+    pub const KEY_MIGRATION: u16 = 1776;
 }
 
 /// Nostr relay configuration
@@ -91,3 +171,20 @@ pub struct EventFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<usize>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917";
+
+    #[test]
+    fn test_pubkey_forms() {
+        let npub = npub(HEX).unwrap();
+        assert!(npub.starts_with("npub1"));
+        assert_eq!(pubkey_hex(&npub).unwrap(), HEX);
+        assert_eq!(pubkey_hex(&format!("nostr:{}", npub)).unwrap(), HEX);
+        assert_eq!(pubkey_hex(HEX).unwrap(), HEX);
+        assert!(pubkey_hex("npub1notakey").is_err());
+    }
+}
@@ -1,11 +1,13 @@
 //! NostrNamespace - Nostr protocol via 9S paths
 
 use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::proxy::Proxy;
 use crate::identity::Identity;
 use crate::node::NostrConfig;
 use crate::nostr::NostrEffectHandler;
 use crate::mind::EffectHandler;
 use nine_s_core::prelude::*;
+use nine_s_store::Store;
 use serde_json::{json, Value};
 use std::sync::{
     Arc,
@@ -23,6 +25,10 @@ pub struct NostrNamespace {
     effect: NostrEffectHandler,
     runtime: Runtime,
     connected: AtomicBool,
+    store: Option<Arc<Store>>,
+    /// App whose PIN must accompany bunker approvals
+    pin_file: Option<std::path::PathBuf>,
+    proxy: Option<Proxy>,
 }
 
 impl NostrNamespace {
@@ -35,9 +41,37 @@ impl NostrNamespace {
             effect,
             runtime,
             connected: AtomicBool::new(false),
+            store: None,
+            pin_file: None,
+            proxy: None,
         }
     }
 
+    /// Local store for cached relay data (profiles, etc.)
+    pub fn with_store(mut self, store: Arc<Store>) -> Self {
+        self.store = Some(store);
+        self.effect = self.effect_handler();
+        self
+    }
+
+    /// Reach relays through a SOCKS5 proxy
+    pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self.effect = self.effect_handler();
+        self
+    }
+
+    fn effect_handler(&self) -> NostrEffectHandler {
+        let effect = NostrEffectHandler::new(Arc::new(self.identity.clone()), self.config.relays.clone()).with_proxy(self.proxy.clone());
+        match self.store { Some(ref store) => effect.with_store(store.clone()), None => effect }
+    }
+
+    /// Require the PIN in the auth file at `path` to approve bunker signing requests
+    pub fn with_pin_auth(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.pin_file = Some(path.into());
+        self
+    }
+
     fn read_status(&self) -> Scroll {
         scroll("/nostr/status", types::STATUS, json!({
             "initialized": true,
@@ -47,7 +81,11 @@ impl NostrNamespace {
     }
 
     fn read_pubkey(&self) -> Scroll {
-        scroll("/nostr/pubkey", types::PUBKEY, json!({"hex": self.identity.pubkey_hex}))
+        scroll("/nostr/pubkey", types::PUBKEY, json!({"hex": self.identity.pubkey_hex, "npub": super::npub(&self.identity.pubkey_hex).ok()}))
+    }
+
+    fn read_npub(&self) -> NineSResult<Scroll> {
+        Ok(scroll("/nostr/npub", types::PUBKEY, json!({"npub": super::npub(&self.identity.pubkey_hex)?, "hex": self.identity.pubkey_hex})))
     }
 
     fn read_mobi(&self) -> Scroll {
@@ -97,12 +135,84 @@ impl NostrNamespace {
         })))
     }
 
+    fn read_profile(&self, pubkey: Option<&str>) -> NineSResult<Option<Scroll>> {
+        let pubkey = pubkey.map(super::pubkey_hex).transpose()?;
+        let pubkey = pubkey.as_deref();
+        let pk = pubkey.unwrap_or(&self.identity.pubkey_hex);
+        let key = format!("{}/{}", paths::PROFILES_PREFIX, pk);
+        let mut cached = match &self.store { Some(s) => s.read(&key)?, None => None };
+        if cached.is_none() && self.connected.load(Ordering::Relaxed) {
+            let filter = json!({"authors": [pk], "kinds": [0], "limit": 1});
+            if let Some(event) = self.runtime.block_on(self.effect.query(filter)).into_iter().next() {
+                let metadata: Value = serde_json::from_str(&event.content).unwrap_or_else(|_| json!({}));
+                cached = Some(self.cache_profile(pk, metadata, event.created_at.as_u64())?);
+            }
+        }
+        let path = match pubkey { Some(pk) => format!("/nostr/profile/{}", pk), None => "/nostr/profile".into() };
+        match cached {
+            Some(s) => Ok(Some(scroll(&path, types::PROFILE, s.data))),
+            None if pubkey.is_none() => Ok(Some(scroll(&path, types::PROFILE, self.profile_data(pk, json!({}), None)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn write_profile(&self, data: Value) -> NineSResult<Scroll> {
+        let fields = data.as_object().ok_or_else(|| NineSError::Other("profile expects an object".into()))?;
+        let current = self.read_profile(None)?.map(|s| s.data["metadata"].clone()).unwrap_or_else(|| json!({}));
+        let mut metadata = current.as_object().cloned().unwrap_or_default();
+        for (k, v) in fields { metadata.insert(k.clone(), v.clone()); }
+        let metadata = Value::Object(metadata);
+
+        let published = self.write_publish(json!({"kind": 0, "content": metadata}))?;
+        let mut cached = self.cache_profile(&self.identity.pubkey_hex, metadata, nostr::Timestamp::now().as_u64())?.data;
+        cached["publish"] = published.data;
+        Ok(scroll("/nostr/profile", types::PROFILE, cached))
+    }
+
+    fn cache_profile(&self, pubkey: &str, metadata: Value, created_at: u64) -> NineSResult<Scroll> {
+        let s = scroll(&format!("{}/{}", paths::PROFILES_PREFIX, pubkey), types::PROFILE, self.profile_data(pubkey, metadata, Some(created_at))?);
+        match &self.store { Some(store) => store.write_scroll(s), None => Ok(s) }
+    }
+
+    fn profile_data(&self, pubkey: &str, metadata: Value, created_at: Option<u64>) -> NineSResult<Value> {
+        let mobi = crate::mobi::Mobi::derive(pubkey)?;
+        Ok(json!({
+            "pubkey": pubkey,
+            "metadata": metadata,
+            "created_at": created_at,
+            "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()}
+        }))
+    }
+
+    fn write_verify(&self, data: Value) -> NineSResult<Scroll> {
+        let raw = data.get("event").cloned().unwrap_or(data);
+        let event: nostr::Event = match raw {
+            Value::String(s) => serde_json::from_str(&s),
+            v => serde_json::from_value(v),
+        }.map_err(|e| NineSError::Other(format!("Invalid event: {}", e)))?;
+
+        let error = event.verify().err().map(|e| e.to_string());
+        let pubkey = event.pubkey.to_hex();
+        let mobi = crate::mobi::Mobi::derive(&pubkey)?;
+        Ok(scroll("/nostr/verify", types::VERIFY, json!({
+            "valid": error.is_none(),
+            "error": error,
+            "event_id": event.id.to_hex(),
+            "pubkey": pubkey,
+            "kind": event.kind.as_u16(),
+            "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()}
+        })))
+    }
+
+    /// Run `data` through the effect handler as a request under `external`
+    fn execute(&self, external: &str, data: Value) -> NineSResult<Value> {
+        let req = Scroll::new(&format!("{}/{}", external, uuid()), data);
+        let what = external.rsplit('/').next().unwrap_or(external);
+        self.runtime.block_on(self.effect.execute(&req)).map_err(|e| NineSError::Other(format!("{}: {}", what, e)))
+    }
+
     fn write_connect(&self) -> NineSResult<Scroll> {
-        let id = uuid();
-        let scroll_req = Scroll::new(&format!("{}/{}", paths::EXTERNAL_CONNECT, id), json!({}));
-        let result = self.runtime
-            .block_on(self.effect.execute(&scroll_req))
-            .map_err(|e| NineSError::Other(format!("connect: {}", e)))?;
+        let result = self.execute(paths::EXTERNAL_CONNECT, json!({}))?;
         let connected = result.get("count").and_then(|v| v.as_u64()).unwrap_or(0) > 0;
         self.connected.store(connected, Ordering::Relaxed);
         Ok(scroll("/nostr/connect", types::CONNECT, json!({
@@ -113,20 +223,66 @@ impl NostrNamespace {
     }
 
     fn write_publish(&self, data: Value) -> NineSResult<Scroll> {
-        let content = data["content"].as_str().ok_or_else(|| NineSError::Other("no 'content'".into()))?;
-        let kind = data["kind"].as_u64().unwrap_or(1) as u16;
-        let tags = data.get("tags").cloned().unwrap_or_else(|| json!([]));
-
-        let id = uuid();
-        let scroll_req = Scroll::new(&format!("{}/{}", paths::EXTERNAL_PUBLISH, id), json!({
-            "kind": kind,
-            "content": content,
-            "tags": tags,
-        }));
-        let result = self.runtime
-            .block_on(self.effect.execute(&scroll_req))
-            .map_err(|e| NineSError::Other(format!("publish: {}", e)))?;
-        Ok(scroll("/nostr/publish", types::PUBLISH, result))
+        if !data.is_object() { return Err(NineSError::Other("publish expects an object".into())); }
+        let mut req = data;
+        if req.get("kind").is_none() { req["kind"] = json!(1); }
+        if req.get("tags").is_none() { req["tags"] = json!([]); }
+        Ok(scroll("/nostr/publish", types::PUBLISH, self.execute(paths::EXTERNAL_PUBLISH, req)?))
+    }
+
+    fn write_dm(&self, mut data: Value) -> NineSResult<Scroll> {
+        let to = data["to"].as_str().ok_or_else(|| NineSError::Other("no 'to'".into()))?;
+        data["to"] = json!(super::pubkey_hex(to)?);
+        Ok(scroll("/nostr/dm", types::DM, self.execute(paths::EXTERNAL_DM, data)?))
+    }
+
+    fn write_zaps_config(&self, data: Value) -> NineSResult<Scroll> {
+        let store = self.store.as_ref().ok_or_else(|| NineSError::Other("zaps need a store".into()))?;
+        let mut config = crate::nostr::zaps::set_config(store, &data)?.data;
+        config["profile"] = self.write_profile(json!({"lud16": config["lud16"]}))?.data["publish"].clone();
+        Ok(scroll("/nostr/zaps/config", types::ZAP_CONFIG, config))
+    }
+
+    fn write_backup(&self, request: Value) -> NineSResult<Scroll> {
+        let path = if request["restore"] == true { "/nostr/backup/restore" } else { "/nostr/backup" };
+        Ok(scroll(path, types::BACKUP, self.execute(paths::EXTERNAL_BACKUP, request)?))
+    }
+
+    fn read_bunker(&self) -> NineSResult<Option<Scroll>> {
+        let Some(store) = &self.store else { return Ok(None) };
+        let mut cfg = crate::nostr::bunker::config(store)?;
+        if cfg["enabled"] == true {
+            cfg["uri"] = json!(crate::nostr::bunker::uri(&self.identity.pubkey_hex, &self.config.relays, cfg["secret"].as_str()));
+        }
+        Ok(Some(scroll("/nostr/bunker", types::BUNKER, cfg)))
+    }
+
+    fn write_bunker(&self, data: Value) -> NineSResult<Scroll> {
+        let store = self.store.as_ref().ok_or_else(|| NineSError::Other("bunker needs a store".into()))?;
+        crate::nostr::bunker::configure(store, &data)?;
+        Ok(self.read_bunker()?.unwrap_or_else(|| scroll("/nostr/bunker", types::BUNKER, json!({}))))
+    }
+
+    /// `/bunker/poll`, or `/bunker/requests/{id}/approve|reject`
+    fn write_bunker_action(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        let req = match path.strip_prefix("/bunker/requests/").and_then(|r| r.rsplit_once('/')) {
+            Some((id, "approve")) => {
+                self.check_pin(data["pin"].as_str())?;
+                json!({"approve": id})
+            }
+            Some((id, "reject")) => json!({"reject": id}),
+            _ if path == paths::BUNKER_POLL => json!({}),
+            _ => return Err(NineSError::Other(format!("unknown: {}", path))),
+        };
+        Ok(scroll(&format!("/nostr{}", path), types::BUNKER, self.execute(paths::EXTERNAL_BUNKER, req)?))
+    }
+
+    fn check_pin(&self, pin: Option<&str>) -> NineSResult<()> {
+        let Some(path) = &self.pin_file else { return Ok(()) };
+        let auth = crate::auth::PinAuth::open(path.clone())?;
+        if !auth.is_initialized() { return Ok(()); }
+        let pin = pin.ok_or_else(|| NineSError::Other("approval requires 'pin'".into()))?;
+        if auth.verify_pin(pin)? { Ok(()) } else { Err(NineSError::Other("invalid PIN".into())) }
     }
 
     fn write_beebase_connect(&self, data: Value) -> NineSResult<Scroll> {
@@ -157,8 +313,9 @@ impl NostrNamespace {
             .ok_or_else(|| NineSError::Other("Missing 'challenge' field".into()))?;
         let challenge_id = data.get("challenge_id").and_then(|v| v.as_str());
 
-        let server_pubkey = nostr::PublicKey::from_hex(server_pubkey_hex)
+        let server_pubkey = nostr::PublicKey::parse(server_pubkey_hex)
             .map_err(|e| NineSError::Other(format!("Invalid server pubkey: {}", e)))?;
+        let server_pubkey_hex = &server_pubkey.to_hex();
 
         use nostr::secp256k1::{Message as SecpMessage, Secp256k1};
         use sha2::{Digest, Sha256};
@@ -207,9 +364,40 @@ impl Namespace for NostrNamespace {
         Ok(Some(match path {
             paths::STATUS | "" | "/" => self.read_status(),
             paths::PUBKEY => self.read_pubkey(),
+            paths::NPUB => self.read_npub()?,
             paths::MOBI => self.read_mobi(),
             paths::RELAYS => self.read_relays(),
             "/beebase/status" => self.read_beebase_status(),
+            paths::PROFILE => return self.read_profile(None),
+            p if p.starts_with("/profile/") => return self.read_profile(Some(&p["/profile/".len()..])),
+            "/outbox" => {
+                let entries = match &self.store { Some(s) => crate::nostr::outbox::queued(s)?, None => vec![] };
+                scroll("/nostr/outbox", types::OUTBOX, json!({"queued": entries.iter().map(|e| e.data.clone()).collect::<Vec<_>>(), "count": entries.len()}))
+            }
+            paths::CONTACTS => match &self.store {
+                Some(s) => {
+                    let list = crate::nostr::contacts::following(s)?;
+                    scroll("/nostr/contacts", types::CONTACTS, json!({"count": list.len(), "contacts": list, "created_at": crate::nostr::contacts::last_applied(s)?}))
+                }
+                None => return Ok(None),
+            },
+            paths::BUNKER => return self.read_bunker(),
+            paths::ZAPS => match &self.store {
+                Some(s) => scroll("/nostr/zaps", types::ZAP, crate::nostr::zaps::summary(s)?),
+                None => return Ok(None),
+            },
+            paths::BACKUP => match &self.store {
+                Some(s) => match crate::nostr::backup::state(s)? {
+                    Some(state) => scroll("/nostr/backup", types::BACKUP, state),
+                    None => return Ok(None),
+                },
+                None => return Ok(None),
+            },
+            "/archive" => match &self.store {
+                Some(s) => scroll("/nostr/archive", types::ARCHIVE, crate::nostr::archive::summary(s)?),
+                None => return Ok(None),
+            },
+            p if p.starts_with("/outbox/") || p.starts_with("/relays/") || p.starts_with("/archive/") || p.starts_with("/zaps/") || p.starts_with("/bunker/") || p.starts_with("/pay/") => return match &self.store { Some(s) => s.read(&format!("/nostr{}", p)), None => Ok(None) },
             _ => return Ok(None),
         }))
     }
@@ -218,9 +406,39 @@ impl Namespace for NostrNamespace {
             paths::SIGN => self.write_sign(data),
             paths::CONNECT => self.write_connect(),
             paths::PUBLISH => self.write_publish(data),
+            paths::DM => self.write_dm(data),
+            paths::VERIFY => self.write_verify(data),
+            paths::PROFILE => self.write_profile(data),
             "/beebase/connect" => self.write_beebase_connect(data),
             "/beebase/disconnect" => self.write_beebase_disconnect(),
             "/nip46/respond" => self.write_nip46_respond(data),
+            "/outbox/drain" => Ok(scroll("/nostr/outbox/drain", types::OUTBOX, self.execute(paths::EXTERNAL_OUTBOX, json!({}))?)),
+            "/archive/sync" => Ok(scroll("/nostr/archive/sync", types::ARCHIVE, self.execute(paths::EXTERNAL_ARCHIVE, json!({}))?)),
+            paths::CONTACTS_SYNC => Ok(scroll("/nostr/contacts/sync", types::CONTACTS, self.execute(paths::EXTERNAL_CONTACTS, json!({"publish": false}))?)),
+            paths::CONTACTS_PUBLISH => Ok(scroll("/nostr/contacts/publish", types::CONTACTS, self.execute(paths::EXTERNAL_CONTACTS, json!({"publish": true}))?)),
+            "/zaps/config" => self.write_zaps_config(data),
+            paths::ZAPS_SYNC => Ok(scroll("/nostr/zaps/sync", types::ZAP, self.execute(paths::EXTERNAL_ZAPS, json!({}))?)),
+            paths::BUNKER => self.write_bunker(data),
+            p if p.starts_with("/bunker/") => self.write_bunker_action(p, data),
+            paths::PAY_POLL => Ok(scroll("/nostr/pay/poll", types::PAY, self.execute(paths::EXTERNAL_PAY, json!({"poll": true}))?)),
+            paths::PAIR => match &self.store {
+                Some(s) => Ok(scroll("/nostr/pair", types::PAIR, crate::nostr::pair::offer(s, &self.identity.pubkey_hex, &self.config.relays, &data)?)),
+                None => Err(NineSError::Other("pairing needs a store".into())),
+            },
+            paths::PAIR_ACCEPT => Ok(scroll("/nostr/pair/accept", types::PAIR, self.execute(paths::EXTERNAL_PAIR, data)?)),
+            paths::PAIR_POLL => Ok(scroll("/nostr/pair/poll", types::PAIR, self.execute(paths::EXTERNAL_PAIR, json!({"poll": true}))?)),
+            paths::PEERS_REQUEST => Ok(scroll("/nostr/peers/request", types::PEER_REQUEST, self.execute(paths::EXTERNAL_PEERS, data)?)),
+            paths::PRESENCE => Ok(scroll("/nostr/presence", types::PRESENCE, self.execute(paths::EXTERNAL_PRESENCE, json!({}))?)),
+            // Serving runs verbs on the node, so only the effect worker's handler can
+            paths::PEERS_POLL => match &self.store {
+                Some(s) => {
+                    let queued = s.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_PEERS, uuid()), json!({"poll": true})).set_type(types::PEER_REQUEST))?;
+                    Ok(scroll("/nostr/peers/poll", types::PEER_REQUEST, json!({"queued": queued.key})))
+                }
+                None => Err(NineSError::Other("serving peers needs a store".into())),
+            },
+            paths::BACKUP => self.write_backup(json!({"full": data["full"]})),
+            paths::BACKUP_RESTORE => self.write_backup(json!({"restore": true, "overwrite": data["overwrite"]})),
             _ => Err(NineSError::Other(format!("unknown: {}", path))),
         }
     }
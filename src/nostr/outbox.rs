@@ -0,0 +1,86 @@
+//! Outbox - Offline queue for Nostr publishes
+//!
+//! Signed events that no relay accepted land in `/nostr/outbox/{event_id}`.
+//! A write to `/external/nostr/outbox/{id}` drains the queue; RelayPool writes
+//! one on reconnect and `drain_pattern` wires it to a clock pulse
+//! (`Node::start_services` registers it on `sync`, else `minute`).
+
+use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use crate::nostr::state::{pulse_pattern, scroll};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::json;
+
+/// Attempts before an event is marked `failed`
+pub const MAX_ATTEMPTS: u64 = 10;
+/// Events older than this are marked `expired` instead of retried
+pub const MAX_AGE_SECS: u64 = 7 * 24 * 3600;
+
+/// Queue a signed event for later delivery
+pub fn enqueue(store: &Store, event: &nostr::Event, error: &str) -> NineSResult<Scroll> {
+    let data = json!({
+        "event": event,
+        "kind": event.kind.as_u16(),
+        "status": "queued",
+        "attempts": 1,
+        "queued_at": now_secs(),
+        "last_error": error
+    });
+    store.write_scroll(scroll(&format!("{}/{}", paths::OUTBOX_PREFIX, event.id.to_hex()), types::OUTBOX, data))
+}
+
+/// Queued entries awaiting delivery
+pub fn queued(store: &Store) -> NineSResult<Vec<Scroll>> {
+    let mut out = Vec::new();
+    for key in store.list(paths::OUTBOX_PREFIX)? {
+        if let Some(s) = store.read(&key)? {
+            if s.data["status"] == "queued" { out.push(s); }
+        }
+    }
+    Ok(out)
+}
+
+/// Record the outcome of a delivery attempt. Returns the new status.
+pub fn settle(store: &Store, entry: Scroll, delivered: bool, error: Option<&str>) -> NineSResult<&'static str> {
+    let mut data = entry.data;
+    let attempts = data["attempts"].as_u64().unwrap_or(0);
+    let age = now_secs().saturating_sub(data["queued_at"].as_u64().unwrap_or(0));
+    let status = if delivered {
+        "sent"
+    } else if age > MAX_AGE_SECS {
+        "expired"
+    } else if attempts + 1 >= MAX_ATTEMPTS {
+        "failed"
+    } else {
+        "queued"
+    };
+    if !delivered { data["attempts"] = json!(attempts + 1); }
+    data["status"] = json!(status);
+    data["last_error"] = json!(error);
+    data["updated_at"] = json!(now_secs());
+    store.write_scroll(scroll(&entry.key, types::OUTBOX, data))?;
+    Ok(status)
+}
+
+/// Mind pattern that drains the outbox on a clock pulse (e.g. "sync")
+pub fn drain_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("outbox", pulse, types::OUTBOX, paths::EXTERNAL_OUTBOX, json!({"trigger": pulse}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::paths::origin;
+    use crate::core::pattern::Pattern;
+
+    #[test]
+    fn test_drain_pattern_emits_external() {
+        let pattern = Pattern::compile(drain_pattern("sync")).unwrap();
+        let pulse = Scroll::new("/sys/clock/pulses/sync", json!({"name": "sync", "tick": 30}));
+        let out = pattern.apply(&pulse, Some(origin::MIND)).unwrap().unwrap();
+        assert!(out.key.starts_with("/external/nostr/outbox/"));
+        assert_eq!(out.data["trigger"], "sync");
+    }
+}
@@ -0,0 +1,174 @@
+//! Pair - Bootstrap trust between two nodes
+//!
+//! The node being paired with writes `/nostr/pair` `{capabilities?, ttl_secs?, name?}`
+//! and shows the returned `beenode-pair://{pubkey}?relay=...&secret=...` URI
+//! as a QR code (`qr`, terminal-ready). The other node writes
+//! `/nostr/pair/accept` `{uri, capabilities?, name?}`: it sends a kind 9006
+//! event NIP-44 encrypted to the offerer,
+//! `{type: "pair_request", id, secret, name, capabilities}`, and waits up to
+//! `WAIT` for `{type: "pair_accept", id, name, granted}` (or
+//! `pair_reject` with `error`). The offerer answers on `/nostr/pair/poll`
+//! (`poll_pattern` runs it on a clock pulse): the secret must match an
+//! unexpired offer and works once.
+//!
+//! Both sides then hold the other at `/peers/{mobi}`:
+//! `{pubkey, mobi, name, granted, allowed, relays, paired_at}`, where
+//! `granted` is what this node lets the peer do and `allowed` what the peer
+//! lets this node do. Capabilities are free-form strings (`read:/notes`,
+//! `presence`, ...) that the features using peers check.
+
+use crate::core::paths::{nostr as paths, nostr_types as types, peers};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use crate::nostr::state::{pulse_pattern, scroll};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How long the accepting side waits for the answer
+pub const WAIT: Duration = Duration::from_secs(120);
+/// Offer lifetime unless `ttl_secs` says otherwise
+pub const DEFAULT_TTL_SECS: u64 = 600;
+
+pub const PAIR_REQUEST: &str = "pair_request";
+pub const PAIR_ACCEPT: &str = "pair_accept";
+pub const PAIR_REJECT: &str = "pair_reject";
+
+const SCHEME: &str = "beenode-pair://";
+
+/// Create a one-time offer → `{uri, qr, expires_at, capabilities}`
+pub fn offer(store: &Store, pubkey: &str, relays: &[String], data: &Value) -> NineSResult<Value> {
+    if relays.is_empty() { return Err(NineSError::Other("pairing needs at least one relay".into())); }
+    let secret = crate::secret::random_hex(16);
+    let expires_at = now_secs() + data["ttl_secs"].as_u64().unwrap_or(DEFAULT_TTL_SECS);
+    let capabilities = capabilities(&data["capabilities"]);
+    store.write_scroll(scroll(&offer_key(&secret), types::PAIR, json!({
+        "capabilities": capabilities,
+        "name": data["name"],
+        "expires_at": expires_at,
+        "status": "open",
+    })))?;
+    let uri = uri(pubkey, relays, &secret);
+    Ok(json!({"uri": uri, "qr": qr(&uri)?, "expires_at": expires_at, "capabilities": capabilities}))
+}
+
+/// Use up the offer for `secret`; errors if there is none, it expired or was used
+pub fn claim(store: &Store, secret: &str) -> NineSResult<Value> {
+    let key = offer_key(secret);
+    let mut offer = store.read(&key)?.map(|s| s.data).ok_or_else(|| NineSError::Other("unknown pairing secret".into()))?;
+    if offer["status"] != "open" { return Err(NineSError::Other("pairing secret already used".into())); }
+    if offer["expires_at"].as_u64().unwrap_or(0) < now_secs() { return Err(NineSError::Other("pairing offer expired".into())); }
+    offer["status"] = json!("used");
+    offer["used_at"] = json!(now_secs());
+    store.write_scroll(scroll(&key, types::PAIR, offer.clone()))?;
+    Ok(offer)
+}
+
+/// Record a paired node at `/peers/{mobi}`. Pairing again keeps its token
+/// `generation`, so revoked tokens stay revoked.
+pub fn record_peer(store: &Store, pubkey: &str, name: &Value, granted: &Value, allowed: &Value, relays: &[String]) -> NineSResult<Scroll> {
+    let mobi = crate::mobi::Mobi::derive(pubkey)?;
+    let key = format!("{}/{}", peers::PREFIX, mobi.display);
+    let generation = store.read(&key)?.and_then(|s| s.data["generation"].as_u64()).unwrap_or(0);
+    store.write_scroll(scroll(&key, peers::TYPE, json!({
+        "pubkey": pubkey,
+        "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()},
+        "name": name,
+        "granted": capabilities(granted),
+        "allowed": capabilities(allowed),
+        "relays": relays,
+        "paired_at": now_secs(),
+        "generation": generation,
+    })))
+}
+
+/// `beenode-pair://` URI for an offer
+pub fn uri(pubkey: &str, relays: &[String], secret: &str) -> String {
+    let mut query: Vec<String> = relays.iter().map(|r| format!("relay={}", r)).collect();
+    query.push(format!("secret={}", secret));
+    format!("{}{}?{}", SCHEME, pubkey, query.join("&"))
+}
+
+/// `(pubkey_hex, relays, secret)` from a pairing URI
+pub fn parse_uri(uri: &str) -> NineSResult<(String, Vec<String>, String)> {
+    let rest = uri.trim().strip_prefix(SCHEME).ok_or_else(|| NineSError::Other(format!("not a pairing URI: {}", uri)))?;
+    let (pubkey, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let mut relays = Vec::new();
+    let mut secret = None;
+    for (k, v) in query.split('&').filter_map(|kv| kv.split_once('=')) {
+        match k {
+            "relay" => relays.push(v.to_string()),
+            "secret" => secret = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    let secret = secret.ok_or_else(|| NineSError::Other("pairing URI has no secret".into()))?;
+    if relays.is_empty() { return Err(NineSError::Other("pairing URI has no relay".into())); }
+    Ok((super::pubkey_hex(pubkey)?, relays, secret))
+}
+
+/// QR code of `data` as unicode half blocks, for terminals
+pub fn qr(data: &str) -> NineSResult<String> {
+    use qrcode::render::unicode::Dense1x2;
+    let code = qrcode::QrCode::new(data.as_bytes()).map_err(|e| NineSError::Other(format!("qr: {}", e)))?;
+    Ok(code.render::<Dense1x2>().dark_color(Dense1x2::Light).light_color(Dense1x2::Dark).quiet_zone(true).build())
+}
+
+/// Handshake payload of `kind` (`pair_request` / `pair_accept` / `pair_reject`)
+pub fn message(kind: &str, fields: Value) -> String {
+    let mut v = json!({"type": kind});
+    if let (Some(map), Value::Object(fields)) = (v.as_object_mut(), fields) { map.extend(fields); }
+    v.to_string()
+}
+
+/// A decrypted handshake payload of one of `expected`
+pub fn parse(plaintext: &str, expected: &[&str]) -> Option<Value> {
+    let v: Value = serde_json::from_str(plaintext).ok()?;
+    expected.iter().any(|e| v["type"] == *e).then_some(v)
+}
+
+/// Mind pattern that answers pairing requests on a clock pulse
+pub fn poll_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("pair", pulse, types::PAIR, paths::EXTERNAL_PAIR, json!({"poll": true}))
+}
+
+fn capabilities(v: &Value) -> Vec<String> {
+    v.as_array().into_iter().flatten().filter_map(|c| c.as_str().map(String::from)).collect()
+}
+
+/// Offers are kept under a hash of their secret, so listing doesn't reveal it
+fn offer_key(secret: &str) -> String {
+    format!("{}/{}", paths::PAIR_OFFERS, &blake3::hash(secret.as_bytes()).to_hex()[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_is_one_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-pair").with_root(dir.path()).open_store().unwrap();
+        let pubkey = nostr::Keys::generate().public_key().to_hex();
+        let relays = vec!["wss://relay.example".to_string()];
+
+        let offer = offer(&store, &pubkey, &relays, &json!({"capabilities": ["presence"]})).unwrap();
+        let (pk, parsed_relays, secret) = parse_uri(offer["uri"].as_str().unwrap()).unwrap();
+        assert_eq!((pk.as_str(), parsed_relays.as_slice()), (pubkey.as_str(), relays.as_slice()));
+        assert!(!offer["qr"].as_str().unwrap().is_empty());
+        assert!(secret.len() == 32 && secret.chars().all(|c| c.is_ascii_hexdigit()));
+
+        assert_eq!(claim(&store, &secret).unwrap()["capabilities"], json!(["presence"]));
+        assert!(claim(&store, &secret).unwrap_err().to_string().contains("already used"));
+        assert!(claim(&store, "nope").is_err());
+
+        let peer = record_peer(&store, &pubkey, &json!("laptop"), &json!(["presence"]), &json!([]), &relays).unwrap();
+        assert!(peer.key.starts_with("/peers/"));
+        assert_eq!(peer.data["granted"], json!(["presence"]));
+
+        let msg = message(PAIR_REQUEST, json!({"id": "1", "secret": secret}));
+        assert_eq!(parse(&msg, &[PAIR_REQUEST]).unwrap()["id"], "1");
+        assert!(parse(&msg, &[PAIR_ACCEPT, PAIR_REJECT]).is_none());
+    }
+}
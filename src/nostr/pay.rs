@@ -0,0 +1,96 @@
+//! Pay - Ask another node for a payment address
+//!
+//! `/wallet/send {to_mobi}` to a payee without a `bitcoin` address in their
+//! profile queues `/external/nostr/pay/{id}`. The effect sends a kind 9005
+//! event NIP-44 encrypted to the payee, `{type: "address_request", id, amount_sat}`,
+//! waits up to `WAIT` for `{type: "address_response", id, address}` and then
+//! queues the regular `/external/bitcoin/send/{id}` to that address.
+//!
+//! On the payee's node `/nostr/pay/poll` parks each request at
+//! `/nostr/pay/requests/{event_id}` with `status: pending`.
+//! `/wallet/pay-requests/answer` reveals a fresh address for each from a contact, marks it
+//! `answered` and queues `{answer: event_id}`, which sends the reply.
+
+use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::time::now_secs;
+use crate::nostr::state::scroll;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How long a payer waits for the address
+pub const WAIT: Duration = Duration::from_secs(60);
+/// Pause between looks for the answer
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub const ADDRESS_REQUEST: &str = "address_request";
+pub const ADDRESS_RESPONSE: &str = "address_response";
+
+/// Request payload for `id`
+pub fn request(id: &str, amount_sat: u64) -> String {
+    json!({"type": ADDRESS_REQUEST, "id": id, "amount_sat": amount_sat}).to_string()
+}
+
+/// Response payload for `id`
+pub fn response(id: &str, address: &str) -> String {
+    json!({"type": ADDRESS_RESPONSE, "id": id, "address": address}).to_string()
+}
+
+/// A decrypted payload of type `expected`
+pub fn parse(plaintext: &str, expected: &str) -> Option<Value> {
+    let v: Value = serde_json::from_str(plaintext).ok()?;
+    (v["type"] == expected && v["id"].is_string()).then_some(v)
+}
+
+/// Park a payer's request until the wallet answers it
+pub fn park(store: &Store, event_id: &str, from: &str, request: &Value) -> NineSResult<Scroll> {
+    let mobi = crate::mobi::Mobi::derive(from)?;
+    store.write_scroll(scroll(&request_key(event_id), types::PAY, json!({
+        "event_id": event_id,
+        "from": from,
+        "mobi": {"display": mobi.display, "formatted": mobi.display_formatted()},
+        "request_id": request["id"],
+        "amount_sat": request["amount_sat"],
+        "status": "pending",
+        "received_at": now_secs(),
+    })))
+}
+
+/// True once a request event has been parked (or answered)
+pub fn known(store: &Store, event_id: &str) -> NineSResult<bool> {
+    Ok(store.read(&request_key(event_id))?.is_some())
+}
+
+/// A request the wallet has picked an address for
+pub fn answered(store: &Store, event_id: &str) -> NineSResult<Value> {
+    let req = store.read(&request_key(event_id))?.ok_or_else(|| NineSError::Other(format!("no pay request {}", event_id)))?.data;
+    if req["status"] != "answered" || !req["address"].is_string() {
+        return Err(NineSError::Other(format!("pay request {} has no address yet", event_id)));
+    }
+    Ok(req)
+}
+
+pub fn delivered(store: &Store, event_id: &str, delivery: Value) -> NineSResult<Scroll> {
+    let mut req = store.read(&request_key(event_id))?.map(|s| s.data).unwrap_or_else(|| json!({}));
+    req["delivery"] = delivery;
+    req["delivered_at"] = json!(now_secs());
+    store.write_scroll(scroll(&request_key(event_id), types::PAY, req))
+}
+
+fn request_key(event_id: &str) -> String { format!("{}/{}", paths::PAY_REQUESTS, event_id) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads() {
+        let req = parse(&request("r1", 5000), ADDRESS_REQUEST).unwrap();
+        assert_eq!(req["amount_sat"], 5000);
+        assert!(parse(&request("r1", 5000), ADDRESS_RESPONSE).is_none());
+        let resp = parse(&response("r1", "tb1qexample"), ADDRESS_RESPONSE).unwrap();
+        assert_eq!(resp["address"], "tb1qexample");
+        assert!(parse("not json", ADDRESS_REQUEST).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! Peers - token-bearing requests to a paired node over Nostr
+//!
+//! `/nostr/peers/request` `{peer, op, path, data?, token?}` sends a kind 9007
+//! event NIP-44 encrypted to the peer: `{type: "peer_request", id, token, op,
+//! path, data}`, `op` being `get`, `put`, `list` or `effect`, `token` the one
+//! given or else the peer record's (`/peers/{mobi}/credential`). It waits up
+//! to `WAIT` for `{type: "peer_response", id, result}` or `{..., error}`.
+//!
+//! The peer serves requests from its effect worker (`poll_pattern` on a clock
+//! pulse, or a write to `/nostr/peers/poll`, which queues one): the token must
+//! verify for the request (see `crate::peers`) and have been issued to the
+//! peer whose pubkey, recorded at pairing, signed the request, so a token
+//! read off the wire is no use to anyone else over Nostr. Each request event
+//! is served once (recorded at `/nostr/peers/served/{event_id}`), and only
+//! within `MAX_SKEW` of its `created_at`, so a relay can't replay old ones.
+//!
+//! ## Remote effects
+//!
+//! A write to `/peers/{mobi}/external/**` (from a pattern, the CLI or HTTP)
+//! runs that effect on the peer: `watch` queues it as
+//! `{peer, op: "effect", path: "/external/...", data, result}` and the answer
+//! lands at `/peers/{mobi}/external/.../result` like a local effect's. The
+//! peer executes it with `NodeApi::execute_effect`, waiting up to
+//! `EFFECT_WAIT`; the token needs `write` on the `/external/...` path. When
+//! the peer record has a `url` (`/peers/{mobi}/credential` `{token, url}`)
+//! the effect goes straight to the peer's HTTP API instead of over Nostr.
+
+use crate::core::api::NodeApi;
+use crate::core::paths::{mind, nostr as paths, nostr_types as types, origin, peers};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use crate::nostr::state::{pulse_pattern, scroll};
+use crate::peers::{Request, Verb};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// How long a request waits for the answer
+pub const WAIT: Duration = Duration::from_secs(60);
+/// How long the peer waits for an effect's result, inside `WAIT`
+pub const EFFECT_WAIT: Duration = Duration::from_secs(45);
+
+/// Requests signed further than this from now are dropped unanswered
+pub const MAX_SKEW: u64 = 5 * 60;
+
+pub const PEER_REQUEST: &str = "peer_request";
+pub const PEER_RESPONSE: &str = "peer_response";
+
+/// Verb a request `op` needs
+pub fn verb(op: &str) -> NineSResult<Verb> {
+    match op {
+        "get" | "list" => Ok(Verb::Read),
+        "put" | "effect" => Ok(Verb::Write),
+        _ => Err(NineSError::Other(format!("unknown op: {}", op))),
+    }
+}
+
+/// Run `request` from `sender` (hex pubkey) on `node` if its token allows it
+/// and was issued to `sender`
+pub fn serve(node: &dyn NodeApi, store: &Store, sender: &str, request: &Value) -> NineSResult<Value> {
+    let op = request["op"].as_str().unwrap_or_default();
+    let path = request["path"].as_str().ok_or_else(|| NineSError::Other("no 'path'".into()))?;
+    let token = request["token"].as_str().ok_or_else(|| NineSError::Other("no 'token'".into()))?;
+    let data = request.get("data").filter(|d| !d.is_null());
+    let mobi = node.verify_peer_token(token, &Request { verb: verb(op)?, path, data })?;
+    issued_to(store, &mobi, sender)?;
+    let to_value = |s: Scroll| serde_json::to_value(s).map_err(|e| NineSError::Other(e.to_string()));
+    match op {
+        "get" => node.get(path)?.map(to_value).transpose().map(Option::unwrap_or_default),
+        "list" => Ok(json!(node.all(path)?)),
+        "effect" => to_value(node.execute_effect(path, data.cloned().unwrap_or_else(|| json!({})), EFFECT_WAIT)?),
+        _ => to_value(node.put(path, data.cloned().unwrap_or_else(|| json!({})))?),
+    }
+}
+
+/// True once the request event was served (or refused)
+pub fn served(store: &Store, event_id: &str) -> NineSResult<bool> {
+    Ok(store.read(&served_key(event_id))?.is_some())
+}
+
+/// Record a request event as handled, before its answer goes out
+pub fn mark_served(store: &Store, event_id: &str, sender: &str, request: &Value, status: &str) -> NineSResult<Scroll> {
+    store.write_scroll(scroll(&served_key(event_id), types::PEER_REQUEST, json!({
+        "from": sender,
+        "op": request["op"],
+        "path": request["path"],
+        "status": status,
+        "served_at": now_secs(),
+    })))
+}
+
+/// True if a request signed at `created_at` is recent enough to serve
+pub fn fresh(created_at: u64) -> bool {
+    now_secs().abs_diff(created_at) <= MAX_SKEW
+}
+
+/// Check that the peer paired as `mobi` has the pubkey `sender`
+pub fn issued_to(store: &Store, mobi: &str, sender: &str) -> NineSResult<()> {
+    let record = store.read(&format!("{}/{}", peers::PREFIX, mobi))?;
+    match record.as_ref().and_then(|r| r.data["pubkey"].as_str()) {
+        Some(pubkey) if pubkey.eq_ignore_ascii_case(sender) => Ok(()),
+        _ => Err(NineSError::Other("token was issued to another peer".into())),
+    }
+}
+
+/// `(mobi, "/external/...")` of a remote effect at `/peers/{mobi}/external/...`
+pub fn remote_effect(key: &str) -> Option<(&str, String)> {
+    let (mobi, rest) = key.strip_prefix(peers::PREFIX)?.strip_prefix('/')?.split_once('/')?;
+    let path = format!("/{}", rest);
+    let effect = path.starts_with(&format!("{}/", mind::EXTERNAL_PREFIX)) && !path.ends_with(mind::RESULT_SUFFIX);
+    (effect && mobi.chars().all(|c| c.is_ascii_digit())).then_some((mobi, path))
+}
+
+/// Queue the peer request that runs a remote effect; `None` if `scroll` isn't one
+pub fn forward(store: &Store, scroll: &Scroll) -> NineSResult<Option<Scroll>> {
+    let Some((mobi, path)) = remote_effect(&scroll.key) else { return Ok(None) };
+    let job = format!("{}/{}-{}", paths::EXTERNAL_PEERS, &blake3::hash(scroll.key.as_bytes()).to_hex()[..16], scroll.metadata.version);
+    if store.read(&job)?.is_some() { return Ok(None); }
+    store.write_scroll(Scroll::new(&job, json!({
+        "peer": mobi,
+        "op": "effect",
+        "path": path,
+        "data": scroll.data,
+        "result": format!("{}{}", scroll.key, mind::RESULT_SUFFIX),
+    })).set_type(types::PEER_REQUEST)).map(Some)
+}
+
+/// Local path a remote effect request's result goes to: its `result`, if
+/// that is under `/peers/{peer}/external/`
+pub fn result_key(request: &Value) -> Option<String> {
+    let key = request["result"].as_str().filter(|_| request["op"] == "effect")?;
+    let peer = request["peer"].as_str()?;
+    key.starts_with(&format!("{}/{}{}/", peers::PREFIX, peer, peers::EXTERNAL)).then(|| key.to_string())
+}
+
+/// Forward remote effects as they are written
+pub fn watch(store: Store) -> NineSResult<()> {
+    let rx = store.watch(&WatchPattern::parse(&format!("{}/**", peers::PREFIX))?)?;
+    std::thread::spawn(move || {
+        while let Ok(s) = rx.recv() {
+            if s.metadata.produced_by.as_deref() == Some(origin::EFFECTS) { continue; }
+            if let Err(e) = forward(&store, &s) { tracing::warn!("peer effect {}: {}", s.key, e); }
+        }
+    });
+    Ok(())
+}
+
+/// Mind pattern that serves peer requests on a clock pulse
+pub fn poll_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("peers", pulse, types::PEER_REQUEST, paths::EXTERNAL_PEERS, json!({"poll": true}))
+}
+
+fn served_key(event_id: &str) -> String { format!("{}/{}", paths::PEERS_SERVED, event_id) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ops_map_to_verbs() {
+        assert_eq!(verb("get").unwrap(), Verb::Read);
+        assert_eq!(verb("list").unwrap(), Verb::Read);
+        assert_eq!(verb("put").unwrap(), Verb::Write);
+        assert_eq!(verb("effect").unwrap(), Verb::Write);
+        assert!(verb("delete").is_err());
+    }
+
+    #[test]
+    fn test_remote_effects_are_forwarded_once() {
+        assert_eq!(remote_effect("/peers/650073047435/external/wallet/sync/1"), Some(("650073047435", "/external/wallet/sync/1".to_string())));
+        assert!(remote_effect("/peers/650073047435/external/wallet/sync/1/result").is_none());
+        assert!(remote_effect("/peers/650073047435/presence").is_none());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-peers").with_root(dir.path()).open_store().unwrap();
+        let effect = store.write_scroll(Scroll::new("/peers/650073047435/external/wallet/sync/1", json!({"full": true}))).unwrap();
+        let job = forward(&store, &effect).unwrap().unwrap();
+        assert!(job.key.starts_with(paths::EXTERNAL_PEERS));
+        assert_eq!(job.data["path"], "/external/wallet/sync/1");
+        assert_eq!(job.data["result"], "/peers/650073047435/external/wallet/sync/1/result");
+        assert_eq!(result_key(&job.data).as_deref(), job.data["result"].as_str());
+        assert!(result_key(&json!({"op": "effect", "peer": "650073047435", "result": "/wallet/x"})).is_none());
+        assert!(forward(&store, &effect).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tokens_bind_to_the_paired_pubkey() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-peers").with_root(dir.path()).open_store().unwrap();
+        let paired = "a".repeat(64);
+        store.write_scroll(Scroll::new("/peers/650073047435", json!({"pubkey": paired, "generation": 0})).set_type(peers::TYPE)).unwrap();
+
+        assert!(issued_to(&store, "650073047435", &paired).is_ok());
+        assert!(issued_to(&store, "650073047435", &paired.to_uppercase()).is_ok());
+        assert!(issued_to(&store, "650073047435", &"b".repeat(64)).is_err());
+        assert!(issued_to(&store, "123456789012", &paired).is_err());
+    }
+    #[test]
+    fn test_requests_are_served_once_and_only_fresh() {
+        assert!(fresh(now_secs()));
+        assert!(!fresh(now_secs() - MAX_SKEW - 60));
+        assert!(!fresh(now_secs() + MAX_SKEW + 60));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-peers").with_root(dir.path()).open_store().unwrap();
+        let request = json!({"op": "put", "path": "/notes/a", "token": "bpt1.x.y"});
+        assert!(!served(&store, "e1").unwrap());
+        let record = mark_served(&store, "e1", &"a".repeat(64), &request, "served").unwrap();
+        assert!(served(&store, "e1").unwrap());
+        assert!(record.data.get("token").is_none());
+    }
+}
@@ -0,0 +1,75 @@
+//! Presence - heartbeats between paired nodes
+//!
+//! A write to `/nostr/presence` (or `heartbeat_pattern` on a clock pulse)
+//! sends every peer at `/peers/{mobi}` a kind 9008 event NIP-44 encrypted to
+//! it, `{type: "heartbeat", tick, version, ip_hint, at}`, then reads the
+//! heartbeats peers sent since the last run into `/peers/{mobi}/presence`
+//! `{tick, version, ip_hint, at, seen_at}`. Reading that path adds `online`
+//! (`crate::peers::presence`).
+//!
+//! Peers whose record has `presence: false` get no heartbeats. `ip_hint` is
+//! the address this machine would reach the internet from (no packet is
+//! sent); it is only a hint, usually a LAN address behind NAT.
+
+use crate::core::paths::{nostr as paths, nostr_types as types, peers};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use crate::nostr::state::{pulse_pattern, scroll};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+pub const HEARTBEAT: &str = "heartbeat";
+
+/// Heartbeat payload for the current `tick`
+pub fn heartbeat(tick: u64) -> String {
+    super::pair::message(HEARTBEAT, json!({
+        "tick": tick,
+        "version": env!("CARGO_PKG_VERSION"),
+        "ip_hint": ip_hint(),
+        "at": now_secs(),
+    }))
+}
+
+/// Address the default route leaves from
+pub fn ip_hint() -> Option<String> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("1.1.1.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+/// Record a peer's heartbeat at `/peers/{mobi}/presence`, keeping the newest
+pub fn record(store: &Store, mobi: &str, beat: &Value) -> NineSResult<Option<Scroll>> {
+    let key = format!("{}/{}{}", peers::PREFIX, mobi, peers::PRESENCE);
+    let at = beat["at"].as_u64().unwrap_or(0);
+    if store.read(&key)?.is_some_and(|s| s.data["at"].as_u64().unwrap_or(0) >= at) { return Ok(None); }
+    store.write_scroll(scroll(&key, peers::PRESENCE_TYPE, json!({
+        "tick": beat["tick"], "version": beat["version"], "ip_hint": beat["ip_hint"], "at": at, "seen_at": now_secs(),
+    }))).map(Some)
+}
+
+/// Mind pattern that exchanges heartbeats on a clock pulse
+pub fn heartbeat_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("presence", pulse, types::PRESENCE, paths::EXTERNAL_PRESENCE, json!({}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence_keeps_newest_and_derives_online() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-presence").with_root(dir.path()).open_store().unwrap();
+
+        let beat: Value = serde_json::from_str(&heartbeat(7)).unwrap();
+        assert_eq!(beat["type"], HEARTBEAT);
+        let recorded = record(&store, "650073047435", &beat).unwrap().unwrap();
+        assert_eq!(recorded.key, "/peers/650073047435/presence");
+        assert_eq!(crate::peers::presence(recorded.data.clone())["online"], true);
+
+        let stale = json!({"tick": 3, "at": 1000});
+        assert!(record(&store, "650073047435", &stale).unwrap().is_none());
+        assert_eq!(crate::peers::presence(stale)["online"], false);
+    }
+}
@@ -0,0 +1,105 @@
+//! Shared plumbing for the nostr effect modules
+//!
+//! Effect-produced scrolls, the clock-pulse patterns that queue a module's
+//! effect, and the `Cursor` every relay poll keeps under its `*_STATE` path.
+
+use crate::core::paths::origin;
+use crate::core::pattern::PatternDef;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Scroll written by the effect worker, so watchers don't react to their own writes
+pub(crate) fn scroll(key: &str, type_: &str, data: Value) -> Scroll {
+    Scroll { key: key.into(), type_: type_.into(), metadata: Metadata::default().with_produced_by(origin::EFFECTS), data }
+}
+
+/// Mind pattern that writes `template` under `external` on each `pulse`
+pub(crate) fn pulse_pattern(name: &str, pulse: &str, emit: &str, external: &str, template: Value) -> PatternDef {
+    PatternDef {
+        name: format!("nostr-{}-{}", name, pulse),
+        watch: format!("/sys/clock/pulses/{}", pulse),
+        x: None,
+        g: None,
+        v: None,
+        emit: emit.into(),
+        emit_path: format!("{}/${{uuid}}", external),
+        template,
+        then: None,
+        when: None,
+        reactions: vec![],
+    }
+}
+
+/// Where a relay poll left off: the newest `created_at` handled and the ids
+/// handled at that second. Relays answer `since` inclusively, so without the
+/// ids every poll would see its newest events again.
+pub(crate) struct Cursor {
+    key: &'static str,
+    type_: &'static str,
+    since: Option<u64>,
+    seen: Vec<String>,
+}
+
+impl Cursor {
+    pub fn load(store: &Store, key: &'static str, type_: &'static str) -> NineSResult<Self> {
+        let data = store.read(key)?.map(|s| s.data).unwrap_or_default();
+        let seen = data["seen"].as_array().into_iter().flatten().filter_map(|v| v.as_str().map(String::from)).collect();
+        Ok(Cursor { key, type_, since: data["since"].as_u64(), seen })
+    }
+
+    pub fn since(&self) -> Option<u64> { self.since }
+
+    /// `filter` narrowed to events from the cursor on
+    pub fn filter(&self, mut filter: Value) -> Value {
+        if let Some(since) = self.since { filter["since"] = json!(since); }
+        filter
+    }
+
+    /// False for an event a previous poll already handled
+    pub fn is_new(&self, event: &nostr::Event) -> bool {
+        !self.seen.contains(&event.id.to_hex())
+    }
+
+    /// Move past `events` (a poll's results) and save
+    pub fn advance(&mut self, store: &Store, events: &[nostr::Event]) -> NineSResult<()> {
+        let Some(newest) = events.iter().map(|e| e.created_at.as_u64()).max() else { return Ok(()) };
+        if self.since.is_some_and(|s| newest < s) { return Ok(()); }
+        if self.since != Some(newest) { self.seen.clear(); }
+        self.since = Some(newest);
+        for event in events.iter().filter(|e| e.created_at.as_u64() == newest) {
+            if self.is_new(event) { self.seen.push(event.id.to_hex()); }
+        }
+        store.write_scroll(scroll(self.key, self.type_, json!({"since": newest, "seen": self.seen})))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{Keys, Kind, Timestamp, UnsignedEvent};
+
+    #[test]
+    fn test_cursor_skips_events_at_its_second() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-nostr-state").with_root(dir.path()).open_store().unwrap();
+        let keys = Keys::generate();
+        let at = |secs: u64, text: &str| UnsignedEvent::new(keys.public_key(), Timestamp::from(secs), Kind::TextNote, vec![], text).sign_with_keys(&keys).unwrap();
+        let (old, first) = (at(100, "old"), at(200, "first"));
+
+        let mut cursor = Cursor::load(&store, "/nostr/test/state", "test").unwrap();
+        assert!(cursor.filter(json!({})).get("since").is_none());
+        cursor.advance(&store, &[first.clone(), old]).unwrap();
+
+        // Reloaded, the boundary event is known but a later one at the same second isn't
+        let mut cursor = Cursor::load(&store, "/nostr/test/state", "test").unwrap();
+        assert_eq!(cursor.filter(json!({}))["since"], 200);
+        assert!(!cursor.is_new(&first));
+        assert!(cursor.is_new(&at(200, "second")));
+
+        cursor.advance(&store, &[at(300, "next")]).unwrap();
+        assert_eq!(cursor.since(), Some(300));
+        assert!(cursor.is_new(&first));
+    }
+}
@@ -0,0 +1,154 @@
+//! Zaps - NIP-57 zap receipts addressed to this node
+//!
+//! There is no Lightning backend in the node, so invoices come from an
+//! external LNURL server. `/nostr/zaps/config` `{lud16, zapper_pubkey}` names
+//! it: the `lud16` goes into our kind-0 profile so clients can zap us, and
+//! `zapper_pubkey` (the server's `nostrPubkey`) is the only key whose kind-9735
+//! receipts we accept.
+//!
+//! `/external/nostr/zaps/{id}` fetches receipts tagging our pubkey since the
+//! last sync and checks each one (NIP-57 appendix F): signed by the zapper,
+//! embedded kind-9734 request validly signed and tagging us, request `amount`
+//! matching the bolt11 amount. Valid receipts land at
+//! `/nostr/zaps/received/{event_id}` `{amount_sat, sender, comment, zapped_event}`.
+
+use crate::core::paths::{nostr as paths, nostr_types as types};
+use crate::core::pattern::PatternDef;
+use crate::core::time::now_secs;
+use crate::nostr::kinds;
+use crate::nostr::state::{pulse_pattern, scroll, Cursor};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// `{lud16, zapper_pubkey}` if configured
+pub fn config(store: &Store) -> NineSResult<Option<Value>> {
+    Ok(store.read(paths::ZAPS_CONFIG)?.map(|s| s.data))
+}
+
+pub fn set_config(store: &Store, data: &Value) -> NineSResult<Scroll> {
+    let lud16 = data["lud16"].as_str().filter(|a| a.split_once('@').is_some_and(|(u, d)| !u.is_empty() && d.contains('.')))
+        .ok_or_else(|| NineSError::Other("'lud16' must be a lightning address (name@domain)".into()))?;
+    let zapper = data["zapper_pubkey"].as_str().filter(|pk| is_hex_pubkey(pk))
+        .ok_or_else(|| NineSError::Other("'zapper_pubkey' must be the LNURL server's 64-char hex nostrPubkey".into()))?;
+    store.write_scroll(scroll(paths::ZAPS_CONFIG, types::ZAP_CONFIG, json!({"lud16": lud16, "zapper_pubkey": zapper, "updated_at": now_secs()})))
+}
+
+/// Check a kind-9735 receipt for `me`. Returns the zap summary or why it was rejected.
+pub fn verify_receipt(receipt: &nostr::Event, me: &str, zapper: &str) -> Result<Value, String> {
+    if receipt.kind.as_u16() != kinds::ZAP_RECEIPT { return Err("not a zap receipt".into()); }
+    receipt.verify().map_err(|e| format!("receipt signature: {}", e))?;
+    if receipt.pubkey.to_hex() != zapper { return Err("receipt not signed by our zapper".into()); }
+
+    let description = tag(receipt, "description").ok_or("no description tag")?;
+    let request: nostr::Event = serde_json::from_str(&description).map_err(|e| format!("zap request: {}", e))?;
+    if request.kind.as_u16() != kinds::ZAP_REQUEST { return Err("description is not a zap request".into()); }
+    request.verify().map_err(|e| format!("zap request signature: {}", e))?;
+    if tag(&request, "p").as_deref() != Some(me) { return Err("zap request is for someone else".into()); }
+
+    let bolt11 = tag(receipt, "bolt11").ok_or("no bolt11 tag")?;
+    let msats = bolt11_msats(&bolt11).ok_or("bolt11 has no amount")?;
+    if let Some(requested) = tag(&request, "amount") {
+        if requested.parse::<u64>().ok() != Some(msats) { return Err("bolt11 amount differs from requested amount".into()); }
+    }
+
+    Ok(json!({
+        "id": receipt.id.to_hex(),
+        "amount_msat": msats,
+        "amount_sat": msats / 1000,
+        "sender": request.pubkey.to_hex(),
+        "comment": request.content,
+        "zapped_event": tag(&request, "e"),
+        "bolt11": bolt11,
+        "created_at": receipt.created_at.as_u64(),
+    }))
+}
+
+/// Store a verified zap. Returns false if it was already recorded.
+pub fn record(store: &Store, zap: Value) -> NineSResult<bool> {
+    let key = format!("{}/{}", paths::ZAPS_RECEIVED, zap["id"].as_str().unwrap_or_default());
+    if store.read(&key)?.is_some() { return Ok(false); }
+    store.write_scroll(scroll(&key, types::ZAP, zap))?;
+    Ok(true)
+}
+
+/// `{count, total_sat, since}` over received zaps
+pub fn summary(store: &Store) -> NineSResult<Value> {
+    let (mut count, mut total) = (0, 0);
+    for key in store.list(paths::ZAPS_RECEIVED)? {
+        if let Some(s) = store.read(&key)? {
+            count += 1;
+            total += s.data["amount_sat"].as_u64().unwrap_or(0);
+        }
+    }
+    Ok(json!({"count": count, "total_sat": total, "since": Cursor::load(store, paths::ZAPS_STATE, types::ZAP)?.since(), "config": config(store)?}))
+}
+
+/// Mind pattern that collects zap receipts on a clock pulse
+pub fn sync_pattern(pulse: &str) -> PatternDef {
+    pulse_pattern("zaps", pulse, types::ZAP, paths::EXTERNAL_ZAPS, json!({"trigger": pulse}))
+}
+
+/// Millisatoshis encoded in a bolt11 human-readable part (`lnbc2500u1...` → 250_000_000)
+pub fn bolt11_msats(invoice: &str) -> Option<u64> {
+    let invoice = invoice.to_ascii_lowercase();
+    let hrp = &invoice[..invoice.rfind('1')?];
+    let rest = ["lnbcrt", "lntbs", "lnbc", "lntb", "lnsb"].iter().find_map(|p| hrp.strip_prefix(p))?;
+    let (digits, unit) = match rest.chars().last()? {
+        c @ ('m' | 'u' | 'n' | 'p') => (&rest[..rest.len() - 1], Some(c)),
+        _ => (rest, None),
+    };
+    let n: u64 = digits.parse().ok()?;
+    // 1 BTC = 10^11 msat
+    match unit {
+        None => n.checked_mul(100_000_000_000),
+        Some('m') => n.checked_mul(100_000_000),
+        Some('u') => n.checked_mul(100_000),
+        Some('n') => n.checked_mul(100),
+        _ => if n % 10 == 0 { Some(n / 10) } else { None },
+    }
+}
+
+fn tag(event: &nostr::Event, name: &str) -> Option<String> {
+    event.tags.iter().find_map(|t| {
+        let t = t.as_slice();
+        (t.first().map(String::as_str) == Some(name)).then(|| t.get(1).cloned()).flatten()
+    })
+}
+
+fn is_hex_pubkey(s: &str) -> bool { s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, Keys, Kind, Tag};
+
+    #[test]
+    fn test_bolt11_msats() {
+        assert_eq!(bolt11_msats("lnbc2500u1pvjluezpp5qqq"), Some(250_000_000));
+        assert_eq!(bolt11_msats("lnbc10n1pxyz"), Some(1_000));
+        assert_eq!(bolt11_msats("lnbcrt1m1pxyz"), Some(100_000_000));
+        assert_eq!(bolt11_msats("lnbc1pvjluezpp5qqq"), None);
+    }
+
+    #[test]
+    fn test_verify_receipt() {
+        let (me, sender, zapper) = (Keys::generate(), Keys::generate(), Keys::generate());
+        let me_hex = me.public_key().to_hex();
+        let request = EventBuilder::new(Kind::Custom(kinds::ZAP_REQUEST), "great post", [
+            Tag::parse(&["p".to_string(), me_hex.clone()]).unwrap(),
+            Tag::parse(&["amount".to_string(), "21000".to_string()]).unwrap(),
+        ]).sign_with_keys(&sender).unwrap();
+        let receipt = |bolt11: &str, signer: &Keys| EventBuilder::new(Kind::Custom(kinds::ZAP_RECEIPT), "", [
+            Tag::parse(&["p".to_string(), me_hex.clone()]).unwrap(),
+            Tag::parse(&["bolt11".to_string(), bolt11.to_string()]).unwrap(),
+            Tag::parse(&["description".to_string(), serde_json::to_string(&request).unwrap()]).unwrap(),
+        ]).sign_with_keys(signer).unwrap();
+
+        let zap = verify_receipt(&receipt("lnbc210n1pxyz", &zapper), &me_hex, &zapper.public_key().to_hex()).unwrap();
+        assert_eq!(zap["amount_sat"], 21);
+        assert_eq!(zap["comment"], "great post");
+        assert!(verify_receipt(&receipt("lnbc210n1pxyz", &sender), &me_hex, &zapper.public_key().to_hex()).is_err());
+        assert!(verify_receipt(&receipt("lnbc1u1pxyz", &zapper), &me_hex, &zapper.public_key().to_hex()).is_err());
+    }
+}
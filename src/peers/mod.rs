@@ -0,0 +1,282 @@
+//! Peers - what paired nodes may do here
+//!
+//! Pairing (`/nostr/pair`) records each side at `/peers/{mobi}`. Access is
+//! then granted with tokens: `/peers/{mobi}/token` issues one, a
+//! macaroon-style bearer credential `bpt1.{body}.{sig}` where `body` is the
+//! base64url JSON `{i: "{mobi}:{generation}:{nonce}", c: [caveats]}` and
+//! `sig` chains keyed BLAKE3 from the node's token key over the id and each
+//! caveat in turn. Anyone holding a token can `attenuate` it (append a
+//! caveat, narrowing it) without the key; nobody can widen it.
+//!
+//! Caveats, all of which must hold:
+//!
+//! | Caveat | Holds when |
+//! |--------|-----------|
+//! | `prefix=/wallet/balance,/wallet/send` | the path is one of these or under one |
+//! | `deny=/wallet/descriptor` | the path is none of these nor under one |
+//! | `verb=read,write` | the request's verb is listed |
+//! | `spend<=50000` | a write that can move funds is a send of at most this many sats |
+//! | `expires=1760000000` | now is before this unix time |
+//!
+//! Under `spend<=`, writes anywhere funds can move from (`FUNDS`: the
+//! wallet, bitcoin and nostr-pay effects, recurring payments, the effect
+//! queue, Mind patterns and plugins) are refused unless they are a
+//! `/wallet/send` or `/external/bitcoin/send` with `amount_sat` in the limit.
+//!
+//! `/peers/{mobi}/revoke` bumps the peer's `generation`, which every token
+//! issued before carries, so they all stop verifying. The HTTP server takes
+//! tokens as `Authorization: Bearer bpt1...` next to `BEENODE_API_TOKEN`;
+//! Nostr requests carry them in the payload (`/nostr/peers/request`).
+//!
+//! Heartbeats (`/nostr/presence`) land at `/peers/{mobi}/presence`; reads
+//! add `online`, false once the last one is `OFFLINE_AFTER` old.
+
+mod namespace;
+
+pub use namespace::PeersNamespace;
+
+use crate::core::paths::{effects, mind, nostr, peers as paths, plugins, recurring, wallet};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine as _};
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+pub const TOKEN_PREFIX: &str = "bpt1.";
+/// A peer is offline once its last heartbeat is older than this
+pub const OFFLINE_AFTER: u64 = 15 * 60;
+
+/// Where a write can move funds, directly or by queueing, automating or
+/// re-running a spend
+const FUNDS: &[&str] = &[
+    "/wallet",
+    "/external/bitcoin",
+    nostr::EXTERNAL_PAY,
+    recurring::PREFIX,
+    effects::PREFIX,
+    mind::PATTERNS_PREFIX,
+    plugins::PREFIX,
+];
+
+/// What a request does, as caveats see it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Read,
+    Write,
+}
+
+impl Verb {
+    pub fn as_str(&self) -> &'static str {
+        match self { Verb::Read => "read", Verb::Write => "write" }
+    }
+}
+
+/// A request to check a token against
+#[derive(Debug, Clone)]
+pub struct Request<'a> {
+    pub verb: Verb,
+    pub path: &'a str,
+    /// Write payload, for `spend<=`
+    pub data: Option<&'a Value>,
+}
+
+/// A decoded (not yet verified) token
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub id: String,
+    pub caveats: Vec<String>,
+    sig: [u8; 32],
+}
+
+impl Token {
+    pub fn parse(token: &str) -> NineSResult<Self> {
+        let bad = || NineSError::Other("malformed peer token".into());
+        let (body, sig) = token.strip_prefix(TOKEN_PREFIX).and_then(|t| t.split_once('.')).ok_or_else(bad)?;
+        let body: Value = serde_json::from_slice(&BASE64.decode(body).map_err(|_| bad())?).map_err(|_| bad())?;
+        let sig: [u8; 32] = hex::decode(sig).ok().and_then(|s| s.try_into().ok()).ok_or_else(bad)?;
+        Ok(Self {
+            id: body["i"].as_str().ok_or_else(bad)?.to_string(),
+            caveats: body["c"].as_array().ok_or_else(bad)?.iter().filter_map(|c| c.as_str().map(String::from)).collect(),
+            sig,
+        })
+    }
+
+    /// `(mobi, generation)` from the id
+    pub fn peer(&self) -> NineSResult<(String, u64)> {
+        let mut parts = self.id.split(':');
+        match (parts.next(), parts.next().and_then(|g| g.parse().ok())) {
+            (Some(mobi), Some(generation)) if !mobi.is_empty() => Ok((mobi.to_string(), generation)),
+            _ => Err(NineSError::Other("malformed peer token".into())),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let body = json!({"i": self.id, "c": self.caveats}).to_string();
+        format!("{}{}.{}", TOKEN_PREFIX, BASE64.encode(body), hex::encode(self.sig))
+    }
+}
+
+/// Issue a token for `mobi` at `generation` with `caveats`
+pub fn issue(key: &[u8; 32], mobi: &str, generation: u64, caveats: Vec<String>) -> String {
+    let id = format!("{}:{}:{}", mobi, generation, crate::secret::random_hex(8));
+    let sig = caveats.iter().fold(*blake3::keyed_hash(key, id.as_bytes()).as_bytes(), |sig, c| *blake3::keyed_hash(&sig, c.as_bytes()).as_bytes());
+    Token { id, caveats, sig }.encode()
+}
+
+/// Narrow `token` with one more caveat (needs no key)
+pub fn attenuate(token: &str, caveat: &str) -> NineSResult<String> {
+    let mut t = Token::parse(token)?;
+    t.sig = *blake3::keyed_hash(&t.sig, caveat.as_bytes()).as_bytes();
+    t.caveats.push(caveat.to_string());
+    Ok(t.encode())
+}
+
+/// Caveats for `/peers/{mobi}/token` `{prefixes, deny, verbs, spend_limit_sat, ttl_secs}`
+pub fn caveats(data: &Value) -> NineSResult<Vec<String>> {
+    let list = |field: &str| -> Vec<String> {
+        data[field].as_array().into_iter().flatten().filter_map(|v| v.as_str().map(String::from)).collect()
+    };
+    let prefixes = list("prefixes");
+    if prefixes.is_empty() { return Err(NineSError::Other("token needs at least one of 'prefixes'".into())); }
+    if let Some(p) = prefixes.iter().chain(list("deny").iter()).find(|p| !p.starts_with('/') || p.contains(',')) {
+        return Err(NineSError::Other(format!("invalid prefix: {}", p)));
+    }
+    let verbs = match list("verbs") { v if v.is_empty() => vec!["read".to_string()], v => v };
+    if let Some(v) = verbs.iter().find(|v| *v != "read" && *v != "write") {
+        return Err(NineSError::Other(format!("unknown verb: {}", v)));
+    }
+    let mut out = vec![format!("prefix={}", prefixes.join(",")), format!("verb={}", verbs.join(","))];
+    let deny = list("deny");
+    if !deny.is_empty() { out.push(format!("deny={}", deny.join(","))); }
+    if let Some(limit) = data["spend_limit_sat"].as_u64() { out.push(format!("spend<={}", limit)); }
+    if let Some(ttl) = data["ttl_secs"].as_u64() { out.push(format!("expires={}", now_secs() + ttl)); }
+    Ok(out)
+}
+
+/// Check `token` for `request`: signature, revocation, then every caveat.
+/// Returns the peer's mobi.
+pub fn verify(key: &[u8; 32], store: &Store, token: &str, request: &Request) -> NineSResult<String> {
+    let t = Token::parse(token)?;
+    let sig = t.caveats.iter().fold(*blake3::keyed_hash(key, t.id.as_bytes()).as_bytes(), |sig, c| *blake3::keyed_hash(&sig, c.as_bytes()).as_bytes());
+    // blake3::Hash compares in constant time
+    if blake3::Hash::from(sig) != blake3::Hash::from(t.sig) { return Err(NineSError::Other("invalid peer token".into())); }
+
+    let (mobi, generation) = t.peer()?;
+    let record = store.read(&format!("{}/{}", paths::PREFIX, mobi))?
+        .ok_or_else(|| NineSError::Other(format!("unknown peer: {}", mobi)))?;
+    if record.data["generation"].as_u64().unwrap_or(0) != generation {
+        return Err(NineSError::Other("peer token revoked".into()));
+    }
+    for caveat in &t.caveats {
+        check(caveat, request).map_err(|e| NineSError::Other(format!("peer token: {}", e)))?;
+    }
+    Ok(mobi)
+}
+
+fn check(caveat: &str, request: &Request) -> Result<(), String> {
+    let path = request.path.split('?').next().unwrap_or_default();
+    let under = |list: &str| list.split(',').any(|p| {
+        let p = p.trim_end_matches('/');
+        p.is_empty() || path == p || path.starts_with(&format!("{}/", p))
+    });
+    if let Some(list) = caveat.strip_prefix("prefix=") {
+        if !under(list) { return Err(format!("{} not allowed", path)); }
+    } else if let Some(list) = caveat.strip_prefix("deny=") {
+        if under(list) { return Err(format!("{} denied", path)); }
+    } else if let Some(list) = caveat.strip_prefix("verb=") {
+        if !list.split(',').any(|v| v == request.verb.as_str()) { return Err(format!("{} not allowed", request.verb.as_str())); }
+    } else if let Some(limit) = caveat.strip_prefix("spend<=") {
+        let limit: u64 = limit.parse().map_err(|_| format!("bad caveat {}", caveat))?;
+        if request.verb == Verb::Write && FUNDS.iter().any(|p| path == *p || path.starts_with(&format!("{}/", p))) {
+            let send = spends(path, wallet::SEND) || path.starts_with(&format!("{}/", wallet::EXTERNAL_SEND));
+            if !send { return Err(format!("{} can move funds; a spend limit allows only sends", path)); }
+            let amount = request.data.and_then(|d| d["amount_sat"].as_u64()).ok_or("send without amount_sat under a spend limit")?;
+            if amount > limit { return Err(format!("{} sats over the {} sat limit", amount, limit)); }
+        }
+    } else if let Some(at) = caveat.strip_prefix("expires=") {
+        if now_secs() >= at.parse::<u64>().map_err(|_| format!("bad caveat {}", caveat))? { return Err("expired".into()); }
+    } else {
+        return Err(format!("unknown caveat {}", caveat));
+    }
+    Ok(())
+}
+
+/// A `/peers/{mobi}/presence` record with `online` derived from its age
+pub fn presence(mut data: Value) -> Value {
+    let at = data["at"].as_u64().unwrap_or(0);
+    data["online"] = json!(now_secs().saturating_sub(at) <= OFFLINE_AFTER);
+    data
+}
+
+/// `/wallet{op}` or `/wallet/{name}{op}`
+fn spends(path: &str, op: &str) -> bool {
+    let Some(rest) = path.strip_prefix("/wallet") else { return false };
+    rest == op || rest.strip_suffix(op).is_some_and(|name| name.starts_with('/') && !name[1..].contains('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_caveats_and_revocation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-peers").with_root(dir.path()).open_store().unwrap();
+        store.write_scroll(Scroll::new("/peers/650073047435", json!({"generation": 0})).set_type(paths::TYPE)).unwrap();
+        let key = [9u8; 32];
+
+        let caveats = caveats(&json!({
+            "prefixes": ["/wallet/balance", "/wallet/send"],
+            "verbs": ["read", "write"],
+            "spend_limit_sat": 50000,
+        })).unwrap();
+        let token = issue(&key, "650073047435", 0, caveats);
+        let read = |path| Request { verb: Verb::Read, path, data: None };
+        assert_eq!(verify(&key, &store, &token, &read("/wallet/balance")).unwrap(), "650073047435");
+        assert!(verify(&key, &store, &token, &read("/wallet/descriptor")).is_err());
+        assert!(verify(&[1u8; 32], &store, &token, &read("/wallet/balance")).is_err());
+
+        let send = |amount: u64| json!({"to": "bc1q", "amount_sat": amount});
+        let (small, big) = (send(1000), send(60000));
+        assert!(verify(&key, &store, &token, &Request { verb: Verb::Write, path: "/wallet/send", data: Some(&small) }).is_ok());
+        assert!(verify(&key, &store, &token, &Request { verb: Verb::Write, path: "/wallet/send", data: Some(&big) }).is_err());
+
+        let narrowed = attenuate(&token, "verb=read").unwrap();
+        assert!(verify(&key, &store, &narrowed, &read("/wallet/balance")).is_ok());
+        assert!(verify(&key, &store, &narrowed, &Request { verb: Verb::Write, path: "/wallet/send", data: Some(&small) }).is_err());
+        let mut forged = Token::parse(&narrowed).unwrap();
+        forged.caveats.pop();
+        assert!(verify(&key, &store, &forged.encode(), &read("/wallet/balance")).is_err());
+
+        store.write_scroll(Scroll::new("/peers/650073047435", json!({"generation": 1})).set_type(paths::TYPE)).unwrap();
+        assert!(verify(&key, &store, &token, &read("/wallet/balance")).unwrap_err().to_string().contains("revoked"));
+    }
+
+    #[test]
+    fn test_spend_limit_allows_only_sends() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-peers").with_root(dir.path()).open_store().unwrap();
+        store.write_scroll(Scroll::new("/peers/650073047435", json!({"generation": 0})).set_type(paths::TYPE)).unwrap();
+        let key = [9u8; 32];
+        let token = issue(&key, "650073047435", 0, caveats(&json!({"prefixes": ["/"], "verbs": ["write"], "spend_limit_sat": 50000})).unwrap());
+        let write = |path, data: &Value| verify(&key, &store, &token, &Request { verb: Verb::Write, path, data: Some(data) });
+
+        let small = json!({"to": "bc1q", "amount_sat": 1000});
+        assert!(write("/wallet/send", &small).is_ok());
+        assert!(write("/wallet/savings/send", &small).is_ok());
+        assert!(write("/external/bitcoin/send/1", &small).is_ok());
+        assert!(write("/notes/today", &small).is_ok());
+        assert!(write("/external/bitcoin/send/1", &json!({"to": "bc1q", "amount_sat": 60000})).is_err());
+
+        // Paths that move funds without being a send stay closed whatever they carry
+        let pay = json!({"pubkey": "a".repeat(64), "amount_sat": 1});
+        assert!(write("/external/nostr/pay/1", &pay).unwrap_err().to_string().contains("only sends"));
+        assert!(write("/wallet/account", &json!({"account": 1})).unwrap_err().to_string().contains("only sends"));
+        assert!(write("/wallet/sweep", &small).is_err());
+        assert!(write("/wallet/advice/consolidation/approve", &json!({})).is_err());
+        assert!(write("/sys/recurring/rent", &small).is_err());
+        assert!(write("/sys/effects/requeue", &json!({})).is_err());
+        assert!(write("/sys/mind/patterns/drain", &json!({})).is_err());
+        assert!(write("/external/bitcoin/sendall/1", &small).is_err());
+    }
+}
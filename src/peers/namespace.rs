@@ -0,0 +1,99 @@
+//! PeersNamespace - `/peers`: paired nodes and the tokens they hold
+//!
+//! ## Paths
+//!
+//! | Path | R/W | Description |
+//! |------|-----|-------------|
+//! | `/peers/{mobi}` | R | Pairing record (written by `/nostr/pair`) |
+//! | `/peers/{mobi}/token` | W | `{prefixes, deny?, verbs?, spend_limit_sat?, ttl_secs?}` → `{token, id, caveats}` |
+//! | `/peers/{mobi}/revoke` | W | Invalidate every token issued to the peer so far |
+//! | `/peers/{mobi}/credential` | W | `{token, url?}` the peer issued us, for requests to it |
+//! | `/peers/{mobi}/tokens/{nonce}` | R | Issued tokens' `{id, caveats, issued_at}` |
+//! | `/peers/{mobi}/presence` | R | Last heartbeat `{tick, version, ip_hint, at, seen_at, online}` |
+//! | `/peers/{mobi}/external/**` | W | Effect run on the peer; result at `.../result` (`nostr::peers`) |
+
+use crate::core::paths::peers as paths;
+use crate::core::time::now_secs;
+use crate::secret::Secret;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+pub struct PeersNamespace {
+    key: Secret<[u8; 32]>,
+    store: Arc<Store>,
+}
+
+impl PeersNamespace {
+    /// `key` signs tokens (`Identity::token_key`)
+    pub fn new(key: [u8; 32], store: Arc<Store>) -> Self {
+        Self { key: Secret::new(key), store }
+    }
+
+    fn record(&self, mobi: &str) -> NineSResult<Scroll> {
+        self.store.read(&format!("{}/{}", paths::PREFIX, mobi))?
+            .ok_or_else(|| NineSError::Other(format!("unknown peer: {}", mobi)))
+    }
+
+    fn write_token(&self, mobi: &str, data: Value) -> NineSResult<Scroll> {
+        let record = self.record(mobi)?;
+        let caveats = super::caveats(&data)?;
+        let token = super::issue(self.key.expose(), mobi, record.data["generation"].as_u64().unwrap_or(0), caveats.clone());
+        let id = super::Token::parse(&token)?.id;
+        let nonce = id.rsplit(':').next().unwrap_or_default().to_string();
+        self.store.write_scroll(Scroll::new(
+            &format!("{}/{}{}/{}", paths::PREFIX, mobi, paths::TOKENS, nonce),
+            json!({"id": id, "caveats": caveats, "issued_at": now_secs()}),
+        ).set_type(paths::TOKEN_TYPE))?;
+        Ok(Scroll::new(&format!("{}/{}{}", paths::PREFIX, mobi, paths::TOKEN), json!({"token": token, "id": id, "caveats": caveats}))
+            .set_type(paths::TOKEN_TYPE))
+    }
+
+    fn write_revoke(&self, mobi: &str) -> NineSResult<Scroll> {
+        let mut record = self.record(mobi)?;
+        record.data["generation"] = json!(record.data["generation"].as_u64().unwrap_or(0) + 1);
+        record.data["revoked_at"] = json!(now_secs());
+        self.store.write_scroll(record)
+    }
+
+    fn write_credential(&self, mobi: &str, data: Value) -> NineSResult<Scroll> {
+        let token = data["token"].as_str().ok_or_else(|| NineSError::Other("no 'token'".into()))?;
+        super::Token::parse(token)?;
+        let mut record = self.record(mobi)?;
+        record.data["token"] = json!(token);
+        if let Some(url) = data["url"].as_str() { record.data["url"] = json!(url); }
+        self.store.write_scroll(record)
+    }
+
+    /// Stored as is; `nostr::peers::watch` forwards it to the peer
+    fn write_effect(&self, mobi: &str, op: &str, data: Value) -> NineSResult<Scroll> {
+        self.record(mobi)?;
+        self.store.write_scroll(Scroll::new(&format!("{}/{}{}", paths::PREFIX, mobi, op), data))
+    }
+}
+
+impl Namespace for PeersNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        let scroll = self.store.read(&format!("{}{}", paths::PREFIX, path))?;
+        if !path.ends_with(paths::PRESENCE) { return Ok(scroll); }
+        Ok(scroll.map(|mut s| { s.data = super::presence(s.data.take()); s }))
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        let (mobi, op) = path.trim_start_matches('/').split_once('/').map(|(m, op)| (m, format!("/{}", op))).unwrap_or_default();
+        match op.as_str() {
+            paths::TOKEN => self.write_token(mobi, data),
+            paths::REVOKE => self.write_revoke(mobi),
+            paths::CREDENTIAL => self.write_credential(mobi, data),
+            op if op.starts_with(&format!("{}/", paths::EXTERNAL)) => self.write_effect(mobi, op, data),
+            _ => Err(NineSError::Other(format!("/peers{} is read-only (pair with /nostr/pair)", path))),
+        }
+    }
+
+    fn list(&self, prefix: &str) -> NineSResult<Vec<String>> {
+        self.store.list(&format!("{}{}", paths::PREFIX, prefix.trim_end_matches('/')))
+    }
+
+    fn close(&self) -> NineSResult<()> { Ok(()) }
+}
@@ -0,0 +1,403 @@
+//! Plugins - sandboxed WASM effect handlers and pattern transforms
+//!
+//! A plugin is a scroll at `/sys/plugins/{name}`:
+//!
+//! ```json
+//! {
+//!   "kind": "effect",             // or "pattern"
+//!   "service": "ocr",             // effect: handles /external/ocr/**
+//!   "watches": "/notes/{id}",     // pattern: a watch pattern (core::watch)
+//!   "grants": {"read": ["/notes"], "write": ["/notes/ocr"]},
+//!   "wasm": "<base64 module>",
+//!   "fuel": 50000000              // optional instruction budget per call
+//! }
+//! ```
+//!
+//! The module gets no WASI and no imports beyond two host calls, both gated
+//! by `grants` on segment boundaries:
+//!
+//! | Import | Signature | Returns |
+//! |--------|-----------|---------|
+//! | `beenode.read` | `(path_ptr, path_len) -> i64` | packed JSON scroll, 0 if absent, -1 if not granted |
+//! | `beenode.write` | `({key, type?, data}_ptr, len) -> i32` | 0, -1 if not granted, -2 on error |
+//!
+//! It exports `memory`, `alloc(len) -> ptr` and `run(ptr, len) -> i64`.
+//! Packed values are `ptr << 32 | len` in the module's memory. `run` gets
+//! JSON and returns JSON:
+//!
+//! - effect: gets `{key, type, data}` of the effect, returns the result
+//!   (`EffectWorker` writes it to `/result` as for any handler)
+//! - pattern: gets `{scroll, captures}` for each matching write, returns an
+//!   array of `{key, type?, data}` to write; keys outside `write` are dropped
+//!
+//! A pattern plugin's `watches` must fall under its `read` grants. No plugin
+//! may write under `/sys/plugins` or `/sys/plugin-approvals`. Each call runs
+//! in a fresh instance with its own fuel and a 16 MiB memory cap, so a plugin
+//! can't keep state between calls or spin forever.
+//!
+//! Writing a manifest doesn't grant anything. A plugin only loads once
+//! `Node::approve_plugin` (which checks the PIN) has recorded the digest of
+//! its manifest at `/sys/plugin-approvals/{name}`; node writes can't reach
+//! that prefix, and any later edit to the manifest needs a new approval.
+//! Pattern plugins load when the node starts, effect plugins when
+//! `start_services` runs with `NodeConfig::with_plugins`.
+
+use crate::core::paths::{mind, plugins as paths};
+use crate::core::time::now_secs;
+use crate::core::watch::Watch;
+use crate::mind::{EffectHandler, EffectWorker};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use base64::Engine as _;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use wasmtime::{Caller, Engine, Linker, Module, StoreLimits, StoreLimitsBuilder};
+
+/// Fuel per call unless the manifest says otherwise
+pub const DEFAULT_FUEL: u64 = 50_000_000;
+/// Linear memory cap per instance
+pub const MAX_MEMORY: usize = 16 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind { Effect, Pattern }
+
+/// Paths a plugin may touch, as prefixes
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Grants {
+    #[serde(default)]
+    pub read: Vec<String>,
+    #[serde(default)]
+    pub write: Vec<String>,
+}
+
+impl Grants {
+    pub fn reads(&self, path: &str) -> bool { covered(&self.read, path) }
+    pub fn writes(&self, path: &str) -> bool {
+        !covered(&[paths::PREFIX.to_string(), paths::APPROVALS.to_string()], path) && covered(&self.write, path)
+    }
+}
+
+fn covered(prefixes: &[String], path: &str) -> bool {
+    prefixes.iter().any(|p| {
+        let p = p.trim_end_matches("/**").trim_end_matches('/');
+        p.is_empty() || path.strip_prefix(p).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// A plugin scroll's data
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub kind: PluginKind,
+    #[serde(default)]
+    pub service: Option<String>,
+    #[serde(default)]
+    pub watches: Option<String>,
+    #[serde(default)]
+    pub grants: Grants,
+    pub wasm: String,
+    #[serde(default)]
+    pub fuel: Option<u64>,
+}
+
+/// A compiled plugin
+#[derive(Clone)]
+pub struct Plugin {
+    pub name: String,
+    pub manifest: Manifest,
+    engine: Engine,
+    module: Module,
+}
+
+struct Host {
+    store: Store,
+    grants: Grants,
+    origin: String,
+    limits: StoreLimits,
+}
+
+impl Plugin {
+    /// Compile `bytes` (a WASM binary) for `manifest`
+    pub fn compile(engine: &Engine, name: &str, manifest: Manifest, bytes: &[u8]) -> Result<Self> {
+        match manifest.kind {
+            PluginKind::Effect if manifest.service.as_deref().map_or(true, |s| s.trim_matches('/').is_empty()) => {
+                return Err(anyhow!("plugin {}: effect plugins need a service", name));
+            }
+            PluginKind::Pattern => {
+                let watches = manifest.watches.as_deref().ok_or_else(|| anyhow!("plugin {}: pattern plugins need watches", name))?;
+                let watch = Watch::parse(watches)?;
+                if !manifest.grants.reads(&literal_prefix(&watch.glob())) {
+                    return Err(anyhow!("plugin {}: watches {} is outside its read grants", name, watches));
+                }
+            }
+            _ => {}
+        }
+        let module = Module::new(engine, bytes).with_context(|| format!("plugin {}", name))?;
+        Ok(Self { name: name.into(), manifest, engine: engine.clone(), module })
+    }
+
+    /// Origin marker on everything this plugin writes
+    pub fn origin(&self) -> String { format!("plugin:{}", self.name) }
+
+    /// Run the module once on `input`
+    pub fn call(&self, store: &Store, input: &Value) -> Result<Value> {
+        let host = Host {
+            store: store.clone(),
+            grants: self.manifest.grants.clone(),
+            origin: self.origin(),
+            limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+        };
+        let mut wasm = wasmtime::Store::new(&self.engine, host);
+        wasm.limiter(|h| &mut h.limits);
+        wasm.set_fuel(self.manifest.fuel.unwrap_or(DEFAULT_FUEL))?;
+        let instance = linker(&self.engine)?.instantiate(&mut wasm, &self.module)?;
+        let memory = instance.get_memory(&mut wasm, "memory").ok_or_else(|| anyhow!("plugin {}: no memory export", self.name))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut wasm, "alloc")?;
+        let run = instance.get_typed_func::<(i32, i32), i64>(&mut wasm, "run")?;
+
+        let bytes = serde_json::to_vec(input)?;
+        let ptr = alloc.call(&mut wasm, bytes.len() as i32)?;
+        memory.write(&mut wasm, ptr as usize, &bytes)?;
+        let (out_ptr, out_len) = unpack(run.call(&mut wasm, (ptr, bytes.len() as i32))?);
+        let out = memory.data(&wasm).get(out_ptr..out_ptr + out_len).ok_or_else(|| anyhow!("plugin {}: output out of bounds", self.name))?;
+        serde_json::from_slice(out).with_context(|| format!("plugin {}: output is not JSON", self.name))
+    }
+}
+
+fn linker(engine: &Engine) -> Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap("beenode", "read", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<i64> {
+        let path = String::from_utf8(guest_bytes(&mut caller, ptr, len)?)?;
+        if !caller.data().grants.reads(&path) { return Ok(-1); }
+        let Some(scroll) = caller.data().store.read(&path)? else { return Ok(0) };
+        let bytes = serde_json::to_vec(&json!({"key": scroll.key, "type": scroll.type_, "data": scroll.data}))?;
+        give(&mut caller, &bytes)
+    })?;
+    linker.func_wrap("beenode", "write", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<i32> {
+        let Ok(scroll) = output(&guest_bytes(&mut caller, ptr, len)?, &caller.data().origin) else { return Ok(-2) };
+        if !caller.data().grants.writes(&scroll.key) { return Ok(-1); }
+        Ok(if caller.data().store.write_scroll(scroll).is_ok() { 0 } else { -2 })
+    })?;
+    Ok(linker)
+}
+
+fn guest_bytes(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let memory = caller.get_export("memory").and_then(|e| e.into_memory()).ok_or_else(|| anyhow!("no memory export"))?;
+    let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+    memory.data(&caller).get(ptr..ptr + len).map(<[u8]>::to_vec).ok_or_else(|| anyhow!("guest pointer out of bounds"))
+}
+
+/// Copy `bytes` into the guest, packed
+fn give(caller: &mut Caller<'_, Host>, bytes: &[u8]) -> Result<i64> {
+    let alloc = caller.get_export("alloc").and_then(|e| e.into_func()).ok_or_else(|| anyhow!("no alloc export"))?;
+    let ptr = alloc.typed::<i32, i32>(&caller)?.call(&mut *caller, bytes.len() as i32)?;
+    let memory = caller.get_export("memory").and_then(|e| e.into_memory()).ok_or_else(|| anyhow!("no memory export"))?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(((ptr as u32 as i64) << 32) | bytes.len() as i64)
+}
+
+fn unpack(packed: i64) -> (usize, usize) { ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize) }
+
+/// `{key, type?, data}` as a scroll produced by `origin`
+fn output(bytes: &[u8], origin: &str) -> Result<Scroll> {
+    let value: Value = serde_json::from_slice(bytes)?;
+    scroll_from(&value, origin)
+}
+
+fn scroll_from(value: &Value, origin: &str) -> Result<Scroll> {
+    let key = value["key"].as_str().filter(|k| k.starts_with('/')).ok_or_else(|| anyhow!("output without a key"))?;
+    let mut scroll = Scroll::new(key, value.get("data").cloned().unwrap_or(Value::Null));
+    if let Some(t) = value["type"].as_str() { scroll = scroll.set_type(t); }
+    scroll.metadata = Metadata::default().with_produced_by(origin);
+    Ok(scroll)
+}
+
+/// The path before the first wildcard of a glob
+fn literal_prefix(glob: &str) -> String {
+    let segs: Vec<&str> = glob.split('/').filter(|s| !s.is_empty()).take_while(|s| !s.contains('*')).collect();
+    format!("/{}", segs.join("/"))
+}
+
+/// What an approval covers: the manifest scroll as stored
+fn digest(manifest: &Scroll) -> String { hex::encode(crate::identity::provenance::hash(manifest)) }
+
+/// Record approval of the manifest now at `/sys/plugins/{name}`. The caller
+/// has checked the PIN (`Node::approve_plugin`).
+pub(crate) fn approve(store: &Store, name: &str) -> Result<Scroll> {
+    let manifest = store.read(&format!("{}/{}", paths::PREFIX, name))?.ok_or_else(|| anyhow!("no plugin {}", name))?;
+    let approval = json!({"digest": digest(&manifest), "approved_at": now_secs()});
+    Ok(store.write_scroll(Scroll::new(&format!("{}/{}", paths::APPROVALS, name), approval).set_type(paths::APPROVAL_TYPE))?)
+}
+
+fn approved(store: &Store, name: &str, manifest: &Scroll) -> Result<bool> {
+    let approval = store.read(&format!("{}/{}", paths::APPROVALS, name))?;
+    Ok(approval.is_some_and(|a| a.data["digest"].as_str() == Some(digest(manifest).as_str())))
+}
+
+/// Compile every approved plugin under `/sys/plugins`; an unapproved or
+/// broken one is logged and skipped
+pub fn load(store: &Store) -> Result<Vec<Plugin>> {
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config)?;
+    let mut plugins = Vec::new();
+    for path in store.list(paths::PREFIX)? {
+        let Some(scroll) = store.read(&path)? else { continue };
+        let name = path.trim_start_matches(paths::PREFIX).trim_start_matches('/').to_string();
+        if !approved(store, &name, &scroll)? {
+            tracing::warn!("plugin {}: not approved", name);
+            continue;
+        }
+        let compiled = serde_json::from_value::<Manifest>(scroll.data).map_err(anyhow::Error::from)
+            .and_then(|m| {
+                let bytes = base64::engine::general_purpose::STANDARD.decode(&m.wasm)?;
+                Plugin::compile(&engine, &name, m, &bytes)
+            });
+        match compiled {
+            Ok(plugin) => plugins.push(plugin),
+            Err(e) => tracing::warn!("plugin {}: {}", name, e),
+        }
+    }
+    Ok(plugins)
+}
+
+/// Run each pattern plugin on the writes its `watches` matches
+pub fn start(store: Store) -> Result<()> {
+    for plugin in load(&store)?.into_iter().filter(|p| p.manifest.kind == PluginKind::Pattern) {
+        let watch = Watch::parse(plugin.manifest.watches.as_deref().unwrap_or_default())?;
+        let rx = store.watch(&WatchPattern::parse(&watch.glob())?)?;
+        let store = store.clone();
+        std::thread::spawn(move || {
+            let origin = plugin.origin();
+            while let Ok(scroll) = rx.recv() {
+                if scroll.metadata.produced_by.as_deref() == Some(origin.as_str()) { continue; }
+                let Some(event) = watch.event(scroll) else { continue };
+                let input = json!({
+                    "scroll": {"key": event.scroll.key, "type": event.scroll.type_, "data": event.scroll.data},
+                    "captures": event.captures,
+                });
+                let outputs = match plugin.call(&store, &input) {
+                    Ok(Value::Array(outputs)) => outputs,
+                    Ok(_) => { tracing::warn!("plugin {}: expected an array of scrolls", plugin.name); continue }
+                    Err(e) => { tracing::warn!("plugin {} on {}: {}", plugin.name, event.scroll.key, e); continue }
+                };
+                for out in outputs {
+                    match scroll_from(&out, &origin) {
+                        Ok(s) if plugin.manifest.grants.writes(&s.key) => { let _ = store.write_scroll(s); }
+                        Ok(s) => tracing::warn!("plugin {}: write to {} not granted", plugin.name, s.key),
+                        Err(e) => tracing::warn!("plugin {}: {}", plugin.name, e),
+                    }
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// An effect plugin as an `EffectHandler`
+struct PluginEffect {
+    watches: String,
+    plugin: Arc<Plugin>,
+    store: Store,
+}
+
+#[async_trait]
+impl EffectHandler for PluginEffect {
+    fn watches(&self) -> &str { &self.watches }
+    async fn execute(&self, scroll: &Scroll) -> Result<Value> {
+        let (plugin, store) = (self.plugin.clone(), self.store.clone());
+        let input = json!({"key": scroll.key, "type": scroll.type_, "data": scroll.data});
+        tokio::task::spawn_blocking(move || plugin.call(&store, &input)).await?
+    }
+}
+
+/// `(service, handler)` for each approved effect plugin under `/sys/plugins`
+pub fn handlers(store: &Store) -> Result<Vec<(String, Box<dyn EffectHandler>)>> {
+    let mut handlers: Vec<(String, Box<dyn EffectHandler>)> = Vec::new();
+    for plugin in load(store)?.into_iter().filter(|p| p.manifest.kind == PluginKind::Effect) {
+        let service = plugin.manifest.service.clone().unwrap_or_default();
+        let watches = format!("{}/{}", mind::EXTERNAL_PREFIX, service.trim_matches('/'));
+        handlers.push((service, Box::new(PluginEffect { watches, plugin: Arc::new(plugin), store: store.clone() })));
+    }
+    Ok(handlers)
+}
+
+/// `worker` with a handler for each approved effect plugin
+pub fn register(mut worker: EffectWorker, store: &Store) -> Result<EffectWorker> {
+    for (service, handler) in handlers(store)? {
+        worker = worker.with_handler(&service, handler);
+    }
+    Ok(worker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Writes its input through `beenode.write` and echoes it back
+    const ECHO: &str = r#"(module
+        (import "beenode" "write" (func $write (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "alloc") (param $n i32) (result i32)
+            (local $p i32)
+            (local.set $p (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $n)))
+            (local.get $p))
+        (func (export "run") (param $p i32) (param $n i32) (result i64)
+            (drop (call $write (local.get $p) (local.get $n)))
+            (i64.or (i64.shl (i64.extend_i32_u (local.get $p)) (i64.const 32)) (i64.extend_i32_u (local.get $n)))))"#;
+
+    #[test]
+    fn test_grants() {
+        let grants = Grants { read: vec!["/notes/**".into()], write: vec!["/out".into(), "/sys".into()] };
+        assert!(grants.reads("/notes/1"));
+        assert!(!grants.reads("/notesx/1"));
+        assert!(grants.writes("/out/a"));
+        assert!(grants.writes("/sys/alerts/x"));
+        assert!(!grants.writes("/sys/plugins/evil"));
+        assert!(!grants.writes("/sys/plugin-approvals/evil"));
+        assert_eq!(literal_prefix("/notes/*/x"), "/notes");
+    }
+
+    #[test]
+    fn test_call_respects_write_grants() {
+        let dir = TempDir::new().expect("tempdir");
+        let store = crate::node::NodeConfig::new("test-plugins").with_root(dir.path()).open_store().unwrap();
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).unwrap();
+        let manifest: Manifest = serde_json::from_value(json!({
+            "kind": "effect", "service": "echo", "grants": {"write": ["/out"]}, "wasm": "",
+        })).unwrap();
+        let plugin = Plugin::compile(&engine, "echo", manifest, ECHO.as_bytes()).unwrap();
+
+        let echoed = plugin.call(&store, &json!({"key": "/out/1", "data": {"n": 1}})).unwrap();
+        assert_eq!(echoed["data"]["n"], 1);
+        let written = store.read("/out/1").unwrap().unwrap();
+        assert_eq!(written.metadata.produced_by.as_deref(), Some("plugin:echo"));
+
+        plugin.call(&store, &json!({"key": "/notes/1", "data": {}})).unwrap();
+        assert!(store.read("/notes/1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_load_needs_approval() {
+        let dir = TempDir::new().expect("tempdir");
+        let store = crate::node::NodeConfig::new("test-plugin-load").with_root(dir.path()).open_store().unwrap();
+        let wasm = base64::engine::general_purpose::STANDARD.encode(ECHO);
+        store.write("/sys/plugins/echo", json!({"kind": "effect", "service": "echo", "grants": {"write": ["/out"]}, "wasm": wasm})).unwrap();
+        assert!(load(&store).unwrap().is_empty());
+
+        approve(&store, "echo").unwrap();
+        assert_eq!(load(&store).unwrap().len(), 1);
+
+        // Widening the grants after approval revokes it
+        store.write("/sys/plugins/echo", json!({"kind": "effect", "service": "echo", "grants": {"write": ["/"]}, "wasm": wasm})).unwrap();
+        assert!(load(&store).unwrap().is_empty());
+    }
+}
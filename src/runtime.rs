@@ -1,6 +1,17 @@
-//! Runtime - Graceful shutdown and signal handling
+//! Runtime - Graceful shutdown, signal handling and crash reports
+//!
+//! Background tasks that panic are restarted by their owner (service
+//! supervisor, effect worker, relay pool) and leave a report at
+//! `/sys/crashes/{ms}-{task}`: `{task, message, location?, thread?,
+//! backtrace?, at}`. Location and backtrace come from the panic hook
+//! (`install_panic_hook`); the backtrace only with `RUST_BACKTRACE` set.
 
-use std::sync::Arc;
+use crate::core::paths::crashes as crash_paths;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
 use tokio::sync::{broadcast, RwLock};
 
 /// Shutdown signal broadcaster
@@ -69,3 +80,183 @@ pub fn install_signal_handlers() -> Shutdown {
 
     shutdown
 }
+
+/// Install a SIGHUP handler; each signal yields one message (config reload)
+pub fn install_reload_handler() -> tokio::sync::mpsc::UnboundedReceiver<()> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sighup = signal(SignalKind::hangup()).expect("SIGHUP handler");
+        while sighup.recv().await.is_some() {
+            tracing::info!("Received SIGHUP");
+            if tx.send(()).is_err() { break; }
+        }
+    });
+
+    #[cfg(not(unix))]
+    drop(tx);
+
+    rx
+}
+
+/// systemd `sd_notify` (e.g. `READY=1`). No-op unless `NOTIFY_SOCKET` is set.
+pub fn sd_notify(state: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::net::UnixDatagram;
+        let Ok(path) = std::env::var("NOTIFY_SOCKET") else { return false };
+        let Ok(sock) = UnixDatagram::unbound() else { return false };
+        let sent = match path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+                    .and_then(|addr| sock.send_to_addr(state.as_bytes(), &addr))
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => return false,
+            None => sock.send_to(state.as_bytes(), &path),
+        };
+        sent.is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = state;
+        false
+    }
+}
+
+/// Pidfile removed on drop. Refuses to start if another live process holds it.
+pub struct Pidfile {
+    path: std::path::PathBuf,
+}
+
+impl Pidfile {
+    pub fn create(path: impl Into<std::path::PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Ok(existing) = std::fs::read_to_string(&path) {
+            let pid = existing.trim();
+            if !pid.is_empty() && std::path::Path::new(&format!("/proc/{}", pid)).exists() {
+                return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("already running (pid {})", pid)));
+            }
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, format!("{}\n", std::process::id()))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Longest wait between restarts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Panics remembered for the next `report_crash`
+const HELD_PANICS: usize = 16;
+
+struct PanicRecord { message: String, location: Option<String>, thread: Option<String>, backtrace: Option<String> }
+
+static PANICS: Mutex<VecDeque<PanicRecord>> = Mutex::new(VecDeque::new());
+static PANIC_HOOK: Once = Once::new();
+
+/// Remember where each panic happened (and its backtrace) for crash reports.
+/// Idempotent; the previous hook still runs.
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let backtrace = std::backtrace::Backtrace::capture();
+            let record = PanicRecord {
+                message: panic_message(info.payload()),
+                location: info.location().map(|l| l.to_string()),
+                thread: std::thread::current().name().map(String::from),
+                backtrace: (backtrace.status() == std::backtrace::BacktraceStatus::Captured).then(|| backtrace.to_string()),
+            };
+            if let Ok(mut panics) = PANICS.lock() {
+                if panics.len() >= HELD_PANICS { panics.pop_front(); }
+                panics.push_back(record);
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Text of a panic payload (`panic!("...")` gives `&str` or `String`)
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".into())
+}
+
+/// Write a crash report for `task`, with the hook's record of the panic if it has one
+pub fn report_crash(store: &Store, task: &str, message: &str) {
+    let record = PANICS.lock().ok().and_then(|mut panics| {
+        let i = panics.iter().rposition(|r| r.message == message)?;
+        panics.remove(i)
+    });
+    let ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    let mut data = serde_json::json!({"task": task, "message": message, "at": ms / 1000});
+    if let Some(record) = record {
+        data["location"] = serde_json::json!(record.location);
+        data["thread"] = serde_json::json!(record.thread);
+        data["backtrace"] = serde_json::json!(record.backtrace);
+    }
+    let slug: String = task.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect();
+    let mut key = format!("{}/{}-{}", crash_paths::PREFIX, ms, slug.trim_matches('-'));
+    // Two crashes in the same millisecond both keep their report
+    for later in ms + 1.. {
+        if !matches!(store.read(&key), Ok(Some(_))) { break; }
+        key = format!("{}/{}-{}", crash_paths::PREFIX, later, slug.trim_matches('-'));
+    }
+    tracing::error!("{} panicked: {}", task, message);
+    if let Err(e) = store.write_scroll(Scroll::new(&key, data).set_type(crash_paths::TYPE)) {
+        tracing::warn!("crash report {}: {}", key, e);
+    }
+}
+
+/// Wait before restart number `attempt` (0-based): `base` doubling, capped at a minute
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1 << attempt.min(16)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pidfile_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/beenode.pid");
+        let pidfile = Pidfile::create(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), std::process::id().to_string());
+        #[cfg(target_os = "linux")]
+        assert!(Pidfile::create(&path).is_err());
+        drop(pidfile);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_crash_report_carries_location() {
+        install_panic_hook();
+        let dir = tempfile::tempdir().unwrap();
+        let store = crate::node::NodeConfig::new("test-crashes").with_root(dir.path()).open_store().unwrap();
+        let payload = std::thread::spawn(|| panic!("worker exploded")).join().unwrap_err();
+        let message = panic_message(&*payload);
+        report_crash(&store, "effects /external/demo", &message);
+
+        let key = store.list(crash_paths::PREFIX).unwrap().pop().unwrap();
+        assert!(key.ends_with("-effects--external-demo"));
+        let report = store.read(&key).unwrap().unwrap();
+        assert_eq!(report.data["message"], "worker exploded");
+        assert!(report.data["location"].as_str().unwrap().contains("runtime.rs"));
+        assert_eq!(backoff(Duration::from_secs(1), 3), Duration::from_secs(8));
+        assert_eq!(backoff(Duration::from_secs(1), 30), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,130 @@
+//! Search - in-memory full-text index over scroll data
+//!
+//! Indexes string and number values of scrolls under configured prefixes.
+//! Built at node startup and kept current by a watch on each prefix, so
+//! effect results and Mind reactions are indexed too. Query terms are ANDed;
+//! a trailing `*` matches by prefix (`e3b0*`).
+
+use nine_s_core::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// One search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub key: String,
+    pub type_: String,
+    pub score: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// term → key → occurrences
+    postings: HashMap<String, HashMap<String, u32>>,
+    /// key → (type, terms) for removal on update
+    docs: HashMap<String, (String, HashSet<String>)>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self { Self::default() }
+
+    /// Index (or re-index) a scroll
+    pub fn index(&mut self, scroll: &Scroll) {
+        self.remove(&scroll.key);
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        let mut push = |t: String| *counts.entry(t).or_default() += 1;
+        collect(&scroll.data, &mut push);
+        for term in tokenize(&scroll.key) { push(term); }
+
+        let terms: HashSet<String> = counts.keys().cloned().collect();
+        for (term, n) in counts {
+            self.postings.entry(term).or_default().insert(scroll.key.clone(), n);
+        }
+        self.docs.insert(scroll.key.clone(), (scroll.type_.clone(), terms));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some((_, terms)) = self.docs.remove(key) {
+            for term in terms {
+                if let Some(keys) = self.postings.get_mut(&term) {
+                    keys.remove(key);
+                    if keys.is_empty() { self.postings.remove(&term); }
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize { self.docs.len() }
+    pub fn is_empty(&self) -> bool { self.docs.is_empty() }
+
+    /// Keys matching every query term, best score first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut scores: Option<HashMap<&str, u32>> = None;
+        for raw in query.split_whitespace() {
+            let prefix = raw.ends_with('*');
+            let Some(term) = tokenize(raw.trim_end_matches('*')).into_iter().next() else { continue };
+            let mut matched: HashMap<&str, u32> = HashMap::new();
+            for (t, keys) in &self.postings {
+                if t == &term || (prefix && t.starts_with(&term)) {
+                    for (k, n) in keys { *matched.entry(k.as_str()).or_default() += n; }
+                }
+            }
+            scores = Some(match scores {
+                None => matched,
+                Some(prev) => prev.into_iter()
+                    .filter_map(|(k, s)| matched.get(k).map(|n| (k, s + n)))
+                    .collect(),
+            });
+        }
+        let mut hits: Vec<SearchHit> = scores.unwrap_or_default().into_iter()
+            .map(|(key, score)| SearchHit {
+                key: key.to_string(),
+                type_: self.docs.get(key).map(|(t, _)| t.clone()).unwrap_or_default(),
+                score,
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.key.cmp(&b.key)));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+fn collect(value: &Value, push: &mut impl FnMut(String)) {
+    match value {
+        Value::String(s) => tokenize(s).into_iter().for_each(push),
+        Value::Number(n) => push(n.to_string()),
+        Value::Array(items) => items.iter().for_each(|v| collect(v, push)),
+        Value::Object(map) => map.values().for_each(|v| collect(v, push)),
+        _ => {}
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() >= 2)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_index_and_query() {
+        let mut idx = SearchIndex::new();
+        idx.index(&Scroll::new("/notes/1", json!({"title": "Paid Alice", "txid": "e3b0c442"})));
+        idx.index(&Scroll::new("/notes/2", json!({"title": "Lunch with Alice"})));
+
+        assert_eq!(idx.search("alice", 10).len(), 2);
+        assert_eq!(idx.search("alice paid", 10)[0].key, "/notes/1");
+        assert_eq!(idx.search("e3b0*", 10)[0].key, "/notes/1");
+        assert!(idx.search("bob", 10).is_empty());
+
+        idx.index(&Scroll::new("/notes/1", json!({"title": "Paid Bob"})));
+        assert_eq!(idx.search("alice", 10).len(), 1);
+        assert_eq!(idx.len(), 2);
+    }
+}
@@ -0,0 +1,64 @@
+//! Secret - Zeroize-on-drop wrapper for mnemonics, seeds and keys
+//!
+//! ```ignore
+//! let seed: Secret<[u8; 64]> = Secret::new(mnemonic.to_seed(""));
+//! derive(seed.expose());
+//! println!("{:?}", seed);   // Secret([REDACTED])
+//! ```
+//!
+//! The value is wiped when the wrapper drops and never shows up in `Debug`
+//! output. Access is explicit through `expose`, so plaintext use is greppable.
+
+use std::fmt;
+use zeroize::Zeroize;
+
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self { Self(value) }
+
+    /// Borrow the plaintext
+    pub fn expose(&self) -> &T { &self.0 }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) { self.0.zeroize(); }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Secret([REDACTED])") }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self { Self(value) }
+}
+
+/// `len` bytes from the OS random source, hex-encoded (pairing secrets, token nonces)
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).expect("OS random source");
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_debug() {
+        let s = Secret::new(String::from("abandon about"));
+        assert_eq!(format!("{:?}", s), "Secret([REDACTED])");
+        assert_eq!(s.expose(), "abandon about");
+    }
+
+    #[test]
+    fn test_random_hex() {
+        let (a, b) = (random_hex(16), random_hex(16));
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,69 @@
+//! Clock feed - one watch on `/sys/clock/**` shared by a router
+//!
+//! `X-Beenode-Tick` reads the latest tick from an atomic and every `/clock`
+//! client subscribes to one broadcast channel, so neither a response nor an
+//! SSE connection reads the store or holds a thread of its own. The feed
+//! starts on first use and again after its watch ends (a locked node may
+//! refuse it until unlocked).
+
+use crate::core::api::NodeApi;
+use crate::core::paths::clock;
+use nine_s_core::prelude::*;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Events a slow `/clock` client may fall behind before it skips ahead
+const BACKLOG: usize = 256;
+/// No tick seen yet
+const NO_TICK: u64 = u64::MAX;
+
+/// Latest tick and a broadcast of clock writes as `(key, data)`
+pub(super) struct ClockFeed {
+    running: AtomicBool,
+    tick: AtomicU64,
+    latest: Mutex<Option<Value>>,
+    events: broadcast::Sender<(String, Value)>,
+}
+
+impl ClockFeed {
+    pub fn new() -> Self {
+        Self { running: AtomicBool::new(false), tick: AtomicU64::new(NO_TICK), latest: Mutex::new(None), events: broadcast::channel(BACKLOG).0 }
+    }
+
+    /// Watch `node`'s clock unless the feed already does
+    pub fn start(self: &Arc<Self>, node: &Arc<dyn NodeApi>) -> NineSResult<()> {
+        if self.running.swap(true, Ordering::SeqCst) { return Ok(()); }
+        let events = match node.on(&format!("{}/**", clock::PREFIX)) {
+            Ok(events) => events,
+            Err(e) => { self.running.store(false, Ordering::SeqCst); return Err(e); }
+        };
+        if let Some(tick) = node.get(clock::TICK).ok().flatten() { self.record(&tick.data); }
+        let feed = self.clone();
+        std::thread::spawn(move || {
+            while let Ok(scroll) = events.recv() {
+                if scroll.key == clock::TICK { feed.record(&scroll.data); }
+                let _ = feed.events.send((scroll.key, scroll.data));
+            }
+            feed.running.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    pub fn tick(&self) -> Option<u64> {
+        Some(self.tick.load(Ordering::SeqCst)).filter(|t| *t != NO_TICK)
+    }
+
+    /// `/sys/clock/tick` data as last seen
+    pub fn latest(&self) -> Option<Value> {
+        self.latest.lock().ok()?.clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(String, Value)> { self.events.subscribe() }
+
+    fn record(&self, data: &Value) {
+        if let Some(tick) = data["tick"].as_u64() { self.tick.store(tick, Ordering::SeqCst); }
+        if let Ok(mut latest) = self.latest.lock() { *latest = Some(data.clone()); }
+    }
+}
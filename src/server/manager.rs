@@ -0,0 +1,104 @@
+//! NodeManager - many named nodes in one process
+//!
+//! Each hosted node keeps its own app store, mnemonic and auth. Requests are
+//! routed by `/n/{app}/...`; a node registered with a token only answers
+//! requests carrying `Authorization: Bearer {token}`. Each node's router
+//! state (and its clock feed) is built once, when it is hosted.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::routes::NodeState;
+use crate::Node;
+
+struct Hosted {
+    node: Arc<Node>,
+    state: NodeState,
+    token: Option<String>,
+}
+
+/// Why a request could not be routed to a node
+#[derive(Debug, PartialEq, Eq)]
+pub enum ManagerError {
+    UnknownApp,
+    Unauthorized,
+}
+
+#[derive(Default)]
+pub struct NodeManager {
+    nodes: RwLock<HashMap<String, Hosted>>,
+    cors_origins: Vec<String>,
+}
+
+impl NodeManager {
+    pub fn new() -> Self { Self::default() }
+
+    /// Allow a browser origin on every hosted node (none allows any origin)
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self { self.cors_origins.push(origin.into()); self }
+
+    pub(super) fn cors_origins(&self) -> &[String] { &self.cors_origins }
+
+    /// Host `node` under `app`, replacing any node already there
+    pub fn host(&self, app: impl Into<String>, node: Arc<Node>, token: Option<String>) {
+        let app = app.into();
+        let state = NodeState::new(node.clone(), app.clone());
+        if let Ok(mut nodes) = self.nodes.write() {
+            nodes.insert(app, Hosted { node, state, token: token.filter(|t| !t.is_empty()) });
+        }
+    }
+
+    /// Stop hosting `app`. Returns the node so the caller can close it.
+    pub fn remove(&self, app: &str) -> Option<Arc<Node>> {
+        self.nodes.write().ok()?.remove(app).map(|h| h.node)
+    }
+
+    /// Hosted app names, sorted
+    pub fn apps(&self) -> Vec<String> {
+        let mut apps: Vec<String> = self.nodes.read().map(|n| n.keys().cloned().collect()).unwrap_or_default();
+        apps.sort();
+        apps
+    }
+
+    /// Resolve `app`, checking its token against the presented bearer token
+    pub fn authorize(&self, app: &str, bearer: Option<&str>) -> Result<Arc<Node>, ManagerError> {
+        self.with_hosted(app, bearer, |h| h.node.clone())
+    }
+
+    /// `authorize`, returning the node's router state
+    pub(super) fn state(&self, app: &str, bearer: Option<&str>) -> Result<NodeState, ManagerError> {
+        self.with_hosted(app, bearer, |h| h.state.clone())
+    }
+
+    fn with_hosted<T>(&self, app: &str, bearer: Option<&str>, f: impl FnOnce(&Hosted) -> T) -> Result<T, ManagerError> {
+        let nodes = self.nodes.read().map_err(|_| ManagerError::UnknownApp)?;
+        let hosted = nodes.get(app).ok_or(ManagerError::UnknownApp)?;
+        match (&hosted.token, bearer) {
+            (None, _) => Ok(f(hosted)),
+            (Some(expected), Some(given)) if constant_time_eq(expected.as_bytes(), given.as_bytes()) => Ok(f(hosted)),
+            _ => Err(ManagerError::Unauthorized),
+        }
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_unknown_app() {
+        let manager = NodeManager::new();
+        assert_eq!(manager.authorize("nope", None).err(), Some(ManagerError::UnknownApp));
+        assert!(manager.apps().is_empty());
+    }
+}
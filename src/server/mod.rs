@@ -1,4 +1,10 @@
 //! HTTP routes for scroll I/O
 
+mod clock;
+mod manager;
+mod rest;
 mod routes;
-pub use routes::{create_router, create_router_with_name, create_router_with_node, AppState, NodeState};
+#[cfg(feature = "ui")]
+pub mod ui;
+pub use manager::{ManagerError, NodeManager};
+pub use routes::{create_router, create_router_with_manager, create_router_with_name, create_router_with_node, AppState, NodeState};
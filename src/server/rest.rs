@@ -0,0 +1,113 @@
+//! Convenience REST routes over wallet and nostr namespaces
+//!
+//! | Route | Maps to |
+//! |-------|---------|
+//! | `GET /wallet/balance` | read `/wallet/balance` |
+//! | `POST /wallet/send` | queue `/wallet/send`, wait for the effect `/result` |
+//! | `GET /nostr/profile?pubkey=` | read `/nostr/profile[/{pubkey}]` |
+//!
+//! Errors are JSON `{error, field?, hint?}`. A send whose effect has not
+//! finished within `timeout_ms` returns 202 with the `result` path to poll.
+
+use axum::{extract::{Query, State}, http::StatusCode, routing::{get, post}, Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+use super::routes::NodeState;
+use crate::core::paths::{mind, wallet as wallet_paths};
+
+type ApiError = (StatusCode, Json<Value>);
+
+/// Default wait for an effect result
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+/// Upper bound on caller-supplied `timeout_ms`
+const MAX_TIMEOUT_MS: u64 = 120_000;
+
+pub(super) fn routes() -> Router<NodeState> {
+    Router::new()
+        .route("/wallet/balance", get(wallet_balance))
+        .route("/wallet/send", post(wallet_send))
+        .route("/nostr/profile", get(nostr_profile))
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(json!({"error": message.into()})))
+}
+
+fn invalid(field: &str, message: &str) -> ApiError {
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({"error": message, "field": field})))
+}
+
+/// Read a namespace path, mapping "not mounted" to 503 with a hint
+fn read(s: &NodeState, path: &str, feature: &str) -> Result<Value, ApiError> {
+    match s.node.get(path) {
+        Ok(Some(scroll)) => Ok(scroll.data),
+        Ok(None) => Err((StatusCode::SERVICE_UNAVAILABLE, Json(json!({
+            "error": format!("{} not available", feature),
+            "hint": format!("configure {} and unlock the node", feature),
+        })))),
+        Err(e) if e.to_string().contains("locked") => Err(error(StatusCode::LOCKED, e.to_string())),
+        Err(e) => Err(error(StatusCode::BAD_GATEWAY, e.to_string())),
+    }
+}
+
+async fn wallet_balance(State(s): State<NodeState>) -> Result<Json<Value>, ApiError> {
+    read(&s, "/wallet/balance", "wallet").map(Json)
+}
+
+#[derive(Deserialize)]
+struct SendRequest {
+    to: Option<String>,
+    amount_sat: Option<Value>,
+    fee_rate: Option<Value>,
+    timeout_ms: Option<u64>,
+}
+
+async fn wallet_send(State(s): State<NodeState>, Json(req): Json<SendRequest>) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let to = req.to.as_deref().map(str::trim).filter(|t| !t.is_empty())
+        .ok_or_else(|| invalid("to", "destination address required"))?;
+    let amount_sat = match req.amount_sat {
+        Some(v) => v.as_u64().filter(|a| *a > 0).ok_or_else(|| invalid("amount_sat", "amount_sat must be a positive integer"))?,
+        None => return Err(invalid("amount_sat", "amount_sat required")),
+    };
+    let fee_rate = match req.fee_rate {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(v.as_f64().filter(|f| *f > 0.0).ok_or_else(|| invalid("fee_rate", "fee_rate must be a positive number (sat/vB)"))?),
+    };
+
+    let queued = s.node.put(&format!("/wallet{}", wallet_paths::SEND), json!({"to": to, "amount_sat": amount_sat, "fee_rate": fee_rate, "now": false}))
+        .map_err(|e| error(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let id = queued.data["request_id"].as_str()
+        .ok_or_else(|| error(StatusCode::BAD_GATEWAY, "wallet did not queue the send"))?;
+    let result_path = format!("{}/{}{}", wallet_paths::EXTERNAL_SEND, id, mind::RESULT_SUFFIX);
+
+    let timeout = Duration::from_millis(req.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS));
+    match wait_for(&s, &result_path, timeout).await {
+        Some(result) if result["success"] == true => Ok((StatusCode::OK, Json(result["result"].clone()))),
+        Some(result) => Err(error(StatusCode::BAD_GATEWAY, result["error"].as_str().unwrap_or("send failed"))),
+        None => Ok((StatusCode::ACCEPTED, Json(json!({"status": "pending", "request_id": id, "result": result_path})))),
+    }
+}
+
+/// Poll `path` until it exists or `timeout` elapses
+async fn wait_for(s: &NodeState, path: &str, timeout: Duration) -> Option<Value> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(Some(scroll)) = s.node.get(path) { return Some(scroll.data); }
+        if Instant::now() >= deadline { return None; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileQuery { pubkey: Option<String> }
+
+async fn nostr_profile(State(s): State<NodeState>, Query(q): Query<ProfileQuery>) -> Result<Json<Value>, ApiError> {
+    let path = match q.pubkey.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(pk) if pk.len() == 64 && pk.chars().all(|c| c.is_ascii_hexdigit()) => format!("/nostr/profile/{}", pk),
+        Some(_) => return Err(invalid("pubkey", "pubkey must be 64 hex characters")),
+        None => "/nostr/profile".into(),
+    };
+    read(&s, &path, "nostr").map(Json)
+}
@@ -1,15 +1,18 @@
 //! HTTP routes for scroll I/O
 
-use axum::{extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, routing::{get, post, put}, Json, Router};
+use axum::{extract::{Path, Query, Request, State}, http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode}, middleware::{self, Next}, response::{IntoResponse, Response}, routing::{get, post, put}, Json, Router};
 use nine_s_core::namespace::Namespace;
+use nine_s_core::prelude::Scroll;
 use nine_s_store::Store;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
-use crate::Node;
+use super::clock::ClockFeed;
+use super::manager::{constant_time_eq, ManagerError, NodeManager};
+use crate::core::api::NodeApi;
 
 // State for Store-based router (legacy)
 #[derive(Clone)]
@@ -21,25 +24,50 @@ impl AppState {
     }
 }
 
-// State for Node-based router (supports /wallet/* paths)
+// State for Node-based router (supports /wallet/* paths); any `NodeApi`
 #[derive(Clone)]
-pub struct NodeState { pub node: Arc<Node>, pub app_name: String }
+pub struct NodeState { pub node: Arc<dyn NodeApi>, pub app_name: String, clock: Arc<ClockFeed> }
 
 impl NodeState {
-    pub fn new(node: Arc<Node>, app_name: impl Into<String>) -> Self {
-        Self { node, app_name: app_name.into() }
+    pub fn new(node: Arc<dyn NodeApi>, app_name: impl Into<String>) -> Self {
+        Self { node, app_name: app_name.into(), clock: Arc::new(ClockFeed::new()) }
     }
 }
 
 #[derive(Deserialize)]
-pub struct ListQuery { #[serde(default = "default_prefix")] prefix: String }
+pub struct ListQuery {
+    #[serde(default = "default_prefix")] prefix: String,
+    /// Only paths whose scroll version is greater than this
+    since_version: Option<u64>,
+}
 fn default_prefix() -> String { "/".into() }
 
 #[derive(Serialize)]
 pub struct ListResponse { paths: Vec<String>, count: usize }
 
 #[derive(Serialize)]
-pub struct WriteResponse { key: String, version: u64 }
+pub struct WriteResponse {
+    key: String,
+    version: u64,
+    /// Effect result, when written with `?wait=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+}
+
+/// Options a namespace read understands, passed on as `?format=..&currency=..`
+/// (`/wallet/export/transactions`)
+#[derive(Deserialize)]
+pub struct ReadQuery {
+    format: Option<String>,
+    currency: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WriteQuery {
+    #[serde(default)] wait: bool,
+    #[serde(default = "default_wait_ms")] timeout_ms: u64,
+}
+fn default_wait_ms() -> u64 { 30_000 }
 
 pub fn create_router(store: Store) -> Router { create_router_with_name(store, "beenode") }
 
@@ -55,18 +83,115 @@ pub fn create_router_with_name(store: Store, app_name: &str) -> Router {
 }
 
 /// Create router with Node backend (supports /wallet/*, /nostr/*, etc.)
-pub fn create_router_with_node(node: Arc<Node>, app_name: &str) -> Router {
+pub fn create_router_with_node(node: Arc<dyn NodeApi>, app_name: &str) -> Router {
+    let http = node.http_config();
+    let state = NodeState::new(node, app_name);
     Router::new()
         .route("/health", get(node_health))
+        .route("/status", get(node_status))
+        .route("/capabilities", get(node_capabilities))
         .route("/scrolls", get(node_list_scrolls))
         .route("/scroll/*path", get(node_read_scroll))
         .route("/scroll/*path", post(node_write_scroll))
+        .route("/scrolls/batch", post(node_batch))
+        .route("/search", get(node_search))
+        .route("/views/:name", get(node_view))
+        .route("/replicate", get(node_replicate))
+        .route("/journal", get(node_journal))
+        .route("/tree", get(node_tree))
+        .route("/meta/*path", get(node_meta))
+        .route("/stats/types", get(node_type_stats))
+        .route("/watch", get(node_watch))
+        .route("/clock", get(node_clock))
         .route("/system/auth/status", get(node_auth_status))
         .route("/system/auth/unlock", put(node_auth_unlock))
         .route("/system/auth/lock", put(node_auth_lock))
-        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+        .merge(super::rest::routes())
+        .layer(middleware::from_fn_with_state(state.clone(), tick_header))
+        .layer(middleware::from_fn_with_state(TokenState { token: http.token.clone(), node: state.node.clone() }, require_token))
+        .layer(cors(&http.cors_origins))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+/// Any origin when none are configured; otherwise only the listed ones.
+/// Preflight (`OPTIONS`) is answered here, before token auth runs.
+fn cors(origins: &[String]) -> CorsLayer {
+    let layer = CorsLayer::new().expose_headers([header::ETAG, HeaderName::from_static(TICK_HEADER)]).max_age(std::time::Duration::from_secs(3600));
+    if origins.is_empty() {
+        return layer.allow_origin(Any).allow_methods(Any).allow_headers(Any);
+    }
+    let origins: Vec<HeaderValue> = origins.iter().filter_map(|o| o.parse().ok()).collect();
+    layer
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::OPTIONS])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::IF_NONE_MATCH])
+}
+
+#[derive(Clone)]
+struct TokenState { token: Option<String>, node: Arc<dyn NodeApi> }
+
+/// Bearer token check (no cookies, so browser apps hold the token themselves).
+/// Peer tokens (`bpt1.`, see `peers`) are checked against the scroll path
+/// they reach; routes without one are refused to them.
+async fn require_token(State(auth): State<TokenState>, req: Request, next: Next) -> Response {
+    let Some(expected) = auth.token.as_ref() else { return next.run(req).await };
+    if req.uri().path() == "/health" { return next.run(req).await; }
+    let given = req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    match given {
+        Some(given) if constant_time_eq(expected.as_bytes(), given.as_bytes()) => next.run(req).await,
+        Some(given) if given.starts_with(crate::peers::TOKEN_PREFIX) => peer_request(&auth.node, &given, req, next).await,
+        _ => (StatusCode::UNAUTHORIZED, "invalid or missing token").into_response(),
+    }
+}
+
+async fn peer_request(node: &Arc<dyn NodeApi>, token: &str, req: Request, next: Next) -> Response {
+    use crate::peers::{Request as PeerRequest, Verb};
+    let uri_path = req.uri().path().to_string();
+    // Caveats compare decoded paths; refuse rather than decode twice differently
+    if uri_path.contains('%') {
+        return (StatusCode::FORBIDDEN, "percent-encoded paths not available to peer tokens").into_response();
+    }
+    let path = if let Some(p) = uri_path.strip_prefix("/scroll/") {
+        format!("/{}", p)
+    } else if uri_path == "/scrolls" {
+        Query::<ListQuery>::try_from_uri(req.uri()).map(|q| q.0.prefix).unwrap_or_else(|_| default_prefix())
+    } else if uri_path.starts_with("/wallet/") || uri_path.starts_with("/nostr/") {
+        uri_path
+    } else {
+        return (StatusCode::FORBIDDEN, "route not available to peer tokens").into_response();
+    };
+    let verb = if req.method() == Method::GET { Verb::Read } else { Verb::Write };
+    let (parts, body) = req.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, PEER_BODY_MAX).await else {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "body too large").into_response();
+    };
+    let data: Option<Value> = serde_json::from_slice(&bytes).ok();
+    let request = PeerRequest { verb, path: &path, data: data.as_ref() };
+    match node.verify_peer_token(token, &request) {
+        Ok(_) => next.run(Request::from_parts(parts, axum::body::Body::from(bytes))).await,
+        Err(e) => (StatusCode::FORBIDDEN, e.to_string()).into_response(),
+    }
+}
+
+/// Largest body a peer-token request may carry
+const PEER_BODY_MAX: usize = 1024 * 1024;
+
+/// Create router hosting every node in `manager` under `/n/{app}/...`
+pub fn create_router_with_manager(manager: Arc<NodeManager>) -> Router {
+    let cors = cors(manager.cors_origins());
+    Router::new()
+        .route("/health", get(manager_health))
+        .route("/n/:app/health", get(tenant_health))
+        .route("/n/:app/scrolls", get(tenant_list_scrolls))
+        .route("/n/:app/scroll/*path", get(tenant_read_scroll))
+        .route("/n/:app/scroll/*path", post(tenant_write_scroll))
+        .layer(cors)
         .layer(TraceLayer::new_for_http())
-        .with_state(NodeState::new(node, app_name))
+        .with_state(manager)
 }
 
 async fn health(State(s): State<AppState>) -> impl IntoResponse {
@@ -74,7 +199,10 @@ async fn health(State(s): State<AppState>) -> impl IntoResponse {
 }
 
 async fn list_scrolls(State(s): State<AppState>, Query(q): Query<ListQuery>) -> Result<Json<ListResponse>, (StatusCode, String)> {
-    let paths = s.store.list(&q.prefix).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut paths = s.store.list(&q.prefix).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(since) = q.since_version {
+        paths.retain(|p| matches!(s.store.read(p), Ok(Some(scroll)) if scroll.metadata.version > since));
+    }
     Ok(Json(ListResponse { count: paths.len(), paths }))
 }
 
@@ -90,7 +218,7 @@ async fn read_scroll(State(s): State<AppState>, Path(path): Path<String>) -> Res
 async fn write_scroll(State(s): State<AppState>, Path(path): Path<String>, Json(data): Json<Value>) -> Result<Json<WriteResponse>, (StatusCode, String)> {
     let p = if path.starts_with('/') { path } else { format!("/{}", path) };
     match s.store.write(&p, data) {
-        Ok(scroll) => Ok(Json(WriteResponse { key: scroll.key, version: scroll.metadata.version })),
+        Ok(scroll) => Ok(Json(WriteResponse { key: scroll.key, version: scroll.metadata.version, result: None })),
         Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
     }
 }
@@ -101,37 +229,304 @@ async fn node_health(State(s): State<NodeState>) -> impl IntoResponse {
     Json(serde_json::json!({"status": "ok", "service": s.app_name}))
 }
 
+async fn node_status(State(s): State<NodeState>) -> Result<Json<Value>, (StatusCode, String)> {
+    s.node.status().map(Json).map_err(|e| match e.to_string() {
+        m if m.contains("locked") => (StatusCode::LOCKED, m),
+        m => (StatusCode::INTERNAL_SERVER_ERROR, m),
+    })
+}
+
+async fn node_capabilities(State(s): State<NodeState>) -> Result<Json<Value>, (StatusCode, String)> {
+    s.node.capabilities().map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
 async fn node_list_scrolls(State(s): State<NodeState>, Query(q): Query<ListQuery>) -> Result<Json<ListResponse>, (StatusCode, String)> {
-    let paths = s.node.all(&q.prefix).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut paths = s.node.all(&q.prefix).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Some(since) = q.since_version {
+        paths.retain(|p| matches!(s.node.get(p), Ok(Some(scroll)) if scroll.metadata.version > since));
+    }
     Ok(Json(ListResponse { count: paths.len(), paths }))
 }
 
-async fn node_read_scroll(State(s): State<NodeState>, Path(path): Path<String>) -> Result<Json<Value>, (StatusCode, String)> {
-    let p = if path.starts_with('/') { path } else { format!("/{}", path) };
+async fn node_read_scroll(State(s): State<NodeState>, Path(path): Path<String>, Query(q): Query<ReadQuery>, headers: HeaderMap) -> Result<Response, (StatusCode, String)> {
+    let mut p = if path.starts_with('/') { path } else { format!("/{}", path) };
+    let options: Vec<String> = [("format", q.format), ("currency", q.currency)].into_iter()
+        .filter_map(|(k, v)| v.map(|v| format!("{}={}", k, v))).collect();
+    if !options.is_empty() { p = format!("{}?{}", p, options.join("&")); }
     match s.node.get(&p) {
-        Ok(Some(scroll)) => Ok(Json(serde_json::json!({
-            "key": scroll.key,
-            "type": scroll.type_,
-            "data": scroll.data,
-            "metadata": {
-                "version": scroll.metadata.version,
-                "created_at": scroll.metadata.created_at,
-                "updated_at": scroll.metadata.updated_at,
+        Ok(Some(scroll)) => {
+            let tag = etag(&scroll);
+            let fresh = headers.get(header::IF_NONE_MATCH)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.split(',').any(|t| t.trim() == tag || t.trim() == "*"));
+            if fresh {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response());
             }
-        }))),
+            Ok(([(header::ETAG, tag)], Json(scroll_json(&scroll))).into_response())
+        }
         Ok(None) => Err((StatusCode::NOT_FOUND, format!("not found: {}", p))),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
-async fn node_write_scroll(State(s): State<NodeState>, Path(path): Path<String>, Json(data): Json<Value>) -> Result<Json<WriteResponse>, (StatusCode, String)> {
+/// Strong ETag: version plus a content hash, since namespace-computed
+/// scrolls (e.g. `/wallet/balance`) can change without a version bump
+fn etag(scroll: &Scroll) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(scroll.type_.as_bytes());
+    hasher.update(scroll.data.to_string().as_bytes());
+    format!("\"{}-{}\"", scroll.metadata.version, &hasher.finalize().to_hex()[..16])
+}
+
+async fn node_write_scroll(State(s): State<NodeState>, Path(path): Path<String>, Query(q): Query<WriteQuery>, Json(data): Json<Value>) -> Result<Json<WriteResponse>, (StatusCode, String)> {
     let p = if path.starts_with('/') { path } else { format!("/{}", path) };
+    if q.wait && p.starts_with(crate::core::paths::mind::EXTERNAL_PREFIX) {
+        let node = s.node.clone();
+        let timeout = std::time::Duration::from_millis(q.timeout_ms);
+        let effect = p.clone();
+        let result = tokio::task::spawn_blocking(move || node.execute_effect(&effect, data, timeout)).await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return match result {
+            Ok(r) => Ok(Json(WriteResponse { key: p, version: r.metadata.version, result: Some(r.data) })),
+            Err(e) if e.to_string().starts_with("effect timed out") => Err((StatusCode::GATEWAY_TIMEOUT, e.to_string())),
+            Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        };
+    }
     match s.node.put(&p, data) {
-        Ok(scroll) => Ok(Json(WriteResponse { key: scroll.key, version: scroll.metadata.version })),
+        Ok(scroll) => Ok(Json(WriteResponse { key: scroll.key, version: scroll.metadata.version, result: None })),
         Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
     }
 }
 
+#[derive(Deserialize)]
+pub struct BatchRequest { #[serde(default)] gets: Vec<String>, #[serde(default)] puts: Vec<BatchPut> }
+
+#[derive(Deserialize)]
+pub struct BatchPut { path: String, data: Value }
+
+/// Reads are independent (per-item `error`); puts are all-or-nothing
+async fn node_batch(State(s): State<NodeState>, Json(req): Json<BatchRequest>) -> Json<Value> {
+    let gets: Vec<Value> = req.gets.into_iter().map(|path| {
+        let p = if path.starts_with('/') { path } else { format!("/{}", path) };
+        match s.node.get(&p) {
+            Ok(Some(scroll)) => serde_json::json!({"path": p, "status": 200, "scroll": scroll_json(&scroll)}),
+            Ok(None) => serde_json::json!({"path": p, "status": 404, "error": "not found"}),
+            Err(e) => serde_json::json!({"path": p, "status": 400, "error": e.to_string()}),
+        }
+    }).collect();
+
+    let puts: Vec<(String, Value)> = req.puts.into_iter()
+        .map(|p| (if p.path.starts_with('/') { p.path } else { format!("/{}", p.path) }, p.data))
+        .collect();
+    let paths: Vec<String> = puts.iter().map(|(p, _)| p.clone()).collect();
+    let puts = match s.node.put_batch(puts) {
+        Ok(written) => serde_json::json!({"ok": true, "results": written.iter()
+            .map(|w| serde_json::json!({"path": w.key, "status": 200, "version": w.metadata.version}))
+            .collect::<Vec<_>>()}),
+        Err(e) => serde_json::json!({"ok": false, "error": e.to_string(), "results": paths.iter()
+            .map(|p| serde_json::json!({"path": p, "status": 409, "error": "batch rolled back"}))
+            .collect::<Vec<_>>()}),
+    };
+    Json(serde_json::json!({"gets": gets, "puts": puts}))
+}
+
+fn scroll_json(scroll: &Scroll) -> Value {
+    serde_json::json!({
+        "key": scroll.key,
+        "type": scroll.type_,
+        "data": scroll.data,
+        "metadata": {
+            "version": scroll.metadata.version,
+            "created_at": scroll.metadata.created_at,
+            "updated_at": scroll.metadata.updated_at,
+        }
+    })
+}
+
+async fn node_view(State(s): State<NodeState>, Path(name): Path<String>) -> Result<Json<Value>, (StatusCode, String)> {
+    s.node.view(&name).map(Json).map_err(|e| {
+        let msg = e.to_string();
+        if msg.starts_with("no view") { (StatusCode::NOT_FOUND, msg) } else { (StatusCode::BAD_REQUEST, msg) }
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ReplicateQuery {
+    #[serde(default)] since: u64,
+    epoch: Option<String>,
+    #[serde(default = "default_batch")] limit: usize,
+}
+fn default_batch() -> usize { crate::node::replication::DEFAULT_BATCH }
+
+async fn node_replicate(State(s): State<NodeState>, Query(q): Query<ReplicateQuery>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let changes = s.node.changes(q.since, q.epoch.as_deref(), q.limit).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], crate::node::replication::to_ndjson(&changes)))
+}
+
+#[derive(Deserialize)]
+pub struct JournalQuery {
+    #[serde(default)] since: u64,
+    #[serde(default = "default_page")] limit: usize,
+}
+fn default_page() -> usize { crate::node::journal::DEFAULT_PAGE }
+
+async fn node_journal(State(s): State<NodeState>, Query(q): Query<JournalQuery>) -> Result<Json<crate::node::JournalPage>, (StatusCode, String)> {
+    s.node.journal(q.since, q.limit).map(Json).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct TreeQuery {
+    #[serde(default = "default_prefix")] prefix: String,
+    #[serde(default = "default_depth")] depth: usize,
+    /// `next` from the previous page
+    after: Option<String>,
+    #[serde(default = "default_tree_page")] limit: usize,
+}
+fn default_depth() -> usize { 1 }
+fn default_tree_page() -> usize { crate::node::browse::DEFAULT_PAGE }
+
+async fn node_tree(State(s): State<NodeState>, Query(q): Query<TreeQuery>) -> Result<Json<crate::node::TreePage>, (StatusCode, String)> {
+    s.node.tree(&q.prefix, q.depth, q.after.as_deref(), q.limit).map(Json).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+async fn node_meta(State(s): State<NodeState>, Path(path): Path<String>) -> Result<Json<Value>, (StatusCode, String)> {
+    let p = if path.starts_with('/') { path } else { format!("/{}", path) };
+    match s.node.meta(&p) {
+        Ok(Some(meta)) => Ok(Json(meta)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, format!("not found: {}", p))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+async fn node_type_stats(State(s): State<NodeState>, Query(q): Query<ListQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let types = s.node.type_stats(&q.prefix).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(serde_json::json!({"prefix": q.prefix, "total": types.values().sum::<usize>(), "types": types})))
+}
+
+#[derive(Deserialize)]
+pub struct WatchQuery {
+    #[serde(default = "default_watch")] pattern: String,
+    /// Replay changes after this `/journal` (or `/replicate`) cursor before going live
+    since: Option<u64>,
+    #[serde(default)] debounce_ms: u64,
+    #[serde(default)] coalesce: bool,
+}
+fn default_watch() -> String { "/**".into() }
+
+/// Server-sent events, one `scroll` event per change. `pattern` takes
+/// captures and predicates (`core::watch`); `since` replays first and then
+/// delivers live writes undebounced.
+async fn node_watch(State(s): State<NodeState>, Query(q): Query<WatchQuery>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    let bad = |e: nine_s_core::errors::NineSError| (StatusCode::BAD_REQUEST, e.to_string());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Value>();
+    match q.since {
+        Some(since) => {
+            let events = s.node.on_with_replay(&q.pattern, since).map_err(bad)?;
+            forward(events, tx, |scroll| serde_json::json!({"scroll": scroll_json(&scroll), "captures": {}}));
+        }
+        None => {
+            let options = crate::core::watch::WatchOptions { debounce_ms: q.debounce_ms, coalesce: q.coalesce };
+            let events = s.node.watch_with(&q.pattern, options).map_err(bad)?;
+            forward(events, tx, |event| serde_json::json!({"scroll": scroll_json(&event.scroll), "captures": event.captures}));
+        }
+    }
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let data = rx.recv().await?;
+        Some((Event::default().event("scroll").json_data(data), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// How often a quiet watch checks that its SSE client is still there
+const WATCH_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Pass watch events to an SSE stream on a thread that ends when either side
+/// does, including a client that leaves while its pattern is quiet
+fn forward<T: Send + 'static>(events: std::sync::mpsc::Receiver<T>, tx: tokio::sync::mpsc::UnboundedSender<Value>, json: impl Fn(T) -> Value + Send + 'static) {
+    use std::sync::mpsc::RecvTimeoutError;
+    std::thread::spawn(move || loop {
+        match events.recv_timeout(WATCH_POLL) {
+            Ok(event) => if tx.send(json(event)).is_err() { break },
+            Err(RecvTimeoutError::Timeout) => if tx.is_closed() { break },
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+}
+
+/// Response header carrying the server clock's current tick
+pub const TICK_HEADER: &str = "x-beenode-tick";
+
+/// Add `X-Beenode-Tick` when the clock has ticked
+async fn tick_header(State(s): State<NodeState>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let tick = s.clock.start(&s.node).ok().and_then(|_| s.clock.tick());
+    if let Some(tick) = tick { response.headers_mut().insert(TICK_HEADER, HeaderValue::from(tick)); }
+    response
+}
+
+#[derive(Deserialize)]
+pub struct ClockQuery {
+    /// Comma-separated pulse names; all pulses when absent
+    pulses: Option<String>,
+    #[serde(default = "default_true")] ticks: bool,
+}
+fn default_true() -> bool { true }
+
+/// Server-sent clock: the current `tick` first, then a `tick` event per
+/// tick and a `pulse` event per pulse as the clock writes them, undebounced
+async fn node_clock(State(s): State<NodeState>, Query(q): Query<ClockQuery>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use crate::core::paths::clock;
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use tokio::sync::broadcast::error::RecvError;
+    s.clock.start(&s.node).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let events = s.clock.subscribe();
+    let current = s.clock.latest().filter(|_| q.ticks);
+    let pulses: Option<Vec<String>> = q.pulses.map(|p| p.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect());
+    let kind = move |key: &str| -> Option<&'static str> {
+        if key == clock::TICK {
+            q.ticks.then_some("tick")
+        } else {
+            let name = key.strip_prefix(&format!("{}/", clock::PULSES))?;
+            if pulses.as_ref().is_some_and(|p| !p.iter().any(|n| n == name)) { return None; }
+            Some("pulse")
+        }
+    };
+    let stream = futures_util::stream::unfold((current, events, kind), |(current, mut events, kind)| async move {
+        if let Some(tick) = current {
+            return Some((Event::default().event("tick").json_data(tick), (None, events, kind)));
+        }
+        loop {
+            match events.recv().await {
+                Ok((key, data)) => {
+                    if let Some(event) = kind(&key) {
+                        return Some((Event::default().event(event).json_data(data), (None, events, kind)));
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "search"), allow(dead_code))]
+pub struct SearchQuery { #[serde(default)] q: String, #[serde(default = "default_limit")] limit: usize }
+fn default_limit() -> usize { 50 }
+
+#[cfg(feature = "search")]
+async fn node_search(State(s): State<NodeState>, Query(q): Query<SearchQuery>) -> Result<Json<Value>, (StatusCode, String)> {
+    let hits = s.node.search(&q.q, q.limit).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(serde_json::json!({"query": q.q, "count": hits.len(), "hits": hits})))
+}
+
+#[cfg(not(feature = "search"))]
+async fn node_search(Query(_): Query<SearchQuery>) -> (StatusCode, String) {
+    (StatusCode::NOT_IMPLEMENTED, "search feature not enabled".into())
+}
+
 #[derive(Deserialize)]
 struct UnlockRequest { pin: String }
 
@@ -158,3 +553,219 @@ async fn node_auth_lock(State(s): State<NodeState>) -> Result<Json<AuthActionRes
         Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
     }
 }
+
+// Multi-tenant handlers: resolve /n/{app} to a NodeState, then reuse the node handlers
+
+fn tenant(manager: &NodeManager, app: &str, headers: &HeaderMap) -> Result<State<NodeState>, (StatusCode, String)> {
+    let bearer = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match manager.state(app, bearer) {
+        Ok(state) => Ok(State(state)),
+        Err(ManagerError::UnknownApp) => Err((StatusCode::NOT_FOUND, format!("unknown app: {}", app))),
+        Err(ManagerError::Unauthorized) => Err((StatusCode::UNAUTHORIZED, "invalid or missing token".into())),
+    }
+}
+
+async fn manager_health(State(m): State<Arc<NodeManager>>) -> impl IntoResponse {
+    Json(serde_json::json!({"status": "ok", "nodes": m.apps()}))
+}
+
+async fn tenant_health(State(m): State<Arc<NodeManager>>, Path(app): Path<String>, headers: HeaderMap) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Ok(node_health(tenant(&m, &app, &headers)?).await)
+}
+
+async fn tenant_list_scrolls(State(m): State<Arc<NodeManager>>, Path(app): Path<String>, headers: HeaderMap, q: Query<ListQuery>) -> Result<Json<ListResponse>, (StatusCode, String)> {
+    node_list_scrolls(tenant(&m, &app, &headers)?, q).await
+}
+
+async fn tenant_read_scroll(State(m): State<Arc<NodeManager>>, Path((app, path)): Path<(String, String)>, headers: HeaderMap, q: Query<ReadQuery>) -> Result<Response, (StatusCode, String)> {
+    node_read_scroll(tenant(&m, &app, &headers)?, Path(path), q, headers).await
+}
+
+async fn tenant_write_scroll(State(m): State<Arc<NodeManager>>, Path((app, path)): Path<(String, String)>, headers: HeaderMap, q: Query<WriteQuery>, body: Json<Value>) -> Result<Json<WriteResponse>, (StatusCode, String)> {
+    node_write_scroll(tenant(&m, &app, &headers)?, Path(path), q, body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Node, NodeConfig};
+    use axum::body::Body;
+    use nine_s_core::prelude::*;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    /// Refuses every write, so a batch fails after its first put landed
+    struct Refuses;
+    impl Namespace for Refuses {
+        fn read(&self, _: &str) -> NineSResult<Option<Scroll>> { Ok(None) }
+        fn write(&self, path: &str, _: Value) -> NineSResult<Scroll> { Err(NineSError::Other(format!("refused: {}", path))) }
+        fn list(&self, _: &str) -> NineSResult<Vec<String>> { Ok(vec![]) }
+    }
+
+    fn router(dir: &tempfile::TempDir, config: NodeConfig) -> (Arc<Node>, Router) {
+        let node = Arc::new(Node::from_config(config.with_root(dir.path())).unwrap());
+        (node.clone(), create_router_with_node(node, "test"))
+    }
+
+    async fn call(router: &Router, request: axum::http::Request<Body>) -> (StatusCode, HeaderMap, Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let (status, headers) = (response.status(), response.headers().clone());
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    fn post_json(uri: &str, body: Value) -> axum::http::Request<Body> {
+        axum::http::Request::post(uri).header(header::CONTENT_TYPE, "application/json").body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_route_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = NodeConfig::new("test-routes-batch").with_namespace("/refuses", |_| Ok(Box::new(Refuses) as Box<dyn Namespace>));
+        let (node, router) = router(&dir, config);
+        node.put("/notes/old", json!({"n": 1})).unwrap();
+
+        let (status, _, body) = call(&router, post_json("/scrolls/batch", json!({
+            "gets": ["/notes/old", "notes/missing"],
+            "puts": [{"path": "/notes/old", "data": {"n": 2}}, {"path": "/notes/new", "data": {"n": 3}}],
+        }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["gets"][0]["scroll"]["data"]["n"], 1);
+        assert_eq!(body["gets"][1]["status"], 404);
+        assert_eq!(body["puts"]["ok"], true);
+        assert_eq!(body["puts"]["results"][1]["path"], "/notes/new");
+
+        let (_, _, body) = call(&router, post_json("/scrolls/batch", json!({
+            "puts": [{"path": "/notes/old", "data": {"n": 4}}, {"path": "/notes/fresh", "data": {}}, {"path": "/refuses/x", "data": {}}],
+        }))).await;
+        assert_eq!(body["puts"]["ok"], false);
+        assert_eq!(body["puts"]["results"][2]["status"], 409);
+        assert_eq!(node.get("/notes/old").unwrap().unwrap().data["n"], 2);
+        assert!(node.get("/notes/fresh").unwrap().is_none());
+        assert!(!node.exists("/notes/fresh").unwrap());
+        node.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_etag_and_since_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let (node, router) = router(&dir, NodeConfig::new("test-routes-etag"));
+        node.put("/notes/1", json!({"n": 1})).unwrap();
+        let second = node.put("/notes/2", json!({"n": 2})).unwrap();
+
+        let get = |tag: Option<&str>| {
+            let request = axum::http::Request::get("/scroll/notes/1");
+            let request = match tag { Some(t) => request.header(header::IF_NONE_MATCH, t), None => request };
+            request.body(Body::empty()).unwrap()
+        };
+        let (status, headers, body) = call(&router, get(None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["n"], 1);
+        let tag = headers[header::ETAG].to_str().unwrap().to_string();
+
+        let (status, headers, _) = call(&router, get(Some(&tag))).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers[header::ETAG], tag.as_str());
+
+        node.put("/notes/1", json!({"n": 3})).unwrap();
+        let (status, headers, body) = call(&router, get(Some(&tag))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(headers[header::ETAG], tag.as_str());
+        assert_eq!(body["data"]["n"], 3);
+
+        let uri = format!("/scrolls?prefix=/notes&since_version={}", second.metadata.version);
+        let (_, _, body) = call(&router, axum::http::Request::get(uri).body(Body::empty()).unwrap()).await;
+        assert_eq!(body["paths"], json!(["/notes/1"]));
+        node.close().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_clock_route_and_tick_header() {
+        use futures_util::StreamExt;
+        let dir = tempfile::tempdir().unwrap();
+        let (node, router) = router(&dir, NodeConfig::new("test-routes-clock"));
+        let store = NodeConfig::new("test-routes-clock").with_root(dir.path()).open_store().unwrap();
+        store.write("/sys/clock/tick", json!({"tick": 7})).unwrap();
+        let health = || axum::http::Request::get("/health").body(Body::empty()).unwrap();
+        assert_eq!(call(&router, health()).await.1[TICK_HEADER], "7");
+
+        let response = router.clone().oneshot(axum::http::Request::get("/clock?pulses=beat").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        async fn next(body: &mut axum::body::BodyDataStream) -> String {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.next()).await.unwrap();
+            String::from_utf8(frame.unwrap().unwrap().to_vec()).unwrap()
+        }
+        let mut body = response.into_body().into_data_stream();
+        let first = next(&mut body).await;
+        assert!(first.contains("event: tick") && first.contains(r#""tick":7"#), "{}", first);
+
+        store.write("/sys/clock/pulses/glow", json!({"name": "glow", "tick": 8})).unwrap();
+        store.write("/sys/clock/pulses/beat", json!({"name": "beat", "tick": 8})).unwrap();
+        store.write("/sys/clock/tick", json!({"tick": 8})).unwrap();
+        let pulse = next(&mut body).await;
+        assert!(pulse.contains("event: pulse") && pulse.contains(r#""name":"beat""#), "{}", pulse);
+        let tick = next(&mut body).await;
+        assert!(tick.contains("event: tick") && tick.contains(r#""tick":8"#), "{}", tick);
+        assert_eq!(call(&router, health()).await.1[TICK_HEADER], "8");
+        node.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_and_token() {
+        use crate::node::HttpConfig;
+        let dir = tempfile::tempdir().unwrap();
+        let http = HttpConfig::default().with_origin("https://app.example").with_token("secret");
+        let (node, router) = router(&dir, NodeConfig::new("test-routes-cors").with_http(http));
+
+        let preflight = |origin: &str| axum::http::Request::options("/scrolls")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+            .body(Body::empty()).unwrap();
+        let (status, headers, _) = call(&router, preflight("https://app.example")).await;
+        assert!(status.is_success());
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        let (_, headers, _) = call(&router, preflight("https://evil.example")).await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let list = |token: Option<&str>| {
+            let request = axum::http::Request::get("/scrolls").header(header::ORIGIN, "https://app.example");
+            let request = match token { Some(t) => request.header(header::AUTHORIZATION, format!("Bearer {}", t)), None => request };
+            request.body(Body::empty()).unwrap()
+        };
+        assert_eq!(call(&router, list(None)).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(&router, list(Some("wrong"))).await.0, StatusCode::UNAUTHORIZED);
+        let (status, headers, _) = call(&router, list(Some("secret"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+
+        let health = axum::http::Request::get("/health").body(Body::empty()).unwrap();
+        assert_eq!(call(&router, health).await.0, StatusCode::OK);
+        node.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_manager_cors_and_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = Arc::new(Node::from_config(NodeConfig::new("test-routes-manager").with_root(dir.path())).unwrap());
+        let manager = Arc::new(NodeManager::new().with_origin("https://app.example"));
+        manager.host("a", node.clone(), None);
+        let router = create_router_with_manager(manager.clone());
+
+        let preflight = |origin: &str| axum::http::Request::options("/n/a/scrolls")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty()).unwrap();
+        let (_, headers, _) = call(&router, preflight("https://app.example")).await;
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        let (_, headers, _) = call(&router, preflight("https://evil.example")).await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // One state (and clock feed) per hosted node, not per request
+        let (first, second) = (manager.state("a", None).unwrap(), manager.state("a", None).unwrap());
+        assert!(Arc::ptr_eq(&first.clock, &second.clock));
+        node.close().unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+//! Bundled web UI at `/ui/**` (`beenode serve --ui`, `ui` feature)
+//!
+//! Serves `web/dist` embedded at build time: the dashboard SPA, plus a WASM
+//! BeeNode bundle if one was built into `web/dist/pkg`. Paths without a file
+//! fall back to `index.html` so client-side routes work. The bundle is built
+//! for the nginx setup (assets at `/assets`, API at `/api`), so `index.html`
+//! is rewritten to `/ui/assets` on the way out and `with_ui` mounts the API a
+//! second time under `/api`. Build the UI before the binary:
+//! `cd web && npm run build`.
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "web/dist"]
+struct Assets;
+
+/// `api` with the UI at `/ui/**` and the API also under `/api`
+pub fn with_ui(api: Router) -> Router {
+    Router::new()
+        .nest("/api", api.clone())
+        .merge(api)
+        .route("/ui", get(|| async { Redirect::permanent("/ui/") }))
+        .route("/ui/", get(index))
+        .route("/ui/*path", get(asset))
+}
+
+async fn index() -> Response {
+    let Some(file) = Assets::get("index.html") else {
+        return (StatusCode::NOT_FOUND, "web UI not built (cd web && npm run build)").into_response();
+    };
+    Html(String::from_utf8_lossy(&file.data).replace("\"/assets/", "\"/ui/assets/")).into_response()
+}
+
+async fn asset(Path(path): Path<String>) -> Response {
+    match Assets::get(&path) {
+        Some(file) => {
+            let mime = mime_guess::from_path(&path).first_or_octet_stream();
+            ([(header::CONTENT_TYPE, mime.to_string())], file.data).into_response()
+        }
+        // Client-side route
+        None if !path.rsplit('/').next().unwrap_or_default().contains('.') => index().await,
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
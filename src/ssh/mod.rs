@@ -0,0 +1,164 @@
+//! SSH Key Derivation
+//!
+//! Derives an ed25519 SSH keypair from the BIP39 mnemonic so the node
+//! identity doubles as a server login identity.
+//!
+//! ## Key Derivation Architecture
+//!
+//! ```text
+//! Master Mnemonic (BIP39)
+//!     │
+//!     ├── BIP84 ────────────→ Bitcoin wallet
+//!     ├── BIP85 ────────────→ Nostr keys
+//!     ├── HMAC-SHA512 ──────→ WireGuard Curve25519 keypair
+//!     └── derive registry ──→ SSH ed25519 keypair (`beenode-ssh-v1`)
+//! ```
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! use beenode::ssh::derive_keypair;
+//!
+//! let keypair = derive_keypair("abandon abandon ...", None).unwrap();
+//! println!("{}", keypair.to_openssh("me@beenode"));
+//! ```
+
+mod namespace;
+
+pub use namespace::SshNamespace;
+
+use base64::{engine::general_purpose::{STANDARD as BASE64, STANDARD_NO_PAD}, Engine as _};
+use ed25519_dalek::SigningKey;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// SSH key errors
+#[derive(Error, Debug)]
+pub enum SshError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+
+    #[error("Key derivation failed: {0}")]
+    DerivationFailed(String),
+}
+
+/// SSH keypair (ed25519)
+#[derive(Clone)]
+pub struct SshKeypair {
+    /// Private key seed (32 bytes)
+    pub private_key: [u8; 32],
+
+    /// Public key (32 bytes)
+    pub public_key: [u8; 32],
+}
+
+impl std::fmt::Debug for SshKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshKeypair")
+            .field("private_key", &"[REDACTED]")
+            .field("public_key", &hex::encode(self.public_key))
+            .finish()
+    }
+}
+
+impl Zeroize for SshKeypair {
+    fn zeroize(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+/// Key type name used in the OpenSSH wire format
+const KEY_TYPE: &str = "ssh-ed25519";
+
+impl SshKeypair {
+    /// OpenSSH public key blob: string(key type) || string(public key)
+    pub fn public_blob(&self) -> Vec<u8> {
+        let mut blob = Vec::with_capacity(51);
+        for field in [KEY_TYPE.as_bytes(), &self.public_key[..]] {
+            blob.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            blob.extend_from_slice(field);
+        }
+        blob
+    }
+
+    /// `authorized_keys` line, e.g. `ssh-ed25519 AAAAC3Nza... comment`
+    pub fn to_openssh(&self, comment: &str) -> String {
+        let line = format!("{} {}", KEY_TYPE, BASE64.encode(self.public_blob()));
+        if comment.is_empty() { line } else { format!("{} {}", line, comment) }
+    }
+
+    /// `SHA256:...` fingerprint as printed by `ssh-keygen -l`
+    pub fn fingerprint(&self) -> String {
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(self.public_blob())))
+    }
+}
+
+/// Derive SSH keypair from a 64-byte BIP39 seed
+///
+/// The `ssh` seed at `index` from the protocol registry
+/// (`identity::derive`), so SSH keys are isolated from Bitcoin/Nostr/WireGuard
+/// keys and `/identity/derive {protocol: "ssh"}` records the same key.
+pub fn derive_from_seed(seed: &[u8; 64], index: u32) -> Result<SshKeypair, SshError> {
+    let mut derived = crate::identity::derive::derive_seed(seed, "ssh", index)
+        .map_err(|e| SshError::DerivationFailed(e.to_string()))?;
+    let mut private_key = [0u8; 32];
+    private_key.copy_from_slice(&derived[..32]);
+    derived.zeroize();
+    let public_key = SigningKey::from_bytes(&private_key).verifying_key().to_bytes();
+    Ok(SshKeypair { private_key, public_key })
+}
+
+/// Derive SSH keypair (index 0) from mnemonic
+pub fn derive_keypair(mnemonic: &str, passphrase: Option<&str>) -> Result<SshKeypair, SshError> {
+    let mnemonic = bip39::Mnemonic::parse(mnemonic)
+        .map_err(|e| SshError::InvalidMnemonic(e.to_string()))?;
+    derive_from_seed(&mnemonic.to_seed(passphrase.unwrap_or("")), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_deterministic_derivation() {
+        let a = derive_keypair(TEST_MNEMONIC, None).unwrap();
+        let b = derive_keypair(TEST_MNEMONIC, None).unwrap();
+        assert_eq!(a.private_key, b.private_key);
+        assert_eq!(a.public_key, b.public_key);
+
+        let c = derive_keypair(TEST_MNEMONIC, Some("secret")).unwrap();
+        assert_ne!(a.public_key, c.public_key);
+    }
+
+    #[test]
+    fn test_matches_registry_seed() {
+        let seed = bip39::Mnemonic::parse(TEST_MNEMONIC).unwrap().to_seed("");
+        let registry = crate::identity::derive::derive_seed(&seed, "ssh", 1).unwrap();
+        assert_eq!(derive_from_seed(&seed, 1).unwrap().private_key, registry[..32]);
+        assert_ne!(derive_from_seed(&seed, 0).unwrap().public_key, derive_from_seed(&seed, 1).unwrap().public_key);
+    }
+
+    #[test]
+    fn test_differs_from_wireguard() {
+        let ssh = derive_keypair(TEST_MNEMONIC, None).unwrap();
+        let wg = crate::wireguard::derive_keypair(TEST_MNEMONIC, None).unwrap();
+        assert_ne!(ssh.private_key, wg.private_key);
+    }
+
+    #[test]
+    fn test_openssh_format() {
+        let keypair = derive_keypair(TEST_MNEMONIC, None).unwrap();
+        let line = keypair.to_openssh("me@beenode");
+        let parts: Vec<&str> = line.split(' ').collect();
+        assert_eq!(parts[0], "ssh-ed25519");
+        // 51-byte blob always encodes with this prefix
+        assert!(parts[1].starts_with("AAAAC3NzaC1lZDI1NTE5AAAAI"));
+        assert_eq!(parts[2], "me@beenode");
+        assert!(keypair.fingerprint().starts_with("SHA256:"));
+        assert_eq!(keypair.fingerprint().len(), 7 + 43);
+    }
+}
@@ -0,0 +1,115 @@
+//! SSH Namespace
+//!
+//! Provides scroll-based access to the derived SSH identity.
+//!
+//! ## Paths
+//!
+//! | Path | R/W | Description |
+//! |------|-----|-------------|
+//! | `/ssh/status` | R | `{ initialized: bool }` |
+//! | `/ssh/pubkey` | R | `{ openssh, hex, fingerprint }` |
+//! | `/ssh/authorized_keys` | R | `{ line }` ready to append to `~/.ssh/authorized_keys` |
+
+use super::SshKeypair;
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// SSH namespace for scroll-based access
+pub struct SshNamespace {
+    keypair: Arc<SshKeypair>,
+    comment: String,
+}
+
+impl SshNamespace {
+    /// Create a new SSH namespace; `comment` is appended to exported keys
+    pub fn new(keypair: SshKeypair, comment: impl Into<String>) -> Self {
+        Self {
+            keypair: Arc::new(keypair),
+            comment: comment.into(),
+        }
+    }
+
+    fn read_status(&self) -> Scroll {
+        Scroll::typed(
+            "/ssh/status",
+            json!({
+                "initialized": true,
+                "key_type": "ssh-ed25519",
+            }),
+            "ssh/status@v1",
+        )
+    }
+
+    fn read_pubkey(&self) -> Scroll {
+        Scroll::typed(
+            "/ssh/pubkey",
+            json!({
+                "openssh": self.keypair.to_openssh(&self.comment),
+                "hex": hex::encode(self.keypair.public_key),
+                "fingerprint": self.keypair.fingerprint(),
+            }),
+            "ssh/pubkey@v1",
+        )
+    }
+
+    fn read_authorized_keys(&self) -> Scroll {
+        Scroll::typed(
+            "/ssh/authorized_keys",
+            json!({ "line": self.keypair.to_openssh(&self.comment) }),
+            "ssh/authorized-keys@v1",
+        )
+    }
+}
+
+impl Namespace for SshNamespace {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        match path {
+            "status" | "/status" => Ok(Some(self.read_status())),
+            "pubkey" | "/pubkey" => Ok(Some(self.read_pubkey())),
+            "authorized_keys" | "/authorized_keys" => Ok(Some(self.read_authorized_keys())),
+            _ => Ok(None),
+        }
+    }
+
+    fn write(&self, path: &str, _data: Value) -> NineSResult<Scroll> {
+        Err(NineSError::invalid_path(path, "ssh namespace is read-only"))
+    }
+
+    fn list(&self, _prefix: &str) -> NineSResult<Vec<String>> {
+        Ok(vec![
+            "/ssh/status".to_string(),
+            "/ssh/pubkey".to_string(),
+            "/ssh/authorized_keys".to_string(),
+        ])
+    }
+
+    fn close(&self) -> NineSResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::derive_keypair;
+
+    const TEST_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_namespace_pubkey() {
+        let ns = SshNamespace::new(derive_keypair(TEST_MNEMONIC, None).unwrap(), "test");
+
+        let scroll = ns.read("pubkey").unwrap().unwrap();
+        assert!(scroll.data["openssh"].as_str().unwrap().starts_with("ssh-ed25519 "));
+        assert!(scroll.data["openssh"].as_str().unwrap().ends_with(" test"));
+        assert_eq!(scroll.data["hex"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_namespace_read_only() {
+        let ns = SshNamespace::new(derive_keypair(TEST_MNEMONIC, None).unwrap(), "");
+        assert!(ns.write("/pubkey", json!({})).is_err());
+    }
+}
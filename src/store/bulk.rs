@@ -0,0 +1,183 @@
+//! Bulk - JSONL/CSV import and JSONL export
+//!
+//! Records become scrolls under a prefix. Keys come from `key_field` when set
+//! (`{prefix}/{record[key_field]}`), otherwise from the record number
+//! (`{prefix}/00000042`). JSONL lines that are whole scrolls (`{key, data, ...}`,
+//! as written by `export`) keep their key and type, so an export re-imports
+//! as-is; their key must sit under the prefix. CSV rows become objects keyed by
+//! the header row, with numeric and boolean cells parsed.
+//!
+//! Each batch is fully keyed and checked before its first write, and
+//! `progress` is called after every batch. Batches are not atomic: a write
+//! that fails stops the import with every earlier write in place, including
+//! those from the failing batch. Keyed records overwrite on a re-run.
+
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+/// Records per batch unless overridden
+pub const DEFAULT_BATCH: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format { Jsonl, Csv }
+
+impl Format {
+    /// From a file extension (`.csv` is CSV, anything else JSONL)
+    pub fn from_path(path: &str) -> Self {
+        if path.to_ascii_lowercase().ends_with(".csv") { Format::Csv } else { Format::Jsonl }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Scroll type for plain records (scroll-shaped lines keep their own)
+    pub type_: Option<String>,
+    /// Record field used as the key segment
+    pub key_field: Option<String>,
+    pub batch: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self { Self { type_: None, key_field: None, batch: DEFAULT_BATCH } }
+}
+
+impl ImportOptions {
+    pub fn with_type(mut self, t: impl Into<String>) -> Self { self.type_ = Some(t.into()); self }
+    pub fn with_key_field(mut self, f: impl Into<String>) -> Self { self.key_field = Some(f.into()); self }
+    pub fn with_batch(mut self, n: usize) -> Self { self.batch = n.max(1); self }
+}
+
+/// Reported after each batch
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub written: usize,
+    pub total: usize,
+}
+
+/// Parse `input` into records
+pub fn parse(input: &str, format: Format) -> NineSResult<Vec<Value>> {
+    match format {
+        Format::Jsonl => input.lines().enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| serde_json::from_str(l).map_err(|e| NineSError::Other(format!("line {}: {}", i + 1, e))))
+            .collect(),
+        Format::Csv => {
+            let mut rows = csv_rows(input).into_iter();
+            let header = rows.next().ok_or_else(|| NineSError::Other("csv: missing header row".into()))?;
+            Ok(rows.filter(|r| r.iter().any(|c| !c.is_empty())).map(|row| {
+                let mut obj = Map::new();
+                for (name, cell) in header.iter().zip(row) { obj.insert(name.clone(), cell_value(cell)); }
+                Value::Object(obj)
+            }).collect())
+        }
+    }
+}
+
+/// Write `records` under `prefix`. Returns the number of scrolls written;
+/// on error, what was written before it stays (see the module docs).
+pub fn import(store: &Store, prefix: &str, records: Vec<Value>, opts: &ImportOptions, mut progress: impl FnMut(Progress)) -> NineSResult<usize> {
+    let prefix = prefix.trim_end_matches('/');
+    let total = records.len();
+    let mut written = 0;
+    let mut index = 0;
+    for chunk in records.chunks(opts.batch.max(1)) {
+        let mut batch = Vec::with_capacity(chunk.len());
+        for record in chunk {
+            batch.push(to_scroll(prefix, index, record, opts)?);
+            index += 1;
+        }
+        for scroll in batch {
+            store.write_scroll(scroll)?;
+            written += 1;
+        }
+        progress(Progress { written, total });
+    }
+    Ok(written)
+}
+
+/// Write every scroll under `prefix` to `out` as JSONL. Returns the count.
+pub fn export(store: &Store, prefix: &str, out: &mut impl Write) -> NineSResult<usize> {
+    let mut keys = store.list(prefix)?;
+    keys.sort();
+    let mut count = 0;
+    for key in keys {
+        let Some(scroll) = store.read(&key)? else { continue };
+        let line = serde_json::to_string(&scroll).map_err(|e| NineSError::Other(format!("export {}: {}", key, e)))?;
+        writeln!(out, "{}", line).map_err(|e| NineSError::Other(format!("export: {}", e)))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn to_scroll(prefix: &str, index: usize, record: &Value, opts: &ImportOptions) -> NineSResult<Scroll> {
+    if record.get("key").is_some() && record.get("data").is_some() {
+        let scroll: Scroll = serde_json::from_value(record.clone()).map_err(|e| NineSError::Other(format!("record {}: {}", index + 1, e)))?;
+        if !scroll.key.starts_with(&format!("{}/", prefix)) {
+            return Err(NineSError::invalid_path(&scroll.key, &format!("outside import prefix {}", prefix)));
+        }
+        let type_ = opts.type_.clone().unwrap_or(scroll.type_);
+        return Ok(Scroll { key: scroll.key, type_, metadata: Metadata::default(), data: scroll.data });
+    }
+    let segment = match &opts.key_field {
+        Some(field) => match &record[field.as_str()] {
+            Value::String(s) if !s.is_empty() && !s.contains('/') => s.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => return Err(NineSError::Other(format!("record {}: missing or invalid '{}'", index + 1, field))),
+        },
+        None => format!("{:08}", index),
+    };
+    let scroll = Scroll::new(&format!("{}/{}", prefix, segment), record.clone());
+    Ok(match &opts.type_ { Some(t) => scroll.set_type(t), None => scroll })
+}
+
+/// RFC 4180-ish: quoted fields, doubled quotes, newlines inside quotes
+fn csv_rows(input: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let (mut row, mut cell, mut quoted) = (Vec::new(), String::new(), false);
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => { cell.push('"'); chars.next(); }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) => {}
+            ('\n', false) => { row.push(std::mem::take(&mut cell)); rows.push(std::mem::take(&mut row)); }
+            _ => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() { row.push(cell); rows.push(row); }
+    rows
+}
+
+fn cell_value(cell: String) -> Value {
+    match serde_json::from_str::<Value>(cell.trim()) {
+        Ok(v @ (Value::Number(_) | Value::Bool(_))) => v,
+        _ => Value::String(cell),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse("id,name,qty\n1,\"Smith, J\",3\n2,\"say \"\"hi\"\"\",true\n", Format::Csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["name"], "Smith, J");
+        assert_eq!(rows[0]["qty"], 3);
+        assert_eq!(rows[1]["name"], "say \"hi\"");
+        assert_eq!(rows[1]["qty"], true);
+    }
+
+    #[test]
+    fn test_keys() {
+        let opts = ImportOptions::default().with_key_field("id").with_type("note@v1");
+        let s = to_scroll("/notes", 0, &serde_json::json!({"id": 7}), &opts).unwrap();
+        assert_eq!((s.key.as_str(), s.type_.as_str()), ("/notes/7", "note@v1"));
+        assert_eq!(to_scroll("/notes", 3, &serde_json::json!({}), &ImportOptions::default()).unwrap().key, "/notes/00000003");
+        assert!(to_scroll("/notes", 0, &serde_json::json!({"key": "/other/1", "data": 1}), &ImportOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Diff - What changed between two snapshots
+//!
+//! Keys only in `b` are added, keys only in `a` are removed, and keys in both
+//! are changed when their type or data differ. Metadata-only churn (timestamps,
+//! a rewrite of identical data) is ignored, but version deltas are reported.
+
+use super::Snapshot;
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind { Added, Changed, Removed }
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self { ChangeKind::Added => "added", ChangeKind::Changed => "changed", ChangeKind::Removed => "removed" }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub key: String,
+    pub kind: ChangeKind,
+    pub type_: String,
+    pub from_version: Option<u64>,
+    pub to_version: Option<u64>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StoreDiff {
+    /// Sorted by key
+    pub changes: Vec<Change>,
+    pub unchanged: usize,
+}
+
+impl StoreDiff {
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+
+    /// `{added, changed, removed, unchanged, changes: [{key, kind, type, from_version, to_version, version_delta}]}`
+    pub fn to_json(&self) -> Value {
+        json!({
+            "added": self.count(ChangeKind::Added),
+            "changed": self.count(ChangeKind::Changed),
+            "removed": self.count(ChangeKind::Removed),
+            "unchanged": self.unchanged,
+            "changes": self.changes.iter().map(|c| json!({
+                "key": c.key,
+                "kind": c.kind.as_str(),
+                "type": c.type_,
+                "from_version": c.from_version,
+                "to_version": c.to_version,
+                "version_delta": c.from_version.zip(c.to_version).map(|(a, b)| b as i64 - a as i64),
+                "before": c.before,
+                "after": c.after,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Compare snapshot `a` (before) with `b` (after)
+pub fn diff(a: &Snapshot, b: &Snapshot) -> StoreDiff {
+    let mut out = StoreDiff::default();
+    for (key, old) in a {
+        match b.get(key) {
+            None => out.changes.push(change(key, ChangeKind::Removed, Some(old), None)),
+            Some(new) if new.type_ != old.type_ || new.data != old.data => out.changes.push(change(key, ChangeKind::Changed, Some(old), Some(new))),
+            Some(_) => out.unchanged += 1,
+        }
+    }
+    for (key, new) in b {
+        if !a.contains_key(key) { out.changes.push(change(key, ChangeKind::Added, None, Some(new))); }
+    }
+    out.changes.sort_by(|x, y| x.key.cmp(&y.key));
+    out
+}
+
+fn change(key: &str, kind: ChangeKind, before: Option<&Scroll>, after: Option<&Scroll>) -> Change {
+    Change {
+        key: key.to_string(),
+        kind,
+        type_: after.or(before).map(|s| s.type_.clone()).unwrap_or_default(),
+        from_version: before.map(|s| s.metadata.version),
+        to_version: after.map(|s| s.metadata.version),
+        before: before.map(|s| s.data.clone()),
+        after: after.map(|s| s.data.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(items: &[(&str, Value)]) -> Snapshot {
+        items.iter().map(|(k, v)| (k.to_string(), Scroll::new(k, v.clone()))).collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = snap(&[("/a", json!(1)), ("/b", json!(2)), ("/c", json!(3))]);
+        let b = snap(&[("/a", json!(1)), ("/b", json!(20)), ("/d", json!(4))]);
+        let d = diff(&a, &b);
+        let kinds: Vec<_> = d.changes.iter().map(|c| (c.key.as_str(), c.kind)).collect();
+        assert_eq!(kinds, vec![("/b", ChangeKind::Changed), ("/c", ChangeKind::Removed), ("/d", ChangeKind::Added)]);
+        assert_eq!(d.unchanged, 1);
+        assert!(diff(&a, &a).is_empty());
+    }
+}
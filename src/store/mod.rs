@@ -0,0 +1,69 @@
+//! Store - Whole-store tooling: snapshots, diffs, bulk import/export
+//!
+//! A snapshot is every scroll under a prefix, keyed by path. On disk it is
+//! NDJSON in the same shape as the replication stream: a header line
+//! `{format, prefix, taken_at, count}` then one scroll per line.
+//!
+//! ```ignore
+//! let before = store::snapshot(&store, "/")?;
+//! store::save(&before, "before.ndjson")?;
+//! // ... overnight ...
+//! let d = store::diff(&store::load("before.ndjson")?, &store::snapshot(&store, "/")?);
+//! println!("{}", d.to_json());
+//! ```
+
+pub mod bulk;
+pub mod diff;
+
+pub use bulk::{export, import, Format, ImportOptions, Progress};
+pub use diff::{diff, Change, ChangeKind, StoreDiff};
+
+use crate::core::time::now_secs;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Header `format` marker for snapshot files
+pub const SNAPSHOT_FORMAT: &str = "beenode-snapshot@v1";
+
+/// Scrolls by key
+pub type Snapshot = BTreeMap<String, Scroll>;
+
+/// Read every scroll under `prefix`
+pub fn snapshot(store: &Store, prefix: &str) -> NineSResult<Snapshot> {
+    let mut out = Snapshot::new();
+    for key in store.list(prefix)? {
+        if let Some(scroll) = store.read(&key)? { out.insert(key, scroll); }
+    }
+    Ok(out)
+}
+
+/// Write a snapshot as NDJSON
+pub fn save(snapshot: &Snapshot, path: impl AsRef<Path>) -> NineSResult<()> {
+    let mut out = json!({"format": SNAPSHOT_FORMAT, "taken_at": now_secs(), "count": snapshot.len()}).to_string();
+    for scroll in snapshot.values() {
+        out.push('\n');
+        out.push_str(&serde_json::to_string(scroll).map_err(|e| NineSError::Other(format!("snapshot: {}", e)))?);
+    }
+    out.push('\n');
+    std::fs::write(path.as_ref(), out).map_err(|e| NineSError::Other(format!("{}: {}", path.as_ref().display(), e)))
+}
+
+/// Read a snapshot written by `save`
+pub fn load(path: impl AsRef<Path>) -> NineSResult<Snapshot> {
+    let raw = std::fs::read_to_string(path.as_ref()).map_err(|e| NineSError::Other(format!("{}: {}", path.as_ref().display(), e)))?;
+    let mut lines = raw.lines().filter(|l| !l.trim().is_empty());
+    let header: serde_json::Value = serde_json::from_str(lines.next().unwrap_or("{}"))
+        .map_err(|e| NineSError::Other(format!("snapshot header: {}", e)))?;
+    if header["format"] != SNAPSHOT_FORMAT {
+        return Err(NineSError::Other(format!("{}: not a snapshot", path.as_ref().display())));
+    }
+    let mut out = Snapshot::new();
+    for line in lines {
+        let scroll: Scroll = serde_json::from_str(line).map_err(|e| NineSError::Other(format!("snapshot scroll: {}", e)))?;
+        out.insert(scroll.key.clone(), scroll);
+    }
+    Ok(out)
+}
@@ -0,0 +1,279 @@
+//! Testing - harness for downstream integration tests
+//!
+//! ```ignore
+//! use beenode::testing::{MockEffectHandler, TestNode};
+//!
+//! let node = TestNode::new("my-app").unwrap();
+//! let send = MockEffectHandler::new("/external/bitcoin").respond("/external/bitcoin/send", json!({"txid": "ab"}));
+//! let worker = node.effect_worker().unwrap().add_handler(Box::new(send.clone()));
+//! tokio::spawn(async move { worker.run().await });
+//! node.put("/wallet/send", json!({...})).unwrap();
+//! node.clock(ClockConfig::fast_test()).unwrap().advance(10);
+//! assert_eq!(send.calls().len(), 1);
+//! ```
+//!
+//! `MockNode` is an in-memory `NodeApi` for code that takes
+//! `Arc<dyn NodeApi>` (the HTTP router, services built on the verbs): no store,
+//! no directory, no lock.
+//!
+//! 9S stores are file-backed, so a `TestNode` owns a throwaway directory and
+//! points its config there with `NodeConfig::with_root`: test nodes run in
+//! parallel and never see each other's data, and callers need no tempdirs,
+//! `NINE_S_ROOT` or env locks of their own.
+
+use crate::clock::{ClockConfig, PulseScroll, UiClock};
+use crate::core::api::NodeApi;
+use crate::core::paths::{clock as clock_paths, origin};
+use crate::mind::{EffectHandler, EffectWorker};
+use crate::node::{Node, NodeConfig};
+use async_trait::async_trait;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+
+/// A `Node` over a private, self-deleting data root. Derefs to `Node`.
+pub struct TestNode {
+    node: Node,
+    config: NodeConfig,
+    dir: PathBuf,
+}
+
+impl TestNode {
+    pub fn new(app: &str) -> NineSResult<Self> {
+        Self::from_config(NodeConfig::new(app))
+    }
+
+    /// `config` rooted in a fresh directory (any root it names is replaced)
+    pub fn from_config(config: NodeConfig) -> NineSResult<Self> {
+        let dir = std::env::temp_dir().join(format!("beenode-test-{}-{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed)));
+        std::fs::create_dir_all(&dir).map_err(|e| NineSError::Other(format!("mkdir: {}", e)))?;
+        let config = config.with_root(&dir);
+        match Node::from_config(config.clone()) {
+            Ok(node) => Ok(Self { node, config, dir }),
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                Err(e)
+            }
+        }
+    }
+
+    /// Data root of this node
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// Raw store for this node's app (bypasses namespaces and the lock)
+    pub fn store(&self) -> NineSResult<Store> {
+        self.config.open_store()
+    }
+
+    /// Effect worker over this node's store; add handlers and `run()` it
+    pub fn effect_worker(&self) -> NineSResult<EffectWorker> {
+        Ok(EffectWorker::new(self.store()?))
+    }
+
+    /// Clock that only ticks when told to
+    pub fn clock(&self, config: ClockConfig) -> NineSResult<ManualClock> {
+        ManualClock::new(self.store()?, config)
+    }
+}
+
+impl std::ops::Deref for TestNode {
+    type Target = Node;
+    fn deref(&self) -> &Node { &self.node }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        let _ = self.node.close();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Effect handler that records every effect and answers with canned results.
+///
+/// Clones share their recordings, so keep one and hand another to the worker.
+#[derive(Clone)]
+pub struct MockEffectHandler {
+    watches: String,
+    calls: Arc<Mutex<Vec<Scroll>>>,
+    responses: Arc<Mutex<Vec<(String, Result<Value, String>)>>>,
+    fallback: Value,
+}
+
+impl MockEffectHandler {
+    /// Answers `{"ok": true}` for anything under `watches` until told otherwise
+    pub fn new(watches: impl Into<String>) -> Self {
+        Self { watches: watches.into(), calls: Arc::default(), responses: Arc::default(), fallback: json!({"ok": true}) }
+    }
+
+    /// Answer effects whose path starts with `prefix` (most specific prefix wins)
+    pub fn respond(self, prefix: impl Into<String>, result: Value) -> Self {
+        self.push(prefix.into(), Ok(result))
+    }
+
+    /// Fail effects whose path starts with `prefix`
+    pub fn fail(self, prefix: impl Into<String>, error: impl Into<String>) -> Self {
+        self.push(prefix.into(), Err(error.into()))
+    }
+
+    /// Effects executed so far, in order
+    pub fn calls(&self) -> Vec<Scroll> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// Effects executed under `prefix`
+    pub fn calls_to(&self, prefix: &str) -> Vec<Scroll> {
+        self.calls().into_iter().filter(|s| s.key.starts_with(prefix)).collect()
+    }
+
+    fn push(self, prefix: String, result: Result<Value, String>) -> Self {
+        if let Ok(mut r) = self.responses.lock() {
+            r.push((prefix, result));
+            r.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        }
+        self
+    }
+}
+
+#[async_trait]
+impl EffectHandler for MockEffectHandler {
+    fn watches(&self) -> &str { &self.watches }
+
+    async fn execute(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        if let Ok(mut calls) = self.calls.lock() { calls.push(scroll.clone()); }
+        let canned = self.responses.lock().ok()
+            .and_then(|r| r.iter().find(|(p, _)| scroll.key.starts_with(p.as_str())).map(|(_, v)| v.clone()));
+        match canned {
+            Some(Ok(v)) => Ok(v),
+            Some(Err(e)) => Err(anyhow::anyhow!(e)),
+            None => Ok(self.fallback.clone()),
+        }
+    }
+}
+
+/// Clock driven by the test: ticks and pulses land in the store exactly
+/// like the background clock's, but only when requested.
+pub struct ManualClock {
+    clock: UiClock,
+    store: Store,
+}
+
+impl ManualClock {
+    pub fn new(store: Store, config: ClockConfig) -> NineSResult<Self> {
+        let clock = UiClock::new(config).map_err(|e| NineSError::Other(format!("clock: {:?}", e)))?;
+        Ok(Self { clock, store })
+    }
+
+    /// One tick; returns the pulses that fired
+    pub fn tick(&mut self) -> Vec<String> {
+        self.clock.tick_to_store(&self.store).pulses.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// `n` ticks; returns every pulse fired, in order
+    pub fn advance(&mut self, n: u64) -> Vec<String> {
+        (0..n).flat_map(|_| self.tick()).collect()
+    }
+
+    /// Fire a pulse now, regardless of its period
+    pub fn pulse(&self, name: &str) -> NineSResult<Scroll> {
+        let snapshot = self.clock.snapshot();
+        let data = PulseScroll { name: name.to_string(), tick: snapshot.tick, epoch: snapshot.epoch };
+        self.store.write_scroll(
+            Scroll::new(&format!("{}/{}", clock_paths::PULSES, name), serde_json::to_value(&data).unwrap_or_default())
+                .set_type(clock_paths::PULSE_TYPE)
+                .with_metadata(Metadata::default().with_produced_by(origin::CLOCK)),
+        )
+    }
+}
+
+/// In-memory `NodeApi`: scrolls in a map, watchers fed on every put.
+///
+/// Clones share state. `with_pin` makes it start locked like a PIN-protected
+/// node; while locked every verb fails.
+#[derive(Clone, Default)]
+pub struct MockNode {
+    scrolls: Arc<Mutex<BTreeMap<String, Scroll>>>,
+    watchers: Arc<Mutex<Vec<(WatchPattern, Sender<Scroll>)>>>,
+    pin: Option<String>,
+    locked: Arc<AtomicBool>,
+}
+
+impl MockNode {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn with_pin(mut self, pin: impl Into<String>) -> Self {
+        self.pin = Some(pin.into());
+        self.locked.store(true, Ordering::Relaxed);
+        self
+    }
+
+    /// Every stored scroll, by path
+    pub fn scrolls(&self) -> BTreeMap<String, Scroll> {
+        self.scrolls.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn check_locked(&self) -> NineSResult<()> {
+        if self.locked.load(Ordering::Relaxed) { return Err(NineSError::Other("node is locked".into())); }
+        Ok(())
+    }
+}
+
+impl NodeApi for MockNode {
+    fn get(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        self.check_locked()?;
+        Ok(self.scrolls.lock().ok().and_then(|s| s.get(path).cloned()))
+    }
+
+    fn put(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        self.check_locked()?;
+        let mut scrolls = self.scrolls.lock().map_err(|_| NineSError::Other("mock lock".into()))?;
+        let mut scroll = Scroll::new(path, data);
+        scroll.metadata.version = scrolls.get(path).map_or(1, |s| s.metadata.version + 1);
+        scrolls.insert(path.to_string(), scroll.clone());
+        drop(scrolls);
+        if let Ok(mut watchers) = self.watchers.lock() {
+            watchers.retain(|(pattern, tx)| !pattern.matches(path) || tx.send(scroll.clone()).is_ok());
+        }
+        Ok(scroll)
+    }
+
+    fn all(&self, prefix: &str) -> NineSResult<Vec<String>> {
+        self.check_locked()?;
+        let base = prefix.trim_end_matches('/');
+        Ok(self.scrolls.lock().map(|s| s.keys().filter(|k| k.as_str() == base || k.starts_with(&format!("{}/", base))).cloned().collect()).unwrap_or_default())
+    }
+
+    fn on(&self, pattern: &str) -> NineSResult<Receiver<Scroll>> {
+        self.check_locked()?;
+        let (tx, rx) = mpsc::channel();
+        self.watchers.lock().map_err(|_| NineSError::Other("mock lock".into()))?.push((WatchPattern::parse(pattern)?, tx));
+        Ok(rx)
+    }
+
+    fn close(&self) -> NineSResult<()> {
+        if let Ok(mut watchers) = self.watchers.lock() { watchers.clear(); }
+        Ok(())
+    }
+
+    fn unlock(&self, pin: &str) -> NineSResult<bool> {
+        let ok = self.pin.as_ref().map(|p| p == pin).unwrap_or(true);
+        if ok { self.locked.store(false, Ordering::Relaxed); }
+        Ok(ok)
+    }
+
+    fn lock(&self) -> NineSResult<bool> {
+        if self.pin.is_none() { return Ok(false); }
+        self.locked.store(true, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn is_locked(&self) -> bool { self.locked.load(Ordering::Relaxed) }
+
+    fn is_initialized(&self) -> bool { self.pin.is_some() }
+}
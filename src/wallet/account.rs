@@ -0,0 +1,53 @@
+//! Account - Move to a fresh BIP84 account on the same seed
+//!
+//! `migrate` syncs, sweeps every UTXO to the first receive address of
+//! `m/84'/coin'/{account}'` and records `/wallet/account`
+//! `{account, previous, address, sweep_txid, amount_sat, migrated_at}`.
+//! The open wallet stays on the old account; on the next start the node opens
+//! the recorded one, unless `WalletConfig::account` names an account itself.
+//!
+//! No new mnemonic is involved: a backup of the seed covers every account.
+
+use crate::core::paths::wallet as paths;
+use crate::core::time::now_secs;
+use crate::wallet::bdk::BdkWallet;
+use crate::wallet::pending;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Hardened indexes stop here
+const MAX_ACCOUNT: u64 = 0x7fff_ffff;
+
+/// Account recorded by the last migration
+pub fn stored(store: &Store) -> NineSResult<Option<u32>> {
+    Ok(store.read(paths::ACCOUNT_CONFIG)?.and_then(|s| s.data["account"].as_u64()).map(|a| a as u32))
+}
+
+/// Sweep to `{account}` and record it for the next start
+pub fn migrate(wallet: &BdkWallet, store: &Store, request: &Value) -> NineSResult<Scroll> {
+    let to = request["account"].as_u64().ok_or_else(|| NineSError::Other("no 'account'".into()))?;
+    if to > MAX_ACCOUNT { return Err(NineSError::Other(format!("account must be at most {}", MAX_ACCOUNT))); }
+    let (to, from) = (to as u32, wallet.account_index());
+    if to == from { return Err(NineSError::Other(format!("already on account {}", to))); }
+
+    let address = wallet.account_address(to)?;
+    wallet.sync()?;
+    let b = wallet.balance()?;
+    let (txid, amount_sat) = if b.confirmed + b.trusted_pending + b.untrusted_pending == 0 {
+        (None, 0)
+    } else {
+        let (txid, amt, _) = wallet.sweep(&address, request["fee_rate"].as_f64())?;
+        pending::record(store, &txid, &address, amt, Some("account migration"), None)?;
+        (Some(txid), amt)
+    };
+    store.write_scroll(Scroll::new(paths::ACCOUNT_CONFIG, json!({
+        "account": to,
+        "previous": from,
+        "address": address,
+        "sweep_txid": txid,
+        "amount_sat": amount_sat,
+        "migrated_at": now_secs(),
+        "restart_required": true,
+    })))
+}
@@ -0,0 +1,167 @@
+//! Advice - UTXO consolidation recommendations
+//!
+//! `run` reviews unspent outputs against the backend fee estimate and writes
+//! `/wallet/advice/consolidation` `{action: consolidate/wait/none, message, candidates, ...}`.
+//! With `psbt: true` and an actionable recommendation it also builds an
+//! unsigned consolidation PSBT (`status: awaiting_approval`); nothing is
+//! signed or broadcast until `approve`.
+
+use crate::core::paths::{wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::{BdkWallet, UtxoDetails};
+use crate::wallet::{coins, pending};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// P2WPKH input size
+const INPUT_VBYTES: u64 = 68;
+/// One P2WPKH output plus transaction overhead
+const BASE_VBYTES: u64 = 31 + 11;
+/// Confirmation target for the fee estimate
+const FEE_TARGET_BLOCKS: usize = 6;
+
+/// Consolidate only when at least this many small UTXOs qualify
+pub const DEFAULT_MIN_UTXOS: usize = 10;
+/// UTXOs below this value are consolidation candidates
+pub const DEFAULT_SMALL_SAT: u64 = 100_000;
+/// Consolidate only at or below this fee rate (sat/vB)
+pub const DEFAULT_MAX_FEE_RATE: f64 = 5.0;
+/// Reference rate for "what this costs when fees spike"
+const HIGH_FEE_RATE: f64 = 50.0;
+
+/// Analysis thresholds, overridable per request
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub min_utxos: usize,
+    pub small_sat: u64,
+    pub max_fee_rate: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self { Self { min_utxos: DEFAULT_MIN_UTXOS, small_sat: DEFAULT_SMALL_SAT, max_fee_rate: DEFAULT_MAX_FEE_RATE } }
+}
+
+impl Thresholds {
+    pub fn from_request(data: &Value) -> Self {
+        let d = Self::default();
+        Self {
+            min_utxos: data["min_utxos"].as_u64().map_or(d.min_utxos, |n| n as usize),
+            small_sat: data["small_sat"].as_u64().unwrap_or(d.small_sat),
+            max_fee_rate: data["max_fee_rate"].as_f64().unwrap_or(d.max_fee_rate),
+        }
+    }
+}
+
+/// Pure recommendation over a UTXO set
+pub fn analyze(utxos: &[UtxoDetails], frozen: &[String], fee_rate: f64, t: Thresholds) -> Value {
+    let mut candidates = Vec::new();
+    let mut dust = Vec::new();
+    let mut candidate_sat = 0;
+    for u in utxos {
+        let outpoint = format!("{}:{}", u.txid, u.vout);
+        if frozen.contains(&outpoint) || u.amount_sat >= t.small_sat { continue; }
+        // Costs more to spend than it is worth at today's rate
+        if (u.amount_sat as f64) <= INPUT_VBYTES as f64 * fee_rate {
+            dust.push(outpoint);
+        } else {
+            candidate_sat += u.amount_sat;
+            candidates.push(outpoint);
+        }
+    }
+
+    let vbytes = BASE_VBYTES + INPUT_VBYTES * candidates.len() as u64;
+    let fee_now = (vbytes as f64 * fee_rate).ceil() as u64;
+    let fee_high = (vbytes as f64 * HIGH_FEE_RATE).ceil() as u64;
+    let n = candidates.len();
+    let (action, message) = if n >= t.min_utxos && fee_rate <= t.max_fee_rate {
+        ("consolidate", format!("consolidate {} UTXOs now while fees are {} sat/vB", n, fmt_rate(fee_rate)))
+    } else if n >= t.min_utxos {
+        ("wait", format!("{} small UTXOs; wait for fees at or below {} sat/vB (now {})", n, fmt_rate(t.max_fee_rate), fmt_rate(fee_rate)))
+    } else {
+        ("none", format!("no consolidation needed ({} small UTXOs)", n))
+    };
+
+    json!({
+        "action": action,
+        "message": message,
+        "fee_rate": fee_rate,
+        "utxo_count": utxos.len(),
+        "candidates": candidates,
+        "candidate_sat": candidate_sat,
+        "dust": dust,
+        "frozen": frozen.len(),
+        "estimated_fee_sat": fee_now,
+        "fee_at_high_sat": fee_high,
+        "savings_sat": fee_high.saturating_sub(fee_now),
+    })
+}
+
+/// Analyze the wallet and write `/wallet/advice/consolidation`
+pub fn run(wallet: &BdkWallet, store: &Store, request: &Value) -> NineSResult<Scroll> {
+    let fee_rate = match request["fee_rate"].as_f64() {
+        Some(r) => r,
+        None => wallet.fee_rate(FEE_TARGET_BLOCKS)?,
+    };
+    let frozen = coins::frozen(store)?;
+    let mut advice = analyze(&wallet.list_unspent()?, &frozen, fee_rate, Thresholds::from_request(request));
+    advice["analyzed_at"] = json!(now_secs());
+    advice["status"] = json!("advisory");
+
+    if request["psbt"] == true && advice["action"] == "consolidate" {
+        let outpoints: Vec<String> = serde_json::from_value(advice["candidates"].clone()).unwrap_or_default();
+        let (psbt, fee, destination) = wallet.consolidation_psbt(&outpoints, fee_rate)?;
+        advice["psbt"] = json!(psbt);
+        advice["fee_sat"] = json!(fee);
+        advice["destination"] = json!(destination);
+        advice["status"] = json!("awaiting_approval");
+    }
+    store.write_scroll(Scroll::new(paths::CONSOLIDATION_ADVICE, advice).set_type(types::ADVICE))
+}
+
+/// Sign and broadcast the PSBT awaiting approval
+pub fn approve(wallet: &BdkWallet, store: &Store) -> NineSResult<Scroll> {
+    let mut advice = store.read(paths::CONSOLIDATION_ADVICE)?
+        .ok_or_else(|| NineSError::Other("no consolidation advice".into()))?.data;
+    if advice["status"] != "awaiting_approval" {
+        return Err(NineSError::Other(format!("nothing to approve (status: {})", advice["status"])));
+    }
+    let psbt = advice["psbt"].as_str().ok_or_else(|| NineSError::Other("advice has no psbt".into()))?;
+    let txid = wallet.broadcast_psbt(psbt)?;
+    let destination = advice["destination"].as_str().unwrap_or_default().to_string();
+    let amount = advice["candidate_sat"].as_u64().unwrap_or(0).saturating_sub(advice["fee_sat"].as_u64().unwrap_or(0));
+    pending::record(store, &txid, &destination, amount, Some("consolidation"), None)?;
+    advice["status"] = json!("broadcast");
+    advice["txid"] = json!(txid);
+    advice["approved_at"] = json!(now_secs());
+    store.write_scroll(Scroll::new(paths::CONSOLIDATION_ADVICE, advice).set_type(types::ADVICE))
+}
+
+fn fmt_rate(rate: f64) -> String {
+    if rate.fract() == 0.0 { format!("{}", rate as u64) } else { format!("{:.1}", rate) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(n: u32, amount_sat: u64) -> UtxoDetails {
+        UtxoDetails { txid: "ab".repeat(32), vout: n, amount_sat, address: None, is_change: false }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let mut utxos: Vec<_> = (0..14).map(|i| utxo(i, 20_000)).collect();
+        utxos.push(utxo(14, 100));
+        utxos.push(utxo(15, 5_000_000));
+        let frozen = vec![format!("{}:0", "ab".repeat(32))];
+
+        let advice = analyze(&utxos, &frozen, 2.0, Thresholds::default());
+        assert_eq!(advice["action"], "consolidate");
+        assert_eq!(advice["message"], "consolidate 13 UTXOs now while fees are 2 sat/vB");
+        assert_eq!(advice["dust"].as_array().unwrap().len(), 1);
+
+        assert_eq!(analyze(&utxos, &frozen, 30.0, Thresholds::default())["action"], "wait");
+        assert_eq!(analyze(&utxos[..3], &[], 2.0, Thresholds::default())["action"], "none");
+    }
+}
@@ -33,15 +33,149 @@ pub struct UtxoDetails {
     pub is_change: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct AddressDetails {
+    pub index: u32,
+    pub address: String,
+    pub is_change: bool,
+    pub used: bool,
+    pub balance_sat: u64,
+}
+
+/// Signed send waiting to be broadcast (`BdkWallet::sign_send`)
+#[derive(Debug, Clone)]
+pub struct SignedTx {
+    pub txid: String,
+    pub hex: String,
+    /// Outpoints spent (`txid:vout`)
+    pub inputs: Vec<String>,
+}
+
+/// Signed send that was never broadcast (simulate mode)
+#[derive(Debug, Clone)]
+pub struct SimulatedTx {
+    pub txid: String,
+    pub vsize: u64,
+    pub weight: u64,
+    pub fee_sat: u64,
+    pub hex: String,
+    /// Outpoints spent (`txid:vout`)
+    pub inputs: Vec<String>,
+}
+
+/// How a send picks its inputs (`coin_selection` on `/wallet/send`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoinSelection {
+    /// Branch and bound for a changeless match, else a random draw (BDK's default)
+    #[default]
+    Bnb,
+    LargestFirst,
+    OldestFirst,
+    /// Coins of a single address only, all of them, so a send never links
+    /// two of the wallet's addresses. Fails if no one address can pay.
+    Privacy,
+}
+
+impl CoinSelection {
+    pub fn parse(s: &str) -> NineSResult<Self> {
+        match s {
+            "bnb" => Ok(Self::Bnb),
+            "largest_first" => Ok(Self::LargestFirst),
+            "oldest_first" => Ok(Self::OldestFirst),
+            "privacy" => Ok(Self::Privacy),
+            _ => Err(NineSError::Other(format!("unknown coin_selection '{}' (bnb, largest_first, oldest_first, privacy)", s))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bnb => "bnb",
+            Self::LargestFirst => "largest_first",
+            Self::OldestFirst => "oldest_first",
+            Self::Privacy => "privacy",
+        }
+    }
+}
+
+/// What happens when an address that already has history would be used again
+/// (`WalletConfig::address_reuse`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressReuse {
+    /// Go ahead, but log it and flag it in the response
+    #[default]
+    Warn,
+    /// Fail the request instead
+    Refuse,
+}
+
+impl AddressReuse {
+    pub fn parse(s: &str) -> NineSResult<Self> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "refuse" => Ok(Self::Refuse),
+            _ => Err(NineSError::Other(format!("unknown address_reuse '{}' (warn, refuse)", s))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Refuse => "refuse",
+        }
+    }
+}
+
+/// A revealed address and the transactions that paid to it
+#[derive(Debug, Clone)]
+pub struct AddressUsage {
+    pub index: u32,
+    pub address: String,
+    pub is_change: bool,
+    pub txids: Vec<String>,
+}
+
+/// Rescan progress: `("external" | "internal", address index)` from
+/// Electrum, `("block", height)` from bitcoind
+pub type RescanProgress = std::sync::Arc<dyn Fn(&str, u32) + Send + Sync>;
+
+/// Addresses past the last used one that a full scan checks before stopping
+pub const DEFAULT_STOP_GAP: usize = 20;
+
+/// Addresses past the last revealed one each keychain watches (BDK's default)
+pub const DEFAULT_LOOKAHEAD: u32 = 25;
+
+/// Where and how far a sync looks for history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// First block that can hold wallet history. bitcoind RPC starts scanning
+    /// here; Electrum indexes by script and always sees full history.
+    pub birthday_height: Option<u32>,
+    pub stop_gap: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self { Self { birthday_height: None, stop_gap: DEFAULT_STOP_GAP } }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AccountXpub {
+    pub fingerprint: String,
+    pub path: String,
+    pub xpub: String,
+}
+
 #[cfg(feature = "wallet")]
 mod inner {
     use super::*;
-    use bdk_electrum::{electrum_client::Client, BdkElectrumClient};
+    use crate::wallet::electrum::{ElectrumOptions, ElectrumPool};
+    use crate::wallet::silent;
     use bdk_wallet::{
-        bitcoin::{bip32::Xpriv, Address, Network},
+        bitcoin::{bip32::{DerivationPath, Xpriv, Xpub}, hashes::Hash, secp256k1::{Secp256k1, SecretKey}, Address, Network, OutPoint, Psbt, ScriptBuf, Transaction, TxOut, Txid},
+        coin_selection::CoinSelectionAlgorithm,
+        descriptor::{ExtendedDescriptor, IntoWalletDescriptor, KeyMap},
         file_store::Store as FileStore,
         template::Bip84,
-        ChangeSet, KeychainKind, PersistedWallet, Wallet,
+        ChangeSet, KeychainKind, PersistedWallet, TxBuilder, Wallet,
     };
     use std::path::Path;
     use std::str::FromStr;
@@ -53,9 +187,9 @@ mod inner {
 
     /// Sync backend for blockchain data
     enum SyncBackend {
-        Electrum(BdkElectrumClient<Client>),
+        Electrum(ElectrumPool),
         #[cfg(feature = "bitcoind-rpc")]
-        Rpc { url: String, user: String, pass: String },
+        Rpc { url: String, user: String, pass: String, proxy: Option<String> },
     }
 
     pub struct BdkWallet {
@@ -63,48 +197,77 @@ mod inner {
         db: Mutex<FileStore<ChangeSet>>,
         backend: SyncBackend,
         network: Network,
+        xprv: Xpriv,
+        account: AccountXpub,
+        account_index: u32,
+        scan: Mutex<ScanOptions>,
+        address_reuse: Mutex<AddressReuse>,
+        lookahead: u32,
+        db_path: std::path::PathBuf,
+    }
+
+    /// Where the wallet syncs and broadcasts
+    pub enum Backend {
+        Electrum(ElectrumOptions),
+        /// bitcoind RPC, optionally through a SOCKS5 `proxy` (`host:port`)
+        #[cfg(feature = "bitcoind-rpc")]
+        Rpc { url: String, user: String, pass: String, proxy: Option<String> },
     }
 
     impl BdkWallet {
         /// Create or load wallet from file store with Electrum backend
         pub fn open(seed: &[u8; 64], network: Network, db_path: &Path, electrum_url: Option<&str>) -> NineSResult<Self> {
-            let (wallet, db) = Self::create_wallet(seed, network, db_path)?;
-
-            let url = electrum_url.unwrap_or(Self::default_url(network));
-            let electrum = Client::new(url)
-                .map_err(|e| NineSError::Other(format!("Electrum: {}", e)))?;
+            Self::open_with(seed, network, db_path, ElectrumOptions::new(electrum_url))
+        }
 
-            Ok(Self {
-                wallet: Mutex::new(wallet),
-                db: Mutex::new(db),
-                backend: SyncBackend::Electrum(BdkElectrumClient::new(electrum)),
-                network,
-            })
+        /// Like `open`, with fallback servers and timeouts. Nothing connects
+        /// until the first sync, broadcast or fee estimate.
+        pub fn open_with(seed: &[u8; 64], network: Network, db_path: &Path, electrum: ElectrumOptions) -> NineSResult<Self> {
+            Self::open_backend(seed, network, db_path, Backend::Electrum(electrum), 0, DEFAULT_LOOKAHEAD)
         }
 
         /// Create or load wallet from file store with bitcoind RPC backend
         #[cfg(feature = "bitcoind-rpc")]
         pub fn open_rpc(seed: &[u8; 64], network: Network, db_path: &Path, rpc_url: &str, rpc_user: &str, rpc_pass: &str) -> NineSResult<Self> {
-            let (wallet, db) = Self::create_wallet(seed, network, db_path)?;
+            let backend = Backend::Rpc { url: rpc_url.into(), user: rpc_user.into(), pass: rpc_pass.into(), proxy: None };
+            Self::open_backend(seed, network, db_path, backend, 0, DEFAULT_LOOKAHEAD)
+        }
+
+        /// Create or load wallet from file store. `account` is the BIP84
+        /// `account'` index; `lookahead` is how many addresses past the last
+        /// revealed one each keychain watches.
+        pub fn open_backend(seed: &[u8; 64], network: Network, db_path: &Path, backend: Backend, account: u32, lookahead: u32) -> NineSResult<Self> {
+            let (wallet, db) = Self::create_wallet(seed, network, db_path, account, lookahead)?;
+            let xprv = Self::master(seed, network)?;
+            let account_index = account;
+            let account = Self::account_xpub(&xprv, network, account)?;
+            let backend = match backend {
+                Backend::Electrum(electrum) => SyncBackend::Electrum(ElectrumPool::new(electrum, Self::default_url(network))),
+                #[cfg(feature = "bitcoind-rpc")]
+                Backend::Rpc { url, user, pass, proxy } => SyncBackend::Rpc { url, user, pass, proxy },
+            };
 
             Ok(Self {
                 wallet: Mutex::new(wallet),
                 db: Mutex::new(db),
-                backend: SyncBackend::Rpc {
-                    url: rpc_url.to_string(),
-                    user: rpc_user.to_string(),
-                    pass: rpc_pass.to_string()
-                },
+                backend,
                 network,
+                xprv,
+                account,
+                account_index,
+                scan: Mutex::new(ScanOptions::default()),
+                address_reuse: Mutex::new(AddressReuse::default()),
+                lookahead,
+                db_path: db_path.to_path_buf(),
             })
         }
 
-        fn create_wallet(seed: &[u8; 64], network: Network, db_path: &Path) -> NineSResult<(PW, FileStore<ChangeSet>)> {
+        fn create_wallet(seed: &[u8; 64], network: Network, db_path: &Path, account: u32, lookahead: u32) -> NineSResult<(PW, FileStore<ChangeSet>)> {
             let xprv = Xpriv::new_master(network, seed)
                 .map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))?;
 
-            let ext = Bip84(xprv, KeychainKind::External);
-            let int = Bip84(xprv, KeychainKind::Internal);
+            let ext = Self::descriptor(xprv, network, account, KeychainKind::External)?;
+            let int = Self::descriptor(xprv, network, account, KeychainKind::Internal)?;
 
             // Try to load existing wallet with descriptor validation
             let mut db: FileStore<ChangeSet> = FileStore::load_or_create(MAGIC, db_path)
@@ -114,6 +277,7 @@ mod inner {
             let wallet_opt = Wallet::load()
                 .descriptor(KeychainKind::External, Some(ext.clone()))
                 .descriptor(KeychainKind::Internal, Some(int.clone()))
+                .lookahead(lookahead)
                 .extract_keys()
                 .load_wallet(&mut db)
                 .map_err(|e| NineSError::Other(format!("Load wallet: {}", e)))?;
@@ -128,6 +292,7 @@ mod inner {
                         .map_err(|e| NineSError::Other(format!("FileStore: {}", e)))?.0;
                     let w = Wallet::create(ext, int)
                         .network(network)
+                        .lookahead(lookahead)
                         .create_wallet(&mut db)
                         .map_err(|e| NineSError::Other(format!("Create wallet: {}", e)))?;
                     return Ok((w, db));
@@ -137,6 +302,38 @@ mod inner {
             Ok((wallet, db))
         }
 
+        /// `wpkh(xprv/84'/coin'/account'/branch/*)`. Account 0 goes through the
+        /// `Bip84` template so wallets created before accounts keep loading.
+        fn descriptor(xprv: Xpriv, network: Network, account: u32, keychain: KeychainKind) -> NineSResult<(ExtendedDescriptor, KeyMap)> {
+            let secp = Secp256k1::new();
+            let descriptor = if account == 0 {
+                Bip84(xprv, keychain).into_wallet_descriptor(&secp, network)
+            } else {
+                let branch = if keychain == KeychainKind::Internal { 1 } else { 0 };
+                format!("wpkh({}/84'/{}'/{}'/{}/*)", xprv, Self::coin_type(network), account, branch).into_wallet_descriptor(&secp, network)
+            };
+            descriptor.map_err(|e| NineSError::Other(format!("Descriptor: {}", e)))
+        }
+
+        /// BIP84 account xpub with master fingerprint (public data only)
+        fn account_xpub(xprv: &Xpriv, network: Network, account: u32) -> NineSResult<AccountXpub> {
+            let secp = Secp256k1::new();
+            let path = format!("m/84'/{}'/{}'", Self::coin_type(network), account);
+            let account = xprv.derive_priv(&secp, &DerivationPath::from_str(&path).map_err(|e| NineSError::Other(format!("Path: {}", e)))?)
+                .map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))?;
+            Ok(AccountXpub {
+                fingerprint: xprv.fingerprint(&secp).to_string(),
+                path,
+                xpub: Xpub::from_priv(&secp, &account).to_string(),
+            })
+        }
+
+        fn coin_type(network: Network) -> u32 { if network == Network::Bitcoin { 0 } else { 1 } }
+
+        fn master(seed: &[u8; 64], network: Network) -> NineSResult<Xpriv> {
+            Xpriv::new_master(network, seed).map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))
+        }
+
         fn default_url(network: Network) -> &'static str {
             match network {
                 Network::Bitcoin => "ssl://electrum.blockstream.info:50002",
@@ -182,20 +379,229 @@ mod inner {
             Ok(addr)
         }
 
+        /// Reveal the next unrevealed index, even if earlier addresses are unused
+        pub fn reveal_next(&self, change: bool) -> NineSResult<AddressDetails> {
+            let keychain = if change { KeychainKind::Internal } else { KeychainKind::External };
+            let info = {
+                let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+                wallet.reveal_next_address(keychain)
+            };
+            self.persist()?;
+            Ok(AddressDetails { index: info.index, address: info.address.to_string(), is_change: change, used: false, balance_sat: 0 })
+        }
+
+        /// All revealed receive and change addresses with usage and balance
+        pub fn addresses(&self) -> NineSResult<Vec<AddressDetails>> {
+            let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let utxos: Vec<_> = wallet.list_unspent().collect();
+            let mut out = Vec::new();
+            for keychain in [KeychainKind::External, KeychainKind::Internal] {
+                let Some(last) = wallet.derivation_index(keychain) else { continue };
+                for index in 0..=last {
+                    let balance_sat = utxos.iter()
+                        .filter(|u| u.keychain == keychain && u.derivation_index == index)
+                        .map(|u| u.txout.value.to_sat())
+                        .sum();
+                    out.push(AddressDetails {
+                        index,
+                        address: wallet.peek_address(keychain, index).address.to_string(),
+                        is_change: keychain == KeychainKind::Internal,
+                        used: wallet.spk_index().is_used(keychain, index),
+                        balance_sat,
+                    });
+                }
+            }
+            Ok(out)
+        }
+
+        /// Revealed addresses that have received at least once, with the paying txids
+        pub fn address_usage(&self) -> NineSResult<Vec<AddressUsage>> {
+            let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let mut paid: std::collections::BTreeMap<(KeychainKind, u32), std::collections::BTreeSet<String>> = std::collections::BTreeMap::new();
+            for tx in wallet.transactions() {
+                for out in &tx.tx_node.tx.output {
+                    if let Some(derivation) = wallet.derivation_of_spk(out.script_pubkey.clone()) {
+                        paid.entry(derivation).or_default().insert(tx.tx_node.txid.to_string());
+                    }
+                }
+            }
+            Ok(paid.into_iter().map(|((keychain, index), txids)| AddressUsage {
+                index,
+                address: wallet.peek_address(keychain, index).address.to_string(),
+                is_change: keychain == KeychainKind::Internal,
+                txids: txids.into_iter().collect(),
+            }).collect())
+        }
+
+        /// Public (external, internal) descriptors with key origin and checksum
+        pub fn descriptors(&self) -> NineSResult<(String, String)> {
+            let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            Ok((
+                wallet.public_descriptor(KeychainKind::External).to_string(),
+                wallet.public_descriptor(KeychainKind::Internal).to_string(),
+            ))
+        }
+
+        pub fn xpub(&self) -> AccountXpub { self.account.clone() }
+
+        /// BIP84 `account'` index this wallet was opened on
+        pub fn account_index(&self) -> u32 { self.account_index }
+
+        /// First receive address of another account on the same seed
+        pub fn account_address(&self, account: u32) -> NineSResult<String> {
+            let ext = Self::descriptor(self.xprv, self.network, account, KeychainKind::External)?;
+            let int = Self::descriptor(self.xprv, self.network, account, KeychainKind::Internal)?;
+            let wallet = Wallet::create(ext, int)
+                .network(self.network)
+                .create_wallet_no_persist()
+                .map_err(|e| NineSError::Other(format!("Create wallet: {}", e)))?;
+            Ok(wallet.peek_address(KeychainKind::External, 0).address.to_string())
+        }
+
+        /// BIP322 simple signature with the key behind one of our addresses
+        pub fn sign_message(&self, address: &str, message: &str) -> NineSResult<String> {
+            use bdk_wallet::bitcoin::{bip32::ChildNumber, PrivateKey};
+
+            let addr = Address::from_str(address)
+                .map_err(|e| NineSError::Other(format!("Address: {}", e)))?
+                .require_network(self.network)
+                .map_err(|e| NineSError::Other(format!("Network: {}", e)))?;
+            let (keychain, index) = {
+                let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+                wallet.derivation_of_spk(addr.script_pubkey())
+                    .ok_or_else(|| NineSError::Other("address not in wallet".into()))?
+            };
+
+            let secp = Secp256k1::new();
+            let branch = if keychain == KeychainKind::Internal { 1 } else { 0 };
+            let path = DerivationPath::from_str(&self.account.path).expect("static path")
+                .extend([ChildNumber::from_normal_idx(branch).expect("branch"), ChildNumber::from_normal_idx(index).map_err(|e| NineSError::Other(format!("Index: {}", e)))?]);
+            let child = self.xprv.derive_priv(&secp, &path)
+                .map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))?;
+            let wif = PrivateKey::new(child.private_key, self.network).to_wif();
+            bip322::sign_simple_encoded(address, message, &wif)
+                .map_err(|e| NineSError::Other(format!("Sign: {}", e)))
+        }
+
+        /// Verify a BIP322 simple signature for any address
+        pub fn verify_message(address: &str, message: &str, signature: &str) -> bool {
+            bip322::verify_simple_encoded(address, message, signature).is_ok()
+        }
+
+        pub fn scan(&self) -> ScanOptions { self.scan.lock().map(|s| *s).unwrap_or_default() }
+
+        pub fn set_scan(&self, scan: ScanOptions) {
+            if let Ok(mut s) = self.scan.lock() { *s = scan; }
+        }
+
+        pub fn address_reuse(&self) -> AddressReuse { self.address_reuse.lock().map(|p| *p).unwrap_or_default() }
+
+        pub fn set_address_reuse(&self, policy: AddressReuse) {
+            if let Ok(mut p) = self.address_reuse.lock() { *p = policy; }
+        }
+
+        pub fn lookahead(&self) -> u32 { self.lookahead }
+
+        /// Last used index per keychain `(external, internal)`
+        pub fn last_used(&self) -> NineSResult<(Option<u32>, Option<u32>)> {
+            let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let index = wallet.spk_index();
+            Ok((index.last_used_index(KeychainKind::External), index.last_used_index(KeychainKind::Internal)))
+        }
+
+        /// Full scan that stops after `stop_gap` unused addresses instead of
+        /// the configured gap. Electrum only: RPC sees what the lookahead covers.
+        pub fn scan_with_gap(&self, stop_gap: usize) -> NineSResult<()> {
+            match &self.backend {
+                SyncBackend::Electrum(pool) => self.sync_electrum(pool, stop_gap),
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { .. } => Err(NineSError::Other("gap check needs an Electrum backend".into())),
+            }
+        }
+
         pub fn sync(&self) -> NineSResult<()> {
             match &self.backend {
-                SyncBackend::Electrum(client) => self.sync_electrum(client),
+                SyncBackend::Electrum(pool) => self.sync_electrum(pool, self.scan().stop_gap),
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { url, user, pass, proxy } => self.sync_rpc(&rpc_client(url, user, pass, proxy.as_deref())?, self.scan().birthday_height.unwrap_or(0), None),
+            }
+        }
+
+        /// Forget every transaction and checkpoint and scan again: bitcoind
+        /// from `from_height` (else the birthday), Electrum the full script
+        /// history. Revealed addresses stay revealed.
+        #[cfg_attr(not(feature = "bitcoind-rpc"), allow(unused_variables))]
+        pub fn rescan(&self, from_height: Option<u32>, progress: RescanProgress) -> NineSResult<()> {
+            self.reset_chain()?;
+            match &self.backend {
+                SyncBackend::Electrum(pool) => {
+                    {
+                        let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+                        let stop_gap = self.scan().stop_gap;
+                        let update = pool.call("Rescan", |client| {
+                            let progress = progress.clone();
+                            let request = wallet.start_full_scan().inspect(move |keychain, index, _| {
+                                progress(if keychain == KeychainKind::Internal { "internal" } else { "external" }, index)
+                            });
+                            client.full_scan(request, stop_gap, 10, false)
+                        })?;
+                        wallet.apply_update(update).map_err(|e| NineSError::Other(format!("Apply: {}", e)))?;
+                    }
+                    self.persist()
+                }
                 #[cfg(feature = "bitcoind-rpc")]
-                SyncBackend::Rpc { url, user, pass } => self.sync_rpc(url, user, pass),
+                SyncBackend::Rpc { url, user, pass, proxy } => {
+                    let start = from_height.or(self.scan().birthday_height).unwrap_or(0);
+                    self.sync_rpc(&rpc_client(url, user, pass, proxy.as_deref())?, start, Some(&progress))
+                }
+            }
+        }
+
+        /// Replace the wallet with a fresh one from the same descriptors,
+        /// revealed up to the same indexes, in a new file store. The old
+        /// store is kept as `.bak` until the new one is written.
+        fn reset_chain(&self) -> NineSResult<()> {
+            let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let mut db = self.db.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let backup = self.db_path.with_extension("bak");
+            std::fs::rename(&self.db_path, &backup).map_err(|e| NineSError::Other(format!("Rescan: {}", e)))?;
+
+            let fresh = (|| -> NineSResult<(PW, FileStore<ChangeSet>)> {
+                let mut fresh_db = FileStore::load_or_create(MAGIC, &self.db_path)
+                    .map_err(|e| NineSError::Other(format!("FileStore: {}", e)))?.0;
+                let ext = Self::descriptor(self.xprv, self.network, self.account_index, KeychainKind::External)?;
+                let int = Self::descriptor(self.xprv, self.network, self.account_index, KeychainKind::Internal)?;
+                let mut fresh = Wallet::create(ext, int)
+                    .network(self.network)
+                    .lookahead(self.lookahead)
+                    .create_wallet(&mut fresh_db)
+                    .map_err(|e| NineSError::Other(format!("Create wallet: {}", e)))?;
+                for keychain in [KeychainKind::External, KeychainKind::Internal] {
+                    if let Some(index) = wallet.derivation_index(keychain) {
+                        let _ = fresh.reveal_addresses_to(keychain, index).count();
+                    }
+                }
+                fresh.persist(&mut fresh_db).map_err(|e| NineSError::Other(format!("Persist: {}", e)))?;
+                Ok((fresh, fresh_db))
+            })();
+            match fresh {
+                Ok((fresh, fresh_db)) => {
+                    *wallet = fresh;
+                    *db = fresh_db;
+                    let _ = std::fs::remove_file(&backup);
+                    Ok(())
+                }
+                Err(e) => {
+                    let _ = std::fs::rename(&backup, &self.db_path);
+                    Err(e)
+                }
             }
         }
 
-        fn sync_electrum(&self, client: &BdkElectrumClient<Client>) -> NineSResult<()> {
+        fn sync_electrum(&self, pool: &ElectrumPool, stop_gap: usize) -> NineSResult<()> {
             {
                 let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
-                let request = wallet.start_full_scan();
-                let update = client.full_scan(request, 10, 10, false)
-                    .map_err(|e| NineSError::Other(format!("Sync: {}", e)))?;
+                let update = pool.call("Sync", |client| client.full_scan(wallet.start_full_scan(), stop_gap, 10, false))?;
                 wallet.apply_update(update).map_err(|e| NineSError::Other(format!("Apply: {}", e)))?;
             }
             self.persist()?;
@@ -203,14 +609,11 @@ mod inner {
         }
 
         #[cfg(feature = "bitcoind-rpc")]
-        fn sync_rpc(&self, url: &str, user: &str, pass: &str) -> NineSResult<()> {
+        fn sync_rpc(&self, rpc: &bitcoincore_rpc::Client, start_height: u32, progress: Option<&RescanProgress>) -> NineSResult<()> {
             use bdk_bitcoind_rpc::Emitter;
-            use bitcoincore_rpc::{Auth, Client as RpcClient, RpcApi};
+            use bitcoincore_rpc::RpcApi;
             use bdk_wallet::chain::{BlockId, local_chain::CheckPoint};
 
-            let rpc = RpcClient::new(url, Auth::UserPass(user.to_string(), pass.to_string()))
-                .map_err(|e| NineSError::Other(format!("RPC connect: {}", e)))?;
-
             // Get server's genesis to use as starting checkpoint
             let genesis_hash = rpc.get_block_hash(0)
                 .map_err(|e| NineSError::Other(format!("RPC genesis: {}", e)))?;
@@ -218,7 +621,7 @@ mod inner {
 
             {
                 let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
-                let mut emitter = Emitter::new(&rpc, genesis_cp, 0, std::iter::empty::<std::sync::Arc<bdk_wallet::bitcoin::Transaction>>());
+                let mut emitter = Emitter::new(rpc, genesis_cp, start_height, std::iter::empty::<std::sync::Arc<bdk_wallet::bitcoin::Transaction>>());
 
                 // Fetch blocks until tip
                 loop {
@@ -228,6 +631,7 @@ mod inner {
                             let connected_to = block_event.connected_to();
                             wallet.apply_block_connected_to(&block_event.block, height, connected_to)
                                 .map_err(|e| NineSError::Other(format!("Apply block: {}", e)))?;
+                            if let Some(progress) = progress { progress("block", height); }
                         }
                         Ok(None) => break, // Reached tip
                         Err(e) => return Err(NineSError::Other(format!("RPC block: {}", e))),
@@ -262,45 +666,143 @@ mod inner {
             }).collect())
         }
 
-        pub fn send(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>) -> NineSResult<String> {
-            use bdk_wallet::bitcoin::Amount;
+        /// Confirmation depth of a wallet tx: `None` if unknown, `Some(0)` if in mempool
+        pub fn confirmations(&self, txid: &str) -> NineSResult<Option<u32>> {
+            let txid = bdk_wallet::bitcoin::Txid::from_str(txid)
+                .map_err(|e| NineSError::Other(format!("Txid: {}", e)))?;
+            let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let tip = wallet.latest_checkpoint().height();
+            Ok(wallet.get_tx(txid).map(|tx| match tx.chain_position {
+                bdk_wallet::chain::ChainPosition::Confirmed { anchor, .. } =>
+                    tip.saturating_sub(anchor.block_id.height) + 1,
+                bdk_wallet::chain::ChainPosition::Unconfirmed { .. } => 0,
+            }))
+        }
 
+        /// Build, sign and broadcast a payment. `frozen` outpoints (`txid:vout`) are never spent.
+        /// Returns `(txid, inputs)`.
+        pub fn send(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>, frozen: &[String], selection: CoinSelection) -> NineSResult<(String, Vec<String>)> {
+            let (tx, _) = self.build_signed(to, amount_sat, fee_rate, frozen, selection)?;
+            Ok((self.broadcast(&tx)?, inputs(&tx)))
+        }
+
+        /// Build and sign a payment like `send` without broadcasting it
+        pub fn sign_send(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>, frozen: &[String], selection: CoinSelection) -> NineSResult<SignedTx> {
+            let (tx, _) = self.build_signed(to, amount_sat, fee_rate, frozen, selection)?;
+            Ok(SignedTx {
+                txid: tx.compute_txid().to_string(),
+                hex: bdk_wallet::bitcoin::consensus::encode::serialize_hex(&tx),
+                inputs: inputs(&tx),
+            })
+        }
+
+        /// Broadcast a transaction from `sign_send`. A backend that already
+        /// has it (an earlier broadcast got through) counts as success.
+        pub fn broadcast_hex(&self, hex: &str) -> NineSResult<String> {
+            let tx: bdk_wallet::bitcoin::Transaction = bdk_wallet::bitcoin::consensus::encode::deserialize_hex(hex)
+                .map_err(|e| NineSError::Other(format!("Transaction: {}", e)))?;
+            match self.broadcast(&tx) {
+                Err(e) if e.to_string().contains("already") => Ok(tx.compute_txid().to_string()),
+                sent => sent,
+            }
+        }
+
+        /// Build and sign exactly like `send`, but never broadcast. The
+        /// inputs and change index are released again afterwards.
+        pub fn simulate_send(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>, frozen: &[String], selection: CoinSelection) -> NineSResult<SimulatedTx> {
+            let (tx, fee_sat) = self.build_signed(to, amount_sat, fee_rate, frozen, selection)?;
+            self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?.cancel_tx(&tx);
+            Ok(SimulatedTx {
+                txid: tx.compute_txid().to_string(),
+                vsize: tx.vsize() as u64,
+                weight: tx.weight().to_wu(),
+                fee_sat,
+                hex: bdk_wallet::bitcoin::consensus::encode::serialize_hex(&tx),
+                inputs: inputs(&tx),
+            })
+        }
+
+        /// Spend every UTXO, frozen ones included, to `to` with no change
+        /// output. Returns `(txid, amount_sat, fee_sat)`.
+        pub fn sweep(&self, to: &str, fee_rate: Option<f64>) -> NineSResult<(String, u64, u64)> {
             let address = Address::from_str(to)
                 .map_err(|e| NineSError::Other(format!("Address: {}", e)))?
                 .require_network(self.network)
                 .map_err(|e| NineSError::Other(format!("Network: {}", e)))?;
 
-            let tx = {
+            let (tx, fee) = {
                 let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
                 let mut builder = wallet.build_tx();
-                builder.add_recipient(address.script_pubkey(), Amount::from_sat(amount_sat));
+                builder.drain_wallet().drain_to(address.script_pubkey());
                 if let Some(rate) = fee_rate {
-                    builder.fee_rate(bdk_wallet::bitcoin::FeeRate::from_sat_per_vb(rate as u64).unwrap());
+                    builder.fee_rate(sat_per_vb(rate as u64)?);
                 }
-
                 let mut psbt = builder.finish().map_err(|e| NineSError::Other(format!("Build: {}", e)))?;
+                let fee = psbt.fee().map_err(|e| NineSError::Other(format!("Calc: {}", e)))?.to_sat();
                 #[allow(deprecated)]
                 wallet.sign(&mut psbt, bdk_wallet::SignOptions::default())
                     .map_err(|e| NineSError::Other(format!("Sign: {}", e)))?;
-
-                psbt.extract_tx().map_err(|e| NineSError::Other(format!("Extract: {}", e)))?
+                (psbt.extract_tx().map_err(|e| NineSError::Other(format!("Extract: {}", e)))?, fee)
             };
+            let amount: u64 = tx.output.iter().map(|o| o.value.to_sat()).sum();
+            Ok((self.broadcast(&tx)?, amount, fee))
+        }
 
-            let txid = tx.compute_txid();
+        /// First receive address of the wallet `seed` would open, without touching a file store
+        pub fn first_address(seed: &[u8; 64], network: Network) -> NineSResult<String> {
+            let xprv = Self::master(seed, network)?;
+            let wallet = Wallet::create(Bip84(xprv, KeychainKind::External), Bip84(xprv, KeychainKind::Internal))
+                .network(network)
+                .create_wallet_no_persist()
+                .map_err(|e| NineSError::Other(format!("Create wallet: {}", e)))?;
+            Ok(wallet.peek_address(KeychainKind::External, 0).address.to_string())
+        }
+
+        fn build_signed(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>, frozen: &[String], selection: CoinSelection) -> NineSResult<(bdk_wallet::bitcoin::Transaction, u64)> {
+            let (script, code) = self.recipient(to)?;
+            let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let mut psbt = Self::build_payment(&mut wallet, &script, amount_sat, fee_rate, Self::outpoints(frozen)?, selection)?;
+            let fee = psbt.fee().map_err(|e| NineSError::Other(format!("Calc: {}", e)))?.to_sat();
+            // Change (or a payment to ourselves) landing on an address that already has history
+            for out in &psbt.unsigned_tx.output {
+                let Some((keychain, index)) = wallet.derivation_of_spk(out.script_pubkey.clone()) else { continue };
+                if !wallet.spk_index().is_used(keychain, index) { continue; }
+                let address = wallet.peek_address(keychain, index).address;
+                if self.address_reuse() == AddressReuse::Refuse {
+                    return Err(NineSError::Other(format!("Build: send would reuse address {}", address)));
+                }
+                tracing::warn!("send reuses address {}", address);
+            }
+            if let Some(code) = code {
+                // The real output depends on the inputs the builder picked
+                let keys = self.input_keys(&psbt)?;
+                let outpoints: Vec<_> = psbt.unsigned_tx.input.iter().map(|i| i.previous_output).collect();
+                let output = silent::sender_output(&keys, &outpoints, &code)?;
+                for out in psbt.unsigned_tx.output.iter_mut().filter(|o| o.script_pubkey == script) {
+                    out.script_pubkey = output.clone();
+                }
+            }
+            #[allow(deprecated)]
+            wallet.sign(&mut psbt, bdk_wallet::SignOptions::default())
+                .map_err(|e| NineSError::Other(format!("Sign: {}", e)))?;
 
-            // Broadcast based on backend
+            let tx = psbt.extract_tx().map_err(|e| NineSError::Other(format!("Extract: {}", e)))?;
+            Ok((tx, fee))
+        }
+
+        /// Broadcast based on backend, then persist
+        fn broadcast(&self, tx: &bdk_wallet::bitcoin::Transaction) -> NineSResult<String> {
+            let txid = tx.compute_txid();
             match &self.backend {
-                SyncBackend::Electrum(client) => {
+                SyncBackend::Electrum(pool) => {
                     use bdk_electrum::electrum_client::ElectrumApi;
-                    client.inner.transaction_broadcast(&tx)
-                        .map_err(|e| NineSError::Other(format!("Broadcast: {}", e)))?;
+                    pool.call("Broadcast", |client| client.inner.transaction_broadcast(tx))?;
                 }
                 #[cfg(feature = "bitcoind-rpc")]
-                SyncBackend::Rpc { url, user, pass } => {
-                    use bitcoincore_rpc::{Auth, Client as RpcClient, RpcApi};
-                    let rpc = RpcClient::new(url, Auth::UserPass(user.clone(), pass.clone()))
-                        .map_err(|e| NineSError::Other(format!("RPC connect: {}", e)))?;
-                    rpc.send_raw_transaction(&tx)
+                SyncBackend::Rpc { url, user, pass, proxy } => {
+                    use bitcoincore_rpc::RpcApi;
+                    let rpc = rpc_client(url, user, pass, proxy.as_deref())?;
+                    rpc.send_raw_transaction(tx)
                         .map_err(|e| NineSError::Other(format!("RPC broadcast: {}", e)))?;
                 }
             }
@@ -309,22 +811,236 @@ mod inner {
             Ok(txid.to_string())
         }
 
-        pub fn estimate_fee(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>) -> NineSResult<u64> {
-            use bdk_wallet::bitcoin::Amount;
+        /// Backend fee estimate in sat/vB for confirmation within `target` blocks
+        pub fn fee_rate(&self, target: usize) -> NineSResult<f64> {
+            match &self.backend {
+                SyncBackend::Electrum(pool) => {
+                    use bdk_electrum::electrum_client::ElectrumApi;
+                    let btc_per_kvb = pool.call("Fee estimate", |client| client.inner.estimate_fee(target))?;
+                    if btc_per_kvb <= 0.0 { return Err(NineSError::Other("Fee estimate unavailable".into())); }
+                    Ok(btc_per_kvb * 100_000.0)
+                }
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { url, user, pass, proxy } => {
+                    use bitcoincore_rpc::RpcApi;
+                    let rpc = rpc_client(url, user, pass, proxy.as_deref())?;
+                    let estimate = rpc.estimate_smart_fee(target as u16, None)
+                        .map_err(|e| NineSError::Other(format!("RPC fee estimate: {}", e)))?;
+                    estimate.fee_rate.map(|per_kvb| per_kvb.to_sat() as f64 / 1000.0)
+                        .ok_or_else(|| NineSError::Other("Fee estimate unavailable".into()))
+                }
+            }
+        }
+
+        /// Connection state of the sync backend, for `/wallet/backend`
+        pub fn backend_status(&self) -> serde_json::Value {
+            match &self.backend {
+                SyncBackend::Electrum(pool) => pool.status(),
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { url, proxy, .. } => serde_json::json!({"backend": "rpc", "url": url, "proxy": proxy}),
+            }
+        }
+
+        /// Unsigned PSBT spending exactly `outpoints` to a fresh change address.
+        /// Returns `(psbt_base64, fee_sat, destination)`.
+        pub fn consolidation_psbt(&self, outpoints: &[String], fee_rate: f64) -> NineSResult<(String, u64, String)> {
+            let fee_rate = sat_per_vb(fee_rate.ceil().max(1.0) as u64)?;
+            let (psbt, destination) = {
+                let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+                let destination = wallet.reveal_next_address(KeychainKind::Internal).address;
+                let mut builder = wallet.build_tx();
+                builder.add_utxos(&Self::outpoints(outpoints)?).map_err(|e| NineSError::Other(format!("Utxos: {}", e)))?;
+                builder.manually_selected_only();
+                builder.drain_to(destination.script_pubkey());
+                builder.fee_rate(fee_rate);
+                let psbt = builder.finish().map_err(|e| NineSError::Other(format!("Build: {}", e)))?;
+                (psbt, destination.to_string())
+            };
+            self.persist()?;
+            let fee = psbt.fee().map_err(|e| NineSError::Other(format!("Calc: {}", e)))?.to_sat();
+            Ok((psbt.to_string(), fee, destination))
+        }
+
+        /// Sign and broadcast a PSBT built by this wallet
+        pub fn broadcast_psbt(&self, psbt: &str) -> NineSResult<String> {
+            let mut psbt = bdk_wallet::bitcoin::Psbt::from_str(psbt)
+                .map_err(|e| NineSError::Other(format!("PSBT: {}", e)))?;
+            let tx = {
+                let wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+                #[allow(deprecated)]
+                wallet.sign(&mut psbt, bdk_wallet::SignOptions::default())
+                    .map_err(|e| NineSError::Other(format!("Sign: {}", e)))?;
+                psbt.extract_tx().map_err(|e| NineSError::Other(format!("Extract: {}", e)))?
+            };
+            self.broadcast(&tx)
+        }
+
+        pub fn estimate_fee(&self, to: &str, amount_sat: u64, fee_rate: Option<f64>, frozen: &[String], selection: CoinSelection) -> NineSResult<u64> {
+            let (script, _) = self.recipient(to)?;
+            let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
+            let psbt = Self::build_payment(&mut wallet, &script, amount_sat, fee_rate, Self::outpoints(frozen)?, selection)?;
+            Ok(psbt.fee().map_err(|e| NineSError::Other(format!("Calc: {}", e)))?.to_sat())
+        }
+
+        /// Unsigned payment of `amount_sat` to `script`, inputs picked by `selection`
+        fn build_payment(wallet: &mut PW, script: &ScriptBuf, amount_sat: u64, fee_rate: Option<f64>, unspendable: Vec<OutPoint>, selection: CoinSelection) -> NineSResult<Psbt> {
+            use bdk_wallet::coin_selection::{BranchAndBoundCoinSelection, LargestFirstCoinSelection, OldestFirstCoinSelection};
 
+            match selection {
+                CoinSelection::Bnb => finish(wallet.build_tx().coin_selection(BranchAndBoundCoinSelection::default()), script, amount_sat, fee_rate, unspendable),
+                CoinSelection::LargestFirst => finish(wallet.build_tx().coin_selection(LargestFirstCoinSelection), script, amount_sat, fee_rate, unspendable),
+                CoinSelection::OldestFirst => finish(wallet.build_tx().coin_selection(OldestFirstCoinSelection), script, amount_sat, fee_rate, unspendable),
+                CoinSelection::Privacy => {
+                    // Every coin of one address, trying the smallest holding that covers the amount first
+                    let mut groups: std::collections::HashMap<ScriptBuf, (u64, Vec<OutPoint>)> = std::collections::HashMap::new();
+                    for utxo in wallet.list_unspent().filter(|u| !unspendable.contains(&u.outpoint)) {
+                        let group = groups.entry(utxo.txout.script_pubkey.clone()).or_default();
+                        group.0 += utxo.txout.value.to_sat();
+                        group.1.push(utxo.outpoint);
+                    }
+                    let mut groups: Vec<_> = groups.into_values().filter(|(total, _)| *total >= amount_sat).collect();
+                    groups.sort_by_key(|(total, _)| *total);
+                    let mut last = NineSError::Other(format!("Build: no single address holds {} sat", amount_sat));
+                    for (_, outpoints) in groups {
+                        let mut builder = wallet.build_tx();
+                        builder.add_utxos(&outpoints).map_err(|e| NineSError::Other(format!("Utxos: {}", e)))?;
+                        builder.manually_selected_only();
+                        match finish(builder, script, amount_sat, fee_rate, Vec::new()) {
+                            Ok(psbt) => return Ok(psbt),
+                            Err(e) => last = e,
+                        }
+                    }
+                    Err(last)
+                }
+            }
+        }
+
+        /// Output script for `to`. A silent payment code gets a same-sized
+        /// P2TR placeholder; its real key depends on the inputs.
+        fn recipient(&self, to: &str) -> NineSResult<(ScriptBuf, Option<silent::Code>)> {
+            if silent::is_code(to) {
+                let code = silent::Code::parse(to, self.network)?;
+                return Ok((silent::p2tr(code.spend.x_only_public_key().0), Some(code)));
+            }
             let address = Address::from_str(to)
                 .map_err(|e| NineSError::Other(format!("Address: {}", e)))?
                 .require_network(self.network)
                 .map_err(|e| NineSError::Other(format!("Network: {}", e)))?;
+            Ok((address.script_pubkey(), None))
+        }
 
-            let mut wallet = self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?;
-            let mut builder = wallet.build_tx();
-            builder.add_recipient(address.script_pubkey(), Amount::from_sat(amount_sat));
-            if let Some(rate) = fee_rate {
-                builder.fee_rate(bdk_wallet::bitcoin::FeeRate::from_sat_per_vb(rate as u64).unwrap());
+        /// Private keys of a PSBT's inputs, from their BIP32 origins
+        fn input_keys(&self, psbt: &bdk_wallet::bitcoin::Psbt) -> NineSResult<Vec<SecretKey>> {
+            let secp = Secp256k1::new();
+            psbt.inputs.iter().map(|input| {
+                let (_, (_, path)) = input.bip32_derivation.iter().next()
+                    .ok_or_else(|| NineSError::Other("input without key origin".into()))?;
+                Ok(self.xprv.derive_priv(&secp, path).map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))?.private_key)
+            }).collect()
+        }
+
+        /// BIP352 `(scan, spend)` keys at `m/352'/coin'/account'/{1',0'}/0`
+        pub fn silent_keys(&self) -> NineSResult<(SecretKey, SecretKey)> {
+            let secp = Secp256k1::new();
+            let key = |branch: u32| -> NineSResult<SecretKey> {
+                let path = DerivationPath::from_str(&format!("m/352'/{}'/{}'/{}'/0", Self::coin_type(self.network), self.account_index, branch))
+                    .map_err(|e| NineSError::Other(format!("Path: {}", e)))?;
+                Ok(self.xprv.derive_priv(&secp, &path).map_err(|e| NineSError::Other(format!("Key derivation: {}", e)))?.private_key)
+            };
+            Ok((key(1)?, key(0)?))
+        }
+
+        /// Reusable `sp1...` (`tsp1...` off mainnet) code for this account
+        pub fn silent_code(&self) -> NineSResult<String> {
+            let secp = Secp256k1::new();
+            let (scan, spend) = self.silent_keys()?;
+            Ok(silent::Code { scan: scan.public_key(&secp), spend: spend.public_key(&secp) }.encode(self.network))
+        }
+
+        /// Height of the last synced block
+        pub fn tip_height(&self) -> NineSResult<u32> {
+            Ok(self.wallet.lock().map_err(|_| NineSError::Other("lock".into()))?.latest_checkpoint().height())
+        }
+
+        /// A transaction from the backend with the outputs its inputs spend
+        pub fn tx_with_prevouts(&self, txid: &str) -> NineSResult<(Transaction, Vec<TxOut>)> {
+            let tx = self.fetch_tx(&Txid::from_str(txid).map_err(|e| NineSError::Other(format!("Txid: {}", e)))?)?;
+            let prevouts = self.prevouts(&tx)?;
+            Ok((tx, prevouts))
+        }
+
+        pub fn prevouts(&self, tx: &Transaction) -> NineSResult<Vec<TxOut>> {
+            tx.input.iter().map(|i| {
+                let prev = self.fetch_tx(&i.previous_output.txid)?;
+                prev.output.get(i.previous_output.vout as usize).cloned()
+                    .ok_or_else(|| NineSError::Other(format!("no output {}", i.previous_output)))
+            }).collect()
+        }
+
+        fn fetch_tx(&self, txid: &Txid) -> NineSResult<Transaction> {
+            match &self.backend {
+                SyncBackend::Electrum(pool) => {
+                    use bdk_electrum::electrum_client::ElectrumApi;
+                    pool.call("Transaction", |client| client.inner.transaction_get(txid))
+                }
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { url, user, pass, proxy } => {
+                    use bitcoincore_rpc::RpcApi;
+                    rpc_client(url, user, pass, proxy.as_deref())?.get_raw_transaction(txid, None)
+                        .map_err(|e| NineSError::Other(format!("RPC transaction: {}", e)))
+                }
+            }
+        }
+
+        /// True when `block_txs` works
+        pub fn serves_blocks(&self) -> bool { !matches!(self.backend, SyncBackend::Electrum(_)) }
+
+        /// Transactions of the block at `height`. Electrum serves no blocks.
+        pub fn block_txs(&self, height: u32) -> NineSResult<Vec<Transaction>> {
+            match &self.backend {
+                SyncBackend::Electrum(_) => Err(NineSError::Other("block scanning needs a bitcoind RPC backend".into())),
+                #[cfg(feature = "bitcoind-rpc")]
+                SyncBackend::Rpc { url, user, pass, proxy } => {
+                    use bitcoincore_rpc::RpcApi;
+                    let rpc = rpc_client(url, user, pass, proxy.as_deref())?;
+                    let hash = rpc.get_block_hash(height as u64).map_err(|e| NineSError::Other(format!("RPC block hash: {}", e)))?;
+                    Ok(rpc.get_block(&hash).map_err(|e| NineSError::Other(format!("RPC block: {}", e)))?.txdata)
+                }
             }
-            let psbt = builder.finish().map_err(|e| NineSError::Other(format!("Fee: {}", e)))?;
-            Ok(psbt.fee().map_err(|e| NineSError::Other(format!("Calc: {}", e)))?.to_sat())
+        }
+
+        /// Move a received silent payment output into the wallet: key-path
+        /// spend with `secret` to a fresh change address. Returns `(txid, amount_sat)`.
+        pub fn sweep_silent(&self, outpoint: &str, prevout: TxOut, secret: SecretKey, fee_rate: f64) -> NineSResult<(String, u64)> {
+            use bdk_wallet::bitcoin::{absolute::LockTime, sighash::{Prevouts, SighashCache, TapSighashType}, transaction::Version, Amount, Sequence, TxIn, Witness};
+
+            // 1 P2TR key-path input, 1 P2WPKH output
+            const VSIZE: f64 = 99.0;
+            let fee = (fee_rate * VSIZE).ceil() as u64;
+            let amount = prevout.value.to_sat().checked_sub(fee).filter(|a| *a >= 294)
+                .ok_or_else(|| NineSError::Other(format!("{} is too small to sweep at {} sat/vB", outpoint, fee_rate)))?;
+            let to = self.reveal_next(true)?.address;
+            let script = Address::from_str(&to).map_err(|e| NineSError::Other(format!("Address: {}", e)))?.assume_checked().script_pubkey();
+
+            let mut tx = Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![TxIn { previous_output: Self::outpoints(&[outpoint.to_string()])?[0], sequence: Sequence::ENABLE_RBF_NO_LOCKTIME, ..Default::default() }],
+                output: vec![TxOut { value: Amount::from_sat(amount), script_pubkey: script }],
+            };
+            let sighash = SighashCache::new(&tx).taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), TapSighashType::Default)
+                .map_err(|e| NineSError::Other(format!("Sighash: {}", e)))?;
+            let secp = Secp256k1::new();
+            let keypair = bdk_wallet::bitcoin::secp256k1::Keypair::from_secret_key(&secp, &secret);
+            let sig = secp.sign_schnorr_no_aux_rand(&bdk_wallet::bitcoin::secp256k1::Message::from_digest(sighash.to_byte_array()), &keypair);
+            tx.input[0].witness = Witness::from_slice(&[sig.serialize()]);
+            Ok((self.broadcast(&tx)?, amount))
+        }
+
+        fn outpoints(list: &[String]) -> NineSResult<Vec<bdk_wallet::bitcoin::OutPoint>> {
+            list.iter()
+                .map(|op| bdk_wallet::bitcoin::OutPoint::from_str(op).map_err(|e| NineSError::Other(format!("Outpoint {}: {}", op, e))))
+                .collect()
         }
 
         pub fn list_unspent(&self) -> NineSResult<Vec<UtxoDetails>> {
@@ -343,10 +1059,49 @@ mod inner {
             }).collect())
         }
     }
+
+    fn finish<Cs: CoinSelectionAlgorithm>(mut builder: TxBuilder<'_, Cs>, script: &ScriptBuf, amount_sat: u64, fee_rate: Option<f64>, unspendable: Vec<OutPoint>) -> NineSResult<Psbt> {
+        builder.add_recipient(script.clone(), bdk_wallet::bitcoin::Amount::from_sat(amount_sat));
+        builder.unspendable(unspendable);
+        if let Some(rate) = fee_rate {
+            builder.fee_rate(sat_per_vb(rate as u64)?);
+        }
+        builder.finish().map_err(|e| NineSError::Other(format!("Build: {}", e)))
+    }
+
+    /// `rate` sat/vB as a fee rate; one too large to represent is an error
+    fn sat_per_vb(rate: u64) -> NineSResult<bdk_wallet::bitcoin::FeeRate> {
+        bdk_wallet::bitcoin::FeeRate::from_sat_per_vb(rate).ok_or_else(|| NineSError::Other(format!("fee rate too high: {} sat/vB", rate)))
+    }
+
+    /// Outpoints a transaction spends, as `txid:vout`
+    fn inputs(tx: &Transaction) -> Vec<String> { tx.input.iter().map(|i| i.previous_output.to_string()).collect() }
+
+    /// bitcoind client, through `proxy` (`host:port`) when set. The transport
+    /// wants a socket address, so a proxy hostname (`tor:9050`) is resolved here.
+    #[cfg(feature = "bitcoind-rpc")]
+    fn rpc_client(url: &str, user: &str, pass: &str, proxy: Option<&str>) -> NineSResult<bitcoincore_rpc::Client> {
+        use bitcoincore_rpc::{Auth, Client as RpcClient};
+        use std::net::ToSocketAddrs;
+        let Some(proxy) = proxy else {
+            return RpcClient::new(url, Auth::UserPass(user.to_string(), pass.to_string()))
+                .map_err(|e| NineSError::Other(format!("RPC connect: {}", e)));
+        };
+        let proxy = proxy.to_socket_addrs()
+            .map_err(|e| NineSError::Other(format!("RPC proxy {}: {}", proxy, e)))?
+            .next()
+            .ok_or_else(|| NineSError::Other(format!("RPC proxy {}: no address", proxy)))?;
+        let transport = jsonrpc::simple_http::SimpleHttpTransport::builder()
+            .url(url)
+            .and_then(|b| b.auth(user, Some(pass)).proxy_addr(proxy.to_string()))
+            .map_err(|e| NineSError::Other(format!("RPC proxy: {}", e)))?
+            .build();
+        Ok(RpcClient::from_jsonrpc(jsonrpc::Client::with_transport(transport)))
+    }
 }
 
 #[cfg(feature = "wallet")]
-pub use inner::BdkWallet;
+pub use inner::{Backend, BdkWallet};
 
 #[cfg(not(feature = "wallet"))]
 pub struct BdkWallet;
@@ -356,9 +1111,35 @@ impl BdkWallet {
     pub fn balance(&self) -> NineSResult<WalletBalance> { Ok(WalletBalance::default()) }
     pub fn receive_address(&self) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
     pub fn new_address(&self) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
+    pub fn reveal_next(&self, _: bool) -> NineSResult<AddressDetails> { Err(NineSError::Other("No wallet".into())) }
+    pub fn addresses(&self) -> NineSResult<Vec<AddressDetails>> { Ok(vec![]) }
+    pub fn descriptors(&self) -> NineSResult<(String, String)> { Err(NineSError::Other("No wallet".into())) }
+    pub fn xpub(&self) -> AccountXpub { AccountXpub::default() }
+    pub fn account_index(&self) -> u32 { 0 }
+    pub fn account_address(&self, _: u32) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
+    pub fn sign_message(&self, _: &str, _: &str) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
+    pub fn verify_message(_: &str, _: &str, _: &str) -> bool { false }
     pub fn sync(&self) -> NineSResult<()> { Err(NineSError::Other("No wallet".into())) }
     pub fn transactions(&self, _: usize) -> NineSResult<Vec<TransactionDetails>> { Ok(vec![]) }
-    pub fn send(&self, _: &str, _: u64, _: Option<f64>) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
-    pub fn estimate_fee(&self, _: &str, _: u64, _: Option<f64>) -> NineSResult<u64> { Err(NineSError::Other("No wallet".into())) }
+    pub fn confirmations(&self, _: &str) -> NineSResult<Option<u32>> { Ok(None) }
+    pub fn send(&self, _: &str, _: u64, _: Option<f64>, _: &[String], _: CoinSelection) -> NineSResult<(String, Vec<String>)> { Err(NineSError::Other("No wallet".into())) }
+    pub fn simulate_send(&self, _: &str, _: u64, _: Option<f64>, _: &[String], _: CoinSelection) -> NineSResult<SimulatedTx> { Err(NineSError::Other("No wallet".into())) }
+    pub fn sign_send(&self, _: &str, _: u64, _: Option<f64>, _: &[String], _: CoinSelection) -> NineSResult<SignedTx> { Err(NineSError::Other("No wallet".into())) }
+    pub fn broadcast_hex(&self, _: &str) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
+    pub fn sweep(&self, _: &str, _: Option<f64>) -> NineSResult<(String, u64, u64)> { Err(NineSError::Other("No wallet".into())) }
+    pub fn estimate_fee(&self, _: &str, _: u64, _: Option<f64>, _: &[String], _: CoinSelection) -> NineSResult<u64> { Err(NineSError::Other("No wallet".into())) }
     pub fn list_unspent(&self) -> NineSResult<Vec<UtxoDetails>> { Ok(vec![]) }
+    pub fn fee_rate(&self, _: usize) -> NineSResult<f64> { Err(NineSError::Other("No wallet".into())) }
+    pub fn consolidation_psbt(&self, _: &[String], _: f64) -> NineSResult<(String, u64, String)> { Err(NineSError::Other("No wallet".into())) }
+    pub fn broadcast_psbt(&self, _: &str) -> NineSResult<String> { Err(NineSError::Other("No wallet".into())) }
+    pub fn backend_status(&self) -> serde_json::Value { serde_json::Value::Null }
+    pub fn scan(&self) -> ScanOptions { ScanOptions::default() }
+    pub fn set_scan(&self, _: ScanOptions) {}
+    pub fn address_reuse(&self) -> AddressReuse { AddressReuse::default() }
+    pub fn set_address_reuse(&self, _: AddressReuse) {}
+    pub fn address_usage(&self) -> NineSResult<Vec<AddressUsage>> { Ok(vec![]) }
+    pub fn lookahead(&self) -> u32 { DEFAULT_LOOKAHEAD }
+    pub fn last_used(&self) -> NineSResult<(Option<u32>, Option<u32>)> { Ok((None, None)) }
+    pub fn scan_with_gap(&self, _: usize) -> NineSResult<()> { Err(NineSError::Other("No wallet".into())) }
+    pub fn rescan(&self, _: Option<u32>, _: RescanProgress) -> NineSResult<()> { Err(NineSError::Other("No wallet".into())) }
 }
@@ -1,33 +1,78 @@
 //! BitcoinEffectHandler - Async BDK operations for /external/bitcoin/**
+//!
+//! Runs against the node's `LazyWallet`, so a request queued while the
+//! wallet is still opening (or the node is locked) waits for it.
 
 use async_trait::async_trait;
 use nine_s_core::prelude::*;
 use nine_s_store::Store;
 use serde_json::{json, Value};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use crate::mind::EffectHandler;
-use crate::wallet::bdk::BdkWallet;
+use crate::wallet::bdk::CoinSelection;
+use crate::wallet::{advice, coins, gap, invoices, pending, rescan, sends, silent, LazyWallet};
 
 pub struct BitcoinEffectHandler {
-    wallet: Arc<RwLock<Option<BdkWallet>>>,
+    wallet: LazyWallet,
     store: Arc<Store>,
+    confirmations: u32,
+    simulate: bool,
+    coin_selection: CoinSelection,
 }
 
 impl BitcoinEffectHandler {
-    pub fn new(wallet: Arc<RwLock<Option<BdkWallet>>>, store: Arc<Store>) -> Self { Self { wallet, store } }
+    pub fn new(wallet: LazyWallet, store: Arc<Store>) -> Self { Self { wallet, store, confirmations: pending::DEFAULT_CONFIRMATIONS, simulate: false, coin_selection: CoinSelection::default() } }
+    pub fn with_confirmations(mut self, n: u32) -> Self { self.confirmations = n; self }
+    /// Dry-run every send: build and sign, report txid/size/fee, never broadcast
+    pub fn with_simulate(mut self, enabled: bool) -> Self { self.simulate = enabled; self }
+    /// Input selection for sends whose scroll names no `coin_selection`
+    pub fn with_coin_selection(mut self, selection: CoinSelection) -> Self { self.coin_selection = selection; self }
 
     async fn do_sync(&self) -> anyhow::Result<Value> {
-        let (wallet, store) = (self.wallet.clone(), self.store.clone());
+        let (wallet, store, threshold) = (self.wallet.clone(), self.store.clone(), self.confirmations);
         tokio::task::spawn_blocking(move || -> anyhow::Result<Value> {
-            let mut guard = wallet.write().map_err(|_| anyhow::anyhow!("lock"))?;
-            let w = guard.as_mut().ok_or_else(|| anyhow::anyhow!("no wallet"))?;
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
             w.sync().map_err(|e| anyhow::anyhow!("{}", e))?;
             let b = w.balance().map_err(|e| anyhow::anyhow!("{}", e))?;
             let txs = w.transactions(50).map_err(|e| anyhow::anyhow!("{}", e))?;
-            drop(guard);
+            let settled = pending::refresh(&w, &store, threshold).map_err(|e| anyhow::anyhow!("{}", e))?;
+            let paid = invoices::refresh(&w, &store).map_err(|e| anyhow::anyhow!("{}", e))?;
+            let silent = silent::refresh(&w, &store);
             let data = json!({"confirmed": b.confirmed, "pending": b.trusted_pending + b.untrusted_pending, "immature": b.immature, "total": b.confirmed + b.trusted_pending + b.untrusted_pending});
             store.write_scroll(Scroll { key: "/wallet/balance".into(), type_: "wallet/balance@v1".into(), metadata: Metadata::default().with_produced_by("effects"), data: data.clone() }).map_err(|e| anyhow::anyhow!("{}", e))?;
-            Ok(json!({"synced": true, "balance": data, "tx_count": txs.len()}))
+            Ok(json!({"synced": true, "balance": data, "tx_count": txs.len(), "settled": settled, "paid": paid, "silent": silent}))
+        }).await?
+    }
+
+    async fn do_advice(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let (wallet, store, request) = (self.wallet.clone(), self.store.clone(), scroll.data.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Value> {
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok(advice::run(&w, &store, &request).map_err(|e| anyhow::anyhow!("{}", e))?.data)
+        }).await?
+    }
+
+    async fn do_gap_check(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let (wallet, store, request) = (self.wallet.clone(), self.store.clone(), scroll.data.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Value> {
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok(gap::check(&w, &store, &request).map_err(|e| anyhow::anyhow!("{}", e))?.data)
+        }).await?
+    }
+
+    async fn do_rescan(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let (wallet, store, request) = (self.wallet.clone(), self.store.clone(), scroll.data.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Value> {
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok(rescan::run(&w, &store, &request).map_err(|e| anyhow::anyhow!("{}", e))?.data)
+        }).await?
+    }
+
+    async fn do_silent(&self, scroll: &Scroll) -> anyhow::Result<Value> {
+        let (wallet, store, request) = (self.wallet.clone(), self.store.clone(), scroll.data.clone());
+        tokio::task::spawn_blocking(move || -> anyhow::Result<Value> {
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok(silent::scan(&w, &store, &request).map_err(|e| anyhow::anyhow!("{}", e))?.data)
         }).await?
     }
 
@@ -38,12 +83,29 @@ impl BitcoinEffectHandler {
             .or_else(|| scroll.data.get("amount").and_then(|v| v.as_u64()))
             .ok_or_else(|| anyhow::anyhow!("no 'amount_sat'"))?;
         let fee_rate = scroll.data["fee_rate"].as_f64();
-        let wallet = self.wallet.clone();
-        let txid = tokio::task::spawn_blocking(move || -> anyhow::Result<String> {
-            let mut guard = wallet.write().map_err(|_| anyhow::anyhow!("lock"))?;
-            guard.as_mut().ok_or_else(|| anyhow::anyhow!("no wallet"))?.send(&to, amount, fee_rate).map_err(|e| anyhow::anyhow!("{}", e))
+        let memo = scroll.data["memo"].as_str().map(String::from);
+        let recipient = scroll.data["recipient"].as_str().map(String::from);
+        let selection = match scroll.data["coin_selection"].as_str() {
+            Some(s) => CoinSelection::parse(s).map_err(|e| anyhow::anyhow!("{}", e))?,
+            None => self.coin_selection,
+        };
+        let (wallet, store) = (self.wallet.clone(), self.store.clone());
+        if self.simulate || scroll.data["simulate"] == true {
+            let sim = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+                let frozen = coins::frozen(&store).map_err(|e| anyhow::anyhow!("{}", e))?;
+                w.simulate_send(&to, amount, fee_rate, &frozen, selection).map_err(|e| anyhow::anyhow!("{}", e))
+            }).await??;
+            return Ok(json!({"success": true, "simulated": true, "txid": sim.txid, "vsize": sim.vsize, "weight": sim.weight, "fee_sat": sim.fee_sat, "hex": sim.hex, "to": scroll.data["to"], "amount_sat": amount, "coin_selection": selection.as_str(), "inputs": sim.inputs}));
+        }
+        let key = scroll.data["idempotency_key"].as_str().map(String::from);
+        let (txid, inputs) = tokio::task::spawn_blocking(move || -> anyhow::Result<(String, Vec<String>)> {
+            let w = wallet.handle().map_err(|e| anyhow::anyhow!("{}", e))?;
+            let (txid, inputs) = sends::send(&store, &w, key.as_deref(), &to, amount, fee_rate, selection).map_err(|e| anyhow::anyhow!("{}", e))?;
+            pending::record(&store, &txid, &to, amount, memo.as_deref(), recipient.as_deref()).map_err(|e| anyhow::anyhow!("{}", e))?;
+            Ok((txid, inputs))
         }).await??;
-        Ok(json!({"success": true, "txid": txid, "to": scroll.data["to"], "amount_sat": amount}))
+        Ok(json!({"success": true, "txid": txid, "to": scroll.data["to"], "amount_sat": amount, "coin_selection": selection.as_str(), "inputs": inputs}))
     }
 }
 
@@ -53,6 +115,10 @@ impl EffectHandler for BitcoinEffectHandler {
     async fn execute(&self, scroll: &Scroll) -> anyhow::Result<Value> {
         if scroll.key.contains("/sync/") { self.do_sync().await }
         else if scroll.key.contains("/send/") { self.do_send(scroll).await }
+        else if scroll.key.contains("/advice/") { self.do_advice(scroll).await }
+        else if scroll.key.contains("/gap-check/") { self.do_gap_check(scroll).await }
+        else if scroll.key.contains("/rescan/") { self.do_rescan(scroll).await }
+        else if scroll.key.contains("/silent-payments/") { self.do_silent(scroll).await }
         else { Err(anyhow::anyhow!("Unknown: {}", scroll.key)) }
     }
 }
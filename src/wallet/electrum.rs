@@ -0,0 +1,179 @@
+//! Electrum - Server list with timeouts, fallbacks and reconnects
+//!
+//! `BdkWallet` reaches Electrum through an `ElectrumPool` instead of a single
+//! client opened at startup:
+//!
+//! - The connection is made on first use and kept for later calls.
+//! - Connect and socket reads/writes time out after `timeout_secs`, so a dead
+//!   server fails the call instead of hanging sync.
+//! - A call that fails on the transport drops the connection and is retried
+//!   on the next server (primary first, then fallbacks, wrapping around).
+//! - Once every server has failed in a row, calls fail fast until a backoff
+//!   runs out (2s, doubling, capped at 5 min). Any success resets it.
+//!
+//! With a `proxy` every server is reached over SOCKS5, `.onion` included.
+//!
+//! Errors the server answers with (a rejected broadcast) are returned as is
+//! and don't count against the server.
+//!
+//! `/wallet/backend` reads `status()`.
+
+use bdk_electrum::electrum_client::{Client, ConfigBuilder, Error, Socks5Config};
+use bdk_electrum::BdkElectrumClient;
+use crate::core::time::now_secs;
+use nine_s_core::errors::{NineSError, NineSResult};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Connect/read timeout when none is configured
+pub const DEFAULT_TIMEOUT_SECS: u8 = 30;
+
+const BACKOFF_BASE: Duration = Duration::from_secs(2);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// How to reach Electrum, built like `WalletConfig`
+#[derive(Debug, Clone)]
+pub struct ElectrumOptions {
+    /// Primary server, else the network default
+    pub url: Option<String>,
+    /// Tried in order after the primary
+    pub fallbacks: Vec<String>,
+    /// Applies to connect and to every socket read/write
+    pub timeout_secs: u8,
+    /// Retries inside one request before the server counts as failed
+    pub retry: u8,
+    /// SOCKS5 proxy `host:port`; hostnames are resolved by the proxy
+    pub proxy: Option<String>,
+}
+
+impl Default for ElectrumOptions {
+    fn default() -> Self { Self { url: None, fallbacks: vec![], timeout_secs: DEFAULT_TIMEOUT_SECS, retry: 1, proxy: None } }
+}
+
+impl ElectrumOptions {
+    pub fn new(url: Option<&str>) -> Self {
+        Self { url: url.map(Into::into), ..Default::default() }
+    }
+    pub fn with_fallback(mut self, url: impl Into<String>) -> Self { self.fallbacks.push(url.into()); self }
+    pub fn with_timeout(mut self, secs: u8) -> Self { self.timeout_secs = secs; self }
+    pub fn with_retry(mut self, n: u8) -> Self { self.retry = n; self }
+    pub fn with_proxy(mut self, addr: impl Into<String>) -> Self { self.proxy = Some(addr.into()); self }
+}
+
+type Electrum = BdkElectrumClient<Client>;
+
+#[derive(Default)]
+struct State {
+    current: usize,
+    client: Option<Arc<Electrum>>,
+    connected_at: Option<u64>,
+    last_error: Option<String>,
+    /// Consecutive failures across all servers
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+pub struct ElectrumPool {
+    servers: Vec<String>,
+    timeout_secs: u8,
+    retry: u8,
+    proxy: Option<String>,
+    state: Mutex<State>,
+}
+
+impl ElectrumPool {
+    pub fn new(opts: ElectrumOptions, default_url: &str) -> Self {
+        let mut servers = vec![opts.url.unwrap_or_else(|| default_url.to_string())];
+        servers.extend(opts.fallbacks);
+        Self { servers, timeout_secs: opts.timeout_secs, retry: opts.retry, proxy: opts.proxy, state: Mutex::new(State::default()) }
+    }
+
+    /// Run `f` on a connected server, moving down the list when the transport fails
+    pub fn call<T>(&self, what: &str, f: impl Fn(&Electrum) -> Result<T, Error>) -> NineSResult<T> {
+        if let Some(at) = self.state()?.retry_at {
+            let wait = at.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                return Err(NineSError::Other(format!("{}: electrum unreachable, retrying in {}s", what, wait.as_secs().max(1))));
+            }
+        }
+        let mut last = String::new();
+        for _ in 0..self.servers.len() {
+            let client = match self.client() {
+                Ok(c) => c,
+                Err(e) => { last = e.to_string(); self.failed(&last)?; continue; }
+            };
+            match f(&client) {
+                Ok(v) => { self.succeeded()?; return Ok(v); }
+                Err(e @ Error::Protocol(_)) => return Err(NineSError::Other(format!("{}: {}", what, e))),
+                Err(e) => { last = e.to_string(); self.failed(&last)?; }
+            }
+        }
+        Err(NineSError::Other(format!("{}: {}", what, last)))
+    }
+
+    /// `{backend, servers, current, connected, connected_at, failures, last_error, retry_in_secs, timeout_secs, proxy}`
+    pub fn status(&self) -> Value {
+        let Ok(state) = self.state() else { return json!({"backend": "electrum", "error": "lock"}) };
+        json!({
+            "backend": "electrum",
+            "servers": self.servers,
+            "current": self.servers[state.current],
+            "connected": state.client.is_some(),
+            "connected_at": state.connected_at,
+            "failures": state.failures,
+            "last_error": state.last_error,
+            "retry_in_secs": state.retry_at.map(|at| at.saturating_duration_since(Instant::now()).as_secs()).filter(|s| *s > 0),
+            "timeout_secs": self.timeout_secs,
+            "proxy": self.proxy,
+        })
+    }
+
+    fn state(&self) -> NineSResult<MutexGuard<'_, State>> {
+        self.state.lock().map_err(|_| NineSError::Other("electrum lock".into()))
+    }
+
+    /// The open connection, else a new one to the current server. The state
+    /// lock isn't held while connecting so `status()` stays responsive.
+    fn client(&self) -> Result<Arc<Electrum>, Error> {
+        let url = {
+            let state = self.state.lock().map_err(|_| Error::Message("electrum lock".into()))?;
+            if let Some(ref c) = state.client { return Ok(c.clone()); }
+            self.servers[state.current].clone()
+        };
+        let config = ConfigBuilder::new()
+            .timeout(Some(self.timeout_secs))
+            .retry(self.retry)
+            .socks5(self.proxy.as_deref().map(Socks5Config::new))
+            .build();
+        let client = Arc::new(BdkElectrumClient::new(Client::from_config(&url, config)?));
+        tracing::info!("electrum connected: {}", url);
+        let mut state = self.state.lock().map_err(|_| Error::Message("electrum lock".into()))?;
+        state.client = Some(client.clone());
+        state.connected_at = Some(now_secs());
+        Ok(client)
+    }
+
+    fn succeeded(&self) -> NineSResult<()> {
+        let mut state = self.state()?;
+        state.failures = 0;
+        state.retry_at = None;
+        Ok(())
+    }
+
+    fn failed(&self, error: &str) -> NineSResult<()> {
+        let mut state = self.state()?;
+        tracing::warn!("electrum {} failed: {}", self.servers[state.current], error);
+        state.client = None;
+        state.connected_at = None;
+        state.last_error = Some(error.to_string());
+        state.failures += 1;
+        state.current = (state.current + 1) % self.servers.len();
+        let rounds = state.failures / self.servers.len() as u32;
+        if rounds > 0 {
+            let backoff = BACKOFF_BASE.saturating_mul(1 << (rounds - 1).min(16)).min(BACKOFF_MAX);
+            state.retry_at = Some(Instant::now() + backoff);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! Export - Transaction history for accountants
+//!
+//! `/wallet/export/transactions?format=json|csv|koinly&currency=usd` renders
+//! the confirmed transactions, oldest first. `json` (the default) and `csv`
+//! share the columns `date, txid, received_sat, sent_sat, fee_sat, net_sat,
+//! block_height, price, fiat_value`; `koinly` is Koinly's universal import
+//! layout with BTC amounts.
+//!
+//! `price` is BTC in `currency` on the day of the transaction
+//! (`market::price_at`) and `fiat_value` is the net amount at that price.
+//! Both are blank when no price had been recorded by then.
+//!
+//! The scroll is `{format, currency, count, filename, content_type, content}`:
+//! `content` is the CSV text, or the rows as an array for `json`.
+
+use crate::market;
+use crate::wallet::bdk::{BdkWallet, TransactionDetails};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Output layout of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format { Json, Csv, Koinly }
+
+impl Format {
+    pub fn parse(s: &str) -> NineSResult<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "koinly" => Ok(Self::Koinly),
+            _ => Err(NineSError::Other(format!("unknown format '{}' (json, csv, koinly)", s))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self { Self::Json => "json", Self::Csv => "csv", Self::Koinly => "koinly" }
+    }
+}
+
+const CSV_HEADER: &str = "date,txid,received_sat,sent_sat,fee_sat,net_sat,block_height,price,fiat_value";
+const KOINLY_HEADER: &str = "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash";
+
+/// Export confirmed transactions as described by `query` (`format=..&currency=..`)
+pub fn transactions(wallet: &BdkWallet, store: &Store, query: &str) -> NineSResult<Value> {
+    let format = param(query, "format").map_or(Ok(Format::Json), Format::parse)?;
+    let currency = param(query, "currency").unwrap_or("usd").to_lowercase();
+    let mut txs: Vec<_> = wallet.transactions(usize::MAX)?.into_iter().filter(|tx| tx.confirmed && tx.timestamp.is_some()).collect();
+    txs.sort_by_key(|tx| (tx.timestamp, tx.block_height));
+    let rows = rows(store, &txs, &currency)?;
+
+    let content = match format {
+        Format::Json => json!(rows),
+        Format::Csv => json!(csv(&rows)),
+        Format::Koinly => json!(koinly(&rows, &currency)),
+    };
+    let (extension, content_type) = if format == Format::Json { ("json", "application/json") } else { ("csv", "text/csv") };
+    Ok(json!({
+        "format": format.as_str(),
+        "currency": currency,
+        "count": rows.len(),
+        "filename": format!("transactions-{}.{}", format.as_str(), extension),
+        "content_type": content_type,
+        "content": content,
+    }))
+}
+
+fn rows(store: &Store, txs: &[TransactionDetails], currency: &str) -> NineSResult<Vec<Value>> {
+    let pair = format!("btc-{}", currency);
+    txs.iter().map(|tx| {
+        let at = tx.timestamp.unwrap_or(0);
+        let net = tx.received as i64 - tx.sent as i64;
+        let price = market::price_at(store, &pair, at)?;
+        let fiat = price.map(|p| market::sats_to_fiat(net.unsigned_abs(), p) * net.signum() as f64);
+        Ok(json!({
+            "date": date(at, "%Y-%m-%dT%H:%M:%SZ"),
+            "timestamp": at,
+            "txid": tx.txid,
+            "received_sat": tx.received,
+            "sent_sat": tx.sent,
+            "fee_sat": tx.fee,
+            "net_sat": net,
+            "block_height": tx.block_height,
+            "price": price,
+            "fiat_value": fiat,
+        }))
+    }).collect()
+}
+
+fn csv(rows: &[Value]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    for r in rows {
+        let cells: Vec<String> = ["date", "txid", "received_sat", "sent_sat", "fee_sat", "net_sat", "block_height", "price", "fiat_value"]
+            .iter().map(|c| cell(&r[*c])).collect();
+        out.push('\n');
+        out.push_str(&cells.join(","));
+    }
+    out.push('\n');
+    out
+}
+
+/// Incoming net amounts are receipts; outgoing ones are sends of the amount
+/// minus the fee, with the fee on its own
+fn koinly(rows: &[Value], currency: &str) -> String {
+    let mut out = String::from(KOINLY_HEADER);
+    let currency = currency.to_uppercase();
+    for r in rows {
+        let net = r["net_sat"].as_i64().unwrap_or(0);
+        let fee = if net < 0 { r["fee_sat"].as_u64().unwrap_or(0) } else { 0 };
+        let sent = net.unsigned_abs().saturating_sub(fee);
+        let (sent, received) = if net < 0 { (btc(sent), String::new()) } else { (String::new(), btc(net as u64)) };
+        let (sent_cur, received_cur) = (if sent.is_empty() { "" } else { "BTC" }, if received.is_empty() { "" } else { "BTC" });
+        let fee_amount = if fee > 0 { btc(fee) } else { String::new() };
+        let worth = r["fiat_value"].as_f64().map(|v| format!("{:.2}", v.abs())).unwrap_or_default();
+        let worth_cur = if worth.is_empty() { "" } else { currency.as_str() };
+        out.push('\n');
+        out.push_str(&[
+            date(r["timestamp"].as_u64().unwrap_or(0), "%Y-%m-%d %H:%M UTC"),
+            sent, sent_cur.into(), received, received_cur.into(),
+            fee_amount.clone(), if fee_amount.is_empty() { String::new() } else { "BTC".into() },
+            worth, worth_cur.into(), String::new(), String::new(),
+            r["txid"].as_str().unwrap_or_default().into(),
+        ].join(","));
+    }
+    out.push('\n');
+    out
+}
+
+fn cell(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn btc(sats: u64) -> String { format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000) }
+
+fn date(at: u64, fmt: &str) -> String {
+    chrono::DateTime::from_timestamp(at as i64, 0).map(|t| t.format(fmt).to_string()).unwrap_or_default()
+}
+
+/// Value of `name` in a `a=1&b=2` query
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').filter_map(|kv| kv.split_once('=')).find(|(k, _)| *k == name).map(|(_, v)| v).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_and_koinly_rows() {
+        let rows = vec![
+            json!({"date": "2024-02-15T00:00:00Z", "timestamp": 1_707_955_200u64, "txid": "aa", "received_sat": 150_000, "sent_sat": 0, "fee_sat": null, "net_sat": 150_000, "block_height": 830_000, "price": 50_000.0, "fiat_value": 75.0}),
+            json!({"date": "2024-03-01T12:00:00Z", "timestamp": 1_709_294_400u64, "txid": "bb", "received_sat": 40_000, "sent_sat": 150_000, "fee_sat": 1_000, "net_sat": -110_000, "block_height": 832_000, "price": null, "fiat_value": null}),
+        ];
+        let csv = csv(&rows);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], "2024-02-15T00:00:00Z,aa,150000,0,,150000,830000,50000.0,75.0");
+        assert_eq!(lines[2], "2024-03-01T12:00:00Z,bb,40000,150000,1000,-110000,832000,,");
+
+        let koinly = koinly(&rows, "usd");
+        let lines: Vec<_> = koinly.lines().collect();
+        assert_eq!(lines[1], "2024-02-15 00:00 UTC,,,0.00150000,BTC,,,75.00,USD,,,aa");
+        assert_eq!(lines[2], "2024-03-01 12:00 UTC,0.00109000,BTC,,,0.00001000,BTC,,,,,bb");
+
+        assert_eq!(param("format=csv&currency=eur", "currency"), Some("eur"));
+        assert!(Format::parse("xlsx").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Gap - Look for funds past the stop gap
+//!
+//! A wallet restored elsewhere, or one that handed out many addresses that
+//! were never paid, can hold funds beyond the `stop_gap` unused addresses a
+//! regular sync checks. `check` runs one full scan with a wider gap
+//! (`{stop_gap?}`, default `DEFAULT_CHECK_GAP`) and compares the last used
+//! index per keychain and the balance before and after.
+//!
+//! When the wider scan turns something up, the wallet's stop gap is raised to
+//! the checked gap and persisted at `/wallet/scan`, so later syncs keep
+//! seeing those addresses. The report is written to `/wallet/gap-check`.
+
+use crate::core::paths::{wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::{BdkWallet, ScanOptions};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Gap a check scans when the request doesn't say
+pub const DEFAULT_CHECK_GAP: usize = 100;
+
+/// Apply `scan` to the wallet and keep it at `/wallet/scan`
+pub fn save_scan(wallet: &BdkWallet, store: &Store, scan: ScanOptions) -> NineSResult<Scroll> {
+    wallet.set_scan(scan);
+    store.write_scroll(Scroll::new(paths::SCAN_CONFIG, json!({"birthday_height": scan.birthday_height, "stop_gap": scan.stop_gap})))
+}
+
+/// Scan with a wider gap and report what it found
+pub fn check(wallet: &BdkWallet, store: &Store, request: &Value) -> NineSResult<Scroll> {
+    let mut scan = wallet.scan();
+    let gap = request["stop_gap"].as_u64().map_or(DEFAULT_CHECK_GAP, |n| n as usize);
+    if gap == 0 { return Err(NineSError::Other("stop_gap must be at least 1".into())); }
+    let gap = gap.max(scan.stop_gap);
+
+    let (external, internal) = wallet.last_used()?;
+    let balance = total(wallet)?;
+    wallet.scan_with_gap(gap)?;
+    let (external_after, internal_after) = wallet.last_used()?;
+    let balance_after = total(wallet)?;
+
+    let found = external_after > external || internal_after > internal || balance_after > balance;
+    if found && gap > scan.stop_gap {
+        scan.stop_gap = gap;
+        save_scan(wallet, store, scan)?;
+    }
+    let report = json!({
+        "found": found,
+        "checked_gap": gap,
+        "stop_gap": scan.stop_gap,
+        "external": {"before": external, "after": external_after},
+        "internal": {"before": internal, "after": internal_after},
+        "balance_before": balance,
+        "balance_after": balance_after,
+        "recovered_sat": balance_after.saturating_sub(balance),
+        "checked_at": now_secs(),
+    });
+    store.write_scroll(Scroll::new(paths::GAP_CHECK_RESULT, report).set_type(types::GAP_CHECK))
+}
+
+fn total(wallet: &BdkWallet) -> NineSResult<u64> {
+    let b = wallet.balance()?;
+    Ok(b.confirmed + b.trusted_pending + b.untrusted_pending)
+}
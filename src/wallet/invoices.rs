@@ -0,0 +1,70 @@
+//! Invoices - Payment requests tracked against fresh addresses
+//!
+//! Each invoice gets its own receive address at `/wallet/invoices/{id}`.
+//! With a `payer` pubkey, the invoice is queued on `/external/nostr/dm/{id}`
+//! and sent NIP-44 encrypted to the payer. Every sync checks unpaid invoices;
+//! once the address holds `amount_sat` the invoice turns `paid` and
+//! `/wallet/events/paid/{id}` fires.
+
+use crate::core::paths::{nostr, origin, wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::BdkWallet;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Record a new unpaid invoice; `invoice` carries `{id, address, amount_sat, uri, memo?, payer?}`
+pub fn create(store: &Store, mut invoice: Value) -> NineSResult<Scroll> {
+    let id = invoice["id"].as_str().ok_or_else(|| NineSError::Other("invoice without id".into()))?.to_string();
+    invoice["status"] = json!("unpaid");
+    invoice["created_at"] = json!(now_secs());
+    if let Some(payer) = invoice["payer"].as_str() {
+        if payer.len() != 64 || !payer.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(NineSError::Other("'payer' must be a 64-char hex pubkey".into()));
+        }
+        let content = json!({"key": format!("{}/{}", paths::INVOICES_PREFIX, id), "type": types::INVOICE, "data": invoice});
+        store.write_scroll(Scroll::new(&format!("{}/{}", nostr::EXTERNAL_DM, id), json!({"to": payer, "content": content.to_string()})))?;
+        invoice["shared"] = json!(true);
+    }
+    store.write_scroll(scroll(&format!("{}/{}", paths::INVOICES_PREFIX, id), types::INVOICE, invoice))
+}
+
+/// All invoices, newest first
+pub fn list(store: &Store) -> NineSResult<Vec<Value>> {
+    let mut out = Vec::new();
+    for key in store.list(paths::INVOICES_PREFIX)? {
+        if let Some(s) = store.read(&key)? { out.push(s.data); }
+    }
+    out.sort_by_key(|i| std::cmp::Reverse(i["created_at"].as_u64().unwrap_or(0)));
+    Ok(out)
+}
+
+/// Mark invoices whose address holds the requested amount. Returns ids paid in this pass.
+pub fn refresh(wallet: &BdkWallet, store: &Store) -> NineSResult<Vec<String>> {
+    let mut unpaid = Vec::new();
+    for key in store.list(paths::INVOICES_PREFIX)? {
+        let Some(s) = store.read(&key)? else { continue };
+        if s.data["status"] == "unpaid" { unpaid.push((key, s.data)); }
+    }
+    if unpaid.is_empty() { return Ok(vec![]); }
+
+    let received: HashMap<String, u64> = wallet.addresses()?.into_iter().map(|a| (a.address, a.balance_sat)).collect();
+    let mut paid = Vec::new();
+    for (key, mut data) in unpaid {
+        let at_address = data["address"].as_str().and_then(|a| received.get(a)).copied().unwrap_or(0);
+        if at_address == 0 || at_address < data["amount_sat"].as_u64().unwrap_or(0) { continue; }
+        let id = data["id"].as_str().unwrap_or_default().to_string();
+        data["status"] = json!("paid");
+        data["received_sat"] = json!(at_address);
+        data["paid_at"] = json!(now_secs());
+        store.write_scroll(scroll(&format!("{}/{}", paths::PAID_PREFIX, id), types::PAID, data.clone()))?;
+        store.write_scroll(scroll(&key, types::INVOICE, data))?;
+        paid.push(id);
+    }
+    Ok(paid)
+}
+
+fn scroll(key: &str, type_: &str, data: Value) -> Scroll {
+    Scroll { key: key.into(), type_: type_.into(), metadata: Metadata::default().with_produced_by(origin::EFFECTS), data }
+}
@@ -0,0 +1,156 @@
+//! LazyWallet - `/wallet` that is mounted before the wallet has opened
+//!
+//! Opening BDK (loading and checking the file store) can take seconds. The
+//! node mounts a `LazyWallet` right away and opens the real namespace on a
+//! background thread. Until then `/wallet/status` reads
+//! `{initialized: false, ready: false, state: "opening"}` right away, while
+//! every other path blocks until the open is signalled (up to `OPEN_WAIT`,
+//! then errors with `wallet starting`); a failed open leaves
+//! `state: "failed"` with the error and raises `/sys/alerts/wallet`.
+
+use super::{BdkWallet, Network, WalletNamespace};
+use crate::core::paths::{alerts, wallet as paths};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard};
+use std::time::Duration;
+
+/// How long a non-status call waits for the open before giving up
+pub const OPEN_WAIT: Duration = Duration::from_secs(30);
+
+enum State {
+    Opening,
+    Ready(WalletNamespace),
+    Failed(String),
+}
+
+#[derive(Clone)]
+pub struct LazyWallet {
+    state: Arc<RwLock<State>>,
+    /// Whether the state has left `Opening`, notified when it does
+    settled: Arc<(Mutex<bool>, Condvar)>,
+    network: Network,
+}
+
+impl LazyWallet {
+    pub fn new(network: Network) -> Self {
+        Self { state: Arc::new(RwLock::new(State::Opening)), settled: Arc::new((Mutex::new(false), Condvar::new())), network }
+    }
+
+    /// Open on a background thread with `open`; alerts go to `store`
+    pub fn spawn(&self, store: Store, open: impl FnOnce() -> NineSResult<WalletNamespace> + Send + 'static) {
+        let this = self.clone();
+        std::thread::spawn(move || {
+            let opened = open();
+            if let Err(ref e) = opened {
+                tracing::warn!("wallet unavailable: {}", e);
+                let alert = json!({"active": true, "level": "error", "message": format!("wallet unavailable: {}", e)});
+                let _ = store.write_scroll(Scroll::new(&format!("{}/wallet", alerts::PREFIX), alert).set_type(alerts::TYPE));
+            }
+            this.set(opened);
+        });
+    }
+
+    pub fn set(&self, opened: NineSResult<WalletNamespace>) {
+        if let Ok(mut state) = self.state.write() {
+            *state = match opened { Ok(ns) => State::Ready(ns), Err(e) => State::Failed(e.to_string()) };
+        }
+        self.signal(true);
+    }
+
+    /// Back to `Opening` before the node opens a different wallet here
+    pub(crate) fn reset(&self) {
+        if let Ok(mut state) = self.state.write() { *state = State::Opening; }
+        self.signal(false);
+    }
+
+    fn signal(&self, settled: bool) {
+        let (flag, opened) = &*self.settled;
+        if let Ok(mut flag) = flag.lock() { *flag = settled; }
+        opened.notify_all();
+    }
+
+    /// The open BDK wallet, waiting like any other non-status call
+    pub fn handle(&self) -> NineSResult<Arc<BdkWallet>> {
+        match &*self.settled()? {
+            State::Ready(ns) => Ok(ns.wallet_handle()),
+            State::Opening => Err(NineSError::Other("wallet starting".into())),
+            State::Failed(e) => Err(NineSError::Other(format!("wallet unavailable: {}", e))),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state.read().is_ok_and(|s| matches!(*s, State::Ready(_)))
+    }
+
+    /// The state once it has left `Opening`, or `Opening` after `OPEN_WAIT`.
+    /// Blocks on the open being signalled rather than polling.
+    fn settled(&self) -> NineSResult<RwLockReadGuard<'_, State>> {
+        let (flag, opened) = &*self.settled;
+        let flag = flag.lock().map_err(|_| NineSError::Other("wallet lock".into()))?;
+        drop(opened.wait_timeout_while(flag, OPEN_WAIT, |settled| !*settled).map_err(|_| NineSError::Other("wallet lock".into()))?);
+        self.state.read().map_err(|_| NineSError::Other("wallet lock".into()))
+    }
+
+    fn status(&self, state: &str, error: Option<&str>) -> Scroll {
+        Scroll::new("/wallet/status", json!({"initialized": false, "ready": false, "state": state, "error": error, "network": self.network.as_str()}))
+    }
+}
+
+impl Namespace for LazyWallet {
+    fn read(&self, path: &str) -> NineSResult<Option<Scroll>> {
+        let is_status = matches!(path, paths::STATUS | "" | "/");
+        let state = if is_status { self.state.read().map_err(|_| NineSError::Other("wallet lock".into()))? } else { self.settled()? };
+        match &*state {
+            State::Ready(ns) => {
+                let scroll = ns.read(path)?;
+                if !is_status { return Ok(scroll); }
+                Ok(scroll.map(|mut s| { s.data["ready"] = json!(true); s.data["state"] = json!("ready"); s }))
+            }
+            State::Opening if is_status => Ok(Some(self.status("opening", None))),
+            State::Failed(e) if is_status => Ok(Some(self.status("failed", Some(e)))),
+            State::Opening => Err(NineSError::Other("wallet starting".into())),
+            State::Failed(e) => Err(NineSError::Other(format!("wallet unavailable: {}", e))),
+        }
+    }
+
+    fn write(&self, path: &str, data: Value) -> NineSResult<Scroll> {
+        let state = self.settled()?;
+        match &*state {
+            State::Ready(ns) => ns.write(path, data),
+            State::Opening => Err(NineSError::Other("wallet starting".into())),
+            State::Failed(e) => Err(NineSError::Other(format!("wallet unavailable: {}", e))),
+        }
+    }
+
+    fn list(&self, path: &str) -> NineSResult<Vec<String>> {
+        let state = self.settled()?;
+        match &*state {
+            State::Ready(ns) => ns.list(path),
+            _ => Ok(vec![paths::STATUS.into()]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waiters_wake_when_the_open_settles() {
+        let lazy = LazyWallet::new(Network::Signet);
+        let setter = lazy.clone();
+        let started = std::time::Instant::now();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            setter.set(Err(NineSError::Other("boom".into())));
+        });
+        let err = lazy.handle().err().expect("failed open");
+        assert!(err.to_string().contains("boom"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        lazy.reset();
+        assert_eq!(lazy.read(paths::STATUS).unwrap().unwrap().data["state"], "opening");
+    }
+}
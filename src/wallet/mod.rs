@@ -24,27 +24,115 @@
 //!
 //! # Namespace Paths
 //!
+//! Paths are relative to the mount: `/wallet`, or `/wallet/{name}` for each
+//! wallet in `NodeConfig::with_wallets`.
+//!
 //! | Path | Method | Description |
 //! |------|--------|-------------|
-//! | `/status` | read | `{initialized, network}` |
+//! | `/status` | read | `{initialized, network, ready, state}`; `state` is `opening` until the wallet has opened in the background |
 //! | `/balance` | read | `{confirmed, pending, total}` sats |
 //! | `/address` | read | Next receive address (bech32) |
+//! | `/addresses` | read | Revealed receive/change addresses `{index, used, balance_sat}` |
+//! | `/privacy/report` | read | Addresses paid more than once `{policy, checked, reused: [{address, keychain, index, tx_count, txids}], count}` |
+//! | `/address/new` | write | Force-reveal next index `{change?}` |
 //! | `/network` | read | bitcoin/testnet/signet/regtest |
+//! | `/scan` | read | `{birthday_height, stop_gap, lookahead}` used by sync; the first two persist across restarts |
+//! | `/scan` | write | `{birthday_height?, stop_gap?}` - change and persist |
+//! | `/account` | read | `{account, path, migrated}`; `migrated` is a recorded migration target not yet opened |
+//! | `/account` | write | Migrate `{account, fee_rate?}`: sweep to that account's first address, open it on the next start |
+//! | `/gap-check` | read | Latest report `{found, checked_gap, stop_gap, external, internal, recovered_sat}` |
+//! | `/gap-check` | write | Full scan with a wider gap `{stop_gap?}` (default 100); raises `stop_gap` when funds turn up. `now: false` queues `/external/bitcoin/gap-check/{id}` |
+//! | `/rescan` | read | Progress `{status: scanning, external, internal, height}`, then `{status: done/failed, balance_before, balance_after, tx_count}` |
+//! | `/rescan` | write | Drop cached chain data and scan again `{from_height?}` (bitcoind; Electrum sees full history); labels and the store are kept. `now: false` queues `/external/bitcoin/rescan/{id}` |
+//! | `/backend` | read | Sync backend connection `{backend, current, servers, connected, failures, last_error, retry_in_secs}` |
+//! | `/descriptor` | read | Public descriptors `{external, internal, fingerprint, path}` |
+//! | `/xpub` | read | Account xpub `{xpub, fingerprint, path}` |
 //! | `/transactions` | read | Last 50 transactions |
+//! | `/export/transactions` | read | Confirmed transactions for accounting, `?format=json\|csv\|koinly&currency=usd`, with the fiat value on the day → `{format, count, filename, content}` |
+//! | `/pending` | read | Unsettled sends with confirmation counts |
+//! | `/pending/{txid}` | read | `{txid, confirmations, status}` for one send |
+//! | `/events/confirmed/{txid}` | read | Send that reached the confirmation threshold |
+//! | `/invoices` | read | Invoices, newest first |
+//! | `/invoices` | write | `{amount_sat, memo?, payer?}` → invoice on a fresh address, sent to `payer` over Nostr |
+//! | `/invoices/{id}` | read | `{address, amount_sat, status: unpaid/paid, received_sat?}` |
+//! | `/events/paid/{id}` | read | Invoice that received its amount |
+//! | `/utxos` | read | Unspent outputs with `frozen` and `label` |
+//! | `/utxos/{txid}:{vout}` | read | Coin control entry `{outpoint, frozen, label}` |
+//! | `/utxos/{txid}:{vout}/freeze` | write | `{frozen?}` (default true) - excluded from send and fee-estimate |
+//! | `/utxos/{txid}:{vout}/label` | write | `{label}` - empty clears |
+//! | `/advice/consolidation` | read | Latest recommendation `{action, message, candidates, estimated_fee_sat, status}` |
+//! | `/advice/consolidation` | write | Analyze now `{psbt?, fee_rate?, min_utxos?, small_sat?, max_fee_rate?}`; `now: false` queues `/external/bitcoin/advice/{id}` |
+//! | `/advice/consolidation/approve` | write | Sign and broadcast the PSBT awaiting approval |
+//! | `/receipts/{txid}` | read | Signed receipt for a confirmed send `{txid, amount_sat, to, memo, signer, event}` |
 //! | `/sync` | write | Queue sync → `/external/bitcoin/sync/{id}` |
-//! | `/send` | write | Queue send → `/external/bitcoin/send/{id}` |
+//! | `/send` | write | Queue send → `/external/bitcoin/send/{id}`; `memo`/`recipient` go into the receipt |
+//! | `/send` | write | `idempotency_key` → a repeat returns the first txid instead of sending again |
+//! | `/sends/{key}` | read | `{idempotency_key, status: sending/queued/broadcast/failed, txid, to, amount_sat}` |
+//! | `/send` | write | `to_mobi` instead of `to` → pay a contact by Mobi: their profile's `bitcoin` address, else `{status: resolving}` while `/external/nostr/pay/{id}` asks their node for one |
+//! | `/pay-requests` | read | Address requests from payers parked by `/nostr/pay/poll` `{requests, count}` |
+//! | `/pay-requests/answer` | write | Reveal a fresh address for each pending request from a contact and queue the encrypted reply; others become `ignored` |
+//! | `/send` | write | `simulate: true` (or `WalletConfig::simulate`) → build and sign only `{txid, vsize, fee_sat}`, nothing broadcast |
+//! | `/send` | write | `coin_selection`: `bnb` (default), `largest_first`, `oldest_first` or `privacy` (one address's coins only); the result lists the spent `inputs` |
+//! | `/send` | write | Change (or a self-payment) to an address with history is logged, or refused with `WalletConfig::address_reuse` `refuse`; `/address` and `/receive` add a `warning` (or fail) the same way |
+//! | `/silent-payment-code` | read | BIP352 reusable code `{code: sp1.../tsp1..., scan_pubkey, spend_pubkey, path}`; `/send` accepts it as `to` |
+//! | `/silent-payments` | read | Received silent payment outputs `{outputs, count, next_height}` |
+//! | `/silent-payments/scan` | write | Check `{txid}` for payments to our code (or the next blocks with a bitcoind backend) and sweep them in; `now: false` queues `/external/bitcoin/silent-payments/{id}` |
+//! | `/sweep` | write | Spend every UTXO (frozen too) to `{to, fee_rate?}` → `{status, txid, amount_sat, fee_sat}` |
 //! | `/fee-estimate` | write | Estimate fee (immediate, no effect) |
+//! | `/sign-message` | write | BIP322 sign `{message, address?}` → `{signature}` |
+//! | `/verify-message` | write | BIP322 verify `{address, message, signature}` → `{valid}` |
+//!
+//! # Regtest
+//!
+//! With `bitcoind-rpc`, [`Regtest`] mines blocks and funds addresses on a
+//! local regtest bitcoind (`beenode dev mine <n>`, `beenode dev fund <amount>`).
 
+#[cfg(feature = "wallet")]
+mod account;
+#[cfg(feature = "wallet")]
+mod advice;
 mod bdk;
 #[cfg(feature = "wallet")]
+mod coins;
+#[cfg(feature = "wallet")]
 mod effects;
+#[cfg(feature = "wallet")]
+mod electrum;
+#[cfg(feature = "wallet")]
+mod export;
 mod namespace;
+#[cfg(feature = "wallet")]
+mod gap;
+#[cfg(feature = "wallet")]
+mod invoices;
+#[cfg(feature = "wallet")]
+mod lazy;
+#[cfg(feature = "wallet")]
+mod payto;
+#[cfg(feature = "wallet")]
+mod pending;
+#[cfg(feature = "wallet")]
+mod privacy;
+#[cfg(feature = "wallet")]
+mod rescan;
+#[cfg(feature = "wallet")]
+mod sends;
+#[cfg(feature = "wallet")]
+mod silent;
+#[cfg(feature = "bitcoind-rpc")]
+mod regtest;
 
-pub use bdk::{TransactionDetails, WalletBalance};
+pub use bdk::{AccountXpub, AddressDetails, AddressReuse, AddressUsage, CoinSelection, RescanProgress, ScanOptions, SignedTx, SimulatedTx, TransactionDetails, WalletBalance, DEFAULT_LOOKAHEAD, DEFAULT_STOP_GAP};
 #[cfg(feature = "wallet")]
-pub use bdk::BdkWallet;
+pub use bdk::{Backend, BdkWallet};
 #[cfg(feature = "wallet")]
 pub use effects::BitcoinEffectHandler;
 pub use namespace::Network;
+#[cfg(feature = "bitcoind-rpc")]
+pub use regtest::Regtest;
 #[cfg(feature = "wallet")]
 pub use namespace::WalletNamespace;
+#[cfg(feature = "wallet")]
+pub use lazy::LazyWallet;
+#[cfg(feature = "wallet")]
+pub use electrum::{ElectrumOptions, ElectrumPool};
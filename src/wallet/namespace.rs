@@ -1,12 +1,15 @@
 //! WalletNamespace - Bitcoin wallet via 9S paths. Writes to /external/* trigger effects.
 
 use crate::core::paths::wallet as paths;
+use crate::core::time::now_secs;
 use nine_s_core::prelude::*;
 use serde_json::{json, Value};
 use std::sync::Arc;
 
 #[cfg(feature = "wallet")]
-use crate::wallet::bdk::BdkWallet;
+use crate::wallet::bdk::{AddressReuse, Backend, BdkWallet, CoinSelection};
+#[cfg(feature = "wallet")]
+use crate::wallet::{account, advice, coins, export, gap, invoices, payto, pending, privacy, rescan, sends, silent, ElectrumOptions, ScanOptions, DEFAULT_STOP_GAP};
 #[cfg(feature = "wallet")]
 use nine_s_store::Store;
 
@@ -24,20 +27,73 @@ impl Network {
 }
 
 #[cfg(feature = "wallet")]
-pub struct WalletNamespace { wallet: Arc<BdkWallet>, store: Arc<Store>, network: Network }
+pub struct WalletNamespace { wallet: Arc<BdkWallet>, store: Arc<Store>, network: Network, confirmations: u32, fiat: bool, simulate: bool, coin_selection: CoinSelection }
 
 #[cfg(feature = "wallet")]
 impl WalletNamespace {
     pub fn open(seed: &[u8; 64], store: Arc<Store>, network: Network, db_path: &std::path::Path, electrum_url: Option<&str>) -> NineSResult<Self> {
-        Ok(Self { wallet: Arc::new(BdkWallet::open(seed, network.to_bdk(), db_path, electrum_url)?), store, network })
+        Self::open_with(seed, store, network, db_path, ElectrumOptions::new(electrum_url))
+    }
+
+    /// Electrum with fallback servers and timeouts
+    pub fn open_with(seed: &[u8; 64], store: Arc<Store>, network: Network, db_path: &std::path::Path, electrum: ElectrumOptions) -> NineSResult<Self> {
+        Ok(Self { wallet: Arc::new(BdkWallet::open_with(seed, network.to_bdk(), db_path, electrum)?), store, network, confirmations: pending::DEFAULT_CONFIRMATIONS, fiat: false, simulate: false, coin_selection: CoinSelection::default() })
+    }
+
+    /// Any backend and BIP84 account, watching `lookahead` addresses past the last revealed one
+    pub fn open_backend(seed: &[u8; 64], store: Arc<Store>, network: Network, db_path: &std::path::Path, backend: Backend, account: u32, lookahead: u32) -> NineSResult<Self> {
+        Ok(Self { wallet: Arc::new(BdkWallet::open_backend(seed, network.to_bdk(), db_path, backend, account, lookahead)?), store, network, confirmations: pending::DEFAULT_CONFIRMATIONS, fiat: false, simulate: false, coin_selection: CoinSelection::default() })
     }
 
     #[cfg(feature = "bitcoind-rpc")]
     pub fn open_rpc(seed: &[u8; 64], store: Arc<Store>, network: Network, db_path: &std::path::Path, rpc_url: &str, rpc_user: &str, rpc_pass: &str) -> NineSResult<Self> {
-        Ok(Self { wallet: Arc::new(BdkWallet::open_rpc(seed, network.to_bdk(), db_path, rpc_url, rpc_user, rpc_pass)?), store, network })
+        Ok(Self { wallet: Arc::new(BdkWallet::open_rpc(seed, network.to_bdk(), db_path, rpc_url, rpc_user, rpc_pass)?), store, network, confirmations: pending::DEFAULT_CONFIRMATIONS, fiat: false, simulate: false, coin_selection: CoinSelection::default() })
+    }
+
+    /// Confirmations before a pending send settles
+    pub fn with_confirmations(mut self, n: u32) -> Self { self.confirmations = n; self }
+
+    /// Include `fiat: {usd, eur}` in `/balance`, priced from `/market/price/*`
+    pub fn with_fiat(mut self, enabled: bool) -> Self { self.fiat = enabled; self }
+
+    /// Build and sign every send without broadcasting it
+    pub fn with_simulate(mut self, enabled: bool) -> Self { self.simulate = enabled; self }
+
+    /// Input selection for sends that don't name a `coin_selection`
+    pub fn with_coin_selection(mut self, selection: CoinSelection) -> Self { self.coin_selection = selection; self }
+
+    /// Warn about or refuse handing out, or sending change to, an address with history
+    pub fn with_address_reuse(self, policy: AddressReuse) -> Self { self.wallet.set_address_reuse(policy); self }
+
+    /// Birthday and stop gap: these, else what `/wallet/scan` kept from an
+    /// earlier run, else the defaults. The result is written back.
+    pub fn with_scan(self, birthday_height: Option<u32>, stop_gap: Option<usize>) -> Self {
+        let stored = self.store.read(paths::SCAN_CONFIG).ok().flatten().map(|s| s.data).unwrap_or(Value::Null);
+        let scan = ScanOptions {
+            birthday_height: birthday_height.or(stored["birthday_height"].as_u64().map(|h| h as u32)),
+            stop_gap: stop_gap.or(stored["stop_gap"].as_u64().map(|g| g as usize)).unwrap_or(DEFAULT_STOP_GAP),
+        };
+        if let Err(e) = self.set_scan(scan) { tracing::warn!("wallet scan config: {}", e); }
+        self
     }
 
     pub fn wallet_handle(&self) -> Arc<BdkWallet> { self.wallet.clone() }
+
+    fn set_scan(&self, scan: ScanOptions) -> NineSResult<Scroll> {
+        gap::save_scan(&self.wallet, &self.store, scan)
+    }
+
+    /// `{address}`, plus a `warning` if it already received funds
+    fn hand_out(&self, address: String) -> NineSResult<Value> {
+        let mut data = json!({"address": address});
+        if let Some(warning) = privacy::check_address(&self.wallet, &address)? { data["warning"] = json!(warning); }
+        Ok(data)
+    }
+
+    /// The request's `coin_selection`, else the configured default
+    fn selection(&self, data: &Value) -> NineSResult<CoinSelection> {
+        data["coin_selection"].as_str().map(CoinSelection::parse).unwrap_or(Ok(self.coin_selection))
+    }
 }
 
 #[cfg(feature = "wallet")]
@@ -49,19 +105,49 @@ impl Namespace for WalletNamespace {
                 let b = self.wallet.balance()?;
                 let pending = b.trusted_pending + b.untrusted_pending;
                 let total = b.confirmed + pending;
+                let mut data = json!({
+                    "confirmed": b.confirmed,
+                    "pending": pending,
+                    "immature": b.immature,
+                    "spendable": b.confirmed,
+                    "total": total
+                });
+                if self.fiat {
+                    data["fiat"] = crate::market::fiat_values(&self.store, total)?;
+                }
+                Scroll::new("/wallet/balance", data)
+            }
+            paths::ADDRESS => Scroll::new("/wallet/address", self.hand_out(self.wallet.receive_address()?)?),
+            paths::PRIVACY_REPORT => Scroll::new("/wallet/privacy/report", privacy::report(&self.wallet)?),
+            p if p.split('?').next() == Some(paths::EXPORT_TRANSACTIONS) => {
+                let query = p.split_once('?').map_or("", |(_, q)| q);
+                Scroll::new("/wallet/export/transactions", export::transactions(&self.wallet, &self.store, query)?)
+            }
+            paths::ADDRESSES => {
+                let addrs = self.wallet.addresses()?;
                 Scroll::new(
-                    "/wallet/balance",
+                    "/wallet/addresses",
                     json!({
-                        "confirmed": b.confirmed,
-                        "pending": pending,
-                        "immature": b.immature,
-                        "spendable": b.confirmed,
-                        "total": total
+                        "addresses": addrs.iter().map(|a| json!({
+                            "index": a.index,
+                            "address": a.address,
+                            "keychain": if a.is_change { "internal" } else { "external" },
+                            "used": a.used,
+                            "balance_sat": a.balance_sat
+                        })).collect::<Vec<_>>(),
+                        "count": addrs.len()
                     }),
                 )
             }
-            paths::ADDRESS => Scroll::new("/wallet/address", json!({"address": self.wallet.receive_address()?})),
+            paths::DESCRIPTOR => {
+                let (external, internal) = self.wallet.descriptors()?;
+                let x = self.wallet.xpub();
+                Scroll::new("/wallet/descriptor", json!({"external": external, "internal": internal, "fingerprint": x.fingerprint, "path": x.path, "network": self.network.as_str()}))
+            }
+            paths::XPUB => { let x = self.wallet.xpub(); Scroll::new("/wallet/xpub", json!({"xpub": x.xpub, "fingerprint": x.fingerprint, "path": x.path, "script_type": "p2wpkh"})) }
             paths::NETWORK => Scroll::new("/wallet/network", json!({"network": self.network.as_str()})),
+            paths::BACKEND => Scroll::new("/wallet/backend", self.wallet.backend_status()),
+            paths::SCAN => { let scan = self.wallet.scan(); Scroll::new(paths::SCAN_CONFIG, json!({"birthday_height": scan.birthday_height, "stop_gap": scan.stop_gap, "lookahead": self.wallet.lookahead()})) }
             paths::TRANSACTIONS => {
                 let txs = self.wallet.transactions(50)?;
                 Scroll::new(
@@ -81,7 +167,45 @@ impl Namespace for WalletNamespace {
                     }),
                 )
             }
-            paths::UTXOS => { let utxos = self.wallet.list_unspent()?; let total: u64 = utxos.iter().map(|u| u.amount_sat).sum(); Scroll::new("/wallet/utxos", json!({"utxos": utxos.iter().map(|u| json!({"txid": u.txid, "vout": u.vout, "amount_sat": u.amount_sat, "address": u.address, "is_change": u.is_change})).collect::<Vec<_>>(), "count": utxos.len(), "total_sat": total})) }
+            paths::GAP_CHECK => return self.store.read(paths::GAP_CHECK_RESULT),
+            paths::RESCAN => return self.store.read(paths::RESCAN_STATE),
+            paths::ACCOUNT => {
+                let migrated = account::stored(&self.store)?.filter(|a| *a != self.wallet.account_index());
+                Scroll::new(paths::ACCOUNT_CONFIG, json!({"account": self.wallet.account_index(), "path": self.wallet.xpub().path, "migrated": migrated}))
+            }
+            paths::PENDING => { let txs = pending::list(&self.store)?; Scroll::new("/wallet/pending", json!({"pending": txs, "count": txs.len(), "threshold": self.confirmations})) }
+            paths::RECEIPTS => {
+                let mut receipts = Vec::new();
+                for key in self.store.list(paths::RECEIPTS_PREFIX)? {
+                    if let Some(s) = self.store.read(&key)? { receipts.push(s.data); }
+                }
+                Scroll::new("/wallet/receipts", json!({"receipts": receipts, "count": receipts.len()}))
+            }
+            paths::SILENT_CODE => silent::code(&self.wallet, &self.store, self.network.as_str())?,
+            paths::SILENT_PAYMENTS => Scroll::new("/wallet/silent-payments", silent::summary(&self.store)?),
+            paths::PAY_REQUESTS => { let items = payto::pending(&self.store)?; Scroll::new("/wallet/pay-requests", json!({"requests": items, "count": items.len()})) }
+            paths::INVOICES => { let items = invoices::list(&self.store)?; Scroll::new("/wallet/invoices", json!({"invoices": items, "count": items.len()})) }
+            p if p.starts_with("/pending/") || p.starts_with("/events/") || p.starts_with("/invoices/") || p.starts_with("/receipts/") || p.starts_with("/advice/") || p.starts_with("/sends/") || p.starts_with("/silent-payments/") => return self.store.read(&format!("/wallet{}", p)),
+            paths::UTXOS => {
+                let utxos = self.wallet.list_unspent()?;
+                let total: u64 = utxos.iter().map(|u| u.amount_sat).sum();
+                let mut items = Vec::new();
+                let mut frozen_sat = 0;
+                for u in &utxos {
+                    let outpoint = format!("{}:{}", u.txid, u.vout);
+                    let coin = coins::get(&self.store, &outpoint)?.unwrap_or(Value::Null);
+                    let frozen = coin["frozen"] == true;
+                    if frozen { frozen_sat += u.amount_sat; }
+                    items.push(json!({"txid": u.txid, "vout": u.vout, "outpoint": outpoint, "amount_sat": u.amount_sat, "address": u.address, "is_change": u.is_change, "frozen": frozen, "label": coin["label"]}));
+                }
+                Scroll::new("/wallet/utxos", json!({"utxos": items, "count": utxos.len(), "total_sat": total, "frozen_sat": frozen_sat, "spendable_sat": total - frozen_sat}))
+            }
+            p if p.starts_with("/utxos/") => {
+                let outpoint = &p["/utxos/".len()..];
+                coins::validate(outpoint)?;
+                let data = coins::get(&self.store, outpoint)?.unwrap_or_else(|| json!({"outpoint": outpoint, "frozen": false, "label": null}));
+                Scroll::new(&format!("/wallet{}", p), data)
+            }
             _ => return Ok(None),
         }))
     }
@@ -99,10 +223,16 @@ impl Namespace for WalletNamespace {
                 } else {
                     self.wallet.receive_address()?
                 };
-                Ok(Scroll::new("/wallet/address", json!({"address": address})))
+                Ok(Scroll::new("/wallet/address", self.hand_out(address)?))
+            }
+            paths::ADDRESS_NEW => {
+                let change = data.get("change").and_then(|v| v.as_bool()).unwrap_or(false);
+                let a = self.wallet.reveal_next(change)?;
+                Ok(Scroll::new("/wallet/address/new", json!({"address": a.address, "index": a.index, "keychain": if change { "internal" } else { "external" }})))
             }
             paths::RECEIVE => {
                 let address = self.wallet.receive_address()?;
+                let warning = privacy::check_address(&self.wallet, &address)?;
                 let amount_sat = data.get("amount_sat")
                     .and_then(|v| v.as_u64())
                     .or_else(|| data.get("amount").and_then(|v| v.as_u64()));
@@ -134,36 +264,167 @@ impl Namespace for WalletNamespace {
                         "uri": uri,
                         "amount_sat": amount_sat,
                         "label": label,
-                        "message": message
+                        "message": message,
+                        "warning": warning
                     }),
                 ))
             }
+            paths::INVOICES => {
+                let amount_sat = data["amount_sat"].as_u64().filter(|a| *a > 0)
+                    .ok_or_else(|| NineSError::Other("no 'amount_sat'".into()))?;
+                let memo = data["memo"].as_str();
+                let address = self.wallet.reveal_next(false)?.address;
+                let mut uri = format!("bitcoin:{}?amount={}", address, format_btc_amount(amount_sat));
+                if let Some(memo) = memo {
+                    uri.push_str(&format!("&message={}", percent_encode(memo)));
+                }
+                invoices::create(&self.store, json!({
+                    "id": id,
+                    "address": address,
+                    "amount_sat": amount_sat,
+                    "memo": memo,
+                    "payer": data["payer"],
+                    "uri": uri,
+                }))
+            }
+            paths::ADVICE_CONSOLIDATION => {
+                if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    advice::run(&self.wallet, &self.store, &data)
+                } else {
+                    self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_ADVICE, id), data))?;
+                    Ok(Scroll::new("/wallet/advice/consolidation", json!({"status": "pending", "request_id": id})))
+                }
+            }
+            paths::ADVICE_APPROVE => advice::approve(&self.wallet, &self.store),
+            paths::PAY_ANSWER => payto::answer(&self.wallet, &self.store),
+            paths::ACCOUNT => account::migrate(&self.wallet, &self.store, &data),
+            paths::GAP_CHECK => {
+                if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    gap::check(&self.wallet, &self.store, &data)
+                } else {
+                    self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_GAP_CHECK, id), data))?;
+                    Ok(Scroll::new(paths::GAP_CHECK_RESULT, json!({"status": "pending", "request_id": id})))
+                }
+            }
+            paths::RESCAN => {
+                if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    rescan::run(&self.wallet, &self.store, &data)
+                } else {
+                    self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_RESCAN, id), data))?;
+                    Ok(Scroll::new(paths::RESCAN_STATE, json!({"status": "pending", "request_id": id})))
+                }
+            }
+            paths::SILENT_SCAN => {
+                if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    silent::scan(&self.wallet, &self.store, &data)
+                } else {
+                    self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_SILENT, id), data))?;
+                    Ok(Scroll::new("/wallet/silent-payments/scan", json!({"status": "pending", "request_id": id})))
+                }
+            }
+            p if p.starts_with("/utxos/") && p.ends_with(paths::FREEZE) => {
+                let outpoint = &p["/utxos/".len()..p.len() - paths::FREEZE.len()];
+                coins::set_frozen(&self.store, outpoint, data["frozen"].as_bool().unwrap_or(true))
+            }
+            p if p.starts_with("/utxos/") && p.ends_with(paths::LABEL) => {
+                let outpoint = &p["/utxos/".len()..p.len() - paths::LABEL.len()];
+                coins::set_label(&self.store, outpoint, data["label"].as_str().filter(|l| !l.is_empty()))
+            }
+            paths::SCAN => {
+                let mut scan = self.wallet.scan();
+                if let Some(h) = data.get("birthday_height") { scan.birthday_height = h.as_u64().map(|h| h as u32); }
+                if let Some(gap) = data["stop_gap"].as_u64() {
+                    if gap == 0 { return Err(NineSError::Other("stop_gap must be at least 1".into())); }
+                    scan.stop_gap = gap as usize;
+                }
+                self.set_scan(scan)
+            }
             paths::SYNC => {
                 // Sync now if requested, else queue to effects
                 if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
                     self.wallet.sync()?;
+                    let settled = pending::refresh(&self.wallet, &self.store, self.confirmations)?;
+                    let paid = invoices::refresh(&self.wallet, &self.store)?;
+                    let silent = silent::refresh(&self.wallet, &self.store);
                     let b = self.wallet.balance()?;
-                    Ok(Scroll::new("/wallet/sync", json!({"status": "synced", "confirmed": b.confirmed, "pending": b.trusted_pending + b.untrusted_pending})))
+                    let state = json!({"synced_at": now_secs(), "confirmed": b.confirmed, "pending": b.trusted_pending + b.untrusted_pending});
+                    self.store.write_scroll(Scroll::new(paths::SYNC_STATE, state))?;
+                    Ok(Scroll::new("/wallet/sync", json!({"status": "synced", "confirmed": b.confirmed, "pending": b.trusted_pending + b.untrusted_pending, "settled": settled, "paid": paid, "silent": silent})))
                 } else {
                     self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_SYNC, id), json!({"network": self.network.as_str()})))?;
                     Ok(Scroll::new("/wallet/sync", json!({"status": "pending", "request_id": id})))
                 }
             }
             paths::SEND => {
-                let to = data["to"].as_str().ok_or_else(|| NineSError::Other("no 'to'".into()))?;
+                let selection = self.selection(&data)?;
+                let payee = match data["to_mobi"].as_str() { Some(m) => Some(payto::resolve(&self.store, m)?), None => None };
+                let to = match payee {
+                    Some(ref p) => match p.address {
+                        Some(ref a) => a.as_str(),
+                        // No published address: ask the payee's node for one first
+                        None => return payto::request(&self.store, &id, p, &data, self.simulate || data["simulate"] == true, selection),
+                    },
+                    None => data["to"].as_str().ok_or_else(|| NineSError::Other("no 'to'".into()))?,
+                };
                 let amt = data.get("amount_sat")
                     .and_then(|v| v.as_u64())
                     .or_else(|| data.get("amount").and_then(|v| v.as_u64()))
                     .ok_or_else(|| NineSError::Other("no 'amount_sat'".into()))?;
                 let fee_rate = data["fee_rate"].as_f64();
-                // Execute now by default, queue to effects if now=false
-                if data.get("now").and_then(|v| v.as_bool()).unwrap_or(true) {
-                    let txid = self.wallet.send(to, amt, fee_rate)?;
-                    Ok(Scroll::new("/wallet/send", json!({"status": "broadcast", "txid": txid, "to": to, "amount_sat": amt})))
+                let memo = data["memo"].as_str();
+                let recipient = payee.as_ref().map(|p| p.pubkey.as_str()).or(data["recipient"].as_str());
+                if recipient.is_some_and(|r| r.len() != 64 || !r.chars().all(|c| c.is_ascii_hexdigit())) {
+                    return Err(NineSError::Other("'recipient' must be a 64-char hex pubkey".into()));
+                }
+                let simulate = self.simulate || data["simulate"] == true;
+                let now = data.get("now").and_then(|v| v.as_bool()).unwrap_or(true);
+                // A repeated idempotency_key answers with the first result
+                let key = if simulate { None } else { data["idempotency_key"].as_str() };
+                if let Some(key) = key {
+                    if let Some(prev) = sends::begin(&self.store, key, to, amt)? { return Ok(Scroll::new("/wallet/send", prev)); }
+                }
+                // Execute now by default, queue to effects if now=false; simulate never broadcasts
+                if now && simulate {
+                    let sim = self.wallet.simulate_send(to, amt, fee_rate, &coins::frozen(&self.store)?, selection)?;
+                    Ok(Scroll::new("/wallet/send", json!({"status": "simulated", "txid": sim.txid, "vsize": sim.vsize, "weight": sim.weight, "fee_sat": sim.fee_sat, "hex": sim.hex, "to": to, "amount_sat": amt, "coin_selection": selection.as_str(), "inputs": sim.inputs})))
+                } else if now {
+                    let (txid, inputs) = sends::send(&self.store, &self.wallet, key, to, amt, fee_rate, selection)?;
+                    pending::record(&self.store, &txid, to, amt, memo, recipient)?;
+                    Ok(Scroll::new("/wallet/send", json!({"status": "broadcast", "txid": txid, "to": to, "amount_sat": amt, "idempotency_key": key, "coin_selection": selection.as_str(), "inputs": inputs})))
                 } else {
-                    self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_SEND, id), json!({"to": to, "amount_sat": amt, "fee_rate": fee_rate})))?;
-                    Ok(Scroll::new("/wallet/send", json!({"status": "pending", "request_id": id, "to": to, "amount_sat": amt})))
+                    let queued = self.store.write_scroll(Scroll::new(&format!("{}/{}", paths::EXTERNAL_SEND, id), json!({"to": to, "amount_sat": amt, "fee_rate": fee_rate, "memo": memo, "recipient": recipient, "simulate": simulate, "idempotency_key": key, "coin_selection": selection.as_str()})));
+                    if let (Err(e), Some(key)) = (&queued, key) { sends::fail(&self.store, key, &e.to_string())?; }
+                    queued?;
+                    if let Some(key) = key { sends::queue(&self.store, key, &id)?; }
+                    Ok(Scroll::new("/wallet/send", json!({"status": "pending", "request_id": id, "to": to, "amount_sat": amt, "idempotency_key": key})))
+                }
+            }
+            paths::SWEEP => {
+                // Empty the wallet into `to` (key rotation, compromised seed)
+                let to = data["to"].as_str().ok_or_else(|| NineSError::Other("no 'to'".into()))?;
+                let b = self.wallet.balance()?;
+                if b.confirmed + b.trusted_pending + b.untrusted_pending == 0 {
+                    return Ok(Scroll::new("/wallet/sweep", json!({"status": "empty", "to": to, "amount_sat": 0})));
                 }
+                let (txid, amt, fee_sat) = self.wallet.sweep(to, data["fee_rate"].as_f64())?;
+                pending::record(&self.store, &txid, to, amt, Some("sweep"), None)?;
+                Ok(Scroll::new("/wallet/sweep", json!({"status": "broadcast", "txid": txid, "to": to, "amount_sat": amt, "fee_sat": fee_sat})))
+            }
+            paths::SIGN_MESSAGE => {
+                let message = data["message"].as_str().ok_or_else(|| NineSError::Other("no 'message'".into()))?;
+                let address = match data["address"].as_str() {
+                    Some(a) => a.to_string(),
+                    None => self.wallet.receive_address()?,
+                };
+                let signature = self.wallet.sign_message(&address, message)?;
+                Ok(Scroll::new("/wallet/sign-message", json!({"address": address, "message": message, "signature": signature, "format": "bip322-simple"})))
+            }
+            paths::VERIFY_MESSAGE => {
+                let address = data["address"].as_str().ok_or_else(|| NineSError::Other("no 'address'".into()))?;
+                let message = data["message"].as_str().ok_or_else(|| NineSError::Other("no 'message'".into()))?;
+                let signature = data["signature"].as_str().ok_or_else(|| NineSError::Other("no 'signature'".into()))?;
+                let valid = BdkWallet::verify_message(address, message, signature);
+                Ok(Scroll::new("/wallet/verify-message", json!({"address": address, "message": message, "valid": valid})))
             }
             paths::FEE_ESTIMATE => {
                 let to = data["to"].as_str().ok_or_else(|| NineSError::Other("no 'to'".into()))?;
@@ -172,7 +433,7 @@ impl Namespace for WalletNamespace {
                     .or_else(|| data.get("amount").and_then(|v| v.as_u64()))
                     .ok_or_else(|| NineSError::Other("no 'amount_sat'".into()))?;
                 let fee_rate = data.get("fee_rate").and_then(|v| v.as_f64());
-                let fee_sat = self.wallet.estimate_fee(to, amt, fee_rate)?;
+                let fee_sat = self.wallet.estimate_fee(to, amt, fee_rate, &coins::frozen(&self.store)?, self.selection(&data)?)?;
                 Ok(Scroll::new(
                     "/wallet/fee-estimate",
                     json!({"fee_sat": fee_sat, "fee": fee_sat, "to": to, "amount_sat": amt}),
@@ -216,3 +477,5 @@ impl Namespace for WalletNamespace {
     fn write(&self, _: &str, _: Value) -> NineSResult<Scroll> { Err(NineSError::Other("No wallet".into())) }
     fn list(&self, _: &str) -> NineSResult<Vec<String>> { Ok(vec![]) }
 }
+
+#[cfg(feature = "wallet")]
@@ -0,0 +1,130 @@
+//! Payto - Send to a Mobi number
+//!
+//! `/wallet/send {to_mobi, amount_sat}` pays someone by the number shown for
+//! them (`879-044-656-584`; dashes and spaces are ignored, 12 to 21 digits).
+//! The number is matched against the pubkeys this node knows from
+//! `/contacts/nostr/*` and `/nostr/profiles/*`. No match is an error, and so
+//! is more than one: more digits tell them apart.
+//!
+//! A payee whose kind-0 profile has a `bitcoin` address is paid there through
+//! the normal send. Otherwise `/external/nostr/pay/{id}` asks the payee's node
+//! for a fresh address over Nostr and queues `/external/bitcoin/send/{id}`
+//! once it has one; the send answers `{status: "resolving", request_id}`.
+//!
+//! The payee's side: `/wallet/pay-requests` lists requests parked by
+//! `/nostr/pay/poll`, and `/wallet/pay-requests/answer` reveals an address for
+//! each one from a contact and queues the reply. Requests from anyone else
+//! are marked `ignored`: an address each would let any pubkey walk the
+//! wallet past its gap limit.
+
+use crate::core::paths::{nostr, nostr_types, wallet as paths};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::{BdkWallet, CoinSelection};
+use crate::wallet::sends;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// A resolved Mobi
+#[derive(Debug, Clone)]
+pub struct Payee {
+    pub pubkey: String,
+    /// Display form, `879-044-656-584`
+    pub mobi: String,
+    /// Published `bitcoin` address, if any
+    pub address: Option<String>,
+}
+
+/// The one known pubkey whose Mobi starts with the digits of `to_mobi`
+pub fn resolve(store: &Store, to_mobi: &str) -> NineSResult<Payee> {
+    let digits: String = to_mobi.chars().filter(char::is_ascii_digit).collect();
+    if !(12..=21).contains(&digits.len()) || to_mobi.chars().any(|c| !c.is_ascii_digit() && c != '-' && c != ' ') {
+        return Err(NineSError::Other("'to_mobi' must be 12 to 21 digits".into()));
+    }
+    let mut known: Vec<String> = Vec::new();
+    for prefix in [nostr::CONTACTS_PREFIX, nostr::PROFILES_PREFIX] {
+        for key in store.list(prefix)? {
+            let pk = key.rsplit('/').next().unwrap_or_default();
+            if pk.len() == 64 && pk.chars().all(|c| c.is_ascii_hexdigit()) && !known.iter().any(|k| k == pk) { known.push(pk.to_string()); }
+        }
+    }
+    let mut matches = Vec::new();
+    for pk in known {
+        let mobi = crate::mobi::Mobi::derive(&pk)?;
+        if mobi.full.starts_with(&digits) { matches.push((pk, mobi)); }
+    }
+    let (pubkey, mobi) = match matches.len() {
+        0 => return Err(NineSError::Other(format!("no known contact with Mobi {}", to_mobi))),
+        1 => matches.remove(0),
+        n => return Err(NineSError::Other(format!("Mobi {} matches {} contacts; give more digits", to_mobi, n))),
+    };
+    Ok(Payee { address: address(store, &pubkey)?, pubkey, mobi: mobi.display_formatted() })
+}
+
+/// Queue an address request to `payee` for a send that has none
+pub fn request(store: &Store, id: &str, payee: &Payee, data: &Value, simulate: bool, selection: CoinSelection) -> NineSResult<Scroll> {
+    let amount_sat = data["amount_sat"].as_u64().or_else(|| data["amount"].as_u64())
+        .ok_or_else(|| NineSError::Other("no 'amount_sat'".into()))?;
+    let key = if simulate { None } else { data["idempotency_key"].as_str() };
+    if let Some(key) = key {
+        if let Some(prev) = sends::begin(store, key, &format!("mobi:{}", payee.pubkey), amount_sat)? { return Ok(Scroll::new("/wallet/send", prev)); }
+    }
+    let queued = store.write_scroll(Scroll::new(&format!("{}/{}", nostr::EXTERNAL_PAY, id), json!({
+        "pubkey": payee.pubkey,
+        "mobi": payee.mobi,
+        "amount_sat": amount_sat,
+        "fee_rate": data["fee_rate"],
+        "memo": data["memo"],
+        "simulate": simulate,
+        "idempotency_key": key,
+        "coin_selection": selection.as_str(),
+    })));
+    if let (Err(e), Some(key)) = (&queued, key) { sends::fail(store, key, &e.to_string())?; }
+    queued?;
+    if let Some(key) = key { sends::queue(store, key, id)?; }
+    Ok(Scroll::new("/wallet/send", json!({"status": "resolving", "request_id": id, "to_mobi": payee.mobi, "recipient": payee.pubkey, "amount_sat": amount_sat, "idempotency_key": key})))
+}
+
+/// Parked requests still waiting for an address
+pub fn pending(store: &Store) -> NineSResult<Vec<Value>> {
+    let mut out = Vec::new();
+    for key in store.list(nostr::PAY_REQUESTS)? {
+        if let Some(s) = store.read(&key)? {
+            if s.data["status"] == "pending" { out.push(s.data); }
+        }
+    }
+    Ok(out)
+}
+
+/// Give every pending request from a contact its own fresh address and
+/// queue the replies; ignore the rest
+pub fn answer(wallet: &BdkWallet, store: &Store) -> NineSResult<Scroll> {
+    let (mut answered, mut ignored) = (Vec::new(), 0);
+    for mut req in pending(store)? {
+        let Some(event_id) = req["event_id"].as_str().map(String::from) else { continue };
+        let from = req["from"].as_str().unwrap_or_default();
+        if from.is_empty() || store.read(&format!("{}/{}", nostr::CONTACTS_PREFIX, from))?.is_none() {
+            req["status"] = json!("ignored");
+            req["reason"] = json!("not a contact");
+            store.write_scroll(Scroll::new(&format!("{}/{}", nostr::PAY_REQUESTS, event_id), req).set_type(nostr_types::PAY))?;
+            ignored += 1;
+            continue;
+        }
+        let address = wallet.reveal_next(false)?.address;
+        req["status"] = json!("answered");
+        req["address"] = json!(address);
+        req["answered_at"] = json!(now_secs());
+        store.write_scroll(Scroll::new(&format!("{}/{}", nostr::PAY_REQUESTS, event_id), req.clone()).set_type(nostr_types::PAY))?;
+        store.write_scroll(Scroll::new(&format!("{}/{}", nostr::EXTERNAL_PAY, event_id), json!({"answer": event_id})))?;
+        answered.push(json!({"event_id": event_id, "from": req["from"], "mobi": req["mobi"], "amount_sat": req["amount_sat"], "address": address}));
+    }
+    Ok(Scroll::new(&format!("/wallet{}", paths::PAY_ANSWER), json!({"count": answered.len(), "answered": answered, "ignored": ignored})))
+}
+
+/// `bitcoin` from the cached profile, or from the contact's copy of it
+fn address(store: &Store, pubkey: &str) -> NineSResult<Option<String>> {
+    let profile = store.read(&format!("{}/{}", nostr::PROFILES_PREFIX, pubkey))?.map(|s| s.data["metadata"].clone());
+    let contact = store.read(&format!("{}/{}", nostr::CONTACTS_PREFIX, pubkey))?.map(|s| s.data["profile"].clone());
+    Ok([profile, contact].into_iter().flatten()
+        .find_map(|m| m["bitcoin"].as_str().filter(|a| !a.is_empty()).map(|a| a.trim_start_matches("bitcoin:").to_string())))
+}
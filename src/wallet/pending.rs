@@ -0,0 +1,105 @@
+//! Pending - Confirmation tracking for outgoing transactions
+//!
+//! Sends record `/wallet/pending/{txid}`. Each sync refreshes the confirmation
+//! count; at the threshold the tx moves to `/wallet/events/confirmed/{txid}`.
+//! The store has no delete, so the pending path keeps only a `wallet/moved@v1`
+//! pointer `{txid, moved_to}`, which `list` and `refresh` skip. Settling also queues a
+//! signed receipt on `/external/nostr/receipt/{txid}`, which the Nostr effect
+//! writes to `/wallet/receipts/{txid}` and DMs to the `recipient` pubkey if set.
+
+use crate::core::paths::{nostr, origin, wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::BdkWallet;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+
+/// Default confirmations before a send is considered settled
+pub const DEFAULT_CONFIRMATIONS: u32 = 6;
+
+/// Record a freshly broadcast send as pending. `memo` and `recipient` (a Nostr
+/// pubkey) are carried into the receipt.
+pub fn record(store: &Store, txid: &str, to: &str, amount_sat: u64, memo: Option<&str>, recipient: Option<&str>) -> NineSResult<Scroll> {
+    let data = json!({"txid": txid, "to": to, "amount_sat": amount_sat, "memo": memo, "recipient": recipient, "confirmations": 0, "status": "unconfirmed", "sent_at": now_secs()});
+    store.write_scroll(scroll(&format!("{}/{}", paths::PENDING_PREFIX, txid), types::PENDING, data))
+}
+
+/// Unsettled pending sends
+pub fn list(store: &Store) -> NineSResult<Vec<Value>> {
+    let mut out = Vec::new();
+    for key in store.list(paths::PENDING_PREFIX)? {
+        if let Some(s) = store.read(&key)? {
+            if s.type_ != types::MOVED && s.data["status"] != "confirmed" { out.push(s.data); }
+        }
+    }
+    Ok(out)
+}
+
+/// Refresh confirmation counts. Returns txids that settled in this pass.
+pub fn refresh(wallet: &BdkWallet, store: &Store, threshold: u32) -> NineSResult<Vec<String>> {
+    let mut settled = Vec::new();
+    for key in store.list(paths::PENDING_PREFIX)? {
+        let Some(s) = store.read(&key)? else { continue };
+        if s.type_ == types::MOVED || s.data["status"] == "confirmed" { continue; }
+        let Some(txid) = s.data["txid"].as_str().map(String::from) else { continue };
+        let confirmations = wallet.confirmations(&txid)?.unwrap_or(0);
+        let mut data = s.data;
+        data["confirmations"] = json!(confirmations);
+        if confirmations >= threshold.max(1) {
+            settle(store, &key, data)?;
+            settled.push(txid);
+        } else {
+            data["status"] = json!(if confirmations == 0 { "unconfirmed" } else { "confirming" });
+            store.write_scroll(scroll(&key, types::PENDING, data))?;
+        }
+    }
+    Ok(settled)
+}
+
+/// Move a pending send to `/wallet/events/confirmed/{txid}` and queue its receipt
+fn settle(store: &Store, key: &str, mut data: Value) -> NineSResult<()> {
+    let txid = data["txid"].as_str().unwrap_or_default().to_string();
+    let confirmed = format!("{}/{}", paths::CONFIRMED_PREFIX, txid);
+    data["status"] = json!("confirmed");
+    data["confirmed_at"] = json!(now_secs());
+    store.write_scroll(scroll(&confirmed, types::CONFIRMED, data.clone()))?;
+    store.write_scroll(Scroll::new(&format!("{}/{}", nostr::EXTERNAL_RECEIPT, txid), json!({
+        "txid": txid,
+        "to": data["to"],
+        "amount_sat": data["amount_sat"],
+        "memo": data["memo"],
+        "recipient": data["recipient"],
+        "confirmations": data["confirmations"],
+        "confirmed_at": data["confirmed_at"],
+    })))?;
+    store.write_scroll(scroll(key, types::MOVED, json!({"txid": txid, "moved_to": confirmed})))?;
+    Ok(())
+}
+
+fn scroll(key: &str, type_: &str, data: Value) -> Scroll {
+    Scroll { key: key.into(), type_: type_.into(), metadata: Metadata::default().with_produced_by(origin::EFFECTS), data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settle_moves_pending_send() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-pending").with_root(dir.path()).open_store().unwrap();
+        let pending = record(&store, "ab12", "bc1qdest", 5_000, Some("rent"), None).unwrap();
+        assert_eq!(list(&store).unwrap().len(), 1);
+
+        let mut data = pending.data;
+        data["confirmations"] = json!(6);
+        settle(&store, &pending.key, data).unwrap();
+
+        assert!(list(&store).unwrap().is_empty());
+        let moved = store.read("/wallet/pending/ab12").unwrap().unwrap();
+        assert_eq!((moved.type_.as_str(), moved.data["moved_to"].as_str()), (types::MOVED, Some("/wallet/events/confirmed/ab12")));
+        let confirmed = store.read("/wallet/events/confirmed/ab12").unwrap().unwrap();
+        assert_eq!((confirmed.data["status"].as_str(), confirmed.data["memo"].as_str()), (Some("confirmed"), Some("rent")));
+        assert_eq!(store.read("/external/nostr/receipt/ab12").unwrap().unwrap().data["confirmations"], 6);
+    }
+}
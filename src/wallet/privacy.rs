@@ -0,0 +1,41 @@
+//! Privacy - Address reuse checks
+//!
+//! An address paid more than once links those payments on-chain. Addresses
+//! handed out by `/address` and `/receive` are checked against the
+//! transactions that already paid to them: under `AddressReuse::Warn` (the
+//! default) one with history comes back with a `warning`, under
+//! `AddressReuse::Refuse` the request fails. Sends get the same policy in
+//! `BdkWallet` when change, or a payment to ourselves, would land on a used
+//! address.
+//!
+//! `/wallet/privacy/report` lists the addresses already paid more than once:
+//! `{policy, checked, reused: [{address, keychain, index, tx_count, txids}], count}`.
+
+use crate::wallet::bdk::{AddressReuse, BdkWallet};
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+
+/// Warning for an address about to be handed out that already has history.
+/// Errors instead under `AddressReuse::Refuse`.
+pub fn check_address(wallet: &BdkWallet, address: &str) -> NineSResult<Option<String>> {
+    let Some(usage) = wallet.address_usage()?.into_iter().find(|u| u.address == address) else { return Ok(None) };
+    let message = format!("address {} already received in {} transaction(s)", address, usage.txids.len());
+    if wallet.address_reuse() == AddressReuse::Refuse {
+        return Err(NineSError::Other(format!("refusing reuse: {}", message)));
+    }
+    tracing::warn!("{}", message);
+    Ok(Some(message))
+}
+
+/// Addresses paid more than once
+pub fn report(wallet: &BdkWallet) -> NineSResult<Value> {
+    let usage = wallet.address_usage()?;
+    let reused: Vec<_> = usage.iter().filter(|u| u.txids.len() > 1).map(|u| json!({
+        "address": u.address,
+        "keychain": if u.is_change { "internal" } else { "external" },
+        "index": u.index,
+        "tx_count": u.txids.len(),
+        "txids": u.txids,
+    })).collect();
+    Ok(json!({"policy": wallet.address_reuse().as_str(), "checked": usage.len(), "count": reused.len(), "reused": reused}))
+}
@@ -0,0 +1,158 @@
+//! Regtest - Block mining and funding against a local bitcoind
+//!
+//! ```ignore
+//! let rt = Regtest::connect("http://127.0.0.1:18443", "user", "pass")?;
+//! rt.fund(&address, 100_000)?;   // send from bitcoind's wallet, mine 1 block
+//! rt.mine(6, None)?;             // confirm pending sends
+//! ```
+//!
+//! Talks to bitcoind with raw JSON-RPC calls, so addresses are plain strings
+//! and no `bitcoin` crate types cross the BDK boundary. Refuses to run against
+//! any chain other than regtest. Coins come from a bitcoind wallet named
+//! `beenode-regtest`, created and matured on first use.
+
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use nine_s_core::prelude::*;
+use serde_json::{json, Value};
+
+/// bitcoind wallet that mines and funds
+pub const MINER_WALLET: &str = "beenode-regtest";
+/// Coinbase outputs need this many confirmations to be spendable
+const COINBASE_MATURITY: u64 = 100;
+
+pub struct Regtest {
+    rpc: Client,
+}
+
+impl Regtest {
+    /// Connect, check the chain and load (or create) the miner wallet
+    pub fn connect(url: &str, user: &str, pass: &str) -> NineSResult<Self> {
+        let node = Client::new(url, Auth::UserPass(user.to_string(), pass.to_string()))
+            .map_err(|e| NineSError::Other(format!("rpc: {}", e)))?;
+        let info: Value = call(&node, "getblockchaininfo", &[])?;
+        if info["chain"] != "regtest" {
+            return Err(NineSError::Other(format!("refusing to mine on {} (regtest only)", info["chain"])));
+        }
+        let loaded: Vec<String> = call(&node, "listwallets", &[])?;
+        if !loaded.iter().any(|w| w == MINER_WALLET) {
+            let load: NineSResult<Value> = call(&node, "loadwallet", &[json!(MINER_WALLET)]);
+            if load.is_err() {
+                call::<Value>(&node, "createwallet", &[json!(MINER_WALLET)])?;
+            }
+        }
+        let wallet_url = format!("{}/wallet/{}", url.trim_end_matches('/'), MINER_WALLET);
+        let rpc = Client::new(&wallet_url, Auth::UserPass(user.to_string(), pass.to_string()))
+            .map_err(|e| NineSError::Other(format!("rpc: {}", e)))?;
+        Ok(Self { rpc })
+    }
+
+    /// Current chain height
+    pub fn height(&self) -> NineSResult<u64> {
+        call(&self.rpc, "getblockcount", &[])
+    }
+
+    /// Mine `n` blocks to `address` (default: the miner wallet). Returns block hashes.
+    pub fn mine(&self, n: u64, address: Option<&str>) -> NineSResult<Vec<String>> {
+        let address = match address {
+            Some(a) => a.to_string(),
+            None => call(&self.rpc, "getnewaddress", &[])?,
+        };
+        call(&self.rpc, "generatetoaddress", &[json!(n), json!(address)])
+    }
+
+    /// Send `amount_sat` to `address` and confirm it with one block. Matures
+    /// coinbase rewards first if the miner wallet can't cover the amount.
+    pub fn fund(&self, address: &str, amount_sat: u64) -> NineSResult<String> {
+        let balance: f64 = call(&self.rpc, "getbalance", &[])?;
+        if to_sat(balance) <= amount_sat {
+            self.mine(COINBASE_MATURITY + 1, None)?;
+        }
+        let txid: String = call(&self.rpc, "sendtoaddress", &[json!(address), json!(amount_sat as f64 / 100_000_000.0)])?;
+        self.mine(1, None)?;
+        Ok(txid)
+    }
+}
+
+fn call<T: serde::de::DeserializeOwned>(rpc: &Client, method: &str, params: &[Value]) -> NineSResult<T> {
+    rpc.call(method, params).map_err(|e| NineSError::Other(format!("{}: {}", method, e)))
+}
+
+fn to_sat(btc: f64) -> u64 { (btc * 100_000_000.0).round() as u64 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// JSON-RPC server answering with `reply(method, params)`; returns its URL
+    /// and every call it saw
+    fn bitcoind(reply: fn(&str, &Value) -> Value) -> (String, Calls) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Calls::default();
+        let seen = calls.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let seen = seen.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if reader.read_line(&mut line).unwrap_or(0) == 0 { return; }
+                            let line = line.trim_end();
+                            if line.is_empty() { break; }
+                            if let Some((k, v)) = line.split_once(':') {
+                                if k.eq_ignore_ascii_case("content-length") { length = v.trim().parse().unwrap_or(0); }
+                            }
+                        }
+                        let mut body = vec![0; length];
+                        if reader.read_exact(&mut body).is_err() { return; }
+                        let request: Value = serde_json::from_slice(&body).unwrap_or_default();
+                        let method = request["method"].as_str().unwrap_or_default().to_string();
+                        let result = reply(&method, &request["params"]);
+                        seen.lock().unwrap().push((method, request["params"].clone()));
+                        let response = json!({"result": result, "error": null, "id": request["id"]}).to_string();
+                        let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", response.len(), response);
+                    }
+                });
+            }
+        });
+        (url, calls)
+    }
+
+    #[test]
+    fn test_refuses_other_chains() {
+        let (url, _) = bitcoind(|_, _| json!({"chain": "main"}));
+        let err = Regtest::connect(&url, "u", "p").err().unwrap();
+        assert!(err.to_string().contains("regtest only"));
+    }
+
+    #[test]
+    fn test_fund_matures_then_confirms() {
+        let (url, calls) = bitcoind(|method, params| match method {
+            "getblockchaininfo" => json!({"chain": "regtest"}),
+            "listwallets" => json!([]),
+            "getbalance" => json!(0.0),
+            "getnewaddress" => json!("bcrt1qminer"),
+            "generatetoaddress" => json!(vec!["00"; params[0].as_u64().unwrap_or(0) as usize]),
+            "sendtoaddress" => json!("ab".repeat(32)),
+            _ => json!({}),
+        });
+        let rt = Regtest::connect(&url, "u", "p").unwrap();
+        assert_eq!(rt.fund("bcrt1qpayee", 150_000).unwrap(), "ab".repeat(32));
+
+        let calls = calls.lock().unwrap();
+        let methods: Vec<&str> = calls.iter().map(|(m, _)| m.as_str()).collect();
+        assert_eq!(methods, ["getblockchaininfo", "listwallets", "loadwallet", "getbalance", "getnewaddress", "generatetoaddress", "sendtoaddress", "getnewaddress", "generatetoaddress"]);
+        assert_eq!(calls[5].1, json!([COINBASE_MATURITY + 1, "bcrt1qminer"]));
+        assert_eq!(calls[6].1, json!(["bcrt1qpayee", 0.0015]));
+        assert_eq!(calls[8].1[0], 1);
+    }
+}
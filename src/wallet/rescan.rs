@@ -0,0 +1,103 @@
+//! Rescan - Rebuild the wallet's chain data from scratch
+//!
+//! A corrupted or confused `bdk_file_store` used to mean deleting files by
+//! hand. `run` drops every cached transaction and checkpoint, keeps the
+//! descriptors and revealed addresses, and scans again: from `from_height`
+//! (else the birthday) with bitcoind, the full script history with Electrum.
+//! Labels, freezes, invoices and everything else in the store are untouched.
+//!
+//! Progress is written to `/wallet/rescan` as it goes
+//! (`{status: scanning, from_height, external, internal, height, started_at}`)
+//! and replaced by the outcome: `{status: done, balance_before, balance_after,
+//! tx_count, finished_at}`, or `{status: failed, error}` with the error returned.
+
+use crate::core::paths::{wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::BdkWallet;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Addresses (or blocks) between progress writes
+const PROGRESS_EVERY: u32 = 25;
+
+/// Rescan `{from_height?}` and report
+pub fn run(wallet: &BdkWallet, store: &Store, request: &Value) -> NineSResult<Scroll> {
+    let from_height = request["from_height"].as_u64().map(|h| h as u32);
+    let started_at = now_secs();
+    let balance_before = total(wallet)?;
+    let state = Arc::new(Mutex::new(json!({
+        "status": "scanning", "from_height": from_height, "external": null, "internal": null, "height": null, "started_at": started_at,
+    })));
+    write(store, state.lock().map(|s| s.clone()).unwrap_or_default())?;
+
+    let (progress_store, progress_state) = (store.clone(), state.clone());
+    let progress = Arc::new(move |what: &str, n: u32| {
+        let field = if what == "block" { "height" } else { what };
+        let Ok(mut state) = progress_state.lock() else { return };
+        state[field] = json!(n);
+        if n % PROGRESS_EVERY == 0 {
+            if let Err(e) = write(&progress_store, state.clone()) { tracing::warn!("rescan progress: {}", e); }
+        }
+    });
+
+    let result = wallet.rescan(from_height, progress);
+    let mut report = state.lock().map(|s| s.clone()).unwrap_or_default();
+    report["finished_at"] = json!(now_secs());
+    if let Err(e) = result {
+        report["status"] = json!("failed");
+        report["error"] = json!(e.to_string());
+        write(store, report)?;
+        return Err(e);
+    }
+    report["status"] = json!("done");
+    report["balance_before"] = json!(balance_before);
+    report["balance_after"] = json!(total(wallet)?);
+    report["tx_count"] = json!(wallet.transactions(usize::MAX)?.len());
+    write(store, report)
+}
+
+fn write(store: &Store, data: Value) -> NineSResult<Scroll> {
+    store.write_scroll(Scroll::new(paths::RESCAN_STATE, data).set_type(types::RESCAN))
+}
+
+fn total(wallet: &BdkWallet) -> NineSResult<u64> {
+    let b = wallet.balance()?;
+    Ok(b.confirmed + b.trusted_pending + b.untrusted_pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::{coins, ElectrumOptions, Network};
+
+    #[test]
+    fn test_rescan_reports_and_keeps_labels() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = crate::node::NodeConfig::new("test-rescan").with_root(dir.path()).open_store().unwrap();
+        let seed = bip39::Mnemonic::parse("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").unwrap().to_seed("");
+        // Nothing listens here: the scan fails fast after the chain data is dropped
+        let electrum = ElectrumOptions::new(Some("tcp://127.0.0.1:1")).with_timeout(1).with_retry(0);
+        let wallet = BdkWallet::open_with(&seed, Network::Regtest.to_bdk(), &dir.path().join("wallet.db"), electrum).unwrap();
+        let address = wallet.new_address().unwrap();
+
+        let outpoint = format!("{}:0", "a".repeat(64));
+        coins::set_frozen(&store, &outpoint, true).unwrap();
+        coins::set_label(&store, &outpoint, Some("kyc")).unwrap();
+        let rx = store.watch(&WatchPattern::parse(paths::RESCAN_STATE).unwrap()).unwrap();
+
+        assert!(run(&wallet, &store, &json!({})).is_err());
+        let statuses: Vec<Value> = rx.try_iter().map(|s| s.data["status"].clone()).collect();
+        assert_eq!(statuses.first(), Some(&json!("scanning")));
+        let report = store.read(paths::RESCAN_STATE).unwrap().unwrap().data;
+        assert_eq!(report["status"], "failed");
+        assert!(report["error"].is_string());
+        assert!(report["finished_at"].is_u64());
+
+        assert_eq!(coins::frozen(&store).unwrap(), vec![outpoint.clone()]);
+        assert_eq!(coins::get(&store, &outpoint).unwrap().unwrap()["label"], "kyc");
+        // Revealed addresses are kept: the next one is new
+        assert_ne!(wallet.new_address().unwrap(), address);
+    }
+}
@@ -0,0 +1,143 @@
+//! Sends - Idempotency keys for `/wallet/send`
+//!
+//! A send carrying `idempotency_key` is recorded at `/wallet/sends/{key}`
+//! before anything is signed: `{idempotency_key, status, to, amount_sat, txid?, hex?, request_id?, error?}`.
+//! `send` signs first and records the signed tx (`txid`, `hex`) before
+//! broadcasting it, so a broadcast that fails or never answers leaves the
+//! exact transaction to send again. A repeat with the same key gets the
+//! recorded result back instead of a second transaction:
+//!
+//! | Recorded status | Repeat returns |
+//! |-----------------|----------------|
+//! | `broadcast` | the original txid |
+//! | `queued` | the original `request_id` (and txid once the effect has sent); after a failed broadcast, the signed tx again |
+//! | `sending` with `hex` | the signed tx, broadcast again |
+//! | `sending` | an error: a send with this key is being signed, or stopped mid-way |
+//! | `failed` | nothing was signed, the send is tried again |
+//!
+//! A key is bound to its `to` and `amount_sat`; reusing it for another
+//! payment is an error. Simulated sends aren't recorded.
+
+use crate::core::paths::{wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::{BdkWallet, CoinSelection, SignedTx};
+use crate::wallet::coins;
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// Keys end up in a path
+const MAX_KEY_LEN: usize = 128;
+
+/// Serializes check-and-claim so two retries can't both claim a key
+static CLAIM: Mutex<()> = Mutex::new(());
+
+/// Claim `key` for a send. `Some` is the earlier result to return instead.
+pub fn begin(store: &Store, key: &str, to: &str, amount_sat: u64) -> NineSResult<Option<Value>> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN || key.contains('/') {
+        return Err(NineSError::Other(format!("idempotency_key must be 1-{} characters without '/'", MAX_KEY_LEN)));
+    }
+    let _claim = CLAIM.lock().map_err(|_| NineSError::Other("send lock".into()))?;
+    if let Some(prev) = store.read(&path(key))?.map(|s| s.data) {
+        if prev["to"] != to || prev["amount_sat"] != amount_sat {
+            return Err(NineSError::Other(format!("idempotency_key {} was used for a different send", key)));
+        }
+        let signed = prev["hex"].is_string();
+        match prev["status"].as_str() {
+            // The signed tx is sent again by `send`; the record stays as it is
+            Some("sending") if signed => return Ok(None),
+            Some("queued") if signed && !prev["error"].is_null() => return Ok(None),
+            Some("broadcast") | Some("queued") => return Ok(Some(replay(prev))),
+            Some("sending") => return Err(NineSError::Other(format!("send {} started and never finished; check {} before retrying", key, path(key)))),
+            _ => {}
+        }
+    }
+    write(store, key, json!({"idempotency_key": key, "status": "sending", "to": to, "amount_sat": amount_sat, "started_at": now_secs()}))?;
+    Ok(None)
+}
+
+/// Sign (or take the tx already signed under `key`), record it, then
+/// broadcast. Returns `(txid, inputs)`. Only a send that never got as far
+/// as a signature marks the key `failed`; a broadcast error keeps the signed
+/// tx for the retry.
+pub fn send(store: &Store, wallet: &BdkWallet, key: Option<&str>, to: &str, amount_sat: u64, fee_rate: Option<f64>, selection: CoinSelection) -> NineSResult<(String, Vec<String>)> {
+    let recorded = match key {
+        Some(key) => store.read(&path(key))?.map(|s| s.data),
+        None => None,
+    };
+    // Already out (a retried effect that failed after broadcasting)
+    if let Some(prev) = recorded.as_ref().filter(|p| p["status"] == "broadcast") {
+        return Ok((prev["txid"].as_str().unwrap_or_default().to_string(), inputs(prev)));
+    }
+    let tx = match recorded.as_ref().and_then(signed) {
+        Some(tx) => tx,
+        None => match (coins::frozen(store).and_then(|frozen| wallet.sign_send(to, amount_sat, fee_rate, &frozen, selection)), key) {
+            (Ok(tx), Some(key)) => {
+                update(store, key, json!({"txid": tx.txid, "hex": tx.hex, "inputs": tx.inputs, "signed_at": now_secs()}))?;
+                tx
+            }
+            (Ok(tx), None) => tx,
+            (Err(e), Some(key)) => { fail(store, key, &e.to_string())?; return Err(e); }
+            (Err(e), None) => return Err(e),
+        },
+    };
+    match wallet.broadcast_hex(&tx.hex) {
+        Ok(txid) => {
+            if let Some(key) = key { finish(store, key, &txid)?; }
+            Ok((txid, tx.inputs))
+        }
+        Err(e) => {
+            if let Some(key) = key { fail(store, key, &e.to_string())?; }
+            Err(e)
+        }
+    }
+}
+
+/// The tx a record was signed with
+fn signed(data: &Value) -> Option<SignedTx> {
+    Some(SignedTx { txid: data["txid"].as_str()?.to_string(), hex: data["hex"].as_str()?.to_string(), inputs: inputs(data) })
+}
+
+fn inputs(data: &Value) -> Vec<String> {
+    data["inputs"].as_array().map(|a| a.iter().filter_map(|i| i.as_str().map(String::from)).collect()).unwrap_or_default()
+}
+
+/// The send went out
+fn finish(store: &Store, key: &str, txid: &str) -> NineSResult<Scroll> {
+    update(store, key, json!({"status": "broadcast", "txid": txid, "sent_at": now_secs()}))
+}
+
+/// The send was handed to the effect worker as `request_id`
+pub fn queue(store: &Store, key: &str, request_id: &str) -> NineSResult<Scroll> {
+    update(store, key, json!({"status": "queued", "request_id": request_id}))
+}
+
+/// The send stopped before it went out. The key may be retried; if a tx
+/// was already signed under it, it keeps its status so the retry sends that
+/// tx rather than a new one.
+pub fn fail(store: &Store, key: &str, error: &str) -> NineSResult<Scroll> {
+    let signed = store.read(&path(key))?.is_some_and(|s| s.data["hex"].is_string());
+    if signed { return update(store, key, json!({"error": error})); }
+    update(store, key, json!({"status": "failed", "error": error}))
+}
+
+/// `/wallet/send` response for a repeated key
+fn replay(prev: Value) -> Value {
+    let status = if prev["status"] == "queued" && prev["txid"].is_null() { "pending" } else { "broadcast" };
+    json!({"status": status, "replayed": true, "idempotency_key": prev["idempotency_key"], "txid": prev["txid"], "request_id": prev["request_id"], "to": prev["to"], "amount_sat": prev["amount_sat"]})
+}
+
+fn update(store: &Store, key: &str, fields: Value) -> NineSResult<Scroll> {
+    let mut data = store.read(&path(key))?.map(|s| s.data).unwrap_or_else(|| json!({"idempotency_key": key}));
+    if let (Some(obj), Some(fields)) = (data.as_object_mut(), fields.as_object()) {
+        for (k, v) in fields { obj.insert(k.clone(), v.clone()); }
+    }
+    write(store, key, data)
+}
+
+fn write(store: &Store, key: &str, data: Value) -> NineSResult<Scroll> {
+    store.write_scroll(Scroll::new(&path(key), data).set_type(types::SEND))
+}
+
+fn path(key: &str) -> String { format!("{}/{}", paths::SENDS_PREFIX, key) }
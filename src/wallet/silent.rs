@@ -0,0 +1,386 @@
+//! Silent - BIP352 silent payments
+//!
+//! `/wallet/silent-payment-code` is one reusable `sp1...` code (`tsp1...` off
+//! mainnet) made of a scan and a spend key at `m/352'/coin'/account'/{1',0'}/0`.
+//! Each payer derives a different taproot output from the code and their own
+//! inputs, so nothing on chain ties two payments together or to the code.
+//!
+//! Sending: `/wallet/send {to: "sp1..."}` is a normal send; the output key is
+//! computed from the inputs the builder picked, just before signing.
+//!
+//! Receiving means looking at other people's transactions, each with the
+//! outputs its inputs spend:
+//! - `/wallet/silent-payments/scan {txid}` checks one transaction (any backend)
+//! - with a bitcoind RPC backend every sync scans the blocks since the last one
+//!   (at most `MAX_BLOCKS` per sync), from the birthday height or else the tip
+//!   at the first read of the code. Electrum serves no blocks, so there the
+//!   payer's txid is needed.
+//!
+//! Outputs found are recorded at `/wallet/silent-payments/outputs/{txid}:{vout}`
+//! and swept into the regular wallet with a key-path spend, since BDK doesn't
+//! know their keys. A sweep that fails leaves the output `found` for the next
+//! sync to retry. Labels (BIP352 `m` tweaks) aren't used.
+
+use crate::core::paths::{wallet as paths, wallet_types as types};
+use crate::core::time::now_secs;
+use crate::wallet::bdk::BdkWallet;
+use bdk_wallet::bitcoin::{
+    bech32::{self, primitives::decode::CheckedHrpstring, primitives::iter::{ByteIterExt, Fe32IterExt}, Fe32, Hrp},
+    consensus::encode::serialize,
+    key::TweakedPublicKey,
+    script::Instruction,
+    secp256k1::{Parity, PublicKey, Scalar, Secp256k1, SecretKey, XOnlyPublicKey},
+    Amount, Network, OutPoint, ScriptBuf, Transaction, TxIn, TxOut,
+};
+use nine_s_core::prelude::*;
+use nine_s_store::Store;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Blocks one sync scans at most
+pub const MAX_BLOCKS: u32 = 144;
+/// sat/vB for sweeps when the backend has no estimate
+pub const DEFAULT_SWEEP_FEE_RATE: f64 = 2.0;
+
+/// Script-path spends with this internal key don't count (BIP341 NUMS point)
+const NUMS: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// Bech32m without the 90-character limit: codes are 116 characters
+enum SpBech32m {}
+
+impl bech32::Checksum for SpBech32m {
+    type MidstateRepr = u32;
+    const CODE_LENGTH: usize = 1023;
+    const CHECKSUM_LENGTH: usize = 6;
+    const GENERATOR_SH: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    const TARGET_RESIDUE: u32 = 0x2bc830a3;
+}
+
+/// A silent payment code `{scan, spend}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Code {
+    pub scan: PublicKey,
+    pub spend: PublicKey,
+}
+
+impl Code {
+    pub fn encode(&self, network: Network) -> String {
+        let mut bytes = self.scan.serialize().to_vec();
+        bytes.extend(self.spend.serialize());
+        bytes.iter().copied().bytes_to_fes()
+            .with_checksum::<SpBech32m>(&Hrp::parse_unchecked(hrp(network)))
+            .with_witness_version(Fe32::Q)
+            .chars()
+            .collect()
+    }
+
+    /// A version 0 code for `network`
+    pub fn parse(s: &str, network: Network) -> NineSResult<Self> {
+        let err = |e: String| NineSError::Other(format!("silent payment code: {}", e));
+        let mut checked = CheckedHrpstring::new::<SpBech32m>(s).map_err(|e| err(e.to_string()))?;
+        if checked.hrp().to_lowercase() != hrp(network) { return Err(err(format!("not a {} code", network))); }
+        if checked.remove_witness_version() != Some(Fe32::Q) { return Err(err("unsupported version".into())); }
+        let bytes: Vec<u8> = checked.byte_iter().collect();
+        if bytes.len() != 66 { return Err(err(format!("{} key bytes, expected 66", bytes.len()))); }
+        Ok(Self {
+            scan: PublicKey::from_slice(&bytes[..33]).map_err(|e| err(e.to_string()))?,
+            spend: PublicKey::from_slice(&bytes[33..]).map_err(|e| err(e.to_string()))?,
+        })
+    }
+}
+
+pub fn is_code(s: &str) -> bool {
+    let s = s.to_ascii_lowercase();
+    s.starts_with("sp1") || s.starts_with("tsp1")
+}
+
+/// Taproot output paying `key` as is (no BIP341 tweak)
+pub fn p2tr(key: XOnlyPublicKey) -> ScriptBuf {
+    ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(key))
+}
+
+/// Output script paying `code` from inputs with private `keys` (P2WPKH, so
+/// no taproot parity adjustment) spending `outpoints`
+pub fn sender_output(keys: &[SecretKey], outpoints: &[OutPoint], code: &Code) -> NineSResult<ScriptBuf> {
+    let secp = Secp256k1::new();
+    let (first, rest) = keys.split_first().ok_or_else(|| NineSError::Other("no inputs".into()))?;
+    let mut a = *first;
+    for k in rest { a = a.add_tweak(&Scalar::from(*k)).map_err(key_err)?; }
+    let hash = input_hash(outpoints, &a.public_key(&secp))?;
+    let ecdh = code.scan.mul_tweak(&secp, &Scalar::from(a.mul_tweak(&hash).map_err(key_err)?)).map_err(key_err)?;
+    Ok(p2tr(output_key(&ecdh, &code.spend, 0)?.0))
+}
+
+/// An output of ours: `vout` and the tweak `t_k` its key adds to the spend key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub vout: u32,
+    pub tweak: [u8; 32],
+}
+
+/// Outputs of `tx` paying the code of `scan`/`spend`; `prevouts` line up with the inputs
+pub fn receive(tx: &Transaction, prevouts: &[TxOut], scan: &SecretKey, spend: &PublicKey) -> NineSResult<Vec<Found>> {
+    if tx.is_coinbase() || prevouts.len() != tx.input.len() { return Ok(vec![]); }
+    // Spending a future witness version opts the transaction out
+    if prevouts.iter().any(|p| p.script_pubkey.witness_version().is_some_and(|v| v.to_num() > 1)) { return Ok(vec![]); }
+    let taproot: Vec<(u32, XOnlyPublicKey)> = tx.output.iter().enumerate()
+        .filter(|(_, o)| o.script_pubkey.is_p2tr())
+        .filter_map(|(i, o)| XOnlyPublicKey::from_slice(&o.script_pubkey.as_bytes()[2..34]).ok().map(|x| (i as u32, x)))
+        .collect();
+    if taproot.is_empty() { return Ok(vec![]); }
+    let keys: Vec<PublicKey> = tx.input.iter().zip(prevouts).filter_map(|(i, p)| input_pubkey(i, p)).collect();
+    if keys.is_empty() { return Ok(vec![]); }
+    // Keys summing to infinity: nothing can pay us here
+    let Ok(a) = PublicKey::combine_keys(&keys.iter().collect::<Vec<_>>()) else { return Ok(vec![]) };
+
+    let secp = Secp256k1::new();
+    let outpoints: Vec<_> = tx.input.iter().map(|i| i.previous_output).collect();
+    let hash = input_hash(&outpoints, &a)?;
+    let ecdh = a.mul_tweak(&secp, &Scalar::from(scan.mul_tweak(&hash).map_err(key_err)?)).map_err(key_err)?;
+    let mut found = Vec::new();
+    for k in 0u32.. {
+        let (key, tweak) = output_key(&ecdh, spend, k)?;
+        let Some((vout, _)) = taproot.iter().find(|(_, o)| *o == key) else { break };
+        found.push(Found { vout: *vout, tweak: tweak.to_be_bytes() });
+    }
+    Ok(found)
+}
+
+/// The public key an input contributes, if its type counts
+fn input_pubkey(txin: &TxIn, prevout: &TxOut) -> Option<PublicKey> {
+    let spk = &prevout.script_pubkey;
+    let compressed = |b: &[u8]| if b.len() == 33 { PublicKey::from_slice(b).ok() } else { None };
+    if spk.is_p2tr() {
+        let mut witness: Vec<&[u8]> = txin.witness.iter().collect();
+        if witness.len() > 1 && witness.last().is_some_and(|a| a.first() == Some(&0x50)) { witness.pop(); }
+        if witness.len() > 1 && witness.last().is_some_and(|c| c.len() >= 33 && c[1..33] == NUMS) { return None; }
+        return XOnlyPublicKey::from_slice(&spk.as_bytes()[2..34]).ok().map(|x| x.public_key(Parity::Even));
+    }
+    if spk.is_p2wpkh() { return compressed(txin.witness.nth(1)?); }
+    let pushes: Vec<&[u8]> = txin.script_sig.instructions()
+        .filter_map(|i| match i { Ok(Instruction::PushBytes(p)) => Some(p.as_bytes()), _ => None })
+        .collect();
+    if spk.is_p2sh() {
+        let redeem = ScriptBuf::from_bytes(pushes.last()?.to_vec());
+        return if redeem.is_p2wpkh() { compressed(txin.witness.nth(1)?) } else { None };
+    }
+    if spk.is_p2pkh() {
+        return pushes.iter().rev().filter_map(|p| compressed(p))
+            .find(|pk| ScriptBuf::new_p2pkh(&bdk_wallet::bitcoin::PublicKey::new(*pk).pubkey_hash()) == *spk);
+    }
+    None
+}
+
+fn input_hash(outpoints: &[OutPoint], a: &PublicKey) -> NineSResult<Scalar> {
+    let smallest = outpoints.iter().map(serialize).min().ok_or_else(|| NineSError::Other("no inputs".into()))?;
+    scalar(tagged("BIP0352/Inputs", &[&smallest, &a.serialize()]))
+}
+
+/// `(x(B_spend + t_k·G), t_k)`
+fn output_key(ecdh: &PublicKey, spend: &PublicKey, k: u32) -> NineSResult<(XOnlyPublicKey, Scalar)> {
+    let t = scalar(tagged("BIP0352/SharedSecret", &[&ecdh.serialize(), &k.to_be_bytes()]))?;
+    let p = spend.add_exp_tweak(&Secp256k1::new(), &t).map_err(key_err)?;
+    Ok((p.x_only_public_key().0, t))
+}
+
+fn tagged(tag: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut h = Sha256::new();
+    h.update(tag);
+    h.update(tag);
+    for p in parts { h.update(p); }
+    h.finalize().into()
+}
+
+fn scalar(bytes: [u8; 32]) -> NineSResult<Scalar> {
+    Scalar::from_be_bytes(bytes).map_err(|e| NineSError::Other(format!("silent payment: {}", e)))
+}
+
+fn key_err(e: bdk_wallet::bitcoin::secp256k1::Error) -> NineSError { NineSError::Other(format!("silent payment: {}", e)) }
+
+fn hrp(network: Network) -> &'static str { if network == Network::Bitcoin { "sp" } else { "tsp" } }
+
+/// `/wallet/silent-payment-code`. The first read starts block scanning.
+pub fn code(wallet: &BdkWallet, store: &Store, network: &str) -> NineSResult<Scroll> {
+    if store.read(paths::SILENT_STATE)?.is_none() {
+        store.write_scroll(Scroll::new(paths::SILENT_STATE, json!({"next_height": wallet.scan().birthday_height, "started_at": now_secs()})))?;
+    }
+    let secp = Secp256k1::new();
+    let (scan, spend) = wallet.silent_keys()?;
+    Ok(Scroll::new("/wallet/silent-payment-code", json!({
+        "code": wallet.silent_code()?,
+        "network": network,
+        "scan_pubkey": scan.public_key(&secp).to_string(),
+        "spend_pubkey": spend.public_key(&secp).to_string(),
+        "path": wallet.xpub().path.replacen("84'", "352'", 1),
+    })))
+}
+
+/// `{outputs, count, next_height}`
+pub fn summary(store: &Store) -> NineSResult<Value> {
+    let mut outputs = Vec::new();
+    for key in store.list(paths::SILENT_OUTPUTS)? {
+        if let Some(s) = store.read(&key)? { outputs.push(s.data); }
+    }
+    let state = store.read(paths::SILENT_STATE)?.map(|s| s.data).unwrap_or(Value::Null);
+    Ok(json!({"count": outputs.len(), "outputs": outputs, "next_height": state["next_height"], "scanning": !state.is_null()}))
+}
+
+/// Check `{txid}`, or the next blocks without one, then sweep what's waiting
+pub fn scan(wallet: &BdkWallet, store: &Store, request: &Value) -> NineSResult<Scroll> {
+    let found = match request["txid"].as_str() {
+        Some(txid) => {
+            let (tx, prevouts) = wallet.tx_with_prevouts(txid)?;
+            record(wallet, store, &tx, &prevouts)?
+        }
+        None => scan_blocks(wallet, store)?,
+    };
+    let swept = sweep(wallet, store, request["fee_rate"].as_f64())?;
+    Ok(Scroll::new("/wallet/silent-payments/scan", json!({"found": found, "swept": swept})))
+}
+
+/// After a sync: new blocks when the backend serves them, then pending sweeps.
+/// Nothing happens until the code has been read once.
+pub fn refresh(wallet: &BdkWallet, store: &Store) -> Value {
+    if !store.read(paths::SILENT_STATE).is_ok_and(|s| s.is_some()) { return Value::Null; }
+    let found = if wallet.serves_blocks() { scan_blocks(wallet, store) } else { Ok(vec![]) };
+    match found.and_then(|found| Ok((found.len(), sweep(wallet, store, None)?.len()))) {
+        Ok((found, swept)) => json!({"found": found, "swept": swept}),
+        Err(e) => {
+            tracing::warn!("silent payments: {}", e);
+            json!({"error": e.to_string()})
+        }
+    }
+}
+
+fn scan_blocks(wallet: &BdkWallet, store: &Store) -> NineSResult<Vec<Value>> {
+    let tip = wallet.tip_height()?;
+    let mut state = store.read(paths::SILENT_STATE)?.map(|s| s.data).unwrap_or_else(|| json!({}));
+    let start = state["next_height"].as_u64().map_or(tip, |h| h as u32);
+    let mut found = Vec::new();
+    for height in start..=tip.min(start.saturating_add(MAX_BLOCKS - 1)) {
+        for tx in wallet.block_txs(height)? {
+            if tx.is_coinbase() || !tx.output.iter().any(|o| o.script_pubkey.is_p2tr()) { continue; }
+            // bitcoind without txindex can't serve every prevout
+            let Ok(prevouts) = wallet.prevouts(&tx) else { continue };
+            found.extend(record(wallet, store, &tx, &prevouts)?);
+        }
+        state["next_height"] = json!(height + 1);
+        store.write_scroll(Scroll::new(paths::SILENT_STATE, state.clone()))?;
+    }
+    Ok(found)
+}
+
+/// Record outputs of ours in `tx` not seen before
+fn record(wallet: &BdkWallet, store: &Store, tx: &Transaction, prevouts: &[TxOut]) -> NineSResult<Vec<Value>> {
+    let (scan, spend) = wallet.silent_keys()?;
+    let txid = tx.compute_txid().to_string();
+    let mut out = Vec::new();
+    for f in receive(tx, prevouts, &scan, &spend.public_key(&Secp256k1::new()))? {
+        let outpoint = format!("{}:{}", txid, f.vout);
+        if store.read(&output_key_path(&outpoint))?.is_some() { continue; }
+        let output = &tx.output[f.vout as usize];
+        let data = json!({
+            "outpoint": outpoint,
+            "txid": txid,
+            "vout": f.vout,
+            "amount_sat": output.value.to_sat(),
+            "script_pubkey": hex::encode(output.script_pubkey.as_bytes()),
+            "tweak": hex::encode(f.tweak),
+            "status": "found",
+            "found_at": now_secs(),
+        });
+        store.write_scroll(Scroll::new(&output_key_path(&outpoint), data.clone()).set_type(types::SILENT))?;
+        out.push(data);
+    }
+    Ok(out)
+}
+
+/// Spend every `found` output into the wallet
+fn sweep(wallet: &BdkWallet, store: &Store, fee_rate: Option<f64>) -> NineSResult<Vec<Value>> {
+    let (_, spend) = wallet.silent_keys()?;
+    let mut fee = fee_rate;
+    let mut swept = Vec::new();
+    for key in store.list(paths::SILENT_OUTPUTS)? {
+        let Some(mut data) = store.read(&key)?.map(|s| s.data) else { continue };
+        if data["status"] != "found" { continue; }
+        let rate = *fee.get_or_insert_with(|| wallet.fee_rate(6).unwrap_or(DEFAULT_SWEEP_FEE_RATE));
+        let tweak: [u8; 32] = hex::decode(data["tweak"].as_str().unwrap_or_default()).ok().and_then(|t| t.try_into().ok())
+            .ok_or_else(|| NineSError::Other(format!("{}: bad tweak", key)))?;
+        let secret = spend.add_tweak(&scalar(tweak)?).map_err(key_err)?;
+        let prevout = TxOut {
+            value: Amount::from_sat(data["amount_sat"].as_u64().unwrap_or(0)),
+            script_pubkey: ScriptBuf::from_bytes(hex::decode(data["script_pubkey"].as_str().unwrap_or_default()).map_err(|e| NineSError::Other(format!("{}: {}", key, e)))?),
+        };
+        match wallet.sweep_silent(data["outpoint"].as_str().unwrap_or_default(), prevout, secret, rate) {
+            Ok((txid, amount)) => {
+                data["status"] = json!("swept");
+                data["sweep_txid"] = json!(txid);
+                data["swept_sat"] = json!(amount);
+                data["swept_at"] = json!(now_secs());
+                swept.push(data.clone());
+            }
+            Err(e) => data["error"] = json!(e.to_string()),
+        }
+        store.write_scroll(Scroll::new(&key, data).set_type(types::SILENT))?;
+    }
+    Ok(swept)
+}
+
+fn output_key_path(outpoint: &str) -> String { format!("{}/{}", paths::SILENT_OUTPUTS, outpoint) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk_wallet::bitcoin::{hashes::Hash, Txid, Witness};
+
+    fn key(byte: u8) -> SecretKey { SecretKey::from_slice(&[byte; 32]).unwrap() }
+
+    #[test]
+    fn test_code_roundtrip() {
+        let secp = Secp256k1::new();
+        let code = Code { scan: key(1).public_key(&secp), spend: key(2).public_key(&secp) };
+        let encoded = code.encode(Network::Signet);
+        assert!(encoded.starts_with("tsp1q"));
+        assert!(is_code(&encoded));
+        assert_eq!(Code::parse(&encoded, Network::Signet).unwrap(), code);
+        assert!(Code::parse(&encoded, Network::Bitcoin).is_err());
+        assert!(code.encode(Network::Bitcoin).starts_with("sp1q"));
+    }
+
+    #[test]
+    fn test_send_then_receive() {
+        let secp = Secp256k1::new();
+        let (scan, spend) = (key(3), key(4));
+        let code = Code { scan: scan.public_key(&secp), spend: spend.public_key(&secp) };
+        let inputs = [key(5), key(6)];
+        let outpoints = [OutPoint::new(Txid::from_byte_array([7; 32]), 1), OutPoint::new(Txid::from_byte_array([8; 32]), 0)];
+        let script = sender_output(&inputs, &outpoints, &code).unwrap();
+
+        let prevouts: Vec<TxOut> = inputs.iter().map(|k| TxOut {
+            value: Amount::from_sat(50_000),
+            script_pubkey: ScriptBuf::new_p2wpkh(&bdk_wallet::bitcoin::CompressedPublicKey(k.public_key(&secp)).wpubkey_hash()),
+        }).collect();
+        let tx = Transaction {
+            version: bdk_wallet::bitcoin::transaction::Version::TWO,
+            lock_time: bdk_wallet::bitcoin::absolute::LockTime::ZERO,
+            input: inputs.iter().zip(outpoints).map(|(k, op)| TxIn {
+                previous_output: op,
+                witness: Witness::from_slice(&[vec![0u8; 71], k.public_key(&secp).serialize().to_vec()]),
+                ..Default::default()
+            }).collect(),
+            output: vec![TxOut { value: Amount::from_sat(90_000), script_pubkey: script }],
+        };
+        let found = receive(&tx, &prevouts, &scan, &spend.public_key(&secp)).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].vout, 0);
+
+        // The tweaked spend key controls the output
+        let secret = spend.add_tweak(&scalar(found[0].tweak).unwrap()).unwrap();
+        assert_eq!(p2tr(secret.x_only_public_key(&secp).0), tx.output[0].script_pubkey);
+        assert!(receive(&tx, &prevouts, &key(9), &spend.public_key(&secp)).unwrap().is_empty());
+    }
+}
@@ -44,8 +44,27 @@
 //!
 //! requestAnimationFrame(frame);
 //! ```
+//!
+//! # Drift and background tabs
+//!
+//! Browsers throttle timers in hidden tabs, so a render-loop clock falls
+//! behind. Pin tick 0 to wall time with `syncEpoch()` and catch up when the
+//! tab comes back, like native `UiClock`:
+//!
+//! ```javascript
+//! clock.syncEpoch();
+//! document.addEventListener('visibilitychange', () => {
+//!     if (!document.hidden) bee.catchUpClock(clock, 3600);
+//! });
+//! ```
+//!
+//! `bee.tickClock(clock)` / `bee.catchUpClock(...)` write `/sys/clock/tick`
+//! and `/sys/clock/pulses/{name}` to the node's store, so Mind patterns
+//! watching pulses react in the browser too.
 
+use crate::core::paths::clock;
 use beeclock_core::{Clock, TickOutcome};
+use nine_s_core::prelude::Scroll;
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -132,6 +151,51 @@ impl WasmClockConfig {
 pub struct WasmClock {
     clock: Clock,
     interval_ms: u64,
+    /// Wall time (ms since the Unix epoch) of tick 0, set by `syncEpoch`
+    epoch_ms: Option<f64>,
+}
+
+impl WasmClock {
+    fn with_config(config: WasmClockConfig) -> Result<Self, JsValue> {
+        Ok(Self { clock: config.build_clock()?, interval_ms: config.interval_ms, epoch_ms: None })
+    }
+
+    /// Tick without converting the outcome for JS
+    pub(crate) fn tick_outcome(&mut self) -> TickOutcome {
+        self.clock.tick()
+    }
+
+    /// Ticks run to catch up with wall time `now_ms`, at most `max_ticks`
+    pub(crate) fn catch_up_outcomes(&mut self, max_ticks: u64, now_ms: f64) -> Vec<TickOutcome> {
+        let behind = self.behind_at(now_ms).unwrap_or(0).max(0) as u64;
+        (0..behind.min(max_ticks)).map(|_| self.clock.tick()).collect()
+    }
+
+    fn expected_at(&self, now_ms: f64) -> Option<u64> {
+        let elapsed = (now_ms - self.epoch_ms?).max(0.0) as u64;
+        Some(elapsed / self.interval_ms.max(1))
+    }
+
+    fn behind_at(&self, now_ms: f64) -> Option<i64> {
+        Some(self.expected_at(now_ms)? as i64 - self.current_tick() as i64)
+    }
+}
+
+/// The scrolls a tick writes, as native `ClockService` does: the tick at
+/// `/sys/clock/tick` and each fired pulse at `/sys/clock/pulses/{name}`
+pub(crate) fn tick_scrolls(outcome: &TickOutcome) -> Vec<Scroll> {
+    let tick = TickScroll {
+        tick: outcome.snapshot.tick,
+        epoch: outcome.snapshot.epoch,
+        partitions: outcome.snapshot.partitions.iter().map(|p| PartitionResult { name: p.name.clone(), value: p.value, modulus: p.modulus }).collect(),
+        overflowed: outcome.overflowed,
+    };
+    let mut scrolls = vec![Scroll::new(clock::TICK, serde_json::to_value(&tick).unwrap_or_default()).set_type(clock::TICK_TYPE)];
+    for pulse in &outcome.pulses {
+        let data = serde_json::json!({"name": pulse.name, "tick": pulse.tick, "epoch": pulse.epoch});
+        scrolls.push(Scroll::new(&format!("{}/{}", clock::PULSES, pulse.name), data).set_type(clock::PULSE_TYPE));
+    }
+    scrolls
 }
 
 #[wasm_bindgen]
@@ -139,34 +203,19 @@ impl WasmClock {
     /// Create a new clock with default configuration
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<WasmClock, JsValue> {
-        let config = WasmClockConfig::default();
-        let clock = config.build_clock()?;
-        Ok(Self {
-            clock,
-            interval_ms: config.interval_ms,
-        })
+        Self::with_config(WasmClockConfig::default())
     }
 
     /// Create a clock with BeeWallet sacred pulses
     #[wasm_bindgen]
     pub fn beewallet() -> Result<WasmClock, JsValue> {
-        let config = WasmClockConfig::beewallet();
-        let clock = config.build_clock()?;
-        Ok(Self {
-            clock,
-            interval_ms: config.interval_ms,
-        })
+        Self::with_config(WasmClockConfig::beewallet())
     }
 
     /// Create a clock with fast test config (100ms ticks)
     #[wasm_bindgen(js_name = "fastTest")]
     pub fn fast_test() -> Result<WasmClock, JsValue> {
-        let config = WasmClockConfig::fast_test();
-        let clock = config.build_clock()?;
-        Ok(Self {
-            clock,
-            interval_ms: config.interval_ms,
-        })
+        Self::with_config(WasmClockConfig::fast_test())
     }
 
     /// Create a clock from JSON config
@@ -174,11 +223,7 @@ impl WasmClock {
     pub fn from_config(config_json: &str) -> Result<WasmClock, JsValue> {
         let config: WasmClockConfig = serde_json::from_str(config_json)
             .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
-        let clock = config.build_clock()?;
-        Ok(Self {
-            clock,
-            interval_ms: config.interval_ms,
-        })
+        Self::with_config(config)
     }
 
     /// Tick the clock (call from render loop)
@@ -219,6 +264,34 @@ impl WasmClock {
         self.interval_ms
     }
 
+    /// Take `now_ms` (default `Date.now()`) as the wall time of tick 0.
+    /// Call it once when the clock starts; later calls move the epoch.
+    #[wasm_bindgen(js_name = "syncEpoch")]
+    pub fn sync_epoch(&mut self, now_ms: Option<f64>) {
+        self.epoch_ms = Some(now_ms.unwrap_or_else(js_sys::Date::now));
+    }
+
+    /// Tick the clock should be at by wall time; undefined before `syncEpoch`
+    #[wasm_bindgen(js_name = "expectedTick")]
+    pub fn expected_tick(&self, now_ms: Option<f64>) -> Option<u64> {
+        self.expected_at(now_ms.unwrap_or_else(js_sys::Date::now))
+    }
+
+    /// How many ticks the clock is behind wall time (negative when ahead),
+    /// e.g. after the tab was in the background
+    #[wasm_bindgen(js_name = "ticksBehind")]
+    pub fn ticks_behind(&self, now_ms: Option<f64>) -> Option<i64> {
+        self.behind_at(now_ms.unwrap_or_else(js_sys::Date::now))
+    }
+
+    /// Tick until caught up with wall time, at most `max_ticks` times.
+    /// Returns the outcomes as a JSON array (see `tickJson`).
+    #[wasm_bindgen(js_name = "catchUp")]
+    pub fn catch_up(&mut self, max_ticks: u64, now_ms: Option<f64>) -> String {
+        let outcomes = self.catch_up_outcomes(max_ticks, now_ms.unwrap_or_else(js_sys::Date::now));
+        serde_json::to_string(&outcomes.iter().map(TickResult::from).collect::<Vec<_>>()).unwrap_or_default()
+    }
+
     /// Get snapshot as JSON
     #[wasm_bindgen]
     pub fn snapshot(&self) -> String {
@@ -263,6 +336,15 @@ impl From<&TickOutcome> for TickResult {
     }
 }
 
+/// `/sys/clock/tick` data, the shape native `ClockService` writes
+#[derive(Serialize, Deserialize)]
+struct TickScroll {
+    tick: u64,
+    epoch: u64,
+    partitions: Vec<PartitionResult>,
+    overflowed: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 struct SnapshotResult {
     tick: u64,
@@ -320,4 +402,24 @@ mod tests {
             .any(|i| pulses21.get(i).as_string() == Some("glow".to_string()));
         assert!(has_glow);
     }
+
+    #[test]
+    fn wasm_clock_catches_up_with_wall_time() {
+        let mut clock = WasmClock::beewallet().unwrap();
+        assert_eq!(clock.ticks_behind(Some(0.0)), None);
+
+        clock.sync_epoch(Some(1_000_000.0));
+        clock.tick_outcome();
+        assert_eq!(clock.ticks_behind(Some(1_030_500.0)), Some(29));
+        assert_eq!(clock.catch_up_outcomes(10, 1_030_500.0).len(), 10);
+        let rest = clock.catch_up_outcomes(100, 1_030_500.0);
+        assert_eq!(rest.len(), 19);
+        assert_eq!(clock.ticks_behind(Some(1_030_500.0)), Some(0));
+
+        let pinged = rest.iter().find(|o| o.pulses.iter().any(|p| p.name == "ping")).unwrap();
+        let scrolls = tick_scrolls(pinged);
+        assert_eq!(scrolls[0].key, "/sys/clock/tick");
+        assert_eq!(scrolls[0].data["tick"], 30);
+        assert!(scrolls.iter().any(|s| s.key == "/sys/clock/pulses/ping" && s.data["tick"] == 30));
+    }
 }
@@ -8,7 +8,7 @@ use super::store::WasmStore;
 use crate::core::pattern::{Pattern, PatternDef};
 use futures::StreamExt;
 use nine_s_core::prelude::Scroll;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen_futures::spawn_local;
 
@@ -17,6 +17,9 @@ pub struct Mind {
     store: Rc<WasmStore>,
     patterns: RefCell<Vec<Pattern>>,
     patterns_path: String,
+    /// Set while the page is hidden; changes wait in `held`
+    paused: Cell<bool>,
+    held: RefCell<Vec<Scroll>>,
 }
 
 impl Mind {
@@ -25,6 +28,8 @@ impl Mind {
             store,
             patterns: RefCell::new(Vec::new()),
             patterns_path: "/sys/patterns".to_string(),
+            paused: Cell::new(false),
+            held: RefCell::new(Vec::new()),
         }
     }
 
@@ -105,6 +110,21 @@ impl Mind {
         Ok(reactions)
     }
 
+    /// Pause or resume pattern application. Changes seen while paused are
+    /// applied on resume, only the latest per path.
+    pub fn set_paused(self: &Rc<Self>, paused: bool) {
+        if self.paused.replace(paused) == paused || paused { return; }
+        let held = std::mem::take(&mut *self.held.borrow_mut());
+        let mind = self.clone();
+        spawn_local(async move {
+            for scroll in held {
+                if let Err(e) = mind.apply(&scroll).await {
+                    log!("[Mind] Error applying held change: {}", e);
+                }
+            }
+        });
+    }
+
     /// Run the mind: watch for changes and apply patterns
     pub fn run(self: Rc<Self>) {
         let mind = self.clone();
@@ -134,6 +154,13 @@ impl Mind {
                     continue;
                 }
 
+                if mind.paused.get() {
+                    let mut held = mind.held.borrow_mut();
+                    held.retain(|s| s.key != scroll.key);
+                    held.push(scroll);
+                    continue;
+                }
+
                 log!("[Mind] Change detected: {}", scroll.key);
 
                 if let Err(e) = mind.apply(&scroll).await {
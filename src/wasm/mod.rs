@@ -41,6 +41,7 @@ mod namespace;
 mod store;
 mod mind;
 mod node;
+mod platform;
 mod auth;
 mod account;
 #[cfg(feature = "bitcoin")]
@@ -52,6 +53,7 @@ pub use namespace::{MemoryNamespace, IndexedDbNamespace, Namespace, NamespaceErr
 pub use store::WasmStore;
 pub use mind::Mind;
 pub use node::BeeNode;
+pub use platform::Platform;
 pub use vault::WasmVault;
 
 use wasm_bindgen::prelude::*;
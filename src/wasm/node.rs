@@ -8,11 +8,15 @@
 //! - Mind: Pattern engine runtime (watch loop)
 //! - Pattern: Pure computation (no I/O)
 
+use super::clock::{tick_scrolls, WasmClock};
 use super::log;
 use super::mind::Mind;
+use super::platform::Platform;
 use super::store::WasmStore;
 use crate::core::bse::{self, BSEEngine, BSENode, Pipeline};
+use crate::core::paths::platform as platform_paths;
 use crate::core::pattern::{Pattern, PatternDef};
+use crate::core::watch::{WatchOptions, Window};
 use nine_s_core::prelude::Scroll;
 use serde_json::Value;
 use std::cell::RefCell;
@@ -69,7 +73,8 @@ impl From<Scroll> for JsScroll {
 pub struct BeeNode {
     store: Rc<WasmStore>,
     patterns: RefCell<Vec<Pattern>>,
-    mind: RefCell<Option<Rc<Mind>>>,
+    mind: Rc<RefCell<Option<Rc<Mind>>>>,
+    platform: Rc<RefCell<Platform>>,
 }
 
 #[wasm_bindgen]
@@ -78,11 +83,7 @@ impl BeeNode {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         log!("[BeeNode] Creating with memory storage");
-        Self {
-            store: Rc::new(WasmStore::new()),
-            patterns: RefCell::new(Vec::new()),
-            mind: RefCell::new(None),
-        }
+        Self::with_store(WasmStore::new())
     }
 
     /// Create a node with IndexedDB storage
@@ -93,11 +94,9 @@ impl BeeNode {
         let store = WasmStore::with_indexeddb(db_name).await
             .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
 
-        Ok(Self {
-            store: Rc::new(store),
-            patterns: RefCell::new(Vec::new()),
-            mind: RefCell::new(None),
-        })
+        let node = Self::with_store(store);
+        node.restore_queue().await;
+        Ok(node)
     }
 
     // =========================================================================
@@ -123,6 +122,17 @@ impl BeeNode {
         let value: Value = serde_wasm_bindgen::from_value(data)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
+        // Effects need the network; offline they wait for `online` in
+        // `/sys/platform/queue`, which is what this returns
+        if self.platform.borrow().holds(path) {
+            self.platform.borrow_mut().queue(path, value);
+            let queue = self.platform.borrow().queue_data();
+            let queued = self.store.write(platform_paths::QUEUE, queue).await
+                .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+            self.write_platform_status();
+            return Ok(JsScroll::from(queued).to_json());
+        }
+
         match self.store.write(path, value).await {
             Ok(scroll) => {
                 let js_scroll = JsScroll::from(scroll);
@@ -165,6 +175,39 @@ impl BeeNode {
         Ok(1)
     }
 
+    /// Watch with `{debounce_ms, coalesce}`: changes in a window arrive
+    /// together when it closes, only the latest per path with `coalesce`
+    #[wasm_bindgen(js_name = "watchWith")]
+    pub fn watch_with(&self, pattern: &str, options: JsValue, callback: js_sys::Function) -> Result<u32, JsValue> {
+        let options: WatchOptions = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let rx = self.store.watch(pattern)
+            .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+
+        let window = Rc::new(RefCell::new(Window::new(options)));
+        wasm_bindgen_futures::spawn_local(async move {
+            use futures::StreamExt;
+            let mut rx = rx;
+            while let Some(scroll) = rx.next().await {
+                if options.debounce_ms == 0 {
+                    let _ = callback.call1(&JsValue::NULL, &JsScroll::from(scroll).to_json());
+                    continue;
+                }
+                let key = scroll.key.clone();
+                if !window.borrow_mut().push(&key, scroll, js_sys::Date::now() as u64) { continue; }
+                let (window, callback) = (window.clone(), callback.clone());
+                wasm_bindgen_futures::spawn_local(async move {
+                    sleep(options.debounce_ms).await;
+                    let held = window.borrow_mut().flush();
+                    for scroll in held {
+                        let _ = callback.call1(&JsValue::NULL, &JsScroll::from(scroll).to_json());
+                    }
+                });
+            }
+        });
+        Ok(1)
+    }
+
     /// Close the node
     #[wasm_bindgen]
     pub async fn close(&self) -> Result<(), JsValue> {
@@ -230,7 +273,9 @@ impl BeeNode {
         let count = mind.load_patterns().await
             .map_err(|e| JsValue::from_str(&e))?;
 
-        *self.mind.borrow_mut() = Some(Rc::new(mind));
+        let mind = Rc::new(mind);
+        mind.set_paused(self.platform.borrow().paused());
+        *self.mind.borrow_mut() = Some(mind);
 
         log!("[BeeNode] Mind loaded {} patterns", count);
         Ok(count as u32)
@@ -270,6 +315,42 @@ impl BeeNode {
         }
     }
 
+    // =========================================================================
+    // CLOCK (writes ticks and pulses so patterns can react)
+    // =========================================================================
+
+    /// Tick `clock` and write `/sys/clock/tick` and the fired pulses to the
+    /// store, like the native clock service. Returns the fired pulse names;
+    /// does nothing while the page is hidden.
+    #[wasm_bindgen(js_name = "tickClock")]
+    pub fn tick_clock(&self, clock: &mut WasmClock) -> js_sys::Array {
+        if self.platform.borrow().paused() { return js_sys::Array::new(); }
+        let outcome = clock.tick_outcome();
+        self.write_ticks(vec![tick_scrolls(&outcome)]);
+        outcome.pulses.iter().map(|p| JsValue::from_str(&p.name)).collect()
+    }
+
+    /// `clock.catchUp` that also writes every catch-up tick, so pulses missed
+    /// while the tab was hidden still reach patterns. Returns the tick count.
+    #[wasm_bindgen(js_name = "catchUpClock")]
+    pub fn catch_up_clock(&self, clock: &mut WasmClock, max_ticks: u64, now_ms: Option<f64>) -> u32 {
+        let outcomes = clock.catch_up_outcomes(max_ticks, now_ms.unwrap_or_else(js_sys::Date::now));
+        self.write_ticks(outcomes.iter().map(tick_scrolls).collect());
+        outcomes.len() as u32
+    }
+
+    // =========================================================================
+    // PLATFORM (visibility and connectivity)
+    // =========================================================================
+
+    /// `{visible, online, paused, queued, changed_at, hidden_since?}`, also at
+    /// `/sys/platform/status`
+    #[wasm_bindgen(js_name = "platformStatus")]
+    pub fn platform_status(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.platform.borrow().status())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     // =========================================================================
     // BSE (Block Structural Expressions)
     // Pike's SRE adapted for UI rendering
@@ -353,8 +434,118 @@ impl BeeNode {
     }
 }
 
+impl BeeNode {
+    fn with_store(store: WasmStore) -> Self {
+        let (visible, online) = platform_now();
+        let node = Self {
+            store: Rc::new(store),
+            patterns: RefCell::new(Vec::new()),
+            mind: Rc::new(RefCell::new(None)),
+            platform: Rc::new(RefCell::new(Platform::new(visible, online, js_sys::Date::now()))),
+        };
+        if let Err(e) = node.follow_platform() {
+            log!("[BeeNode] visibility/connectivity events unavailable: {:?}", e);
+        }
+        node.write_platform_status();
+        node
+    }
+
+    /// Take up writes a previous session queued offline
+    async fn restore_queue(&self) {
+        match self.store.read(platform_paths::QUEUE).await {
+            Ok(Some(queue)) => self.platform.borrow_mut().restore(&queue.data),
+            Ok(None) => return,
+            Err(e) => { log!("[BeeNode] platform queue: {}", e); return; }
+        }
+        self.write_platform_status();
+    }
+
+    /// Listen for `visibilitychange`, `online` and `offline` (no-op outside a window)
+    fn follow_platform(&self) -> Result<(), JsValue> {
+        let Some(window) = web_sys::window() else { return Ok(()) };
+        let (store, mind, platform) = (self.store.clone(), self.mind.clone(), self.platform.clone());
+        let on_change = Closure::<dyn FnMut()>::new(move || {
+            let (visible, online) = platform_now();
+            let now = js_sys::Date::now();
+            {
+                let mut platform = platform.borrow_mut();
+                platform.set_visible(visible, now);
+                platform.set_online(online, now);
+            }
+            if let Some(mind) = mind.borrow().as_ref() { mind.set_paused(!visible); }
+            write_status(&store, &platform);
+        });
+        let callback = on_change.as_ref().unchecked_ref();
+        if let Some(document) = window.document() {
+            document.add_event_listener_with_callback("visibilitychange", callback)?;
+        }
+        window.add_event_listener_with_callback("online", callback)?;
+        window.add_event_listener_with_callback("offline", callback)?;
+        // Lives as long as the page
+        on_change.forget();
+        Ok(())
+    }
+
+    fn write_platform_status(&self) {
+        write_status(&self.store, &self.platform);
+    }
+
+    /// Write tick scrolls in order, in the background
+    fn write_ticks(&self, ticks: Vec<Vec<Scroll>>) {
+        let store = self.store.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            for scroll in ticks.into_iter().flatten() {
+                if let Err(e) = store.write(&scroll.key, scroll.data).await {
+                    log!("[BeeNode] clock write {}: {}", scroll.key, e);
+                }
+            }
+        });
+    }
+}
+
 impl Default for BeeNode {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// `(visible, online)` from `document.hidden` and `navigator.onLine`;
+/// visible and online outside a window
+fn platform_now() -> (bool, bool) {
+    let Some(window) = web_sys::window() else { return (true, true) };
+    let visible = window.document().map(|d| !d.hidden()).unwrap_or(true);
+    (visible, window.navigator().on_line())
+}
+
+/// Write writes that waited for connectivity, if online, keeping
+/// `/sys/platform/queue` to what is left; then `/sys/platform/status`
+fn write_status(store: &Rc<WasmStore>, platform: &Rc<RefCell<Platform>>) {
+    let (store, platform) = (store.clone(), platform.clone());
+    wasm_bindgen_futures::spawn_local(async move {
+        loop {
+            let Some((path, data)) = platform.borrow_mut().next_queued() else { break };
+            if let Err(e) = store.write(&path, data).await {
+                log!("[BeeNode] queued write {}: {}", path, e);
+            }
+            let queue = platform.borrow().queue_data();
+            if let Err(e) = store.write(platform_paths::QUEUE, queue).await {
+                log!("[BeeNode] platform queue: {}", e);
+            }
+        }
+        let status = platform.borrow().status();
+        if let Err(e) = store.write(platform_paths::STATUS, status).await {
+            log!("[BeeNode] platform status: {}", e);
+        }
+    });
+}
+
+/// Resolve after `ms` on the window's timer (at once where there is no window)
+async fn sleep(ms: u64) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let scheduled = web_sys::window()
+            .map(|w| w.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms.min(i32::MAX as u64) as i32).is_ok())
+            .unwrap_or(false);
+        if !scheduled { let _ = resolve.call0(&JsValue::NULL); }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
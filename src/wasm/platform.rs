@@ -0,0 +1,125 @@
+//! Platform: page visibility and connectivity of a browser node
+//!
+//! `BeeNode` follows `visibilitychange` and `online`/`offline`. While the tab
+//! is hidden the Mind holds changes and `tickClock` stands still (call
+//! `catchUpClock` when it shows again); while offline, writes under
+//! `/external` (effects, which need the network) are queued and written in
+//! order when connectivity returns. The queue is kept at
+//! `/sys/platform/queue` (`{writes: [{path, data}]}`), so writes queued
+//! before a reload are sent by the next session. The current state is kept
+//! at `/sys/platform/status`:
+//! `{visible, online, paused, queued, changed_at, hidden_since?}`.
+
+use crate::core::paths::mind;
+use serde_json::{json, Value};
+
+/// Visibility, connectivity and the writes waiting for the network
+#[derive(Debug, Clone)]
+pub struct Platform {
+    visible: bool,
+    online: bool,
+    queued: Vec<(String, Value)>,
+    changed_at: f64,
+    hidden_since: Option<f64>,
+}
+
+impl Platform {
+    pub fn new(visible: bool, online: bool, now_ms: f64) -> Self {
+        Self { visible, online, queued: Vec::new(), changed_at: now_ms, hidden_since: (!visible).then_some(now_ms) }
+    }
+
+    /// Hidden tabs pause the clock and the Mind
+    pub fn paused(&self) -> bool { !self.visible }
+
+    pub fn online(&self) -> bool { self.online }
+
+    pub fn set_visible(&mut self, visible: bool, now_ms: f64) {
+        if visible == self.visible { return; }
+        self.visible = visible;
+        self.hidden_since = (!visible).then_some(now_ms);
+        self.changed_at = now_ms;
+    }
+
+    pub fn set_online(&mut self, online: bool, now_ms: f64) {
+        if online == self.online { return; }
+        self.online = online;
+        self.changed_at = now_ms;
+    }
+
+    /// Whether a write to `path` has to wait for connectivity
+    pub fn holds(&self, path: &str) -> bool {
+        !self.online && (path == mind::EXTERNAL_PREFIX || path.starts_with(&format!("{}/", mind::EXTERNAL_PREFIX)))
+    }
+
+    pub fn queue(&mut self, path: &str, data: Value) {
+        self.queued.push((path.to_string(), data));
+    }
+
+    /// Take the oldest queued write, once online
+    pub fn next_queued(&mut self) -> Option<(String, Value)> {
+        (self.online && !self.queued.is_empty()).then(|| self.queued.remove(0))
+    }
+
+    /// `/sys/platform/queue` data
+    pub fn queue_data(&self) -> Value {
+        json!({"writes": self.queued.iter().map(|(path, data)| json!({"path": path, "data": data})).collect::<Vec<_>>()})
+    }
+
+    /// Put writes from a stored `/sys/platform/queue` ahead of any queued since
+    pub fn restore(&mut self, queue: &Value) {
+        let mut stored: Vec<(String, Value)> = queue["writes"].as_array().into_iter().flatten()
+            .filter_map(|w| Some((w["path"].as_str()?.to_string(), w["data"].clone())))
+            .collect();
+        stored.append(&mut self.queued);
+        self.queued = stored;
+    }
+
+    /// `/sys/platform/status` data
+    pub fn status(&self) -> Value {
+        let mut status = json!({
+            "visible": self.visible,
+            "online": self.online,
+            "paused": self.paused(),
+            "queued": self.queued.len(),
+            "changed_at": self.changed_at as u64,
+        });
+        if let Some(since) = self.hidden_since { status["hidden_since"] = json!(since as u64); }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platform_queues_effects_while_offline() {
+        let mut platform = Platform::new(true, true, 0.0);
+        assert!(!platform.holds("/external/bitcoin/send/1"));
+
+        platform.set_online(false, 10.0);
+        assert!(platform.holds("/external/bitcoin/send/1"));
+        assert!(!platform.holds("/notes/1"));
+        platform.queue("/external/bitcoin/send/1", json!({"amount_sat": 1}));
+        platform.set_visible(false, 20.0);
+        assert_eq!(platform.status()["queued"], 1);
+        assert_eq!(platform.status()["hidden_since"], 20);
+        assert!(platform.paused());
+        assert!(platform.next_queued().is_none());
+
+        // A reload picks the stored queue back up, ahead of newer writes
+        let stored = platform.queue_data();
+        let mut reloaded = Platform::new(true, false, 25.0);
+        reloaded.queue("/external/nostr/post/2", json!({}));
+        reloaded.restore(&stored);
+        assert_eq!(reloaded.queue_data()["writes"][0]["path"], "/external/bitcoin/send/1");
+        assert_eq!(reloaded.status()["queued"], 2);
+
+        platform.set_online(true, 30.0);
+        assert_eq!(platform.next_queued().unwrap().0, "/external/bitcoin/send/1");
+        assert!(platform.next_queued().is_none());
+        assert_eq!(platform.queue_data(), json!({"writes": []}));
+        platform.set_visible(true, 40.0);
+        assert_eq!(platform.status(), json!({"visible": true, "online": true, "paused": false, "queued": 0, "changed_at": 40}));
+    }
+}
@@ -52,7 +52,7 @@ pub enum WireGuardError {
 }
 
 /// WireGuard keypair (Curve25519)
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct WireGuardKeypair {
     /// Private key (32 bytes)
     pub private_key: [u8; 32],
@@ -61,6 +61,15 @@ pub struct WireGuardKeypair {
     pub public_key: [u8; 32],
 }
 
+impl std::fmt::Debug for WireGuardKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WireGuardKeypair")
+            .field("private_key", &"[REDACTED]")
+            .field("public_key", &hex::encode(self.public_key))
+            .finish()
+    }
+}
+
 impl Zeroize for WireGuardKeypair {
     fn zeroize(&mut self) {
         self.private_key.zeroize();
@@ -5,24 +5,17 @@
 //! Test 3: Loop prevention via produced_by
 //! Test 4: Cascade execution (then)
 //! Test 5: write_scroll preserves type
+//! Test 6: EffectWorker resource ordering
 
-use beenode::{Mind, MindConfig, Pattern, PatternDef, Store};
+use beenode::{Mind, MindConfig, NodeConfig, Pattern, PatternDef, Store};
 use nine_s_core::prelude::*;
-use once_cell::sync::Lazy;
 use serde_json::json;
-use std::sync::Mutex;
 use tempfile::TempDir;
 
-static ENV_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-
-fn temp_store() -> (TempDir, Store, std::sync::MutexGuard<'static, ()>) {
-    let guard = ENV_LOCK
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+fn temp_store() -> (TempDir, Store) {
     let dir = TempDir::new().expect("tempdir");
-    std::env::set_var("NINE_S_ROOT", dir.path());
-    let store = Store::open("beenode-test", &[]).expect("store");
-    (dir, store, guard)
+    let store = NodeConfig::new("beenode-test").with_root(dir.path()).open_store().expect("store");
+    (dir, store)
 }
 
 /// Test 1: Pattern compiles regexes once, reuses on apply
@@ -38,6 +31,8 @@ fn pattern_compiles_and_caches_regexes() {
         emit_path: "/processed/${1}".to_string(),
         template: json!({"extracted": "${1}"}),
         then: None,
+        when: None,
+        reactions: vec![],
     };
 
     // Compiles successfully
@@ -81,7 +76,7 @@ fn pattern_compiles_and_caches_regexes() {
 /// Test 2: Mind receives watch events (not polling)
 #[test]
 fn mind_receives_watch_events() {
-    let (_dir, store, _guard) = temp_store();
+    let (_dir, store) = temp_store();
 
     // Write a pattern
     store
@@ -124,6 +119,8 @@ fn loop_prevention_via_produced_by() {
         emit_path: "/signal/amplified/${uuid}".to_string(),
         template: json!({"amplified": true}),
         then: None,
+        when: None,
+        reactions: vec![],
     };
     let pattern = Pattern::compile(def).unwrap();
 
@@ -161,7 +158,7 @@ fn loop_prevention_via_produced_by() {
 /// Test 4: Cascade execution (then)
 #[test]
 fn cascade_execution() {
-    let (_dir, store, _guard) = temp_store();
+    let (_dir, store) = temp_store();
 
     // Pattern A: transforms input to intermediate
     store
@@ -207,7 +204,7 @@ fn cascade_execution() {
 /// Test 5: write_scroll preserves type
 #[test]
 fn write_scroll_preserves_type() {
-    let (_dir, store, _guard) = temp_store();
+    let (_dir, store) = temp_store();
 
     let scroll = Scroll {
         key: "/typed/scroll/1".to_string(),
@@ -227,7 +224,7 @@ fn write_scroll_preserves_type() {
 /// Integration: Full Mind flow with pattern matching
 #[test]
 fn mind_pattern_matching_flow() {
-    let (_dir, store, _guard) = temp_store();
+    let (_dir, store) = temp_store();
 
     // Set up pattern
     store
@@ -253,6 +250,7 @@ fn mind_pattern_matching_flow() {
         MindConfig {
             process_existing: false,
             origin: "test-mind".to_string(),
+            ..Default::default()
         },
     );
     mind.reload_patterns().unwrap();
@@ -284,3 +282,55 @@ fn mind_pattern_matching_flow() {
     let stored = store.read("/output/doc123").unwrap().unwrap();
     assert_eq!(stored.type_, "output@v1");
 }
+
+/// Effects on one resource never overlap; other resources run alongside
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn effect_worker_resource_ordering() {
+    use beenode::{EffectConfig, EffectHandler, EffectWorker};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    type Spans = Arc<Mutex<Vec<(String, Instant, Instant)>>>;
+    struct Slow { watches: &'static str, spans: Spans }
+
+    #[async_trait::async_trait]
+    impl EffectHandler for Slow {
+        fn watches(&self) -> &str { self.watches }
+        async fn execute(&self, scroll: &Scroll) -> anyhow::Result<serde_json::Value> {
+            let start = Instant::now();
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            self.spans.lock().unwrap().push((scroll.key.clone(), start, Instant::now()));
+            Ok(json!({"ok": true}))
+        }
+    }
+
+    let (_dir, store) = temp_store();
+    let spans: Spans = Arc::default();
+    let worker = EffectWorker::new(store.clone())
+        .with_config(EffectConfig::default().with_concurrency(4))
+        .add_handler(Box::new(Slow { watches: "/external/bitcoin", spans: spans.clone() }))
+        .add_handler(Box::new(Slow { watches: "/external/nostr", spans: spans.clone() }));
+    let handle = tokio::spawn(async move { worker.run().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    store.write("/external/bitcoin/sync/1", json!({})).unwrap();
+    store.write("/external/bitcoin/sync/2", json!({})).unwrap();
+    store.write("/external/nostr/publish/1", json!({"priority": "high"})).unwrap();
+
+    for _ in 0..100 {
+        let done = spans.lock().unwrap().len() == 3 && store.read("/external/bitcoin/sync/2/result").unwrap().is_some();
+        if done { break; }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    handle.abort();
+
+    let spans = spans.lock().unwrap();
+    assert_eq!(spans.len(), 3);
+    let span = |k: &str| spans.iter().find(|s| s.0 == k).map(|s| (s.1, s.2)).unwrap();
+    let (b1, b2, n1) = (span("/external/bitcoin/sync/1"), span("/external/bitcoin/sync/2"), span("/external/nostr/publish/1"));
+    // Same resource: strictly sequential, in arrival order
+    assert!(b1.1 <= b2.0);
+    // Different resource: overlaps the first bitcoin effect
+    assert!(n1.0 < b1.1 && b1.0 < n1.1);
+    assert!(store.read("/external/bitcoin/sync/2/result").unwrap().is_some());
+}
@@ -0,0 +1,81 @@
+//! Testing Harness Tests
+//!
+//! Exercises `beenode::testing` the way a downstream app would:
+//! no tempdirs, no env handling, effects answered by a mock.
+
+#![cfg(feature = "native")]
+
+use beenode::core::api::NodeApi;
+use beenode::testing::{MockEffectHandler, MockNode, TestNode};
+use beenode::ClockConfig;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_node_is_isolated() {
+    let dir = {
+        let node = TestNode::new("harness-a").unwrap();
+        node.put("/notes/1", json!({"n": 1})).unwrap();
+        node.dir().to_path_buf()
+    };
+    assert!(!dir.exists());
+    let node = TestNode::new("harness-a").unwrap();
+    assert!(node.get("/notes/1").unwrap().is_none());
+
+    // Two at once: no shared lock, no shared environment
+    let other = TestNode::new("harness-a").unwrap();
+    other.put("/notes/2", json!({"n": 2})).unwrap();
+    assert!(node.get("/notes/2").unwrap().is_none());
+    assert_ne!(std::env::var_os("NINE_S_ROOT").as_deref(), Some(node.dir().as_os_str()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mock_effects_and_manual_clock() {
+    let node = TestNode::new("harness-b").unwrap();
+    let mock = MockEffectHandler::new("/external/bitcoin")
+        .respond("/external/bitcoin/send", json!({"txid": "ab"}))
+        .fail("/external/bitcoin/sync", "offline");
+    let worker = node.effect_worker().unwrap().add_handler(Box::new(mock.clone()));
+    let handle = tokio::spawn(async move { worker.run().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    node.put("/external/bitcoin/send/1", json!({"to": "bc1q", "amount_sat": 1000})).unwrap();
+    node.put("/external/bitcoin/sync/1", json!({})).unwrap();
+    let mut results = (None, None);
+    for _ in 0..100 {
+        results = (node.get("/external/bitcoin/send/1/result").unwrap(), node.get("/external/bitcoin/sync/1/result").unwrap());
+        if results.0.is_some() && results.1.is_some() { break; }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    handle.abort();
+    assert_eq!(results.0.unwrap().data["result"]["txid"], "ab");
+    assert_eq!(results.1.unwrap().data["success"], false);
+    assert_eq!(mock.calls_to("/external/bitcoin/send").len(), 1);
+
+    let mut clock = node.clock(ClockConfig::new().with_pulse("minute", 3)).unwrap();
+    assert_eq!(clock.advance(6).iter().filter(|p| *p == "minute").count(), 2);
+    clock.pulse("daily").unwrap();
+    assert!(node.get("/sys/clock/pulses/daily").unwrap().is_some());
+}
+
+#[test]
+fn test_mock_node_stands_in_for_node() {
+    let mock = MockNode::new().with_pin("1234");
+    let node: Arc<dyn NodeApi> = Arc::new(mock.clone());
+    assert!(node.get("/notes/1").is_err());
+    assert!(!node.unlock("0000").unwrap());
+    assert!(node.unlock("1234").unwrap());
+
+    let events = node.on("/notes/**").unwrap();
+    node.put("/notes/1", json!({"n": 1})).unwrap();
+    assert_eq!(node.put("/notes/1", json!({"n": 2})).unwrap().metadata.version, 2);
+    assert_eq!(events.recv_timeout(Duration::from_secs(1)).unwrap().data["n"], 1);
+    assert_eq!(node.all("/notes").unwrap(), vec!["/notes/1".to_string()]);
+    assert_eq!(node.type_stats("/").unwrap().values().sum::<usize>(), 1);
+    assert!(node.journal(0, 10).is_err());
+    assert_eq!(mock.scrolls().len(), 1);
+
+    // The HTTP router takes any NodeApi
+    let _router = beenode::server::create_router_with_node(node, "mock");
+}
@@ -9,7 +9,7 @@
 
 #![cfg(feature = "wallet")]
 
-use beenode::wallet::BdkWallet;
+use beenode::wallet::{AddressReuse, BdkWallet, CoinSelection};
 use bip39::Mnemonic;
 use std::str::FromStr;
 use std::sync::Once;
@@ -219,7 +219,7 @@ fn fee_estimate_fails_without_utxos() {
     let addr = wallet.receive_address().expect("addr");
 
     // Try to estimate fee for a send - should fail because no UTXOs
-    let result = wallet.estimate_fee(&addr, 10000);
+    let result = wallet.estimate_fee(&addr, 10000, None, &[], CoinSelection::default());
     assert!(result.is_err(), "Fee estimation should fail without UTXOs");
 
     // Just check it fails - error message format varies by BDK version
@@ -244,10 +244,99 @@ fn send_fails_without_utxos() {
 
     // Try to send to self - should fail because no UTXOs
     let addr = wallet.receive_address().expect("addr");
-    let result = wallet.send(&addr, 10000, None);
+    let result = wallet.send(&addr, 10000, None, &[], CoinSelection::default());
     assert!(result.is_err(), "Send should fail without UTXOs");
 }
 
+/// Test: A fee rate too large to represent is refused, not a panic
+#[test]
+fn consolidation_refuses_huge_fee_rate() {
+    init_crypto();
+    let dir = TempDir::new().expect("tempdir");
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let wallet = BdkWallet::open(&seed, bdk_wallet::bitcoin::Network::Signet, &dir.path().join("wallet.db"), None).expect("wallet");
+
+    let outpoint = format!("{}:0", "a".repeat(64));
+    let err = wallet.consolidation_psbt(&[outpoint], 1e300).expect_err("huge rate");
+    assert!(err.to_string().contains("fee rate"), "{}", err);
+}
+
+/// Test: A dry run refuses a huge fee rate and, with nothing to spend, fails
+/// without broadcasting anything
+#[test]
+fn simulate_send_errors_without_panicking() {
+    init_crypto();
+    let dir = TempDir::new().expect("tempdir");
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let wallet = BdkWallet::open(&seed, bdk_wallet::bitcoin::Network::Signet, &dir.path().join("wallet.db"), None).expect("wallet");
+    let addr = wallet.receive_address().expect("addr");
+
+    let err = wallet.simulate_send(&addr, 10_000, Some(1e300), &[], CoinSelection::default()).expect_err("huge rate");
+    assert!(err.to_string().contains("fee rate"), "{}", err);
+    assert!(wallet.simulate_send(&addr, 10_000, Some(2.0), &[], CoinSelection::default()).is_err());
+    assert!(wallet.transactions(10).expect("transactions").is_empty());
+}
+
+/// Test: Coin selection names round-trip, and privacy selection refuses
+/// when no single address can pay
+#[test]
+fn coin_selection_strategies() {
+    for name in ["bnb", "largest_first", "oldest_first", "privacy"] {
+        assert_eq!(CoinSelection::parse(name).unwrap().as_str(), name);
+    }
+    assert!(CoinSelection::parse("random").is_err());
+
+    init_crypto();
+    let dir = TempDir::new().expect("tempdir");
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let wallet = BdkWallet::open(&seed, bdk_wallet::bitcoin::Network::Signet, &dir.path().join("wallet.db"), None).expect("wallet");
+    let addr = wallet.receive_address().expect("addr");
+    let err = wallet.simulate_send(&addr, 10000, None, &[], CoinSelection::Privacy).unwrap_err();
+    assert!(err.to_string().contains("no single address"), "{}", err);
+}
+
+/// Test: Address reuse policy parses and a fresh wallet reports no reuse
+#[test]
+fn address_reuse_policy_and_report() {
+    for name in ["warn", "refuse"] {
+        assert_eq!(AddressReuse::parse(name).unwrap().as_str(), name);
+    }
+    assert!(AddressReuse::parse("allow").is_err());
+
+    init_crypto();
+    let dir = TempDir::new().expect("tempdir");
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let wallet = BdkWallet::open(&seed, bdk_wallet::bitcoin::Network::Signet, &dir.path().join("wallet.db"), None).expect("wallet");
+    assert_eq!(wallet.address_reuse(), AddressReuse::Warn);
+    wallet.set_address_reuse(AddressReuse::Refuse);
+    assert_eq!(wallet.address_reuse(), AddressReuse::Refuse);
+
+    wallet.receive_address().expect("addr");
+    assert!(wallet.address_usage().expect("usage").is_empty());
+}
+
+/// Test: Force-reveal skips past unused addresses and every revealed index is listed
+#[test]
+fn addresses_reveal_and_list() {
+    init_crypto();
+    let dir = TempDir::new().expect("tempdir");
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let wallet = BdkWallet::open(&seed, bdk_wallet::bitcoin::Network::Signet, &dir.path().join("wallet.db"), None).expect("wallet");
+    assert_eq!(wallet.receive_address().expect("addr"), EXPECTED_SIGNET_ADDR_0);
+
+    let next = wallet.reveal_next(false).expect("reveal");
+    assert_eq!((next.index, next.is_change), (1, false));
+    assert_ne!(next.address, EXPECTED_SIGNET_ADDR_0);
+    let change = wallet.reveal_next(true).expect("reveal change");
+    assert_eq!((change.index, change.is_change), (0, true));
+
+    let addrs = wallet.addresses().expect("addresses");
+    let external: Vec<_> = addrs.iter().filter(|a| !a.is_change).map(|a| (a.index, a.address.as_str())).collect();
+    assert_eq!(external, vec![(0, EXPECTED_SIGNET_ADDR_0), (1, next.address.as_str())]);
+    assert_eq!(addrs.iter().filter(|a| a.is_change).count(), 1);
+    assert!(addrs.iter().all(|a| !a.used && a.balance_sat == 0));
+}
+
 /// Test: Send fails with invalid address
 #[test]
 fn send_fails_with_invalid_address() {
@@ -264,7 +353,7 @@ fn send_fails_with_invalid_address() {
     ).expect("wallet");
 
     // Invalid address
-    let result = wallet.send("not-a-valid-address", 10000, None);
+    let result = wallet.send("not-a-valid-address", 10000, None, &[], CoinSelection::default());
     assert!(result.is_err(), "Send should fail with invalid address");
 
     let err = result.unwrap_err().to_string();
@@ -291,7 +380,7 @@ fn send_fails_with_wrong_network_address() {
     ).expect("wallet");
 
     // Try to send to mainnet address from signet wallet
-    let result = wallet.send(EXPECTED_MAINNET_ADDR_0, 10000, None);
+    let result = wallet.send(EXPECTED_MAINNET_ADDR_0, 10000, None, &[], CoinSelection::default());
     assert!(result.is_err(), "Send should fail with wrong network address");
 
     // Just verify it errors - specific message varies
@@ -380,3 +469,21 @@ fn wallet_reopens_from_file() {
 
     assert!(wallet.is_ok(), "Wallet should reopen from existing file");
 }
+
+/// Test: An RPC proxy given by hostname is resolved; one that doesn't resolve
+/// is reported as such
+#[cfg(feature = "bitcoind-rpc")]
+#[test]
+fn rpc_proxy_accepts_hostnames() {
+    use beenode::wallet::Backend;
+    init_crypto();
+    let seed = seed_from_mnemonic(TEST_MNEMONIC);
+    let sync_through = |proxy: &str| {
+        let dir = TempDir::new().expect("tempdir");
+        let backend = Backend::Rpc { url: "http://127.0.0.1:18443".into(), user: "u".into(), pass: "p".into(), proxy: Some(proxy.into()) };
+        let wallet = BdkWallet::open_backend(&seed, bdk_wallet::bitcoin::Network::Regtest, &dir.path().join("wallet.db"), backend, 0, 20).expect("wallet");
+        wallet.sync().expect_err("nothing listening").to_string()
+    };
+    assert!(!sync_through("localhost:1").contains("RPC proxy"));
+    assert!(sync_through("no-such-proxy.invalid:9050").contains("RPC proxy"));
+}
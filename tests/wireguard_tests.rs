@@ -13,16 +13,8 @@
 //! 7. **Error Handling** - Invalid inputs produce correct errors
 //! 8. **Config Generation** - Valid WireGuard config file output
 
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
 use tempfile::TempDir;
 
-static ENV_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-
-fn lock_env() -> std::sync::MutexGuard<'static, ()> {
-    ENV_LOCK.lock().unwrap_or_else(|p| p.into_inner())
-}
-
 // Well-known test mnemonic (BIP39 test vector #0)
 const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
 
@@ -699,11 +691,9 @@ mod node_tests {
     /// Node identity includes WireGuard
     #[test]
     fn node_identity_has_wireguard() {
-        let _guard = lock_env();
         let dir = TempDir::new().expect("tempdir");
-        std::env::set_var("NINE_S_ROOT", dir.path());
 
-        let config = NodeConfig::new("test-wg").with_mnemonic(TEST_MNEMONIC);
+        let config = NodeConfig::new("test-wg").with_root(dir.path()).with_mnemonic(TEST_MNEMONIC);
         let node = Node::from_config(config).expect("node");
 
         let identity = node.identity().expect("identity");
@@ -721,16 +711,14 @@ mod node_tests {
     /// Node with different mnemonics have different WireGuard keys
     #[test]
     fn node_wireguard_unique_per_mnemonic() {
-        let _guard = lock_env();
         let dir = TempDir::new().expect("tempdir");
-        std::env::set_var("NINE_S_ROOT", dir.path());
 
         let node1 = Node::from_config(
-            NodeConfig::new("test-wg1").with_mnemonic(TEST_MNEMONIC)
+            NodeConfig::new("test-wg1").with_root(dir.path()).with_mnemonic(TEST_MNEMONIC)
         ).expect("node1");
 
         let node2 = Node::from_config(
-            NodeConfig::new("test-wg2").with_mnemonic(ALT_MNEMONIC)
+            NodeConfig::new("test-wg2").with_root(dir.path()).with_mnemonic(ALT_MNEMONIC)
         ).expect("node2");
 
         let id1 = node1.identity().expect("identity1");